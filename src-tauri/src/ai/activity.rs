@@ -0,0 +1,226 @@
+// Activity clusters: groups command history embeddings into what the user was doing
+// ("docker debugging", "frontend dev") with spherical k-means, then reports how
+// much terminal time each activity takes and which commands to suggest for it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::EmbeddingVector;
+
+/// Most recent entries considered per analysis run
+pub const MAX_ENTRIES: usize = 5_000;
+const MAX_CLUSTERS: usize = 8;
+const ITERATIONS: usize = 25;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityCluster {
+    pub label: String,
+    /// "llm" when named by a model backend, "heuristic" otherwise
+    pub labeled_by: String,
+    pub commands: usize,
+    /// Fraction of analysed commands in this activity
+    pub share: f32,
+    pub total_duration_ms: u64,
+    pub success_rate: f32,
+    pub top_programs: Vec<(String, usize)>,
+    pub representative_commands: Vec<String>,
+    /// Commands worth suggesting while the user is in this activity
+    pub suggestions: Vec<String>,
+    pub last_active: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityReport {
+    pub clusters: Vec<ActivityCluster>,
+    pub analysed_commands: usize,
+    pub embedding_model: String,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = dot(vector, vector).sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// Spherical k-means with farthest-point seeding; returns the cluster of each vector
+pub fn kmeans(vectors: &[&[f32]], k: usize) -> Vec<usize> {
+    if vectors.is_empty() || k == 0 {
+        return Vec::new();
+    }
+    let k = k.min(vectors.len());
+    let mut centroids: Vec<Vec<f32>> = vec![vectors[0].to_vec()];
+    while centroids.len() < k {
+        let farthest = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, centroids.iter().map(|c| dot(v, c)).fold(f32::MIN, f32::max)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        centroids.push(vectors[farthest].to_vec());
+    }
+
+    let mut assignment = vec![0; vectors.len()];
+    for _ in 0..ITERATIONS {
+        let mut changed = false;
+        for (i, vector) in vectors.iter().enumerate() {
+            let best = centroids
+                .iter()
+                .enumerate()
+                .max_by(|a, b| dot(vector, a.1).total_cmp(&dot(vector, b.1)))
+                .map(|(c, _)| c)
+                .unwrap_or(0);
+            if assignment[i] != best {
+                assignment[i] = best;
+                changed = true;
+            }
+        }
+        let dimension = vectors[0].len();
+        let mut sums = vec![vec![0.0f32; dimension]; k];
+        for (vector, &cluster) in vectors.iter().zip(&assignment) {
+            sums[cluster].iter_mut().zip(vector.iter()).for_each(|(s, v)| *s += v);
+        }
+        for (centroid, mut sum) in centroids.iter_mut().zip(sums) {
+            if sum.iter().any(|v| *v != 0.0) {
+                normalize(&mut sum);
+                *centroid = sum;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    assignment
+}
+
+/// Program of a command line, skipping `sudo` and env assignments
+fn program(command: &str) -> Option<&str> {
+    command.split_whitespace().find(|t| *t != "sudo" && (!t.contains('=') || t.starts_with('-')))
+}
+
+/// Name an activity after the tools that dominate it
+pub fn heuristic_label(top_programs: &[(String, usize)]) -> String {
+    let area = |program: &str| -> Option<&'static str> {
+        Some(match program {
+            "docker" | "docker-compose" | "podman" | "kubectl" | "helm" | "k9s" => "containers",
+            "npm" | "yarn" | "pnpm" | "npx" | "node" | "vite" | "bun" | "deno" => "frontend dev",
+            "cargo" | "rustc" | "rustup" => "rust dev",
+            "python" | "python3" | "pip" | "pip3" | "pytest" | "poetry" | "uv" => "python dev",
+            "go" => "go dev",
+            "git" | "gh" | "glab" => "version control",
+            "ssh" | "scp" | "rsync" | "curl" | "wget" | "ping" | "dig" => "remote & network",
+            "terraform" | "aws" | "gcloud" | "az" | "ansible" => "infrastructure",
+            "psql" | "mysql" | "sqlite3" | "redis-cli" | "mongosh" => "databases",
+            "make" | "cmake" | "gcc" | "clang" => "builds",
+            "vim" | "nvim" | "nano" | "code" | "emacs" => "editing",
+            "ls" | "cd" | "pwd" | "find" | "tree" | "exa" | "eza" | "mkdir" | "cp" | "mv" | "rm" => "file navigation",
+            "top" | "htop" | "ps" | "kill" | "df" | "du" | "free" => "system monitoring",
+            "tail" | "journalctl" | "less" | "grep" | "rg" => "log digging",
+            _ => return None,
+        })
+    };
+    let mut areas: Vec<(&str, usize)> = Vec::new();
+    for (program, count) in top_programs {
+        if let Some(name) = area(program) {
+            match areas.iter_mut().find(|(a, _)| *a == name) {
+                Some(entry) => entry.1 += count,
+                None => areas.push((name, *count)),
+            }
+        }
+    }
+    areas.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    match (areas.first(), top_programs.first()) {
+        (Some((name, _)), _) => name.to_string(),
+        (None, Some((program, _))) => format!("{} work", program),
+        (None, None) => "misc".to_string(),
+    }
+}
+
+/// Group entries into activities; labels are heuristic until replaced by a model
+pub fn cluster_activity(entries: &[EmbeddingVector]) -> Vec<ActivityCluster> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+    let k = ((entries.len() as f32 / 2.0).sqrt().round() as usize).clamp(1, MAX_CLUSTERS);
+    let vectors: Vec<&[f32]> = entries.iter().map(|e| e.vector.as_slice()).collect();
+    let assignment = kmeans(&vectors, k);
+
+    let mut groups: Vec<Vec<&EmbeddingVector>> = vec![Vec::new(); k];
+    for (entry, &cluster) in entries.iter().zip(&assignment) {
+        groups[cluster].push(entry);
+    }
+
+    let mut clusters: Vec<ActivityCluster> = groups
+        .into_iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            let mut programs: HashMap<String, usize> = HashMap::new();
+            let mut commands: HashMap<&str, (usize, usize)> = HashMap::new(); // uses, successes
+            let mut duration = 0u64;
+            let mut successes = 0usize;
+            for entry in &group {
+                if let Some(program) = program(&entry.text) {
+                    *programs.entry(program.to_string()).or_default() += 1;
+                }
+                let succeeded = entry.metadata.get("exit_code").map(|c| c == "0").unwrap_or(true);
+                let stats = commands.entry(entry.text.as_str()).or_default();
+                stats.0 += 1;
+                if succeeded {
+                    stats.1 += 1;
+                    successes += 1;
+                }
+                duration += entry.metadata.get("duration_ms").and_then(|d| d.parse::<u64>().ok()).unwrap_or(0);
+            }
+
+            let mut top_programs: Vec<(String, usize)> = programs.into_iter().collect();
+            top_programs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            top_programs.truncate(5);
+
+            let mut ranked: Vec<(&str, (usize, usize))> = commands.into_iter().collect();
+            ranked.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.0.cmp(b.0)));
+            let representative_commands = ranked.iter().take(5).map(|(c, _)| c.to_string()).collect();
+            let suggestions = ranked
+                .iter()
+                .filter(|(_, (uses, ok))| *ok * 2 >= *uses)
+                .take(5)
+                .map(|(c, _)| c.to_string())
+                .collect();
+
+            ActivityCluster {
+                label: heuristic_label(&top_programs),
+                labeled_by: "heuristic".to_string(),
+                commands: group.len(),
+                share: group.len() as f32 / entries.len() as f32,
+                total_duration_ms: duration,
+                success_rate: successes as f32 / group.len() as f32,
+                top_programs,
+                representative_commands,
+                suggestions,
+                last_active: group.iter().map(|e| e.timestamp).max().unwrap_or_else(chrono::Utc::now),
+            }
+        })
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.commands));
+    clusters
+}
+
+/// Prompt asking a model to name a cluster
+pub fn labeling_prompt(cluster: &ActivityCluster) -> String {
+    format!(
+        "These shell commands were run together as one activity:\n{}\nName the activity in two to four lowercase words, e.g. \"docker debugging\" or \"frontend dev\". Reply with the name only.",
+        cluster.representative_commands.iter().map(|c| format!("- {}", c)).collect::<Vec<_>>().join("\n")
+    )
+}
+
+/// A usable label from a model reply
+pub fn clean_label(reply: &str) -> Option<String> {
+    let label = reply.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let label = label.trim_matches(|c: char| c == '"' || c == '\'' || c == '.' || c == '`').trim().to_lowercase();
+    (!label.is_empty() && label.len() <= 40 && label.split_whitespace().count() <= 6).then_some(label)
+}
@@ -16,6 +16,12 @@ pub struct AgentTask {
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     pub progress: f32, // 0.0 to 1.0
+    /// Where the steps run; a scratch directory keeps experiments out of the user's folders
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    /// The working directory is a throwaway scratch directory
+    #[serde(default)]
+    pub in_scratch: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -148,6 +154,20 @@ impl Default for SafetySettings {
     }
 }
 
+/// Build tools named by the manifests among project index entries ("Cargo.toml package ...")
+fn build_systems(project_facts: &[String]) -> String {
+    let mut tools: Vec<&str> = project_facts
+        .iter()
+        .filter_map(|fact| match fact.split_whitespace().next()?.rsplit('/').next()? {
+            "package.json" => Some("npm"),
+            "Cargo.toml" => Some("cargo"),
+            _ => None,
+        })
+        .collect();
+    tools.dedup();
+    tools.join(" ")
+}
+
 impl IntelligentAgent {
     pub fn new(learning_engine: LearningEngine) -> Self {
         Self {
@@ -159,12 +179,13 @@ impl IntelligentAgent {
         }
     }
 
-    /// Create a new autonomous task from natural language description
-    pub async fn create_task_from_description(&mut self, description: &str) -> Result<String, String> {
+    /// Create a new autonomous task from natural language description, run in `scratch_dir` when given.
+    /// `project_facts` are entries of the project index relevant to the task
+    pub async fn create_task_from_description(&mut self, description: &str, scratch_dir: Option<String>, project_facts: &[String]) -> Result<String, String> {
         let task_id = uuid::Uuid::new_v4().to_string();
         
         // Parse natural language into executable steps
-        let steps = self.parse_natural_language_to_steps(description).await?;
+        let steps = self.parse_natural_language_to_steps(description, project_facts).await?;
         
         let task = AgentTask {
             id: task_id.clone(),
@@ -175,6 +196,8 @@ impl IntelligentAgent {
             started_at: None,
             completed_at: None,
             progress: 0.0,
+            in_scratch: scratch_dir.is_some(),
+            working_directory: scratch_dir,
         };
 
         // Validate task safety
@@ -185,9 +208,11 @@ impl IntelligentAgent {
     }
 
     /// Parse natural language into executable steps
-    async fn parse_natural_language_to_steps(&self, description: &str) -> Result<Vec<AgentStep>, String> {
+    async fn parse_natural_language_to_steps(&self, description: &str, project_facts: &[String]) -> Result<Vec<AgentStep>, String> {
         let mut steps = Vec::new();
         let desc_lower = description.to_lowercase();
+        // "build it" names no build system; the project's indexed manifests do
+        let with_project = format!("{} {}", description, build_systems(project_facts));
 
         // Common task patterns
         if desc_lower.contains("create") && (desc_lower.contains("project") || desc_lower.contains("app")) {
@@ -195,9 +220,9 @@ impl IntelligentAgent {
         } else if desc_lower.contains("git") && desc_lower.contains("commit") {
             steps.extend(self.create_git_commit_steps(description)?);
         } else if desc_lower.contains("build") || desc_lower.contains("compile") {
-            steps.extend(self.create_build_steps(description)?);
+            steps.extend(self.create_build_steps(&with_project)?);
         } else if desc_lower.contains("test") {
-            steps.extend(self.create_test_steps(description)?);
+            steps.extend(self.create_test_steps(&with_project)?);
         } else if desc_lower.contains("deploy") {
             steps.extend(self.create_deploy_steps(description)?);
         } else if desc_lower.contains("backup") {
@@ -344,7 +369,7 @@ impl IntelligentAgent {
 
         steps.push(AgentStep {
             id: format!("{}_3", step_id_base),
-            command: format!("git commit -m \"{}\"", commit_message),
+            command: format!("git commit -m {}", crate::terminal::shell_quote::quote_arg(commit_message)),
             description: "Commit changes".to_string(),
             expected_outcome: "Changes committed".to_string(),
             status: StepStatus::Waiting,
@@ -459,38 +484,21 @@ impl IntelligentAgent {
         Ok(steps)
     }
 
-    /// Create backup steps
-    fn create_backup_steps(&self, description: &str) -> Result<Vec<AgentStep>, String> {
-        let mut steps = Vec::new();
+    /// Create backup steps: one snapshot of the working directory, taken and verified by the backup module
+    fn create_backup_steps(&self, _description: &str) -> Result<Vec<AgentStep>, String> {
         let step_id_base = uuid::Uuid::new_v4().to_string();
 
-        let backup_dir = format!("backup_{}", Utc::now().format("%Y%m%d_%H%M%S"));
-
-        steps.push(AgentStep {
+        Ok(vec![AgentStep {
             id: format!("{}_1", step_id_base),
-            command: format!("mkdir -p {}", backup_dir),
-            description: "Create backup directory".to_string(),
-            expected_outcome: "Backup directory created".to_string(),
-            status: StepStatus::Waiting,
-            retry_count: 0,
-            max_retries: 1,
-            dependencies: vec![],
-            conditional: None,
-        });
-
-        steps.push(AgentStep {
-            id: format!("{}_2", step_id_base),
-            command: format!("cp -r . {}/", backup_dir),
-            description: "Copy files to backup".to_string(),
-            expected_outcome: "Files backed up successfully".to_string(),
+            command: "backup .".to_string(),
+            description: "Snapshot the directory, linking unchanged files to the previous snapshot".to_string(),
+            expected_outcome: "Snapshot taken and verified against the source".to_string(),
             status: StepStatus::Waiting,
             retry_count: 0,
             max_retries: 2,
-            dependencies: vec![format!("{}_1", step_id_base)],
+            dependencies: vec![],
             conditional: None,
-        });
-
-        Ok(steps)
+        }])
     }
 
     /// Create cleanup steps
@@ -608,11 +616,9 @@ impl IntelligentAgent {
                 }
             }
 
-            // Check for destructive operations
-            if self.safety_checks.require_confirmation_for_destructive {
-                if step.command.contains("rm") && step.command.contains("-rf") {
-                    return Err("Destructive operation requires manual confirmation".to_string());
-                }
+            // Check for destructive operations, in scratch directories too: a step can name paths outside them
+            if self.safety_checks.require_confirmation_for_destructive && step.command.contains("rm") && step.command.contains("-rf") {
+                return Err("Destructive operation requires manual confirmation".to_string());
             }
         }
 
@@ -729,6 +735,11 @@ impl IntelligentAgent {
             })
     }
 
+    /// A task, active or finished
+    pub fn get_task(&self, task_id: &str) -> Option<&AgentTask> {
+        self.active_tasks.iter().chain(self.task_history.iter()).find(|task| task.id == task_id)
+    }
+
     /// Get all active tasks
     pub fn get_active_tasks(&self) -> Vec<&AgentTask> {
         self.active_tasks.iter().collect()
@@ -0,0 +1,116 @@
+// Code-aware context for AI prompts
+// Extracts file:line references from command output and attaches the
+// surrounding source lines, so fixes are grounded in the actual code
+
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::trust::TrustStore;
+
+const MAX_FILES: usize = 3;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const LINES_AROUND: usize = 8;
+const MAX_SNIPPET_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReference {
+    pub path: String,
+    pub line: usize,
+}
+
+/// Find file references such as `src/main.rs:42:5`, `--> src/lib.rs:10`
+/// or Python's `File "app.py", line 42`
+pub fn extract_file_references(text: &str) -> Vec<FileReference> {
+    let patterns = [
+        Regex::new(r#"File "([^"]+)", line (\d+)"#).unwrap(),
+        Regex::new(r"(?:^|[\s(\[>'])((?:[A-Za-z]:)?[\w./\\-]+\.[A-Za-z0-9]+):(\d+)(?::\d+)?").unwrap(),
+        Regex::new(r"((?:[A-Za-z]:)?[\w./\\-]+\.[A-Za-z0-9]+)\((\d+)(?:,\d+)?\)").unwrap(),
+    ];
+
+    let mut seen = HashSet::new();
+    let mut references = Vec::new();
+    for pattern in &patterns {
+        for captures in pattern.captures_iter(text) {
+            let path = captures[1].to_string();
+            let line: usize = match captures[2].parse() {
+                Ok(line) if line > 0 => line,
+                _ => continue,
+            };
+            // Skip URLs and host:port pairs that look like paths
+            if path.contains("://") || path.starts_with("http") {
+                continue;
+            }
+            if seen.insert((path.clone(), line)) {
+                references.push(FileReference { path, line });
+            }
+        }
+    }
+    references
+}
+
+/// Build a prompt section with the source lines around each reference.
+/// Only files inside trusted directories are read.
+pub fn build_code_context(text: &str, working_dir: &str) -> Option<String> {
+    let trust = TrustStore::load();
+    let mut sections = Vec::new();
+    let mut total_chars = 0;
+
+    for reference in extract_file_references(text).into_iter().take(MAX_FILES * 2) {
+        if sections.len() >= MAX_FILES || total_chars >= MAX_SNIPPET_CHARS {
+            break;
+        }
+
+        let path = resolve(&reference.path, working_dir);
+        match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() && metadata.len() <= MAX_FILE_BYTES => {}
+            _ => continue,
+        }
+        if !trust.is_trusted(&path) {
+            continue;
+        }
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => continue, // binary or unreadable
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        if reference.line > lines.len() {
+            continue;
+        }
+
+        let start = reference.line.saturating_sub(LINES_AROUND + 1);
+        let end = (reference.line + LINES_AROUND).min(lines.len());
+        let mut section = format!("File: {} (lines {}-{})\n", reference.path, start + 1, end);
+        for (index, line) in lines[start..end].iter().enumerate() {
+            let number = start + index + 1;
+            let marker = if number == reference.line { ">" } else { " " };
+            section.push_str(&format!("{}{:>5} | {}\n", marker, number, line));
+        }
+
+        // Counted in chars throughout, so the cut never lands inside a character
+        let remaining = MAX_SNIPPET_CHARS - total_chars;
+        let mut section_chars = section.chars().count();
+        if section_chars > remaining {
+            section = section.chars().take(remaining).collect();
+            section_chars = remaining;
+        }
+        total_chars += section_chars;
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n"))
+    }
+}
+
+fn resolve(path: &str, working_dir: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        Path::new(working_dir).join(path)
+    }
+}
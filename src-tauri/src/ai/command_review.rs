@@ -0,0 +1,305 @@
+// Review of a command before it runs
+// Gives the command about to run a one-line summary of what it will do and flags
+// for what makes it risky (deleting, forcing, root, remote scripts, paths outside
+// the project). Flags come from the command itself and are instant; the summary
+// comes from a routed model when one answers within the latency budget, cached
+// per command template so `rm -rf build` and `rm -rf dist` share one answer, and
+// otherwise from built-in descriptions. A model that answers after the budget
+// still fills the cache for the next review of the same template.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::Duration;
+
+use super::translation_guard::{self, GuardSettings};
+use crate::terminal::shell_quote;
+
+/// Total time a review may take before the built-in summary is used
+pub const REVIEW_BUDGET: Duration = Duration::from_millis(300);
+const MAX_CACHED: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskFlag {
+    /// Deletes or overwrites data
+    Destructive,
+    Recursive,
+    /// Skips confirmations (`-f`, `--force`)
+    Force,
+    /// Runs as root
+    Privileged,
+    /// Rewrites git history or discards uncommitted work
+    RewritesHistory,
+    /// Downloads and runs a script
+    RemoteScript,
+    Network,
+    StopsProcesses,
+    /// Installs or changes things for the whole system
+    SystemWide,
+    /// Writes to paths outside the working directory
+    OutsideWorkspace,
+    /// Runs nested commands through `$(...)` or backticks
+    Substitution,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewSource {
+    Model,
+    Cache,
+    BuiltIn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandReview {
+    pub command: String,
+    pub summary: String,
+    pub flags: Vec<RiskFlag>,
+    /// Whether the UI should point the review out before the command runs
+    pub risky: bool,
+    pub source: ReviewSource,
+    pub elapsed_ms: u64,
+}
+
+impl CommandReview {
+    pub fn new(command: &str, summary: String, flags: Vec<RiskFlag>, source: ReviewSource, elapsed: Duration) -> Self {
+        let risky = flags.iter().any(|flag| !matches!(flag, RiskFlag::Network | RiskFlag::Recursive));
+        Self { command: command.to_string(), summary, flags, risky, source, elapsed_ms: elapsed.as_millis() as u64 }
+    }
+}
+
+/// Model summaries by command template, oldest dropped first
+#[derive(Debug, Default)]
+pub struct ReviewCache {
+    entries: HashMap<String, String>,
+    order: VecDeque<String>,
+    /// Templates a model is explaining right now
+    pending: HashSet<String>,
+}
+
+impl ReviewCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, template: &str) -> Option<String> {
+        self.entries.get(template).cloned()
+    }
+
+    /// Mark a template as being explained; false when it already is
+    pub fn start(&mut self, template: &str) -> bool {
+        self.pending.insert(template.to_string())
+    }
+
+    /// End an explanation begun with `start`, keeping its summary when there is one
+    pub fn finish(&mut self, template: &str, summary: Option<String>) {
+        self.pending.remove(template);
+        if let Some(summary) = summary {
+            self.insert(template.to_string(), summary);
+        }
+    }
+
+    pub fn insert(&mut self, template: String, summary: String) {
+        if self.entries.insert(template.clone(), summary).is_none() {
+            self.order.push_back(template);
+        }
+        while self.order.len() > MAX_CACHED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// The command with its arguments replaced by placeholders; the program, subcommand and flags stay
+pub fn template(command: &str) -> String {
+    let Ok(words) = shell_quote::split(command) else {
+        return command.trim().to_string();
+    };
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            let keep = index == 0
+                || word.starts_with('-')
+                || matches!(word.as_str(), "|" | "&&" | "||" | ";" | ">" | ">>" | "sudo")
+                || (index == 1 && word.chars().all(|c| c.is_ascii_lowercase() || c == '-'));
+            if keep {
+                word.clone()
+            } else if word.contains("://") {
+                "<url>".to_string()
+            } else if word.contains('/') || word.starts_with('~') || word.starts_with('.') {
+                "<path>".to_string()
+            } else if word.parse::<f64>().is_ok() {
+                "<number>".to_string()
+            } else {
+                "<arg>".to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Risk flags from the command's words, and the working directory when known
+pub fn risk_flags(command: &str, working_dir: Option<&str>) -> Vec<RiskFlag> {
+    let mut flags = Vec::new();
+    let mut flag = |risk: RiskFlag| {
+        if !flags.contains(&risk) {
+            flags.push(risk);
+        }
+    };
+    let settings = GuardSettings::load();
+    if !translation_guard::syntax_violations(command, &settings).is_empty() {
+        flag(RiskFlag::Substitution);
+    }
+
+    let words = shell_quote::split(command).unwrap_or_else(|_| command.split_whitespace().map(str::to_string).collect());
+    let has = |option: &str| words.iter().any(|word| word == option);
+    let has_short = |letter: char| {
+        words.iter().any(|word| word.starts_with('-') && !word.starts_with("--") && word.contains(letter))
+    };
+
+    let mut program_words: &[String] = &words;
+    if words.first().is_some_and(|first| matches!(first.as_str(), "sudo" | "doas" | "pkexec")) {
+        flag(RiskFlag::Privileged);
+        program_words = &words[1..];
+    }
+    let program = program_words.first().map(|p| p.rsplit('/').next().unwrap_or(p)).unwrap_or_default();
+    let subcommand = program_words.get(1).map(String::as_str).unwrap_or_default();
+
+    if has("--force") || (has_short('f') && matches!(program, "rm" | "cp" | "mv" | "ln" | "git" | "docker")) {
+        flag(RiskFlag::Force);
+    }
+    if has("--recursive") || (matches!(program, "rm" | "cp" | "chmod" | "chown" | "chgrp") && (has_short('r') || has_short('R'))) {
+        flag(RiskFlag::Recursive);
+    }
+    match program {
+        "rm" | "rmdir" | "shred" | "dd" | "truncate" | "unlink" => flag(RiskFlag::Destructive),
+        p if p.starts_with("mkfs") || p == "fdisk" || p == "parted" || p == "diskutil" => {
+            flag(RiskFlag::Destructive);
+            flag(RiskFlag::SystemWide);
+        }
+        "find" if has("-delete") => flag(RiskFlag::Destructive),
+        "git" => match subcommand {
+            "push" if has("--force") || has("-f") || has("--force-with-lease") => flag(RiskFlag::RewritesHistory),
+            "reset" if has("--hard") => {
+                flag(RiskFlag::RewritesHistory);
+                flag(RiskFlag::Destructive);
+            }
+            "clean" => flag(RiskFlag::Destructive),
+            "rebase" | "filter-branch" | "filter-repo" => flag(RiskFlag::RewritesHistory),
+            "checkout" | "restore" if has("--") || has(".") => flag(RiskFlag::Destructive),
+            "push" | "pull" | "fetch" | "clone" => flag(RiskFlag::Network),
+            _ => {}
+        },
+        "docker" | "podman" => match subcommand {
+            "rm" | "rmi" | "prune" => flag(RiskFlag::Destructive),
+            "system" | "volume" | "image" | "container" if words.iter().any(|w| w == "prune" || w == "rm") => flag(RiskFlag::Destructive),
+            _ => {}
+        },
+        "kubectl" if matches!(subcommand, "delete" | "drain" | "replace") => flag(RiskFlag::Destructive),
+        "kill" | "pkill" | "killall" | "taskkill" => flag(RiskFlag::StopsProcesses),
+        "shutdown" | "reboot" | "halt" | "poweroff" | "systemctl" | "launchctl" => flag(RiskFlag::SystemWide),
+        "npm" | "pnpm" | "yarn" if has("-g") || has("--global") || subcommand == "global" => flag(RiskFlag::SystemWide),
+        "brew" | "apt" | "apt-get" | "dnf" | "yum" | "pacman" | "port" if matches!(subcommand, "install" | "remove" | "uninstall" | "upgrade" | "-S" | "-R") => {
+            flag(RiskFlag::SystemWide)
+        }
+        "curl" | "wget" | "ssh" | "scp" | "rsync" | "ftp" | "sftp" => flag(RiskFlag::Network),
+        _ => {}
+    }
+
+    // `curl ... | sh` runs whatever the server sends
+    if let Some(pipe) = words.iter().position(|word| word == "|") {
+        let downloads = words[..pipe].iter().any(|w| matches!(w.as_str(), "curl" | "wget"));
+        let runs = words.get(pipe + 1).map(|w| w.rsplit('/').next().unwrap_or(w)).is_some_and(|w| {
+            matches!(w, "sh" | "bash" | "zsh" | "fish" | "python" | "python3" | "node" | "sudo" | "iex")
+        });
+        if downloads && runs {
+            flag(RiskFlag::RemoteScript);
+        }
+    }
+    // Redirects overwrite their target
+    if words.iter().any(|word| word == ">" || (word.starts_with('>') && !word.starts_with(">>"))) {
+        flag(RiskFlag::Destructive);
+    }
+    if let Some(dir) = working_dir {
+        if translation_guard::scope_violations(command, Path::new(dir), &settings).is_ok_and(|violations| !violations.is_empty()) {
+            flag(RiskFlag::OutsideWorkspace);
+        }
+    }
+    flags
+}
+
+/// Built-in one-line summary for common commands
+pub fn describe(command: &str) -> String {
+    let words = shell_quote::split(command).unwrap_or_else(|_| command.split_whitespace().map(str::to_string).collect());
+    let (privileged, words) = match words.split_first() {
+        Some((first, rest)) if matches!(first.as_str(), "sudo" | "doas") => (true, rest.to_vec()),
+        _ => (false, words),
+    };
+    let Some(program) = words.first().map(|p| p.rsplit('/').next().unwrap_or(p).to_string()) else {
+        return "Does nothing".to_string();
+    };
+    let targets: Vec<&str> = words[1..].iter().map(String::as_str).filter(|w| !w.starts_with('-')).collect();
+    let listed = if targets.is_empty() { "nothing given".to_string() } else { targets.join(", ") };
+    let recursive = words.iter().any(|w| w == "--recursive" || (w.starts_with('-') && !w.starts_with("--") && (w.contains('r') || w.contains('R'))));
+    let subcommand = targets.first().copied().unwrap_or_default();
+
+    let summary = match program.as_str() {
+        "rm" if recursive => format!("Deletes {} and everything inside", listed),
+        "rm" | "unlink" => format!("Deletes {}", listed),
+        "rmdir" => format!("Removes the empty directories {}", listed),
+        "mv" => format!("Moves or renames {}", listed),
+        "cp" => format!("Copies {}", listed),
+        "mkdir" => format!("Creates the directories {}", listed),
+        "touch" => format!("Creates {} or updates its timestamp", listed),
+        "chmod" => format!("Changes the permissions of {}", listed),
+        "chown" | "chgrp" => format!("Changes the owner of {}", listed),
+        "ln" => format!("Creates a link: {}", listed),
+        "dd" => format!("Copies raw data ({})", listed),
+        "kill" | "pkill" | "killall" => format!("Stops the processes {}", listed),
+        "curl" | "wget" => format!("Downloads {}", listed),
+        "ssh" => format!("Opens a shell on {}", subcommand),
+        "scp" | "rsync" => format!("Copies files between machines: {}", listed),
+        "cd" => format!("Changes the directory to {}", listed),
+        "ls" | "tree" | "cat" | "less" | "head" | "tail" | "grep" | "rg" | "find" | "stat" | "wc" | "du" | "df" | "pwd" | "which" => {
+            "Only reads; changes nothing".to_string()
+        }
+        "git" => match subcommand {
+            "push" if words.iter().any(|w| w == "--force" || w == "-f") => "Overwrites the remote branch with local history".to_string(),
+            "push" => "Uploads local commits to the remote".to_string(),
+            "pull" => "Fetches and merges remote changes into the current branch".to_string(),
+            "reset" if words.iter().any(|w| w == "--hard") => "Discards all uncommitted changes".to_string(),
+            "reset" => "Moves the branch pointer; working files stay".to_string(),
+            "clean" => "Deletes untracked files".to_string(),
+            "commit" => "Records the staged changes as a commit".to_string(),
+            "checkout" | "switch" => "Switches branches or restores files".to_string(),
+            "rebase" => "Rewrites commits onto another base".to_string(),
+            "merge" => "Merges another branch into the current one".to_string(),
+            "status" | "log" | "diff" | "show" | "branch" => "Only reads the repository".to_string(),
+            other => format!("Runs git {}", other),
+        },
+        "npm" | "pnpm" | "yarn" => match subcommand {
+            "install" | "i" | "add" => "Installs packages and updates the lockfile".to_string(),
+            "uninstall" | "remove" => "Removes packages".to_string(),
+            "run" => format!("Runs the {} script", targets.get(1).copied().unwrap_or("package")),
+            other => format!("Runs {} {}", program, other),
+        },
+        "cargo" => match subcommand {
+            "build" => "Builds the Rust project".to_string(),
+            "check" | "clippy" => "Checks the Rust project without building binaries".to_string(),
+            "test" => "Builds and runs the Rust tests".to_string(),
+            "run" => "Builds and runs the Rust project".to_string(),
+            "install" => "Installs a Rust binary into ~/.cargo/bin".to_string(),
+            other => format!("Runs cargo {}", other),
+        },
+        "docker" | "podman" => format!("Runs {} {}", program, subcommand),
+        _ => format!("Runs {}", program),
+    };
+    if privileged {
+        format!("{} (as root)", summary)
+    } else {
+        summary
+    }
+}
@@ -0,0 +1,290 @@
+// Memory of user corrections to suggested commands
+// When a suggestion is edited before it runs, the token diff is stored. Repeated
+// program swaps ("exa" instead of "ls") become personal style rules applied to
+// future suggestions, and corrected suggestions are ranked lower. Only edits that
+// keep to the suggestion count: the same program with a few arguments changed, or
+// another program with the same arguments. Typing something else entirely is a
+// new command, not a correction.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const MAX_CORRECTIONS: usize = 500;
+/// Net number of identical corrections before a swap becomes a style rule
+const STYLE_THRESHOLD: i32 = 2;
+/// Arguments that may be added, removed or changed for an edit to still be a correction
+const MAX_ARGUMENT_EDITS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Correction {
+    pub suggested: String,
+    pub executed: String,
+    pub replaced_program: Option<(String, String)>,
+    pub added_args: Vec<String>,
+    pub removed_args: Vec<String>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleRule {
+    pub description: String,
+    pub program: String,
+    pub replacement: Option<String>,
+    pub flag: Option<String>,
+    pub occurrences: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrectionSummary {
+    pub total: usize,
+    pub style: Vec<StyleRule>,
+    pub recent: Vec<Correction>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CorrectionStore {
+    corrections: Vec<Correction>,
+}
+
+fn is_assignment(token: &str) -> bool {
+    token.contains('=') && !token.starts_with('-')
+}
+
+/// Program name of a command, ignoring a leading `sudo` and env assignments
+fn program_index(tokens: &[&str]) -> Option<usize> {
+    tokens.iter().position(|t| *t != "sudo" && !is_assignment(t))
+}
+
+/// Number of tokens to insert, remove or replace to turn one list into the other
+fn edit_distance(a: &[&str], b: &[&str]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(x != y);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Whether `executed` is a correction of `suggested`: the same program with a few
+/// arguments edited, or another program given the same arguments
+fn is_correction(suggested: &str, executed: &str) -> bool {
+    let before: Vec<&str> = suggested.split_whitespace().collect();
+    let after: Vec<&str> = executed.split_whitespace().collect();
+    let (Some(i), Some(j)) = (program_index(&before), program_index(&after)) else {
+        return false;
+    };
+    let edits = edit_distance(&before[i + 1..], &after[j + 1..]);
+    if before[i] == after[j] {
+        edits <= MAX_ARGUMENT_EDITS
+    } else {
+        edits == 0
+    }
+}
+
+fn diff(suggested: &str, executed: &str) -> Correction {
+    let before: Vec<&str> = suggested.split_whitespace().collect();
+    let after: Vec<&str> = executed.split_whitespace().collect();
+    let program_before = program_index(&before).map(|i| before[i]);
+    let program_after = program_index(&after).map(|i| after[i]);
+    let replaced_program = match (program_before, program_after) {
+        (Some(a), Some(b)) if a != b => Some((a.to_string(), b.to_string())),
+        _ => None,
+    };
+
+    let mut remaining: Vec<&str> = before.clone();
+    let mut added_args = Vec::new();
+    for token in after.iter().skip(program_index(&after).map(|i| i + 1).unwrap_or(0)) {
+        match remaining.iter().position(|t| t == token) {
+            Some(i) => {
+                remaining.remove(i);
+            }
+            None => added_args.push(token.to_string()),
+        }
+    }
+    let removed_args = remaining
+        .into_iter()
+        .filter(|t| Some(*t) != program_before && *t != "sudo")
+        .filter(|t| !after.contains(t))
+        .map(str::to_string)
+        .collect();
+
+    Correction {
+        suggested: suggested.to_string(),
+        executed: executed.to_string(),
+        replaced_program,
+        added_args,
+        removed_args,
+        timestamp: chrono::Utc::now(),
+    }
+}
+
+impl CorrectionStore {
+    fn store_file() -> PathBuf {
+        super::data_directory().join("suggestion_corrections.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::store_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::store_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// Store the difference between a suggestion and what was actually run
+    pub fn record(&mut self, suggested: &str, executed: &str) -> Option<Correction> {
+        let suggested = suggested.trim().trim_start_matches("🤖 ").trim();
+        let executed = executed.trim();
+        if suggested.is_empty() || executed.is_empty() || suggested == executed || !is_correction(suggested, executed) {
+            return None;
+        }
+        let correction = diff(suggested, executed);
+        self.corrections.push(correction.clone());
+        if self.corrections.len() > MAX_CORRECTIONS {
+            let excess = self.corrections.len() - MAX_CORRECTIONS;
+            self.corrections.drain(..excess);
+        }
+        if let Err(e) = self.save() {
+            println!("⚠️ Failed to save suggestion corrections: {}", e);
+        }
+        Some(correction)
+    }
+
+    /// Corrections that keep to their suggestion; ones stored before the check are left out
+    fn relevant(&self) -> impl Iterator<Item = &Correction> {
+        self.corrections.iter().filter(|c| is_correction(&c.suggested, &c.executed))
+    }
+
+    /// Program swaps seen often enough to apply automatically
+    fn substitutions(&self) -> HashMap<String, (String, i32)> {
+        let mut counts: HashMap<(String, String), i32> = HashMap::new();
+        for correction in self.relevant() {
+            if let Some((from, to)) = &correction.replaced_program {
+                *counts.entry((from.clone(), to.clone())).or_default() += 1;
+                // Swapping back cancels out
+                *counts.entry((to.clone(), from.clone())).or_default() -= 1;
+            }
+        }
+        let mut best: HashMap<String, (String, i32)> = HashMap::new();
+        for ((from, to), count) in counts {
+            if count >= STYLE_THRESHOLD && best.get(&from).map(|(_, c)| count > *c).unwrap_or(true) {
+                best.insert(from, (to, count));
+            }
+        }
+        best
+    }
+
+    /// The implicit personal style learned from corrections
+    pub fn style(&self) -> Vec<StyleRule> {
+        let mut rules: Vec<StyleRule> = self
+            .substitutions()
+            .into_iter()
+            .map(|(from, (to, count))| StyleRule {
+                description: format!("uses `{}` instead of `{}`", to, from),
+                program: from,
+                replacement: Some(to),
+                flag: None,
+                occurrences: count,
+            })
+            .collect();
+
+        let mut flags: HashMap<(String, String), i32> = HashMap::new();
+        for correction in self.relevant() {
+            let tokens: Vec<&str> = correction.executed.split_whitespace().collect();
+            let Some(program) = program_index(&tokens).map(|i| tokens[i]) else { continue };
+            for flag in correction.added_args.iter().filter(|a| a.starts_with('-')) {
+                *flags.entry((program.to_string(), flag.clone())).or_default() += 1;
+            }
+            for flag in correction.removed_args.iter().filter(|a| a.starts_with('-')) {
+                *flags.entry((program.to_string(), flag.clone())).or_default() -= 1;
+            }
+        }
+        rules.extend(flags.into_iter().filter(|(_, count)| *count >= STYLE_THRESHOLD).map(|((program, flag), count)| {
+            StyleRule {
+                description: format!("adds `{}` to `{}`", flag, program),
+                program,
+                replacement: None,
+                flag: Some(flag),
+                occurrences: count,
+            }
+        }));
+        rules.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then(a.program.cmp(&b.program)));
+        rules
+    }
+
+    /// Rewrite a suggestion to the user's style: the program of each pipeline segment is swapped
+    /// where a rule exists for that very program
+    pub fn personalize(&self, command: &str) -> String {
+        let substitutions = self.substitutions();
+        if substitutions.is_empty() {
+            return command.to_string();
+        }
+        let (marker, body) = match command.strip_prefix("🤖 ") {
+            Some(rest) => ("🤖 ", rest),
+            None => ("", command),
+        };
+        let mut at_segment_start = true;
+        let rewritten: Vec<String> = body
+            .split(' ')
+            .map(|token| {
+                let out = if at_segment_start && !token.is_empty() && token != "sudo" && !is_assignment(token) {
+                    at_segment_start = false;
+                    substitutions.get(token).map(|(to, _)| to.clone()).unwrap_or_else(|| token.to_string())
+                } else {
+                    token.to_string()
+                };
+                if matches!(token, "|" | "&&" | "||" | ";") {
+                    at_segment_start = true;
+                }
+                out
+            })
+            .collect();
+        format!("{}{}", marker, rewritten.join(" "))
+    }
+
+    /// How often a suggestion was corrected; used to rank it lower
+    pub fn penalty(&self, suggestion: &str) -> usize {
+        let suggestion = suggestion.trim();
+        self.corrections.iter().filter(|c| c.suggested == suggestion).count()
+    }
+
+    /// Personalize suggestions and move frequently corrected ones to the end
+    pub fn rank(&self, suggestions: Vec<String>) -> Vec<String> {
+        let mut ranked: Vec<(usize, String)> = Vec::new();
+        for suggestion in suggestions {
+            let penalty = self.penalty(&suggestion);
+            let personalized = self.personalize(&suggestion);
+            if !ranked.iter().any(|(_, s)| *s == personalized) {
+                ranked.push((penalty, personalized));
+            }
+        }
+        ranked.sort_by_key(|(penalty, _)| *penalty);
+        ranked.into_iter().map(|(_, s)| s).collect()
+    }
+
+    pub fn summary(&self, recent: usize) -> CorrectionSummary {
+        CorrectionSummary {
+            total: self.corrections.len(),
+            style: self.style(),
+            recent: self.corrections.iter().rev().take(recent).cloned().collect(),
+        }
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.corrections.clear();
+        self.save()
+    }
+}
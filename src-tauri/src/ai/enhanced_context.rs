@@ -15,9 +15,34 @@ pub struct SystemContext {
     pub system_resources: SystemResources,
     pub recent_files: Vec<String>,
     pub git_status: Option<GitStatus>,
+    pub pull_request: Option<crate::integrations::forge::PullRequestStatus>,
+    pub ci_status: Option<crate::integrations::ci::CiStatus>,
     pub environment_variables: HashMap<String, String>,
     pub network_interfaces: Vec<NetworkInterface>,
     pub installed_tools: Vec<String>,
+    /// `user@host` of an SSH session; nothing else is gathered for one, since this machine's state says nothing about it
+    #[serde(default)]
+    pub remote_host: Option<String>,
+}
+
+impl SystemContext {
+    /// Context of an SSH session: where it is, and that it is another machine
+    pub fn remote(working_directory: &str, host: String) -> Self {
+        Self {
+            working_directory: working_directory.to_string(),
+            project_type: None,
+            running_processes: Vec::new(),
+            system_resources: SystemResources { cpu: 0.0, memory: 0.0, disk: 0.0, load_average: Vec::new(), process_count: 0 },
+            recent_files: Vec::new(),
+            git_status: None,
+            pull_request: None,
+            ci_status: None,
+            environment_variables: HashMap::new(),
+            network_interfaces: Vec::new(),
+            installed_tools: Vec::new(),
+            remote_host: Some(host),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +62,9 @@ pub struct GitStatus {
     pub behind: i32,
     pub last_commit: String,
     pub remote_url: Option<String>,
+    pub has_upstream: bool,
+    pub in_progress_operation: Option<String>, // rebase, merge, cherry-pick, revert
+    pub stale_branches: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,12 +91,99 @@ pub struct ProactiveSuggestion {
     pub description: String,
     pub commands: Vec<String>,
     pub trigger_condition: String,
+    /// Provenance record for `explain_suggestion`, set when the suggestion is returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// User settings for proactive suggestions, persisted in the AI data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestionSettings {
+    pub protected_branches: Vec<String>,
+    pub stale_branch_days: u32,
+    /// repo path -> trigger condition -> snoozed until (ms since epoch), None = dismissed
+    pub snoozed: HashMap<String, HashMap<String, Option<u64>>>,
+}
+
+impl Default for SuggestionSettings {
+    fn default() -> Self {
+        Self {
+            protected_branches: vec!["main".to_string(), "master".to_string()],
+            stale_branch_days: 30,
+            snoozed: HashMap::new(),
+        }
+    }
+}
+
+impl SuggestionSettings {
+    fn settings_file() -> PathBuf {
+        super::config_directory().join("suggestion_settings.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::settings_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::settings_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// Dismiss a suggestion for a repo, or snooze it for the given number of minutes
+    pub fn snooze(&mut self, repo_path: &str, trigger_condition: &str, minutes: Option<u64>) {
+        let until = minutes.map(|m| now_millis() + m * 60 * 1000);
+        self.snoozed
+            .entry(repo_path.to_string())
+            .or_default()
+            .insert(trigger_condition.to_string(), until);
+    }
+
+    pub fn is_suppressed(&self, repo_path: &str, trigger_condition: &str) -> bool {
+        match self.snoozed.get(repo_path).and_then(|repo| repo.get(trigger_condition)) {
+            Some(None) => true,
+            Some(Some(until)) => *until > now_millis(),
+            None => false,
+        }
+    }
+}
+
+/// Snoozes are stored per repository root so they apply from any subdirectory
+pub fn repo_key(path: &str) -> String {
+    Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
 }
 
 pub struct EnhancedContextProvider {
     cache_ttl: u64,
     last_update: u64,
     cached_context: Option<SystemContext>,
+    settings: SuggestionSettings,
+}
+
+impl Default for EnhancedContextProvider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EnhancedContextProvider {
@@ -77,6 +192,7 @@ impl EnhancedContextProvider {
             cache_ttl: 5000, // 5 seconds
             last_update: 0,
             cached_context: None,
+            settings: SuggestionSettings::load(),
         }
     }
 
@@ -94,16 +210,27 @@ impl EnhancedContextProvider {
         }
 
         // Gather fresh context
+        let git_status = self.get_git_status(working_dir).await;
+        let (pull_request, ci_status) = match git_status {
+            Some(ref status) if status.remote_url.is_some() => (
+                crate::integrations::forge::context_status(working_dir).await,
+                crate::integrations::ci::context_status(working_dir).await,
+            ),
+            _ => (None, None),
+        };
         let context = SystemContext {
             working_directory: working_dir.to_string(),
             project_type: self.detect_project_type(working_dir),
             running_processes: self.get_running_processes().await,
             system_resources: self.get_system_resources().await,
             recent_files: self.get_recent_files(working_dir).await,
-            git_status: self.get_git_status(working_dir).await,
+            git_status,
+            pull_request,
+            ci_status,
             environment_variables: self.get_relevant_env_vars(),
             network_interfaces: self.get_network_interfaces().await,
             installed_tools: self.get_installed_tools().await,
+            remote_host: None,
         };
 
         self.cached_context = Some(context.clone());
@@ -358,6 +485,9 @@ impl EnhancedContextProvider {
             behind: 0,
             last_commit: String::new(),
             remote_url: None,
+            has_upstream: false,
+            in_progress_operation: None,
+            stale_branches: Vec::new(),
         };
 
         // Get current branch
@@ -383,12 +513,16 @@ impl EnhancedContextProvider {
             .output() {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let parts: Vec<&str> = stdout.trim().split('\t').collect();
-            if parts.len() >= 2 {
+            if output.status.success() && parts.len() >= 2 {
+                git_status.has_upstream = true;
                 git_status.ahead = parts[0].parse().unwrap_or(0);
                 git_status.behind = parts[1].parse().unwrap_or(0);
             }
         }
 
+        git_status.in_progress_operation = self.detect_in_progress_operation(working_dir);
+        git_status.stale_branches = self.find_stale_branches(working_dir, &git_status.branch);
+
         // Get last commit
         if let Ok(output) = Command::new("git")
             .args(&["log", "-1", "--pretty=format:%h %s"])
@@ -411,6 +545,55 @@ impl EnhancedContextProvider {
         Some(git_status)
     }
 
+    /// Detect an unfinished rebase/merge/cherry-pick from the git directory markers
+    fn detect_in_progress_operation(&self, working_dir: &str) -> Option<String> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--git-dir"])
+            .current_dir(working_dir)
+            .output()
+            .ok()?;
+        let git_dir = PathBuf::from(working_dir).join(String::from_utf8_lossy(&output.stdout).trim());
+
+        if git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists() {
+            Some("rebase".to_string())
+        } else if git_dir.join("MERGE_HEAD").exists() {
+            Some("merge".to_string())
+        } else if git_dir.join("CHERRY_PICK_HEAD").exists() {
+            Some("cherry-pick".to_string())
+        } else if git_dir.join("REVERT_HEAD").exists() {
+            Some("revert".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Local branches without commits for longer than the configured threshold
+    fn find_stale_branches(&self, working_dir: &str, current_branch: &str) -> Vec<String> {
+        let output = match Command::new("git")
+            .args(["for-each-ref", "--format=%(refname:short) %(committerdate:unix)", "refs/heads/"])
+            .current_dir(working_dir)
+            .output() {
+            Ok(output) => output,
+            Err(_) => return vec![],
+        };
+
+        let cutoff = (now_millis() / 1000).saturating_sub(self.settings.stale_branch_days as u64 * 24 * 60 * 60);
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (name, timestamp) = line.rsplit_once(' ')?;
+                let timestamp: u64 = timestamp.parse().ok()?;
+                if timestamp < cutoff
+                    && name != current_branch
+                    && !self.settings.protected_branches.iter().any(|b| b == name) {
+                    Some(name.to_string())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     fn get_relevant_env_vars(&self) -> HashMap<String, String> {
         let mut env_vars = HashMap::new();
         
@@ -508,6 +691,7 @@ impl EnhancedContextProvider {
         // Disk space warning
         if context.system_resources.disk > 90.0 {
             suggestions.push(ProactiveSuggestion {
+                id: None,
                 suggestion_type: "maintenance".to_string(),
                 priority: 0.9,
                 description: "Disk space is running low".to_string(),
@@ -523,6 +707,7 @@ impl EnhancedContextProvider {
         // High CPU usage
         if context.system_resources.cpu > 85.0 {
             suggestions.push(ProactiveSuggestion {
+                id: None,
                 suggestion_type: "performance".to_string(),
                 priority: 0.8,
                 description: "High CPU usage detected".to_string(),
@@ -536,8 +721,70 @@ impl EnhancedContextProvider {
 
         // Git repository with uncommitted changes
         if let Some(ref git_status) = context.git_status {
-            if git_status.has_changes {
+            suggestions.extend(self.get_git_suggestions(git_status));
+        }
+
+        // Red CI on the current branch
+        if let Some(ref ci_status) = context.ci_status {
+            if ci_status.is_failing() {
+                suggestions.push(ProactiveSuggestion {
+                    id: None,
+                    suggestion_type: "ci".to_string(),
+                    priority: 0.85,
+                    description: format!("{} - inspect the failed jobs", ci_status.summary()),
+                    commands: ci_status.follow_up_commands(),
+                    trigger_condition: "ci_failed".to_string(),
+                });
+            }
+        }
+
+        // Drop anything the user dismissed or snoozed for this repo
+        let repo = repo_key(&context.working_directory);
+        suggestions.retain(|suggestion| !self.settings.is_suppressed(&repo, &suggestion.trigger_condition));
+
+        suggestions
+    }
+
+    /// Branch-aware git suggestions: sync with upstream, protected branches,
+    /// unfinished operations and stale branches
+    fn get_git_suggestions(&self, git_status: &GitStatus) -> Vec<ProactiveSuggestion> {
+        let mut suggestions = Vec::new();
+
+        // An unfinished rebase/merge blocks everything else
+        if let Some(ref operation) = git_status.in_progress_operation {
+            suggestions.push(ProactiveSuggestion {
+                id: None,
+                suggestion_type: "git_workflow".to_string(),
+                priority: 0.95,
+                description: format!("A git {} is in progress", operation),
+                commands: vec![
+                    "git status".to_string(),
+                    format!("git {} --continue", operation),
+                    format!("git {} --abort", operation),
+                ],
+                trigger_condition: format!("git_{}_in_progress", operation),
+            });
+            return suggestions;
+        }
+
+        let on_protected_branch = self.settings.protected_branches.contains(&git_status.branch);
+
+        if git_status.has_changes {
+            if on_protected_branch {
+                suggestions.push(ProactiveSuggestion {
+                    id: None,
+                    suggestion_type: "git_workflow".to_string(),
+                    priority: 0.8,
+                    description: format!("You have uncommitted changes on protected branch '{}'", git_status.branch),
+                    commands: vec![
+                        "git switch -c feature/my-change".to_string(),
+                        "git stash".to_string(),
+                    ],
+                    trigger_condition: "git_protected_branch_changes".to_string(),
+                });
+            } else {
                 suggestions.push(ProactiveSuggestion {
+                    id: None,
                     suggestion_type: "git_workflow".to_string(),
                     priority: 0.7,
                     description: "You have uncommitted changes".to_string(),
@@ -551,6 +798,75 @@ impl EnhancedContextProvider {
             }
         }
 
+        if git_status.behind > 0 && git_status.ahead > 0 {
+            suggestions.push(ProactiveSuggestion {
+                id: None,
+                suggestion_type: "git_sync".to_string(),
+                priority: 0.8,
+                description: format!(
+                    "Branch has diverged from upstream ({} ahead, {} behind)",
+                    git_status.ahead, git_status.behind
+                ),
+                commands: vec![
+                    "git pull --rebase --autostash".to_string(),
+                    "git log --oneline --left-right HEAD...@{upstream}".to_string(),
+                ],
+                trigger_condition: "git_diverged".to_string(),
+            });
+        } else if git_status.behind > 0 {
+            suggestions.push(ProactiveSuggestion {
+                id: None,
+                suggestion_type: "git_sync".to_string(),
+                priority: 0.75,
+                description: format!("Branch is {} commit(s) behind upstream", git_status.behind),
+                commands: vec![if git_status.has_changes {
+                    "git pull --rebase --autostash".to_string()
+                } else {
+                    "git pull".to_string()
+                }],
+                trigger_condition: "git_behind_upstream".to_string(),
+            });
+        } else if git_status.ahead > 0 {
+            suggestions.push(ProactiveSuggestion {
+                id: None,
+                suggestion_type: "git_sync".to_string(),
+                priority: 0.6,
+                description: format!("Branch has {} unpushed commit(s)", git_status.ahead),
+                commands: vec!["git push".to_string()],
+                trigger_condition: "git_ahead_upstream".to_string(),
+            });
+        } else if !git_status.has_upstream && !git_status.branch.is_empty() && git_status.remote_url.is_some() {
+            suggestions.push(ProactiveSuggestion {
+                id: None,
+                suggestion_type: "git_sync".to_string(),
+                priority: 0.5,
+                description: format!("Branch '{}' has no upstream", git_status.branch),
+                commands: vec![format!("git push -u origin {}", git_status.branch)],
+                trigger_condition: "git_no_upstream".to_string(),
+            });
+        }
+
+        if !git_status.stale_branches.is_empty() {
+            let mut commands: Vec<String> = git_status.stale_branches.iter()
+                .take(3)
+                .map(|branch| format!("git branch -d {}", branch))
+                .collect();
+            commands.insert(0, "git branch --merged".to_string());
+
+            suggestions.push(ProactiveSuggestion {
+                id: None,
+                suggestion_type: "git_maintenance".to_string(),
+                priority: 0.3,
+                description: format!(
+                    "{} branch(es) with no commits in {} days",
+                    git_status.stale_branches.len(),
+                    self.settings.stale_branch_days
+                ),
+                commands,
+                trigger_condition: "git_stale_branches".to_string(),
+            });
+        }
+
         suggestions
     }
 }
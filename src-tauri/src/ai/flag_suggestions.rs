@@ -0,0 +1,317 @@
+// Flag suggestions for a typed command
+// Once a command has been typed but not its flags (`tar `, `ffmpeg `), the
+// examples of its tldr page are ranked for what the user wants: the intent they
+// stated, or one inferred from the files they named or that sit in the working
+// directory. Flags the user already typed, or used with the command before, rank
+// an example higher. Every option lists its flags with the explanation from the
+// man page or `--help`. The model may add options of its own; they are kept only
+// when they run the typed command.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use crate::tools::help_docs::HelpFlag;
+use crate::tools::tldr::TldrPage;
+
+const MAX_OPTIONS: usize = 8;
+const MAX_MODEL_OPTIONS: usize = 3;
+/// Commands whose first argument may be bundled flags without a dash (`tar xvf`)
+const DASHLESS_FLAGS: [&str; 3] = ["tar", "ps", "ar"];
+const STOP_WORDS: [&str; 16] = [
+    "the", "and", "for", "with", "from", "into", "that", "this", "all", "its", "using", "want", "need", "some", "file", "files",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagSource {
+    Tldr,
+    Model,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagExplanation {
+    pub flag: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagOption {
+    /// The full command, placeholders in `{{...}}`
+    pub command: String,
+    pub flags: Vec<String>,
+    pub explanation: String,
+    /// What the man page or `--help` says about each flag it knows
+    pub flag_explanations: Vec<FlagExplanation>,
+    pub placeholders: Vec<String>,
+    /// 0.0 to 1.0
+    pub score: f32,
+    pub source: FlagSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagSuggestions {
+    pub command: String,
+    pub intent: Option<String>,
+    /// The intent was worked out from the context rather than stated
+    pub intent_inferred: bool,
+    pub options: Vec<FlagOption>,
+}
+
+/// What is known about the situation the command is typed in
+#[derive(Debug, Clone, Default)]
+pub struct FlagContext {
+    /// Names in the working directory
+    pub file_names: Vec<String>,
+    /// Earlier command lines running the same command
+    pub previous: Vec<String>,
+}
+
+/// The words naming the command: `git commit -m x` is git commit, while `tar xvf` is tar
+pub fn topic(command: &str) -> Vec<String> {
+    let mut topic = crate::tools::help_docs::help_topic(command);
+    if topic.first().is_some_and(|program| DASHLESS_FLAGS.contains(&program.as_str())) {
+        topic.truncate(1);
+    }
+    topic
+}
+
+fn mnemonic() -> &'static Regex {
+    static MNEMONIC: OnceLock<Regex> = OnceLock::new();
+    MNEMONIC.get_or_init(|| Regex::new(r"\[(\w)\]").unwrap())
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{.*?\}\}").unwrap())
+}
+
+/// Meaningful lowercase words, with common endings dropped so "extracting" meets "extract"
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| word.len() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| {
+            for ending in ["ing", "ed", "es", "s"] {
+                if word.len() > ending.len() + 3 {
+                    if let Some(stem) = word.strip_suffix(ending) {
+                        return stem.to_string();
+                    }
+                }
+            }
+            word
+        })
+        .collect()
+}
+
+fn extension(name: &str) -> Option<String> {
+    let (stem, extension) = name.rsplit_once('.')?;
+    let valid = !stem.is_empty() && (1..=5).contains(&extension.len()) && extension.chars().all(|c| c.is_ascii_alphanumeric());
+    valid.then(|| extension.to_lowercase())
+}
+
+/// The flags of a command line, after the words naming the command
+fn flags_of(command: &str, topic_words: usize) -> Vec<String> {
+    let line = placeholder().replace_all(command, "_");
+    let mut tokens = line.split_whitespace();
+    let program = tokens.next().unwrap_or_default().to_string();
+    let mut flags = Vec::new();
+    for (position, token) in tokens.skip(topic_words.saturating_sub(1)).enumerate() {
+        if token.len() > 1 && token.starts_with('-') && token != "--" {
+            let flag = token.split('=').next().unwrap_or(token).to_string();
+            if !flags.contains(&flag) {
+                flags.push(flag);
+            }
+        } else if position == 0 && DASHLESS_FLAGS.contains(&program.as_str()) && token.chars().all(|c| c.is_ascii_alphabetic()) {
+            flags.push(format!("-{}", token));
+        }
+    }
+    flags
+}
+
+/// Flags as compared for ranking: bundled ones of dashless commands letter by letter,
+/// so `tar xzf` counts as having used -x, -z and -f
+fn comparable(program: &str, flags: Vec<String>) -> HashSet<String> {
+    if !DASHLESS_FLAGS.contains(&program) {
+        return flags.into_iter().collect();
+    }
+    flags
+        .iter()
+        .flat_map(|flag| match flag.strip_prefix('-').filter(|rest| !rest.starts_with('-')) {
+            Some(letters) => letters.chars().map(|letter| format!("-{}", letter)).collect(),
+            None => vec![flag.clone()],
+        })
+        .collect()
+}
+
+/// What the help page says about a flag; bundled short flags (`-xvf`) are explained one by one
+fn explain_flags(flags: &[String], help: &[HelpFlag]) -> Vec<FlagExplanation> {
+    let describe = |name: &str| {
+        help.iter()
+            .find(|flag| flag.names.iter().any(|n| n == name))
+            .map(|flag| FlagExplanation { flag: name.to_string(), description: flag.description.clone() })
+    };
+    let mut explanations = Vec::new();
+    for flag in flags {
+        if let Some(explanation) = describe(flag) {
+            explanations.push(explanation);
+            continue;
+        }
+        // ffmpeg style stream specifiers: -c:v is -c for the video stream
+        if let Some(explanation) = flag.split_once(':').and_then(|(name, _)| describe(name)) {
+            explanations.push(FlagExplanation { flag: flag.clone(), ..explanation });
+            continue;
+        }
+        let letters = flag.strip_prefix('-').filter(|rest| !rest.starts_with('-') && rest.len() > 1).unwrap_or_default();
+        let bundled: Vec<FlagExplanation> = letters.chars().filter_map(|letter| describe(&format!("-{}", letter))).collect();
+        if !letters.is_empty() && bundled.len() == letters.chars().count() {
+            explanations.extend(bundled);
+        }
+    }
+    explanations
+}
+
+fn mentions_extension(command: &str, extension: &str) -> bool {
+    command.to_lowercase().contains(&format!(".{}", extension))
+}
+
+/// Extensions of the files the line names, else of those in the working directory,
+/// that the page has examples for: `tar ` next to a .gz is likely about that archive
+fn infer_intent(page: &TldrPage, typed: &str, context: &FlagContext) -> Option<String> {
+    let relevant = |names: Vec<&str>| {
+        let mut extensions: Vec<String> = Vec::new();
+        for extension in names.into_iter().filter_map(extension) {
+            if !extensions.contains(&extension) && page.examples.iter().any(|example| mentions_extension(&example.command, &extension)) {
+                extensions.push(extension);
+            }
+        }
+        extensions
+    };
+    let mut extensions = relevant(typed.split_whitespace().skip(1).collect());
+    if extensions.is_empty() {
+        extensions = relevant(context.file_names.iter().map(String::as_str).collect());
+    }
+    extensions.truncate(3);
+    if extensions.is_empty() {
+        return None;
+    }
+    let listed: Vec<String> = extensions.iter().map(|e| format!(".{}", e)).collect();
+    Some(format!("Work with the {} files here", listed.join(", ")))
+}
+
+/// The page's examples ranked for the intent; the intent, and whether it was inferred
+pub fn rank(
+    page: &TldrPage,
+    help: &[HelpFlag],
+    typed: &str,
+    topic_words: usize,
+    intent: Option<&str>,
+    context: &FlagContext,
+) -> (Vec<FlagOption>, Option<String>, bool) {
+    let stated = intent.map(str::trim).filter(|i| !i.is_empty()).map(str::to_string);
+    let inferred = stated.is_none();
+    let intent = stated.or_else(|| infer_intent(page, typed, context));
+    let intent_words = intent.as_deref().map(words).unwrap_or_default();
+    let program = page.name.split(['-', ' ']).next().unwrap_or_default();
+    let typed_flags = comparable(program, flags_of(typed, topic_words));
+    let used_before = comparable(program, context.previous.iter().flat_map(|line| flags_of(line, topic_words)).collect());
+    let extensions: Vec<String> = typed.split_whitespace().chain(context.file_names.iter().map(String::as_str)).filter_map(extension).collect();
+
+    let count = page.examples.len().max(1) as f32;
+    let mut options: Vec<FlagOption> = page
+        .examples
+        .iter()
+        .enumerate()
+        .map(|(position, example)| {
+            let explanation = capitalized(&mnemonic().replace_all(&example.description, "$1"));
+            let flags = flags_of(&example.command, topic_words);
+            let compared = comparable(program, flags.clone());
+            let example_words: HashSet<String> = words(&explanation).union(&words(&example.command)).cloned().collect();
+
+            let mut score = 0.0;
+            if !intent_words.is_empty() {
+                score += intent_words.intersection(&example_words).count() as f32 / intent_words.len() as f32;
+            }
+            if inferred && extensions.iter().any(|e| mentions_extension(&example.command, e)) {
+                score += 0.3;
+            }
+            if !typed_flags.is_empty() {
+                let kept = typed_flags.intersection(&compared).count();
+                score += 0.4 * kept as f32 / typed_flags.len() as f32;
+            }
+            if !compared.is_empty() {
+                score += 0.2 * compared.intersection(&used_before).count() as f32 / compared.len() as f32;
+            }
+            // tldr lists the most common uses first
+            score += 0.1 * (1.0 - position as f32 / count);
+
+            FlagOption {
+                command: example.command.clone(),
+                flag_explanations: explain_flags(&flags, help),
+                flags,
+                explanation,
+                placeholders: example.placeholders(),
+                score: score.min(1.0),
+                source: FlagSource::Tldr,
+            }
+        })
+        .collect();
+    options.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    options.truncate(MAX_OPTIONS);
+    let inferred = inferred && intent.is_some();
+    (options, intent, inferred)
+}
+
+/// Prompt and context for the model's own options
+pub fn model_prompt(command: &str, intent: Option<&str>, page: Option<&TldrPage>, synopsis: Option<&str>) -> (String, String) {
+    let prompt = format!(
+        "Suggest flags for `{}` to {}. Reply with one complete command per line.",
+        command,
+        intent.unwrap_or("do what it is most often used for")
+    );
+    let mut context = Vec::new();
+    if let Some(synopsis) = synopsis {
+        context.push(format!("Synopsis: {}", synopsis));
+    }
+    if let Some(page) = page {
+        context.extend(page.examples.iter().map(|example| format!("{}: {}", example.description, example.command)));
+    }
+    (prompt, context.join("\n"))
+}
+
+/// Command lines in the model's reply that run the command and are not already offered
+pub fn model_options(reply: &str, explanation: &str, confidence: f32, command: &str, help: &[HelpFlag], existing: &[FlagOption]) -> Vec<FlagOption> {
+    let topic_words = command.split_whitespace().count();
+    let mut options: Vec<FlagOption> = Vec::new();
+    for line in reply.lines() {
+        let line = line.trim().trim_start_matches("- ").trim_start_matches("$ ").trim_matches('`').replace("🤖 ", "");
+        let line = line.trim();
+        let runs_command = line.strip_prefix(command).is_some_and(|rest| rest.starts_with(' '));
+        if !runs_command || existing.iter().chain(options.iter()).any(|option| option.command == line) {
+            continue;
+        }
+        let flags = flags_of(line, topic_words);
+        if flags.is_empty() {
+            continue;
+        }
+        options.push(FlagOption {
+            command: line.to_string(),
+            flag_explanations: explain_flags(&flags, help),
+            flags,
+            explanation: explanation.to_string(),
+            placeholders: Vec::new(),
+            score: (confidence * 0.8).clamp(0.0, 1.0),
+            source: FlagSource::Model,
+        });
+        if options.len() == MAX_MODEL_OPTIONS {
+            break;
+        }
+    }
+    options
+}
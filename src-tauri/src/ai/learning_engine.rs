@@ -3,6 +3,18 @@ use std::fs;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, Timelike};
+use super::provenance::{HistoricalFrequency, MatchedPattern};
+
+/// Version of learning_data.json this build writes; see `ai::migrations`
+pub const DATA_VERSION: u32 = 2;
+
+/// Days for an unused pattern's confidence, or a command's weight, to halve
+const DECAY_HALF_LIFE_DAYS: f32 = 30.0;
+/// Patterns whose decayed confidence falls below this are pruned
+const PRUNE_CONFIDENCE: f32 = 0.05;
+/// Commands unused this long and run fewer than `STALE_MIN_RUNS` times are forgotten
+const STALE_STATS_DAYS: i64 = 180;
+const STALE_MIN_RUNS: u32 = 5;
 
 /// Learning data structure for AI training
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +49,36 @@ pub struct NeuralPattern {
     pub confidence: f32,
     pub usage_count: u32,
     pub success_rate: f32,
+    pub last_used: DateTime<Utc>,
+}
+
+impl NeuralPattern {
+    /// Confidence faded by the time since the pattern was last seen
+    pub fn decayed_confidence(&self, now: DateTime<Utc>) -> f32 {
+        self.confidence * decay_factor(self.last_used, now)
+    }
+}
+
+/// Half-life fading of a habit not seen since `last_used`
+fn decay_factor(last_used: DateTime<Utc>, now: DateTime<Utc>) -> f32 {
+    let days = (now - last_used).num_seconds().max(0) as f32 / 86_400.0;
+    0.5f32.powf(days / DECAY_HALF_LIFE_DAYS)
+}
+
+/// What `prune` removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PruneSummary {
+    pub patterns_removed: usize,
+    pub command_stats_removed: usize,
+}
+
+/// What `reset_pattern` removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatternReset {
+    pub command: String,
+    pub patterns_removed: Vec<String>,
+    pub command_stats_removed: bool,
+    pub examples_removed: usize,
 }
 
 /// Command frequency and success tracking
@@ -64,6 +106,7 @@ pub struct LearningEngine {
     session_workflows: HashMap<String, Vec<String>>, // Track command sequences per session
     temporal_patterns: HashMap<String, Vec<DateTime<Utc>>>, // Track usage times
     context_memory: HashMap<String, f32>, // Remember successful contexts
+    save_deferred: bool, // A periodic save was skipped to save power
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,7 +135,7 @@ impl LearningEngine {
         let (learning_data, patterns, command_stats, user_preferences) = 
             Self::load_or_create_data(&data_file);
 
-        Self {
+        let mut engine = Self {
             learning_data,
             patterns,
             command_stats,
@@ -103,7 +146,10 @@ impl LearningEngine {
             session_workflows: HashMap::new(),
             temporal_patterns: HashMap::new(),
             context_memory: HashMap::new(),
-        }
+            save_deferred: false,
+        };
+        engine.prune();
+        engine
     }
 
     fn load_or_create_data(data_file: &PathBuf) -> (
@@ -113,13 +159,20 @@ impl LearningEngine {
         UserPreferences
     ) {
         if let Ok(data) = fs::read_to_string(data_file) {
-            if let Ok(saved_data) = serde_json::from_str::<SavedLearningData>(&data) {
-                return (
-                    saved_data.learning_data,
-                    saved_data.patterns,
-                    saved_data.command_stats,
-                    saved_data.user_preferences,
-                );
+            match serde_json::from_str::<SavedLearningData>(&data) {
+                Ok(saved_data) => {
+                    return (
+                        saved_data.learning_data,
+                        saved_data.patterns,
+                        saved_data.command_stats,
+                        saved_data.user_preferences,
+                    );
+                }
+                // Starting over must not overwrite data a later fix could still recover
+                Err(e) => match super::migrations::quarantine(data_file) {
+                    Some(moved) => println!("⚠️ Learning data could not be read ({}), moved to {}", e, moved.display()),
+                    None => println!("⚠️ Learning data could not be read ({})", e),
+                },
             }
         }
 
@@ -172,14 +225,32 @@ impl LearningEngine {
             self.learning_data.remove(0);
         }
 
-        // Save data periodically
-        if self.learning_data.len() % 10 == 0 {
+        // Save data periodically, unless power saving asks to wait
+        if self.learning_data.len() % 10 == 0 || self.save_deferred {
+            self.prune();
+            self.save_deferred = crate::system::power::should_defer(crate::system::power::BackgroundWork::LearningPersistence);
+            if !self.save_deferred {
+                self.save_data();
+            }
+        }
+    }
+
+    /// Write a save that power saving put off
+    pub fn flush_deferred_save(&mut self) {
+        if self.save_deferred {
+            self.save_deferred = false;
             self.save_data();
         }
     }
 
     /// Update user feedback for a previous interaction
     pub fn update_feedback(&mut self, input: &str, feedback: f32) {
+        // The pattern behind a suggestion moves toward the rating, so rejected ones fade out
+        let key = if self.patterns.contains_key(input) { input.to_string() } else { self.generate_pattern_key(input) };
+        if let Some(pattern) = self.patterns.get_mut(&key) {
+            pattern.confidence = (pattern.confidence + feedback.clamp(0.0, 1.0)) / 2.0;
+        }
+        
         if let Some(example) = self.learning_data.iter_mut()
             .rev()
             .find(|ex| ex.input == input) {
@@ -200,10 +271,11 @@ impl LearningEngine {
         let context_features = self.extract_context_features(context);
         
         // Get suggestions from patterns
+        let now = Utc::now();
         for (pattern_key, pattern) in &self.patterns {
             let similarity = self.calculate_similarity(&context_features, &pattern.input_features);
             if similarity > 0.3 {
-                suggestions.push((pattern_key.clone(), similarity * pattern.confidence));
+                suggestions.push((pattern_key.clone(), similarity * pattern.decayed_confidence(now)));
             }
         }
 
@@ -221,12 +293,13 @@ impl LearningEngine {
     pub fn get_smart_completions(&self, partial_command: &str, context: &str) -> Vec<String> {
         let mut completions = Vec::new();
         
-        // Find similar commands from history
+        // Find similar commands from history; commands not run in a while weigh less
+        let now = Utc::now();
         for stats in self.command_stats.values() {
             if stats.command.starts_with(partial_command) && stats.success_count > 0 {
                 completions.push((
                     stats.command.clone(),
-                    stats.success_rate * (stats.frequency as f32).log2(),
+                    stats.success_rate * (stats.frequency as f32).log2() * decay_factor(stats.last_used, now),
                 ));
             }
         }
@@ -323,10 +396,14 @@ impl LearningEngine {
                 confidence: 0.5,
                 usage_count: 0,
                 success_rate: 0.0,
+                last_used: example.timestamp,
             });
 
         // Update pattern using gradient descent-like approach
         pattern.usage_count += 1;
+        // Time away fades confidence before this use counts
+        pattern.confidence = pattern.decayed_confidence(example.timestamp);
+        pattern.last_used = example.timestamp;
         let success_weight = if example.success { 1.0 } else { -0.5 };
         
         for (i, feature) in input_features.iter().enumerate() {
@@ -486,12 +563,65 @@ impl LearningEngine {
                 .collect(),
             learning_examples: self.learning_data.len(),
             patterns_learned: self.patterns.len(),
+            model_quality: Vec::new(),
+            activities: Vec::new(),
+            outcomes: std::collections::BTreeMap::new(),
         }
     }
 
+    /// Drop patterns that faded or were rated down, and rarely used commands not run in months
+    pub fn prune(&mut self) -> PruneSummary {
+        let now = Utc::now();
+        let patterns_before = self.patterns.len();
+        self.patterns.retain(|_, pattern| pattern.decayed_confidence(now) >= PRUNE_CONFIDENCE);
+        let stats_before = self.command_stats.len();
+        self.command_stats.retain(|_, stats| {
+            stats.frequency >= STALE_MIN_RUNS || now - stats.last_used < chrono::Duration::days(STALE_STATS_DAYS)
+        });
+        let stats_removed = stats_before - self.command_stats.len();
+        if stats_removed > 0 {
+            let command_stats = &self.command_stats;
+            self.user_preferences.preferred_commands.retain(|command, _| command_stats.contains_key(command));
+        }
+        PruneSummary { patterns_removed: patterns_before - self.patterns.len(), command_stats_removed: stats_removed }
+    }
+
+    /// Forget what was learned from a command, or a pattern by its key, so it stops being suggested
+    pub fn reset_pattern(&mut self, command: &str) -> PatternReset {
+        let command = command.trim();
+        let key = if self.patterns.contains_key(command) { command.to_string() } else { self.generate_pattern_key(command) };
+        let mut patterns_removed: Vec<String> = self
+            .patterns
+            .keys()
+            .filter(|pattern_key| **pattern_key == key || (pattern_key.starts_with("workflow:") && pattern_key.split("->").any(|step| step.trim_start_matches("workflow:") == key)))
+            .cloned()
+            .collect();
+        patterns_removed.sort();
+        for pattern_key in &patterns_removed {
+            self.patterns.remove(pattern_key);
+        }
+        self.temporal_patterns.remove(&key);
+        let command_stats_removed = self.command_stats.remove(command).is_some();
+        self.user_preferences.preferred_commands.remove(command);
+        let examples_before = self.learning_data.len();
+        self.learning_data.retain(|example| example.input != command);
+        for workflow in self.session_workflows.values_mut() {
+            workflow.retain(|step| step != command);
+        }
+        let reset = PatternReset {
+            command: command.to_string(),
+            patterns_removed,
+            command_stats_removed,
+            examples_removed: examples_before - self.learning_data.len(),
+        };
+        self.save_data();
+        reset
+    }
+
     /// Save learning data to disk
     pub fn save_data(&self) {
         let saved_data = SavedLearningData {
+            schema_version: DATA_VERSION,
             learning_data: self.learning_data.clone(),
             patterns: self.patterns.clone(),
             command_stats: self.command_stats.clone(),
@@ -565,11 +695,13 @@ impl LearningEngine {
                     confidence: 0.7,
                     usage_count: 0,
                     success_rate: 0.8, // Assume workflows are generally successful
+                    last_used: Utc::now(),
                 }
             });
             
             workflow_pattern.usage_count += 1;
-            workflow_pattern.confidence = (workflow_pattern.confidence + 0.1).min(1.0);
+            workflow_pattern.confidence = (workflow_pattern.decayed_confidence(Utc::now()) + 0.1).min(1.0);
+            workflow_pattern.last_used = Utc::now();
         }
     }
 
@@ -607,9 +739,10 @@ impl LearningEngine {
         }
         
         // Get regular pattern-based suggestions with context boost
+        let now = Utc::now();
         for (pattern_key, pattern) in &self.patterns {
             let similarity = self.calculate_similarity(&context_features, &pattern.input_features);
-            let boosted_confidence = pattern.confidence * (1.0 + context_boost);
+            let boosted_confidence = pattern.decayed_confidence(now) * (1.0 + context_boost);
             
             if similarity > 0.3 {
                 suggestions.push((pattern_key.clone(), similarity * boosted_confidence));
@@ -624,6 +757,24 @@ impl LearningEngine {
             .collect()
     }
 
+    /// The learned pattern behind a suggestion and how often it was run, for explaining it
+    pub fn evidence(&self, suggestion: &str) -> (Option<MatchedPattern>, Option<HistoricalFrequency>) {
+        let key = if self.patterns.contains_key(suggestion) { suggestion.to_string() } else { self.generate_pattern_key(suggestion) };
+        let pattern = self.patterns.get(&key).map(|pattern| MatchedPattern {
+            key,
+            usage_count: pattern.usage_count,
+            confidence: pattern.confidence,
+            success_rate: pattern.success_rate,
+        });
+        let frequency = self.command_stats.get(suggestion.trim()).map(|stats| HistoricalFrequency {
+            runs: stats.frequency,
+            success_rate: Some(stats.success_rate),
+            last_used: Some(stats.last_used),
+            ..Default::default()
+        });
+        (pattern, frequency)
+    }
+
     /// Get workflow-based suggestions
     fn get_workflow_suggestions(&self, recent_commands: &[String]) -> Vec<(String, f32)> {
         let mut suggestions = Vec::new();
@@ -631,7 +782,7 @@ impl LearningEngine {
         for (pattern_key, pattern) in &self.patterns {
             if pattern_key.starts_with("workflow:") && pattern.usage_count > 2 {
                 // Simple workflow matching - could be more sophisticated
-                let workflow_confidence = pattern.confidence * 1.2; // Boost workflow suggestions
+                let workflow_confidence = pattern.decayed_confidence(Utc::now()) * 1.2; // Boost workflow suggestions
                 suggestions.push((pattern_key.clone(), workflow_confidence));
             }
         }
@@ -643,12 +794,19 @@ impl LearningEngine {
 /// Data structure for saving/loading
 #[derive(Serialize, Deserialize)]
 struct SavedLearningData {
+    #[serde(default)]
+    schema_version: u32,
     learning_data: Vec<LearningExample>,
     patterns: HashMap<String, NeuralPattern>,
     command_stats: HashMap<String, CommandStats>,
     user_preferences: UserPreferences,
 }
 
+/// Whether `json` loads as saved learning data
+pub fn validate_saved_data(json: &str) -> Result<(), String> {
+    serde_json::from_str::<SavedLearningData>(json).map(|_| ()).map_err(|e| e.to_string())
+}
+
 /// User analytics for insights
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserAnalytics {
@@ -657,6 +815,15 @@ pub struct UserAnalytics {
     pub most_used_commands: Vec<(String, u32)>,
     pub learning_examples: usize,
     pub patterns_learned: usize,
+    /// Per-backend results from model comparisons
+    #[serde(default)]
+    pub model_quality: Vec<crate::models::routing::BackendQuality>,
+    /// Activity clusters from the last history analysis
+    #[serde(default)]
+    pub activities: Vec<crate::ai::activity::ActivityCluster>,
+    /// Recent commands by how they ended (success, exit_code, signaled, timed_out, not_found, spawn_error)
+    #[serde(default)]
+    pub outcomes: std::collections::BTreeMap<String, usize>,
 }
 
 impl Drop for LearningEngine {
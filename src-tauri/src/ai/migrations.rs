@@ -0,0 +1,264 @@
+// Versioned migrations for files in the AI data directory
+// Each data file carries a `schema_version`; files written before versioning
+// count as version 0. At startup every registered file older than its current
+// version is backed up and migrated step by step, then checked against what
+// the loader expects before it is written back. A file that cannot be migrated
+// is left untouched and reported, never replaced with empty data.
+//
+// Changing a saved struct: bump the file's version and add a step that brings
+// the previous version's JSON up to date (e.g. filling in the new field).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::learning_engine;
+
+pub const VERSION_KEY: &str = "schema_version";
+
+struct Step {
+    /// Version the file has after this step
+    to: u32,
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>) -> Result<(), String>,
+}
+
+struct DataFile {
+    name: &'static str,
+    version: u32,
+    steps: &'static [Step],
+    /// Whether the migrated JSON loads the way the app will read it
+    validate: fn(&str) -> Result<(), String>,
+}
+
+const DATA_FILES: [DataFile; 1] = [DataFile {
+    name: "learning_data.json",
+    version: learning_engine::DATA_VERSION,
+    steps: &[
+        Step { to: 1, description: "fill in fields missing from early learning data", apply: learning_data_v1 },
+        Step { to: 2, description: "date learned patterns so unused ones fade", apply: learning_data_v2 },
+    ],
+    validate: learning_engine::validate_saved_data,
+}];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStatus {
+    /// Not created yet
+    Missing,
+    UpToDate,
+    Migrated,
+    /// Written by a newer version of the app; left alone
+    Newer,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMigration {
+    pub file: String,
+    pub status: MigrationStatus,
+    pub from_version: Option<u32>,
+    pub to_version: u32,
+    /// Steps applied, in order
+    pub applied: Vec<String>,
+    /// Copy of the file as it was before migrating
+    pub backup: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub ran_at: chrono::DateTime<chrono::Utc>,
+    pub files: Vec<FileMigration>,
+}
+
+fn last_report() -> &'static OnceLock<MigrationReport> {
+    static REPORT: OnceLock<MigrationReport> = OnceLock::new();
+    &REPORT
+}
+
+/// The report of the migrations run at startup
+pub fn report() -> Option<MigrationReport> {
+    last_report().get().cloned()
+}
+
+fn backup_directory() -> PathBuf {
+    super::data_directory().join("backups")
+}
+
+/// Copy `path` into the backups directory, tagged with `label`
+fn backup(path: &Path, label: &str) -> Result<PathBuf, String> {
+    let dir = backup_directory();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let target = dir.join(format!("{}.{}.{}", name, label, chrono::Utc::now().format("%Y%m%dT%H%M%S")));
+    std::fs::copy(path, &target).map_err(|e| format!("Cannot back up {}: {}", path.display(), e))?;
+    Ok(target)
+}
+
+/// Move an unreadable data file out of the way so starting over cannot overwrite it
+pub fn quarantine(path: &Path) -> Option<PathBuf> {
+    let target = backup(path, "unreadable").ok()?;
+    std::fs::remove_file(path).ok()?;
+    Some(target)
+}
+
+/// Migrate every registered file; run once at startup, before anything loads them
+pub fn run() -> MigrationReport {
+    let files = DATA_FILES.iter().map(|file| migrate_file(&super::data_directory().join(file.name), file)).collect();
+    let report = MigrationReport { ran_at: chrono::Utc::now(), files };
+    for file in &report.files {
+        match file.status {
+            MigrationStatus::Migrated => println!("🗄️ Migrated {} to version {} ({})", file.file, file.to_version, file.applied.join(", ")),
+            MigrationStatus::Failed => println!("⚠️ Could not migrate {}: {}", file.file, file.error.as_deref().unwrap_or_default()),
+            _ => {}
+        }
+    }
+    let _ = last_report().set(report.clone());
+    report
+}
+
+fn migrate_file(path: &Path, file: &DataFile) -> FileMigration {
+    let mut outcome = FileMigration {
+        file: file.name.to_string(),
+        status: MigrationStatus::UpToDate,
+        from_version: None,
+        to_version: file.version,
+        applied: Vec::new(),
+        backup: None,
+        error: None,
+    };
+    let Ok(text) = std::fs::read_to_string(path) else {
+        outcome.status = MigrationStatus::Missing;
+        return outcome;
+    };
+    let fail = |mut outcome: FileMigration, error: String| {
+        outcome.status = MigrationStatus::Failed;
+        outcome.error = Some(error);
+        outcome
+    };
+
+    let mut value: Map<String, Value> = match serde_json::from_str(&text) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => return fail(outcome, "not a JSON object".to_string()),
+        Err(e) => return fail(outcome, format!("not valid JSON: {}", e)),
+    };
+    let from = value.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0) as u32;
+    outcome.from_version = Some(from);
+    if from > file.version {
+        outcome.status = MigrationStatus::Newer;
+        return outcome;
+    }
+    if from == file.version {
+        return outcome;
+    }
+
+    match backup(path, &format!("v{}", from)) {
+        Ok(backup) => outcome.backup = Some(backup.to_string_lossy().to_string()),
+        Err(e) => return fail(outcome, e),
+    }
+    for step in file.steps.iter().filter(|step| step.to > from && step.to <= file.version) {
+        if let Err(e) = (step.apply)(&mut value) {
+            return fail(outcome, format!("{}: {}", step.description, e));
+        }
+        value.insert(VERSION_KEY.to_string(), json!(step.to));
+        outcome.applied.push(step.description.to_string());
+    }
+
+    let migrated = match serde_json::to_string_pretty(&value) {
+        Ok(json) => json,
+        Err(e) => return fail(outcome, e.to_string()),
+    };
+    if let Err(e) = (file.validate)(&migrated) {
+        return fail(outcome, format!("migrated data does not load: {}", e));
+    }
+    // Written beside the file and renamed, so a crash leaves either version intact
+    let partial = path.with_extension("json.migrating");
+    if let Err(e) = std::fs::write(&partial, migrated).and_then(|_| std::fs::rename(&partial, path)) {
+        return fail(outcome, format!("cannot write {}: {}", path.display(), e));
+    }
+    outcome.status = MigrationStatus::Migrated;
+    outcome
+}
+
+/// Insert `default` for keys the object does not have
+fn fill(object: &mut Map<String, Value>, defaults: &[(&str, Value)]) {
+    for (key, default) in defaults {
+        object.entry(key.to_string()).or_insert_with(|| default.clone());
+    }
+}
+
+/// Early builds saved examples and stats before some fields existed
+fn learning_data_v1(data: &mut Map<String, Value>) -> Result<(), String> {
+    let now = json!(chrono::Utc::now());
+    fill(data, &[
+        ("learning_data", json!([])),
+        ("patterns", json!({})),
+        ("command_stats", json!({})),
+        ("user_preferences", json!({})),
+    ]);
+
+    let examples = data.get_mut("learning_data").and_then(Value::as_array_mut).ok_or("learning_data is not a list")?;
+    for example in examples.iter_mut() {
+        let example = example.as_object_mut().ok_or("a learning example is not an object")?;
+        if !example.contains_key("input") || !example.contains_key("output") {
+            return Err("a learning example has no input or output".to_string());
+        }
+        fill(example, &[
+            ("context", json!("")),
+            ("user_feedback", Value::Null),
+            ("timestamp", now.clone()),
+            ("success", json!(true)),
+            ("command_type", json!("Other")),
+        ]);
+    }
+
+    let patterns = data.get_mut("patterns").and_then(Value::as_object_mut).ok_or("patterns is not an object")?;
+    for pattern in patterns.values_mut() {
+        let pattern = pattern.as_object_mut().ok_or("a pattern is not an object")?;
+        fill(pattern, &[
+            ("input_features", json!([])),
+            ("output_weights", json!([])),
+            ("bias", json!(0.0)),
+            ("confidence", json!(0.0)),
+            ("usage_count", json!(0)),
+            ("success_rate", json!(0.0)),
+        ]);
+    }
+
+    let stats = data.get_mut("command_stats").and_then(Value::as_object_mut).ok_or("command_stats is not an object")?;
+    for (command, stat) in stats.iter_mut() {
+        let stat = stat.as_object_mut().ok_or("a command statistic is not an object")?;
+        fill(stat, &[
+            ("command", json!(command)),
+            ("frequency", json!(0)),
+            ("success_count", json!(0)),
+            ("failure_count", json!(0)),
+            ("success_rate", json!(0.0)),
+            ("avg_execution_time", json!(0.0)),
+            ("contexts", json!([])),
+            ("last_used", now.clone()),
+        ]);
+    }
+
+    let preferences = data.get_mut("user_preferences").and_then(Value::as_object_mut).ok_or("user_preferences is not an object")?;
+    fill(preferences, &[
+        ("preferred_commands", json!({})),
+        ("command_aliases", json!({})),
+        ("context_weights", json!({})),
+        ("learning_aggressiveness", json!(0.7)),
+    ]);
+    Ok(())
+}
+
+/// Patterns gained `last_used` for decay; existing ones start fading from the upgrade
+fn learning_data_v2(data: &mut Map<String, Value>) -> Result<(), String> {
+    let now = json!(chrono::Utc::now());
+    let patterns = data.get_mut("patterns").and_then(Value::as_object_mut).ok_or("patterns is not an object")?;
+    for pattern in patterns.values_mut() {
+        let pattern = pattern.as_object_mut().ok_or("a pattern is not an object")?;
+        fill(pattern, &[("last_used", now.clone())]);
+    }
+    Ok(())
+}
@@ -4,7 +4,19 @@
 pub mod learning_engine;
 pub mod agent;
 pub mod enhanced_context;
+pub mod trust;
+pub mod code_context;
+pub mod corrections;
+pub mod activity;
+pub mod translation_guard;
+pub mod migrations;
+pub mod command_review;
+pub mod suggestion_throttle;
+pub mod provenance;
+pub mod voice;
+pub mod flag_suggestions;
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -12,7 +24,13 @@ use serde::{Deserialize, Serialize};
 
 use learning_engine::LearningEngine;
 use agent::IntelligentAgent;
+use corrections::{CorrectionStore, Correction, CorrectionSummary};
+use activity::ActivityReport;
 use crate::models::{LightweightLLM, LLMFactory, InferenceRequest, Capability};
+use crate::models::routing::{self, ModelBackend, ModelRoutes, Comparison, ComparisonResult, ComparisonStore};
+use crate::models::usage::{UsageBudget, UsageLedger, UsageStats};
+use crate::models::{LLMResponse, LocalEmbeddingStore, EmbeddingIndexStats, SemanticSearchResult};
+use crate::models::sentence_encoder::{self, SentenceEncoder};
 
 // Re-export public types
 pub use learning_engine::UserAnalytics;
@@ -23,6 +41,9 @@ pub struct AIResponse {
     pub text: String,
     pub confidence: f32,
     pub reasoning: Option<String>,
+    /// Provenance record for `explain_suggestion`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -46,21 +67,177 @@ impl Default for ModelConfig {
     }
 }
 
+/// Directory where learning data and other persisted AI state live
+pub fn data_directory() -> PathBuf {
+    crate::system::app_dirs::data_dir()
+}
+
+/// Directory for settings the user changes
+pub fn config_directory() -> PathBuf {
+    crate::system::app_dirs::config_dir()
+}
+
+/// Downloaded models, shared by every profile: they are large, hold nothing of the
+/// user's, and demo mode starts each time from an emptied profile
+pub fn models_directory() -> PathBuf {
+    crate::system::app_dirs::base_dirs().0.join("models")
+}
+
+/// Run a backend within the daily budget and record its token usage
+async fn run_accounted(
+    usage: &Mutex<UsageLedger>,
+    backend: &ModelBackend,
+    request: InferenceRequest,
+    pattern_engine: Option<&LightweightLLM>,
+) -> Result<LLMResponse, String> {
+    // The pattern engine is local and free, so budgets only gate model backends
+    if *backend != ModelBackend::PatternEngine {
+        usage.lock().await.check_budget()?;
+    }
+    let capability = request.capability.clone();
+    let (response, tokens) = routing::run_backend(backend, request, pattern_engine).await?;
+    let cost = backend.cost(&tokens);
+    usage.lock().await.record(&capability, &backend.label(), tokens, cost);
+    Ok(response)
+}
+
+/// Shares the ModelManager's stores; labeling calls out to models, which can take a while
+pub struct ActivityAnalyzer {
+    routes: Arc<Mutex<ModelRoutes>>,
+    usage: Arc<Mutex<UsageLedger>>,
+    embeddings: Arc<Mutex<LocalEmbeddingStore>>,
+    activity: Arc<Mutex<Option<ActivityReport>>>,
+}
+
+impl ActivityAnalyzer {
+    /// Cluster recent history into activities and name them, with a model when one is routed
+    pub async fn run(&self) -> ActivityReport {
+        let (entries, model) = {
+            let embeddings = self.embeddings.lock().await;
+            let model = embeddings.active_model().to_string();
+            (embeddings.recent_entries(&model, activity::MAX_ENTRIES), model)
+        };
+        let analysed_commands = entries.len();
+        let mut clusters = tokio::task::spawn_blocking(move || activity::cluster_activity(&entries))
+            .await
+            .unwrap_or_default();
+
+        // The pattern engine cannot name things, so only model backends replace heuristic labels
+        let backend = self.routes.lock().await.backend_for(&Capability::CommandSuggestion);
+        if backend != ModelBackend::PatternEngine {
+            for cluster in clusters.iter_mut() {
+                let request = InferenceRequest {
+                    prompt: activity::labeling_prompt(cluster),
+                    max_tokens: Some(16),
+                    temperature: Some(0.2),
+                    capability: Capability::CommandSuggestion,
+                    context: None,
+                };
+                match run_accounted(&self.usage, &backend, request, None).await {
+                    Ok(response) => {
+                        if let Some(label) = activity::clean_label(&response.text) {
+                            cluster.label = label;
+                            cluster.labeled_by = "llm".to_string();
+                        }
+                    }
+                    Err(e) => {
+                        println!("⚠️ Activity labeling with {} failed: {}", backend.label(), e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        let report = ActivityReport { clusters, analysed_commands, embedding_model: model, generated_at: chrono::Utc::now() };
+        *self.activity.lock().await = Some(report.clone());
+        report
+    }
+}
+
+fn is_project_entry(embedding: &crate::models::embeddings::EmbeddingVector, root: &str) -> bool {
+    embedding.metadata.get("type").is_some_and(|kind| kind == "project")
+        && embedding.metadata.get("project").is_some_and(|project| project == root)
+}
+
+/// What a free-form prompt asks for, so that each capability can be routed to its own backend.
+/// Natural language to translate into a command goes through `natural_language_to_command_ml`
+fn capability_for_prompt(prompt: &str) -> Option<Capability> {
+    let prompt = prompt.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| prompt.contains(word));
+    if has(&["natural language"]) {
+        return None;
+    }
+    if has(&["find file", "find files", "locate file", "search for file", "where is the file"]) {
+        Some(Capability::FileSearch)
+    } else if has(&["log file", "logs", "journalctl", "syslog"]) {
+        Some(Capability::LogAnalysis)
+    } else if has(&["error", "failed", "fix this", "how do i fix", "how to fix"]) {
+        Some(Capability::ErrorAnalysis)
+    } else if has(&["diagnose", "diagnostic", "slow", "high cpu", "memory usage", "disk full", "out of memory"]) {
+        Some(Capability::SystemDiagnostics)
+    } else if has(&["write a script", "write a function", "generate code", "write code", "code for"]) {
+        Some(Capability::CodeGeneration)
+    } else if has(&["explain"]) {
+        Some(Capability::CommandExplanation)
+    } else if has(&["summarize", "summarise", "output"]) {
+        Some(Capability::OutputAnalysis)
+    } else if has(&["suggest command", "what command", "recommend"]) {
+        Some(Capability::CommandSuggestion)
+    } else {
+        None
+    }
+}
+
+/// Replace a project's symbols, README and manifests in the embedding index. The documents are
+/// embedded in one batch while nothing is locked; the store is taken only to swap them in
+pub async fn index_project(embeddings: &Mutex<LocalEmbeddingStore>, index: &crate::project::symbols::ProjectIndex) -> usize {
+    let documents = index.documents();
+    let embedder = embeddings.lock().await.embedder();
+    let vectors = embedder.embed_batch_blocking(documents.iter().map(|(_, text, _)| text.clone()).collect()).await;
+    let count = documents.len();
+    let timestamp = chrono::Utc::now();
+    let mut embeddings = embeddings.lock().await;
+    embeddings.remove_where(|e| is_project_entry(e, &index.root));
+    for ((id, text, mut metadata), embedded) in documents.into_iter().zip(vectors) {
+        metadata.insert("project".to_string(), index.root.clone());
+        embeddings.insert_document(&id, &text, "project", metadata, timestamp, embedded);
+    }
+    count
+}
+
+/// An execution id, command, metadata and time, waiting to be embedded
+type DeferredCommand = (String, String, HashMap<String, String>, chrono::DateTime<chrono::Utc>);
+
 pub struct ModelManager {
     learning_engine: Arc<Mutex<LearningEngine>>,
     agent: Arc<Mutex<IntelligentAgent>>,
     llm_engine: Arc<Mutex<Option<LightweightLLM>>>,
+    corrections: Arc<Mutex<CorrectionStore>>,
+    routes: Arc<Mutex<ModelRoutes>>,
+    comparisons: Arc<Mutex<ComparisonStore>>,
+    usage: Arc<Mutex<UsageLedger>>,
+    embeddings: Arc<Mutex<LocalEmbeddingStore>>,
+    activity: Arc<Mutex<Option<ActivityReport>>>,
     config: ModelConfig,
     is_loaded: bool,
     data_directory: PathBuf,
+    /// Commands waiting to be embedded until power saving ends
+    deferred_commands: Arc<Mutex<Vec<DeferredCommand>>>,
+    encoder_deferred: bool,
+    /// Short-lived processes (the `ph7` tool) leave model loading and re-indexing to the app
+    headless: bool,
+}
+
+impl Default for ModelManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ModelManager {
     pub fn new() -> Self {
         // Setup data directory for learning engine
-        let data_directory = std::env::current_dir()
-            .unwrap_or_else(|_| PathBuf::from("."))
-            .join("ai_data");
+        let data_directory = data_directory();
         
         // Create directory if it doesn't exist
         std::fs::create_dir_all(&data_directory).ok();
@@ -81,12 +258,26 @@ impl ModelManager {
             learning_engine,
             agent,
             llm_engine: Arc::new(Mutex::new(None)),
+            corrections: Arc::new(Mutex::new(CorrectionStore::load())),
+            routes: Arc::new(Mutex::new(ModelRoutes::load())),
+            comparisons: Arc::new(Mutex::new(ComparisonStore::load())),
+            usage: Arc::new(Mutex::new(UsageLedger::load())),
+            embeddings: Arc::new(Mutex::new(LocalEmbeddingStore::open(data_directory.join("embeddings").join("commands.segment")))),
+            activity: Arc::new(Mutex::new(None)),
             config: ModelConfig::default(),
             is_loaded: false,
             data_directory,
+            deferred_commands: Arc::new(Mutex::new(Vec::new())),
+            encoder_deferred: false,
+            headless: false,
         }
     }
 
+    /// For one-off use from the command line: nothing keeps running in the background
+    pub fn new_headless() -> Self {
+        Self { headless: true, ..Self::new() }
+    }
+
     pub async fn load_model(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if self.is_loaded {
             return Ok(());
@@ -110,21 +301,98 @@ impl ModelManager {
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         
         self.is_loaded = true;
+        // Loading the sentence model and re-embedding history is heavy; on low battery it waits
+        self.encoder_deferred = crate::system::power::should_defer(crate::system::power::BackgroundWork::ModelWarmup);
+        if !self.encoder_deferred && !self.headless {
+            self.start_sentence_encoder();
+        }
         println!("✅ AI learning system with lightweight LLM loaded successfully");
         println!("🧠 Ready to process natural language commands with ML accuracy");
         
         Ok(())
     }
 
+    /// Load the sentence embedding model and wait for it, so a one-off search compares against
+    /// the vectors the app indexed with; without it searches use hashed vectors
+    pub async fn load_sentence_encoder(&self) {
+        if self.embeddings.lock().await.encoder().is_some() {
+            return;
+        }
+        match SentenceEncoder::load(sentence_encoder::model_directory()).await {
+            Ok(encoder) => self.embeddings.lock().await.set_encoder(Arc::new(encoder)),
+            Err(e) => println!("⚠️ Sentence embeddings unavailable, using hashed vectors: {}", e),
+        }
+    }
+
+    /// Load the sentence embedding model in the background (downloading it on first run),
+    /// then re-embed entries indexed with hashed vectors. Failure keeps the hash method.
+    fn start_sentence_encoder(&self) {
+        let embeddings = self.embeddings.clone();
+        tokio::spawn(async move {
+            if embeddings.lock().await.encoder().is_some() {
+                return;
+            }
+            let encoder = match SentenceEncoder::load(sentence_encoder::model_directory()).await {
+                Ok(encoder) => Arc::new(encoder),
+                Err(e) => {
+                    println!("⚠️ Sentence embeddings unavailable, using hashed vectors: {}", e);
+                    return;
+                }
+            };
+            embeddings.lock().await.set_encoder(encoder.clone());
+            println!("✅ Sentence embedding model {} loaded", sentence_encoder::MODEL_NAME);
+
+            let mut reembedded = 0;
+            loop {
+                let batch = embeddings.lock().await.stale_entries(64);
+                if batch.is_empty() {
+                    break;
+                }
+                let (ids, texts): (Vec<String>, Vec<String>) = batch.into_iter().unzip();
+                let batch_encoder = encoder.clone();
+                let vectors = match tokio::task::spawn_blocking(move || batch_encoder.encode_batch(&texts)).await {
+                    Ok(Ok(vectors)) => vectors,
+                    Ok(Err(e)) => {
+                        println!("⚠️ Re-embedding stopped: {}", e);
+                        break;
+                    }
+                    Err(e) => {
+                        println!("⚠️ Re-embedding stopped: {}", e);
+                        break;
+                    }
+                };
+                let replaced = embeddings.lock().await.replace_vectors(ids.into_iter().zip(vectors).collect(), sentence_encoder::MODEL_NAME);
+                if replaced == 0 {
+                    break;
+                }
+                reembedded += replaced;
+            }
+            if reembedded > 0 {
+                println!("🔁 Re-embedded {} indexed commands", reembedded);
+                if let Err(e) = embeddings.lock().await.compact() {
+                    println!("⚠️ {}", e);
+                }
+            }
+        });
+    }
+
     pub async fn generate_response(&self, prompt: &str, context: Option<&str>) -> AIResponse {
         if !self.is_loaded {
             return AIResponse {
                 text: "AI learning system not loaded".to_string(),
                 confidence: 0.0,
                 reasoning: Some("AI learning system needs to be initialized".to_string()),
+                suggestion_id: None,
             };
         }
 
+        // A capability routed to a model answers before the learned heuristics
+        if let Some(capability) = capability_for_prompt(prompt) {
+            if let Some(response) = self.try_routed_response(capability, prompt, context).await {
+                return response;
+            }
+        }
+
         // Use learning engine for intelligent responses
         let learning_engine = self.learning_engine.lock().await;
         
@@ -138,6 +406,7 @@ impl ModelManager {
                     text: suggestions.join(", "),
                     confidence: 0.9,
                     reasoning: Some("Based on learned patterns and context".to_string()),
+                    suggestion_id: None,
                 };
             }
         }
@@ -146,6 +415,35 @@ impl ModelManager {
         self.generate_learned_response(prompt, context, &learning_engine).await
     }
 
+    /// An answer from the backend routed for `capability`; `None` when that is the pattern engine,
+    /// whose free-form answers are no better than the learned heuristics
+    async fn try_routed_response(&self, capability: Capability, prompt: &str, context: Option<&str>) -> Option<AIResponse> {
+        let backend = self.routes.lock().await.backend_for(&capability);
+        if backend == ModelBackend::PatternEngine {
+            return None;
+        }
+        let request = InferenceRequest {
+            prompt: prompt.to_string(),
+            max_tokens: Some(256),
+            temperature: Some(0.3),
+            capability,
+            context: context.map(|s| s.to_string()),
+        };
+        match self.run_accounted(&backend, request, None).await {
+            Ok(response) if !response.text.trim().is_empty() => Some(AIResponse {
+                text: response.text.trim().to_string(),
+                confidence: response.confidence,
+                reasoning: Some(format!("Answered by {}", backend.label())),
+                suggestion_id: None,
+            }),
+            Ok(_) => None,
+            Err(e) => {
+                println!("⚠️ {} failed, falling back to patterns: {}", backend.label(), e);
+                None
+            }
+        }
+    }
+
     // Generate responses using learned patterns and enhanced heuristics
     async fn generate_learned_response(&self, prompt: &str, context: Option<&str>, learning_engine: &LearningEngine) -> AIResponse {
         let prompt_lower = prompt.to_lowercase();
@@ -187,6 +485,7 @@ impl ModelManager {
             confidence,
             reasoning: Some(format!("Generated using {} learned patterns from {} commands", 
                 analytics.patterns_learned, analytics.total_commands)),
+            suggestion_id: None,
         }
     }
 
@@ -224,28 +523,94 @@ impl ModelManager {
     }
 
     async fn try_llm_processing(&self, prompt: &str, context: Option<&str>) -> Option<String> {
+        // Finding files is its own capability, so it can go to a model that is good at it
+        let capability = match capability_for_prompt(prompt) {
+            Some(Capability::FileSearch) => Capability::FileSearch,
+            _ => Capability::NaturalLanguageToCommand,
+        };
+        let backend = self.routes.lock().await.backend_for(&capability);
         let llm_guard = self.llm_engine.lock().await;
-        if let Some(ref llm) = *llm_guard {
-            if llm.is_loaded() {
-                let request = InferenceRequest {
-                    prompt: prompt.to_string(),
-                    max_tokens: Some(128),
-                    temperature: Some(0.3), // Lower temperature for more deterministic command generation
-                    capability: Capability::NaturalLanguageToCommand,
-                    context: context.map(|s| s.to_string()),
-                };
+        let pattern_engine = llm_guard.as_ref().filter(|llm| llm.is_loaded());
+        if backend == ModelBackend::PatternEngine && pattern_engine.is_none() {
+            return None;
+        }
+        let request = InferenceRequest {
+            prompt: prompt.to_string(),
+            max_tokens: Some(128),
+            temperature: Some(0.3), // Lower temperature for more deterministic command generation
+            capability,
+            context: context.map(|s| s.to_string()),
+        };
 
-                if let Ok(response) = llm.generate(request).await {
-                    // Only use LLM result if confidence is high enough
-                    if response.confidence > 0.6 {
-                        println!("🤖 LLM generated command with {:.1}% confidence: {}", 
-                               response.confidence * 100.0, response.text);
-                        return Some(response.text);
-                    }
-                }
+        match self.run_accounted(&backend, request, pattern_engine).await {
+            // Only use LLM result if confidence is high enough
+            Ok(response) if response.confidence > 0.6 => {
+                println!("🤖 {} generated command with {:.1}% confidence: {}",
+                       response.model_used, response.confidence * 100.0, response.text);
+                Some(response.text)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                println!("⚠️ {} failed, falling back to patterns: {}", backend.label(), e);
+                None
             }
         }
-        None
+    }
+
+    /// One-line summary of what a command will do, from the routed model; `None` when only
+    /// the pattern engine is routed, whose answers are no better than the built-in descriptions
+    pub async fn explain_command(&self, command: &str, context: &str) -> Option<String> {
+        let backend = self.routes.lock().await.backend_for(&Capability::CommandExplanation);
+        if backend == ModelBackend::PatternEngine {
+            return None;
+        }
+        let request = InferenceRequest {
+            prompt: command.to_string(),
+            max_tokens: Some(48),
+            temperature: Some(0.1),
+            capability: Capability::CommandExplanation,
+            context: Some(context.to_string()),
+        };
+        match self.run_accounted(&backend, request, None).await {
+            Ok(response) => response.text.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string),
+            Err(e) => {
+                println!("⚠️ {} could not explain the command: {}", backend.label(), e);
+                None
+            }
+        }
+    }
+
+    /// A short summary of command output meant to be listened to, from the routed model; `None`
+    /// when only the pattern engine is routed, which cannot summarize
+    pub async fn summarize_output_for_speech(&self, command: &str, output: &str, exit_code: Option<i32>) -> Option<String> {
+        let backend = self.routes.lock().await.backend_for(&Capability::OutputAnalysis);
+        if backend == ModelBackend::PatternEngine {
+            return None;
+        }
+        let request = InferenceRequest {
+            prompt: crate::terminal::accessible::summary_prompt(command, output, exit_code),
+            max_tokens: Some(120),
+            temperature: Some(0.2),
+            capability: Capability::OutputAnalysis,
+            context: None,
+        };
+        match self.run_accounted(&backend, request, None).await {
+            Ok(response) => Some(response.text.trim().to_string()).filter(|text| !text.is_empty()),
+            Err(e) => {
+                println!("⚠️ {} could not summarize the output: {}", backend.label(), e);
+                None
+            }
+        }
+    }
+
+    /// Run a backend within the daily budget and record its token usage
+    async fn run_accounted(
+        &self,
+        backend: &ModelBackend,
+        request: InferenceRequest,
+        pattern_engine: Option<&LightweightLLM>,
+    ) -> Result<LLMResponse, String> {
+        run_accounted(&self.usage, backend, request, pattern_engine).await
     }
 
     fn natural_language_to_command(&self, prompt: &str, context: Option<&str>) -> String {
@@ -256,38 +621,38 @@ impl ModelManager {
         // Navigation operations (prioritized first)
         if prompt_lower.contains("go to") || prompt_lower.contains("navigate to") || prompt_lower.contains("change to") {
             if prompt_lower.contains("home") {
-                return "cd ~".to_string();
+                "cd ~".to_string()
             } else if prompt_lower.contains("parent") || prompt_lower.contains("up") || prompt_lower.contains("..") {
-                return "cd ..".to_string();
+                "cd ..".to_string()
             } else if prompt_lower.contains("root") {
-                return "cd /".to_string();
+                "cd /".to_string()
             } else if prompt_lower.contains("desktop") {
-                return "cd ~/Desktop".to_string();
+                "cd ~/Desktop".to_string()
             } else if prompt_lower.contains("documents") {
-                return "cd ~/Documents".to_string();
+                "cd ~/Documents".to_string()
             } else if prompt_lower.contains("downloads") {
-                return "cd ~/Downloads".to_string();
+                "cd ~/Downloads".to_string()
             } else if prompt_lower.contains("applications") {
-                return "cd /Applications".to_string();
+                "cd /Applications".to_string()
             } else if let Some(path) = self.extract_path_from_prompt(&prompt_lower) {
-                return format!("cd {}", path);
+                format!("cd {}", crate::terminal::shell_quote::quote_arg(&path))
             } else {
-                return "cd directory_name".to_string();
+                "cd directory_name".to_string()
             }
         } else if (prompt_lower.contains("cd") || prompt_lower.contains("change directory")) && !prompt_lower.contains("git") {
             if prompt_lower.contains("home") {
-                return "cd ~".to_string();
+                "cd ~".to_string()
             } else if prompt_lower.contains("back") || prompt_lower.contains("previous") {
-                return "cd -".to_string();
+                "cd -".to_string()
             } else if prompt_lower.contains("parent") || prompt_lower.contains("up") {
-                return "cd ..".to_string();
+                "cd ..".to_string()
             } else if prompt_lower.contains("root") {
-                return "cd /".to_string();
+                "cd /".to_string()
             } else {
-                return "cd directory_name".to_string();
+                "cd directory_name".to_string()
             }
         } else if prompt_lower.contains("where am i") || prompt_lower.contains("current directory") || prompt_lower.contains("pwd") {
-            return "pwd".to_string();
+            "pwd".to_string()
         }
         
         // File operations
@@ -308,7 +673,7 @@ impl ModelManager {
         } else if prompt_lower.contains("find") {
             if prompt_lower.contains("name") {
                 if let Some(name) = self.extract_filename_from_prompt(&prompt_lower) {
-                    format!("find . -name \"*{}*\" -type f", name)
+                    format!("find . -name {} -type f", crate::terminal::shell_quote::quote_arg(&format!("*{}*", name)))
                 } else {
                     "find . -name \"*.txt\" -type f".to_string()
                 }
@@ -402,7 +767,7 @@ impl ModelManager {
             }
         } else if prompt_lower.contains("ping") || prompt_lower.contains("connectivity") {
             if let Some(host) = self.extract_host_from_prompt(&prompt_lower) {
-                format!("ping -c 4 {}", host)
+                format!("ping -c 4 {}", crate::terminal::shell_quote::quote_arg(&host))
             } else {
                 "ping -c 4 google.com".to_string()
             }
@@ -413,7 +778,7 @@ impl ModelManager {
         // Text processing
         else if prompt_lower.contains("search") || prompt_lower.contains("grep") {
             if let Some(pattern) = self.extract_search_pattern(&prompt_lower) {
-                format!("grep -r \"{}\" .", pattern)
+                format!("grep -r {} .", crate::terminal::shell_quote::quote_arg(&pattern))
             } else {
                 "grep -r \"pattern\" .".to_string()
             }
@@ -643,6 +1008,7 @@ impl ModelManager {
                 text: "AI system not loaded. Please wait for initialization.".to_string(),
                 confidence: 0.0,
                 reasoning: Some("System not ready".to_string()),
+                suggestion_id: None,
             };
         }
 
@@ -650,6 +1016,7 @@ impl ModelManager {
         
         // Try ML-powered processing first
         let command_result = self.natural_language_to_command_ml(prompt, context).await;
+        let command_result = self.corrections.lock().await.personalize(&command_result);
         
         let processing_time = start_time.elapsed().as_millis() as f32;
         let has_ml_marker = command_result.contains("🤖");
@@ -660,6 +1027,35 @@ impl ModelManager {
             reasoning: Some(format!("Processed in {:.1}ms using {} approach", 
                           processing_time,
                           if has_ml_marker { "ML" } else { "pattern-based" })),
+            suggestion_id: None,
+        }
+    }
+
+    /// Natural language to a command, with the model that produced it (for provenance)
+    pub async fn translate_natural_language(&self, natural_language: &str, context: &str) -> (AIResponse, String) {
+        // Use ML-powered command processing for better accuracy
+        let ml_response = self.process_command_with_ml(natural_language, Some(context)).await;
+        
+        // If ML processing has high confidence, use it directly
+        if ml_response.confidence > 0.8 {
+            let model = if ml_response.reasoning.as_deref().is_some_and(|r| r.contains("using ML")) {
+                self.model_for(&Capability::NaturalLanguageToCommand).await
+            } else {
+                "pattern rules".to_string()
+            };
+            return (ml_response, model);
+        }
+        
+        // Otherwise, try the enhanced approach as fallback
+        let prompt = format!("Convert this natural language request to a terminal command: \"{}\"", natural_language);
+        let response = self.generate_response(&prompt, Some(context)).await;
+        
+        // If the response looks like a comment, try a more specific approach
+        if response.text.starts_with('#') || response.text.contains("need more") {
+            let enhanced_prompt = format!("natural language: {}", natural_language);
+            (self.generate_response(&enhanced_prompt, Some(context)).await, "learning engine".to_string())
+        } else {
+            (response, "learning engine".to_string())
         }
     }
 
@@ -672,8 +1068,8 @@ impl ModelManager {
             return vec![];
         }
 
-        let learning_engine = self.learning_engine.lock().await;
-        learning_engine.get_smart_completions(partial_command, context)
+        let completions = self.learning_engine.lock().await.get_smart_completions(partial_command, context);
+        self.corrections.lock().await.rank(completions)
     }
 
     /// Enhanced completions with session context and workflow awareness
@@ -682,11 +1078,30 @@ impl ModelManager {
             return vec![];
         }
 
-        let learning_engine = self.learning_engine.lock().await;
-        learning_engine.get_enhanced_suggestions(context, session_id, 8)
+        let completions = self.learning_engine.lock().await.get_enhanced_suggestions(context, session_id, 8)
             .into_iter()
             .filter(|cmd| cmd.starts_with(partial_command) || cmd.contains(partial_command))
-            .collect()
+            .collect();
+        self.corrections.lock().await.rank(completions)
+    }
+
+    /// Remember that a suggestion was edited before running; the suggestion loses standing
+    pub async fn record_correction(&self, suggested: &str, executed: &str) -> Option<Correction> {
+        let correction = self.corrections.lock().await.record(suggested, executed)?;
+        if self.is_loaded {
+            let mut learning_engine = self.learning_engine.lock().await;
+            learning_engine.update_feedback(&correction.suggested, 0.0);
+        }
+        Some(correction)
+    }
+
+    /// Learned style rules and recent corrections
+    pub async fn get_corrections(&self, recent: usize) -> CorrectionSummary {
+        self.corrections.lock().await.summary(recent)
+    }
+
+    pub async fn clear_corrections(&self) -> Result<(), String> {
+        self.corrections.lock().await.clear()
     }
 
     /// Learn from user interactions
@@ -710,6 +1125,115 @@ impl ModelManager {
         }
     }
 
+    /// Add an executed command to the persistent embedding index
+    pub async fn index_command(
+        &self,
+        execution_id: &str,
+        command: &str,
+        session_id: &str,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) {
+        let mut metadata = HashMap::new();
+        metadata.insert("session_id".to_string(), session_id.to_string());
+        metadata.insert("duration_ms".to_string(), duration_ms.to_string());
+        if let Some(code) = exit_code {
+            metadata.insert("exit_code".to_string(), code.to_string());
+        }
+        if crate::system::power::should_defer(crate::system::power::BackgroundWork::EmbeddingIndexing) {
+            self.deferred_commands.lock().await.push((execution_id.to_string(), command.to_string(), metadata, timestamp));
+            return;
+        }
+        // Encoding runs outside the store's lock, which searches and indexing share
+        let embedder = self.embeddings.lock().await.embedder();
+        let embedded = embedder.embed_batch_blocking(vec![command.to_string()]).await.remove(0);
+        self.embeddings.lock().await.insert_document(execution_id, command, "command", metadata, timestamp, embedded);
+    }
+
+    /// Write learning data now, e.g. before the app restarts
+    pub async fn save_learning_data(&self) {
+        self.learning_engine.lock().await.save_data();
+    }
+
+    /// Catch up on work power saving put off: pending saves, embeddings and the sentence model
+    pub async fn resume_deferred_work(&mut self) {
+        self.flush_deferred_work().await;
+        if self.encoder_deferred {
+            self.encoder_deferred = false;
+            self.start_sentence_encoder();
+        }
+    }
+
+    /// Write what power saving put off and would otherwise be lost, e.g. when the app quits:
+    /// the pending learning data save and the commands waiting to be embedded
+    pub async fn flush_deferred_work(&self) {
+        self.learning_engine.lock().await.flush_deferred_save();
+        let deferred = std::mem::take(&mut *self.deferred_commands.lock().await);
+        if !deferred.is_empty() {
+            let embedder = self.embeddings.lock().await.embedder();
+            let commands = deferred.iter().map(|(_, command, _, _)| command.clone()).collect();
+            let vectors = embedder.embed_batch_blocking(commands).await;
+            let mut embeddings = self.embeddings.lock().await;
+            for ((id, command, metadata, timestamp), embedded) in deferred.into_iter().zip(vectors) {
+                embeddings.insert_document(&id, &command, "command", metadata, timestamp, embedded);
+            }
+        }
+    }
+
+    /// Drop indexed commands older than `before` (all of them with `None`), compacting afterwards
+    pub async fn purge_embeddings(&self, before: Option<chrono::DateTime<chrono::Utc>>) -> Result<usize, String> {
+        let mut embeddings = self.embeddings.lock().await;
+        let removed = embeddings.remove_where(|e| before.map(|cutoff| e.timestamp < cutoff).unwrap_or(true));
+        embeddings.compact()?;
+        Ok(removed)
+    }
+
+    pub async fn embedding_stats(&self) -> EmbeddingIndexStats {
+        self.embeddings.lock().await.stats()
+    }
+
+    pub async fn compact_embeddings(&self) -> Result<EmbeddingIndexStats, String> {
+        self.embeddings.lock().await.compact()
+    }
+
+    pub async fn rebuild_embedding_index(&self) -> Result<EmbeddingIndexStats, String> {
+        self.embeddings.lock().await.rebuild_index()
+    }
+
+    /// Past commands most similar in meaning to a query
+    pub async fn semantic_history_search(&self, query: &str, limit: usize) -> Vec<SemanticSearchResult> {
+        let (vector, model) = self.embed_query(query).await;
+        self.embeddings.lock().await.semantic_search_where(&vector, &model, limit, crate::models::embeddings::is_command)
+    }
+
+    /// A query's vector, computed without holding the embedding store
+    async fn embed_query(&self, query: &str) -> (Vec<f32>, String) {
+        let embedder = self.embeddings.lock().await.embedder();
+        embedder.embed_batch_blocking(vec![query.to_string()]).await.remove(0)
+    }
+
+    /// The embedding store, for `index_project` to run without holding the model manager
+    pub fn embedding_store(&self) -> Arc<Mutex<LocalEmbeddingStore>> {
+        self.embeddings.clone()
+    }
+
+    /// Indexed facts about a project most relevant to a question
+    pub async fn project_knowledge(&self, root: &str, query: &str, limit: usize) -> Vec<SemanticSearchResult> {
+        let (vector, model) = self.embed_query(query).await;
+        self.embeddings.lock().await.semantic_search_where(&vector, &model, limit, |e| is_project_entry(e, root))
+    }
+
+    /// Everything activity analysis uses, so it can run after the ModelManager lock is released
+    pub fn activity_analyzer(&self) -> ActivityAnalyzer {
+        ActivityAnalyzer {
+            routes: self.routes.clone(),
+            usage: self.usage.clone(),
+            embeddings: self.embeddings.clone(),
+            activity: self.activity.clone(),
+        }
+    }
+
     /// Track session workflow for enhanced pattern recognition
     pub async fn track_session_workflow(&self, session_id: &str, command: &str) {
         if self.is_loaded {
@@ -726,24 +1250,139 @@ impl ModelManager {
         }
     }
 
+    /// Forget a command or pattern that keeps resurfacing
+    pub async fn reset_pattern(&self, command: &str) -> Result<learning_engine::PatternReset, String> {
+        if !self.is_loaded {
+            return Err("AI learning system not loaded".to_string());
+        }
+        Ok(self.learning_engine.lock().await.reset_pattern(command))
+    }
+
+    /// Prune faded patterns and stale command statistics now rather than at the next save
+    pub async fn prune_patterns(&self) -> Result<learning_engine::PruneSummary, String> {
+        if !self.is_loaded {
+            return Err("AI learning system not loaded".to_string());
+        }
+        let mut learning_engine = self.learning_engine.lock().await;
+        let summary = learning_engine.prune();
+        learning_engine.save_data();
+        Ok(summary)
+    }
+
     /// Get user analytics
     pub async fn get_analytics(&self) -> Option<UserAnalytics> {
         if self.is_loaded {
-            let learning_engine = self.learning_engine.lock().await;
-            Some(learning_engine.get_user_analytics())
+            let mut analytics = self.learning_engine.lock().await.get_user_analytics();
+            analytics.model_quality = self.comparisons.lock().await.quality();
+            analytics.activities = self.activity.lock().await.as_ref().map(|r| r.clusters.clone()).unwrap_or_default();
+            Some(analytics)
         } else {
             None
         }
     }
 
-    /// Agent mode: Create autonomous task
-    pub async fn create_agent_task(&self, description: &str) -> Result<String, String> {
+    /// Configured backend for every capability that is not on the pattern engine
+    /// The learned pattern and run history behind a suggestion
+    pub async fn suggestion_evidence(&self, suggestion: &str) -> (Option<provenance::MatchedPattern>, Option<provenance::HistoricalFrequency>) {
+        if !self.is_loaded {
+            return (None, None);
+        }
+        self.learning_engine.lock().await.evidence(suggestion)
+    }
+
+    /// Label of the backend a capability is routed to
+    pub async fn model_for(&self, capability: &Capability) -> String {
+        self.routes.lock().await.backend_for(capability).label()
+    }
+
+    pub async fn get_model_routes(&self) -> HashMap<Capability, ModelBackend> {
+        self.routes.lock().await.routes().clone()
+    }
+
+    pub async fn set_model_route(&self, capability: Capability, backend: Option<ModelBackend>) -> Result<(), String> {
+        self.routes.lock().await.set_route(capability, backend)
+    }
+
+    /// Run the same request on two backends side by side and keep the results for later acceptance
+    pub async fn compare_backends(
+        &self,
+        capability: Capability,
+        prompt: &str,
+        context: Option<&str>,
+        backends: [ModelBackend; 2],
+    ) -> Result<Comparison, String> {
+        let llm_guard = self.llm_engine.lock().await;
+        let pattern_engine = llm_guard.as_ref().filter(|llm| llm.is_loaded());
+        let request = InferenceRequest {
+            prompt: prompt.to_string(),
+            max_tokens: Some(256),
+            temperature: Some(0.3),
+            capability: capability.clone(),
+            context: context.map(|s| s.to_string()),
+        };
+
+        let run = |backend: &ModelBackend| {
+            let request = request.clone();
+            let backend = backend.clone();
+            async move {
+                let start = std::time::Instant::now();
+                let outcome = self.run_accounted(&backend, request, pattern_engine).await;
+                let latency_ms = start.elapsed().as_millis() as u64;
+                match outcome {
+                    Ok(response) => ComparisonResult { backend: backend.label(), text: Some(response.text), error: None, latency_ms },
+                    Err(e) => ComparisonResult { backend: backend.label(), text: None, error: Some(e), latency_ms },
+                }
+            }
+        };
+        let (first, second) = tokio::join!(run(&backends[0]), run(&backends[1]));
+        drop(llm_guard);
+
+        let comparison = Comparison {
+            id: uuid::Uuid::new_v4().to_string(),
+            capability,
+            prompt: prompt.to_string(),
+            results: vec![first, second],
+            accepted: None,
+            timestamp: chrono::Utc::now(),
+        };
+        self.comparisons.lock().await.record(comparison.clone())?;
+        Ok(comparison)
+    }
+
+    /// Token, invocation and cost totals for today and the last `period_days` days
+    pub async fn get_usage_stats(&self, period_days: u32) -> UsageStats {
+        self.usage.lock().await.stats(period_days)
+    }
+
+    pub async fn set_usage_budget(&self, budget: UsageBudget) -> Result<(), String> {
+        self.usage.lock().await.set_budget(budget)
+    }
+
+    /// Record which comparison result the user went with
+    pub async fn accept_comparison(&self, comparison_id: &str, index: usize) -> Result<Comparison, String> {
+        self.comparisons.lock().await.accept(comparison_id, index)
+    }
+
+    /// Agent mode: Create autonomous task, planned with what the index knows about `project_root`
+    pub async fn create_agent_task(&self, description: &str, scratch_dir: Option<String>, project_root: Option<&str>) -> Result<String, String> {
         if !self.is_loaded {
             return Err("AI system not loaded".to_string());
         }
+        let facts: Vec<String> = match project_root {
+            Some(root) => self.project_knowledge(root, description, 8).await.into_iter().map(|k| k.text).collect(),
+            None => Vec::new(),
+        };
 
         let mut agent = self.agent.lock().await;
-        agent.create_task_from_description(description).await
+        agent.create_task_from_description(description, scratch_dir, &facts).await
+    }
+
+    /// The agent, to plan a task without holding the model manager for as long as planning takes
+    pub fn agent(&self) -> Result<Arc<Mutex<IntelligentAgent>>, String> {
+        if !self.is_loaded {
+            return Err("AI system not loaded".to_string());
+        }
+        Ok(self.agent.clone())
     }
 
     /// Get agent task status
@@ -752,6 +1391,11 @@ impl ModelManager {
         agent.get_task_status(task_id)
     }
 
+    /// An agent task with its planned steps
+    pub async fn get_agent_task(&self, task_id: &str) -> Option<agent::AgentTask> {
+        self.agent.lock().await.get_task(task_id).cloned()
+    }
+
     /// Get all active agent tasks
     pub async fn get_active_agent_tasks(&self) -> Vec<String> {
         let agent = self.agent.lock().await;
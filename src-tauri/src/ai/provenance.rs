@@ -0,0 +1,159 @@
+// Suggestion provenance ("why am I seeing this?")
+// Every completion, context suggestion, proactive suggestion and translation is
+// recorded with what produced it: the learned pattern it matched, how often the
+// command was run before, what in the session or directory triggered it and
+// which model answered. `explain_suggestion` looks the record up by id, or by
+// the suggestion text for the kinds that are returned as plain strings, and says
+// which setting to change when the suggestion is unwanted.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Records kept; older suggestions can no longer be explained
+const MAX_RECORDS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    Completion,
+    Context,
+    Proactive,
+    Translation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchedPattern {
+    pub key: String,
+    pub usage_count: u32,
+    pub confidence: f32,
+    pub success_rate: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoricalFrequency {
+    /// Runs across all directories, as seen by the learning engine
+    pub runs: u32,
+    pub success_rate: Option<f32>,
+    pub last_used: Option<DateTime<Utc>>,
+    /// Runs in the directory the suggestion was made for
+    pub runs_here: u32,
+    pub failures_here: u32,
+}
+
+impl HistoricalFrequency {
+    /// Overall history joined with the runs in one directory; `None` when the command never ran
+    pub fn with_directory(overall: Option<Self>, here: Option<&crate::terminal::dir_usage::CommandStats>) -> Option<Self> {
+        if overall.is_none() && here.is_none() {
+            return None;
+        }
+        let mut frequency = overall.unwrap_or_default();
+        if let Some(here) = here {
+            frequency.runs_here = here.runs;
+            frequency.failures_here = here.failures;
+            frequency.last_used = frequency.last_used.max(Some(here.last_run));
+        }
+        Some(frequency)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    pub id: String,
+    pub kind: SuggestionKind,
+    pub suggestion: String,
+    pub session_id: Option<String>,
+    pub working_directory: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// What produced it, e.g. "learned patterns" or "help page flags"
+    pub source: String,
+    pub matched_pattern: Option<MatchedPattern>,
+    pub frequency: Option<HistoricalFrequency>,
+    /// What in the session, directory or system brought it up
+    pub trigger: Option<String>,
+    /// Model backend that answered, for suggestions that went through one
+    pub model: Option<String>,
+    pub confidence: Option<f32>,
+    pub reasoning: Option<String>,
+    /// What the user can do to see less of it
+    pub how_to_change: Vec<String>,
+}
+
+impl Provenance {
+    pub fn new(kind: SuggestionKind, suggestion: &str, source: &str) -> Self {
+        Self {
+            id: String::new(),
+            kind,
+            suggestion: suggestion.to_string(),
+            session_id: None,
+            working_directory: None,
+            created_at: Utc::now(),
+            source: source.to_string(),
+            matched_pattern: None,
+            frequency: None,
+            trigger: None,
+            model: None,
+            confidence: None,
+            reasoning: None,
+            how_to_change: default_hints(kind),
+        }
+    }
+
+    pub fn in_session(mut self, session_id: &str, working_directory: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self.working_directory = Some(working_directory.to_string());
+        self
+    }
+}
+
+fn default_hints(kind: SuggestionKind) -> Vec<String> {
+    match kind {
+        SuggestionKind::Completion => vec![
+            "Editing a completion before running it teaches the style you prefer".to_string(),
+            "Rate it with update_ai_feedback to lower its standing".to_string(),
+        ],
+        SuggestionKind::Context => vec![
+            "Context suggestions that are rarely used are shown less often; set the \"context\" category to off to hide them".to_string(),
+        ],
+        SuggestionKind::Proactive => vec![
+            "Dismiss or snooze it for this repository".to_string(),
+            "Set its category to off in the suggestion throttle settings".to_string(),
+        ],
+        SuggestionKind::Translation => vec![
+            "Editing the command before running it records a correction for next time".to_string(),
+            "Route natural language translation to another model in the model settings".to_string(),
+        ],
+    }
+}
+
+/// Recent suggestions and how they came about, newest last
+#[derive(Debug, Default)]
+pub struct ProvenanceLog {
+    records: VecDeque<Provenance>,
+}
+
+impl ProvenanceLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep a record and return its id
+    pub fn record(&mut self, mut provenance: Provenance) -> String {
+        provenance.id = uuid::Uuid::new_v4().to_string();
+        let id = provenance.id.clone();
+        self.records.push_back(provenance);
+        while self.records.len() > MAX_RECORDS {
+            self.records.pop_front();
+        }
+        id
+    }
+
+    /// A record by id, or the latest one for a suggestion text
+    pub fn get(&self, id_or_suggestion: &str) -> Option<&Provenance> {
+        self.records
+            .iter()
+            .rev()
+            .find(|p| p.id == id_or_suggestion)
+            .or_else(|| self.records.iter().rev().find(|p| p.suggestion == id_or_suggestion.trim()))
+    }
+}
@@ -0,0 +1,252 @@
+// Adaptive suggestion throttling
+// Counts how often each category of suggestion (context suggestions and each
+// kind of proactive suggestion) is shown and how often it is taken up: a shown
+// command that the user runs soon after counts as accepted, a dismissal as
+// rejected. Categories that are rarely accepted are shown less often, and ones
+// that are almost never accepted are suppressed, with an occasional retry in case
+// the user's habits changed. Each category can also be pinned on or off.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The category of `get_context_suggestions`; proactive suggestions use their type
+pub const CONTEXT_CATEGORY: &str = "context";
+
+/// Showings before the acceptance rate is trusted
+const MIN_SAMPLES: u32 = 20;
+/// Below this rate a category is shown less often
+const REDUCE_BELOW: f64 = 0.15;
+/// Below this rate a category is suppressed
+const SUPPRESS_BELOW: f64 = 0.03;
+/// A reduced category is shown at most this often
+const REDUCED_INTERVAL_MINUTES: i64 = 60;
+/// A suppressed category is tried again after this long
+const RETRY_AFTER_HOURS: i64 = 24;
+/// Suggestions left when context suggestions are reduced
+const REDUCED_CONTEXT_ITEMS: usize = 3;
+/// A shown command run within this long counts as accepted
+const ACCEPT_WINDOW_MINUTES: i64 = 15;
+/// Counts are halved past this many showings, so recent behaviour weighs more
+const DECAY_AT: u32 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleMode {
+    /// Follow the acceptance rate
+    Adaptive,
+    Always,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleDecision {
+    Show,
+    Reduce,
+    Suppress,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryStats {
+    pub shown: u32,
+    pub accepted: u32,
+    pub dismissed: u32,
+    pub last_shown: Option<DateTime<Utc>>,
+}
+
+impl CategoryStats {
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        (self.shown >= MIN_SAMPLES).then(|| self.accepted as f64 / self.shown as f64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryStatus {
+    pub category: String,
+    pub mode: ThrottleMode,
+    pub decision: ThrottleDecision,
+    pub stats: CategoryStats,
+    pub acceptance_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThrottleSettings {
+    /// Categories not listed are adaptive
+    pub modes: HashMap<String, ThrottleMode>,
+}
+
+impl ThrottleSettings {
+    fn data_file() -> PathBuf {
+        super::config_directory().join("suggestion_throttle.json")
+    }
+
+    fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    fn mode(&self, category: &str) -> ThrottleMode {
+        self.modes.get(category).copied().unwrap_or(ThrottleMode::Adaptive)
+    }
+}
+
+pub struct SuggestionThrottle {
+    settings: ThrottleSettings,
+    stats: HashMap<String, CategoryStats>,
+    /// Recently shown commands and their category, to spot them being run
+    shown: HashMap<String, (String, DateTime<Utc>)>,
+}
+
+impl SuggestionThrottle {
+    fn data_file() -> PathBuf {
+        super::data_directory().join("suggestion_acceptance.json")
+    }
+
+    pub fn load() -> Self {
+        let stats = std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self { settings: ThrottleSettings::load(), stats, shown: HashMap::new() }
+    }
+
+    fn save(&self) {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&self.stats) {
+            let _ = std::fs::write(file, json);
+        }
+    }
+
+    pub fn settings(&self) -> &ThrottleSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: ThrottleSettings) -> Result<(), String> {
+        settings.save()?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Whether to show a category now
+    pub fn decide(&self, category: &str) -> ThrottleDecision {
+        match self.settings.mode(category) {
+            ThrottleMode::Always => return ThrottleDecision::Show,
+            ThrottleMode::Off => return ThrottleDecision::Suppress,
+            ThrottleMode::Adaptive => {}
+        }
+        let Some(stats) = self.stats.get(category) else { return ThrottleDecision::Show };
+        let since_shown = stats.last_shown.map(|last| Utc::now() - last);
+        match stats.acceptance_rate() {
+            Some(rate) if rate < SUPPRESS_BELOW => match since_shown {
+                Some(elapsed) if elapsed < Duration::hours(RETRY_AFTER_HOURS) => ThrottleDecision::Suppress,
+                _ => ThrottleDecision::Reduce,
+            },
+            Some(rate) if rate < REDUCE_BELOW => match since_shown {
+                Some(elapsed) if elapsed < Duration::minutes(REDUCED_INTERVAL_MINUTES) => ThrottleDecision::Suppress,
+                _ => ThrottleDecision::Reduce,
+            },
+            _ => ThrottleDecision::Show,
+        }
+    }
+
+    /// Trim context suggestions to what the throttle allows, and note them as shown
+    pub fn filter_context(&mut self, mut suggestions: Vec<String>) -> Vec<String> {
+        match self.decide(CONTEXT_CATEGORY) {
+            ThrottleDecision::Show => {}
+            ThrottleDecision::Reduce => suggestions.truncate(REDUCED_CONTEXT_ITEMS),
+            ThrottleDecision::Suppress => suggestions.clear(),
+        }
+        self.record_shown(CONTEXT_CATEGORY, &suggestions);
+        suggestions
+    }
+
+    /// Note suggestions as shown; the same command shown again soon after counts once
+    pub fn record_shown(&mut self, category: &str, commands: &[String]) {
+        let now = Utc::now();
+        let window = Duration::minutes(ACCEPT_WINDOW_MINUTES);
+        self.shown.retain(|_, (_, at)| now - *at < window);
+        let fresh = commands.iter().filter(|command| !self.shown.contains_key(command.trim())).count();
+        for command in commands {
+            self.shown.insert(command.trim().to_string(), (category.to_string(), now));
+        }
+        if fresh == 0 {
+            return;
+        }
+        let stats = self.stats.entry(category.to_string()).or_default();
+        // One showing per batch: a list of five commands is one chance to be accepted
+        stats.shown += 1;
+        stats.last_shown = Some(now);
+        if stats.shown > DECAY_AT {
+            stats.shown /= 2;
+            stats.accepted /= 2;
+            stats.dismissed /= 2;
+        }
+        self.save();
+    }
+
+    /// A command was run; if it was just suggested, its category is credited
+    pub fn record_command(&mut self, command: &str) {
+        let Some((category, at)) = self.shown.remove(command.trim()) else { return };
+        if Utc::now() - at >= Duration::minutes(ACCEPT_WINDOW_MINUTES) {
+            return;
+        }
+        // The rest of the batch is not credited again
+        self.shown.retain(|_, (other, shown_at)| !(other == &category && *shown_at == at));
+        let stats = self.stats.entry(category).or_default();
+        stats.accepted = (stats.accepted + 1).min(stats.shown.max(1));
+        self.save();
+    }
+
+    pub fn record_dismissed(&mut self, category: &str) {
+        self.stats.entry(category.to_string()).or_default().dismissed += 1;
+        self.save();
+    }
+
+    /// Every category seen or configured, with its numbers and current decision
+    pub fn status(&self) -> Vec<CategoryStatus> {
+        let mut categories: Vec<&String> = self.stats.keys().chain(self.settings.modes.keys()).collect();
+        categories.sort();
+        categories.dedup();
+        categories
+            .into_iter()
+            .map(|category| {
+                let stats = self.stats.get(category).cloned().unwrap_or_default();
+                CategoryStatus {
+                    category: category.clone(),
+                    mode: self.settings.mode(category),
+                    decision: self.decide(category),
+                    acceptance_rate: stats.acceptance_rate(),
+                    stats,
+                }
+            })
+            .collect()
+    }
+
+    /// Start a category's numbers over, e.g. after the user changed how they work
+    pub fn reset(&mut self, category: Option<&str>) {
+        match category {
+            Some(category) => {
+                self.stats.remove(category);
+            }
+            None => self.stats.clear(),
+        }
+        self.save();
+    }
+}
@@ -0,0 +1,67 @@
+// Per-directory trust model
+// Decides whether AI features may read file contents from a directory.
+// The closest configured ancestor wins, and unknown directories are untrusted.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    /// canonical directory path -> trusted
+    rules: HashMap<String, bool>,
+}
+
+impl TrustStore {
+    fn trust_file() -> PathBuf {
+        super::config_directory().join("directory_trust.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::trust_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::trust_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    fn canonical(path: &Path) -> PathBuf {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// Trust or distrust a directory and everything below it
+    pub fn set_trust(&mut self, directory: &str, trusted: bool) -> Result<(), String> {
+        let path = Path::new(directory);
+        if !path.is_dir() {
+            return Err(format!("Directory not found: {}", directory));
+        }
+        self.rules.insert(Self::canonical(path).to_string_lossy().to_string(), trusted);
+        self.save()
+    }
+
+    /// Remove an explicit rule so the directory inherits from its parents again
+    pub fn clear_trust(&mut self, directory: &str) -> Result<(), String> {
+        let key = Self::canonical(Path::new(directory)).to_string_lossy().to_string();
+        self.rules.remove(&key);
+        self.save()
+    }
+
+    /// The rule that applies to a path: the closest ancestor with an explicit setting
+    pub fn trust_for(&self, path: &Path) -> Option<bool> {
+        let canonical = Self::canonical(path);
+        canonical.ancestors()
+            .find_map(|ancestor| self.rules.get(ancestor.to_string_lossy().as_ref()).copied())
+    }
+
+    pub fn is_trusted(&self, path: &Path) -> bool {
+        self.trust_for(path).unwrap_or(false)
+    }
+}
@@ -0,0 +1,254 @@
+// Voice command intake
+// The frontend records a spoken request and sends the audio; it is decoded with
+// the built-in demuxers (or ffmpeg, for formats such as WebM/Opus that browsers
+// record), turned into 16 kHz mono WAV and transcribed by whisper.cpp's
+// `whisper-cli`, run the same way local GGUF models run through `llama-cli`.
+// The transcript then goes through natural language translation. Nothing is run
+// from here: the proposed command always comes back for the user to confirm.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Sample rate whisper models expect
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+/// Audio past this is dropped; spoken commands are a sentence or two
+const MAX_SECONDS: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceSettings {
+    /// whisper.cpp command line binary
+    pub binary: String,
+    /// ggml whisper model, e.g. `ggml-base.en.bin`
+    pub model_path: String,
+    /// Spoken language code, or `auto` to detect it
+    pub language: String,
+    pub threads: Option<u32>,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            binary: "whisper-cli".to_string(),
+            model_path: super::models_directory().join("ggml-base.en.bin").to_string_lossy().to_string(),
+            language: "en".to_string(),
+            threads: None,
+        }
+    }
+}
+
+impl VoiceSettings {
+    fn settings_file() -> PathBuf {
+        super::config_directory().join("voice.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::settings_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::settings_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+/// What was heard and what it would run; `confirmation_required` is always set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceIntake {
+    pub transcript: String,
+    pub proposed_command: String,
+    pub confidence: f32,
+    pub reasoning: Option<String>,
+    /// Provenance record of the translation, for `explain_suggestion`
+    pub suggestion_id: Option<String>,
+    pub confirmation_required: bool,
+}
+
+/// Audio samples in [-1, 1], averaged to mono
+struct MonoAudio {
+    samples: Vec<f32>,
+    sample_rate: u32,
+}
+
+fn decode(path: &Path) -> Result<MonoAudio, String> {
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::errors::Error;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension() {
+        hint.with_extension(&ext.to_string_lossy());
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported audio: {}", e))?
+        .format;
+    let track = format.default_track().ok_or("The recording has no audio track")?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.ok_or("The recording has no sample rate")?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported audio codec: {}", e))?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(format!("Cannot read the recording: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet costs a few milliseconds of audio, not the recording
+            Err(Error::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Cannot decode the recording: {}", e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(buffer.samples().chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32));
+        if samples.len() >= MAX_SECONDS * sample_rate as usize {
+            samples.truncate(MAX_SECONDS * sample_rate as usize);
+            break;
+        }
+    }
+    Ok(MonoAudio { samples, sample_rate })
+}
+
+/// Linear interpolation is plenty for speech going to a 16 kHz model
+fn resample(audio: MonoAudio, rate: u32) -> Vec<f32> {
+    if audio.sample_rate == rate || audio.samples.is_empty() {
+        return audio.samples;
+    }
+    let step = audio.sample_rate as f64 / rate as f64;
+    let len = (audio.samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let index = pos as usize;
+            let next = audio.samples.get(index + 1).copied().unwrap_or(audio.samples[index]);
+            let frac = (pos - index as f64) as f32;
+            audio.samples[index] * (1.0 - frac) + next * frac
+        })
+        .collect()
+}
+
+/// 16-bit PCM mono WAV, the input `whisper-cli` reads
+fn write_wav(path: &Path, samples: &[f32], rate: u32) -> Result<(), String> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&rate.to_le_bytes());
+    wav.extend_from_slice(&(rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    std::fs::write(path, wav).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Convert with ffmpeg, for what the built-in decoders do not read
+async fn ffmpeg_to_wav(input: &Path, output: &Path) -> Result<(), String> {
+    let result = tokio::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(input)
+        .args(["-t", &MAX_SECONDS.to_string(), "-ac", "1", "-ar", &WHISPER_SAMPLE_RATE.to_string(), "-c:a", "pcm_s16le"])
+        .arg(output)
+        .output()
+        .await
+        .map_err(|_| "This audio format needs ffmpeg, which is not installed; record WAV instead".to_string())?;
+    if !result.status.success() {
+        return Err(format!("ffmpeg could not read the recording: {}", String::from_utf8_lossy(&result.stderr).trim()));
+    }
+    Ok(())
+}
+
+async fn prepare_wav(input: &Path, output: &Path) -> Result<(), String> {
+    let (input_path, output_path) = (input.to_path_buf(), output.to_path_buf());
+    let decoded = tokio::task::spawn_blocking(move || {
+        decode(&input_path).and_then(|audio| write_wav(&output_path, &resample(audio, WHISPER_SAMPLE_RATE), WHISPER_SAMPLE_RATE))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    match decoded {
+        Ok(()) => Ok(()),
+        // Browsers record WebM/Opus or MP4/AAC, which only ffmpeg reads
+        Err(_) => ffmpeg_to_wav(input, output).await,
+    }
+}
+
+async fn run_whisper(settings: &VoiceSettings, wav: &Path) -> Result<String, String> {
+    if !Path::new(&settings.model_path).is_file() {
+        return Err(format!("Whisper model not found: {}; download a ggml model and set it in the voice settings", settings.model_path));
+    }
+    let mut command = tokio::process::Command::new(&settings.binary);
+    command.args(["-m", &settings.model_path, "-nt", "-np", "-l", &settings.language, "-f"]).arg(wav);
+    if let Some(threads) = settings.threads {
+        command.arg("-t").arg(threads.to_string());
+    }
+    let output = command.output().await.map_err(|e| format!("Cannot run {}: {}", settings.binary, e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", settings.binary, String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or("")));
+    }
+    Ok(clean_transcript(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// One line of text, without the markers whisper writes for silence and noise
+fn clean_transcript(raw: &str) -> String {
+    raw.split_whitespace()
+        .filter(|word| !((word.starts_with('[') && word.ends_with(']')) || (word.starts_with('(') && word.ends_with(')'))))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Transcribe a recording sent by the frontend; `format` is its file extension, e.g. `wav` or `webm`
+pub async fn transcribe(audio: &[u8], format: &str) -> Result<String, String> {
+    if audio.is_empty() {
+        return Err("The recording is empty".to_string());
+    }
+    let settings = VoiceSettings::load();
+    let stem = std::env::temp_dir().join(format!("ph7console-voice-{}", uuid::Uuid::new_v4().simple()));
+    let extension: String = format.trim_start_matches('.').chars().filter(char::is_ascii_alphanumeric).collect();
+    let input = stem.with_extension(if extension.is_empty() { "bin".to_string() } else { extension });
+    let wav = stem.with_extension("16k.wav");
+
+    let result = async {
+        tokio::fs::write(&input, audio).await.map_err(|e| format!("Cannot store the recording: {}", e))?;
+        prepare_wav(&input, &wav).await?;
+        run_whisper(&settings, &wav).await
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&input).await;
+    let _ = tokio::fs::remove_file(&wav).await;
+
+    let transcript = result?;
+    if transcript.is_empty() {
+        return Err("No speech was recognised in the recording".to_string());
+    }
+    Ok(transcript)
+}
@@ -0,0 +1,329 @@
+// ph7: the terminal's AI from a plain shell or a script
+// Translates requests into commands, plans (and runs) agent tasks and searches
+// the command history with the same models, learned patterns and data
+// directory as the desktop app. Commands run through the same TerminalManager,
+// so project rules apply and what runs here is learned by the app too.
+// Answers go to stdout and everything the core logs to stderr, so
+// `eval "$(ph7 do ...)"` and pipes only ever see the answer.
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+
+use ph7_console_lib::ai::{self, translation_guard, ModelManager};
+use ph7_console_lib::terminal::launch_profiles::SessionOptions;
+use ph7_console_lib::terminal::{CommandExecution, TerminalManager};
+
+#[derive(Parser)]
+#[command(name = "ph7", version, about = "pH7Console's local AI from the command line")]
+struct Cli {
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Turn a request into a shell command, e.g. `ph7 do "compress this folder"`
+    Do {
+        #[arg(required = true)]
+        request: Vec<String>,
+        /// Run the command in the current directory
+        #[arg(long)]
+        run: bool,
+        /// Run without asking first
+        #[arg(short, long, requires = "run")]
+        yes: bool,
+    },
+    /// Plan the steps of a task, e.g. `ph7 agent "build and test the project"`
+    Agent {
+        #[arg(required = true)]
+        task: Vec<String>,
+        /// Run the steps in order, stopping at the first that fails
+        #[arg(long)]
+        run: bool,
+        /// Run without asking first
+        #[arg(short, long, requires = "run")]
+        yes: bool,
+    },
+    /// Search past commands by meaning
+    History {
+        #[arg(required = true)]
+        query: Vec<String>,
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// What recent history was spent on
+    Activity,
+}
+
+/// Where answers are written: the process's real stdout
+struct Answer {
+    out: Box<dyn Write>,
+    json: bool,
+}
+
+impl Answer {
+    /// Keep stdout for answers and send the core's `println!` output to stderr
+    #[cfg(unix)]
+    fn claim_stdout(json: bool) -> Self {
+        use std::os::unix::io::FromRawFd;
+
+        // SAFETY: descriptors 1 and 2 are open, and the duplicate is owned by the File alone
+        let saved = unsafe { libc::dup(1) };
+        if saved >= 0 && unsafe { libc::dup2(2, 1) } >= 0 {
+            return Self { out: Box::new(unsafe { std::fs::File::from_raw_fd(saved) }), json };
+        }
+        Self { out: Box::new(std::io::stdout()), json }
+    }
+
+    #[cfg(not(unix))]
+    fn claim_stdout(json: bool) -> Self {
+        Self { out: Box::new(std::io::stdout()), json }
+    }
+
+    fn line(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{}", text);
+    }
+
+    fn value(&mut self, value: &impl Serialize) {
+        let json = serde_json::to_string_pretty(value).unwrap_or_default();
+        self.line(&json);
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut answer = Answer::claim_stdout(cli.json);
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match runtime.block_on(run(cli.command, &mut answer)) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(command: Command, answer: &mut Answer) -> Result<ExitCode, String> {
+    // Saved data from older versions is brought up to date before anything reads it, as in the app
+    ai::migrations::run();
+    let mut models = ModelManager::new_headless();
+
+    match command {
+        Command::Do { request, run, yes } => {
+            let request = request.join(" ");
+            models.load_model().await.map_err(|e| e.to_string())?;
+            let mut terminal = Terminal::open()?;
+            let context = terminal.context().await;
+            let (response, model) = models.translate_natural_language(&request, &context).await;
+            let command = response.text.replace("🤖 ", "").trim().to_string();
+            if command.is_empty() || command.starts_with('#') {
+                return Err(format!("Could not turn \"{}\" into a command", request));
+            }
+
+            if answer.json {
+                answer.value(&serde_json::json!({
+                    "command": command,
+                    "confidence": response.confidence,
+                    "model": model,
+                    "reasoning": response.reasoning,
+                }));
+            } else if run {
+                eprintln!("💡 {} ({:.0}% confident, {})", command, response.confidence * 100.0, model);
+            } else {
+                eprintln!("💡 {:.0}% confident, {}", response.confidence * 100.0, model);
+                answer.line(&command);
+            }
+            if !run {
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            // Translations run under the same guard as in the app
+            terminal.guard(&command, &translation_guard::GuardSettings::load())?;
+            if !yes && !confirm(&format!("Run `{}`?", command))? {
+                return Ok(ExitCode::FAILURE);
+            }
+            let execution = terminal.execute(&models, &command, &request, &context).await?;
+            report_execution(answer, &execution);
+            models.save_learning_data().await;
+            Ok(exit_code(&execution))
+        }
+        Command::Agent { task, run, yes } => {
+            let task = task.join(" ");
+            models.load_model().await.map_err(|e| e.to_string())?;
+            let root = std::env::current_dir().map(|dir| ph7_console_lib::project::symbols::project_root(&dir).to_string_lossy().to_string()).ok();
+            let task_id = models.create_agent_task(&task, None, root.as_deref()).await?;
+            let plan = models.get_agent_task(&task_id).await.ok_or("The task disappeared after planning")?;
+
+            if answer.json {
+                answer.value(&plan);
+            } else {
+                for (number, step) in plan.steps.iter().enumerate() {
+                    answer.line(&format!("{}. {}\n   {}", number + 1, step.description, step.command));
+                }
+            }
+            if !run {
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            // Planned steps are model output too, so they run under the same guard as a
+            // translation: the whole plan is refused before anything runs, and each step is
+            // checked again where the steps before it left the session
+            let guard_settings = translation_guard::GuardSettings::load();
+            let mut terminal = Terminal::open()?;
+            for step in &plan.steps {
+                terminal.guard(&step.command, &guard_settings)?;
+            }
+            if !yes && !confirm(&format!("Run these {} steps?", plan.steps.len()))? {
+                return Ok(ExitCode::FAILURE);
+            }
+
+            for (number, step) in plan.steps.iter().enumerate() {
+                eprintln!("▶️ {}/{} {}", number + 1, plan.steps.len(), step.description);
+                if let Err(refusal) = terminal.guard(&step.command, &guard_settings) {
+                    models.save_learning_data().await;
+                    return Err(format!("{}\nStopped at step {}", refusal, number + 1));
+                }
+                let context = terminal.context().await;
+                let execution = terminal.execute(&models, &step.command, &step.command, &context).await?;
+                report_execution(answer, &execution);
+                if !execution.outcome.is_success() {
+                    eprintln!("❌ Stopped at step {}: {}", number + 1, step.command);
+                    models.save_learning_data().await;
+                    return Ok(exit_code(&execution));
+                }
+            }
+            models.save_learning_data().await;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::History { query, limit } => {
+            let query = query.join(" ");
+            models.load_sentence_encoder().await;
+            let results = models.semantic_history_search(&query, limit).await;
+            if answer.json {
+                answer.value(&results);
+            } else {
+                for result in &results {
+                    answer.line(&result.text);
+                }
+            }
+            Ok(if results.is_empty() { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+        }
+        Command::Activity => {
+            models.load_sentence_encoder().await;
+            let report = models.activity_analyzer().run().await;
+            if answer.json {
+                answer.value(&report);
+            } else {
+                for cluster in &report.clusters {
+                    answer.line(&format!(
+                        "{} — {} commands, {:.0}% of history, {:.0}% succeeded",
+                        cluster.label,
+                        cluster.commands,
+                        cluster.share * 100.0,
+                        cluster.success_rate * 100.0
+                    ));
+                    for command in &cluster.representative_commands {
+                        answer.line(&format!("    {}", command));
+                    }
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// A terminal session in the directory `ph7` was started from
+struct Terminal {
+    manager: TerminalManager,
+    session_id: String,
+    working_directory: String,
+}
+
+impl Terminal {
+    fn open() -> Result<Self, String> {
+        let mut manager = TerminalManager::new_headless();
+        let options = SessionOptions { title: Some("ph7".to_string()), ..SessionOptions::default() };
+        let (session_id, _) = manager.create_session(options)?;
+        let working_directory = manager.get_session(&session_id).map(|s| s.working_directory.clone()).unwrap_or_default();
+        Ok(Self { manager, session_id, working_directory })
+    }
+
+    /// Refuse a command the translation guard would block in the app, checked against
+    /// the session's current directory
+    fn guard(&self, command: &str, settings: &translation_guard::GuardSettings) -> Result<(), String> {
+        let working_directory = self
+            .manager
+            .get_session(&self.session_id)
+            .map(|s| s.working_directory.clone())
+            .unwrap_or_else(|| self.working_directory.clone());
+        let report = translation_guard::check(command, &working_directory, settings);
+        if report.allowed {
+            return Ok(());
+        }
+        Err(format!(
+            "🛡️ Not run: {}\n{}",
+            command,
+            report.violations.iter().map(|v| format!("  • {}", v)).collect::<Vec<_>>().join("\n")
+        ))
+    }
+
+    async fn context(&self) -> String {
+        self.manager.get_smart_context(&self.session_id).await
+    }
+
+    /// Run a command and let the models learn from it as the app would; `learned_as` is what
+    /// goes into history (the request for a translation)
+    async fn execute(&mut self, models: &ModelManager, command: &str, learned_as: &str, context: &str) -> Result<CommandExecution, String> {
+        let execution = self
+            .manager
+            .execute_command_with_history(&self.session_id, command, learned_as)
+            .await
+            .map_err(|e| e.to_string())?;
+        models
+            .learn_from_command(learned_as, &execution.output, context, execution.outcome.is_success(), Some(execution.duration_ms))
+            .await;
+        models
+            .index_command(&execution.id, learned_as, &self.session_id, execution.exit_code, execution.duration_ms, execution.timestamp)
+            .await;
+        Ok(execution)
+    }
+}
+
+fn report_execution(answer: &mut Answer, execution: &CommandExecution) {
+    if answer.json {
+        answer.value(execution);
+    } else if !execution.output.is_empty() {
+        answer.line(execution.output.trim_end());
+    }
+}
+
+fn exit_code(execution: &CommandExecution) -> ExitCode {
+    match execution.exit_code {
+        Some(0) => ExitCode::SUCCESS,
+        Some(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+        None => ExitCode::FAILURE,
+    }
+}
+
+/// Ask on the terminal; scripts have to pass --yes instead
+fn confirm(question: &str) -> Result<bool, String> {
+    if !std::io::stdin().is_terminal() {
+        return Err("Not asking without a terminal; pass --yes to run anyway".to_string());
+    }
+    eprint!("{} [y/N] ", question);
+    let _ = std::io::stderr().flush();
+    let mut reply = String::new();
+    std::io::stdin().lock().read_line(&mut reply).map_err(|e| e.to_string())?;
+    Ok(matches!(reply.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -1,31 +1,274 @@
 use crate::{AppState, ai};
 use crate::ai::{AIResponse};
+use crate::ai::provenance::{HistoricalFrequency, Provenance, SuggestionKind};
 use crate::terminal::CommandExecution;
+use crate::system::latency;
 use tauri::State;
 use std::path::PathBuf;
 
+/// Start a session, optionally in a given directory ("open terminal here"), with another shell,
+/// environment overrides or a launch profile. Falling back from a missing directory emits a recovery event
 #[tauri::command]
 pub async fn create_terminal(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-    title: Option<String>
+    title: Option<String>,
+    working_directory: Option<String>,
+    shell: Option<String>,
+    env: Option<std::collections::HashMap<String, Option<String>>>,
+    profile_id: Option<String>,
 ) -> Result<String, String> {
-    let mut terminal_manager = state.inner().terminal_manager.lock().await;
-    
-    terminal_manager.create_session(title)
-        .map_err(|e| e.to_string())
+    let _timing = latency::track("create_terminal");
+    let options = crate::terminal::launch_profiles::SessionOptions {
+        title,
+        working_directory,
+        shell,
+        env: env.unwrap_or_default(),
+        profile_id,
+    };
+    let (session_id, recovery) = state.inner().terminal_manager.lock().await.create_session(options)?;
+    if let Some(recovery) = recovery {
+        emit_to_session(&app, &state, &session_id, crate::terminal::DIRECTORY_RECOVERED_EVENT, recovery).await;
+    }
+    Ok(session_id)
+}
+
+/// Keys an SSH host offers, to show before the first connection; empty when known_hosts already has it
+#[tauri::command]
+pub async fn get_ssh_host_keys(config: crate::terminal::ssh::SshSessionConfig) -> Result<Vec<crate::terminal::ssh::HostKey>, String> {
+    let _timing = latency::track("get_ssh_host_keys");
+    config.validate()?;
+    tokio::task::spawn_blocking(move || if config.is_known_host() { Ok(Vec::new()) } else { config.scan_host_keys() })
+        .await
+        .map_err(|e| e.to_string())?
 }
 
+/// Open a terminal connected to another machine over SSH, with key or agent authentication.
+/// A host not yet in known_hosts needs `trusted_fingerprint`, one of the fingerprints
+/// `get_ssh_host_keys` showed the user
+#[tauri::command]
+pub async fn create_remote_terminal(
+    state: State<'_, AppState>,
+    config: crate::terminal::ssh::SshSessionConfig,
+    title: Option<String>,
+    trusted_fingerprint: Option<String>,
+) -> Result<String, String> {
+    let _timing = latency::track("create_remote_terminal");
+    config.validate()?;
+    let checked = config.clone();
+    tokio::task::spawn_blocking(move || {
+        if checked.is_known_host() {
+            return Ok(());
+        }
+        match trusted_fingerprint {
+            Some(fingerprint) => checked.trust_host_key(&fingerprint),
+            None => Err(format!("The host key of {} is not known yet; confirm its fingerprint before connecting", checked.label())),
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    state.inner().terminal_manager.lock().await.create_remote_session(config, title)
+}
+
+/// Run a command in a session. Commands for one session run in submission order;
+/// `concurrently` lets this one start without waiting for those ahead of it, and
+/// `options` runs it once with another environment, directory, shell or locale
 #[tauri::command]
 pub async fn execute_command(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
     session_id: String,
-    command: String
+    command: String,
+    suggested_command: Option<String>,
+    concurrently: Option<bool>,
+    options: Option<crate::terminal::exec_options::ExecutionOptions>,
+) -> Result<crate::system::ipc::CappedExecution, String> {
+    use crate::terminal::command_queue::QUEUE_EVENT;
+    let _timing = latency::track("execute_command");
+    
+    let queues = state.inner().command_queues.clone();
+    let (ticket, queued) = queues.enqueue(&session_id, &command, concurrently.unwrap_or(false));
+    emit_to_session(&app, &state, &session_id, QUEUE_EVENT, queued).await;
+    let turn = match queues.wait(&ticket).await {
+        Ok((turn, running)) => {
+            emit_to_session(&app, &state, &session_id, QUEUE_EVENT, running).await;
+            turn
+        }
+        Err(e) => {
+            emit_to_session(&app, &state, &session_id, QUEUE_EVENT, queues.state(&session_id)).await;
+            return Err(e);
+        }
+    };
+    
+    let result = execute_in_turn(&app, &state, session_id.clone(), command, suggested_command, options.unwrap_or_default()).await;
+    drop(turn);
+    emit_to_session(&app, &state, &session_id, QUEUE_EVENT, queues.finish(&ticket)).await;
+    result
+}
+
+/// Everything `execute_command` does once it is the command's turn
+async fn execute_in_turn(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    session_id: String,
+    command: String,
+    suggested_command: Option<String>,
+    options: crate::terminal::exec_options::ExecutionOptions,
+) -> Result<crate::system::ipc::CappedExecution, String> {
+    // Running a command that was just suggested counts for its kind of suggestion
+    state.inner().suggestion_throttle.lock().await.record_command(&command);
+    
+    // A deleted or unmounted working directory would make every command fail confusingly
+    let recovery = state.inner().terminal_manager.lock().await.recover_working_directory(&session_id);
+    if let Some(recovery) = &recovery {
+        emit_to_session(app, state, &session_id, crate::terminal::DIRECTORY_RECOVERED_EVENT, recovery).await;
+    }
+    
+    // Power hungry commands get a lighter alternative suggested on low battery
+    let power_hint = {
+        let command = command.clone();
+        tokio::task::spawn_blocking(move || crate::system::power::alternative_for(&command)).await.ok().flatten()
+    };
+    
+    let directory_before = session_working_directory(state, Some(&session_id)).await;
+    let result = run_command(app, state, session_id.clone(), command, suggested_command, options).await;
+    let result = match power_hint {
+        Some(hint) => result.map(|mut execution| {
+            execution.output = format!("{}\n{}", execution.output, hint);
+            execution
+        }),
+        None => result,
+    };
+    if let Ok(execution) = &result {
+        notify_completion(app, state, &session_id, execution).await;
+        state.inner().sinks.publish(crate::integrations::sinks::SinkMessage::command(execution, &directory_before));
+    }
+    // History, directory and project state may have changed
+    state.inner().completion_gate.invalidate_session(&session_id);
+    // After a `cd` the new directory's suggestions, led by what is usually run there, are ready before they are asked for
+    let directory_after = session_working_directory(state, Some(&session_id)).await;
+    if directory_after != directory_before {
+        let key = crate::terminal::completion_gate::RequestKey::new(&session_id, "context_suggestions", "", &directory_after);
+        let suggestions = state.inner().terminal_manager.lock().await.get_context_suggestions(&session_id);
+        state.inner().completion_gate.store(key, &suggestions);
+    }
+    let result = match recovery {
+        Some(recovery) => result
+            .map(|mut execution| {
+                execution.output = format!("{}\n{}", recovery.notice(), execution.output);
+                execution
+            })
+            .map_err(|e| format!("{}\n{}", recovery.notice(), e)),
+        None => result,
+    };
+    // Screen-reader sessions get plain output with long tables summarized; history keeps the original
+    let accessible = state.inner().terminal_manager.lock().await
+        .get_session(&session_id)
+        .is_some_and(|session| session.accessible_output);
+    let result = if accessible {
+        result.map(|execution| {
+            let output = crate::terminal::accessible::render(&execution.output, execution.structured.as_ref());
+            execution.with_output(output)
+        })
+    } else {
+        result
+    };
+    // Huge outputs go out capped, the rest is paged in with get_output_page
+    let execution = std::sync::Arc::new(result?);
+    Ok(state.inner().output_pages.lock().await.cap(execution))
+}
+
+async fn run_command(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    session_id: String,
+    command: String,
+    suggested_command: Option<String>,
+    options: crate::terminal::exec_options::ExecutionOptions,
 ) -> Result<CommandExecution, String> {
     let _start_time = std::time::Instant::now();
+    
+    // A suggestion edited before running is remembered as a correction
+    if let Some(suggested) = suggested_command {
+        if let Some(correction) = state.inner().model_manager.lock().await.record_correction(&suggested, &command).await {
+            println!("✏️ Suggestion corrected: '{}' → '{}'", correction.suggested, correction.executed);
+        }
+    }
+    
+    // Expand {{...}} template variables; history keeps the template so secrets never land there
+    let template_context = state.inner().terminal_manager.lock().await.template_context(&session_id);
+    let expansion = crate::terminal::templates::expand(&command, &template_context).await;
+    if !expansion.unresolved.is_empty() && template_context.remote {
+        return Err(format!(
+            "Template variables unknown or not available in SSH sessions, which cannot read the remote machine's git, directories or environment: {}",
+            expansion.unresolved.join(", ")
+        ));
+    }
+    if !expansion.unresolved.is_empty() {
+        return Err(format!("Unknown template variables: {}", expansion.unresolved.join(", ")));
+    }
+    let original_command = command;
+    let command = expansion.command;
+    
+    // Slash commands and common requests (hashes, images, transforms) are answered locally
+    // An SSH session's requests all go to the remote shell: these would read this machine's files
+    let local_start = std::time::Instant::now();
+    let local = if template_context.remote { None } else { run_local_request(&command, &template_context).await? };
+    if let Some((output, exit_code)) = local {
+        let execution = CommandExecution {
+            id: uuid::Uuid::new_v4().to_string(),
+            command: original_command,
+            output,
+            exit_code: Some(exit_code),
+            duration_ms: local_start.elapsed().as_millis() as u64,
+            timestamp: chrono::Utc::now(),
+            outcome: crate::terminal::outcome::ExecutionOutcome::Exited(exit_code),
+            coverage: None,
+            structured: None,
+            overrides: None,
+            styled: None,
+            session_id: Some(session_id.clone()),
+        };
+        state.inner().terminal_manager.lock().await.record_execution(execution.clone());
+        let git = crate::terminal::status::load_git_summary(&template_context.working_directory).await;
+        publish_session_status(app, state, &session_id, command_finished(Some(&execution), &template_context.working_directory, git)).await;
+        publish_prompt(app, state, &session_id, template_context.environment.clone()).await;
+        return Ok(execution);
+    }
+    
     let mut terminal_manager = state.inner().terminal_manager.lock().await;
     
+    // capture NAME from "cmd" binds output to session variables
+    if let Some(request) = crate::terminal::capture::parse_capture(&command) {
+        let request = request?;
+        publish_session_status(app, state, &session_id, command_started(&request.command)).await;
+        let result = terminal_manager.execute_capture(&session_id, &request, &original_command)
+            .await
+            .map_err(|e| e.to_string());
+        drop(terminal_manager);
+        let git = crate::terminal::status::load_git_summary(&template_context.working_directory).await;
+        publish_session_status(app, state, &session_id, command_finished(result.as_ref().ok(), &template_context.working_directory, git)).await;
+        publish_prompt(app, state, &session_id, template_context.environment.clone()).await;
+        return result;
+    }
+    
+    // Phrases declared in the project's context file win over model translation
+    let project_hint = terminal_manager.project_context(&session_id)
+        .and_then(|project| project.hint_for(&command))
+        .map(str::to_string)
+        .filter(|_| {
+            let first_word = command.split_whitespace().next().unwrap_or_default();
+            is_natural_language_command(&command) || !crate::system::command_resolver::is_known_command(first_word)
+        });
+    
+    // Project hints are written by the user; only model translations are guarded
+    let translated = project_hint.is_none() && is_natural_language_command(&command);
+    
     // Detect if this is a natural language command and translate it first
-    let actual_command = if is_natural_language_command(&command) {
+    let actual_command = if let Some(hint) = project_hint {
+        println!("📌 Project hint: '{}' → '{}'", command, hint);
+        hint
+    } else if is_natural_language_command(&command) {
         println!("🔍 Detected natural language command: '{}'", command);
         
         // Get the model manager to translate
@@ -43,7 +286,7 @@ pub async fn execute_command(
                 command.clone()
             } else {
                 println!("✅ Model loaded successfully!");
-                let context = terminal_manager.get_smart_context(&session_id);
+                let context = terminal_manager.get_smart_context(&session_id).await;
                 let translation_result = model_manager.process_command_with_ml(&command, Some(&context)).await;
                 
                 if translation_result.confidence > 0.6 {
@@ -58,7 +301,7 @@ pub async fn execute_command(
                 }
             }
         } else {
-            let context = terminal_manager.get_smart_context(&session_id);
+            let context = terminal_manager.get_smart_context(&session_id).await;
             
             // Translate natural language to command
             let translation_result = model_manager.process_command_with_ml(&command, Some(&context)).await;
@@ -79,28 +322,60 @@ pub async fn execute_command(
         command.clone()
     };
     
-    // Execute the command - use special method for natural language to preserve original in history
-    let result = if is_natural_language_command(&command) && actual_command != command {
-        // For natural language commands, execute the translated command but store original in history
-        terminal_manager.execute_command_with_history(&session_id, &actual_command, &command)
-            .await
-            .map_err(|e| e.to_string())
+    // Generated commands are written for POSIX shells
+    let actual_command = if translated {
+        terminal_manager.adapt_to_shell(&session_id, &actual_command)
     } else {
-        // For regular commands, use normal execution
-        terminal_manager.execute_command(&session_id, &actual_command)
-            .await
-            .map_err(|e| e.to_string())
+        actual_command
+    };
+    
+    if translated && actual_command != command {
+        let report = crate::ai::translation_guard::check(
+            &actual_command,
+            &template_context.working_directory,
+            &crate::ai::translation_guard::GuardSettings::load(),
+        );
+        if !report.allowed {
+            return Err(format!(
+                "🛡️ Translated command was not run: {}\n{}\n💡 Review it and run it yourself if it is what you meant",
+                actual_command,
+                report.violations.iter().map(|v| format!("  • {}", v)).collect::<Vec<_>>().join("\n")
+            ));
+        }
+    }
+    
+    publish_session_status(app, state, &session_id, command_started(&actual_command)).await;
+    
+    // Natural language, project hints and templates run as `actual_command`; history keeps what was typed.
+    // The manager is let go while the command runs, so an editor or a long build holds up no other session
+    drop(terminal_manager);
+    let result = crate::terminal::execute_unlocked(&state.inner().terminal_manager, &session_id, &actual_command, &original_command, &options)
+        .await
+        .map_err(|e| e.to_string());
+    let (working_directory, environment, learning) = {
+        let terminal_manager = state.inner().terminal_manager.lock().await;
+        // cd may have moved the session, and export changed its environment
+        let (working_directory, environment) = terminal_manager.get_session(&session_id)
+            .map(|session| (session.working_directory.clone(), session.environment_vars.clone()))
+            .unwrap_or((template_context.working_directory.clone(), template_context.environment.clone()));
+        // Aliases are the user's own shorthand; what they stand for is what is learned
+        let learning = result.is_ok()
+            .then(|| (terminal_manager.get_smart_context(&session_id), terminal_manager.expand_aliases(&original_command)));
+        (working_directory, environment, learning)
     };
+    let git = crate::terminal::status::load_git_summary(&working_directory).await;
+    publish_session_status(app, state, &session_id, command_finished(result.as_ref().ok(), &working_directory, git)).await;
+    publish_prompt(app, state, &session_id, environment).await;
 
     // Learn from this command execution
-    if let Ok(execution) = &result {
+    if let (Ok(execution), Some((context, learned_command))) = (&result, learning) {
+        let context = context.await;
         let model_manager = state.inner().model_manager.lock().await;
-        let context = terminal_manager.get_smart_context(&session_id);
-        let success = execution.exit_code.unwrap_or(0) == 0;
+        let success = execution.outcome.is_success();
         
         // Enhanced learning with session context
         model_manager.learn_from_command(
-            &command, // Use original command for learning
+            &learned_command,
             &execution.output,
             &context,
             success,
@@ -108,12 +383,312 @@ pub async fn execute_command(
         ).await;
         
         // Track session workflow for pattern recognition
-        model_manager.track_session_workflow(&session_id, &command).await;
+        model_manager.track_session_workflow(&session_id, &learned_command).await;
+        model_manager.index_command(&execution.id, &original_command, &session_id, execution.exit_code, execution.duration_ms, execution.timestamp).await;
     }
 
     result
 }
 
+/// Tell the user a long command finished while the window was in the background.
+/// Focus mode and quiet hours keep it out of the system tray but not out of the notification center
+async fn notify_completion(app: &tauri::AppHandle, state: &State<'_, AppState>, session_id: &str, execution: &CommandExecution) {
+    use tauri::Manager;
+    use tauri_plugin_notification::NotificationExt;
+    
+    if !state.inner().notification_center.lock().await.wants(execution.duration_ms) {
+        return;
+    }
+    let label = state.inner().window_registry.lock().await.window_for(session_id).to_string();
+    if app.get_webview_window(&label).is_some_and(|window| window.is_focused().unwrap_or(false)) {
+        return;
+    }
+    let focus_mode = tokio::task::spawn_blocking(crate::terminal::notifications::focus_mode_active).await.unwrap_or(false);
+    let success = execution.outcome.is_success();
+    let title = if success { "✅ Command finished" } else { "❌ Command failed" };
+    let body = format!("{} ({:.1}s)", execution.command, execution.duration_ms as f64 / 1000.0);
+    let notification = state.inner().notification_center.lock().await.add(Some(session_id), title.to_string(), body, success, focus_mode);
+    
+    if notification.suppressed.is_none() {
+        if let Err(e) = app.notification().builder().title(&notification.title).body(&notification.body).show() {
+            println!("⚠️ Could not show notification: {}", e);
+        }
+    }
+    let mut speaker = state.inner().speaker.lock().await;
+    if notification.suppressed.is_none() || !speaker.follows_notifications() {
+        let program = execution.command.split_whitespace().next().unwrap_or(&execution.command);
+        let alert = format!("{} {} after {} seconds", program, if success { "finished" } else { "failed" }, execution.duration_ms / 1000);
+        if let Err(e) = speaker.speak(crate::system::speech::SpeechCategory::CompletionAlert, &alert) {
+            println!("⚠️ Could not speak alert: {}", e);
+        }
+    }
+    drop(speaker);
+    emit_to_session(app, state, session_id, crate::terminal::notifications::NOTIFICATION_EVENT, notification).await;
+}
+
+/// Emit a session's event to the window showing that session only
+async fn emit_to_session<S: serde::Serialize + Clone>(app: &tauri::AppHandle, state: &State<'_, AppState>, session_id: &str, event: &str, payload: S) {
+    use tauri::Emitter;
+    
+    let label = state.inner().window_registry.lock().await.window_for(session_id).to_string();
+    let _ = app.emit_to(label, event, payload);
+}
+
+/// Tell every window which sessions each window now shows
+pub async fn broadcast_windows(app: &tauri::AppHandle, state: &AppState) {
+    use tauri::Emitter;
+    
+    let session_ids: Vec<String> = state.terminal_manager.lock().await.get_all_sessions().iter().map(|s| s.id.clone()).collect();
+    let layouts = state.window_registry.lock().await.layouts(&session_ids);
+    let _ = app.emit(crate::terminal::windows::WINDOWS_CHANGED_EVENT, layouts);
+}
+
+/// Update a session's status and emit it when something changed
+async fn publish_session_status(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    session_id: &str,
+    change: impl FnOnce(&mut crate::terminal::status::SessionStatus),
+) {
+    let status = state.inner().session_status.lock().await.update(session_id, change);
+    if let Some(status) = status {
+        emit_to_session(app, state, session_id, crate::terminal::status::STATUS_EVENT, status).await;
+    }
+}
+
+/// Re-render the session's prompt from its status and emit it when it changed
+async fn publish_prompt(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    session_id: &str,
+    environment: std::collections::HashMap<String, String>,
+) {
+    let Some(status) = state.inner().session_status.lock().await.get(session_id).cloned() else {
+        return;
+    };
+    let engine = state.inner().prompt_engine.clone();
+    if let Ok(Some(prompt)) = tokio::task::spawn_blocking(move || engine.update(&status, &environment)).await {
+        emit_to_session(app, state, session_id, crate::terminal::prompt::PROMPT_EVENT, prompt).await;
+    }
+}
+
+fn command_started(command: &str) -> impl FnOnce(&mut crate::terminal::status::SessionStatus) {
+    let program = command.split_whitespace().next().map(str::to_string);
+    move |status| status.running_process = program
+}
+
+/// A command that could not run at all leaves no exit code or duration
+fn command_finished(
+    execution: Option<&CommandExecution>,
+    working_directory: &str,
+    git: Option<crate::terminal::status::GitSummary>,
+) -> impl FnOnce(&mut crate::terminal::status::SessionStatus) {
+    let exit = execution.map(|e| (e.exit_code, e.duration_ms));
+    let working_directory = working_directory.to_string();
+    move |status| {
+        status.running_process = None;
+        status.last_exit_code = exit.and_then(|(code, _)| code);
+        status.last_duration_ms = exit.map(|(_, duration)| duration);
+        status.set_working_directory(&working_directory, git);
+    }
+}
+
+/// Handle requests that need no shell: `/slash` transforms and natural language asks
+/// for checksums, image work, text transforms and parallel runs. Returns output and exit code.
+async fn run_local_request(
+    command: &str,
+    context: &crate::terminal::templates::TemplateContext,
+) -> Result<Option<(String, i32)>, String> {
+    use crate::tools::text_transform;
+    
+    let transform_output = |transform, input: &str| match text_transform::apply(transform, input) {
+        Ok(result) => {
+            let warnings: String = result.warnings.iter().map(|w| format!("\n⚠️ {}", w)).collect();
+            (format!("{}{}", result.output, warnings), 0)
+        }
+        Err(e) => (e, 1),
+    };
+    
+    if let Some((transform, input)) = text_transform::parse_slash_command(command) {
+        return Ok(Some(transform_output(transform, &input)));
+    }
+    // Everything below reads or changes real files; demo mode answers from canned output instead
+    if crate::system::demo::is_active() {
+        return Ok(None);
+    }
+    let working_dir = context.working_directory.clone();
+    
+    // "what-if rm -r build/*": list what the command would change instead of running it
+    if let Some(target) = command.strip_prefix("what-if ").or_else(|| command.strip_prefix("dry-run ")) {
+        let (target, dir) = (target.trim().to_string(), working_dir.clone());
+        let simulation = tokio::task::spawn_blocking(move || crate::terminal::what_if::simulate(&target, std::path::Path::new(&dir)))
+            .await
+            .map_err(|e| format!("Simulation failed: {}", e))?;
+        return Ok(Some(match simulation {
+            Some(Ok(simulation)) => (simulation.report(), 0),
+            Some(Err(e)) => (e, 1),
+            None => ("Only rm, mv, cp, rsync, chmod and find -delete can be simulated\n💡 Try other commands on copies in a `scratch new` directory".to_string(), 1),
+        }));
+    }
+    // "backup ." takes a snapshot with the directory's saved job, or with a suggested one when it has none.
+    // A program or alias named `backup` on this machine runs instead
+    if let Some(target) = command.strip_prefix("backup ").filter(|_| !crate::system::command_resolver::is_known_command("backup")) {
+        let target = target.trim();
+        let source = match target.strip_prefix('~') {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest.trim_start_matches('/')),
+            None => std::path::Path::new(&working_dir).join(target),
+        };
+        if let Some(source) = source.canonicalize().ok().filter(|source| source.is_dir()) {
+            let saved = crate::tools::backup::BackupJobs::load().for_source(&source).cloned();
+            let job = saved.clone().unwrap_or_else(|| crate::tools::backup::suggest(&source));
+            let report = tokio::task::spawn_blocking(move || crate::tools::backup::run(&job, |_| {}))
+                .await
+                .map_err(|e| format!("Backup task failed: {}", e))?;
+            return Ok(Some(match report {
+                Ok(report) => {
+                    if let Some(job) = saved {
+                        crate::tools::backup::BackupJobs::load().record_run(&job.id, report.started_at)?;
+                    }
+                    let failed = report.verification.as_ref().is_some_and(|v| !v.passed());
+                    (report.summary(), if failed { 1 } else { 0 })
+                }
+                Err(e) => (e, 1),
+            }));
+        }
+    }
+    // "find where parse_config is defined"; checked before the natural language test, which treats "find ..." as a command
+    if let Some(request) = crate::project::search::plan_from_natural_language(command) {
+        let results = tokio::task::spawn_blocking(move || {
+            crate::project::search::search_in_files(&request.query, std::path::Path::new(&working_dir), &request.options, |_| {})
+        })
+        .await
+        .map_err(|e| format!("Search task failed: {}", e))?;
+        return Ok(Some(match results {
+            Ok(results) => (results.summary(), if results.matches.is_empty() { 1 } else { 0 }),
+            Err(e) => (e, 1),
+        }));
+    }
+    // "find duplicate photos in Downloads", for the same reason
+    if let Some(request) = crate::tools::duplicates::plan_from_natural_language(command, &working_dir) {
+        let report = tokio::task::spawn_blocking(move || crate::tools::duplicates::find_duplicates(&request.path, &request.options))
+            .await
+            .map_err(|e| format!("Duplicate search failed: {}", e))?;
+        return Ok(Some(match report {
+            Ok(report) => (report.summary(), 0),
+            Err(e) => (e, 1),
+        }));
+    }
+    // "make this script executable"; `make` is also a real program, so this comes before the test too.
+    // Only the preview is shown: the user runs the printed chmod to apply it
+    if let Some(request) = crate::system::permissions::plan_from_natural_language(command, &working_dir) {
+        return Ok(Some(match crate::system::permissions::set_permissions(&request.path, &request.spec, false) {
+            Ok(change) if change.from == change.to => (change.summary(), 0),
+            Ok(change) => (format!("{}\nRun `{}` to apply", change.summary(), change.command()), 0),
+            Err(e) => (e, 1),
+        }));
+    }
+    // "run diagnostics", before the test since a bare "doctor" looks like a program name
+    if crate::system::diagnostics::is_diagnostics_request(command) {
+        let shell = context.environment.get("SHELL").cloned().unwrap_or_else(crate::terminal::launch_profiles::default_shell);
+        let models = local_gguf_models();
+        let report = tokio::task::spawn_blocking(move || crate::system::diagnostics::run(&shell, &models))
+            .await
+            .map_err(|e| format!("Diagnostics failed: {}", e))?;
+        let failed = report.status == crate::system::diagnostics::CheckStatus::Fail;
+        return Ok(Some((report.summary(), if failed { 1 } else { 0 })));
+    }
+    if !is_natural_language_command(command) {
+        return Ok(None);
+    }
+    
+    // "verify this ISO against its sha256"
+    if let Some(request) = crate::tools::checksum::plan_from_natural_language(command, &working_dir) {
+        let outcome = tokio::task::spawn_blocking(move || {
+            if request.verify || request.expected.is_some() {
+                crate::tools::checksum::verify_checksum(&request.path, request.expected.as_deref(), request.algorithm, |_, _| {})
+                    .map(|v| (v.summary(), v.matches))
+            } else {
+                let algorithm = request.algorithm.unwrap_or(crate::tools::checksum::HashAlgorithm::Sha256);
+                crate::tools::checksum::hash_file(&request.path, algorithm, |_, _| {})
+                    .map(|h| (format!("{}  {}", h.digest, h.path), true))
+            }
+        })
+        .await
+        .map_err(|e| format!("Checksum task failed: {}", e))?;
+        return Ok(Some(match outcome {
+            Ok((summary, ok)) => (summary, if ok { 0 } else { 1 }),
+            Err(e) => (e, 1),
+        }));
+    }
+    
+    // "shrink these screenshots"
+    if let Some(plan) = crate::tools::media::plan_from_natural_language(command) {
+        let results = tokio::task::spawn_blocking(move || crate::tools::media::run_plan(&plan, &working_dir))
+            .await
+            .map_err(|e| format!("Media task failed: {}", e))?;
+        return Ok(Some(match results {
+            Ok(results) => {
+                let failed = results.iter().any(|r| r.is_err());
+                (crate::tools::media::summarize(&results), if failed { 1 } else { 0 })
+            }
+            Err(e) => (e, 1),
+        }));
+    }
+    
+    // "pretty print this JSON", "convert config.yaml to toml"
+    if let Some(request) = crate::tools::config_format::plan_from_natural_language(command) {
+        use crate::tools::config_format::{self, ConfigFormat, ConfigSource};
+        let (text, file_format) = match &request.source {
+            ConfigSource::File(file) => {
+                let path = std::path::Path::new(&working_dir).join(file);
+                match tokio::fs::read_to_string(&path).await {
+                    Ok(text) => (text, ConfigFormat::from_path(&path)),
+                    Err(e) => return Ok(Some((format!("Cannot read {}: {}", file, e), 1))),
+                }
+            }
+            ConfigSource::LastOutput => match &context.last_output {
+                Some(output) => (output.clone(), None),
+                None => return Ok(Some(("No previous output to format".to_string(), 1))),
+            },
+        };
+        let input_format = request.input_format.or(file_format).unwrap_or_else(|| ConfigFormat::sniff(&text));
+        if request.validate_only {
+            let validation = config_format::validate(&text, input_format);
+            return Ok(Some(match validation.error {
+                None => (format!("✅ Valid {:?}", input_format), 0),
+                Some(error) => (
+                    format!("❌ {}{}", error.message, error.snippet.map(|s| format!("\n{}", s)).unwrap_or_default()),
+                    1,
+                ),
+            }));
+        }
+        let output_format = request.output_format.unwrap_or(input_format);
+        return Ok(Some(match config_format::format(&text, input_format, output_format, 2) {
+            Ok(formatted) => (formatted.output, 0),
+            Err(e) => (e, 1),
+        }));
+    }
+    
+    // "decode this JWT eyJ..."
+    if let Some((transform, input)) = text_transform::plan_from_natural_language(command) {
+        return Ok(Some(transform_output(transform, &input)));
+    }
+    
+    // "convert every mp4 in this folder to mp3"
+    if let Some(plan) = crate::terminal::parallel::plan_from_natural_language(command) {
+        let items = crate::terminal::parallel::items_from_glob(&plan.glob, &working_dir)?;
+        let report = crate::terminal::parallel::run_parallel(
+            &plan.command_template, items, default_concurrency(), &working_dir, context.environment.clone(),
+        ).await?;
+        return Ok(Some((
+            format!("{}\n{}", plan.description, report.summary()),
+            if report.failed == 0 { 0 } else { 1 },
+        )));
+    }
+    
+    Ok(None)
+}
+
 /// Detect if a command is natural language vs a regular shell command
 fn is_natural_language_command(command: &str) -> bool {
     let cmd_lower = command.to_lowercase().trim().to_string();
@@ -161,6 +736,18 @@ fn is_natural_language_command(command: &str) -> bool {
         }
     }
     
+    // An installed tool followed by flags or arguments is a command, even when a
+    // word like "run" or "status" appears in it (e.g. `docker run`, `systemctl status`)
+    let words: Vec<&str> = cmd_lower.split_whitespace().collect();
+    if let Some(first_word) = words.first() {
+        let prose_words = ["the", "all", "my", "me", "than", "every", "please", "that", "which", "with"];
+        let looks_like_prose = !words.iter().any(|w| w.starts_with('-'))
+            && words.iter().skip(1).any(|w| prose_words.contains(w));
+        if !looks_like_prose && crate::system::command_resolver::is_known_command(first_word) {
+            return false;
+        }
+    }
+    
     // Check for natural language sentence structure patterns
     let natural_patterns = [
         "go to", "navigate to", "change to", "move to", "switch to",
@@ -189,7 +776,6 @@ fn is_natural_language_command(command: &str) -> bool {
     }
     
     // Additional check: if it doesn't start with a known command and contains spaces, likely natural language
-    let words: Vec<&str> = cmd_lower.split_whitespace().collect();
     if words.len() > 1 {
         let first_word = words[0];
         // Comprehensive list of Unix/macOS/Linux commands
@@ -244,38 +830,160 @@ pub async fn get_terminal_output(
     state: State<'_, AppState>,
     _session_id: String,
     limit: Option<usize>
-) -> Result<Vec<CommandExecution>, String> {
-    let terminal_manager = state.inner().terminal_manager.lock().await;
+) -> Result<tauri::ipc::Response, String> {
+    let _timing = latency::track("get_terminal_output");
+    let history = state.inner().terminal_manager.lock().await.get_command_history(limit);
+    let mut pages = state.inner().output_pages.lock().await;
+    let capped: Vec<_> = history.into_iter().map(|execution| pages.cap(execution)).collect();
+    crate::system::ipc::json_response(&capped)
+}
+
+/// A session's past executions, newest first, `limit` at a time with large outputs capped
+#[tauri::command]
+pub async fn get_scrollback(
+    state: State<'_, AppState>,
+    session_id: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<tauri::ipc::Response, String> {
+    use crate::system::ipc::ScrollbackPage;
+    let _timing = latency::track("get_scrollback");
     
-    let history = terminal_manager.get_command_history(limit);
-    Ok(history.into_iter().cloned().collect())
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    let history = state.inner().terminal_manager.lock().await.get_session_history(&session_id, None);
+    let more = history.len() > offset + limit;
+    let mut pages = state.inner().output_pages.lock().await;
+    crate::system::ipc::json_response(&ScrollbackPage {
+        executions: history.into_iter().skip(offset).take(limit).map(|execution| pages.cap(execution)).collect(),
+        next_offset: more.then_some(offset + limit),
+    })
+}
+
+/// Commands running and waiting in a session
+#[tauri::command]
+pub async fn get_command_queue(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::terminal::command_queue::QueueState, String> {
+    let _timing = latency::track("get_command_queue");
+    Ok(state.inner().command_queues.state(&session_id))
+}
+
+/// Drop a command still waiting in a session's queue; its execute_command call fails
+#[tauri::command]
+pub async fn cancel_queued_command(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    command_id: String,
+) -> Result<crate::terminal::command_queue::QueueState, String> {
+    let _timing = latency::track("cancel_queued_command");
+    let queue = state.inner().command_queues.cancel(&session_id, &command_id)?;
+    emit_to_session(&app, &state, &session_id, crate::terminal::command_queue::QUEUE_EVENT, queue.clone()).await;
+    Ok(queue)
+}
+
+/// History newest first, `limit` entries older than the `before` cursor.
+/// Entries are shared with the history and serialized once, straight into the response
+#[tauri::command]
+pub async fn get_history_page(
+    state: State<'_, AppState>,
+    before: Option<u64>,
+    limit: Option<usize>,
+) -> Result<tauri::ipc::Response, String> {
+    let _timing = latency::track("get_history_page");
+    let page = state.inner().terminal_manager.lock().await.history_page(before, limit.unwrap_or(100));
+    let mut pages = state.inner().output_pages.lock().await;
+    crate::system::ipc::json_response(&page.map(|execution| pages.cap(execution)))
+}
+
+/// Entries recorded after `after_id`, for views that already show the rest.
+/// `reset` is set when that entry is gone and the view should reload instead
+#[tauri::command]
+pub async fn get_history_since(
+    state: State<'_, AppState>,
+    after_id: Option<String>,
+) -> Result<tauri::ipc::Response, String> {
+    let _timing = latency::track("get_history_since");
+    let delta = state.inner().terminal_manager.lock().await.history_since(after_id.as_deref());
+    let mut pages = state.inner().output_pages.lock().await;
+    crate::system::ipc::json_response(&delta.map(|execution| pages.cap(execution)))
+}
+
+/// A page of an output that was capped, gzip + base64 encoded when `compress` is set and the page is large
+#[tauri::command]
+pub async fn get_output_page(
+    state: State<'_, AppState>,
+    handle: String,
+    offset: Option<usize>,
+    length: Option<usize>,
+    compress: Option<bool>,
+) -> Result<tauri::ipc::Response, String> {
+    let _timing = latency::track("get_output_page");
+    let page = state.inner().output_pages.lock().await.page(&handle, offset.unwrap_or(0), length, compress.unwrap_or(false))?;
+    crate::system::ipc::json_response(&page)
 }
 
 #[tauri::command]
 pub async fn ai_suggest_command(
     state: State<'_, AppState>,
     context: String,
-    intent: Option<String>
+    intent: Option<String>,
+    session_id: Option<String>,
 ) -> Result<AIResponse, String> {
+    let _timing = latency::track("ai_suggest_command");
+    // Facts from the project index ground suggestions like "run only the auth module tests"
+    let project_facts = match &session_id {
+        Some(session_id) => {
+            let root = project_root_for(&state, Some(session_id)).await;
+            let question = intent.as_deref().unwrap_or(&context);
+            project_facts(&state, &root, question).await
+        }
+        None => None,
+    };
     let model_manager = state.inner().model_manager.lock().await;
     
     let prompt = match intent {
         Some(i) => format!("Suggest commands for: {}. Context: {}", i, context),
         None => format!("Suggest next commands based on context: {}", context),
     };
+    let context = match project_facts {
+        Some(facts) => format!("{}\n\nProject facts:\n{}", context, facts),
+        None => context,
+    };
     
     Ok(model_manager.generate_response(&prompt, Some(&context)).await)
 }
 
+async fn project_root_for(state: &State<'_, AppState>, session_id: Option<&str>) -> String {
+    let working_dir = session_working_directory(state, session_id).await;
+    crate::project::symbols::project_root(std::path::Path::new(&working_dir)).to_string_lossy().to_string()
+}
+
+/// Indexed facts about the project relevant to a question, one per line
+async fn project_facts(state: &State<'_, AppState>, root: &str, question: &str) -> Option<String> {
+    let knowledge = state.inner().model_manager.lock().await.project_knowledge(root, question, 8).await;
+    if knowledge.is_empty() {
+        return None;
+    }
+    Some(knowledge.iter().map(|k| format!("- {}", k.text)).collect::<Vec<_>>().join("\n"))
+}
+
 #[tauri::command]
 pub async fn ai_explain_command(
     state: State<'_, AppState>,
-    command: String
+    command: String,
+    session_id: Option<String>
 ) -> Result<AIResponse, String> {
+    let _timing = latency::track("ai_explain_command");
+    let working_dir = session_working_directory(&state, session_id.as_deref()).await;
+    let code_context = crate::ai::code_context::build_code_context(&command, &working_dir);
+    
     let model_manager = state.inner().model_manager.lock().await;
     let prompt = format!("Explain this command: {}", command);
     
-    Ok(model_manager.generate_response(&prompt, None).await)
+    Ok(model_manager.generate_response(&prompt, code_context.as_deref()).await)
 }
 
 #[tauri::command]
@@ -283,8 +991,15 @@ pub async fn ai_fix_error(
     state: State<'_, AppState>,
     error_output: String,
     command: String,
-    context: Option<String>
+    context: Option<String>,
+    session_id: Option<String>
 ) -> Result<AIResponse, String> {
+    let _timing = latency::track("ai_fix_error");
+    let working_dir = session_working_directory(&state, session_id.as_deref()).await;
+    let code_context = crate::ai::code_context::build_code_context(&error_output, &working_dir);
+    // A taken port is looked into here, so the fix can name the holder and a free port
+    let port_conflict = diagnose_port_conflict(state.clone(), error_output.clone(), command.clone()).await.ok().flatten();
+    
     let model_manager = state.inner().model_manager.lock().await;
     
     let prompt = format!(
@@ -292,15 +1007,28 @@ pub async fn ai_fix_error(
         command, error_output, context.unwrap_or_default()
     );
     
-    Ok(model_manager.generate_response(&prompt, Some(&error_output)).await)
+    let model_context = match code_context {
+        Some(code) => format!("{}\n\nRelevant code:\n{}", error_output, code),
+        None => error_output.clone(),
+    };
+    let model_context = match port_conflict {
+        Some(conflict) => format!("{}\n\n{}\n{}", model_context, conflict.summary(), crate::terminal::port_conflicts::remedy_hints(&conflict)),
+        None => model_context,
+    };
+    Ok(model_manager.generate_response(&prompt, Some(&model_context)).await)
 }
 
 #[tauri::command]
 pub async fn ai_analyze_output(
     state: State<'_, AppState>,
     output: String,
-    command: String
+    command: String,
+    session_id: Option<String>
 ) -> Result<AIResponse, String> {
+    let _timing = latency::track("ai_analyze_output");
+    let working_dir = session_working_directory(&state, session_id.as_deref()).await;
+    let code_context = crate::ai::code_context::build_code_context(&output, &working_dir);
+    
     let model_manager = state.inner().model_manager.lock().await;
     
     let prompt = format!(
@@ -308,7 +1036,45 @@ pub async fn ai_analyze_output(
         command, output
     );
     
-    Ok(model_manager.generate_response(&prompt, Some(&output)).await)
+    let model_context = match code_context {
+        Some(code) => format!("{}\n\nRelevant code:\n{}", output, code),
+        None => output.clone(),
+    };
+    Ok(model_manager.generate_response(&prompt, Some(&model_context)).await)
+}
+
+/// Working directory of a session, falling back to the process directory
+async fn session_working_directory(state: &State<'_, AppState>, session_id: Option<&str>) -> String {
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    session_id
+        .and_then(|id| terminal_manager.get_session(id))
+        .map(|session| session.working_directory.clone())
+        .unwrap_or_else(|| {
+            std::env::current_dir()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|_| ".".to_string())
+        })
+}
+
+/// Allow or deny AI features reading files from a directory (and below)
+#[tauri::command]
+pub async fn set_directory_trust(
+    directory: String,
+    trusted: Option<bool>,
+) -> Result<(), String> {
+    let _timing = latency::track("set_directory_trust");
+    let mut trust = crate::ai::trust::TrustStore::load();
+    match trusted {
+        Some(trusted) => trust.set_trust(&directory, trusted),
+        None => trust.clear_trust(&directory),
+    }
+}
+
+/// Effective trust for a directory: true/false, or null when never configured
+#[tauri::command]
+pub async fn get_directory_trust(directory: String) -> Result<Option<bool>, String> {
+    let _timing = latency::track("get_directory_trust");
+    Ok(crate::ai::trust::TrustStore::load().trust_for(std::path::Path::new(&directory)))
 }
 
 #[tauri::command]
@@ -317,13 +1083,84 @@ pub async fn get_smart_completions(
     partial_command: String,
     session_id: String
 ) -> Result<Vec<String>, String> {
+    use crate::terminal::completion_gate::{Admission, RequestKey, SUPERSEDED};
+    let _timing = latency::track("get_smart_completions");
+    
+    // Called on every keystroke: debounce, drop superseded requests and reuse recent results
+    let working_dir = session_working_directory(&state, Some(&session_id)).await;
+    let key = RequestKey::new(&session_id, "smart_completions", &partial_command, &working_dir);
+    let gate = state.inner().completion_gate.clone();
+    let ticket = match gate.admit(&key).await {
+        Admission::Cached(completions) => return Ok(completions),
+        Admission::Superseded => return Err(SUPERSEDED.to_string()),
+        Admission::Run(ticket) => ticket,
+    };
+    
+    // Typing a flag: complete it from the command's help page
+    let last_word = partial_command.rsplit(' ').next().unwrap_or("");
+    if last_word.starts_with('-') && partial_command.contains(' ') {
+        if let Ok(doc) = help_document(&state, &partial_command, Some(&session_id), false).await {
+            let stem = &partial_command[..partial_command.len() - last_word.len()];
+            let flag_completions: Vec<String> = doc.complete_flags(last_word)
+                .into_iter()
+                .filter_map(|flag| flag.names.iter().find(|name| name.starts_with(last_word)))
+                .map(|name| format!("{}{}", stem, name))
+                .take(12)
+                .collect();
+            if !flag_completions.is_empty() {
+                let mut provenance = state.inner().provenance.lock().await;
+                for completion in &flag_completions {
+                    let mut record = Provenance::new(SuggestionKind::Completion, completion, "help page flags")
+                        .in_session(&session_id, &working_dir);
+                    record.trigger = Some(format!("Typing the flag `{}`", last_word));
+                    provenance.record(record);
+                }
+                drop(provenance);
+                gate.store(key, &flag_completions);
+                return Ok(flag_completions);
+            }
+        }
+    }
+    
+    let context = state.inner().terminal_manager.lock().await.get_smart_context(&session_id);
+    let context = context.await;
     let model_manager = state.inner().model_manager.lock().await;
     let terminal_manager = state.inner().terminal_manager.lock().await;
-    
-    let context = terminal_manager.get_smart_context(&session_id);
+    // A newer keystroke may have arrived while waiting for the managers
+    if !gate.is_current(&key, ticket) {
+        return Err(SUPERSEDED.to_string());
+    }
     
     // Get enhanced completions with session context
     let completions = model_manager.get_enhanced_completions(&partial_command, &context, &session_id).await;
+    
+    // Keep why each completion was offered, for `explain_suggestion`
+    let mut records = Vec::with_capacity(completions.len());
+    for completion in &completions {
+        let (pattern, overall) = model_manager.suggestion_evidence(completion).await;
+        let here = terminal_manager.directory_command_stats(&working_dir, completion);
+        let mut record = Provenance::new(SuggestionKind::Completion, completion, "learned patterns")
+            .in_session(&session_id, &working_dir);
+        record.trigger = Some(if pattern.as_ref().is_some_and(|p| p.key.starts_with("workflow:")) {
+            "Matches a command sequence from this session".to_string()
+        } else {
+            format!("Matches what you typed: `{}`", partial_command)
+        });
+        record.confidence = pattern.as_ref().map(|p| p.confidence);
+        record.matched_pattern = pattern;
+        record.frequency = HistoricalFrequency::with_directory(overall, here.as_ref());
+        record.model = Some("learning engine".to_string());
+        records.push(record);
+    }
+    drop(terminal_manager);
+    drop(model_manager);
+    let mut provenance = state.inner().provenance.lock().await;
+    for record in records {
+        provenance.record(record);
+    }
+    drop(provenance);
+    
+    gate.store(key, &completions);
     Ok(completions)
 }
 
@@ -333,28 +1170,21 @@ pub async fn ai_translate_natural_language(
     natural_language: String,
     context: String,
 ) -> Result<AIResponse, String> {
+    let _timing = latency::track("ai_translate_natural_language");
     let model_manager = state.inner().model_manager.lock().await;
+    let (mut response, model) = model_manager.translate_natural_language(&natural_language, &context).await;
     
-    // Use ML-powered command processing for better accuracy
-    let ml_response = model_manager.process_command_with_ml(&natural_language, Some(&context)).await;
-    
-    // If ML processing has high confidence, use it directly
-    if ml_response.confidence > 0.8 {
-        return Ok(ml_response);
-    }
-    
-    // Otherwise, try the enhanced approach as fallback
-    let prompt = format!("Convert this natural language request to a terminal command: \"{}\"", natural_language);
-    let response = model_manager.generate_response(&prompt, Some(&context)).await;
-    
-    // If the response looks like a comment, try a more specific approach
-    if response.text.starts_with('#') || response.text.contains("need more") {
-        let enhanced_prompt = format!("natural language: {}", natural_language);
-        let enhanced_response = model_manager.generate_response(&enhanced_prompt, Some(&context)).await;
-        Ok(enhanced_response)
-    } else {
-        Ok(response)
-    }
+    let (pattern, frequency) = model_manager.suggestion_evidence(&response.text).await;
+    drop(model_manager);
+    let mut record = Provenance::new(SuggestionKind::Translation, &response.text, "natural language translation");
+    record.trigger = Some(format!("You asked: \"{}\"", natural_language));
+    record.model = Some(model);
+    record.confidence = Some(response.confidence);
+    record.reasoning = response.reasoning.clone();
+    record.matched_pattern = pattern;
+    record.frequency = frequency;
+    response.suggestion_id = Some(state.inner().provenance.lock().await.record(record));
+    Ok(response)
 }
 
 /// Get user analytics from learning engine
@@ -362,8 +1192,13 @@ pub async fn ai_translate_natural_language(
 pub async fn get_user_analytics(
     state: State<'_, AppState>,
 ) -> Result<Option<ai::UserAnalytics>, String> {
+    let _timing = latency::track("get_user_analytics");
+    let outcomes = state.inner().terminal_manager.lock().await.outcome_counts();
     let model_manager = state.inner().model_manager.lock().await;
-    Ok(model_manager.get_analytics().await)
+    Ok(model_manager.get_analytics().await.map(|mut analytics| {
+        analytics.outcomes = outcomes;
+        analytics
+    }))
 }
 
 /// Update feedback for learning
@@ -373,19 +1208,34 @@ pub async fn update_ai_feedback(
     command: String,
     feedback: f32,
 ) -> Result<(), String> {
+    let _timing = latency::track("update_ai_feedback");
     let model_manager = state.inner().model_manager.lock().await;
     model_manager.update_feedback(&command, feedback).await;
     Ok(())
 }
 
-/// Agent mode: Create autonomous task
+/// Agent mode: Create autonomous task; with `in_scratch` it runs in a new scratch directory of the session
 #[tauri::command]
 pub async fn create_agent_task(
     state: State<'_, AppState>,
     description: String,
+    session_id: Option<String>,
+    in_scratch: Option<bool>,
 ) -> Result<String, String> {
+    let _timing = latency::track("create_agent_task");
+    let scratch_dir = if in_scratch.unwrap_or(false) {
+        let mut terminal_manager = state.inner().terminal_manager.lock().await;
+        // Without a session to close, the directory goes after a day
+        let policy = session_id.is_none().then_some(crate::terminal::scratch::CleanupPolicy::AfterHours { hours: 24 });
+        Some(terminal_manager.create_scratch_dir(session_id.as_deref(), Some("agent-task"), policy, false)?.path)
+    } else {
+        None
+    };
+    let root = project_root_for(&state, session_id.as_deref()).await;
     let model_manager = state.inner().model_manager.lock().await;
-    model_manager.create_agent_task(&description).await
+    let task_id = model_manager.create_agent_task(&description, scratch_dir, Some(&root)).await?;
+    state.inner().sinks.publish_agent_plan(&model_manager, &task_id).await;
+    Ok(task_id)
 }
 
 /// Get agent task status
@@ -394,6 +1244,7 @@ pub async fn get_agent_task_status(
     state: State<'_, AppState>,
     task_id: String,
 ) -> Result<Option<ai::TaskStatus>, String> {
+    let _timing = latency::track("get_agent_task_status");
     let model_manager = state.inner().model_manager.lock().await;
     Ok(model_manager.get_agent_task_status(&task_id).await)
 }
@@ -403,6 +1254,7 @@ pub async fn get_agent_task_status(
 pub async fn get_active_agent_tasks(
     state: State<'_, AppState>,
 ) -> Result<Vec<String>, String> {
+    let _timing = latency::track("get_active_agent_tasks");
     let model_manager = state.inner().model_manager.lock().await;
     Ok(model_manager.get_active_agent_tasks().await)
 }
@@ -413,6 +1265,7 @@ pub async fn cancel_agent_task(
     state: State<'_, AppState>,
     task_id: String,
 ) -> Result<(), String> {
+    let _timing = latency::track("cancel_agent_task");
     let model_manager = state.inner().model_manager.lock().await;
     model_manager.cancel_agent_task(&task_id).await
 }
@@ -420,11 +1273,24 @@ pub async fn cancel_agent_task(
 /// Close terminal session
 #[tauri::command]
 pub async fn close_terminal_session(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
     session_id: String,
 ) -> Result<(), String> {
-    let mut terminal_manager = state.inner().terminal_manager.lock().await;
-    terminal_manager.close_session(&session_id)
+    use tauri::Manager;
+    let _timing = latency::track("close_terminal_session");
+    
+    state.inner().terminal_manager.lock().await.close_session(&session_id)?;
+    state.inner().session_status.lock().await.remove(&session_id);
+    state.inner().command_queues.remove_session(&session_id);
+    state.inner().prompt_engine.remove_session(&session_id);
+    state.inner().notification_center.lock().await.remove_session(&session_id);
+    // A detached window closes with its last session
+    let emptied = state.inner().window_registry.lock().await.release(&session_id);
+    if let Some(window) = emptied.and_then(|label| app.get_webview_window(&label)) {
+        let _ = window.close();
+    }
+    Ok(())
 }
 
 /// Update session title
@@ -434,6 +1300,7 @@ pub async fn update_session_title(
     session_id: String,
     title: String,
 ) -> Result<(), String> {
+    let _timing = latency::track("update_session_title");
     let mut terminal_manager = state.inner().terminal_manager.lock().await;
     terminal_manager.update_session_title(&session_id, title)
 }
@@ -446,57 +1313,357 @@ pub async fn resize_terminal(
     cols: u16,
     rows: u16,
 ) -> Result<(), String> {
+    let _timing = latency::track("resize_terminal");
     let mut terminal_manager = state.inner().terminal_manager.lock().await;
-    terminal_manager.resize_terminal(&session_id, cols, rows)
+    terminal_manager.resize_terminal(&session_id, rows, cols)
 }
 
-/// Get system information
+/// Interrupt (SIGINT), terminate (SIGTERM) or kill (SIGKILL) a running command; without
+/// `execution_id`, the one the session started last
 #[tauri::command]
-pub async fn get_system_info(
+pub async fn terminate_command(
     state: State<'_, AppState>,
-) -> Result<std::collections::HashMap<String, String>, String> {
-    let terminal_manager = state.inner().terminal_manager.lock().await;
-    Ok(terminal_manager.get_system_info())
+    session_id: String,
+    execution_id: Option<String>,
+    signal: Option<crate::terminal::running::TerminationSignal>,
+) -> Result<crate::terminal::running::RunningCommand, String> {
+    let _timing = latency::track("terminate_command");
+    let signal = signal.unwrap_or(crate::terminal::running::TerminationSignal::Interrupt);
+    state.inner().terminal_manager.lock().await.terminate_command(&session_id, execution_id.as_deref(), signal)
 }
 
-/// Get context-aware command suggestions
+/// What is running now, with the execution ids `terminate_command` takes
 #[tauri::command]
-pub async fn get_context_suggestions(
+pub async fn get_running_commands(
     state: State<'_, AppState>,
-    session_id: String,
-) -> Result<Vec<String>, String> {
-    let terminal_manager = state.inner().terminal_manager.lock().await;
-    Ok(terminal_manager.get_context_suggestions(&session_id))
+    session_id: Option<String>,
+) -> Result<Vec<crate::terminal::running::RunningCommand>, String> {
+    let _timing = latency::track("get_running_commands");
+    Ok(state.inner().terminal_manager.lock().await.running_commands(session_id.as_deref()))
 }
 
-/// Get all sessions
 #[tauri::command]
-pub async fn get_all_sessions(
+pub async fn get_child_policies(
     state: State<'_, AppState>,
-) -> Result<Vec<crate::terminal::TerminalSession>, String> {
-    let terminal_manager = state.inner().terminal_manager.lock().await;
-    Ok(terminal_manager.get_all_sessions().into_iter().cloned().collect())
+) -> Result<crate::terminal::lifecycle::LifecycleSettings, String> {
+    let _timing = latency::track("get_child_policies");
+    Ok(state.inner().terminal_manager.lock().await.lifecycle_settings().clone())
 }
 
-/// Get path completions for Tab autocomplete
+/// Default policy and per-command rules for what happens to commands when their session closes
 #[tauri::command]
-pub async fn get_path_completions(
+pub async fn update_child_policies(
     state: State<'_, AppState>,
-    session_id: String,
-    partial_path: String,
-) -> Result<Vec<String>, String> {
-    let terminal_manager = state.inner().terminal_manager.lock().await;
-    Ok(terminal_manager.get_path_completions(&session_id, &partial_path))
+    settings: crate::terminal::lifecycle::LifecycleSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_child_policies");
+    state.inner().terminal_manager.lock().await.set_lifecycle_settings(settings)
 }
 
-/// Get command history for arrow key navigation
+/// Give a session its own child policy; `null` follows the rules again
 #[tauri::command]
-pub async fn get_command_history_for_navigation(
+pub async fn set_session_child_policy(
     state: State<'_, AppState>,
     session_id: String,
-) -> Result<Vec<String>, String> {
-    let terminal_manager = state.inner().terminal_manager.lock().await;
-    Ok(terminal_manager.get_command_history_for_navigation(&session_id))
+    policy: Option<crate::terminal::lifecycle::ChildPolicy>,
+) -> Result<(), String> {
+    let _timing = latency::track("set_session_child_policy");
+    state.inner().terminal_manager.lock().await.set_session_child_policy(&session_id, policy)
+}
+
+/// Background jobs, commands running on their own and orphans that are still alive
+#[tauri::command]
+pub async fn list_background_jobs(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::lifecycle::TrackedProcess>, String> {
+    let _timing = latency::track("list_background_jobs");
+    let processes = state.inner().terminal_manager.lock().await.processes().clone();
+    tokio::task::spawn_blocking(move || processes.list()).await.map_err(|e| e.to_string())
+}
+
+/// Processes an earlier run of the console left running
+#[tauri::command]
+pub async fn list_orphaned_processes(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::lifecycle::TrackedProcess>, String> {
+    let _timing = latency::track("list_orphaned_processes");
+    let processes = state.inner().terminal_manager.lock().await.processes().clone();
+    tokio::task::spawn_blocking(move || processes.orphans()).await.map_err(|e| e.to_string())
+}
+
+/// Keep an orphan as a background job of this run
+#[tauri::command]
+pub async fn adopt_orphaned_process(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<crate::terminal::lifecycle::TrackedProcess, String> {
+    let _timing = latency::track("adopt_orphaned_process");
+    state.inner().terminal_manager.lock().await.processes().adopt(&id)
+}
+
+/// Terminate (the default), kill or interrupt a background job or orphan
+#[tauri::command]
+pub async fn stop_background_job(
+    state: State<'_, AppState>,
+    id: String,
+    signal: Option<crate::terminal::running::TerminationSignal>,
+) -> Result<crate::terminal::lifecycle::TrackedProcess, String> {
+    let _timing = latency::track("stop_background_job");
+    let signal = signal.unwrap_or(crate::terminal::running::TerminationSignal::Terminate);
+    let processes = state.inner().terminal_manager.lock().await.processes().clone();
+    tokio::task::spawn_blocking(move || processes.signal(&id, signal)).await.map_err(|e| e.to_string())?
+}
+
+/// Defined dev services and their state
+#[tauri::command]
+pub async fn list_services(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::dev_services::ServiceInfo>, String> {
+    let _timing = latency::track("list_services");
+    Ok(state.inner().terminal_manager.lock().await.services().list())
+}
+
+/// Start a dev service by name; with `definition` it is saved under that name first
+#[tauri::command]
+pub async fn start_service(
+    state: State<'_, AppState>,
+    name: String,
+    definition: Option<crate::terminal::dev_services::ServiceDefinition>,
+) -> Result<crate::terminal::dev_services::ServiceInfo, String> {
+    let _timing = latency::track("start_service");
+    let services = state.inner().terminal_manager.lock().await.services().clone();
+    if let Some(definition) = definition {
+        services.define(crate::terminal::dev_services::ServiceDefinition { name: name.clone(), ..definition })?;
+    }
+    services.start(&name)
+}
+
+/// Stop a dev service; it is killed if it does not exit in a few seconds
+#[tauri::command]
+pub async fn stop_service(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<crate::terminal::dev_services::ServiceInfo, String> {
+    let _timing = latency::track("stop_service");
+    let services = state.inner().terminal_manager.lock().await.services().clone();
+    tokio::task::spawn_blocking(move || services.stop(&name)).await.map_err(|e| e.to_string())?
+}
+
+/// The last lines of a dev service's output (200 by default)
+#[tauri::command]
+pub async fn get_service_logs(
+    state: State<'_, AppState>,
+    name: String,
+    lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let _timing = latency::track("get_service_logs");
+    state.inner().terminal_manager.lock().await.services().logs(&name, lines.unwrap_or(200))
+}
+
+/// Forget a stopped dev service
+#[tauri::command]
+pub async fn remove_service(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<(), String> {
+    let _timing = latency::track("remove_service");
+    state.inner().terminal_manager.lock().await.services().remove(&name)
+}
+
+/// Dev servers the session's project has that are not defined as services yet
+#[tauri::command]
+pub async fn suggest_services(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<crate::terminal::dev_services::ServiceDefinition>, String> {
+    let _timing = latency::track("suggest_services");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let session = terminal_manager.get_session(&session_id).ok_or("Session not found")?;
+    Ok(terminal_manager.services().suggestions(std::path::Path::new(&session.working_directory)))
+}
+
+/// Apply one of the remedies offered for a dev service that failed on a taken port, and start it again
+#[tauri::command]
+pub async fn resolve_service_port_conflict(
+    state: State<'_, AppState>,
+    name: String,
+    remedy: crate::terminal::port_conflicts::PortRemedy,
+) -> Result<crate::terminal::dev_services::ServiceInfo, String> {
+    let _timing = latency::track("resolve_service_port_conflict");
+    let services = state.inner().terminal_manager.lock().await.services().clone();
+    services.resolve_conflict(&name, remedy).await
+}
+
+/// The taken port a failed command's output reports, who holds it and the remedies; `None` when it reports none
+#[tauri::command]
+pub async fn diagnose_port_conflict(
+    state: State<'_, AppState>,
+    output: String,
+    command: String,
+) -> Result<Option<crate::terminal::port_conflicts::PortConflict>, String> {
+    let _timing = latency::track("diagnose_port_conflict");
+    let Some(port) = crate::terminal::port_conflicts::detect(&output).flatten() else { return Ok(None) };
+    let registered = state.inner().terminal_manager.lock().await.services().ports();
+    tokio::task::spawn_blocking(move || {
+        crate::terminal::port_conflicts::investigate(port, &command, &std::collections::HashMap::new(), &registered, None)
+    })
+    .await
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Type into the session's terminal, e.g. to answer a prompt from the command running there
+#[tauri::command]
+pub async fn write_to_terminal(
+    state: State<'_, AppState>,
+    session_id: String,
+    data: String,
+) -> Result<(), String> {
+    let _timing = latency::track("write_to_terminal");
+    state.inner().terminal_manager.lock().await.write_input(&session_id, &data)
+}
+
+/// Get system information
+#[tauri::command]
+pub async fn get_system_info(
+    state: State<'_, AppState>,
+) -> Result<std::collections::HashMap<String, String>, String> {
+    let _timing = latency::track("get_system_info");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    Ok(terminal_manager.get_system_info())
+}
+
+/// Get context-aware command suggestions
+#[tauri::command]
+pub async fn get_context_suggestions(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<String>, String> {
+    use crate::terminal::completion_gate::{Admission, RequestKey, SUPERSEDED};
+    let _timing = latency::track("get_context_suggestions");
+    
+    let working_dir = session_working_directory(&state, Some(&session_id)).await;
+    let key = RequestKey::new(&session_id, "context_suggestions", "", &working_dir);
+    let gate = state.inner().completion_gate.clone();
+    let suggestions = match gate.admit(&key).await {
+        Admission::Cached(suggestions) => suggestions,
+        Admission::Superseded => return Err(SUPERSEDED.to_string()),
+        Admission::Run(_) => {
+            let terminal_manager = state.inner().terminal_manager.lock().await;
+            let suggestions = terminal_manager.context_suggestions_with_triggers(&session_id);
+            let records: Vec<Provenance> = suggestions
+                .iter()
+                .map(|(suggestion, trigger)| {
+                    let mut record = Provenance::new(SuggestionKind::Context, suggestion, "session and directory context")
+                        .in_session(&session_id, &working_dir);
+                    record.trigger = Some(trigger.clone());
+                    let here = terminal_manager.directory_command_stats(&working_dir, suggestion);
+                    record.frequency = HistoricalFrequency::with_directory(None, here.as_ref());
+                    record
+                })
+                .collect();
+            drop(terminal_manager);
+            let mut provenance = state.inner().provenance.lock().await;
+            for record in records {
+                provenance.record(record);
+            }
+            drop(provenance);
+            let suggestions: Vec<String> = suggestions.into_iter().map(|(suggestion, _)| suggestion).collect();
+            gate.store(key, &suggestions);
+            suggestions
+        }
+    };
+    // Fewer or no suggestions when they are rarely taken up
+    Ok(state.inner().suggestion_throttle.lock().await.filter_context(suggestions))
+}
+
+/// Top commands, failure hotspots and usual next steps for a directory
+#[tauri::command]
+pub async fn get_directory_profile(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<crate::terminal::dir_usage::DirectoryProfile, String> {
+    let _timing = latency::track("get_directory_profile");
+    Ok(state.inner().terminal_manager.lock().await.directory_profile(&path))
+}
+
+/// Command runs and failures per directory, busiest first, for the analytics dashboard
+#[tauri::command]
+pub async fn get_directory_heatmap(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::terminal::dir_usage::DirectoryHeat>, String> {
+    let _timing = latency::track("get_directory_heatmap");
+    Ok(state.inner().terminal_manager.lock().await.directory_heatmap(limit.unwrap_or(50)))
+}
+
+/// Get all sessions
+#[tauri::command]
+pub async fn get_all_sessions(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::TerminalSession>, String> {
+    let _timing = latency::track("get_all_sessions");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    Ok(terminal_manager.get_all_sessions().into_iter().cloned().collect())
+}
+
+/// User-defined aliases, as the `alias` built-in defines them
+#[tauri::command]
+pub async fn get_aliases(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::aliases::Alias>, String> {
+    let _timing = latency::track("get_aliases");
+    Ok(state.inner().terminal_manager.lock().await.aliases())
+}
+
+/// Reopen the sessions that were open when the app last ran and are not open yet
+#[tauri::command]
+pub async fn restore_sessions(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::TerminalSession>, String> {
+    let _timing = latency::track("restore_sessions");
+    let (restored, recoveries) = state.inner().terminal_manager.lock().await.restore_sessions();
+    for recovery in recoveries {
+        let session_id = recovery.session_id.clone();
+        emit_to_session(&app, &state, &session_id, crate::terminal::DIRECTORY_RECOVERED_EVENT, recovery).await;
+    }
+    Ok(restored)
+}
+
+/// Get path completions for Tab autocomplete
+#[tauri::command]
+pub async fn get_path_completions(
+    state: State<'_, AppState>,
+    session_id: String,
+    partial_path: String,
+) -> Result<Vec<String>, String> {
+    let _timing = latency::track("get_path_completions");
+    let completions = state.inner().terminal_manager.lock().await.get_path_completions(&session_id, &partial_path);
+    Ok(completions.await)
+}
+
+/// Files the session worked with lately, for the AI panel and `@recent`
+#[tauri::command]
+pub async fn get_recent_files(
+    state: State<'_, AppState>,
+    session_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<crate::terminal::recent_files::RecentFile>, String> {
+    let _timing = latency::track("get_recent_files");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    Ok(terminal_manager.recent_files(&session_id, limit.unwrap_or(20)))
+}
+
+/// Get command history for arrow key navigation
+#[tauri::command]
+pub async fn get_command_history_for_navigation(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<String>, String> {
+    let _timing = latency::track("get_command_history_for_navigation");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    Ok(terminal_manager.get_command_history_for_navigation(&session_id))
 }
 
 /// Search command history
@@ -505,6 +1672,7 @@ pub async fn search_command_history(
     state: State<'_, AppState>,
     pattern: String,
 ) -> Result<Vec<String>, String> {
+    let _timing = latency::track("search_command_history");
     let terminal_manager = state.inner().terminal_manager.lock().await;
     Ok(terminal_manager.search_command_history(&pattern))
 }
@@ -516,6 +1684,7 @@ pub async fn store_command_in_history(
     session_id: String,
     command: String,
 ) -> Result<(), String> {
+    let _timing = latency::track("store_command_in_history");
     let mut terminal_manager = state.inner().terminal_manager.lock().await;
     terminal_manager.store_command_in_history(&session_id, &command)
         .map_err(|e| e.to_string())
@@ -523,6 +1692,7 @@ pub async fn store_command_in_history(
 
 #[tauri::command]
 pub async fn test_command() -> Result<String, String> {
+    let _timing = latency::track("test_command");
     Ok("Test successful".to_string())
 }
 
@@ -532,6 +1702,7 @@ pub async fn validate_frequent_directories(
     frequent_dirs: Vec<String>,
     current_working_dir: String,
 ) -> Result<Vec<String>, String> {
+    let _timing = latency::track("validate_frequent_directories");
     let mut valid_dirs = Vec::new();
     
     for dir in frequent_dirs {
@@ -558,44 +1729,49 @@ pub async fn validate_frequent_directories(
     Ok(valid_dirs)
 }
 
-/// Find the correct path for a given directory name in common locations
+/// Find the directory a mistyped `cd` meant: one with that name in the working directory or one of its parents
 #[tauri::command]
 pub async fn find_path_in_common_locations(
     target_name: String,
     current_working_dir: String,
 ) -> Result<Option<String>, String> {
-    let search_locations = vec![
-        current_working_dir.clone(),
-        dirs::home_dir().map(|p| p.to_string_lossy().to_string()).unwrap_or_default(),
-        "/usr/local".to_string(),
-        "/opt".to_string(),
-        format!("{}/Desktop", dirs::home_dir().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()),
-        format!("{}/Documents", dirs::home_dir().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()),
-        format!("{}/Downloads", dirs::home_dir().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()),
-    ];
-    
-    for location in search_locations {
-        let potential_path = PathBuf::from(&location).join(&target_name);
-        if potential_path.is_dir() {
-            return Ok(Some(potential_path.to_string_lossy().to_string()));
-        }
-        
-        // Also search one level deep in common directories
-        if let Ok(entries) = std::fs::read_dir(&location) {
-            for entry in entries.take(50) { // Limit search to prevent performance issues
-                if let Ok(entry) = entry {
-                    if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
-                        let nested_path = entry.path().join(&target_name);
-                        if nested_path.is_dir() {
-                            return Ok(Some(nested_path.to_string_lossy().to_string()));
-                        }
-                    }
-                }
-            }
-        }
-    }
+    let _timing = latency::track("find_path_in_common_locations");
+    // Only an exact name this close is a safe correction; anything further is for the search panel
+    crate::system::fs_tasks::run(move || {
+        crate::system::path_search::nearest_named(&target_name, std::path::Path::new(&current_working_dir))
+            .map(|path| path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Search directories by name, ranked by match quality and how often they were visited
+#[tauri::command]
+pub async fn search_paths(
+    state: State<'_, AppState>,
+    query: String,
+    current_working_dir: String,
+    options: Option<crate::system::path_search::PathSearchOptions>,
+) -> Result<Vec<crate::system::path_search::PathMatch>, String> {
+    let _timing = latency::track("search_paths");
+    search_directories(&state, query, current_working_dir, options.unwrap_or_default()).await
+}
+
+async fn search_directories(
+    state: &State<'_, AppState>,
+    query: String,
+    current_working_dir: String,
+    options: crate::system::path_search::PathSearchOptions,
+) -> Result<Vec<crate::system::path_search::PathMatch>, String> {
+    let frecency = state.inner().terminal_manager.lock().await.directory_scores();
+    let mut roots = vec![PathBuf::from(&current_working_dir)];
+    roots.extend(dirs::home_dir());
+    roots.extend([PathBuf::from("/usr/local"), PathBuf::from("/opt")]);
     
-    Ok(None)
+    let timeout = crate::system::path_search::SEARCH_BUDGET + std::time::Duration::from_secs(1);
+    crate::system::fs_tasks::run_with_timeout(timeout, move || {
+        crate::system::path_search::search(&query, &roots, &frecency, &options)
+    })
+    .await
 }
 
 /// Validate if a specific path exists and return corrected path
@@ -605,6 +1781,7 @@ pub async fn validate_and_correct_path(
     current_working_dir: String,
     frequent_directories: Vec<String>,
 ) -> Result<Option<String>, String> {
+    let _timing = latency::track("validate_and_correct_path");
     let expanded_path = if path.starts_with('~') {
         if let Some(home_dir) = dirs::home_dir() {
             path.replacen("~", home_dir.to_string_lossy().as_ref(), 1)
@@ -636,7 +1813,7 @@ pub async fn validate_and_correct_path(
         }
     }
     
-    // Last resort: search in common locations
+    // Last resort: the working directory and its parents
     find_path_in_common_locations(path_name.to_string(), current_working_dir).await
 }
 
@@ -670,6 +1847,7 @@ pub struct RuntimeInfo {
 pub async fn get_repo_info(
     path: String,
 ) -> Result<RepoInfo, String> {
+    let _timing = latency::track("get_repo_info");
     let working_dir = path;
 
     let mut repo_info = RepoInfo {
@@ -753,6 +1931,7 @@ pub async fn get_repo_info(
 /// Get runtime/language version information
 #[tauri::command]
 pub async fn get_runtime_info(path: String) -> Result<RuntimeInfo, String> {
+    let _timing = latency::track("get_runtime_info");
     let working_dir = path;
     
     let mut runtime_info = RuntimeInfo {
@@ -945,6 +2124,7 @@ fn parse_ahead_behind(output: &str) -> Option<(i32, i32)> {
 pub async fn initialize_ml_system(
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    let _timing = latency::track("initialize_ml_system");
     let mut model_manager = state.inner().model_manager.lock().await;
     
     match model_manager.load_model().await {
@@ -960,10 +2140,19 @@ pub struct DirectoryInfo {
     is_directory: bool,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct ChildDirectories {
+    entries: Vec<DirectoryInfo>,
+    /// Entries in the directory, more than `entries` holds when it was cut off
+    total: usize,
+    truncated: bool,
+}
+
 /// Get parent directories for navigation
 #[tauri::command]
 pub async fn get_parent_directories(current_path: String) -> Result<Vec<DirectoryInfo>, String> {
     use std::path::Path;
+    let _timing = latency::track("get_parent_directories");
     
     let path = Path::new(&current_path);
     let mut parents = Vec::new();
@@ -996,47 +2185,49 @@ pub async fn get_parent_directories(current_path: String) -> Result<Vec<Director
     Ok(parents)
 }
 
-/// Get child directories and files for navigation
+/// Get child directories and files for navigation, directories first.
+/// Capped at one full page and marked `truncated` beyond it; `list_directory` pages through larger directories
 #[tauri::command]
-pub async fn get_child_directories(current_path: String) -> Result<Vec<DirectoryInfo>, String> {
-    use std::fs;
-    use std::path::Path;
-    
-    let path = Path::new(&current_path);
-    let mut children = Vec::new();
-    
-    match fs::read_dir(path) {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let entry_path = entry.path();
-                    if let Some(name) = entry_path.file_name() {
-                        let name_str = name.to_string_lossy().to_string();
-                        // Skip hidden files and directories (starting with .)
-                        if !name_str.starts_with('.') {
-                            children.push(DirectoryInfo {
-                                name: name_str,
-                                path: entry_path.to_string_lossy().to_string(),
-                                is_directory: entry_path.is_dir(),
-                            });
-                        }
-                    }
-                }
-            }
-        }
-        Err(e) => return Err(format!("Failed to read directory: {}", e)),
-    }
+pub async fn get_child_directories(current_path: String, include_hidden: Option<bool>) -> Result<ChildDirectories, String> {
+    use crate::system::dir_listing::{ListingOptions, MAX_PAGE_SIZE};
+    let _timing = latency::track("get_child_directories");
     
-    // Sort with directories first, then files, both alphabetically
-    children.sort_by(|a, b| {
-        match (a.is_directory, b.is_directory) {
-            (true, false) => std::cmp::Ordering::Less,    // Directories first
-            (false, true) => std::cmp::Ordering::Greater, // Files second
-            _ => a.name.cmp(&b.name),                      // Alphabetical within same type
-        }
-    });
+    let options = ListingOptions {
+        limit: MAX_PAGE_SIZE,
+        include_hidden: include_hidden.unwrap_or(false),
+        ..Default::default()
+    };
+    let page = crate::system::fs_tasks::run(move || crate::system::dir_listing::list_page(std::path::Path::new(&current_path), &options))
+        .await?
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
     
-    Ok(children)
+    Ok(ChildDirectories {
+        entries: page.entries
+            .into_iter()
+            .map(|entry| DirectoryInfo { name: entry.name, path: entry.path, is_directory: entry.is_directory })
+            .collect(),
+        total: page.total,
+        truncated: page.next_cursor.is_some(),
+    })
+}
+
+/// One page of a directory listing with sorting, hidden files and metadata as options
+#[tauri::command]
+pub async fn list_directory(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<crate::system::dir_listing::ListingOptions>,
+    session_id: Option<String>,
+) -> Result<crate::system::dir_listing::DirectoryPage, String> {
+    let _timing = latency::track("list_directory");
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    let options = options.unwrap_or_default();
+    // Size and date sorts stat every entry, which takes a while on large directories
+    crate::system::fs_tasks::run_with_timeout(std::time::Duration::from_secs(15), move || {
+        crate::system::dir_listing::list_page(&path, &options)
+    })
+    .await?
+    .map_err(|e| format!("Failed to read directory: {}", e))
 }
 
 /// Change current working directory
@@ -1046,10 +2237,11 @@ pub async fn change_directory(
     session_id: String,
     new_path: String,
 ) -> Result<String, String> {
+    let _timing = latency::track("change_directory");
     let mut terminal_manager = state.inner().terminal_manager.lock().await;
     
     // Execute cd command in the terminal
-    let command = format!("cd \"{}\"", new_path);
+    let command = format!("cd {}", crate::terminal::shell_quote::quote_arg(&new_path));
     match terminal_manager.execute_command(&session_id, &command).await {
         Ok(_) => Ok(new_path),
         Err(e) => Err(format!("Failed to change directory: {}", e)),
@@ -1064,8 +2256,10 @@ pub async fn execute_file(
     file_path: String,
 ) -> Result<String, String> {
     use std::path::Path;
+    let _timing = latency::track("execute_file");
     
     let path = Path::new(&file_path);
+    let quoted = crate::terminal::shell_quote::quote_arg(&file_path);
     let mut terminal_manager = state.inner().terminal_manager.lock().await;
     
     if let Some(extension) = path.extension() {
@@ -1073,34 +2267,34 @@ pub async fn execute_file(
         
         let command = match ext.as_str() {
             // Executable scripts
-            "sh" | "bash" => format!("bash \"{}\"", file_path),
-            "py" => format!("python \"{}\"", file_path),
-            "js" => format!("node \"{}\"", file_path),
-            "ts" => format!("npx ts-node \"{}\"", file_path),
-            "rs" => format!("cargo run --manifest-path \"{}\"", file_path),
+            "sh" | "bash" => format!("bash {}", quoted),
+            "py" => format!("python {}", quoted),
+            "js" => format!("node {}", quoted),
+            "ts" => format!("npx ts-node {}", quoted),
+            "rs" => format!("cargo run --manifest-path {}", quoted),
             
             // Text files - open with default editor
             "txt" | "md" | "json" | "yaml" | "yml" | "toml" | "xml" | "html" | "css" | "scss" => {
-                format!("open \"{}\"", file_path)
+                format!("open {}", quoted)
             },
             
             // Source code files - open with default editor
             "jsx" | "tsx" | "vue" | "svelte" | "php" | "rb" | "go" | "java" | "cpp" | "c" | "h" => {
-                format!("open \"{}\"", file_path)
+                format!("open {}", quoted)
             },
             
             // Configuration files
             "env" | "gitignore" | "dockerfile" | "makefile" => {
-                format!("open \"{}\"", file_path)
+                format!("open {}", quoted)
             },
             
             // Images and media - open with default application
             "png" | "jpg" | "jpeg" | "gif" | "svg" | "pdf" | "mp4" | "mov" | "mp3" => {
-                format!("open \"{}\"", file_path)
+                format!("open {}", quoted)
             },
             
             // Default: try to open with system default application
-            _ => format!("open \"{}\"", file_path),
+            _ => format!("open {}", quoted),
         };
         
         match terminal_manager.execute_command(&session_id, &command).await {
@@ -1118,20 +2312,20 @@ pub async fn execute_file(
                     let permissions = metadata.permissions();
                     if permissions.mode() & 0o111 != 0 {
                         // File is executable
-                        format!("\"{}\"", file_path)
+                        quoted.to_string()
                     } else {
-                        format!("open \"{}\"", file_path)
+                        format!("open {}", quoted)
                     }
                 } else {
-                    format!("open \"{}\"", file_path)
+                    format!("open {}", quoted)
                 }
             }
             #[cfg(not(unix))]
             {
-                format!("\"{}\"", file_path)
+                quoted.to_string()
             }
         } else {
-            format!("open \"{}\"", file_path)
+            format!("open {}", quoted)
         };
         
         match terminal_manager.execute_command(&session_id, &command).await {
@@ -1149,13 +2343,25 @@ pub async fn get_enhanced_system_context(
     state: State<'_, AppState>,
     session_id: String,
 ) -> Result<crate::ai::enhanced_context::SystemContext, String> {
+    let _timing = latency::track("get_enhanced_system_context");
     let terminal_manager = state.inner().terminal_manager.lock().await;
     let working_dir = terminal_manager.get_session(&session_id)
         .map(|session| session.working_directory.clone())
         .unwrap_or_else(|| std::env::current_dir().unwrap().to_string_lossy().to_string());
+    let remote = terminal_manager.get_session(&session_id).and_then(|session| session.remote.as_ref()).map(|remote| remote.label());
+    
+    drop(terminal_manager);
+    
+    if let Some(host) = remote {
+        return Ok(crate::ai::enhanced_context::SystemContext::remote(&working_dir, host));
+    }
     
     let mut context_provider = crate::ai::enhanced_context::EnhancedContextProvider::new();
-    context_provider.get_system_context(&working_dir).await
+    let context = context_provider.get_system_context(&working_dir).await?;
+    
+    let mut terminal_manager = state.inner().terminal_manager.lock().await;
+    terminal_manager.update_ci_status(&working_dir, context.ci_status.clone());
+    Ok(context)
 }
 
 /// Get learned workflow patterns
@@ -1163,6 +2369,7 @@ pub async fn get_enhanced_system_context(
 pub async fn get_learned_workflow_patterns(
     _state: State<'_, AppState>,
 ) -> Result<Vec<crate::ai::enhanced_context::WorkflowPattern>, String> {
+    let _timing = latency::track("get_learned_workflow_patterns");
     // This would integrate with the learning engine to get patterns
     // For now, return empty vector as placeholder
     Ok(vec![])
@@ -1175,6 +2382,7 @@ pub async fn get_recent_command_sequence(
     _session_id: String,
     limit: usize,
 ) -> Result<Vec<String>, String> {
+    let _timing = latency::track("get_recent_command_sequence");
     let terminal_manager = state.inner().terminal_manager.lock().await;
     let history = terminal_manager.get_command_history(Some(limit));
     Ok(history.into_iter().map(|cmd| cmd.command.clone()).collect())
@@ -1186,16 +2394,3084 @@ pub async fn get_proactive_suggestions(
     state: State<'_, AppState>,
     session_id: String,
 ) -> Result<Vec<crate::ai::enhanced_context::ProactiveSuggestion>, String> {
+    use crate::ai::suggestion_throttle::ThrottleDecision;
+    let _timing = latency::track("get_proactive_suggestions");
+    
     let terminal_manager = state.inner().terminal_manager.lock().await;
     let working_dir = terminal_manager.get_session(&session_id)
         .map(|session| session.working_directory.clone())
         .unwrap_or_else(|| std::env::current_dir().unwrap().to_string_lossy().to_string());
+    // This machine's state suggests nothing for a session on another one
+    if terminal_manager.get_session(&session_id).is_some_and(|session| session.remote.is_some()) {
+        return Ok(Vec::new());
+    }
+    
+    drop(terminal_manager);
     
     let mut context_provider = crate::ai::enhanced_context::EnhancedContextProvider::new();
     let context = context_provider.get_system_context(&working_dir).await
         .map_err(|e| format!("Failed to get system context: {}", e))?;
     
-    Ok(context_provider.get_proactive_suggestions(&context).await)
+    let mut suggestions = context_provider.get_proactive_suggestions(&context).await;
+    state.inner().terminal_manager.lock().await.update_ci_status(&working_dir, context.ci_status.clone());
+    
+    // Include high-severity dependency findings from the latest audit
+    let auditor = state.inner().dependency_auditor.lock().await;
+    let settings = crate::ai::enhanced_context::SuggestionSettings::load();
+    let repo = crate::ai::enhanced_context::repo_key(&working_dir);
+    suggestions.extend(
+        auditor.proactive_suggestions(&working_dir)
+            .into_iter()
+            .filter(|s| !settings.is_suppressed(&repo, &s.trigger_condition))
+    );
+    drop(auditor);
+    
+    // Coverage that fell below the project's baseline in the latest test run
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    if let Some(latest) = terminal_manager.latest_coverage(&working_dir) {
+        let root = crate::project::symbols::project_root(std::path::Path::new(&working_dir));
+        if let Some(baseline) = crate::project::coverage::CoverageBaselines::load().get(&root.to_string_lossy()) {
+            suggestions.extend(
+                crate::project::coverage::proactive_suggestions(latest, baseline)
+                    .into_iter()
+                    .filter(|s| !settings.is_suppressed(&repo, &s.trigger_condition))
+            );
+        }
+    }
+    drop(terminal_manager);
+    suggestions.sort_by(|a, b| b.priority.partial_cmp(&a.priority).unwrap_or(std::cmp::Ordering::Equal));
+    
+    // Kinds of suggestion the user rarely acts on come less often; a reduced kind shows only its top suggestion
+    let mut throttle = state.inner().suggestion_throttle.lock().await;
+    let mut reduced = std::collections::HashSet::new();
+    suggestions.retain(|s| match throttle.decide(&s.suggestion_type) {
+        ThrottleDecision::Show => true,
+        ThrottleDecision::Reduce => reduced.insert(s.suggestion_type.clone()),
+        ThrottleDecision::Suppress => false,
+    });
+    for suggestion in &suggestions {
+        throttle.record_shown(&suggestion.suggestion_type, &suggestion.commands);
+    }
+    drop(throttle);
+    
+    let mut provenance = state.inner().provenance.lock().await;
+    for suggestion in &mut suggestions {
+        let mut record = Provenance::new(SuggestionKind::Proactive, &suggestion.description, &format!("{} check", suggestion.suggestion_type))
+            .in_session(&session_id, &working_dir);
+        record.trigger = Some(suggestion.trigger_condition.clone());
+        record.confidence = Some(suggestion.priority);
+        record.reasoning = (!suggestion.commands.is_empty()).then(|| format!("Suggests: {}", suggestion.commands.join("; ")));
+        if reduced.contains(&suggestion.suggestion_type) {
+            record.how_to_change.push("This kind is rarely acted on, so only its top suggestion is shown".to_string());
+        }
+        suggestion.id = Some(provenance.record(record));
+    }
+    
+    Ok(suggestions)
+}
+
+/// Dismiss a proactive suggestion for a repo, or snooze it for a number of minutes
+#[tauri::command]
+pub async fn dismiss_proactive_suggestion(
+    state: State<'_, AppState>,
+    repo_path: String,
+    trigger_condition: String,
+    snooze_minutes: Option<u64>,
+    suggestion_type: Option<String>,
+) -> Result<(), String> {
+    use crate::ai::enhanced_context::{repo_key, SuggestionSettings};
+    let _timing = latency::track("dismiss_proactive_suggestion");
+    
+    if let Some(suggestion_type) = suggestion_type {
+        state.inner().suggestion_throttle.lock().await.record_dismissed(&suggestion_type);
+    }
+    let mut settings = SuggestionSettings::load();
+    settings.snooze(&repo_key(&repo_path), &trigger_condition, snooze_minutes);
+    settings.save()
+}
+
+/// Configure protected branches and the stale-branch threshold for git suggestions
+#[tauri::command]
+pub async fn update_git_suggestion_settings(
+    protected_branches: Option<Vec<String>>,
+    stale_branch_days: Option<u32>,
+) -> Result<crate::ai::enhanced_context::SuggestionSettings, String> {
+    let _timing = latency::track("update_git_suggestion_settings");
+    let mut settings = crate::ai::enhanced_context::SuggestionSettings::load();
+    if let Some(branches) = protected_branches {
+        settings.protected_branches = branches;
+    }
+    if let Some(days) = stale_branch_days {
+        settings.stale_branch_days = days.max(1);
+    }
+    settings.save()?;
+    Ok(settings)
+}
+
+/// Run dependency vulnerability and outdated-package checks for a project
+#[tauri::command]
+pub async fn run_dependency_audit(
+    state: State<'_, AppState>,
+    project_path: String,
+) -> Result<crate::project::audit::AuditReport, String> {
+    let _timing = latency::track("run_dependency_audit");
+    if !PathBuf::from(&project_path).is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+    
+    // Run the tools without holding the auditor lock
+    let report = crate::project::audit::run_audit(&project_path).await;
+    state.inner().dependency_auditor.lock().await.store_report(report.clone());
+    Ok(report)
+}
+
+/// Get the most recent dependency audit for a project
+#[tauri::command]
+pub async fn get_dependency_audit(
+    state: State<'_, AppState>,
+    project_path: String,
+) -> Result<Option<crate::project::audit::AuditReport>, String> {
+    let _timing = latency::track("get_dependency_audit");
+    let auditor = state.inner().dependency_auditor.lock().await;
+    Ok(auditor.get_report(&project_path).cloned())
+}
+
+/// Schedule periodic dependency audits for a project
+#[tauri::command]
+pub async fn schedule_dependency_audit(
+    state: State<'_, AppState>,
+    project_path: String,
+    interval_hours: Option<u64>,
+) -> Result<(), String> {
+    let _timing = latency::track("schedule_dependency_audit");
+    if !PathBuf::from(&project_path).is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+    
+    let mut auditor = state.inner().dependency_auditor.lock().await;
+    auditor.schedule(&project_path, interval_hours);
+    Ok(())
+}
+
+// Forge Integration Commands (GitHub / GitLab)
+
+/// Store or remove the API token for a forge ("github" or "gitlab")
+#[tauri::command]
+pub async fn set_forge_token(
+    forge: String,
+    token: Option<String>,
+) -> Result<(), String> {
+    use crate::integrations::forge::ForgeKind;
+    let _timing = latency::track("set_forge_token");
+    
+    let kind = match forge.to_lowercase().as_str() {
+        "github" => ForgeKind::GitHub,
+        "gitlab" => ForgeKind::GitLab,
+        other => return Err(format!("Unsupported forge: {}", other)),
+    };
+    
+    let mut secrets = crate::integrations::secrets::SecretsStore::load();
+    match token.filter(|t| !t.trim().is_empty()) {
+        Some(token) => secrets.set(kind.token_key(), token.trim()),
+        None => secrets.remove(kind.token_key()),
+    }
+}
+
+/// List pull requests (GitHub) or merge requests (GitLab) for a repository
+#[tauri::command]
+pub async fn list_pull_requests(
+    repo_path: String,
+    state: Option<String>,
+) -> Result<Vec<crate::integrations::forge::PullRequest>, String> {
+    let _timing = latency::track("list_pull_requests");
+    let forge = crate::integrations::forge::Forge::for_repo(&repo_path)?;
+    forge.list_pull_requests(state.as_deref()).await
+}
+
+/// Open a pull request for the current branch, describing it from its commits
+#[tauri::command]
+pub async fn create_pull_request(
+    state: State<'_, AppState>,
+    repo_path: String,
+    title: Option<String>,
+    base: Option<String>,
+    draft: Option<bool>,
+) -> Result<String, String> {
+    use crate::integrations::forge;
+    let _timing = latency::track("create_pull_request");
+    
+    let forge_client = forge::Forge::for_repo(&repo_path)?;
+    let base = base.unwrap_or_else(|| forge::default_base_branch(&repo_path));
+    let commits = forge::branch_commits(&repo_path, &base);
+    if commits.is_empty() {
+        return Err(format!("No commits on this branch that are not in '{}'", base));
+    }
+    
+    // git log lists newest first; the oldest commit usually names the change
+    let title = title.unwrap_or_else(|| commits.last().cloned().unwrap_or_default());
+    let commit_list = commits.iter().rev()
+        .map(|commit| format!("- {}", commit))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let summary = {
+        let model_manager = state.inner().model_manager.lock().await;
+        let prompt = format!("Summarize these commits as a pull request description: {}", commits.join("; "));
+        let response = model_manager.generate_response(&prompt, Some(&commit_list)).await;
+        if response.confidence > 0.0 { Some(response.text) } else { None }
+    };
+    
+    let body = match summary {
+        Some(summary) => format!("## Summary\n\n{}\n\n## Commits\n\n{}", summary.trim(), commit_list),
+        None => format!("## Commits\n\n{}", commit_list),
+    };
+    
+    forge_client.create_pull_request(&title, &body, &base, draft.unwrap_or(false)).await
+}
+
+/// Latest CI pipeline status for the session's current branch
+#[tauri::command]
+pub async fn get_ci_status(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Option<crate::integrations::ci::CiStatus>, String> {
+    let _timing = latency::track("get_ci_status");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let working_dir = terminal_manager.get_session(&session_id)
+        .map(|session| session.working_directory.clone())
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    drop(terminal_manager);
+    
+    let status = crate::integrations::ci::fetch_branch_status(&working_dir).await?;
+    state.inner().terminal_manager.lock().await.update_ci_status(&working_dir, status.clone());
+    Ok(status)
+}
+
+/// Check out a pull request / merge request locally by number
+#[tauri::command]
+pub async fn checkout_pr(
+    repo_path: String,
+    number: u64,
+) -> Result<String, String> {
+    let _timing = latency::track("checkout_pr");
+    let forge = crate::integrations::forge::Forge::for_repo(&repo_path)?;
+    forge.checkout_pull_request(number).await
+}
+
+// Script Authoring Commands
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GeneratedScript {
+    pub language: crate::tools::script_lint::ScriptLanguage,
+    pub script: String,
+    pub issues: Vec<crate::tools::script_lint::LintIssue>,
+    pub linter: String,
+    pub suggested_file_name: String,
+}
+
+/// Generate a bash/PowerShell script from a description and lint it
+#[tauri::command]
+pub async fn ai_generate_script(
+    state: State<'_, AppState>,
+    description: String,
+    language: Option<String>,
+) -> Result<GeneratedScript, String> {
+    use crate::tools::script_lint::{build_script, lint_script, ScriptLanguage};
+    let _timing = latency::track("ai_generate_script");
+    
+    let language = match language {
+        Some(language) => ScriptLanguage::parse(&language)?,
+        None if cfg!(target_os = "windows") => ScriptLanguage::PowerShell,
+        None => ScriptLanguage::Bash,
+    };
+    let shell_name = match language {
+        ScriptLanguage::Bash => "bash",
+        ScriptLanguage::PowerShell => "PowerShell",
+    };
+    
+    let response = {
+        let model_manager = state.inner().model_manager.lock().await;
+        let prompt = format!("Write a {} script that will: {}", shell_name, description);
+        model_manager.generate_response(&prompt, None).await
+    };
+    if response.confidence <= 0.0 {
+        return Err(response.reasoning.unwrap_or_else(|| "AI model is not available".to_string()));
+    }
+    
+    let script = build_script(&description, &response.text, language);
+    let (issues, linter) = lint_script(&script, language).await;
+    
+    let slug: String = description.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(4)
+        .collect::<Vec<_>>()
+        .join("-");
+    
+    Ok(GeneratedScript {
+        language,
+        script,
+        issues,
+        linter: linter.to_string(),
+        suggested_file_name: format!("{}.{}", if slug.is_empty() { "script".to_string() } else { slug }, language.extension()),
+    })
+}
+
+/// Lint an edited script again before saving
+#[tauri::command]
+pub async fn lint_script(
+    script: String,
+    language: String,
+) -> Result<Vec<crate::tools::script_lint::LintIssue>, String> {
+    let _timing = latency::track("lint_script");
+    let language = crate::tools::script_lint::ScriptLanguage::parse(&language)?;
+    Ok(crate::tools::script_lint::lint_script(&script, language).await.0)
+}
+
+/// Save a generated script as an executable file inside the project.
+/// Nothing is written until the user has confirmed the target path.
+#[tauri::command]
+pub async fn save_generated_script(
+    project_path: String,
+    file_name: String,
+    content: String,
+    confirmed: bool,
+    overwrite: Option<bool>,
+) -> Result<String, String> {
+    let _timing = latency::track("save_generated_script");
+    let relative = PathBuf::from(&file_name);
+    if relative.is_absolute() || relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err("Script path must be relative to the project and stay inside it".to_string());
+    }
+    
+    let project = PathBuf::from(&project_path);
+    if !project.is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+    let target = project.join(&relative);
+    
+    if !confirmed {
+        return Err(format!("Confirmation required to write {}", target.display()));
+    }
+    if target.exists() && !overwrite.unwrap_or(false) {
+        return Err(format!("{} already exists", target.display()));
+    }
+    
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    tokio::fs::write(&target, content).await.map_err(|e| format!("Failed to write script: {}", e))?;
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o755)).await
+            .map_err(|e| format!("Failed to make script executable: {}", e))?;
+    }
+    
+    Ok(target.to_string_lossy().to_string())
+}
+
+// Regex Commands
+
+/// Test a regex against sample text, line by line
+#[tauri::command]
+pub async fn test_regex(
+    pattern: String,
+    sample: String,
+    flavor: Option<String>,
+) -> Result<crate::tools::regex_tester::RegexTestResult, String> {
+    use crate::tools::regex_tester::RegexFlavor;
+    let _timing = latency::track("test_regex");
+    
+    let flavor = flavor.map(|f| RegexFlavor::parse(&f)).transpose()?.unwrap_or(RegexFlavor::Rust);
+    Ok(crate::tools::regex_tester::test_regex(&pattern, &sample, flavor))
+}
+
+/// Turn a description ("lines with an IPv4 address") into a verified regex
+#[tauri::command]
+pub async fn ai_build_regex(
+    state: State<'_, AppState>,
+    description: String,
+    flavor: Option<String>,
+    sample: Option<String>,
+) -> Result<crate::tools::regex_tester::RegexSuggestion, String> {
+    use crate::tools::regex_tester::{self, RegexFlavor, RegexSuggestion};
+    let _timing = latency::track("ai_build_regex");
+    
+    let flavor = flavor.map(|f| RegexFlavor::parse(&f)).transpose()?.unwrap_or(RegexFlavor::Posix);
+    if let Some(suggestion) = regex_tester::build_known_regex(&description, flavor) {
+        return Ok(suggestion);
+    }
+    
+    // Fall back to the model and only accept a pattern that compiles
+    let response = {
+        let model_manager = state.inner().model_manager.lock().await;
+        let prompt = format!("Write a regular expression that matches: {}", description);
+        model_manager.generate_response(&prompt, sample.as_deref()).await
+    };
+    let pattern = response.text.trim().trim_matches('`').replace("🤖 ", "");
+    let result = regex_tester::test_regex(&pattern, sample.as_deref().unwrap_or(""), flavor);
+    if !result.valid {
+        return Err(format!("Could not build a valid regex for '{}'", description));
+    }
+    
+    // Lines of the sample the pattern matched become positive test cases
+    let cases = sample.as_deref().unwrap_or("").lines()
+        .enumerate()
+        .map(|(index, line)| (line.to_string(), result.matched_lines.contains(&(index + 1))))
+        .collect();
+    let (test_cases, _) = regex_tester::verify(&pattern, flavor, cases);
+    
+    Ok(RegexSuggestion {
+        explanation: response.reasoning.unwrap_or_else(|| "Generated by the local model".to_string()),
+        grep_command: result.grep_command,
+        pattern,
+        flavor,
+        test_cases,
+        // Model output has no independent test cases to check against
+        verified: false,
+    })
+}
+
+// Data File Commands
+
+/// Preview the first rows of a CSV/JSON/Parquet file
+#[tauri::command]
+pub async fn preview_data_file(
+    path: String,
+    rows: Option<u32>,
+) -> Result<crate::tools::data_preview::DataTable, String> {
+    let _timing = latency::track("preview_data_file");
+    let rows = rows.unwrap_or(50).clamp(1, 1000);
+    tokio::task::spawn_blocking(move || crate::tools::data_preview::preview(&path, rows))
+        .await
+        .map_err(|e| format!("Preview task failed: {}", e))?
+}
+
+/// Query a data file with SQL, optionally exporting the full result
+#[tauri::command]
+pub async fn query_data_file(
+    path: String,
+    sql: String,
+    export_path: Option<String>,
+) -> Result<crate::tools::data_preview::DataTable, String> {
+    let _timing = latency::track("query_data_file");
+    tokio::task::spawn_blocking(move || {
+        crate::tools::data_preview::query(&path, &sql, export_path.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Query task failed: {}", e))?
+}
+
+// Database Commands
+
+/// Save a database connection profile; the password goes to the secrets store
+#[tauri::command]
+pub async fn save_db_connection(
+    state: State<'_, AppState>,
+    profile: crate::database::ConnectionProfile,
+    password: Option<String>,
+) -> Result<(), String> {
+    let _timing = latency::track("save_db_connection");
+    let mut database_manager = state.inner().database_manager.lock().await;
+    database_manager.save_profile(profile, password)
+}
+
+#[tauri::command]
+pub async fn list_db_connections(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::database::ConnectionProfile>, String> {
+    let _timing = latency::track("list_db_connections");
+    let database_manager = state.inner().database_manager.lock().await;
+    Ok(database_manager.list_profiles())
+}
+
+#[tauri::command]
+pub async fn remove_db_connection(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<(), String> {
+    let _timing = latency::track("remove_db_connection");
+    let mut database_manager = state.inner().database_manager.lock().await;
+    database_manager.remove_profile(&name)
+}
+
+/// Get (or open) the pool for a saved connection without holding the lock while connecting
+async fn database_pool(
+    state: &State<'_, AppState>,
+    name: &str,
+) -> Result<(crate::database::DbPool, crate::database::ConnectionProfile), String> {
+    let database_manager = state.inner().database_manager.lock().await;
+    let profile = database_manager.get_profile(name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown connection: {}", name))?;
+    if let Some(pool) = database_manager.cached_pool(name) {
+        return Ok((pool, profile));
+    }
+    drop(database_manager);
+    
+    let pool = crate::database::connect(&profile).await?;
+    state.inner().database_manager.lock().await.store_pool(name, pool.clone());
+    Ok((pool, profile))
+}
+
+/// Run SQL against a saved connection. Statements that modify data need confirmation.
+#[tauri::command]
+pub async fn run_query(
+    state: State<'_, AppState>,
+    connection: String,
+    sql: String,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    confirmed: Option<bool>,
+) -> Result<crate::database::query::QueryResult, String> {
+    let _timing = latency::track("run_query");
+    crate::database::query::single_statement(&sql)?;
+    let (pool, profile) = database_pool(&state, &connection).await?;
+    
+    if !crate::database::query::is_read_only(&sql) {
+        if profile.read_only {
+            return Err(format!("Connection '{}' is read-only", connection));
+        }
+        if !confirmed.unwrap_or(false) {
+            return Err("This statement modifies data; review it and confirm to run".to_string());
+        }
+    }
+    
+    crate::database::query::run_query(&pool, &sql, page.unwrap_or(0), page_size.unwrap_or(100)).await
+}
+
+/// Tables and columns of a connection (cached until refreshed)
+#[tauri::command]
+pub async fn get_database_schema(
+    state: State<'_, AppState>,
+    connection: String,
+    refresh: Option<bool>,
+) -> Result<Vec<crate::database::query::TableSchema>, String> {
+    let _timing = latency::track("get_database_schema");
+    if !refresh.unwrap_or(false) {
+        if let Some(schema) = state.inner().database_manager.lock().await.cached_schema(&connection) {
+            return Ok(schema);
+        }
+    }
+    
+    let (pool, profile) = database_pool(&state, &connection).await?;
+    let schema = crate::database::query::introspect_schema(&pool, profile.kind).await?;
+    state.inner().database_manager.lock().await.store_schema(&connection, schema.clone());
+    Ok(schema)
+}
+
+/// Table/column completions for the SQL editor
+#[tauri::command]
+pub async fn get_sql_completions(
+    state: State<'_, AppState>,
+    connection: String,
+    prefix: String,
+) -> Result<Vec<String>, String> {
+    let _timing = latency::track("get_sql_completions");
+    let schema = get_database_schema(state, connection, None).await?;
+    Ok(crate::database::query::completions(&schema, &prefix))
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SqlDraft {
+    pub sql: String,
+    pub read_only: bool,
+    pub source: String,
+}
+
+/// Translate a description into SQL for review; it is never executed here
+#[tauri::command]
+pub async fn ai_generate_sql(
+    state: State<'_, AppState>,
+    connection: String,
+    description: String,
+) -> Result<SqlDraft, String> {
+    let _timing = latency::track("ai_generate_sql");
+    let schema = get_database_schema(state.clone(), connection, None).await?;
+    
+    let (sql, source) = match crate::database::query::draft_from_schema(&description, &schema) {
+        Some(sql) => (sql, "schema"),
+        None => {
+            let schema_context = schema.iter()
+                .map(|table| format!(
+                    "{}({})",
+                    table.name,
+                    table.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ")
+                ))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let model_manager = state.inner().model_manager.lock().await;
+            let prompt = format!("Write a SQL query that will: {}", description);
+            let response = model_manager.generate_response(&prompt, Some(&schema_context)).await;
+            (response.text.trim().trim_matches('`').replace("🤖 ", ""), "model")
+        }
+    };
+    
+    if sql.is_empty() {
+        return Err(format!("Could not generate SQL for '{}'", description));
+    }
+    Ok(SqlDraft {
+        read_only: crate::database::query::is_read_only(&sql),
+        sql,
+        source: source.to_string(),
+    })
+}
+
+// Redis and Queue Inspector Commands
+
+const DEFAULT_REDIS_URL: &str = "redis://127.0.0.1:6379";
+
+/// Redis and message queue services declared in the project's docker-compose file
+#[tauri::command]
+pub async fn list_project_services(
+    project_path: String,
+) -> Result<Vec<crate::tools::compose::ComposeService>, String> {
+    let _timing = latency::track("list_project_services");
+    Ok(crate::tools::compose::detect_services(std::path::Path::new(&project_path)))
+}
+
+#[tauri::command]
+pub async fn redis_scan_keys(
+    url: Option<String>,
+    pattern: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::tools::redis_inspector::RedisKeyInfo>, String> {
+    let _timing = latency::track("redis_scan_keys");
+    crate::tools::redis_inspector::scan_keys(
+        url.as_deref().unwrap_or(DEFAULT_REDIS_URL),
+        pattern.as_deref().unwrap_or("*"),
+        limit.unwrap_or(200).clamp(1, 10_000),
+    ).await
+}
+
+#[tauri::command]
+pub async fn redis_get_key(
+    url: Option<String>,
+    key: String,
+) -> Result<crate::tools::redis_inspector::RedisValue, String> {
+    let _timing = latency::track("redis_get_key");
+    crate::tools::redis_inspector::get_key(url.as_deref().unwrap_or(DEFAULT_REDIS_URL), &key).await
+}
+
+#[tauri::command]
+pub async fn redis_memory_info(
+    url: Option<String>,
+) -> Result<crate::tools::redis_inspector::RedisMemoryInfo, String> {
+    let _timing = latency::track("redis_memory_info");
+    crate::tools::redis_inspector::memory_info(url.as_deref().unwrap_or(DEFAULT_REDIS_URL)).await
+}
+
+/// Queues or topics of a RabbitMQ/Kafka service from the project's compose file
+#[tauri::command]
+pub async fn list_queues(
+    project_path: String,
+    service_name: String,
+) -> Result<Vec<crate::tools::queue_inspector::QueueInfo>, String> {
+    let _timing = latency::track("list_queues");
+    let service = crate::tools::compose::detect_services(std::path::Path::new(&project_path))
+        .into_iter()
+        .find(|service| service.name == service_name)
+        .ok_or_else(|| format!("No queue service named '{}' in docker-compose", service_name))?;
+    crate::tools::queue_inspector::list_queues(&project_path, &service).await
+}
+
+// Environment Diff Commands
+
+/// Snapshot a session's environment, or load one from a file when `source` is a path
+async fn environment_snapshot_for(
+    state: &State<'_, AppState>,
+    source: &str,
+) -> Result<crate::system::env_diff::EnvironmentSnapshot, String> {
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let session = terminal_manager.get_session(source)
+        .map(|session| (session.title.clone(), session.environment_vars.clone(), session.working_directory.clone()));
+    drop(terminal_manager);
+    
+    match session {
+        Some((title, variables, working_directory)) => {
+            Ok(crate::system::env_diff::capture(&title, &variables, &working_directory).await)
+        }
+        None if std::path::Path::new(source).is_file() => crate::system::env_diff::import(source),
+        None => Err(format!("No session or snapshot file named '{}'", source)),
+    }
+}
+
+/// Compare environment variables, PATH entries and tool versions of two
+/// sessions or exported snapshots
+#[tauri::command]
+pub async fn diff_environment(
+    state: State<'_, AppState>,
+    session_a: String,
+    session_b: String,
+) -> Result<crate::system::env_diff::EnvironmentDiff, String> {
+    let _timing = latency::track("diff_environment");
+    let a = environment_snapshot_for(&state, &session_a).await?;
+    let b = environment_snapshot_for(&state, &session_b).await?;
+    Ok(crate::system::env_diff::diff(&a, &b))
+}
+
+/// Save a session's environment to a file, e.g. to compare with another machine
+#[tauri::command]
+pub async fn export_environment_snapshot(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+) -> Result<crate::system::env_diff::EnvironmentSnapshot, String> {
+    let _timing = latency::track("export_environment_snapshot");
+    let snapshot = environment_snapshot_for(&state, &session_id).await?;
+    crate::system::env_diff::export(&snapshot, &path)?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub async fn import_environment_snapshot(
+    path: String,
+) -> Result<crate::system::env_diff::EnvironmentSnapshot, String> {
+    let _timing = latency::track("import_environment_snapshot");
+    crate::system::env_diff::import(&path)
+}
+
+/// Inspect a session's PATH for duplicates, shadowed binaries and broken symlinks
+#[tauri::command]
+pub async fn analyze_path(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<crate::system::path_inspector::PathAnalysis, String> {
+    let _timing = latency::track("analyze_path");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let path_var = session_id.as_deref()
+        .and_then(|id| terminal_manager.get_session(id))
+        .and_then(|session| session.environment_vars.get("PATH").cloned())
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    drop(terminal_manager);
+    
+    tokio::task::spawn_blocking(move || crate::system::path_inspector::analyze_path(&path_var))
+        .await
+        .map_err(|e| format!("PATH analysis failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn resolve_command(
+    state: State<'_, AppState>,
+    name: String,
+    session_id: Option<String>,
+) -> Result<crate::system::command_resolver::CommandResolution, String> {
+    let _timing = latency::track("resolve_command");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let path_var = session_id.as_deref()
+        .and_then(|id| terminal_manager.get_session(id))
+        .and_then(|session| session.environment_vars.get("PATH").cloned())
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    drop(terminal_manager);
+    
+    tokio::task::spawn_blocking(move || crate::system::command_resolver::resolve_command(&name, &path_var))
+        .await
+        .map_err(|e| format!("Command resolution failed: {}", e))
+}
+
+/// Help for a command from the cache or its man page, shared by the docs panel and flag
+/// completions; `explicit` (the user asked for help) may also run `<program> --help`
+async fn help_document(
+    state: &State<'_, AppState>,
+    command: &str,
+    session_id: Option<&str>,
+    explicit: bool,
+) -> Result<crate::tools::help_docs::HelpDocument, String> {
+    let words = crate::tools::help_docs::help_topic(command);
+    let topic = words.join(" ");
+    let program = words.first().cloned().unwrap_or_default();
+    {
+        let cache = state.inner().help_cache.lock().await;
+        if let Some(doc) = cache.get(&program, &topic) {
+            return Ok(doc);
+        }
+    }
+    
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let path_var = session_id
+        .and_then(|id| terminal_manager.get_session(id))
+        .and_then(|session| session.environment_vars.get("PATH").cloned())
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    drop(terminal_manager);
+    
+    let doc = crate::tools::help_docs::get_help(command, &path_var, explicit).await?;
+    state.inner().help_cache.lock().await.insert(program, topic, doc.clone());
+    Ok(doc)
+}
+
+#[tauri::command]
+pub async fn get_help(
+    state: State<'_, AppState>,
+    command: String,
+    session_id: Option<String>,
+) -> Result<crate::tools::help_docs::HelpDocument, String> {
+    let _timing = latency::track("get_help");
+    help_document(&state, &command, session_id.as_deref(), true).await
+}
+
+/// Flag combinations for a typed command (`tar `), ranked for the stated intent or one inferred
+/// from the working directory, from its tldr page and the model, each flag explained
+#[tauri::command]
+pub async fn ai_suggest_flags(
+    state: State<'_, AppState>,
+    command: String,
+    intent: Option<String>,
+    session_id: Option<String>,
+) -> Result<crate::ai::flag_suggestions::FlagSuggestions, String> {
+    use crate::ai::flag_suggestions::{self, FlagContext, FlagSuggestions};
+    let _timing = latency::track("ai_suggest_flags");
+    
+    let topic = flag_suggestions::topic(&command);
+    let name = topic.join(" ");
+    if name.is_empty() {
+        return Err("Type a command first".to_string());
+    }
+    let working_dir = session_working_directory(&state, session_id.as_deref()).await;
+    let (page, file_names) = {
+        let topic = topic.clone();
+        tokio::task::spawn_blocking(move || {
+            let file_names: Vec<String> = std::fs::read_dir(&working_dir)
+                .map(|entries| entries.flatten().take(500).map(|entry| entry.file_name().to_string_lossy().to_string()).collect())
+                .unwrap_or_default();
+            (crate::tools::tldr::find_page(&topic), file_names)
+        })
+        .await
+        .map_err(|e| format!("Flag lookup failed: {}", e))?
+    };
+    let help = help_document(&state, &command, session_id.as_deref(), false).await.ok();
+    let help_flags = help.as_ref().map(|doc| doc.flags.as_slice()).unwrap_or_default();
+    let previous = state.inner().terminal_manager.lock().await.get_command_history(Some(500))
+        .iter()
+        .filter(|execution| flag_suggestions::topic(&execution.command) == topic)
+        .map(|execution| execution.command.clone())
+        .collect();
+    let context = FlagContext { file_names, previous };
+    
+    let (mut options, intent, intent_inferred) = match &page {
+        Some(page) => flag_suggestions::rank(page, help_flags, &command, topic.len(), intent.as_deref(), &context),
+        None => (Vec::new(), intent.filter(|i| !i.trim().is_empty()), false),
+    };
+    
+    // The model is asked when the user said what they want, or when there is no page to go on
+    if !intent_inferred && (intent.is_some() || options.is_empty()) {
+        let synopsis = help.as_ref().and_then(|doc| doc.synopsis.as_deref());
+        let (prompt, model_context) = flag_suggestions::model_prompt(&name, intent.as_deref(), page.as_ref(), synopsis);
+        let response = state.inner().model_manager.lock().await.generate_response(&prompt, Some(&model_context)).await;
+        let explanation = response.reasoning.clone().unwrap_or_else(|| "Suggested by the local model".to_string());
+        let suggested = flag_suggestions::model_options(&response.text, &explanation, response.confidence, &name, help_flags, &options);
+        options.extend(suggested);
+        options.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    }
+    
+    if options.is_empty() {
+        return Err(format!("No flag suggestions for {}", name));
+    }
+    Ok(FlagSuggestions { command: name, intent, intent_inferred, options })
+}
+
+/// Preview how a command, snippet or agent step expands before running it
+#[tauri::command]
+pub async fn preview_command_expansion(
+    state: State<'_, AppState>,
+    session_id: String,
+    command: String,
+) -> Result<crate::terminal::templates::TemplateExpansion, String> {
+    let _timing = latency::track("preview_command_expansion");
+    let template_context = state.inner().terminal_manager.lock().await.template_context(&session_id);
+    Ok(crate::terminal::templates::expand(&command, &template_context).await)
+}
+
+/// Store a value for `{{secret:NAME}}` placeholders
+#[tauri::command]
+pub async fn set_template_secret(name: String, value: Option<String>) -> Result<(), String> {
+    let _timing = latency::track("set_template_secret");
+    let key = crate::terminal::templates::secret_key(name.trim());
+    let mut secrets = crate::integrations::secrets::SecretsStore::load();
+    match value.filter(|v| !v.is_empty()) {
+        Some(value) => secrets.set(&key, &value),
+        None => secrets.remove(&key),
+    }
+}
+
+#[tauri::command]
+pub async fn list_session_variables(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<std::collections::HashMap<String, String>, String> {
+    let _timing = latency::track("list_session_variables");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    terminal_manager.get_session_variables(&session_id)
+        .cloned()
+        .ok_or_else(|| "Session not found".to_string())
+}
+
+#[tauri::command]
+pub async fn clear_session_variables(
+    state: State<'_, AppState>,
+    session_id: String,
+    name: Option<String>,
+) -> Result<(), String> {
+    let _timing = latency::track("clear_session_variables");
+    let mut terminal_manager = state.inner().terminal_manager.lock().await;
+    terminal_manager.clear_session_variables(&session_id, name.as_deref())
+}
+
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+}
+
+/// Run a command template over explicit items, files matching a glob, or lines of the last output
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_parallel(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    command_template: String,
+    items: Option<Vec<String>>,
+    glob: Option<String>,
+    from_last_output: Option<bool>,
+    concurrency: Option<usize>,
+) -> Result<crate::terminal::parallel::ParallelRunReport, String> {
+    use crate::terminal::parallel;
+    let _timing = latency::track("run_parallel");
+    
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    let session = terminal_manager.get_session(&session_id).ok_or("Session not found")?;
+    if session.remote.is_some() {
+        return Err("Parallel runs use this machine and are not available in SSH sessions".to_string());
+    }
+    let (working_dir, env_vars) = (session.working_directory.clone(), session.environment_vars.clone());
+    let template_context = terminal_manager.template_context(&session_id);
+    drop(terminal_manager);
+    
+    let items = match (items, glob) {
+        (Some(items), _) => items,
+        (None, Some(pattern)) => parallel::items_from_glob(&pattern, &working_dir)?,
+        (None, None) if from_last_output.unwrap_or(false) => {
+            parallel::items_from_output(template_context.last_output.as_deref().unwrap_or(""))
+        }
+        _ => return Err("Provide items, a glob or from_last_output".to_string()),
+    };
+    
+    let expansion = crate::terminal::templates::expand(&command_template, &template_context).await;
+    if !expansion.unresolved.is_empty() {
+        return Err(format!("Unknown template variables: {}", expansion.unresolved.join(", ")));
+    }
+    
+    publish_session_status(&app, &state, &session_id, |status| status.background_jobs += 1).await;
+    let report = parallel::run_parallel(
+        &expansion.command,
+        items,
+        concurrency.unwrap_or_else(default_concurrency),
+        &working_dir,
+        env_vars,
+    ).await;
+    publish_session_status(&app, &state, &session_id, |status| status.background_jobs = status.background_jobs.saturating_sub(1)).await;
+    let report = report?;
+    
+    state.inner().terminal_manager.lock().await.record_execution(CommandExecution {
+        id: uuid::Uuid::new_v4().to_string(),
+        command: format!("parallel: {}", command_template),
+        output: report.summary(),
+        exit_code: Some(if report.failed == 0 { 0 } else { 1 }),
+        duration_ms: report.duration_ms,
+        timestamp: chrono::Utc::now(),
+        outcome: crate::terminal::outcome::ExecutionOutcome::Exited(if report.failed == 0 { 0 } else { 1 }),
+        coverage: None,
+        structured: None,
+        overrides: None,
+        styled: None,
+        session_id: Some(session_id.clone()),
+    });
+    Ok(report)
+}
+
+/// Preview the parallel run a natural language request maps to, with the matched files
+#[tauri::command]
+pub async fn plan_parallel_from_request(
+    state: State<'_, AppState>,
+    session_id: String,
+    request: String,
+) -> Result<Option<(crate::terminal::parallel::ParallelPlan, Vec<String>)>, String> {
+    let _timing = latency::track("plan_parallel_from_request");
+    let working_dir = session_working_directory(&state, Some(&session_id)).await;
+    match crate::terminal::parallel::plan_from_natural_language(&request) {
+        Some(plan) => {
+            let items = crate::terminal::parallel::items_from_glob(&plan.glob, &working_dir)?;
+            Ok(Some((plan, items)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Resolve a user-supplied path against the session's working directory
+async fn session_path(state: &State<'_, AppState>, session_id: Option<&str>, path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        path
+    } else {
+        PathBuf::from(session_working_directory(state, session_id).await).join(path)
+    }
+}
+
+#[tauri::command]
+pub async fn image_info(
+    state: State<'_, AppState>,
+    path: String,
+    session_id: Option<String>,
+) -> Result<crate::tools::media::ImageInfo, String> {
+    let _timing = latency::track("image_info");
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    tokio::task::spawn_blocking(move || crate::tools::media::image_info(&path))
+        .await
+        .map_err(|e| format!("Image task failed: {}", e))?
+}
+
+/// Resize (and optionally convert) an image; without an output path a `-resized` copy is written
+#[tauri::command]
+pub async fn resize_image(
+    state: State<'_, AppState>,
+    path: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    output: Option<String>,
+    quality: Option<u8>,
+    session_id: Option<String>,
+) -> Result<crate::tools::media::ImageOutcome, String> {
+    let _timing = latency::track("resize_image");
+    if width.is_none() && height.is_none() {
+        return Err("Give a width, a height or both".to_string());
+    }
+    let input = session_path(&state, session_id.as_deref(), &path).await;
+    let output = match output {
+        Some(output) => session_path(&state, session_id.as_deref(), &output).await,
+        None => crate::tools::media::sibling_output(&input, "-resized", None),
+    };
+    tokio::task::spawn_blocking(move || {
+        crate::tools::media::resize_image(&input, &output, width, height, quality.unwrap_or(85).clamp(1, 100))
+    })
+    .await
+    .map_err(|e| format!("Image task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn convert_image(
+    state: State<'_, AppState>,
+    path: String,
+    output: String,
+    quality: Option<u8>,
+    session_id: Option<String>,
+) -> Result<crate::tools::media::ImageOutcome, String> {
+    let _timing = latency::track("convert_image");
+    let input = session_path(&state, session_id.as_deref(), &path).await;
+    let output = session_path(&state, session_id.as_deref(), &output).await;
+    if input == output {
+        return Err("Output must differ from the input".to_string());
+    }
+    tokio::task::spawn_blocking(move || {
+        crate::tools::media::convert_image(&input, &output, quality.unwrap_or(90).clamp(1, 100))
+    })
+    .await
+    .map_err(|e| format!("Image task failed: {}", e))?
+}
+
+/// Remove EXIF/GPS and other metadata; writes a `-clean` copy unless overwrite is set
+#[tauri::command]
+pub async fn strip_image_metadata(
+    state: State<'_, AppState>,
+    path: String,
+    overwrite: Option<bool>,
+    session_id: Option<String>,
+) -> Result<crate::tools::media::ImageOutcome, String> {
+    let _timing = latency::track("strip_image_metadata");
+    let input = session_path(&state, session_id.as_deref(), &path).await;
+    let output = if overwrite.unwrap_or(false) {
+        input.clone()
+    } else {
+        crate::tools::media::sibling_output(&input, "-clean", None)
+    };
+    tokio::task::spawn_blocking(move || crate::tools::media::strip_metadata(&input, &output))
+        .await
+        .map_err(|e| format!("Image task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn media_info(
+    state: State<'_, AppState>,
+    path: String,
+    session_id: Option<String>,
+) -> Result<crate::tools::media::MediaInfo, String> {
+    let _timing = latency::track("media_info");
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    crate::tools::media::media_info(&path).await
+}
+
+/// Hash a file, emitting `checksum-progress` events for large files
+#[tauri::command]
+pub async fn hash_file(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    algorithm: Option<String>,
+    session_id: Option<String>,
+) -> Result<crate::tools::checksum::FileHash, String> {
+    use crate::tools::checksum::{HashAlgorithm, HashProgress};
+    use tauri::Emitter;
+    let _timing = latency::track("hash_file");
+    
+    let algorithm = match algorithm {
+        Some(algorithm) => HashAlgorithm::parse(&algorithm)?,
+        None => HashAlgorithm::Sha256,
+    };
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    tokio::task::spawn_blocking(move || {
+        let display = path.display().to_string();
+        crate::tools::checksum::hash_file(&path, algorithm, |bytes_done, total_bytes| {
+            let _ = app.emit("checksum-progress", HashProgress { path: display.clone(), bytes_done, total_bytes });
+        })
+    })
+    .await
+    .map_err(|e| format!("Checksum task failed: {}", e))?
+}
+
+/// Verify a file against an expected digest, or a published SHA256SUMS/.sha256 file next to it
+#[tauri::command]
+pub async fn verify_checksum(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    expected: Option<String>,
+    algorithm: Option<String>,
+    session_id: Option<String>,
+) -> Result<crate::tools::checksum::ChecksumVerification, String> {
+    use crate::tools::checksum::{HashAlgorithm, HashProgress};
+    use tauri::Emitter;
+    let _timing = latency::track("verify_checksum");
+    
+    let algorithm = algorithm.as_deref().map(HashAlgorithm::parse).transpose()?;
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    tokio::task::spawn_blocking(move || {
+        let display = path.display().to_string();
+        crate::tools::checksum::verify_checksum(&path, expected.as_deref(), algorithm, |bytes_done, total_bytes| {
+            let _ = app.emit("checksum-progress", HashProgress { path: display.clone(), bytes_done, total_bytes });
+        })
+    })
+    .await
+    .map_err(|e| format!("Checksum task failed: {}", e))?
+}
+
+/// base64, URL encoding, JWT decoding, UUIDs and epoch/ISO conversion
+#[tauri::command]
+pub async fn text_transform(
+    transform: String,
+    input: String,
+) -> Result<crate::tools::text_transform::TransformResult, String> {
+    let _timing = latency::track("text_transform");
+    let transform = crate::tools::text_transform::Transform::parse(&transform)?;
+    crate::tools::text_transform::apply(transform, &input)
+}
+
+/// Config text from either an inline string or a file, with the format implied by the file
+async fn config_input(
+    state: &State<'_, AppState>,
+    text: Option<String>,
+    path: Option<String>,
+    format: Option<String>,
+    session_id: Option<&str>,
+) -> Result<(String, crate::tools::config_format::ConfigFormat, Option<PathBuf>), String> {
+    use crate::tools::config_format::ConfigFormat;
+    
+    let (text, path) = match (text, path) {
+        (Some(text), _) => (text, None),
+        (None, Some(path)) => {
+            let path = session_path(state, session_id, &path).await;
+            let text = tokio::fs::read_to_string(&path).await
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            (text, Some(path))
+        }
+        (None, None) => return Err("Provide text or a path".to_string()),
+    };
+    let format = match format {
+        Some(format) => ConfigFormat::parse(&format)?,
+        None => path.as_deref().and_then(ConfigFormat::from_path).unwrap_or_else(|| ConfigFormat::sniff(&text)),
+    };
+    Ok((text, format, path))
+}
+
+/// Pretty-print or convert JSON/YAML/TOML; `write` saves a reformatted file in place
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn format_config(
+    state: State<'_, AppState>,
+    text: Option<String>,
+    path: Option<String>,
+    format: Option<String>,
+    output_format: Option<String>,
+    indent: Option<usize>,
+    write: Option<bool>,
+    session_id: Option<String>,
+) -> Result<crate::tools::config_format::FormattedConfig, String> {
+    use crate::tools::config_format::{self, ConfigFormat};
+    let _timing = latency::track("format_config");
+    
+    let (text, input_format, path) = config_input(&state, text, path, format, session_id.as_deref()).await?;
+    let output_format = output_format.as_deref().map(ConfigFormat::parse).transpose()?.unwrap_or(input_format);
+    let formatted = config_format::format(&text, input_format, output_format, indent.unwrap_or(2))?;
+    
+    if write.unwrap_or(false) {
+        let path = path.ok_or("Only files can be written back")?;
+        if output_format != input_format {
+            return Err("Refusing to overwrite a file with a different format".to_string());
+        }
+        if formatted.dropped_comments {
+            return Err(format!("Not rewriting {}: its comments would be lost", path.display()));
+        }
+        if formatted.changed {
+            tokio::fs::write(&path, &formatted.output).await
+                .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(formatted)
+}
+
+#[tauri::command]
+pub async fn validate_config(
+    state: State<'_, AppState>,
+    text: Option<String>,
+    path: Option<String>,
+    format: Option<String>,
+    session_id: Option<String>,
+) -> Result<crate::tools::config_format::ConfigValidation, String> {
+    let _timing = latency::track("validate_config");
+    let (text, format, _) = config_input(&state, text, path, format, session_id.as_deref()).await?;
+    Ok(crate::tools::config_format::validate(&text, format))
+}
+
+/// Project context file (.ph7console.toml / PH7.md) for a session; re-read so trust changes apply
+#[tauri::command]
+pub async fn get_project_context(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::project::context_file::ProjectContextStatus, String> {
+    let _timing = latency::track("get_project_context");
+    state.inner().terminal_manager.lock().await.reload_project_context(&session_id)
+}
+
+/// Personal style learned from edited suggestions, plus the most recent corrections
+#[tauri::command]
+pub async fn get_suggestion_corrections(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<crate::ai::corrections::CorrectionSummary, String> {
+    let _timing = latency::track("get_suggestion_corrections");
+    Ok(state.inner().model_manager.lock().await.get_corrections(limit.unwrap_or(20)).await)
+}
+
+#[tauri::command]
+pub async fn clear_suggestion_corrections(state: State<'_, AppState>) -> Result<(), String> {
+    let _timing = latency::track("clear_suggestion_corrections");
+    state.inner().model_manager.lock().await.clear_corrections().await
+}
+
+/// Backends configured per capability; capabilities not listed use the pattern engine
+#[tauri::command]
+pub async fn get_model_routes(
+    state: State<'_, AppState>,
+) -> Result<std::collections::HashMap<crate::models::Capability, crate::models::routing::ModelBackend>, String> {
+    let _timing = latency::track("get_model_routes");
+    Ok(state.inner().model_manager.lock().await.get_model_routes().await)
+}
+
+#[tauri::command]
+pub async fn set_model_route(
+    state: State<'_, AppState>,
+    capability: crate::models::Capability,
+    backend: Option<crate::models::routing::ModelBackend>,
+) -> Result<(), String> {
+    let _timing = latency::track("set_model_route");
+    state.inner().model_manager.lock().await.set_model_route(capability, backend).await
+}
+
+/// Run one request on two backends; accept_model_comparison records the winner
+#[tauri::command]
+pub async fn compare_models(
+    state: State<'_, AppState>,
+    capability: crate::models::Capability,
+    prompt: String,
+    backend_a: crate::models::routing::ModelBackend,
+    backend_b: crate::models::routing::ModelBackend,
+    session_id: Option<String>,
+) -> Result<crate::models::routing::Comparison, String> {
+    let _timing = latency::track("compare_models");
+    let context = match session_id {
+        Some(session_id) => {
+            let context = state.inner().terminal_manager.lock().await.get_smart_context(&session_id);
+            Some(context.await)
+        }
+        None => None,
+    };
+    let model_manager = state.inner().model_manager.lock().await;
+    model_manager.compare_backends(capability, &prompt, context.as_deref(), [backend_a, backend_b]).await
+}
+
+#[tauri::command]
+pub async fn accept_model_comparison(
+    state: State<'_, AppState>,
+    comparison_id: String,
+    index: usize,
+) -> Result<crate::models::routing::Comparison, String> {
+    let _timing = latency::track("accept_model_comparison");
+    state.inner().model_manager.lock().await.accept_comparison(&comparison_id, index).await
+}
+
+/// AI usage per capability and provider, with estimated cost and budget state
+#[tauri::command]
+pub async fn get_ai_usage_stats(
+    state: State<'_, AppState>,
+    period_days: Option<u32>,
+) -> Result<crate::models::usage::UsageStats, String> {
+    let _timing = latency::track("get_ai_usage_stats");
+    Ok(state.inner().model_manager.lock().await.get_usage_stats(period_days.unwrap_or(30)).await)
+}
+
+/// Daily limits for model backends; omitted fields remove the limit
+#[tauri::command]
+pub async fn set_ai_usage_budget(
+    state: State<'_, AppState>,
+    daily_tokens: Option<u64>,
+    daily_cost_usd: Option<f64>,
+) -> Result<(), String> {
+    let _timing = latency::track("set_ai_usage_budget");
+    let budget = crate::models::usage::UsageBudget { daily_tokens, daily_cost_usd };
+    state.inner().model_manager.lock().await.set_usage_budget(budget).await
+}
+
+/// Delete command history (and its embeddings) older than the given number of days, or all of it
+#[tauri::command]
+pub async fn purge_command_history(
+    state: State<'_, AppState>,
+    older_than_days: Option<u32>,
+) -> Result<usize, String> {
+    let _timing = latency::track("purge_command_history");
+    let cutoff = older_than_days.map(|days| chrono::Utc::now() - chrono::Duration::days(days as i64));
+    let removed = state.inner().terminal_manager.lock().await.purge_history(cutoff);
+    state.inner().model_manager.lock().await.purge_embeddings(cutoff).await?;
+    Ok(removed)
+}
+
+/// Delete all command history, in memory and on disk, with its embeddings
+#[tauri::command]
+pub async fn clear_history(
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let _timing = latency::track("clear_history");
+    purge_command_history(state, None).await
+}
+
+#[tauri::command]
+pub async fn get_history_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::terminal::history::HistorySettings, String> {
+    let _timing = latency::track("get_history_settings");
+    Ok(state.inner().terminal_manager.lock().await.history_settings().clone())
+}
+
+/// Whether history is kept between runs and how many entries are kept
+#[tauri::command]
+pub async fn update_history_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::history::HistorySettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_history_settings");
+    state.inner().terminal_manager.lock().await.set_history_settings(settings)
+}
+
+#[tauri::command]
+pub async fn get_shell_history_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::terminal::shell_history::ShellHistorySettings, String> {
+    let _timing = latency::track("get_shell_history_settings");
+    Ok(state.inner().terminal_manager.lock().await.shell_history_settings().clone())
+}
+
+/// Whether commands are also appended to the shells' own history files, and what is redacted first
+#[tauri::command]
+pub async fn update_shell_history_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::shell_history::ShellHistorySettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_shell_history_settings");
+    state.inner().terminal_manager.lock().await.set_shell_history_settings(settings)
+}
+
+#[tauri::command]
+pub async fn get_embedding_index_stats(
+    state: State<'_, AppState>,
+) -> Result<crate::models::EmbeddingIndexStats, String> {
+    let _timing = latency::track("get_embedding_index_stats");
+    Ok(state.inner().model_manager.lock().await.embedding_stats().await)
+}
+
+/// Rewrite the embedding segment file without deleted and replaced records
+#[tauri::command]
+pub async fn compact_embedding_index(
+    state: State<'_, AppState>,
+) -> Result<crate::models::EmbeddingIndexStats, String> {
+    let _timing = latency::track("compact_embedding_index");
+    state.inner().model_manager.lock().await.compact_embeddings().await
+}
+
+/// Rebuild the approximate nearest neighbour graph over the embedding index
+#[tauri::command]
+pub async fn rebuild_embedding_index(
+    state: State<'_, AppState>,
+) -> Result<crate::models::EmbeddingIndexStats, String> {
+    let _timing = latency::track("rebuild_embedding_index");
+    state.inner().model_manager.lock().await.rebuild_embedding_index().await
+}
+
+/// Past commands closest in meaning to the query
+#[tauri::command]
+pub async fn semantic_history_search(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<crate::models::SemanticSearchResult>, String> {
+    let _timing = latency::track("semantic_history_search");
+    Ok(state.inner().model_manager.lock().await.semantic_history_search(&query, limit.unwrap_or(10)).await)
+}
+
+/// Group recent history into activities with time spent and suggestions for each
+#[tauri::command]
+pub async fn analyze_command_activity(
+    state: State<'_, AppState>,
+) -> Result<crate::ai::activity::ActivityReport, String> {
+    let _timing = latency::track("analyze_command_activity");
+    let analyzer = state.inner().model_manager.lock().await.activity_analyzer();
+    Ok(analyzer.run().await)
+}
+
+/// Commands that failed and were not fixed yet; entries clear once a similar command succeeds
+#[tauri::command]
+pub async fn get_unresolved_failures(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::terminal::failures::UnresolvedFailure>, String> {
+    let _timing = latency::track("get_unresolved_failures");
+    Ok(state.inner().terminal_manager.lock().await.unresolved_failures(limit))
+}
+
+/// Last exit code and duration, running program, cwd, git summary and background jobs of a session.
+/// Changes are also emitted as `session-status` events.
+#[tauri::command]
+pub async fn get_session_status(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::terminal::status::SessionStatus, String> {
+    let _timing = latency::track("get_session_status");
+    if let Some(status) = state.inner().session_status.lock().await.get(&session_id) {
+        return Ok(status.clone());
+    }
+    // Nothing ran yet: start from the session's directory
+    let working_directory = state.inner().terminal_manager.lock().await
+        .get_session(&session_id)
+        .map(|session| session.working_directory.clone())
+        .ok_or("Session not found")?;
+    let git = crate::terminal::status::load_git_summary(&working_directory).await;
+    let mut board = state.inner().session_status.lock().await;
+    board.update(&session_id, |status| status.set_working_directory(&working_directory, git));
+    board.get(&session_id).cloned().ok_or_else(|| "Session not found".to_string())
+}
+
+/// How paths are displayed and whether `cd` resolves symlinks
+#[tauri::command]
+pub async fn get_path_display_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::terminal::path_display::PathDisplaySettings, String> {
+    let _timing = latency::track("get_path_display_settings");
+    Ok(state.inner().terminal_manager.lock().await.path_display_settings().clone())
+}
+
+#[tauri::command]
+pub async fn update_path_display_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::path_display::PathDisplaySettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_path_display_settings");
+    state.inner().terminal_manager.lock().await.set_path_display_settings(settings)
+}
+
+/// Join arguments into a command line quoted for a shell (default: the session's shell)
+#[tauri::command]
+pub async fn quote_shell_command(
+    state: State<'_, AppState>,
+    args: Vec<String>,
+    shell: Option<String>,
+    session_id: Option<String>,
+) -> Result<String, String> {
+    use crate::terminal::shell_quote::{self, ShellKind};
+    let _timing = latency::track("quote_shell_command");
+    
+    let shell = match shell {
+        Some(shell) => shell,
+        None => {
+            let terminal_manager = state.inner().terminal_manager.lock().await;
+            session_id.as_deref()
+                .and_then(|id| terminal_manager.get_session(id))
+                .map(|session| session.shell.clone())
+                .unwrap_or_default()
+        }
+    };
+    Ok(shell_quote::join(&args, ShellKind::from_shell(&shell)))
+}
+
+/// Run the translation safety checks on a command without executing it
+#[tauri::command]
+pub async fn check_translated_command(
+    state: State<'_, AppState>,
+    command: String,
+    session_id: Option<String>,
+) -> Result<crate::ai::translation_guard::GuardReport, String> {
+    let _timing = latency::track("check_translated_command");
+    let working_dir = session_working_directory(&state, session_id.as_deref()).await;
+    Ok(crate::ai::translation_guard::check(&command, &working_dir, &crate::ai::translation_guard::GuardSettings::load()))
+}
+
+#[tauri::command]
+pub async fn get_translation_guard_settings() -> Result<crate::ai::translation_guard::GuardSettings, String> {
+    let _timing = latency::track("get_translation_guard_settings");
+    Ok(crate::ai::translation_guard::GuardSettings::load())
+}
+
+/// Allow substitutions or line breaks in translations, or add directories they may write to
+#[tauri::command]
+pub async fn update_translation_guard_settings(
+    settings: crate::ai::translation_guard::GuardSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_translation_guard_settings");
+    settings.save()
+}
+
+/// Search file contents under a directory, emitting `file-search-matches` batches while it runs
+#[tauri::command]
+pub async fn search_in_files(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    query: String,
+    path: String,
+    options: Option<crate::project::search::SearchOptions>,
+    session_id: Option<String>,
+    search_id: Option<String>,
+) -> Result<crate::project::search::SearchResults, String> {
+    use crate::project::search::{SearchBatch, MATCHES_EVENT};
+    use tauri::Emitter;
+    let _timing = latency::track("search_in_files");
+    
+    let root = session_path(&state, session_id.as_deref(), &path).await;
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        crate::project::search::search_in_files(&query, &root, &options, |matches| {
+            let _ = app.emit(MATCHES_EVENT, SearchBatch { search_id: search_id.clone(), matches: matches.to_vec() });
+        })
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))?
+}
+
+/// Index the session's project (symbols, README, manifests) for AI suggestions
+#[tauri::command]
+pub async fn index_project(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<crate::project::symbols::ProjectIndex, String> {
+    let _timing = latency::track("index_project");
+    let root = project_root_for(&state, session_id.as_deref()).await;
+    let index = tokio::task::spawn_blocking(move || crate::project::symbols::index_project(std::path::Path::new(&root)))
+        .await
+        .map_err(|e| format!("Indexing task failed: {}", e))??;
+    let embeddings = state.inner().model_manager.lock().await.embedding_store();
+    let documents = crate::ai::index_project(&embeddings, &index).await;
+    println!("📚 Indexed {} ({} files, {} entries)", index.root, index.files_indexed, documents);
+    Ok(index)
+}
+
+/// Indexed project facts most relevant to a question
+#[tauri::command]
+pub async fn query_project_index(
+    state: State<'_, AppState>,
+    question: String,
+    session_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::models::embeddings::SemanticSearchResult>, String> {
+    let _timing = latency::track("query_project_index");
+    let root = project_root_for(&state, session_id.as_deref()).await;
+    Ok(state.inner().model_manager.lock().await.project_knowledge(&root, &question, limit.unwrap_or(10)).await)
+}
+
+/// Tests found in the session's project, without running them
+#[tauri::command]
+pub async fn list_tests(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<crate::project::test_runner::TestCase>, String> {
+    let _timing = latency::track("list_tests");
+    let root = project_root_for(&state, Some(&session_id)).await;
+    tokio::task::spawn_blocking(move || crate::project::test_runner::discover(std::path::Path::new(&root)))
+        .await
+        .map_err(|e| format!("Test discovery failed: {}", e))
+}
+
+/// Run only the selected tests
+#[tauri::command]
+pub async fn run_tests(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    selection: crate::project::test_runner::TestSelection,
+) -> Result<crate::system::ipc::CappedExecution, String> {
+    let _timing = latency::track("run_tests");
+    let root = project_root_for(&state, Some(&session_id)).await;
+    let command = crate::project::test_runner::invocation(&selection, std::path::Path::new(&root))?;
+    // Queued behind the session's running command like any other, and recorded in its history
+    execute_command(app, state, session_id, command, None, None, None).await
+}
+
+/// Failing tests reported by the session's last command, ready to pass to `run_tests`
+#[tauri::command]
+pub async fn get_failed_tests(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<crate::project::test_runner::TestCase>, String> {
+    let _timing = latency::track("get_failed_tests");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    Ok(terminal_manager
+        .get_session_history(&session_id, Some(1))
+        .first()
+        .map(|execution| crate::project::test_runner::failed_tests(&execution.output))
+        .unwrap_or_default())
+}
+
+/// Latest coverage of the session's project compared with its baseline
+#[tauri::command]
+pub async fn get_coverage_summary(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::project::coverage::CoverageStatus, String> {
+    let _timing = latency::track("get_coverage_summary");
+    let root = project_root_for(&state, Some(&session_id)).await;
+    let latest = state.inner().terminal_manager.lock().await.latest_coverage(&root).cloned();
+    let latest = match latest {
+        Some(latest) => Some(latest),
+        // Nothing from this session yet, so read whatever report the project already has
+        None => {
+            let project = std::path::PathBuf::from(&root);
+            tokio::task::spawn_blocking(move || crate::project::coverage::fresh_report(&project, std::time::UNIX_EPOCH))
+                .await
+                .map_err(|e| format!("Coverage task failed: {}", e))?
+        }
+    };
+    let baseline = crate::project::coverage::CoverageBaselines::load().get(&root).cloned();
+    Ok(crate::project::coverage::CoverageStatus::new(root, latest, baseline))
+}
+
+/// Accept the latest coverage as the project's baseline, e.g. after deliberately removing tested code
+#[tauri::command]
+pub async fn set_coverage_baseline(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::project::coverage::CoverageStatus, String> {
+    let _timing = latency::track("set_coverage_baseline");
+    let root = project_root_for(&state, Some(&session_id)).await;
+    let latest = state.inner().terminal_manager.lock().await.latest_coverage(&root).cloned()
+        .ok_or_else(|| "No coverage report from a test run yet".to_string())?;
+    let mut baselines = crate::project::coverage::CoverageBaselines::load();
+    baselines.set(&root, &latest);
+    baselines.save()?;
+    let baseline = baselines.get(&root).cloned();
+    Ok(crate::project::coverage::CoverageStatus::new(root, Some(latest), baseline))
+}
+
+/// Find reclaimable space in known projects (or the given directories), user caches and Docker
+#[tauri::command]
+pub async fn scan_cleanup_candidates(
+    state: State<'_, AppState>,
+    paths: Option<Vec<String>>,
+) -> Result<crate::system::cleanup::CleanupPlan, String> {
+    let _timing = latency::track("scan_cleanup_candidates");
+    let directories: Vec<String> = match paths {
+        Some(paths) => paths,
+        None => {
+            // Projects are the ones the user works in: open sessions and visited directories
+            let terminal_manager = state.inner().terminal_manager.lock().await;
+            let mut directories: Vec<String> = terminal_manager.get_all_sessions().iter().map(|s| s.working_directory.clone()).collect();
+            directories.extend(terminal_manager.directory_scores().into_keys());
+            directories
+        }
+    };
+    crate::system::fs_tasks::run_with_timeout(std::time::Duration::from_secs(120), move || {
+        let mut projects: Vec<std::path::PathBuf> = directories
+            .iter()
+            .map(|dir| crate::project::symbols::project_root(std::path::Path::new(dir)))
+            .filter(|root| root.is_dir())
+            .collect();
+        projects.sort();
+        projects.dedup();
+        crate::system::cleanup::scan(&projects)
+    })
+    .await
+}
+
+/// Remove the selected items of a reviewed cleanup plan, emitting `cleanup-progress` after each
+#[tauri::command]
+pub async fn execute_cleanup_plan(
+    app: tauri::AppHandle,
+    plan: crate::system::cleanup::CleanupPlan,
+    use_trash: Option<bool>,
+) -> Result<crate::system::cleanup::CleanupReport, String> {
+    use tauri::Emitter;
+    let _timing = latency::track("execute_cleanup_plan");
+    
+    tokio::task::spawn_blocking(move || {
+        crate::system::cleanup::execute(&plan, use_trash.unwrap_or(true), |progress| {
+            let _ = app.emit(crate::system::cleanup::PROGRESS_EVENT, progress);
+        })
+    })
+    .await
+    .map_err(|e| format!("Cleanup task failed: {}", e))
+}
+
+/// Groups of identical files under a directory, with the copies that are safe to delete
+#[tauri::command]
+pub async fn find_duplicates(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<crate::tools::duplicates::DuplicateOptions>,
+    session_id: Option<String>,
+) -> Result<crate::tools::duplicates::DuplicateReport, String> {
+    let _timing = latency::track("find_duplicates");
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || crate::tools::duplicates::find_duplicates(&path, &options))
+        .await
+        .map_err(|e| format!("Duplicate search failed: {}", e))?
+}
+
+/// Owner, group, mode bits and ACL entries of a path
+#[tauri::command]
+pub async fn get_permissions(
+    state: State<'_, AppState>,
+    path: String,
+    session_id: Option<String>,
+) -> Result<crate::system::permissions::PermissionInfo, String> {
+    let _timing = latency::track("get_permissions");
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    crate::system::fs_tasks::run(move || crate::system::permissions::get_permissions(&path)).await?
+}
+
+/// Change a path's mode with a numeric ("644") or symbolic ("u+x,go-w") spec.
+/// Without confirmation only the resulting change is returned, nothing is applied
+#[tauri::command]
+pub async fn set_permissions(
+    state: State<'_, AppState>,
+    path: String,
+    mode: String,
+    confirmed: Option<bool>,
+    session_id: Option<String>,
+) -> Result<crate::system::permissions::ModeChange, String> {
+    let _timing = latency::track("set_permissions");
+    let path = session_path(&state, session_id.as_deref(), &path).await;
+    crate::system::permissions::set_permissions(&path, &mode, confirmed.unwrap_or(false))
+}
+
+/// Mounted volumes with capacity, file system and whether they are removable
+#[tauri::command]
+pub async fn list_volumes() -> Result<Vec<crate::system::volumes::Volume>, String> {
+    let _timing = latency::track("list_volumes");
+    tokio::task::spawn_blocking(crate::system::volumes::list)
+        .await
+        .map_err(|e| format!("Volume listing failed: {}", e))
+}
+
+/// Eject a volume. Unless forced, nothing happens while processes have files open on it
+/// or sessions are inside it; the report lists them instead
+#[tauri::command]
+pub async fn eject_volume(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    mount_point: String,
+    force: Option<bool>,
+) -> Result<crate::system::volumes::EjectReport, String> {
+    use crate::system::volumes::{self, EjectReport};
+    let _timing = latency::track("eject_volume");
+    
+    let volume = volumes::list()
+        .into_iter()
+        .find(|v| v.mount_point == mount_point)
+        .ok_or_else(|| format!("{} is not a mounted volume", mount_point))?;
+    if !volume.ejectable {
+        return Err(format!("{} is not a removable or external volume", mount_point));
+    }
+    let mount = PathBuf::from(&volume.mount_point);
+    let sessions_inside = state.inner().terminal_manager.lock().await.sessions_under(&mount);
+    let busy_processes = {
+        let mount = mount.clone();
+        tokio::task::spawn_blocking(move || volumes::busy_processes(&mount))
+            .await
+            .map_err(|e| format!("Volume check failed: {}", e))?
+    };
+    
+    if !force.unwrap_or(false) && (!busy_processes.is_empty() || !sessions_inside.is_empty()) {
+        let mut reasons = Vec::new();
+        if !busy_processes.is_empty() {
+            let names: Vec<String> = busy_processes.iter().map(|p| format!("{} ({})", p.command, p.pid)).collect();
+            reasons.push(format!("in use by {}", names.join(", ")));
+        }
+        if !sessions_inside.is_empty() {
+            reasons.push(format!("{} terminal session(s) are inside it", sessions_inside.len()));
+        }
+        return Ok(EjectReport {
+            message: format!("⚠️ {} is {}; close them or force the eject", mount_point, reasons.join(" and ")),
+            mount_point,
+            ejected: false,
+            busy_processes,
+            sessions_inside,
+        });
+    }
+    
+    // Sessions leave first so they do not hold the volume or end up in a dead directory
+    let recoveries = state.inner().terminal_manager.lock().await.leave_directory(&mount);
+    for recovery in recoveries {
+        let session_id = recovery.session_id.clone();
+        emit_to_session(&app, &state, &session_id, crate::terminal::DIRECTORY_RECOVERED_EVENT, recovery).await;
+    }
+    let ejected = {
+        let volume = volume.clone();
+        tokio::task::spawn_blocking(move || volumes::eject(&volume))
+            .await
+            .map_err(|e| format!("Eject failed: {}", e))?
+    };
+    Ok(EjectReport {
+        message: match &ejected {
+            Ok(()) => format!("⏏️ {} ejected, it can be removed safely", mount_point),
+            Err(e) => format!("Could not eject {}: {}", mount_point, e),
+        },
+        mount_point,
+        ejected: ejected.is_ok(),
+        busy_processes,
+        sessions_inside,
+    })
+}
+
+/// Battery level, power source and whether background work is being deferred
+#[tauri::command]
+pub async fn get_power_state() -> Result<crate::system::power::PowerState, String> {
+    let _timing = latency::track("get_power_state");
+    tokio::task::spawn_blocking(crate::system::power::refresh)
+        .await
+        .map_err(|e| format!("Power status failed: {}", e))
+}
+
+/// Configure when power saving starts and which background work it defers
+#[tauri::command]
+pub async fn set_power_policy(
+    policy: crate::system::power::PowerPolicy,
+) -> Result<crate::system::power::PowerState, String> {
+    let _timing = latency::track("set_power_policy");
+    tokio::task::spawn_blocking(move || crate::system::power::set_policy(policy))
+        .await
+        .map_err(|e| format!("Power policy update failed: {}", e))?
+}
+
+/// Notification center entries, newest first
+#[tauri::command]
+pub async fn list_notifications(
+    state: State<'_, AppState>,
+    unread_only: Option<bool>,
+) -> Result<Vec<crate::terminal::notifications::AppNotification>, String> {
+    let _timing = latency::track("list_notifications");
+    Ok(state.inner().notification_center.lock().await.list(unread_only.unwrap_or(false)))
+}
+
+/// Mark notifications read (or unread with `read: false`); all of them when no ids are given.
+/// Returns the number still unread
+#[tauri::command]
+pub async fn mark_notifications_read(
+    state: State<'_, AppState>,
+    ids: Option<Vec<String>>,
+    read: Option<bool>,
+) -> Result<usize, String> {
+    let _timing = latency::track("mark_notifications_read");
+    let mut center = state.inner().notification_center.lock().await;
+    center.mark_read(ids.as_deref(), read.unwrap_or(true));
+    Ok(center.unread_count())
+}
+
+/// Remove notifications, only the read ones with `read_only`
+#[tauri::command]
+pub async fn clear_notifications(
+    state: State<'_, AppState>,
+    read_only: Option<bool>,
+) -> Result<usize, String> {
+    let _timing = latency::track("clear_notifications");
+    Ok(state.inner().notification_center.lock().await.clear(read_only.unwrap_or(false)))
+}
+
+#[tauri::command]
+pub async fn get_notification_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::terminal::notifications::NotificationSettings, String> {
+    let _timing = latency::track("get_notification_settings");
+    Ok(state.inner().notification_center.lock().await.settings().clone())
+}
+
+#[tauri::command]
+pub async fn update_notification_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::notifications::NotificationSettings,
+) -> Result<crate::terminal::notifications::NotificationSettings, String> {
+    let _timing = latency::track("update_notification_settings");
+    let mut center = state.inner().notification_center.lock().await;
+    center.set_settings(settings)?;
+    Ok(center.settings().clone())
+}
+
+/// Quiet hours for one session, or mute it; passing neither returns the session to the global
+/// quiet hours. Overrides last until the session closes and are not saved. Returns every override
+#[tauri::command]
+pub async fn set_session_quiet_hours(
+    state: State<'_, AppState>,
+    session_id: String,
+    quiet_hours: Option<crate::terminal::notifications::QuietHours>,
+    muted: Option<bool>,
+) -> Result<std::collections::HashMap<String, Option<crate::terminal::notifications::QuietHours>>, String> {
+    let _timing = latency::track("set_session_quiet_hours");
+    let mut center = state.inner().notification_center.lock().await;
+    let quiet_hours = if muted.unwrap_or(false) { Some(None) } else { quiet_hours.map(Some) };
+    center.set_session_quiet_hours(&session_id, quiet_hours)?;
+    Ok(center.session_quiet_hours().clone())
+}
+
+/// Which sessions each window shows, the main window first
+#[tauri::command]
+pub async fn list_windows(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::windows::WindowLayout>, String> {
+    let _timing = latency::track("list_windows");
+    let session_ids: Vec<String> = state.inner().terminal_manager.lock().await.get_all_sessions().iter().map(|s| s.id.clone()).collect();
+    Ok(state.inner().window_registry.lock().await.layouts(&session_ids))
+}
+
+/// Move a session into a window of its own, placed where that window slot was last closed.
+/// The window loads `index.html?session=<id>` and only receives that session's events
+#[tauri::command]
+pub async fn detach_session(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::terminal::windows::WindowLayout, String> {
+    use crate::terminal::windows::{WindowLayout, WindowRegistry};
+    use tauri::Manager;
+    let _timing = latency::track("detach_session");
+    
+    let title = state.inner().terminal_manager.lock().await
+        .get_session(&session_id)
+        .map(|session| session.title.clone())
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+    
+    let (label, geometry) = {
+        let registry = state.inner().window_registry.lock().await;
+        let current = registry.window_for(&session_id).to_string();
+        let label = if WindowRegistry::is_detached(&current) { current } else { registry.free_label() };
+        let geometry = registry.geometry(&label).cloned();
+        (label, geometry)
+    };
+    
+    match app.get_webview_window(&label) {
+        // Already detached: bring its window forward
+        Some(window) => {
+            let _ = window.set_focus();
+        }
+        None => {
+            let url = tauri::WebviewUrl::App(format!("index.html?session={}", session_id).into());
+            let mut builder = tauri::WebviewWindowBuilder::new(&app, &label, url)
+                .title(format!("pH7Console - {}", title))
+                .min_inner_size(480.0, 320.0);
+            builder = match &geometry {
+                Some(geometry) => builder.position(geometry.x, geometry.y).inner_size(geometry.width, geometry.height),
+                None => builder.inner_size(900.0, 600.0),
+            };
+            let window = builder.build().map_err(|e| format!("Could not open a window: {}", e))?;
+            if geometry.is_some_and(|g| g.maximized) {
+                let _ = window.maximize();
+            }
+        }
+    }
+    
+    state.inner().window_registry.lock().await.assign(&session_id, &label);
+    broadcast_windows(&app, state.inner()).await;
+    let registry = state.inner().window_registry.lock().await;
+    Ok(WindowLayout {
+        session_ids: registry.sessions_in(&label),
+        geometry: registry.geometry(&label).cloned(),
+        label,
+    })
+}
+
+/// Move a session back into the main window, or into another open window.
+/// A detached window left without sessions closes
+#[tauri::command]
+pub async fn attach_session(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    window_label: Option<String>,
+) -> Result<Vec<crate::terminal::windows::WindowLayout>, String> {
+    use crate::terminal::windows::MAIN_WINDOW;
+    use tauri::Manager;
+    let _timing = latency::track("attach_session");
+    
+    if state.inner().terminal_manager.lock().await.get_session(&session_id).is_none() {
+        return Err(format!("Session {} not found", session_id));
+    }
+    let target = window_label.unwrap_or_else(|| MAIN_WINDOW.to_string());
+    let target_window = app.get_webview_window(&target).ok_or_else(|| format!("No window named {}", target))?;
+    
+    let emptied = {
+        let mut registry = state.inner().window_registry.lock().await;
+        let previous = registry.window_for(&session_id).to_string();
+        registry.assign(&session_id, &target);
+        (previous != target && previous != MAIN_WINDOW && registry.sessions_in(&previous).is_empty()).then_some(previous)
+    };
+    if let Some(window) = emptied.and_then(|label| app.get_webview_window(&label)) {
+        let _ = window.close();
+    }
+    let _ = target_window.set_focus();
+    
+    broadcast_windows(&app, state.inner()).await;
+    let session_ids: Vec<String> = state.inner().terminal_manager.lock().await.get_all_sessions().iter().map(|s| s.id.clone()).collect();
+    Ok(state.inner().window_registry.lock().await.layouts(&session_ids))
+}
+
+/// Model files that routes send requests to
+fn local_gguf_models() -> Vec<String> {
+    use crate::models::routing::{ModelBackend, ModelRoutes};
+    
+    let mut models: Vec<String> = ModelRoutes::load()
+        .routes()
+        .values()
+        .filter_map(|backend| match backend {
+            ModelBackend::LocalGguf { model_path, .. } => Some(model_path.clone()),
+            _ => None,
+        })
+        .collect();
+    models.sort();
+    models.dedup();
+    models
+}
+
+/// Check the shell, PTY, data directory, disk space, saved data and model files.
+/// Every check that does not pass comes with a suggested fix
+#[tauri::command]
+pub async fn run_diagnostics(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<crate::system::diagnostics::DiagnosticsReport, String> {
+    let _timing = latency::track("run_diagnostics");
+    let session_shell = match &session_id {
+        Some(id) => state.inner().terminal_manager.lock().await.get_session(id).map(|session| session.shell.clone()),
+        None => None,
+    };
+    let shell = session_shell.unwrap_or_else(crate::terminal::launch_profiles::default_shell);
+    let gguf_models = local_gguf_models();
+    
+    tokio::task::spawn_blocking(move || crate::system::diagnostics::run(&shell, &gguf_models))
+        .await
+        .map_err(|e| format!("Diagnostics failed: {}", e))
+}
+
+/// What the startup migration of saved data did, with the backups it made
+#[tauri::command]
+pub async fn get_migration_status() -> Result<crate::ai::migrations::MigrationReport, String> {
+    let _timing = latency::track("get_migration_status");
+    crate::ai::migrations::report().ok_or_else(|| "Migrations have not run yet".to_string())
+}
+
+#[tauri::command]
+pub async fn list_launch_profiles(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::terminal::launch_profiles::LaunchProfile>, String> {
+    let _timing = latency::track("list_launch_profiles");
+    Ok(state.inner().terminal_manager.lock().await.launch_profiles().list().to_vec())
+}
+
+/// Create or update a launch profile; a new one gets an id when it has none
+#[tauri::command]
+pub async fn save_launch_profile(
+    state: State<'_, AppState>,
+    mut profile: crate::terminal::launch_profiles::LaunchProfile,
+) -> Result<crate::terminal::launch_profiles::LaunchProfile, String> {
+    let _timing = latency::track("save_launch_profile");
+    if profile.id.trim().is_empty() {
+        profile.id = uuid::Uuid::new_v4().to_string();
+    }
+    state.inner().terminal_manager.lock().await.launch_profiles_mut().upsert(profile.clone())?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn delete_launch_profile(
+    state: State<'_, AppState>,
+    profile_id: String,
+) -> Result<(), String> {
+    let _timing = latency::track("delete_launch_profile");
+    state.inner().terminal_manager.lock().await.launch_profiles_mut().remove(&profile_id)
+}
+
+/// Shells installed on this machine, with their versions
+#[tauri::command]
+pub async fn list_shells() -> Result<Vec<crate::terminal::shells::InstalledShell>, String> {
+    let _timing = latency::track("list_shells");
+    tokio::task::spawn_blocking(crate::terminal::shells::detect)
+        .await
+        .map_err(|e| format!("Shell detection failed: {}", e))
+}
+
+/// Switch a session to another installed shell; returns the shell's resolved path
+#[tauri::command]
+pub async fn set_session_shell(
+    state: State<'_, AppState>,
+    session_id: String,
+    shell: String,
+) -> Result<String, String> {
+    let _timing = latency::track("set_session_shell");
+    state.inner().terminal_manager.lock().await.set_session_shell(&session_id, &shell)
+}
+
+#[tauri::command]
+pub async fn get_structured_output_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::terminal::structured::StructuredSettings, String> {
+    let _timing = latency::track("get_structured_output_settings");
+    Ok(state.inner().terminal_manager.lock().await.structured_settings().clone())
+}
+
+#[tauri::command]
+pub async fn update_structured_output_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::structured::StructuredSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_structured_output_settings");
+    state.inner().terminal_manager.lock().await.set_structured_settings(settings)
+}
+
+/// Query a command's JSON output with a jq-style path
+#[tauri::command]
+pub async fn query_structured_output(
+    state: State<'_, AppState>,
+    execution_id: String,
+    path: String,
+) -> Result<serde_json::Value, String> {
+    let _timing = latency::track("query_structured_output");
+    state.inner().terminal_manager.lock().await.query_structured_output(&execution_id, &path)
+}
+
+/// The session's prompt segments; later changes arrive as `session-prompt` events
+#[tauri::command]
+pub async fn get_session_prompt(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<crate::terminal::prompt::PromptModel, String> {
+    let _timing = latency::track("get_session_prompt");
+    let status = get_session_status(state.clone(), session_id.clone()).await?;
+    let environment = state.inner().terminal_manager.lock().await
+        .get_session(&session_id)
+        .map(|session| session.environment_vars.clone())
+        .unwrap_or_default();
+    let engine = state.inner().prompt_engine.clone();
+    tokio::task::spawn_blocking(move || engine.render(&status, &environment))
+        .await
+        .map_err(|e| format!("Prompt rendering failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_prompt_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::terminal::prompt::PromptSettings, String> {
+    let _timing = latency::track("get_prompt_settings");
+    Ok(state.inner().prompt_engine.settings())
+}
+
+#[tauri::command]
+pub async fn update_prompt_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::prompt::PromptSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_prompt_settings");
+    state.inner().prompt_engine.set_settings(settings)
+}
+
+/// What a command is about to do and what makes it risky, answered within a fixed latency budget.
+/// The UI shows the hint next to the enter key when `risky` is set
+#[tauri::command]
+pub async fn review_command(
+    state: State<'_, AppState>,
+    command: String,
+    session_id: Option<String>,
+) -> Result<crate::ai::command_review::CommandReview, String> {
+    use crate::ai::command_review::{self, CommandReview, ReviewSource, REVIEW_BUDGET};
+    let _timing = latency::track("review_command");
+    
+    let started = std::time::Instant::now();
+    // A running command holds the terminal manager; review without the session's directory then
+    let working_directory = match (&session_id, state.inner().terminal_manager.try_lock()) {
+        (Some(id), Ok(terminal_manager)) => terminal_manager.get_session(id).map(|session| session.working_directory.clone()),
+        _ => None,
+    };
+    let flags = command_review::risk_flags(&command, working_directory.as_deref());
+    
+    let template = command_review::template(&command);
+    if let Some(summary) = state.inner().review_cache.lock().await.get(&template) {
+        return Ok(CommandReview::new(&command, summary, flags, ReviewSource::Cache, started.elapsed()));
+    }
+    let context = working_directory.map(|dir| format!("Working Directory: {}", dir)).unwrap_or_default();
+    let explained = if state.inner().review_cache.lock().await.start(&template) {
+        // The explanation runs on its own so one that misses the budget is still cached
+        let model_manager = state.inner().model_manager.clone();
+        let review_cache = state.inner().review_cache.clone();
+        let explanation = tokio::spawn(async move {
+            let summary = match model_manager.try_lock() {
+                Ok(model_manager) => model_manager.explain_command(&template, &context).await,
+                Err(_) => None,
+            };
+            review_cache.lock().await.finish(&template, summary.clone());
+            summary
+        });
+        let remaining = REVIEW_BUDGET.saturating_sub(started.elapsed());
+        tokio::time::timeout(remaining, explanation).await.ok().and_then(Result::ok).flatten()
+    } else {
+        None
+    };
+    Ok(match explained {
+        Some(summary) => CommandReview::new(&command, summary, flags, ReviewSource::Model, started.elapsed()),
+        None => CommandReview::new(&command, command_review::describe(&command), flags, ReviewSource::BuiltIn, started.elapsed()),
+    })
+}
+
+/// What a file-changing command would do, for the confirmation dialog; `None` for commands that cannot be simulated
+#[tauri::command]
+pub async fn simulate_command(
+    state: State<'_, AppState>,
+    command: String,
+    session_id: String,
+) -> Result<Option<crate::terminal::what_if::Simulation>, String> {
+    let _timing = latency::track("simulate_command");
+    let working_directory = {
+        let terminal_manager = state.inner().terminal_manager.lock().await;
+        terminal_manager.get_session(&session_id).ok_or("Session not found")?.working_directory.clone()
+    };
+    tokio::task::spawn_blocking(move || crate::terminal::what_if::simulate(&command, std::path::Path::new(&working_directory)))
+        .await
+        .map_err(|e| format!("Simulation failed: {}", e))?
+        .transpose()
+}
+
+// Backup Commands
+
+#[tauri::command]
+pub async fn list_backup_jobs() -> Result<Vec<crate::tools::backup::BackupJob>, String> {
+    let _timing = latency::track("list_backup_jobs");
+    Ok(crate::tools::backup::BackupJobs::load().list().to_vec())
+}
+
+/// A job for a directory with its rebuildable folders excluded, for the backup wizard to start from
+#[tauri::command]
+pub async fn suggest_backup_job(source: String) -> Result<crate::tools::backup::BackupJob, String> {
+    let _timing = latency::track("suggest_backup_job");
+    let source = PathBuf::from(source);
+    if !source.is_dir() {
+        return Err(format!("Directory not found: {}", source.display()));
+    }
+    Ok(crate::tools::backup::suggest(&source))
+}
+
+/// Create or update a job; a job without an id is new
+#[tauri::command]
+pub async fn save_backup_job(job: crate::tools::backup::BackupJob) -> Result<crate::tools::backup::BackupJob, String> {
+    let _timing = latency::track("save_backup_job");
+    crate::tools::backup::BackupJobs::load().upsert(job)
+}
+
+/// Remove a job; its snapshots stay where they are
+#[tauri::command]
+pub async fn delete_backup_job(job_id: String) -> Result<(), String> {
+    let _timing = latency::track("delete_backup_job");
+    crate::tools::backup::BackupJobs::load().remove(&job_id)
+}
+
+/// Take a snapshot now, emitting `backup-progress` while copying and verifying
+#[tauri::command]
+pub async fn run_backup_job(app: tauri::AppHandle, job_id: String) -> Result<crate::tools::backup::BackupReport, String> {
+    use tauri::Emitter;
+    let _timing = latency::track("run_backup_job");
+    
+    let job = backup_job(&job_id)?;
+    let report = tokio::task::spawn_blocking(move || {
+        crate::tools::backup::run(&job, |progress| {
+            let _ = app.emit(crate::tools::backup::PROGRESS_EVENT, progress);
+        })
+    })
+    .await
+    .map_err(|e| format!("Backup task failed: {}", e))??;
+    crate::tools::backup::BackupJobs::load().record_run(&job_id, report.started_at)?;
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn list_backup_snapshots(job_id: String) -> Result<Vec<crate::tools::backup::Snapshot>, String> {
+    let _timing = latency::track("list_backup_snapshots");
+    let job = backup_job(&job_id)?;
+    crate::system::fs_tasks::run(move || crate::tools::backup::list_snapshots(&job)).await
+}
+
+/// Check a snapshot against the current files of its sources
+#[tauri::command]
+pub async fn verify_backup_snapshot(job_id: String, snapshot: String) -> Result<crate::tools::backup::Verification, String> {
+    let _timing = latency::track("verify_backup_snapshot");
+    let job = backup_job(&job_id)?;
+    tokio::task::spawn_blocking(move || crate::tools::backup::verify(&job, &snapshot))
+        .await
+        .map_err(|e| format!("Verification failed: {}", e))?
+}
+
+/// Entries of a folder inside a snapshot; an empty path lists its top level
+#[tauri::command]
+pub async fn browse_backup_snapshot(
+    job_id: String,
+    snapshot: String,
+    path: Option<String>,
+) -> Result<Vec<crate::tools::backup::SnapshotEntry>, String> {
+    let _timing = latency::track("browse_backup_snapshot");
+    let job = backup_job(&job_id)?;
+    crate::system::fs_tasks::run(move || crate::tools::backup::browse(&job, &snapshot, &path.unwrap_or_default())).await?
+}
+
+/// Copy a file or folder from a snapshot into a directory, keeping existing files unless `overwrite`
+#[tauri::command]
+pub async fn restore_from_backup(
+    job_id: String,
+    snapshot: String,
+    path: String,
+    target_directory: String,
+    overwrite: Option<bool>,
+) -> Result<crate::tools::backup::RestoreReport, String> {
+    let _timing = latency::track("restore_from_backup");
+    let job = backup_job(&job_id)?;
+    let target = PathBuf::from(target_directory);
+    if !target.is_dir() {
+        return Err(format!("Directory not found: {}", target.display()));
+    }
+    tokio::task::spawn_blocking(move || crate::tools::backup::restore(&job, &snapshot, &path, &target, overwrite.unwrap_or(false)))
+        .await
+        .map_err(|e| format!("Restore failed: {}", e))?
+}
+
+fn backup_job(job_id: &str) -> Result<crate::tools::backup::BackupJob, String> {
+    crate::tools::backup::BackupJobs::load().get(job_id).cloned().ok_or_else(|| format!("No backup job {}", job_id))
+}
+
+// Scratch Directory Commands
+
+/// Scratch directories of a session, or all of them
+#[tauri::command]
+pub async fn list_scratch_dirs(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<Vec<crate::terminal::scratch::ScratchDir>, String> {
+    let _timing = latency::track("list_scratch_dirs");
+    let terminal_manager = state.inner().terminal_manager.lock().await;
+    Ok(terminal_manager.scratch_dirs().list(session_id.as_deref()).into_iter().cloned().collect())
+}
+
+/// Make a scratch directory for a session, changing into it when `enter` is set
+#[tauri::command]
+pub async fn create_scratch_dir(
+    state: State<'_, AppState>,
+    session_id: String,
+    name: Option<String>,
+    policy: Option<crate::terminal::scratch::CleanupPolicy>,
+    enter: Option<bool>,
+) -> Result<crate::terminal::scratch::ScratchDir, String> {
+    let _timing = latency::track("create_scratch_dir");
+    let mut terminal_manager = state.inner().terminal_manager.lock().await;
+    terminal_manager.get_session(&session_id).ok_or("Session not found")?;
+    terminal_manager.create_scratch_dir(Some(&session_id), name.as_deref(), policy, enter.unwrap_or(true))
+}
+
+#[tauri::command]
+pub async fn remove_scratch_dir(
+    state: State<'_, AppState>,
+    scratch_id: String,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let _timing = latency::track("remove_scratch_dir");
+    let mut terminal_manager = state.inner().terminal_manager.lock().await;
+    terminal_manager.remove_scratch_dir(session_id.as_deref(), &scratch_id).map(|_| ())
+}
+
+#[tauri::command]
+pub async fn get_scratch_settings(state: State<'_, AppState>) -> Result<crate::terminal::scratch::ScratchSettings, String> {
+    let _timing = latency::track("get_scratch_settings");
+    Ok(state.inner().terminal_manager.lock().await.scratch_dirs().settings().clone())
+}
+
+#[tauri::command]
+pub async fn update_scratch_settings(
+    state: State<'_, AppState>,
+    settings: crate::terminal::scratch::ScratchSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_scratch_settings");
+    state.inner().terminal_manager.lock().await.scratch_dirs_mut().set_settings(settings)
+}
+
+/// Acceptance numbers, mode and current throttling of every suggestion category
+#[tauri::command]
+pub async fn get_suggestion_throttle(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::ai::suggestion_throttle::CategoryStatus>, String> {
+    let _timing = latency::track("get_suggestion_throttle");
+    Ok(state.inner().suggestion_throttle.lock().await.status())
+}
+
+/// Pin categories to always shown or off; unlisted categories follow their acceptance rate
+#[tauri::command]
+pub async fn update_suggestion_throttle_settings(
+    state: State<'_, AppState>,
+    settings: crate::ai::suggestion_throttle::ThrottleSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_suggestion_throttle_settings");
+    state.inner().suggestion_throttle.lock().await.set_settings(settings)
+}
+
+/// Forget the acceptance numbers of one category, or of all
+#[tauri::command]
+pub async fn reset_suggestion_stats(
+    state: State<'_, AppState>,
+    category: Option<String>,
+) -> Result<(), String> {
+    let _timing = latency::track("reset_suggestion_stats");
+    state.inner().suggestion_throttle.lock().await.reset(category.as_deref());
+    Ok(())
+}
+
+/// Why a suggestion was shown: the pattern it matched, its history, its trigger and the model behind it.
+/// Takes the id returned with proactive suggestions and translations, or the text of a completion or context suggestion
+#[tauri::command]
+pub async fn explain_suggestion(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<crate::ai::provenance::Provenance, String> {
+    let _timing = latency::track("explain_suggestion");
+    state.inner().provenance.lock().await.get(&id).cloned()
+        .ok_or_else(|| format!("No record of suggestion {}; it may be too old to explain", id))
+}
+
+/// Forget what was learned from a command (or a pattern key from a suggestion) so it stops resurfacing
+#[tauri::command]
+pub async fn reset_pattern(
+    state: State<'_, AppState>,
+    command: String,
+) -> Result<crate::ai::learning_engine::PatternReset, String> {
+    let _timing = latency::track("reset_pattern");
+    state.inner().model_manager.lock().await.reset_pattern(&command).await
+}
+
+/// Remove learned patterns that faded from disuse or were rated down
+#[tauri::command]
+pub async fn prune_learned_patterns(
+    state: State<'_, AppState>,
+) -> Result<crate::ai::learning_engine::PruneSummary, String> {
+    let _timing = latency::track("prune_learned_patterns");
+    state.inner().model_manager.lock().await.prune_patterns().await
+}
+
+/// Profiles on this machine, marking the one in use
+#[tauri::command]
+pub async fn list_profiles() -> Result<Vec<crate::system::profiles::ProfileInfo>, String> {
+    use crate::system::{app_dirs, profiles::ProfileRegistry};
+    let _timing = latency::track("list_profiles");
+    
+    let (_, base_config) = app_dirs::base_dirs();
+    Ok(ProfileRegistry::load(&base_config).list(&app_dirs::active_profile()))
+}
+
+#[tauri::command]
+pub async fn create_profile(
+    name: String,
+    passphrase: Option<String>,
+) -> Result<crate::system::profiles::ProfileInfo, String> {
+    use crate::system::{app_dirs, profiles::ProfileRegistry};
+    let _timing = latency::track("create_profile");
+    
+    let (_, base_config) = app_dirs::base_dirs();
+    ProfileRegistry::load(&base_config).create(&base_config, &name, passphrase.as_deref())
+}
+
+/// Switch to another profile; the app restarts so history, learning data, secrets and settings load from it
+#[tauri::command]
+pub async fn switch_profile(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    use crate::system::{app_dirs, profiles::ProfileRegistry};
+    let _timing = latency::track("switch_profile");
+    
+    if name == app_dirs::active_profile() {
+        return Ok(());
+    }
+    let (_, base_config) = app_dirs::base_dirs();
+    ProfileRegistry::load(&base_config).switch(&base_config, &name, passphrase.as_deref())?;
+    state.inner().model_manager.lock().await.save_learning_data().await;
+    app.restart()
+}
+
+/// Set, change or remove (with no `new_passphrase`) a profile's passphrase
+#[tauri::command]
+pub async fn set_profile_passphrase(
+    name: String,
+    current_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+) -> Result<(), String> {
+    use crate::system::{app_dirs, profiles::ProfileRegistry};
+    let _timing = latency::track("set_profile_passphrase");
+    
+    let (base_data, base_config) = app_dirs::base_dirs();
+    ProfileRegistry::load(&base_config).set_passphrase(
+        &base_data,
+        &base_config,
+        &name,
+        current_passphrase.as_deref(),
+        new_passphrase.as_deref(),
+        &app_dirs::active_profile(),
+    )
+}
+
+/// Delete a profile other than the one in use, with all its data
+#[tauri::command]
+pub async fn delete_profile(
+    name: String,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    use crate::system::{app_dirs, profiles::ProfileRegistry};
+    let _timing = latency::track("delete_profile");
+    
+    let (base_data, base_config) = app_dirs::base_dirs();
+    ProfileRegistry::load(&base_config).delete(&base_data, &base_config, &name, passphrase.as_deref(), &app_dirs::active_profile())
+}
+
+/// Restart in demo mode: synthetic history and learning data, and commands that are never really run
+#[tauri::command]
+pub async fn start_demo_mode(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    use crate::system::{app_dirs, demo, profiles::ProfileRegistry};
+    let _timing = latency::track("start_demo_mode");
+    
+    let (base_data, base_config) = app_dirs::base_dirs();
+    if demo::is_active() {
+        return Ok(());
+    }
+    ProfileRegistry::load(&base_config).enter_demo(&base_data, &base_config)?;
+    state.inner().model_manager.lock().await.save_learning_data().await;
+    app.restart()
+}
+
+/// Whether the app is in demo mode; leave it with `switch_profile`
+#[tauri::command]
+pub async fn is_demo_mode() -> Result<bool, String> {
+    let _timing = latency::track("is_demo_mode");
+    Ok(crate::system::demo::is_active())
+}
+
+/// Turn screen-reader friendly output on or off for a session
+#[tauri::command]
+pub async fn set_accessible_output(
+    state: State<'_, AppState>,
+    session_id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let _timing = latency::track("set_accessible_output");
+    state.inner().terminal_manager.lock().await.set_accessible_output(&session_id, enabled)
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SpokenSummary {
+    pub text: String,
+    /// Model that wrote it, or `heuristic`
+    pub source: String,
+}
+
+/// A summary of a command's output worded for listening, by the model routed for output analysis when there is one
+#[tauri::command]
+pub async fn ai_summarize_output(
+    state: State<'_, AppState>,
+    execution_id: String,
+) -> Result<SpokenSummary, String> {
+    use crate::models::Capability;
+    let _timing = latency::track("ai_summarize_output");
+    
+    let execution = state.inner().terminal_manager.lock().await
+        .get_execution(&execution_id)
+        .ok_or("Command not found in history")?;
+    let model_manager = state.inner().model_manager.lock().await;
+    if let Some(text) = model_manager.summarize_output_for_speech(&execution.command, &execution.output, execution.exit_code).await {
+        return Ok(SpokenSummary { text, source: model_manager.model_for(&Capability::OutputAnalysis).await });
+    }
+    Ok(SpokenSummary {
+        text: crate::terminal::accessible::spoken_summary(&execution.command, &execution.output, execution.exit_code),
+        source: "heuristic".to_string(),
+    })
+}
+
+/// Raw terminal output (streamed chunks, a pasted log) split into plain text and styled lines for display
+#[tauri::command]
+pub async fn process_ansi_output(text: String) -> Result<crate::terminal::ansi::ProcessedOutput, String> {
+    let _timing = latency::track("process_ansi_output");
+    Ok(crate::terminal::ansi::process(&text))
+}
+
+/// A redacted markdown report of a command for an issue or a teammate, plus a zip with everything when `archive` is set;
+/// the session, the command's own when none is given, supplies the directory, shell and project type
+#[tauri::command]
+pub async fn create_bug_report(
+    state: State<'_, AppState>,
+    execution_id: String,
+    session_id: Option<String>,
+    archive: Option<bool>,
+) -> Result<crate::system::bug_report::BugReport, String> {
+    let _timing = latency::track("create_bug_report");
+    let (execution, history, context) = {
+        let terminal_manager = state.inner().terminal_manager.lock().await;
+        let execution = terminal_manager.get_execution(&execution_id).ok_or("Command not found in history")?;
+        let session = session_id.as_deref().or(execution.session_id.as_deref()).and_then(|id| terminal_manager.get_session(id));
+        let context = crate::system::bug_report::ReportContext {
+            working_directory: session.map(|s| s.working_directory.clone()),
+            shell: session.map(|s| s.shell.clone()),
+            project_type: session.and_then(|s| detect_project_type(&s.working_directory)),
+            environment: session.map(|s| s.environment_vars.clone()).unwrap_or_else(|| std::env::vars().collect()),
+        };
+        (execution, terminal_manager.get_command_history(None), context)
+    };
+    crate::system::bug_report::create(&execution, &history, context, archive.unwrap_or(false)).await
+}
+
+/// Transcribe a spoken command and translate it; the command is returned for confirmation, never run
+#[tauri::command]
+pub async fn transcribe_voice_command(
+    state: State<'_, AppState>,
+    session_id: String,
+    audio_base64: String,
+    format: String,
+) -> Result<crate::ai::voice::VoiceIntake, String> {
+    use base64::Engine;
+    let _timing = latency::track("transcribe_voice_command");
+    
+    let audio = base64::engine::general_purpose::STANDARD
+        .decode(audio_base64.trim())
+        .map_err(|e| format!("Invalid audio data: {}", e))?;
+    let transcript = crate::ai::voice::transcribe(&audio, &format).await?;
+    let context = state.inner().terminal_manager.lock().await.get_smart_context(&session_id);
+    let translation = ai_translate_natural_language(state, transcript.clone(), context.await).await?;
+    Ok(crate::ai::voice::VoiceIntake {
+        transcript,
+        proposed_command: translation.text.replace("🤖 ", ""),
+        confidence: translation.confidence,
+        reasoning: translation.reasoning,
+        suggestion_id: translation.suggestion_id,
+        confirmation_required: true,
+    })
+}
+
+#[tauri::command]
+pub async fn get_voice_settings() -> Result<crate::ai::voice::VoiceSettings, String> {
+    let _timing = latency::track("get_voice_settings");
+    Ok(crate::ai::voice::VoiceSettings::load())
+}
+
+#[tauri::command]
+pub async fn update_voice_settings(
+    settings: crate::ai::voice::VoiceSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_voice_settings");
+    settings.save()
+}
+
+/// Read an AI explanation or error summary aloud, unless its category is off or notifications are being held back
+#[tauri::command]
+pub async fn speak_response(
+    state: State<'_, AppState>,
+    text: String,
+    category: crate::system::speech::SpeechCategory,
+    session_id: Option<String>,
+) -> Result<crate::system::speech::SpeechResult, String> {
+    use crate::system::speech::SpeechResult;
+    let _timing = latency::track("speak_response");
+    
+    if let Some(reason) = state.inner().speaker.lock().await.muted(category) {
+        return Ok(SpeechResult::skipped(reason));
+    }
+    if state.inner().speaker.lock().await.follows_notifications() {
+        let focus_mode = tokio::task::spawn_blocking(crate::terminal::notifications::focus_mode_active).await.unwrap_or(false);
+        if let Some(reason) = state.inner().notification_center.lock().await.suppression(session_id.as_deref(), focus_mode) {
+            return Ok(SpeechResult::skipped(reason));
+        }
+    }
+    state.inner().speaker.lock().await.speak(category, &text)
+}
+
+/// Stop reading aloud; false when nothing was being said
+#[tauri::command]
+pub async fn stop_speaking(
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let _timing = latency::track("stop_speaking");
+    Ok(state.inner().speaker.lock().await.stop())
+}
+
+#[tauri::command]
+pub async fn get_speech_settings(
+    state: State<'_, AppState>,
+) -> Result<crate::system::speech::SpeechSettings, String> {
+    let _timing = latency::track("get_speech_settings");
+    Ok(state.inner().speaker.lock().await.settings().clone())
+}
+
+#[tauri::command]
+pub async fn update_speech_settings(
+    state: State<'_, AppState>,
+    settings: crate::system::speech::SpeechSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_speech_settings");
+    state.inner().speaker.lock().await.set_settings(settings)
+}
+
+/// Voices the OS speech engine offers
+#[tauri::command]
+pub async fn list_speech_voices() -> Result<Vec<String>, String> {
+    let _timing = latency::track("list_speech_voices");
+    crate::system::speech::list_voices().await
+}
+
+/// Per-command latency percentiles, with the commands over their budget first
+#[tauri::command]
+pub async fn get_performance_report(
+    state: State<'_, AppState>,
+) -> Result<crate::system::latency::PerformanceReport, String> {
+    let _timing = latency::track("get_performance_report");
+    Ok(state.inner().latency.report())
+}
+
+#[tauri::command]
+pub async fn reset_performance_stats(
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let _timing = latency::track("reset_performance_stats");
+    state.inner().latency.reset();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_latency_budgets(
+    state: State<'_, AppState>,
+) -> Result<crate::system::latency::LatencyBudgets, String> {
+    let _timing = latency::track("get_latency_budgets");
+    Ok(state.inner().latency.budgets())
+}
+
+/// Set how long a command may take before it is flagged; `None` restores its default
+#[tauri::command]
+pub async fn set_latency_budget(
+    state: State<'_, AppState>,
+    command: String,
+    budget_ms: Option<f64>,
+) -> Result<(), String> {
+    let _timing = latency::track("set_latency_budget");
+    state.inner().latency.set_budget(&command, budget_ms)
+}
+
+#[tauri::command]
+pub async fn get_remote_access_status(
+    state: State<'_, AppState>,
+) -> Result<crate::system::remote::RemoteStatus, String> {
+    let _timing = latency::track("get_remote_access_status");
+    Ok(state.inner().remote.lock().await.status())
+}
+
+/// Serve the console over HTTPS for browsers on other devices; needs an access token first
+#[tauri::command]
+pub async fn start_remote_access(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<crate::system::remote::RemoteStatus, String> {
+    let _timing = latency::track("start_remote_access");
+    state.inner().remote.lock().await.start(app).await
+}
+
+#[tauri::command]
+pub async fn stop_remote_access(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let _timing = latency::track("stop_remote_access");
+    Ok(state.inner().remote.lock().await.stop(&app))
+}
+
+/// Address, port, certificate and start-up settings; a running server picks them up when restarted
+#[tauri::command]
+pub async fn update_remote_access_options(
+    state: State<'_, AppState>,
+    options: crate::system::remote::RemoteOptions,
+) -> Result<(), String> {
+    let _timing = latency::track("update_remote_access_options");
+    state.inner().remote.lock().await.set_options(options)
+}
+
+/// A new access token; it is returned this once and only its hash is kept
+#[tauri::command]
+pub async fn create_remote_access_token(
+    state: State<'_, AppState>,
+    name: String,
+    mode: crate::system::remote::RemoteMode,
+) -> Result<crate::system::remote::CreatedToken, String> {
+    let _timing = latency::track("create_remote_access_token");
+    state.inner().remote.lock().await.create_token(&name, mode)
+}
+
+#[tauri::command]
+pub async fn revoke_remote_access_token(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let _timing = latency::track("revoke_remote_access_token");
+    state.inner().remote.lock().await.revoke_token(&id)
+}
+
+/// A time-limited link giving a collaborator a live read-only view of a session, optionally with input;
+/// starts remote access when it is not running
+#[tauri::command]
+pub async fn share_session(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    minutes: Option<u32>,
+    allow_input: Option<bool>,
+) -> Result<crate::system::session_share::CreatedShare, String> {
+    let _timing = latency::track("share_session");
+    if state.inner().terminal_manager.lock().await.get_session(&session_id).is_none() {
+        return Err("Session not found".to_string());
+    }
+    state.inner().remote.lock().await.share_session(app, &session_id, minutes, allow_input.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn get_session_shares(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::system::session_share::SessionShare>, String> {
+    let _timing = latency::track("get_session_shares");
+    Ok(state.inner().remote.lock().await.shares())
+}
+
+/// Hand a viewer input control, or take it back
+#[tauri::command]
+pub async fn set_session_share_input(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    share_id: String,
+    allow_input: bool,
+) -> Result<crate::system::session_share::SessionShare, String> {
+    let _timing = latency::track("set_session_share_input");
+    state.inner().remote.lock().await.set_share_input(&app, &share_id, allow_input)
+}
+
+#[tauri::command]
+pub async fn stop_session_share(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    share_id: String,
+) -> Result<(), String> {
+    let _timing = latency::track("stop_session_share");
+    state.inner().remote.lock().await.stop_share(&app, &share_id)
+}
+
+#[tauri::command]
+pub async fn get_automation_status(
+    state: State<'_, AppState>,
+) -> Result<crate::system::automation::AutomationStatus, String> {
+    let _timing = latency::track("get_automation_status");
+    Ok(state.inner().automation.lock().await.status())
+}
+
+/// Open the local JSON-RPC socket for editors and scripts; each start makes a new token
+#[tauri::command]
+pub async fn start_automation(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<crate::system::automation::AutomationStatus, String> {
+    let _timing = latency::track("start_automation");
+    state.inner().automation.lock().await.start(app).await
+}
+
+#[tauri::command]
+pub async fn stop_automation(
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let _timing = latency::track("stop_automation");
+    Ok(state.inner().automation.lock().await.stop())
+}
+
+#[tauri::command]
+pub async fn update_automation_settings(
+    state: State<'_, AppState>,
+    settings: crate::system::automation::AutomationSettings,
+) -> Result<(), String> {
+    let _timing = latency::track("update_automation_settings");
+    state.inner().automation.lock().await.set_settings(settings)
+}
+
+#[tauri::command]
+pub async fn get_webhook_status(
+    state: State<'_, AppState>,
+) -> Result<crate::integrations::webhooks::WebhookStatus, String> {
+    let _timing = latency::track("get_webhook_status");
+    Ok(state.inner().webhooks.lock().await.status())
+}
+
+/// Listen for signed webhook deliveries that start the user's triggers
+#[tauri::command]
+pub async fn start_webhooks(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<crate::integrations::webhooks::WebhookStatus, String> {
+    let _timing = latency::track("start_webhooks");
+    state.inner().webhooks.lock().await.start(app).await
+}
+
+#[tauri::command]
+pub async fn stop_webhooks(
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let _timing = latency::track("stop_webhooks");
+    Ok(state.inner().webhooks.lock().await.stop())
+}
+
+/// Address, port, allowed senders and start-up settings; a running listener picks them up when restarted
+#[tauri::command]
+pub async fn update_webhook_options(
+    state: State<'_, AppState>,
+    options: crate::integrations::webhooks::WebhookOptions,
+) -> Result<(), String> {
+    let _timing = latency::track("update_webhook_options");
+    state.inner().webhooks.lock().await.set_options(options)
+}
+
+/// A new trigger; its signing secret is returned this once
+#[tauri::command]
+pub async fn create_webhook_trigger(
+    state: State<'_, AppState>,
+    name: String,
+    action: crate::integrations::webhooks::TriggerAction,
+    working_directory: Option<String>,
+) -> Result<crate::integrations::webhooks::CreatedTrigger, String> {
+    let _timing = latency::track("create_webhook_trigger");
+    state.inner().webhooks.lock().await.create_trigger(&name, action, working_directory)
+}
+
+#[tauri::command]
+pub async fn set_webhook_trigger_enabled(
+    state: State<'_, AppState>,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let _timing = latency::track("set_webhook_trigger_enabled");
+    state.inner().webhooks.lock().await.set_trigger_enabled(&id, enabled)
+}
+
+#[tauri::command]
+pub async fn delete_webhook_trigger(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let _timing = latency::track("delete_webhook_trigger");
+    state.inner().webhooks.lock().await.delete_trigger(&id)
+}
+
+/// Every delivery received, accepted or refused, and what it ran; newest first
+#[tauri::command]
+pub async fn get_webhook_audit_log(
+    limit: Option<usize>,
+) -> Result<Vec<crate::integrations::webhooks::AuditEntry>, String> {
+    let _timing = latency::track("get_webhook_audit_log");
+    Ok(crate::integrations::webhooks::audit_log(limit.unwrap_or(100)))
+}
+
+/// Slack, Discord and email destinations for command results, monitor alerts and agent reports
+#[tauri::command]
+pub async fn get_notification_sinks(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::integrations::sinks::NotificationSink>, String> {
+    let _timing = latency::track("get_notification_sinks");
+    Ok(state.inner().sinks.list())
+}
+
+/// `secret` is the webhook URL of a Slack or Discord sink, or the SMTP password of an email one
+#[tauri::command]
+pub async fn create_notification_sink(
+    state: State<'_, AppState>,
+    sink: crate::integrations::sinks::SinkDraft,
+    secret: String,
+) -> Result<crate::integrations::sinks::NotificationSink, String> {
+    let _timing = latency::track("create_notification_sink");
+    state.inner().sinks.create(sink, &secret)
+}
+
+/// Without a `secret` the stored one is kept
+#[tauri::command]
+pub async fn update_notification_sink(
+    state: State<'_, AppState>,
+    id: String,
+    sink: crate::integrations::sinks::SinkDraft,
+    enabled: bool,
+    secret: Option<String>,
+) -> Result<crate::integrations::sinks::NotificationSink, String> {
+    let _timing = latency::track("update_notification_sink");
+    state.inner().sinks.update(&id, sink, enabled, secret.as_deref())
+}
+
+#[tauri::command]
+pub async fn delete_notification_sink(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let _timing = latency::track("delete_notification_sink");
+    state.inner().sinks.delete(&id)
+}
+
+/// Send a sample message, so a sink can be checked before it matters
+#[tauri::command]
+pub async fn test_notification_sink(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let _timing = latency::track("test_notification_sink");
+    state.inner().sinks.test(&id).await
 }
 
 // Simple command execution for validation purposes
@@ -1205,6 +5481,7 @@ pub async fn execute_simple_command(
     directory: Option<String>
 ) -> Result<String, String> {
     use std::process::Command;
+    let _timing = latency::track("execute_simple_command");
     
     let working_dir = directory.unwrap_or_else(|| {
         std::env::current_dir()
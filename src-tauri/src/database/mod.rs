@@ -0,0 +1,281 @@
+// Database client for Postgres, MySQL and SQLite
+// Connection profiles are persisted in the AI data directory and pools are
+// opened lazily on first use. Passwords live in the OS keychain (Keychain on
+// macOS, Credential Manager on Windows, the Secret Service on Linux), never in a
+// file of the app; ones an older version kept in the secrets store move there on
+// first use.
+// Read-only profiles are enforced by the database itself (a read-only session
+// on Postgres and MySQL, a read-only file on SQLite), not only by looking at the
+// statement, so a write hidden in a query that looks like a read is refused.
+
+pub mod query;
+
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::integrations::secrets::SecretsStore;
+use query::TableSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseKind {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    pub name: String,
+    pub kind: DatabaseKind,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub database: Option<String>, // database name, or file path for SQLite
+    pub username: Option<String>,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Service the keychain entries of the connections are filed under
+const KEYCHAIN_SERVICE: &str = "pH7Console";
+
+impl ConnectionProfile {
+    /// Where older versions kept the password, in the secrets store
+    fn legacy_password_key(&self) -> String {
+        format!("db_password:{}", self.name)
+    }
+
+    fn password_entry(&self) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, &format!("db:{}", self.name))
+            .map_err(|e| format!("Keychain unavailable: {}", e))
+    }
+
+    fn store_password(&self, password: &str) -> Result<(), String> {
+        self.password_entry()?
+            .set_password(password)
+            .map_err(|e| format!("Failed to save the password in the keychain: {}", e))
+    }
+
+    fn forget_password(&self) -> Result<(), String> {
+        match self.password_entry()?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(format!("Failed to remove the password from the keychain: {}", e)),
+        }
+        let mut secrets = SecretsStore::load();
+        if secrets.get(&self.legacy_password_key()).is_some() {
+            secrets.remove(&self.legacy_password_key())?;
+        }
+        Ok(())
+    }
+
+    /// The password from the keychain, moving one an older version stored in the
+    /// secrets store there first. Blocks on the keychain, so call it off the runtime.
+    fn load_password(&self) -> Result<Option<String>, String> {
+        let entry = self.password_entry()?;
+        match entry.get_password() {
+            Ok(password) => return Ok(Some(password)),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(format!("Failed to read the password from the keychain: {}", e)),
+        }
+        let mut secrets = SecretsStore::load();
+        let Some(password) = secrets.get(&self.legacy_password_key()).map(str::to_string) else {
+            return Ok(None);
+        };
+        self.store_password(&password)?;
+        secrets.remove(&self.legacy_password_key())?;
+        Ok(Some(password))
+    }
+}
+
+#[derive(Clone)]
+pub enum DbPool {
+    Postgres(PgPool),
+    MySql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
+pub struct DatabaseManager {
+    profiles: HashMap<String, ConnectionProfile>,
+    pools: HashMap<String, DbPool>,
+    schemas: HashMap<String, Vec<TableSchema>>,
+}
+
+impl Default for DatabaseManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DatabaseManager {
+    pub fn new() -> Self {
+        let profiles = std::fs::read_to_string(Self::profiles_file())
+            .ok()
+            .and_then(|data| serde_json::from_str::<Vec<ConnectionProfile>>(&data).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|profile| (profile.name.clone(), profile))
+            .collect();
+
+        Self {
+            profiles,
+            pools: HashMap::new(),
+            schemas: HashMap::new(),
+        }
+    }
+
+    fn profiles_file() -> PathBuf {
+        crate::ai::config_directory().join("db_connections.json")
+    }
+
+    fn save_profiles(&self) -> Result<(), String> {
+        let file = Self::profiles_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut profiles: Vec<&ConnectionProfile> = self.profiles.values().collect();
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        let json = serde_json::to_string_pretty(&profiles).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn list_profiles(&self) -> Vec<ConnectionProfile> {
+        let mut profiles: Vec<ConnectionProfile> = self.profiles.values().cloned().collect();
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
+    pub fn get_profile(&self, name: &str) -> Option<&ConnectionProfile> {
+        self.profiles.get(name)
+    }
+
+    /// Add or replace a profile; a new password replaces the stored one
+    pub fn save_profile(&mut self, profile: ConnectionProfile, password: Option<String>) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("Connection name cannot be empty".to_string());
+        }
+        if profile.kind == DatabaseKind::Sqlite && profile.database.is_none() {
+            return Err("SQLite connections need a database file path".to_string());
+        }
+        if let Some(password) = password {
+            profile.store_password(&password)?;
+        }
+
+        // Settings may have changed, so reconnect on next use
+        self.pools.remove(&profile.name);
+        self.schemas.remove(&profile.name);
+        self.profiles.insert(profile.name.clone(), profile);
+        self.save_profiles()
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = self.profiles.remove(name)
+            .ok_or_else(|| format!("Unknown connection: {}", name))?;
+        profile.forget_password()?;
+        self.pools.remove(name);
+        self.schemas.remove(name);
+        self.save_profiles()
+    }
+
+    pub fn cached_pool(&self, name: &str) -> Option<DbPool> {
+        self.pools.get(name).cloned()
+    }
+
+    pub fn store_pool(&mut self, name: &str, pool: DbPool) {
+        self.pools.insert(name.to_string(), pool);
+    }
+
+    pub fn cached_schema(&self, name: &str) -> Option<Vec<TableSchema>> {
+        self.schemas.get(name).cloned()
+    }
+
+    pub fn store_schema(&mut self, name: &str, schema: Vec<TableSchema>) {
+        self.schemas.insert(name.to_string(), schema);
+    }
+}
+
+/// Open a small connection pool for a profile
+pub async fn connect(profile: &ConnectionProfile) -> Result<DbPool, String> {
+    let password = {
+        let profile = profile.clone();
+        tokio::task::spawn_blocking(move || profile.load_password())
+            .await
+            .map_err(|e| e.to_string())??
+    };
+    let timeout = Duration::from_secs(10);
+
+    match profile.kind {
+        DatabaseKind::Postgres => {
+            let mut options = PgConnectOptions::new()
+                .host(profile.host.as_deref().unwrap_or("localhost"))
+                .port(profile.port.unwrap_or(5432));
+            if let Some(ref username) = profile.username {
+                options = options.username(username);
+            }
+            if let Some(ref password) = password {
+                options = options.password(password);
+            }
+            if let Some(ref database) = profile.database {
+                options = options.database(database);
+            }
+            if profile.read_only {
+                options = options.options([("default_transaction_read_only", "on")]);
+            }
+            PgPoolOptions::new()
+                .max_connections(3)
+                .acquire_timeout(timeout)
+                .connect_with(options)
+                .await
+                .map(DbPool::Postgres)
+                .map_err(|e| format!("Failed to connect to {}: {}", profile.name, e))
+        }
+        DatabaseKind::MySql => {
+            let mut options = MySqlConnectOptions::new()
+                .host(profile.host.as_deref().unwrap_or("localhost"))
+                .port(profile.port.unwrap_or(3306));
+            if let Some(ref username) = profile.username {
+                options = options.username(username);
+            }
+            if let Some(ref password) = password {
+                options = options.password(password);
+            }
+            if let Some(ref database) = profile.database {
+                options = options.database(database);
+            }
+            let read_only = profile.read_only;
+            MySqlPoolOptions::new()
+                .max_connections(3)
+                .acquire_timeout(timeout)
+                .after_connect(move |connection, _| {
+                    Box::pin(async move {
+                        if read_only {
+                            sqlx::query("SET SESSION TRANSACTION READ ONLY").execute(&mut *connection).await?;
+                        }
+                        Ok(())
+                    })
+                })
+                .connect_with(options)
+                .await
+                .map(DbPool::MySql)
+                .map_err(|e| format!("Failed to connect to {}: {}", profile.name, e))
+        }
+        DatabaseKind::Sqlite => {
+            let path = profile.database.as_deref().unwrap_or_default();
+            let options = SqliteConnectOptions::new()
+                .filename(path)
+                .read_only(profile.read_only)
+                .create_if_missing(false);
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .acquire_timeout(timeout)
+                .connect_with(options)
+                .await
+                .map(DbPool::Sqlite)
+                .map_err(|e| format!("Failed to open {}: {}", path, e))
+        }
+    }
+}
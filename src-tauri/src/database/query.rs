@@ -0,0 +1,429 @@
+// Query execution, typed row conversion and schema introspection
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Column, Row, TypeInfo};
+use std::time::Instant;
+
+use super::{DatabaseKind, DbPool};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryColumn {
+    pub name: String,
+    pub type_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub columns: Vec<QueryColumn>,
+    pub rows: Vec<Vec<Value>>,
+    pub page: u32,
+    pub page_size: u32,
+    pub has_more: bool,
+    pub rows_affected: Option<u64>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+}
+
+/// Keywords that make a statement write, wherever they appear in a `WITH` or `EXPLAIN`
+const WRITE_KEYWORDS: [&str; 12] = [
+    "insert", "update", "delete", "merge", "upsert", "replace", "create", "drop", "alter", "truncate", "grant", "revoke",
+];
+
+/// SQLite pragmas that only report, whatever argument they are given
+const READ_ONLY_PRAGMAS: [&str; 16] = [
+    "table_info", "table_xinfo", "table_list", "index_list", "index_info", "index_xinfo", "foreign_key_list",
+    "foreign_key_check", "integrity_check", "quick_check", "database_list", "collation_list", "function_list",
+    "module_list", "pragma_list", "compile_options",
+];
+
+/// SQLite pragmas that report a setting when given no value, and change it when given one
+const SETTING_PRAGMAS: [&str; 12] = [
+    "user_version", "schema_version", "application_id", "journal_mode", "page_size", "page_count",
+    "freelist_count", "encoding", "foreign_keys", "auto_vacuum", "cache_size", "data_version",
+];
+
+/// The statements of `sql` with comments removed, split on the semicolons outside
+/// quotes, identifiers and Postgres dollar-quoted bodies
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                current.push(c);
+                for inner in chars.by_ref() {
+                    current.push(inner);
+                    if inner == c {
+                        break;
+                    }
+                }
+            }
+            // `#` starts a comment on MySQL
+            '#' => {
+                for inner in chars.by_ref() {
+                    if inner == '\n' {
+                        break;
+                    }
+                }
+                current.push(' ');
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for inner in chars.by_ref() {
+                    if inner == '\n' {
+                        break;
+                    }
+                }
+                current.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for inner in chars.by_ref() {
+                    if previous == '*' && inner == '/' {
+                        break;
+                    }
+                    previous = inner;
+                }
+                current.push(' ');
+            }
+            '$' => {
+                // `$$ … $$` or `$tag$ … $tag$`; `$1` parameters have no closing `$`
+                current.push('$');
+                let tag: String = chars.clone().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+                let tag_len = tag.chars().count();
+                if tag.starts_with(|c: char| c.is_ascii_digit()) || chars.clone().nth(tag_len) != Some('$') {
+                    continue;
+                }
+                current.extend(chars.by_ref().take(tag_len + 1));
+                let delimiter = format!("${}$", tag);
+                let body = current.len();
+                while !current[body..].ends_with(&delimiter) {
+                    match chars.next() {
+                        Some(inner) => current.push(inner),
+                        None => break,
+                    }
+                }
+            }
+            ';' => statements.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    statements.push(current);
+    statements.into_iter().map(|statement| statement.trim().to_string()).filter(|statement| !statement.is_empty()).collect()
+}
+
+/// Refuses a batch: each statement has to be read or confirmed on its own
+pub fn single_statement(sql: &str) -> Result<(), String> {
+    match split_statements(sql).len() {
+        0 => Err("Nothing to run".to_string()),
+        1 => Ok(()),
+        _ => Err("Run one statement at a time".to_string()),
+    }
+}
+
+fn statement_is_read_only(statement: &str) -> bool {
+    let lowered = statement.to_lowercase();
+    let mut words = lowered.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|word| !word.is_empty());
+    match words.next().unwrap_or("") {
+        "show" | "describe" | "desc" => true,
+        // `SELECT … INTO` creates a table on Postgres and writes a file on MySQL
+        "select" | "values" | "with" | "explain" => !words.any(|word| WRITE_KEYWORDS.contains(&word) || word == "into"),
+        "pragma" => {
+            let name = words.find(|word| *word != "main" && *word != "temp").unwrap_or("");
+            let sets = lowered.contains('=') || lowered.contains('(');
+            READ_ONLY_PRAGMAS.contains(&name) || (SETTING_PRAGMAS.contains(&name) && !sets)
+        }
+        _ => false,
+    }
+}
+
+/// Statements that only read data and can be paginated or run on read-only profiles.
+/// `WITH` and `EXPLAIN` can wrap a write (`EXPLAIN ANALYZE DELETE …` runs the delete),
+/// so those count as writes when any write keyword appears in them; of the pragmas
+/// only the ones that report count, and every statement of a batch has to read.
+pub fn is_read_only(sql: &str) -> bool {
+    let statements = split_statements(sql);
+    !statements.is_empty() && statements.iter().all(|statement| statement_is_read_only(statement))
+}
+
+fn is_pageable(sql: &str) -> bool {
+    let first = sql.split_whitespace().next().unwrap_or("").to_lowercase();
+    matches!(first.as_str(), "select" | "with" | "values")
+}
+
+// Try each Rust type in turn until the driver accepts one for the column
+macro_rules! decode_cell {
+    ($row:expr, $index:expr, [$($ty:ty),*]) => {{
+        let row = $row;
+        let index = $index;
+        'decoded: {
+            $(
+                if let Ok(value) = row.try_get::<Option<$ty>, _>(index) {
+                    break 'decoded value
+                        .map(|v| serde_json::to_value(v).unwrap_or(Value::Null))
+                        .unwrap_or(Value::Null);
+                }
+            )*
+            if let Ok(bytes) = row.try_get::<Option<Vec<u8>>, _>(index) {
+                break 'decoded bytes
+                    .map(|b| Value::String(format!("<{} bytes>", b.len())))
+                    .unwrap_or(Value::Null);
+            }
+            Value::String(format!("<{}>", row.columns()[index].type_info().name()))
+        }
+    }};
+}
+
+macro_rules! convert_rows {
+    ($rows:expr) => {{
+        let rows = $rows;
+        let columns: Vec<QueryColumn> = rows.first()
+            .map(|row| {
+                row.columns().iter()
+                    .map(|column| QueryColumn {
+                        name: column.name().to_string(),
+                        type_name: column.type_info().name().to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let values: Vec<Vec<Value>> = rows.iter()
+            .map(|row| {
+                (0..row.columns().len())
+                    .map(|index| {
+                        // SQLite and MySQL accept integers as bool, so only decode declared booleans as such
+                        let type_name = row.columns()[index].type_info().name().to_uppercase();
+                        if type_name.contains("BOOL") {
+                            decode_cell!(row, index, [bool])
+                        } else {
+                            decode_cell!(row, index, [
+                                i64, i32, i16, f64, f32, String,
+                                chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate,
+                                chrono::NaiveTime, serde_json::Value
+                            ])
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        (columns, values)
+    }};
+}
+
+/// Run a statement. Reads are paginated with LIMIT/OFFSET; writes report rows affected.
+pub async fn run_query(pool: &DbPool, sql: &str, page: u32, page_size: u32) -> Result<QueryResult, String> {
+    let started = Instant::now();
+    single_statement(sql)?;
+    let sql = sql.trim().trim_end_matches(';');
+    let page_size = page_size.clamp(1, 5000);
+
+    if !is_read_only(sql) {
+        let rows_affected = match pool {
+            DbPool::Postgres(pool) => sqlx::query(sql).execute(pool).await.map(|r| r.rows_affected()),
+            DbPool::MySql(pool) => sqlx::query(sql).execute(pool).await.map(|r| r.rows_affected()),
+            DbPool::Sqlite(pool) => sqlx::query(sql).execute(pool).await.map(|r| r.rows_affected()),
+        }
+        .map_err(|e| e.to_string())?;
+
+        return Ok(QueryResult {
+            columns: Vec::new(),
+            rows: Vec::new(),
+            page: 0,
+            page_size,
+            has_more: false,
+            rows_affected: Some(rows_affected),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+
+    // Fetch one extra row to know whether another page exists
+    let (statement, offset_in_memory) = if is_pageable(sql) {
+        (
+            format!(
+                "SELECT * FROM ({}) AS paged_query LIMIT {} OFFSET {}",
+                sql,
+                page_size + 1,
+                page as u64 * page_size as u64
+            ),
+            false,
+        )
+    } else {
+        (sql.to_string(), true)
+    };
+
+    let (columns, mut rows) = match pool {
+        DbPool::Postgres(pool) => {
+            let rows = sqlx::query(&statement).fetch_all(pool).await.map_err(|e| e.to_string())?;
+            convert_rows!(&rows)
+        }
+        DbPool::MySql(pool) => {
+            let rows = sqlx::query(&statement).fetch_all(pool).await.map_err(|e| e.to_string())?;
+            convert_rows!(&rows)
+        }
+        DbPool::Sqlite(pool) => {
+            let rows = sqlx::query(&statement).fetch_all(pool).await.map_err(|e| e.to_string())?;
+            convert_rows!(&rows)
+        }
+    };
+
+    if offset_in_memory {
+        let start = (page as usize * page_size as usize).min(rows.len());
+        rows.drain(..start);
+    }
+    let has_more = rows.len() > page_size as usize;
+    rows.truncate(page_size as usize);
+
+    Ok(QueryResult {
+        columns,
+        rows,
+        page,
+        page_size,
+        has_more,
+        rows_affected: None,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Tables and columns, used for completions and as context for NL-to-SQL
+pub async fn introspect_schema(pool: &DbPool, kind: DatabaseKind) -> Result<Vec<TableSchema>, String> {
+    let sql = match kind {
+        DatabaseKind::Postgres => {
+            "SELECT table_name::text, column_name::text, data_type::text, is_nullable::text \
+             FROM information_schema.columns \
+             WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+             ORDER BY table_name, ordinal_position"
+        }
+        DatabaseKind::MySql => {
+            "SELECT CAST(table_name AS CHAR), CAST(column_name AS CHAR), CAST(data_type AS CHAR), CAST(is_nullable AS CHAR) \
+             FROM information_schema.columns \
+             WHERE table_schema = DATABASE() \
+             ORDER BY table_name, ordinal_position"
+        }
+        DatabaseKind::Sqlite => {
+            "SELECT m.name, p.name, p.type, CASE p.\"notnull\" WHEN 0 THEN 'YES' ELSE 'NO' END \
+             FROM sqlite_master m JOIN pragma_table_info(m.name) p \
+             WHERE m.type IN ('table', 'view') AND m.name NOT LIKE 'sqlite_%' \
+             ORDER BY m.name, p.cid"
+        }
+    };
+
+    let result = run_query(pool, sql, 0, 5000).await?;
+    let mut tables: Vec<TableSchema> = Vec::new();
+    for row in result.rows {
+        let text = |index: usize| row.get(index).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let table_name = text(0);
+        let column = ColumnSchema {
+            name: text(1),
+            data_type: text(2),
+            nullable: text(3) == "YES",
+        };
+        match tables.last_mut() {
+            Some(table) if table.name == table_name => table.columns.push(column),
+            _ => tables.push(TableSchema { name: table_name, columns: vec![column] }),
+        }
+    }
+    Ok(tables)
+}
+
+/// Table and column names starting with the prefix
+pub fn completions(schema: &[TableSchema], prefix: &str) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    let mut candidates: Vec<String> = schema.iter()
+        .flat_map(|table| {
+            std::iter::once(table.name.clone())
+                .chain(table.columns.iter().map(move |column| format!("{}.{}", table.name, column.name)))
+                .chain(table.columns.iter().map(|column| column.name.clone()))
+        })
+        .filter(|candidate| candidate.to_lowercase().starts_with(&prefix))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    candidates.truncate(50);
+    candidates
+}
+
+/// Simple description-to-SQL for common requests ("count users", "show orders")
+pub fn draft_from_schema(description: &str, schema: &[TableSchema]) -> Option<String> {
+    let description = description.to_lowercase();
+    let table = schema.iter()
+        .filter(|table| {
+            let name = table.name.to_lowercase();
+            description.contains(&name) || description.contains(name.trim_end_matches('s'))
+        })
+        .max_by_key(|table| table.name.len())?;
+
+    if description.contains("count") || description.contains("how many") {
+        Some(format!("SELECT COUNT(*) FROM {}", table.name))
+    } else if description.contains("latest") || description.contains("recent") || description.contains("newest") {
+        let order_column = table.columns.iter()
+            .find(|column| {
+                let name = column.name.to_lowercase();
+                name.contains("created") || name.contains("updated") || name.ends_with("_at") || name == "id"
+            })
+            .map(|column| column.name.clone());
+        match order_column {
+            Some(column) => Some(format!("SELECT * FROM {} ORDER BY {} DESC LIMIT 20", table.name, column)),
+            None => Some(format!("SELECT * FROM {} LIMIT 20", table.name)),
+        }
+    } else {
+        Some(format!("SELECT * FROM {} LIMIT 100", table.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_split_outside_quotes_and_comments() {
+        assert_eq!(split_statements("SELECT 1; DROP TABLE x;"), vec!["SELECT 1", "DROP TABLE x"]);
+        assert_eq!(split_statements("SELECT ';' AS a, \"b;c\" FROM t").len(), 1);
+        assert_eq!(split_statements("SELECT 1 -- ; DROP TABLE x\n").len(), 1);
+        assert_eq!(split_statements("SELECT /* ; */ 1").len(), 1);
+        assert_eq!(split_statements("SELECT 1 # ; DROP TABLE x").len(), 1);
+        assert_eq!(split_statements("SELECT $body$ a; b $body$, $1 FROM t; SELECT 2").len(), 2);
+        assert_eq!(split_statements("SELECT $$ ; $$").len(), 1);
+        assert!(split_statements(" ; ;").is_empty());
+    }
+
+    #[test]
+    fn every_statement_has_to_read() {
+        assert!(is_read_only("SELECT * FROM users;"));
+        assert!(is_read_only("select 1; select 2"));
+        assert!(!is_read_only("SELECT 1; DROP TABLE x"));
+        assert!(!is_read_only("-- SELECT\nDROP TABLE x"));
+        assert!(!is_read_only("/* SELECT */ DELETE FROM x"));
+        assert!(!is_read_only("WITH gone AS (DELETE FROM x RETURNING *) SELECT * FROM gone"));
+        assert!(!is_read_only("EXPLAIN ANALYZE DELETE FROM x"));
+        assert!(!is_read_only("SELECT * INTO backup FROM users"));
+        assert!(!is_read_only(""));
+        assert!(single_statement("SELECT 1; SELECT 2").is_err());
+        assert!(single_statement("SELECT ';';").is_ok());
+    }
+
+    #[test]
+    fn only_reporting_pragmas_are_read_only() {
+        assert!(is_read_only("PRAGMA table_info(users)"));
+        assert!(is_read_only("PRAGMA main.index_list('users')"));
+        assert!(is_read_only("PRAGMA user_version"));
+        assert!(!is_read_only("PRAGMA user_version = 3"));
+        assert!(!is_read_only("PRAGMA journal_mode(WAL)"));
+        assert!(!is_read_only("PRAGMA optimize"));
+        assert!(!is_read_only("PRAGMA wal_checkpoint(TRUNCATE)"));
+        assert!(!is_read_only("PRAGMA writable_schema = ON"));
+    }
+}
@@ -0,0 +1,175 @@
+// CI status awareness
+// Detects the CI configuration of a repository and fetches the latest
+// pipeline run for the current branch through the forge CLIs
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::forge::{summarize_checks, Forge};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLabCi,
+}
+
+impl CiProvider {
+    /// CI systems configured in the repository `repo_path` is in, which may be a subdirectory
+    pub fn detect(repo_path: &str) -> Vec<Self> {
+        let root = repository_root(Path::new(repo_path));
+        let path = root.as_path();
+        let mut providers = Vec::new();
+
+        let has_workflows = std::fs::read_dir(path.join(".github").join("workflows"))
+            .map(|entries| {
+                entries.flatten().any(|entry| {
+                    matches!(
+                        entry.path().extension().and_then(|e| e.to_str()),
+                        Some("yml") | Some("yaml")
+                    )
+                })
+            })
+            .unwrap_or(false);
+        if has_workflows {
+            providers.push(CiProvider::GitHubActions);
+        }
+        if path.join(".gitlab-ci.yml").exists() {
+            providers.push(CiProvider::GitLabCi);
+        }
+        providers
+    }
+}
+
+/// Nearest directory at or above `path` with a `.git` entry (a file in worktrees), or `path` itself
+fn repository_root(path: &Path) -> PathBuf {
+    path.ancestors().find(|dir| dir.join(".git").exists()).unwrap_or(path).to_path_buf()
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters, for a value in a query string
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CiStatus {
+    pub provider: CiProvider,
+    pub branch: String,
+    pub status: String, // success, failure, pending
+    pub workflow: Option<String>,
+    pub run_id: Option<String>,
+    pub url: Option<String>,
+}
+
+impl CiStatus {
+    pub fn is_failing(&self) -> bool {
+        self.status == "failure"
+    }
+
+    /// One-line summary for AI prompts and status displays
+    pub fn summary(&self) -> String {
+        match self.workflow {
+            Some(ref workflow) => format!("CI ({}): {} on {}", workflow, self.status, self.branch),
+            None => format!("CI: {} on {}", self.status, self.branch),
+        }
+    }
+
+    /// Follow-up commands for inspecting and retrying a red pipeline
+    pub fn follow_up_commands(&self) -> Vec<String> {
+        let run_arg = self.run_id.as_ref().map(|id| format!(" {}", id)).unwrap_or_default();
+        match self.provider {
+            CiProvider::GitHubActions => vec![
+                format!("gh run view{} --log-failed", run_arg),
+                format!("gh run rerun{} --failed", run_arg),
+                format!("gh run list --branch {}", self.branch),
+            ],
+            CiProvider::GitLabCi => vec![
+                format!("glab ci view {}", self.branch),
+                format!("glab ci trace --branch {}", self.branch),
+                format!("glab ci retry{}", run_arg),
+            ],
+        }
+    }
+}
+
+/// Latest pipeline for the current branch; requires a configured forge token
+pub async fn fetch_branch_status(repo_path: &str) -> Result<Option<CiStatus>, String> {
+    let provider = match CiProvider::detect(repo_path).into_iter().next() {
+        Some(provider) => provider,
+        None => return Ok(None),
+    };
+
+    let forge = Forge::for_repo(repo_path)?;
+    if !forge.has_token() {
+        return Err("No forge token configured; set one to enable CI status".to_string());
+    }
+
+    let branch = std::process::Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(repo_path)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .map_err(|e| format!("Failed to read current branch: {}", e))?;
+
+    match provider {
+        CiProvider::GitHubActions => {
+            let output = forge.run(&[
+                "run", "list", "--branch", &branch, "--limit", "1",
+                "--json", "databaseId,status,conclusion,workflowName,url",
+            ]).await?;
+            let json: Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+            let run = match json.as_array().and_then(|runs| runs.first()) {
+                Some(run) => run,
+                None => return Ok(None),
+            };
+
+            let state = match run.get("status").and_then(|s| s.as_str()) {
+                Some("completed") => run.get("conclusion").and_then(|c| c.as_str()).unwrap_or("success"),
+                Some(status) => status,
+                None => "pending",
+            };
+            Ok(Some(CiStatus {
+                provider,
+                branch,
+                status: summarize_checks(&[state.to_lowercase()]).unwrap_or_else(|| "pending".to_string()),
+                workflow: run.get("workflowName").and_then(|w| w.as_str()).map(|w| w.to_string()),
+                run_id: run.get("databaseId").and_then(|id| id.as_u64()).map(|id| id.to_string()),
+                url: run.get("url").and_then(|u| u.as_str()).map(|u| u.to_string()),
+            }))
+        }
+        CiProvider::GitLabCi => {
+            let endpoint = format!("projects/:id/pipelines?ref={}&per_page=1", encode_query_value(&branch));
+            let output = forge.run(&["api", &endpoint]).await?;
+            let json: Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+            let pipeline = match json.as_array().and_then(|pipelines| pipelines.first()) {
+                Some(pipeline) => pipeline,
+                None => return Ok(None),
+            };
+
+            let state = pipeline.get("status").and_then(|s| s.as_str()).unwrap_or("pending");
+            Ok(Some(CiStatus {
+                provider,
+                branch,
+                status: summarize_checks(&[state.to_lowercase()]).unwrap_or_else(|| "pending".to_string()),
+                workflow: None,
+                run_id: pipeline.get("id").and_then(|id| id.as_u64()).map(|id| id.to_string()),
+                url: pipeline.get("web_url").and_then(|u| u.as_str()).map(|u| u.to_string()),
+            }))
+        }
+    }
+}
+
+/// CI status for smart context, bounded so a slow forge never stalls the terminal
+pub async fn context_status(repo_path: &str) -> Option<CiStatus> {
+    tokio::time::timeout(std::time::Duration::from_secs(5), fetch_branch_status(repo_path))
+        .await
+        .ok()?
+        .ok()
+        .flatten()
+}
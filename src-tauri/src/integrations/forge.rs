@@ -0,0 +1,326 @@
+// GitHub / GitLab integration for pull requests and merge requests
+// Talks to the forge through the official CLIs (gh, glab), passing the token
+// from the secrets store so no separate CLI login is required
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Command;
+
+use super::secrets::SecretsStore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+impl ForgeKind {
+    pub fn detect(remote_url: &str) -> Option<Self> {
+        let url = remote_url.to_lowercase();
+        if url.contains("github") {
+            Some(ForgeKind::GitHub)
+        } else if url.contains("gitlab") {
+            Some(ForgeKind::GitLab)
+        } else {
+            None
+        }
+    }
+
+    /// Key of the token in the secrets store
+    pub fn token_key(&self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "github_token",
+            ForgeKind::GitLab => "gitlab_token",
+        }
+    }
+
+    fn cli(&self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "gh",
+            ForgeKind::GitLab => "glab",
+        }
+    }
+
+    fn token_env(&self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "GH_TOKEN",
+            ForgeKind::GitLab => "GITLAB_TOKEN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub author: String,
+    pub source_branch: String,
+    pub target_branch: String,
+    pub url: String,
+    pub state: String,
+    pub draft: bool,
+}
+
+/// Open PR for the current branch and its CI checks, for smart context
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestStatus {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub ci_status: Option<String>, // success, failure, pending
+}
+
+pub struct Forge {
+    kind: ForgeKind,
+    repo_path: String,
+    token: Option<String>,
+}
+
+impl Forge {
+    /// Detect the forge from the repo's origin remote
+    pub fn for_repo(repo_path: &str) -> Result<Self, String> {
+        let remote_url = git_output(repo_path, &["remote", "get-url", "origin"])
+            .ok_or_else(|| "No 'origin' remote configured for this repository".to_string())?;
+        let kind = ForgeKind::detect(&remote_url)
+            .ok_or_else(|| format!("Unsupported forge for remote: {}", remote_url))?;
+        let token = SecretsStore::load().get(kind.token_key()).map(|t| t.to_string());
+
+        Ok(Self {
+            kind,
+            repo_path: repo_path.to_string(),
+            token,
+        })
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub(super) async fn run(&self, args: &[&str]) -> Result<String, String> {
+        let mut command = tokio::process::Command::new(self.kind.cli());
+        command.args(args).current_dir(&self.repo_path);
+        if let Some(ref token) = self.token {
+            command.env(self.kind.token_env(), token);
+        }
+
+        let output = tokio::time::timeout(std::time::Duration::from_secs(30), command.output())
+            .await
+            .map_err(|_| format!("{} {} timed out", self.kind.cli(), args.join(" ")))?
+            .map_err(|e| format!("Failed to run {} (is it installed?): {}", self.kind.cli(), e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{} failed: {}", self.kind.cli(), stderr.trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    pub async fn list_pull_requests(&self, state: Option<&str>) -> Result<Vec<PullRequest>, String> {
+        let state = state.unwrap_or("open");
+        let output = match self.kind {
+            ForgeKind::GitHub => self.run(&[
+                "pr", "list", "--state", state,
+                "--json", "number,title,author,headRefName,baseRefName,url,state,isDraft",
+            ]).await?,
+            ForgeKind::GitLab => {
+                let state_flag = match state {
+                    "closed" => "--closed",
+                    "merged" => "--merged",
+                    "all" => "--all",
+                    _ => "--opened",
+                };
+                self.run(&["mr", "list", state_flag, "--output", "json"]).await?
+            }
+        };
+
+        let json: Value = serde_json::from_str(&output)
+            .map_err(|e| format!("Failed to parse {} output: {}", self.kind.cli(), e))?;
+        Ok(json.as_array()
+            .map(|items| items.iter().map(|item| self.parse_pull_request(item)).collect())
+            .unwrap_or_default())
+    }
+
+    fn parse_pull_request(&self, item: &Value) -> PullRequest {
+        let str_field = |pointer: &str| {
+            item.pointer(pointer).and_then(|v| v.as_str()).unwrap_or("").to_string()
+        };
+        match self.kind {
+            ForgeKind::GitHub => PullRequest {
+                number: item.get("number").and_then(|n| n.as_u64()).unwrap_or(0),
+                title: str_field("/title"),
+                author: str_field("/author/login"),
+                source_branch: str_field("/headRefName"),
+                target_branch: str_field("/baseRefName"),
+                url: str_field("/url"),
+                state: str_field("/state").to_lowercase(),
+                draft: item.get("isDraft").and_then(|d| d.as_bool()).unwrap_or(false),
+            },
+            ForgeKind::GitLab => PullRequest {
+                number: item.get("iid").and_then(|n| n.as_u64()).unwrap_or(0),
+                title: str_field("/title"),
+                author: str_field("/author/username"),
+                source_branch: str_field("/source_branch"),
+                target_branch: str_field("/target_branch"),
+                url: str_field("/web_url"),
+                state: str_field("/state"),
+                draft: item.get("draft").and_then(|d| d.as_bool()).unwrap_or(false),
+            },
+        }
+    }
+
+    /// Open a PR/MR for the current branch and return its URL
+    pub async fn create_pull_request(
+        &self,
+        title: &str,
+        body: &str,
+        base: &str,
+        draft: bool,
+    ) -> Result<String, String> {
+        let mut args: Vec<&str> = match self.kind {
+            ForgeKind::GitHub => vec!["pr", "create", "--title", title, "--body", body, "--base", base],
+            ForgeKind::GitLab => vec![
+                "mr", "create", "--title", title, "--description", body,
+                "--target-branch", base, "--yes",
+            ],
+        };
+        if draft {
+            args.push("--draft");
+        }
+
+        let output = self.run(&args).await?;
+        // Both CLIs print the new PR/MR URL as the last line
+        Ok(output.lines()
+            .rev()
+            .find(|line| line.trim().starts_with("http"))
+            .unwrap_or(output.trim())
+            .trim()
+            .to_string())
+    }
+
+    pub async fn checkout_pull_request(&self, number: u64) -> Result<String, String> {
+        let number = number.to_string();
+        match self.kind {
+            ForgeKind::GitHub => self.run(&["pr", "checkout", &number]).await,
+            ForgeKind::GitLab => self.run(&["mr", "checkout", &number]).await,
+        }
+    }
+
+    /// The open PR/MR for the current branch with a summarized CI state
+    pub async fn current_branch_status(&self) -> Result<Option<PullRequestStatus>, String> {
+        match self.kind {
+            ForgeKind::GitHub => {
+                let output = match self.run(&["pr", "view", "--json", "number,title,url,state,statusCheckRollup"]).await {
+                    Ok(output) => output,
+                    Err(_) => return Ok(None), // no PR for this branch
+                };
+                let json: Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+                if json.get("state").and_then(|s| s.as_str()) != Some("OPEN") {
+                    return Ok(None);
+                }
+                let checks: Vec<String> = json.get("statusCheckRollup")
+                    .and_then(|c| c.as_array())
+                    .map(|checks| {
+                        checks.iter()
+                            .filter_map(|check| {
+                                // CheckRun entries report a conclusion once finished, StatusContext a state
+                                check.get("conclusion").and_then(|c| c.as_str()).filter(|c| !c.is_empty())
+                                    .or_else(|| check.get("state").and_then(|s| s.as_str()))
+                                    .or_else(|| check.get("status").and_then(|s| s.as_str()))
+                                    .map(|s| s.to_lowercase())
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Ok(Some(PullRequestStatus {
+                    number: json.get("number").and_then(|n| n.as_u64()).unwrap_or(0),
+                    title: json.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+                    url: json.get("url").and_then(|u| u.as_str()).unwrap_or("").to_string(),
+                    ci_status: summarize_checks(&checks),
+                }))
+            }
+            ForgeKind::GitLab => {
+                let branch = match git_output(&self.repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]) {
+                    Some(branch) => branch,
+                    None => return Ok(None),
+                };
+                let output = match self.run(&["mr", "view", &branch, "--output", "json"]).await {
+                    Ok(output) => output,
+                    Err(_) => return Ok(None),
+                };
+                let json: Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+                if json.get("state").and_then(|s| s.as_str()) != Some("opened") {
+                    return Ok(None);
+                }
+                let pipeline = json.pointer("/head_pipeline/status")
+                    .and_then(|s| s.as_str())
+                    .map(|s| vec![s.to_string()])
+                    .unwrap_or_default();
+
+                Ok(Some(PullRequestStatus {
+                    number: json.get("iid").and_then(|n| n.as_u64()).unwrap_or(0),
+                    title: json.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+                    url: json.get("web_url").and_then(|u| u.as_str()).unwrap_or("").to_string(),
+                    ci_status: summarize_checks(&pipeline),
+                }))
+            }
+        }
+    }
+}
+
+/// Collapse individual check states into success / failure / pending
+pub(super) fn summarize_checks(states: &[String]) -> Option<String> {
+    if states.is_empty() {
+        return None;
+    }
+    let failed = ["failure", "failed", "error", "cancelled", "canceled", "timed_out", "action_required"];
+    let pending = ["pending", "queued", "in_progress", "running", "created", "waiting", "expected"];
+
+    if states.iter().any(|s| failed.contains(&s.as_str())) {
+        Some("failure".to_string())
+    } else if states.iter().any(|s| pending.contains(&s.as_str())) {
+        Some("pending".to_string())
+    } else {
+        Some("success".to_string())
+    }
+}
+
+fn git_output(repo_path: &str, args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|output| !output.is_empty())
+}
+
+/// Default base branch for new PRs, from origin/HEAD
+pub fn default_base_branch(repo_path: &str) -> String {
+    git_output(repo_path, &["rev-parse", "--abbrev-ref", "origin/HEAD"])
+        .and_then(|head| head.strip_prefix("origin/").map(|b| b.to_string()))
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Commit subjects on the current branch that are not yet in `base`
+pub fn branch_commits(repo_path: &str, base: &str) -> Vec<String> {
+    git_output(repo_path, &["log", "--format=%s", &format!("origin/{}..HEAD", base)])
+        .or_else(|| git_output(repo_path, &["log", "--format=%s", &format!("{}..HEAD", base)]))
+        .map(|log| log.lines().map(|line| line.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// PR status for smart context; only queried when a forge token is configured
+/// so that plain terminals never block on network calls
+pub async fn context_status(repo_path: &str) -> Option<PullRequestStatus> {
+    let forge = Forge::for_repo(repo_path).ok()?;
+    if !forge.has_token() {
+        return None;
+    }
+    tokio::time::timeout(std::time::Duration::from_secs(5), forge.current_branch_status())
+        .await
+        .ok()?
+        .ok()
+        .flatten()
+}
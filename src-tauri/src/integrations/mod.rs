@@ -0,0 +1,9 @@
+// External service integrations: code forges, CI providers and the
+// credentials needed to talk to them, webhooks that let outside events in and
+// sinks that post results out to chat and email
+
+pub mod secrets;
+pub mod forge;
+pub mod ci;
+pub mod webhooks;
+pub mod sinks;
@@ -0,0 +1,136 @@
+// Local secrets store for integration tokens
+// Secrets are kept in a JSON file in the AI data directory that is only
+// readable by the current user (created that way, never narrowed afterwards);
+// values are never returned to the frontend. A profile with a passphrase keeps
+// them sealed instead (ChaCha20-Poly1305, with a key only its passphrase unlocks).
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const PLAIN_FILE: &str = "secrets.json";
+const SEALED_FILE: &str = "secrets.sealed";
+const NONCE_LENGTH: usize = 12;
+
+/// Key the active profile's secrets are sealed with; none for profiles without a passphrase
+fn active_key() -> &'static Mutex<Option<[u8; 32]>> {
+    static KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+    &KEY
+}
+
+pub fn set_key(key: Option<[u8; 32]>) {
+    *active_key().lock().unwrap_or_else(|e| e.into_inner()) = key;
+}
+
+fn current_key() -> Option<[u8; 32]> {
+    *active_key().lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Encrypt with a fresh nonce, which is put in front of the ciphertext
+pub fn seal(key: &[u8; 32], plain: &[u8]) -> Vec<u8> {
+    let nonce = random_bytes::<NONCE_LENGTH>();
+    let sealed = ChaCha20Poly1305::new(key.into()).encrypt(Nonce::from_slice(&nonce), plain).expect("in-memory encryption does not fail");
+    [nonce.as_slice(), &sealed].concat()
+}
+
+pub fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < NONCE_LENGTH {
+        return Err("Sealed data is truncated".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    ChaCha20Poly1305::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Sealed data does not open with this key".to_string())
+}
+
+/// Write a file that is private from the start, then move it over the old one,
+/// so the contents are never readable by others, even for a moment
+fn write_private(file: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let temp = file.with_extension("tmp");
+    let _ = std::fs::remove_file(&temp);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut handle = options.open(&temp).map_err(|e| format!("Failed to write secrets: {}", e))?;
+    std::io::Write::write_all(&mut handle, contents).map_err(|e| format!("Failed to write secrets: {}", e))?;
+    handle.sync_all().map_err(|e| format!("Failed to write secrets: {}", e))?;
+    std::fs::rename(&temp, file).map_err(|e| format!("Failed to write secrets: {}", e))
+}
+
+/// Seal the plain secrets file in a profile's data directory, when a passphrase is set
+pub fn seal_file(dir: &Path, key: &[u8; 32]) -> Result<(), String> {
+    let plain = dir.join(PLAIN_FILE);
+    let Ok(contents) = std::fs::read(&plain) else { return Ok(()) };
+    write_private(&dir.join(SEALED_FILE), &seal(key, &contents))?;
+    std::fs::remove_file(&plain).map_err(|e| format!("Failed to remove {}: {}", plain.display(), e))
+}
+
+/// Back to a plain file, when a profile's passphrase is removed
+pub fn unseal_file(dir: &Path, key: &[u8; 32]) -> Result<(), String> {
+    let sealed = dir.join(SEALED_FILE);
+    let Ok(contents) = std::fs::read(&sealed) else { return Ok(()) };
+    write_private(&dir.join(PLAIN_FILE), &open(key, &contents)?)?;
+    std::fs::remove_file(&sealed).map_err(|e| format!("Failed to remove {}: {}", sealed.display(), e))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretsStore {
+    secrets: HashMap<String, String>,
+}
+
+impl SecretsStore {
+    fn secrets_file(key: Option<&[u8; 32]>) -> PathBuf {
+        crate::ai::data_directory().join(if key.is_some() { SEALED_FILE } else { PLAIN_FILE })
+    }
+
+    pub fn load() -> Self {
+        let key = current_key();
+        let contents = std::fs::read(Self::secrets_file(key.as_ref())).ok();
+        let contents = match (key, contents) {
+            (Some(key), Some(sealed)) => open(&key, &sealed).map_err(|e| println!("⚠️ Cannot read secrets: {}", e)).ok(),
+            (_, contents) => contents,
+        };
+        contents.and_then(|data| serde_json::from_slice(&data).ok()).unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let key = current_key();
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let contents = match &key {
+            Some(key) => seal(key, json.as_bytes()),
+            None => json.into_bytes(),
+        };
+        write_private(&Self::secrets_file(key.as_ref()), &contents)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.secrets.get(key).map(|value| value.as_str())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.secrets.insert(key.to_string(), value.to_string());
+        self.save()
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<(), String> {
+        self.secrets.remove(key);
+        self.save()
+    }
+}
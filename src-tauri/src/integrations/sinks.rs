@@ -0,0 +1,444 @@
+// Outbound notifications
+// Command results, monitor alerts (scheduled dependency audits and backups) and
+// agent task reports can be posted to Slack or Discord incoming webhooks or sent
+// by email. Each sink picks the events it wants and formats them with its own
+// template; before anything leaves the machine the message goes through the
+// sink's redaction rules and, unless turned off, the patterns of well-known
+// secrets (tokens, keys, passwords). Command output is only sent when a
+// template asks for it with {{output}}. Webhook URLs and SMTP passwords are
+// kept with the other credentials, not in the settings file.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::secrets::SecretsStore;
+use crate::terminal::CommandExecution;
+
+/// Lines of output kept for {{output}}
+const OUTPUT_LINES: usize = 20;
+/// Discord rejects longer messages
+const DISCORD_LIMIT: usize = 2000;
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkEvent {
+    CommandFinished,
+    CommandFailed,
+    /// Findings of scheduled dependency audits, failed scheduled backups
+    MonitorAlert,
+    AgentReport,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    Slack,
+    Discord,
+    Email,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailSettings {
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    /// STARTTLS on the submission port; off means TLS from the start (port 465)
+    pub starttls: bool,
+    pub username: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSink {
+    pub id: String,
+    pub name: String,
+    pub kind: SinkKind,
+    /// Set for email sinks
+    pub email: Option<EmailSettings>,
+    pub events: Vec<SinkEvent>,
+    /// Command results only go out for commands running at least this long
+    pub min_duration_secs: u64,
+    /// `{{title}}`, `{{body}}`, `{{event}}`, `{{command}}`, `{{exit_code}}`, `{{duration}}`,
+    /// `{{directory}}`, `{{output}}`, `{{host}}`; `None` for the plain title and body
+    pub template: Option<String>,
+    /// Regular expressions whose matches are replaced before sending
+    pub redactions: Vec<String>,
+    pub mask_known_secrets: bool,
+    pub enabled: bool,
+    pub last_sent: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// What the user fills in for a new sink; `secret` is the webhook URL, or the SMTP password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkDraft {
+    pub name: String,
+    pub kind: SinkKind,
+    pub email: Option<EmailSettings>,
+    pub events: Vec<SinkEvent>,
+    #[serde(default)]
+    pub min_duration_secs: u64,
+    pub template: Option<String>,
+    #[serde(default)]
+    pub redactions: Vec<String>,
+    #[serde(default = "default_true")]
+    pub mask_known_secrets: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SinkSettings {
+    sinks: Vec<NotificationSink>,
+}
+
+impl SinkSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("notification_sinks.json")
+    }
+
+    fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+fn secret_key(sink_id: &str) -> String {
+    format!("sink:{}", sink_id)
+}
+
+/// Something to tell the sinks about
+#[derive(Debug, Clone)]
+pub struct SinkMessage {
+    pub event: SinkEvent,
+    pub title: String,
+    pub body: String,
+    /// For `min_duration_secs`
+    pub duration_ms: Option<u64>,
+    pub fields: HashMap<&'static str, String>,
+}
+
+impl SinkMessage {
+    pub fn new(event: SinkEvent, title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self { event, title: title.into(), body: body.into(), duration_ms: None, fields: HashMap::new() }
+    }
+
+    pub fn command(execution: &CommandExecution, working_directory: &str) -> Self {
+        let success = execution.outcome.is_success();
+        let duration = format!("{:.1}s", execution.duration_ms as f64 / 1000.0);
+        let (event, title) = if success {
+            (SinkEvent::CommandFinished, "✅ Command finished")
+        } else {
+            (SinkEvent::CommandFailed, "❌ Command failed")
+        };
+        let exit_code = execution.exit_code.map_or_else(|| "none".to_string(), |code| code.to_string());
+        let body = format!("`{}` exited with {} after {} in {}", execution.command, exit_code, duration, working_directory);
+        let lines: Vec<&str> = execution.output.lines().collect();
+        let output = lines[lines.len().saturating_sub(OUTPUT_LINES)..].join("\n");
+
+        let mut message = Self::new(event, title, body);
+        message.duration_ms = Some(execution.duration_ms);
+        message.fields.insert("command", execution.command.clone());
+        message.fields.insert("exit_code", exit_code);
+        message.fields.insert("duration", duration);
+        message.fields.insert("directory", working_directory.to_string());
+        message.fields.insert("output", output);
+        message
+    }
+
+    /// High and critical findings of a scheduled dependency audit; `None` when there are none
+    pub fn audit(report: &crate::project::audit::AuditReport) -> Option<Self> {
+        let high = report.high_severity_findings();
+        if high.is_empty() {
+            return None;
+        }
+        let findings: Vec<String> = high.iter().map(|finding| format!("{:?} {}: {}", finding.severity, finding.package, finding.title)).collect();
+        let title = format!("🔒 {} serious dependency advisories", high.len());
+        Some(Self::new(SinkEvent::MonitorAlert, title, format!("{}\n{}", report.project_path, findings.join("\n"))))
+    }
+
+    pub fn agent_plan(task: &crate::ai::agent::AgentTask) -> Self {
+        let steps: Vec<String> = task.steps.iter().enumerate().map(|(number, step)| format!("{}. {}", number + 1, step.command)).collect();
+        Self::new(SinkEvent::AgentReport, "🤖 Agent task planned", format!("{}\n{}", task.description, steps.join("\n")))
+    }
+}
+
+/// Secrets that should never leave the machine, whatever the sink's own rules
+fn known_secrets() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----", REDACTED),
+            (r"(?i)\b(password|passwd|pwd|secret|token|api[_-]?key)\b(\s*[=:]\s*)\S+", "$1$2[redacted]"),
+            (r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]+=*", "Bearer [redacted]"),
+            (r"\bgh[pousr]_[A-Za-z0-9]{20,}", REDACTED),
+            (r"\bgithub_pat_[A-Za-z0-9_]{20,}", REDACTED),
+            (r"\bxox[abprs]-[A-Za-z0-9-]{10,}", REDACTED),
+            (r"\bAKIA[0-9A-Z]{16}\b", REDACTED),
+            (r"\bsk-[A-Za-z0-9_-]{20,}", REDACTED),
+            (r"://[^/\s:@]+:[^/\s@]+@", "://[redacted]@"),
+        ]
+        .iter()
+        .filter_map(|(pattern, replacement)| Regex::new(pattern).ok().map(|regex| (regex, *replacement)))
+        .collect()
+    })
+}
+
+/// Mask tokens, keys and passwords of well-known shapes
+pub fn mask_known_secrets(text: &str) -> String {
+    let mut text = text.to_string();
+    for (pattern, replacement) in known_secrets() {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text
+}
+
+fn redact(text: &str, sink: &NotificationSink) -> String {
+    let mut text = if sink.mask_known_secrets { mask_known_secrets(text) } else { text.to_string() };
+    for rule in &sink.redactions {
+        match Regex::new(rule) {
+            Ok(pattern) => text = pattern.replace_all(&text, REDACTED).into_owned(),
+            // A rule that no longer compiles must not let everything through
+            Err(_) => return format!("{} (message withheld: redaction rule `{}` is invalid)", REDACTED, rule),
+        }
+    }
+    text
+}
+
+fn render(message: &SinkMessage, sink: &NotificationSink) -> (String, String) {
+    let default_template = match sink.kind {
+        SinkKind::Slack => "*{{title}}*\n{{body}}",
+        SinkKind::Discord => "**{{title}}**\n{{body}}",
+        SinkKind::Email => "{{body}}\n\n— pH7Console on {{host}}",
+    };
+    let template = sink.template.as_deref().filter(|t| !t.trim().is_empty()).unwrap_or(default_template);
+    let event = serde_json::to_value(message.event).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+    let host = sysinfo::System::host_name().unwrap_or_default();
+    let mut text = template.replace("{{title}}", &message.title).replace("{{body}}", &message.body);
+    text = text.replace("{{event}}", &event).replace("{{host}}", &host);
+    for name in ["command", "exit_code", "duration", "directory", "output"] {
+        text = text.replace(&format!("{{{{{}}}}}", name), message.fields.get(name).map(String::as_str).unwrap_or_default());
+    }
+    (redact(&message.title, sink), redact(&text, sink))
+}
+
+async fn deliver(sink: &NotificationSink, secret: &str, subject: &str, text: &str) -> Result<(), String> {
+    let client = reqwest::Client::new();
+    let payload = match sink.kind {
+        SinkKind::Slack => serde_json::json!({ "text": text }),
+        SinkKind::Discord => serde_json::json!({ "content": text.chars().take(DISCORD_LIMIT).collect::<String>() }),
+        SinkKind::Email => return send_email(sink, secret, subject, text).await,
+    };
+    let response = client
+        .post(secret)
+        .timeout(std::time::Duration::from_secs(15))
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| format!("Cannot reach the webhook: {}", e.without_url()))?;
+    if !response.status().is_success() {
+        return Err(format!("The webhook answered {}", response.status()));
+    }
+    Ok(())
+}
+
+async fn send_email(sink: &NotificationSink, password: &str, subject: &str, text: &str) -> Result<(), String> {
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+    let settings = sink.email.as_ref().ok_or("The email sink has no SMTP settings")?;
+    let mut email = Message::builder()
+        .from(settings.from.parse().map_err(|e| format!("Invalid sender {}: {}", settings.from, e))?)
+        .subject(subject);
+    for to in &settings.to {
+        email = email.to(to.parse().map_err(|e| format!("Invalid recipient {}: {}", to, e))?);
+    }
+    let email = email.body(text.to_string()).map_err(|e| e.to_string())?;
+
+    let relay = if settings.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.smtp_host)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.smtp_host)
+    };
+    let mut transport = relay.map_err(|e| format!("Cannot use {}: {}", settings.smtp_host, e))?.port(settings.smtp_port);
+    if let Some(username) = &settings.username {
+        transport = transport.credentials(Credentials::new(username.clone(), password.to_string()));
+    }
+    transport.build().send(email).await.map_err(|e| format!("Could not send the email: {}", e))?;
+    Ok(())
+}
+
+/// The configured sinks; messages are sent in the background and the outcome noted on the sink
+#[derive(Clone)]
+pub struct SinkManager {
+    settings: Arc<Mutex<SinkSettings>>,
+}
+
+impl Default for SinkManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SinkManager {
+    pub fn new() -> Self {
+        Self { settings: Arc::new(Mutex::new(SinkSettings::load())) }
+    }
+
+    pub fn list(&self) -> Vec<NotificationSink> {
+        self.settings.lock().unwrap().sinks.clone()
+    }
+
+    fn validate(draft: &SinkDraft, secret: &str) -> Result<(), String> {
+        for rule in &draft.redactions {
+            Regex::new(rule).map_err(|e| format!("Redaction rule `{}` is not a valid regular expression: {}", rule, e))?;
+        }
+        match draft.kind {
+            SinkKind::Slack | SinkKind::Discord if !secret.starts_with("https://") => {
+                Err("Paste the webhook's https:// URL".to_string())
+            }
+            SinkKind::Email => {
+                let email = draft.email.as_ref().ok_or("An email sink needs SMTP settings")?;
+                if email.smtp_host.trim().is_empty() || email.to.is_empty() {
+                    return Err("An email sink needs an SMTP host and at least one recipient".to_string());
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn create(&self, draft: SinkDraft, secret: &str) -> Result<NotificationSink, String> {
+        Self::validate(&draft, secret.trim())?;
+        let sink = NotificationSink {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: if draft.name.trim().is_empty() { format!("{:?}", draft.kind) } else { draft.name.trim().to_string() },
+            kind: draft.kind,
+            email: draft.email,
+            events: draft.events,
+            min_duration_secs: draft.min_duration_secs,
+            template: draft.template,
+            redactions: draft.redactions,
+            mask_known_secrets: draft.mask_known_secrets,
+            enabled: true,
+            last_sent: None,
+            last_error: None,
+        };
+        if !secret.trim().is_empty() {
+            SecretsStore::load().set(&secret_key(&sink.id), secret.trim())?;
+        }
+        let mut settings = self.settings.lock().unwrap();
+        settings.sinks.push(sink.clone());
+        settings.save()?;
+        Ok(sink)
+    }
+
+    /// Change a sink's settings; `secret` replaces the stored one when given
+    pub fn update(&self, id: &str, draft: SinkDraft, enabled: bool, secret: Option<&str>) -> Result<NotificationSink, String> {
+        let stored_secret = SecretsStore::load().get(&secret_key(id)).unwrap_or_default().to_string();
+        Self::validate(&draft, secret.map_or(stored_secret.as_str(), str::trim))?;
+        let mut settings = self.settings.lock().unwrap();
+        let sink = settings.sinks.iter_mut().find(|s| s.id == id).ok_or_else(|| format!("No notification sink {}", id))?;
+        sink.name = draft.name.trim().to_string();
+        sink.kind = draft.kind;
+        sink.email = draft.email;
+        sink.events = draft.events;
+        sink.min_duration_secs = draft.min_duration_secs;
+        sink.template = draft.template;
+        sink.redactions = draft.redactions;
+        sink.mask_known_secrets = draft.mask_known_secrets;
+        sink.enabled = enabled;
+        let updated = sink.clone();
+        settings.save()?;
+        if let Some(secret) = secret.filter(|s| !s.trim().is_empty()) {
+            SecretsStore::load().set(&secret_key(id), secret.trim())?;
+        }
+        Ok(updated)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let mut settings = self.settings.lock().unwrap();
+        let before = settings.sinks.len();
+        settings.sinks.retain(|s| s.id != id);
+        if settings.sinks.len() == before {
+            return Err(format!("No notification sink {}", id));
+        }
+        settings.save()?;
+        SecretsStore::load().remove(&secret_key(id))
+    }
+
+    fn wants(sink: &NotificationSink, message: &SinkMessage) -> bool {
+        let long_enough = message.duration_ms.map_or(true, |ms| ms >= sink.min_duration_secs * 1000);
+        sink.enabled && sink.events.contains(&message.event) && long_enough
+    }
+
+    /// Send to every sink that wants the message, without waiting for them
+    pub fn publish(&self, message: SinkMessage) {
+        let sinks: Vec<NotificationSink> = self.list().into_iter().filter(|sink| Self::wants(sink, &message)).collect();
+        for sink in sinks {
+            let (manager, message) = (self.clone(), message.clone());
+            tauri::async_runtime::spawn(async move {
+                let _ = manager.send(&sink, &message).await;
+            });
+        }
+    }
+
+    /// Report a newly planned agent task with its steps
+    pub async fn publish_agent_plan(&self, model_manager: &crate::ai::ModelManager, task_id: &str) {
+        if let Some(task) = model_manager.get_agent_task(task_id).await {
+            self.publish(SinkMessage::agent_plan(&task));
+        }
+    }
+
+    /// Send a sample message to one sink and report how it went
+    pub async fn test(&self, id: &str) -> Result<(), String> {
+        let sink = self.list().into_iter().find(|s| s.id == id).ok_or_else(|| format!("No notification sink {}", id))?;
+        let mut message = SinkMessage::new(SinkEvent::CommandFinished, "🔔 Test from pH7Console", "Notifications from this sink arrive here");
+        message.fields.insert("command", "echo test".to_string());
+        message.fields.insert("exit_code", "0".to_string());
+        message.fields.insert("duration", "0.0s".to_string());
+        message.fields.insert("output", "test".to_string());
+        self.send(&sink, &message).await
+    }
+
+    async fn send(&self, sink: &NotificationSink, message: &SinkMessage) -> Result<(), String> {
+        let secret = SecretsStore::load().get(&secret_key(&sink.id)).unwrap_or_default().to_string();
+        let (subject, text) = render(message, sink);
+        let result = deliver(sink, &secret, &subject, &text).await;
+        if let Err(e) = &result {
+            println!("⚠️ Notification sink {} failed: {}", sink.name, e);
+        }
+
+        let mut settings = self.settings.lock().unwrap();
+        if let Some(stored) = settings.sinks.iter_mut().find(|s| s.id == sink.id) {
+            match &result {
+                Ok(()) => {
+                    stored.last_sent = Some(Utc::now());
+                    stored.last_error = None;
+                }
+                Err(e) => stored.last_error = Some(e.clone()),
+            }
+        }
+        let _ = settings.save();
+        result
+    }
+}
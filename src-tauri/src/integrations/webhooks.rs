@@ -0,0 +1,795 @@
+// Webhook triggers
+// An optional HTTP listener lets outside events (a CI run finished, a deploy
+// was requested) start something the user set up beforehand. A trigger either
+// runs a fixed list of commands in a new session, stopping at the first that
+// fails, or plans an agent task that then waits in the agent panel for review.
+// Nothing in a request changes what runs: only defined, enabled triggers can be
+// called, only from allowed addresses, and only when signed with the trigger's
+// secret: HMAC-SHA256 of `<X-Ph7-Timestamp>.<body>` in `X-Ph7-Signature`, with
+// the timestamp (Unix seconds) a few minutes old at most, or of the body alone in
+// GitHub's `X-Hub-Signature-256`. A signed delivery seen before is refused, also
+// after a restart since GitHub's signature carries no time, and every delivery,
+// accepted or not, goes into an audit log that keeps its newest entries.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::convert::Infallible;
+use std::io::{BufRead, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::watch;
+
+use super::secrets::SecretsStore;
+use crate::AppState;
+
+/// Largest payload accepted; only its signature is looked at
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Signed deliveries remembered to refuse replays
+const REMEMBERED_DELIVERIES: usize = 4096;
+/// How far `X-Ph7-Timestamp` may be from now, either way
+const MAX_DELIVERY_AGE_SECONDS: u64 = 5 * 60;
+/// Size past which the oldest half of the audit log is dropped
+const MAX_AUDIT_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TriggerAction {
+    /// Run these commands in order
+    Snippet { commands: Vec<String> },
+    /// Plan this task; its steps run only once the user starts them
+    AgentTask { description: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTrigger {
+    pub id: String,
+    pub name: String,
+    pub action: TriggerAction,
+    /// Where snippet commands run; the home directory when unset
+    pub working_directory: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_triggered: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookOptions {
+    /// Start listening when the app starts
+    pub start_on_launch: bool,
+    pub bind_address: String,
+    pub port: u16,
+    /// Addresses (`203.0.113.7`) or ranges (`10.0.0.0/8`) requests may come from
+    pub allowed_addresses: Vec<String>,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        Self {
+            start_on_launch: false,
+            bind_address: "127.0.0.1".to_string(),
+            port: 7444,
+            allowed_addresses: vec!["127.0.0.1".to_string(), "::1".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    #[serde(flatten)]
+    pub options: WebhookOptions,
+    pub triggers: Vec<WebhookTrigger>,
+}
+
+impl WebhookSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("webhooks.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Secrets live with the other credentials, not in the settings file
+fn secret_key(trigger_id: &str) -> String {
+    format!("webhook:{}", trigger_id)
+}
+
+/// A new trigger with its signing secret, shown to the user this once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedTrigger {
+    pub trigger: WebhookTrigger,
+    pub secret: String,
+    /// Path to POST to, e.g. `/hooks/<id>`
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookStatus {
+    pub running: bool,
+    pub url: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub options: WebhookOptions,
+    pub triggers: Vec<WebhookTrigger>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditedCommand {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Rejected { reason: String },
+    Accepted,
+    Finished { success: bool, commands: Vec<AuditedCommand>, error: Option<String> },
+    Planned { task_id: Option<String>, error: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub remote_address: String,
+    pub trigger_id: Option<String>,
+    pub trigger_name: Option<String>,
+    pub delivery: Option<String>,
+    #[serde(flatten)]
+    pub outcome: AuditOutcome,
+}
+
+fn audit_file() -> PathBuf {
+    crate::ai::data_directory().join("webhook_audit.jsonl")
+}
+
+fn audit(entry: AuditEntry) {
+    match &entry.outcome {
+        AuditOutcome::Rejected { reason } => println!("🪝 Webhook from {} refused: {}", entry.remote_address, reason),
+        outcome => println!("🪝 Webhook {} from {}: {:?}", entry.trigger_name.as_deref().unwrap_or("?"), entry.remote_address, outcome),
+    }
+    let file = audit_file();
+    if let Some(parent) = file.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let written = serde_json::to_string(&entry).map_err(|e| e.to_string()).and_then(|line| {
+        let mut log = std::fs::OpenOptions::new().create(true).append(true).open(&file).map_err(|e| e.to_string())?;
+        writeln!(log, "{}", line).map_err(|e| e.to_string())
+    });
+    if let Err(e) = written.and_then(|_| trim_audit_log(&file)) {
+        println!("⚠️ Could not write the webhook audit log: {}", e);
+    }
+}
+
+/// Keep the newest entries in half the allowed size once the log outgrows it
+fn trim_audit_log(file: &std::path::Path) -> Result<(), String> {
+    if std::fs::metadata(file).map_err(|e| e.to_string())?.len() <= MAX_AUDIT_BYTES {
+        return Ok(());
+    }
+    let data = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
+    let mut kept = 0;
+    let newest: Vec<&str> = data.lines().rev().take_while(|line| {
+        kept += line.len() + 1;
+        kept as u64 <= MAX_AUDIT_BYTES / 2
+    }).collect();
+    let trimmed: String = newest.iter().rev().map(|line| format!("{}\n", line)).collect();
+    let partial = file.with_extension("jsonl.partial");
+    std::fs::write(&partial, trimmed).map_err(|e| e.to_string())?;
+    std::fs::rename(&partial, file).map_err(|e| e.to_string())
+}
+
+fn deliveries_file() -> PathBuf {
+    crate::ai::data_directory().join("webhook_deliveries")
+}
+
+/// Deliveries accepted before, oldest first, so a restart does not open the door to replays
+fn load_deliveries() -> VecDeque<[u8; 32]> {
+    let data = std::fs::read_to_string(deliveries_file()).unwrap_or_default();
+    let mut deliveries: VecDeque<[u8; 32]> = data.lines().filter_map(|line| hex::decode(line.trim()).ok()?.try_into().ok()).collect();
+    if deliveries.len() > REMEMBERED_DELIVERIES {
+        deliveries.drain(..deliveries.len() - REMEMBERED_DELIVERIES);
+        let lines: String = deliveries.iter().map(|key| format!("{}\n", hex::encode(key))).collect();
+        if let Err(e) = std::fs::write(deliveries_file(), lines) {
+            println!("⚠️ Could not trim the webhook delivery log: {}", e);
+        }
+    }
+    deliveries
+}
+
+fn remember_delivery(key: &[u8; 32]) {
+    let file = deliveries_file();
+    if let Some(parent) = file.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)
+        .and_then(|mut log| writeln!(log, "{}", hex::encode(key)));
+    if let Err(e) = written {
+        println!("⚠️ Could not record the webhook delivery: {}", e);
+    }
+}
+
+/// The audit log, newest first
+pub fn audit_log(limit: usize) -> Vec<AuditEntry> {
+    let Ok(file) = std::fs::File::open(audit_file()) else { return Vec::new() };
+    let entries: Vec<AuditEntry> = std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+    entries.into_iter().rev().take(limit).collect()
+}
+
+/// Whether `address` is one of the allowed addresses or in one of the allowed ranges
+fn address_allowed(allowed: &[String], address: IpAddr) -> bool {
+    // IPv4 clients of a dual-stack socket show up as ::ffff:a.b.c.d
+    let address = match address {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+        v4 => v4,
+    };
+    allowed.iter().any(|entry| {
+        let (network, bits) = match entry.trim().split_once('/') {
+            Some((network, bits)) => (network, bits.parse::<u32>().ok()),
+            None => (entry.trim(), None),
+        };
+        match (network.parse::<IpAddr>(), address) {
+            (Ok(IpAddr::V4(network)), IpAddr::V4(address)) => {
+                let bits = bits.unwrap_or(32).min(32);
+                let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (Ok(IpAddr::V6(network)), IpAddr::V6(address)) => {
+                let bits = bits.unwrap_or(128).min(128);
+                let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Check `sha256=<hex>` against the body; the comparison takes the same time whatever the signature
+fn signature_valid(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature.trim().strip_prefix("sha256=").and_then(|hex| hex::decode(hex).ok()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else { return false };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// The signature headers of a delivery
+#[derive(Debug, Default)]
+struct DeliverySignature<'a> {
+    timestamp: Option<&'a str>,
+    signature: Option<&'a str>,
+    github_signature: Option<&'a str>,
+}
+
+/// Check a delivery's signature and return the key it is remembered by. A signed
+/// timestamp has to be recent; GitHub signs the body alone, so the same GitHub
+/// delivery is only ever accepted once.
+fn verify_delivery(secret: &str, body: &[u8], headers: &DeliverySignature, now: DateTime<Utc>) -> Result<[u8; 32], &'static str> {
+    let (signed, signature) = match (headers.signature, headers.github_signature) {
+        (Some(signature), _) => {
+            let timestamp = headers.timestamp.map(str::trim).ok_or("Missing X-Ph7-Timestamp")?;
+            let sent = timestamp.parse::<i64>().map_err(|_| "X-Ph7-Timestamp is not a Unix time")?;
+            if now.timestamp().abs_diff(sent) > MAX_DELIVERY_AGE_SECONDS {
+                return Err("The delivery is too old, or the sender's clock is off");
+            }
+            ([timestamp.as_bytes(), b".", body].concat(), signature)
+        }
+        (None, Some(signature)) => (body.to_vec(), signature),
+        (None, None) => return Err("Missing or invalid signature"),
+    };
+    if !signature_valid(secret, &signed, signature) {
+        return Err("Missing or invalid signature");
+    }
+    Ok(Sha256::new().chain_update(&signed).chain_update(signature.trim()).finalize().into())
+}
+
+struct Running {
+    address: SocketAddr,
+    started_at: DateTime<Utc>,
+    shutdown: watch::Sender<bool>,
+}
+
+/// Shared by every connection of a running listener
+struct Context {
+    app: AppHandle,
+    settings: Arc<RwLock<WebhookSettings>>,
+    /// Hashes of the signed deliveries accepted lately
+    deliveries: Mutex<VecDeque<[u8; 32]>>,
+    /// Triggers running now; a trigger does not run twice at once
+    busy: Mutex<HashSet<String>>,
+    shutdown: watch::Receiver<bool>,
+}
+
+pub struct WebhookServer {
+    settings: Arc<RwLock<WebhookSettings>>,
+    running: Option<Running>,
+}
+
+impl Default for WebhookServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookServer {
+    pub fn new() -> Self {
+        Self { settings: Arc::new(RwLock::new(WebhookSettings::load())), running: None }
+    }
+
+    pub fn starts_on_launch(&self) -> bool {
+        self.settings.read().unwrap().options.start_on_launch
+    }
+
+    pub fn status(&self) -> WebhookStatus {
+        let settings = self.settings.read().unwrap();
+        let running = self.running.as_ref();
+        WebhookStatus {
+            running: running.is_some(),
+            url: running.map(|r| format!("http://{}/hooks/", r.address)),
+            started_at: running.map(|r| r.started_at),
+            options: settings.options.clone(),
+            triggers: settings.triggers.clone(),
+        }
+    }
+
+    pub fn set_options(&mut self, options: WebhookOptions) -> Result<(), String> {
+        for entry in &options.allowed_addresses {
+            let network = entry.trim().split_once('/').map_or(entry.trim(), |(network, _)| network);
+            if network.parse::<IpAddr>().is_err() {
+                return Err(format!("'{}' is not an address or a range like 10.0.0.0/8", entry));
+            }
+        }
+        let mut settings = self.settings.write().unwrap();
+        let mut updated = settings.clone();
+        updated.options = options;
+        updated.save()?;
+        *settings = updated;
+        Ok(())
+    }
+
+    pub fn create_trigger(&mut self, name: &str, action: TriggerAction, working_directory: Option<String>) -> Result<CreatedTrigger, String> {
+        match &action {
+            TriggerAction::Snippet { commands } if commands.iter().all(|c| c.trim().is_empty()) => {
+                return Err("A snippet trigger needs at least one command".to_string());
+            }
+            TriggerAction::AgentTask { description } if description.trim().is_empty() => {
+                return Err("An agent task trigger needs a task description".to_string());
+            }
+            _ => {}
+        }
+        if let Some(directory) = &working_directory {
+            if !std::path::Path::new(directory).is_dir() {
+                return Err(format!("{} is not a directory", directory));
+            }
+        }
+        let action = match action {
+            TriggerAction::Snippet { commands } => TriggerAction::Snippet {
+                commands: commands.into_iter().map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect(),
+            },
+            task => task,
+        };
+        let trigger = WebhookTrigger {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            name: if name.trim().is_empty() { "Webhook".to_string() } else { name.trim().to_string() },
+            action,
+            working_directory,
+            enabled: true,
+            created_at: Utc::now(),
+            last_triggered: None,
+        };
+        let secret = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        SecretsStore::load().set(&secret_key(&trigger.id), &secret)?;
+
+        let mut settings = self.settings.write().unwrap();
+        settings.triggers.push(trigger.clone());
+        settings.save()?;
+        let path = format!("/hooks/{}", trigger.id);
+        Ok(CreatedTrigger { trigger, secret, path })
+    }
+
+    pub fn set_trigger_enabled(&mut self, id: &str, enabled: bool) -> Result<(), String> {
+        let mut settings = self.settings.write().unwrap();
+        let trigger = settings.triggers.iter_mut().find(|t| t.id == id).ok_or_else(|| format!("No webhook trigger {}", id))?;
+        trigger.enabled = enabled;
+        settings.save()
+    }
+
+    pub fn delete_trigger(&mut self, id: &str) -> Result<(), String> {
+        let mut settings = self.settings.write().unwrap();
+        let before = settings.triggers.len();
+        settings.triggers.retain(|t| t.id != id);
+        if settings.triggers.len() == before {
+            return Err(format!("No webhook trigger {}", id));
+        }
+        settings.save()?;
+        SecretsStore::load().remove(&secret_key(id))
+    }
+
+    pub async fn start(&mut self, app: AppHandle) -> Result<WebhookStatus, String> {
+        if self.running.is_some() {
+            return Ok(self.status());
+        }
+        let options = self.settings.read().unwrap().options.clone();
+        let listener = tokio::net::TcpListener::bind((options.bind_address.as_str(), options.port))
+            .await
+            .map_err(|e| format!("Cannot listen on {}:{}: {}", options.bind_address, options.port, e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let context = Arc::new(Context {
+            app,
+            settings: self.settings.clone(),
+            deliveries: Mutex::new(load_deliveries()),
+            busy: Mutex::new(HashSet::new()),
+            shutdown: shutdown_rx,
+        });
+        tokio::spawn(serve(listener, context));
+
+        println!("🪝 Webhooks listening on http://{}", address);
+        self.running = Some(Running { address, started_at: Utc::now(), shutdown });
+        Ok(self.status())
+    }
+
+    /// Stop listening; triggers already running finish. False when it was not running
+    pub fn stop(&mut self) -> bool {
+        let Some(running) = self.running.take() else { return false };
+        let _ = running.shutdown.send(true);
+        println!("🪝 Webhooks stopped");
+        true
+    }
+}
+
+async fn serve(listener: tokio::net::TcpListener, context: Arc<Context>) {
+    let mut shutdown = context.shutdown.clone();
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(_) => continue,
+            },
+        };
+        let context = context.clone();
+        tokio::spawn(async move {
+            let mut shutdown = context.shutdown.clone();
+            let service_context = context.clone();
+            let service = hyper::service::service_fn(move |request| handle(request, service_context.clone(), peer));
+            let connection = hyper::server::conn::http1::Builder::new().serve_connection(hyper_util::rt::TokioIo::new(stream), service);
+            tokio::select! {
+                _ = connection => {}
+                _ = shutdown.changed() => {}
+            }
+        });
+    }
+}
+
+fn respond(status: StatusCode, value: Value) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(value.to_string())));
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn header<'a>(request: &'a Request<Incoming>, name: &str) -> Option<&'a str> {
+    request.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+async fn handle(request: Request<Incoming>, context: Arc<Context>, peer: SocketAddr) -> Result<Response<Full<Bytes>>, Infallible> {
+    let mut entry = AuditEntry {
+        at: Utc::now(),
+        remote_address: peer.ip().to_string(),
+        trigger_id: None,
+        trigger_name: None,
+        delivery: None,
+        outcome: AuditOutcome::Accepted,
+    };
+    let refuse = |mut entry: AuditEntry, status: StatusCode, reason: &str| {
+        entry.outcome = AuditOutcome::Rejected { reason: reason.to_string() };
+        audit(entry);
+        Ok(respond(status, json!({ "error": reason })))
+    };
+
+    let allowed = address_allowed(&context.settings.read().unwrap().options.allowed_addresses, peer.ip());
+    if !allowed {
+        return refuse(entry, StatusCode::FORBIDDEN, "This address may not call webhooks");
+    }
+    let id = match (request.method(), request.uri().path().strip_prefix("/hooks/")) {
+        (&Method::POST, Some(id)) if !id.is_empty() && !id.contains('/') => id.to_string(),
+        _ => return refuse(entry, StatusCode::NOT_FOUND, "Not found"),
+    };
+    entry.trigger_id = Some(id.clone());
+    let trigger = context.settings.read().unwrap().triggers.iter().find(|t| t.id == id && t.enabled).cloned();
+    let Some(trigger) = trigger else {
+        return refuse(entry, StatusCode::NOT_FOUND, "No such trigger, or it is disabled");
+    };
+    entry.trigger_name = Some(trigger.name.clone());
+    entry.delivery = header(&request, "x-ph7-delivery").or_else(|| header(&request, "x-github-delivery")).map(str::to_string);
+    let timestamp = header(&request, "x-ph7-timestamp").map(str::to_string);
+    let signature = header(&request, "x-ph7-signature").map(str::to_string);
+    let github_signature = header(&request, "x-hub-signature-256").map(str::to_string);
+
+    let body = match http_body_util::Limited::new(request.into_body(), MAX_BODY_BYTES).collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return refuse(entry, StatusCode::PAYLOAD_TOO_LARGE, "The payload is too large"),
+    };
+    let Some(secret) = SecretsStore::load().get(&secret_key(&trigger.id)).map(str::to_string) else {
+        return refuse(entry, StatusCode::UNAUTHORIZED, "Missing or invalid signature");
+    };
+    let headers = DeliverySignature {
+        timestamp: timestamp.as_deref(),
+        signature: signature.as_deref(),
+        github_signature: github_signature.as_deref(),
+    };
+    let replay_key = match verify_delivery(&secret, &body, &headers, Utc::now()) {
+        Ok(key) => key,
+        Err(reason) => return refuse(entry, StatusCode::UNAUTHORIZED, reason),
+    };
+    // Checked and remembered under one lock, so a delivery sent twice at once runs once
+    let admitted = {
+        let mut deliveries = context.deliveries.lock().unwrap();
+        if deliveries.contains(&replay_key) {
+            Err("This delivery was already received")
+        } else if !context.busy.lock().unwrap().insert(trigger.id.clone()) {
+            // Refused while busy, a delivery can be sent again later
+            Err("The trigger is still running")
+        } else {
+            if deliveries.len() >= REMEMBERED_DELIVERIES {
+                deliveries.pop_front();
+            }
+            deliveries.push_back(replay_key);
+            Ok(())
+        }
+    };
+    if let Err(reason) = admitted {
+        return refuse(entry, StatusCode::CONFLICT, reason);
+    }
+    remember_delivery(&replay_key);
+
+    {
+        let mut settings = context.settings.write().unwrap();
+        if let Some(stored) = settings.triggers.iter_mut().find(|t| t.id == trigger.id) {
+            stored.last_triggered = Some(entry.at);
+        }
+        let _ = settings.save();
+    }
+    audit(entry.clone());
+    let reply = json!({ "accepted": true, "trigger": trigger.name, "delivery": entry.delivery });
+    tokio::spawn(async move {
+        let outcome = run_trigger(&context.app, &trigger).await;
+        context.busy.lock().unwrap().remove(&trigger.id);
+        report(&context.app, &trigger, &outcome).await;
+        audit(AuditEntry { at: Utc::now(), outcome, ..entry });
+    });
+    Ok(respond(StatusCode::ACCEPTED, reply))
+}
+
+/// Run a snippet in a session of its own, closed afterwards, or plan an agent task
+async fn run_trigger(app: &AppHandle, trigger: &WebhookTrigger) -> AuditOutcome {
+    let commands = match &trigger.action {
+        TriggerAction::AgentTask { description } => {
+            let state = app.state::<AppState>();
+            // Planning may wait on a model; the model manager stays free for everything else meanwhile
+            let agent = state.model_manager.lock().await.agent();
+            let planned = match agent {
+                Ok(agent) => agent.lock().await.create_task_from_description(description, None, &[]).await,
+                Err(e) => Err(e),
+            };
+            if let Ok(task_id) = &planned {
+                state.sinks.publish_agent_plan(&*state.model_manager.lock().await, task_id).await;
+            }
+            return AuditOutcome::Planned { task_id: planned.as_ref().ok().cloned(), error: planned.err() };
+        }
+        TriggerAction::Snippet { commands } => commands,
+    };
+    let title = Some(format!("🪝 {}", trigger.name));
+    let session_id = match crate::commands::create_terminal(app.clone(), app.state(), title, trigger.working_directory.clone(), None, None, None).await {
+        Ok(session_id) => session_id,
+        Err(e) => return AuditOutcome::Finished { success: false, commands: Vec::new(), error: Some(e) },
+    };
+
+    let mut results = Vec::new();
+    let mut error = None;
+    for command in commands {
+        let started = std::time::Instant::now();
+        match crate::commands::execute_command(app.clone(), app.state(), session_id.clone(), command.clone(), None, None, None).await {
+            Ok(capped) => {
+                let execution = &capped.execution;
+                results.push(AuditedCommand { command: command.clone(), exit_code: execution.exit_code, duration_ms: execution.duration_ms, error: None });
+                if !execution.outcome.is_success() {
+                    error = Some(format!("`{}` failed", command));
+                    break;
+                }
+            }
+            Err(e) => {
+                results.push(AuditedCommand { command: command.clone(), exit_code: None, duration_ms: started.elapsed().as_millis() as u64, error: Some(e) });
+                error = Some(format!("`{}` could not run", command));
+                break;
+            }
+        }
+    }
+    let _ = crate::commands::close_terminal_session(app.clone(), app.state(), session_id).await;
+    AuditOutcome::Finished { success: error.is_none(), commands: results, error }
+}
+
+/// Tell the user what a trigger did, like a finished command
+async fn report(app: &AppHandle, trigger: &WebhookTrigger, outcome: &AuditOutcome) {
+    use tauri_plugin_notification::NotificationExt;
+
+    let (title, body, success) = match outcome {
+        AuditOutcome::Finished { error: None, commands, .. } => ("🪝 Webhook ran", format!("{}: {} commands succeeded", trigger.name, commands.len()), true),
+        AuditOutcome::Finished { error, .. } => ("🪝 Webhook failed", format!("{}: {}", trigger.name, error.as_deref().unwrap_or_default()), false),
+        AuditOutcome::Planned { task_id: Some(_), .. } => ("🪝 Agent task planned", format!("{}: review it in the agent panel before running it", trigger.name), true),
+        AuditOutcome::Planned { error, .. } => ("🪝 Agent task not planned", format!("{}: {}", trigger.name, error.as_deref().unwrap_or_default()), false),
+        _ => return,
+    };
+    let focus_mode = tokio::task::spawn_blocking(crate::terminal::notifications::focus_mode_active).await.unwrap_or(false);
+    let state = app.state::<AppState>();
+    let notification = state.notification_center.lock().await.add(None, title.to_string(), body, success, focus_mode);
+    if notification.suppressed.is_none() {
+        if let Err(e) = app.notification().builder().title(&notification.title).body(&notification.body).show() {
+            println!("⚠️ Could not show notification: {}", e);
+        }
+    }
+    let _ = app.emit(crate::terminal::notifications::NOTIFICATION_EVENT, notification);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef";
+
+    fn sign(data: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(data);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn allowed(entries: &[&str], address: &str) -> bool {
+        let entries: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        address_allowed(&entries, address.parse().unwrap())
+    }
+
+    #[test]
+    fn signature_valid_checks_the_hmac_of_the_body() {
+        let body = br#"{"ref":"main"}"#;
+        assert!(signature_valid(SECRET, body, &sign(body)));
+        assert!(signature_valid(SECRET, body, &format!("  {}\n", sign(body))));
+        assert!(!signature_valid(SECRET, br#"{"ref":"evil"}"#, &sign(body)));
+        assert!(!signature_valid("another secret", body, &sign(body)));
+        assert!(!signature_valid(SECRET, body, sign(body).trim_start_matches("sha256=")));
+        assert!(!signature_valid(SECRET, body, "sha256=not-hex"));
+        assert!(!signature_valid(SECRET, body, &sign(body)[..40]));
+        assert!(!signature_valid(SECRET, body, ""));
+    }
+
+    #[test]
+    fn signed_timestamps_must_be_recent() {
+        let now = Utc::now();
+        let body = b"{}";
+        let at = |seconds_ago: i64| (now.timestamp() - seconds_ago).to_string();
+        let check = |timestamp: &str, signature: &str| {
+            let headers = DeliverySignature { timestamp: Some(timestamp), signature: Some(signature), github_signature: None };
+            verify_delivery(SECRET, body, &headers, now)
+        };
+        let signed_at = |timestamp: &str| sign(&[timestamp.as_bytes(), b".", body].concat());
+
+        for seconds_ago in [0, 60, -60, 300, -300] {
+            let timestamp = at(seconds_ago);
+            assert!(check(&timestamp, &signed_at(&timestamp)).is_ok(), "{} seconds ago", seconds_ago);
+        }
+        for seconds_ago in [301, -301, 86_400] {
+            let timestamp = at(seconds_ago);
+            assert!(check(&timestamp, &signed_at(&timestamp)).is_err(), "{} seconds ago", seconds_ago);
+        }
+        // The timestamp is part of what is signed, so a stale delivery cannot be freshened
+        let stale = at(3_600);
+        assert!(check(&at(0), &signed_at(&stale)).is_err());
+        // Signing the body alone is not enough for pH7's own header
+        assert!(check(&at(0), &sign(body)).is_err());
+        assert!(check("yesterday", &signed_at("yesterday")).is_err());
+        assert!(check(&i64::MIN.to_string(), &signed_at(&i64::MIN.to_string())).is_err());
+
+        let headers = DeliverySignature { timestamp: None, signature: Some(&signed_at(&at(0))), github_signature: None };
+        assert_eq!(verify_delivery(SECRET, body, &headers, now), Err("Missing X-Ph7-Timestamp"));
+        assert!(verify_delivery(SECRET, body, &DeliverySignature::default(), now).is_err());
+    }
+
+    #[test]
+    fn replay_keys_identify_signed_deliveries() {
+        let now = Utc::now();
+        let body = b"{}";
+        let key = |timestamp: i64| {
+            let timestamp = timestamp.to_string();
+            let signature = sign(&[timestamp.as_bytes(), b".", body].concat());
+            let headers = DeliverySignature { timestamp: Some(&timestamp), signature: Some(&signature), github_signature: None };
+            verify_delivery(SECRET, body, &headers, now).unwrap()
+        };
+        assert_eq!(key(now.timestamp()), key(now.timestamp()));
+        assert_ne!(key(now.timestamp()), key(now.timestamp() - 1));
+
+        // GitHub deliveries carry no timestamp: the same body and signature is the same delivery,
+        // whatever its delivery id says
+        let github = |body: &[u8]| {
+            let signature = sign(body);
+            let headers = DeliverySignature { github_signature: Some(&signature), ..DeliverySignature::default() };
+            verify_delivery(SECRET, body, &headers, now)
+        };
+        assert_eq!(github(b"{\"a\":1}").unwrap(), github(b"{\"a\":1}").unwrap());
+        assert_ne!(github(b"{\"a\":1}").unwrap(), github(b"{\"a\":2}").unwrap());
+        let headers = DeliverySignature { github_signature: Some("sha256=00"), ..DeliverySignature::default() };
+        assert!(verify_delivery(SECRET, body, &headers, now).is_err());
+    }
+
+    #[test]
+    fn audit_log_keeps_its_newest_entries() {
+        let file = std::env::temp_dir().join(format!("ph7-webhook-audit-{}.jsonl", std::process::id()));
+        let lines = (MAX_AUDIT_BYTES / 1024) as usize + 100;
+        let log: String = (0..lines).map(|i| format!("{:0>1023}\n", i)).collect();
+        std::fs::write(&file, &log).unwrap();
+        trim_audit_log(&file).unwrap();
+        let trimmed = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(trimmed.len() as u64 <= MAX_AUDIT_BYTES / 2);
+        assert!(trimmed.len() as u64 > MAX_AUDIT_BYTES / 2 - 1024);
+        // Whole lines, ending with the newest one
+        assert!(log.ends_with(&trimmed) && log[..log.len() - trimmed.len()].ends_with('\n'));
+    }
+
+    #[test]
+    fn address_allowed_matches_addresses_and_ranges() {
+        assert!(allowed(&["127.0.0.1"], "127.0.0.1"));
+        assert!(!allowed(&["127.0.0.1"], "127.0.0.2"));
+        assert!(allowed(&[" 10.0.0.0/8 "], "10.200.3.4"));
+        assert!(!allowed(&["10.0.0.0/8"], "11.0.0.1"));
+        assert!(allowed(&["192.168.1.0/24"], "192.168.1.255"));
+        assert!(!allowed(&["192.168.1.0/24"], "192.168.2.0"));
+        assert!(allowed(&["0.0.0.0/0"], "203.0.113.7"));
+        assert!(allowed(&["::1"], "::1"));
+        assert!(allowed(&["2001:db8::/32"], "2001:db8:1::5"));
+        assert!(!allowed(&["2001:db8::/32"], "2001:db9::5"));
+        // IPv4 clients of a dual-stack socket
+        assert!(allowed(&["203.0.113.0/24"], "::ffff:203.0.113.7"));
+        // Families do not mix, and entries that are not addresses match nothing
+        assert!(!allowed(&["::/0"], "127.0.0.1"));
+        assert!(!allowed(&["localhost", "127.0.0.x/8"], "127.0.0.1"));
+        assert!(!allowed(&[], "127.0.0.1"));
+        // Prefix lengths past the address size mean the whole address
+        assert!(allowed(&["10.0.0.1/99"], "10.0.0.1"));
+        assert!(!allowed(&["10.0.0.1/99"], "10.0.0.2"));
+    }
+}
@@ -0,0 +1,676 @@
+// The terminal's core, shared by the desktop app (`run`) and the `ph7` command line tool
+
+pub mod ai;
+pub mod terminal;
+mod commands;
+pub mod models;
+pub mod project;
+pub mod integrations;
+pub mod tools;
+pub mod database;
+pub mod system;
+
+use tauri::Manager;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use ai::ModelManager;
+use terminal::TerminalManager;
+use terminal::status::SessionStatusBoard;
+use terminal::completion_gate::CompletionGate;
+use terminal::command_queue::CommandQueues;
+use terminal::prompt::PromptEngine;
+use ai::command_review::ReviewCache;
+use ai::suggestion_throttle::SuggestionThrottle;
+use ai::provenance::ProvenanceLog;
+use project::audit::DependencyAuditor;
+use database::DatabaseManager;
+use tools::help_docs::HelpCache;
+use terminal::notifications::NotificationCenter;
+use system::speech::Speaker;
+use system::latency::LatencyMonitor;
+use system::remote::RemoteServer;
+use system::automation::AutomationServer;
+use integrations::webhooks::WebhookServer;
+use integrations::sinks::SinkManager;
+use system::ipc::OutputPages;
+use terminal::windows::{WindowGeometry, WindowRegistry, MAIN_WINDOW};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub model_manager: Arc<Mutex<ModelManager>>,
+    pub terminal_manager: Arc<Mutex<TerminalManager>>,
+    pub session_status: Arc<Mutex<SessionStatusBoard>>,
+    pub completion_gate: Arc<CompletionGate>,
+    pub command_queues: Arc<CommandQueues>,
+    pub prompt_engine: Arc<PromptEngine>,
+    pub review_cache: Arc<Mutex<ReviewCache>>,
+    pub suggestion_throttle: Arc<Mutex<SuggestionThrottle>>,
+    pub provenance: Arc<Mutex<ProvenanceLog>>,
+    pub dependency_auditor: Arc<Mutex<DependencyAuditor>>,
+    pub database_manager: Arc<Mutex<DatabaseManager>>,
+    pub help_cache: Arc<Mutex<HelpCache>>,
+    pub notification_center: Arc<Mutex<NotificationCenter>>,
+    pub speaker: Arc<Mutex<Speaker>>,
+    pub latency: Arc<LatencyMonitor>,
+    pub remote: Arc<Mutex<RemoteServer>>,
+    pub automation: Arc<Mutex<AutomationServer>>,
+    pub webhooks: Arc<Mutex<WebhookServer>>,
+    pub sinks: SinkManager,
+    pub window_registry: Arc<Mutex<WindowRegistry>>,
+    pub output_pages: Arc<Mutex<OutputPages>>,
+}
+
+/// Start the desktop app
+pub fn run() {
+    // Every command goes through a timing wrapper; see system::latency
+    let latency = system::latency::monitor().clone();
+    let timed = latency.clone();
+    let handler: Box<dyn Fn(tauri::ipc::Invoke) -> bool + Send + Sync> = Box::new(tauri::generate_handler![
+        commands::create_terminal,
+        commands::get_ssh_host_keys,
+        commands::create_remote_terminal,
+        commands::execute_command,
+        commands::execute_simple_command,
+        commands::get_terminal_output,
+        commands::ai_suggest_command,
+        commands::ai_explain_command,
+        commands::ai_fix_error,
+        commands::ai_analyze_output,
+        commands::get_smart_completions,
+        commands::ai_translate_natural_language,
+        commands::get_user_analytics,
+        commands::update_ai_feedback,
+        commands::create_agent_task,
+        commands::get_agent_task_status,
+        commands::get_active_agent_tasks,
+        commands::cancel_agent_task,
+        commands::close_terminal_session,
+        commands::update_session_title,
+        commands::resize_terminal,
+        commands::write_to_terminal,
+        commands::terminate_command,
+        commands::get_running_commands,
+        commands::get_child_policies,
+        commands::update_child_policies,
+        commands::set_session_child_policy,
+        commands::list_background_jobs,
+        commands::list_orphaned_processes,
+        commands::adopt_orphaned_process,
+        commands::stop_background_job,
+        commands::list_services,
+        commands::start_service,
+        commands::stop_service,
+        commands::get_service_logs,
+        commands::remove_service,
+        commands::suggest_services,
+        commands::resolve_service_port_conflict,
+        commands::diagnose_port_conflict,
+        commands::get_system_info,
+        commands::get_context_suggestions,
+        commands::get_all_sessions,
+        commands::restore_sessions,
+        commands::get_aliases,
+        commands::get_path_completions,
+        commands::get_recent_files,
+        commands::get_command_history_for_navigation,
+        commands::search_command_history,
+        commands::store_command_in_history,
+        commands::initialize_ml_system,
+        commands::get_repo_info,
+        commands::get_runtime_info,
+        commands::get_parent_directories,
+        commands::get_child_directories,
+        commands::change_directory,
+        commands::execute_file,
+        commands::validate_frequent_directories,
+        commands::find_path_in_common_locations,
+        commands::validate_and_correct_path,
+        commands::get_proactive_suggestions,
+        commands::dismiss_proactive_suggestion,
+        commands::update_git_suggestion_settings,
+        commands::run_dependency_audit,
+        commands::get_dependency_audit,
+        commands::schedule_dependency_audit,
+        commands::set_forge_token,
+        commands::list_pull_requests,
+        commands::create_pull_request,
+        commands::checkout_pr,
+        commands::get_ci_status,
+        commands::ai_generate_script,
+        commands::lint_script,
+        commands::save_generated_script,
+        commands::set_directory_trust,
+        commands::get_directory_trust,
+        commands::test_regex,
+        commands::ai_build_regex,
+        commands::preview_data_file,
+        commands::query_data_file,
+        commands::save_db_connection,
+        commands::list_db_connections,
+        commands::remove_db_connection,
+        commands::run_query,
+        commands::get_database_schema,
+        commands::get_sql_completions,
+        commands::ai_generate_sql,
+        commands::list_project_services,
+        commands::redis_scan_keys,
+        commands::redis_get_key,
+        commands::redis_memory_info,
+        commands::list_queues,
+        commands::diff_environment,
+        commands::export_environment_snapshot,
+        commands::import_environment_snapshot,
+        commands::analyze_path,
+        commands::resolve_command,
+        commands::get_help,
+        commands::ai_suggest_flags,
+        commands::preview_command_expansion,
+        commands::set_template_secret,
+        commands::list_session_variables,
+        commands::clear_session_variables,
+        commands::run_parallel,
+        commands::plan_parallel_from_request,
+        commands::image_info,
+        commands::resize_image,
+        commands::convert_image,
+        commands::strip_image_metadata,
+        commands::media_info,
+        commands::hash_file,
+        commands::verify_checksum,
+        commands::text_transform,
+        commands::format_config,
+        commands::validate_config,
+        commands::get_project_context,
+        commands::get_suggestion_corrections,
+        commands::clear_suggestion_corrections,
+        commands::get_model_routes,
+        commands::set_model_route,
+        commands::compare_models,
+        commands::accept_model_comparison,
+        commands::get_ai_usage_stats,
+        commands::set_ai_usage_budget,
+        commands::purge_command_history,
+        commands::clear_history,
+        commands::get_history_settings,
+        commands::update_history_settings,
+        commands::get_shell_history_settings,
+        commands::update_shell_history_settings,
+        commands::get_embedding_index_stats,
+        commands::compact_embedding_index,
+        commands::rebuild_embedding_index,
+        commands::semantic_history_search,
+        commands::analyze_command_activity,
+        commands::get_unresolved_failures,
+        commands::get_session_status,
+        commands::get_path_display_settings,
+        commands::update_path_display_settings,
+        commands::quote_shell_command,
+        commands::check_translated_command,
+        commands::get_translation_guard_settings,
+        commands::update_translation_guard_settings,
+        commands::search_paths,
+        commands::search_in_files,
+        commands::index_project,
+        commands::query_project_index,
+        commands::list_tests,
+        commands::run_tests,
+        commands::get_failed_tests,
+        commands::get_coverage_summary,
+        commands::set_coverage_baseline,
+        commands::scan_cleanup_candidates,
+        commands::execute_cleanup_plan,
+        commands::find_duplicates,
+        commands::list_directory,
+        commands::get_permissions,
+        commands::set_permissions,
+        commands::list_volumes,
+        commands::eject_volume,
+        commands::get_power_state,
+        commands::set_power_policy,
+        commands::list_notifications,
+        commands::mark_notifications_read,
+        commands::clear_notifications,
+        commands::get_notification_settings,
+        commands::update_notification_settings,
+        commands::set_session_quiet_hours,
+        commands::list_windows,
+        commands::detach_session,
+        commands::attach_session,
+        commands::get_scrollback,
+        commands::get_output_page,
+        commands::get_history_page,
+        commands::get_history_since,
+        commands::get_command_queue,
+        commands::cancel_queued_command,
+        commands::run_diagnostics,
+        commands::get_migration_status,
+        commands::list_launch_profiles,
+        commands::save_launch_profile,
+        commands::delete_launch_profile,
+        commands::list_shells,
+        commands::set_session_shell,
+        commands::get_structured_output_settings,
+        commands::update_structured_output_settings,
+        commands::query_structured_output,
+        commands::get_session_prompt,
+        commands::get_prompt_settings,
+        commands::update_prompt_settings,
+        commands::review_command,
+        commands::simulate_command,
+        commands::list_backup_jobs,
+        commands::suggest_backup_job,
+        commands::save_backup_job,
+        commands::delete_backup_job,
+        commands::run_backup_job,
+        commands::list_backup_snapshots,
+        commands::verify_backup_snapshot,
+        commands::browse_backup_snapshot,
+        commands::restore_from_backup,
+        commands::list_scratch_dirs,
+        commands::create_scratch_dir,
+        commands::remove_scratch_dir,
+        commands::get_scratch_settings,
+        commands::update_scratch_settings,
+        commands::get_directory_profile,
+        commands::get_directory_heatmap,
+        commands::get_suggestion_throttle,
+        commands::update_suggestion_throttle_settings,
+        commands::reset_suggestion_stats,
+        commands::explain_suggestion,
+        commands::reset_pattern,
+        commands::prune_learned_patterns,
+        commands::list_profiles,
+        commands::create_profile,
+        commands::switch_profile,
+        commands::set_profile_passphrase,
+        commands::delete_profile,
+        commands::start_demo_mode,
+        commands::is_demo_mode,
+        commands::set_accessible_output,
+        commands::ai_summarize_output,
+        commands::process_ansi_output,
+        commands::create_bug_report,
+        commands::transcribe_voice_command,
+        commands::get_voice_settings,
+        commands::update_voice_settings,
+        commands::speak_response,
+        commands::stop_speaking,
+        commands::get_speech_settings,
+        commands::update_speech_settings,
+        commands::list_speech_voices,
+        commands::get_performance_report,
+        commands::reset_performance_stats,
+        commands::get_latency_budgets,
+        commands::set_latency_budget,
+        commands::get_remote_access_status,
+        commands::start_remote_access,
+        commands::stop_remote_access,
+        commands::update_remote_access_options,
+        commands::create_remote_access_token,
+        commands::revoke_remote_access_token,
+        commands::share_session,
+        commands::get_session_shares,
+        commands::set_session_share_input,
+        commands::stop_session_share,
+        commands::get_automation_status,
+        commands::start_automation,
+        commands::stop_automation,
+        commands::update_automation_settings,
+        commands::get_webhook_status,
+        commands::start_webhooks,
+        commands::stop_webhooks,
+        commands::update_webhook_options,
+        commands::create_webhook_trigger,
+        commands::set_webhook_trigger_enabled,
+        commands::delete_webhook_trigger,
+        commands::get_webhook_audit_log,
+        commands::get_notification_sinks,
+        commands::create_notification_sink,
+        commands::update_notification_sink,
+        commands::delete_notification_sink,
+        commands::test_notification_sink,
+    ]);
+    
+    tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(move |app| {
+            // Files live in the platform's app directories; older builds left them in ./ai_data
+            system::app_dirs::init(app.handle());
+            system::app_dirs::move_legacy_data();
+            latency.load_budgets();
+            
+            // Saved data from older versions is brought up to date before anything reads it
+            ai::migrations::run();
+            
+            // Demo mode starts from synthetic data, written before the stores load it
+            let demo = system::demo::is_active();
+            if demo {
+                if let Err(e) = system::demo::seed() {
+                    println!("⚠️ Could not set up demo data: {}", e);
+                }
+            }
+            
+            // Initialize app state
+            let model_manager = Arc::new(Mutex::new(ModelManager::new()));
+            let mut terminal_manager = TerminalManager::new();
+            if demo {
+                for execution in system::demo::history() {
+                    terminal_manager.record_execution(execution);
+                }
+            }
+            // Users come back to the sessions they had open
+            for recovery in terminal_manager.restore_sessions().1 {
+                println!("{}", recovery.notice());
+            }
+            let mut pty_output = terminal_manager.subscribe_output();
+            let mut pty_directories = terminal_manager.subscribe_directories();
+            let mut service_events = terminal_manager.services().subscribe();
+            let terminal_manager = Arc::new(Mutex::new(terminal_manager));
+            let dependency_auditor = Arc::new(Mutex::new(DependencyAuditor::new()));
+            let window_registry = WindowRegistry::new();
+            
+            // The main window opens where it was last closed
+            if let (Some(window), Some(geometry)) = (app.get_webview_window(MAIN_WINDOW), window_registry.geometry(MAIN_WINDOW)) {
+                let _ = window.set_position(tauri::LogicalPosition::new(geometry.x, geometry.y));
+                let _ = window.set_size(tauri::LogicalSize::new(geometry.width, geometry.height));
+                if geometry.maximized {
+                    let _ = window.maximize();
+                }
+            }
+            let window_registry = Arc::new(Mutex::new(window_registry));
+            let sinks = SinkManager::new();
+            
+            let app_state = AppState {
+                model_manager: model_manager.clone(),
+                terminal_manager: terminal_manager.clone(),
+                session_status: Arc::new(Mutex::new(SessionStatusBoard::new())),
+                completion_gate: Arc::new(CompletionGate::new()),
+                command_queues: Arc::new(CommandQueues::new()),
+                prompt_engine: Arc::new(PromptEngine::new()),
+                review_cache: Arc::new(Mutex::new(ReviewCache::new())),
+                suggestion_throttle: Arc::new(Mutex::new(SuggestionThrottle::load())),
+                provenance: Arc::new(Mutex::new(ProvenanceLog::new())),
+                dependency_auditor: dependency_auditor.clone(),
+                database_manager: Arc::new(Mutex::new(DatabaseManager::new())),
+                help_cache: Arc::new(Mutex::new(HelpCache::new())),
+                notification_center: Arc::new(Mutex::new(NotificationCenter::new())),
+                speaker: Arc::new(Mutex::new(Speaker::new())),
+                latency: latency.clone(),
+                remote: Arc::new(Mutex::new(RemoteServer::new())),
+                automation: Arc::new(Mutex::new(AutomationServer::new())),
+                webhooks: Arc::new(Mutex::new(WebhookServer::new())),
+                sinks: sinks.clone(),
+                window_registry: window_registry.clone(),
+                output_pages: Arc::new(Mutex::new(OutputPages::new())),
+            };
+            
+            app.manage(app_state);
+            
+            // What sessions' shells write goes live to the window showing the session
+            let output_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match pty_output.recv().await {
+                        Ok(output) => {
+                            let state = output_app.state::<AppState>();
+                            let label = state.window_registry.lock().await.window_for(&output.session_id).to_string();
+                            let _ = output_app.emit_to(label, terminal::pty::OUTPUT_EVENT, output);
+                        }
+                        Err(RecvError::Lagged(skipped)) => println!("⚠️ Live terminal output fell behind, {} chunks dropped", skipped),
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+            
+            // Sessions follow their shells into the directories they change to
+            let directory_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match pty_directories.recv().await {
+                        Ok(report) => {
+                            let state = directory_app.state::<AppState>();
+                            if !state.terminal_manager.lock().await.follow_shell_directory(&report.session_id, &report.directory) {
+                                continue;
+                            }
+                            let label = state.window_registry.lock().await.window_for(&report.session_id).to_string();
+                            let _ = directory_app.emit_to(label, terminal::DIRECTORY_CHANGED_EVENT, report);
+                        }
+                        // Only the latest directory matters, and the next report has it
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+            
+            // Dev service state changes go to every window's services panel
+            let services_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match service_events.recv().await {
+                        Ok(info) => {
+                            let _ = services_app.emit(terminal::dev_services::SERVICES_EVENT, info);
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+            
+            // Remote access comes back up if the user asked for it to start with the app
+            let remote_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = remote_app.state::<AppState>();
+                let mut remote = state.remote.lock().await;
+                if remote.starts_on_launch() {
+                    if let Err(e) = remote.start(remote_app.clone()).await {
+                        println!("⚠️ Could not start remote access: {}", e);
+                    }
+                }
+            });
+            
+            let automation_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = automation_app.state::<AppState>();
+                let mut automation = state.automation.lock().await;
+                if automation.starts_on_launch() {
+                    if let Err(e) = automation.start(automation_app.clone()).await {
+                        println!("⚠️ Could not start the automation API: {}", e);
+                    }
+                }
+            });
+            
+            let webhooks_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = webhooks_app.state::<AppState>();
+                let mut webhooks = state.webhooks.lock().await;
+                if webhooks.starts_on_launch() {
+                    if let Err(e) = webhooks.start(webhooks_app.clone()).await {
+                        println!("⚠️ Could not start webhooks: {}", e);
+                    }
+                }
+            });
+            
+            let power_models = model_manager.clone();
+            
+            // Initialize local AI models on startup
+            let _app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                println!("🤖 Initializing local AI models...");
+                // Auto-load the model on startup
+                match model_manager.lock().await.load_model().await {
+                    Ok(_) => println!("✅ AI models loaded successfully and ready for natural language commands!"),
+                    Err(e) => println!("⚠️ Failed to load AI models: {}", e),
+                }
+            });
+
+            // Periodic dependency audits for scheduled projects
+            let audit_sinks = sinks.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    // Scheduled audits wait while on low battery
+                    let deferred = system::power::should_defer(system::power::BackgroundWork::Monitors);
+                    let due = if deferred { Vec::new() } else { dependency_auditor.lock().await.due_projects() };
+                    for project_path in due {
+                        println!("🔒 Running scheduled dependency audit for {}", project_path);
+                        let report = project::audit::run_audit(&project_path).await;
+                        if let Some(alert) = integrations::sinks::SinkMessage::audit(&report) {
+                            audit_sinks.publish(alert);
+                        }
+                        dependency_auditor.lock().await.store_report(report);
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(15 * 60)).await;
+                }
+            });
+            
+            // Scheduled backups, checked like the audits and also held back on low battery
+            let backup_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                loop {
+                    let deferred = system::power::should_defer(system::power::BackgroundWork::Monitors);
+                    let due = if deferred { Vec::new() } else { tools::backup::BackupJobs::load().due() };
+                    for job in due {
+                        println!("💾 Running scheduled backup {}", job.name);
+                        let progress_app = backup_app.clone();
+                        let (job_id, job_name) = (job.id.clone(), job.name.clone());
+                        let result = tokio::task::spawn_blocking(move || {
+                            tools::backup::run(&job, |progress| {
+                                let _ = progress_app.emit(tools::backup::PROGRESS_EVENT, progress);
+                            })
+                        })
+                        .await;
+                        let failure = match result {
+                            Ok(Ok(report)) => {
+                                let _ = tools::backup::BackupJobs::load().record_run(&job_id, report.started_at);
+                                None
+                            }
+                            Ok(Err(e)) => Some(e.to_string()),
+                            Err(e) => Some(e.to_string()),
+                        };
+                        if let Some(e) = failure {
+                            println!("⚠️ Scheduled backup failed: {}", e);
+                            let body = format!("{}: {}", job_name, e);
+                            sinks.publish(integrations::sinks::SinkMessage::new(integrations::sinks::SinkEvent::MonitorAlert, "💾 Scheduled backup failed", body));
+                        }
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(15 * 60)).await;
+                }
+            });
+            
+            // Power saving starts and ends with the battery; deferred work runs once it ends
+            let power_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                let mut saving = false;
+                loop {
+                    if let Ok(state) = tokio::task::spawn_blocking(system::power::refresh).await {
+                        if state.saving != saving {
+                            saving = state.saving;
+                            if !saving {
+                                power_models.lock().await.resume_deferred_work().await;
+                            }
+                            let _ = power_app.emit(system::power::POWER_EVENT, state);
+                        }
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                }
+            });
+            
+            // Mount and unmount notifications; sessions on a volume that vanished move off it
+            let volume_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                let mut watch = system::volumes::MountWatch::new(tokio::task::spawn_blocking(system::volumes::list).await.unwrap_or_default());
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    let Ok(current) = tokio::task::spawn_blocking(system::volumes::list).await else { continue };
+                    let (mounted, unmounted) = watch.update(current);
+                    for volume in mounted {
+                        let _ = volume_app.emit(system::volumes::MOUNTED_EVENT, volume);
+                    }
+                    for volume in unmounted {
+                        let recoveries = terminal_manager.lock().await.leave_directory(std::path::Path::new(&volume.mount_point));
+                        for recovery in recoveries {
+                            let label = window_registry.lock().await.window_for(&recovery.session_id).to_string();
+                            let _ = volume_app.emit_to(label, terminal::DIRECTORY_RECOVERED_EVENT, recovery);
+                        }
+                        let _ = volume_app.emit(system::volumes::UNMOUNTED_EVENT, volume);
+                    }
+                }
+            });
+            
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            // Windows can report events before setup has put the state in place
+            let Some(state) = window.try_state::<AppState>().map(|state| state.inner().clone()) else { return };
+            let label = window.label().to_string();
+            match event {
+                tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
+                    if let Some(geometry) = WindowGeometry::of(window) {
+                        tauri::async_runtime::spawn(async move {
+                            state.window_registry.lock().await.set_geometry(&label, geometry);
+                        });
+                    }
+                }
+                tauri::WindowEvent::CloseRequested { .. } => {
+                    let geometry = WindowGeometry::of(window);
+                    tauri::async_runtime::spawn(async move {
+                        let mut registry = state.window_registry.lock().await;
+                        if let Some(geometry) = geometry {
+                            registry.set_geometry(&label, geometry);
+                        }
+                        if let Err(e) = registry.save() {
+                            println!("⚠️ Could not save window layout: {}", e);
+                        }
+                    });
+                }
+                tauri::WindowEvent::Destroyed => {
+                    let app = window.app_handle().clone();
+                    if label == MAIN_WINDOW {
+                        // Detached windows only make sense next to the main one
+                        app.exit(0);
+                        return;
+                    }
+                    tauri::async_runtime::spawn(async move {
+                        let returned = state.window_registry.lock().await.window_closed(&label);
+                        if !returned.is_empty() {
+                            commands::broadcast_windows(&app, &state).await;
+                        }
+                    });
+                }
+                _ => {}
+            }
+        })
+        .invoke_handler(move |invoke| {
+            let command = invoke.message.command().to_string();
+            let started = std::time::Instant::now();
+            let handled = handler(invoke);
+            timed.record_dispatch(&command, started.elapsed());
+            handled
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Saves and indexing put off for power saving would be lost with the process
+            if let (tauri::RunEvent::Exit, Some(state)) = (&event, app.try_state::<AppState>()) {
+                let (model_manager, terminal_manager) = (state.model_manager.clone(), state.terminal_manager.clone());
+                tauri::async_runtime::block_on(async move {
+                    model_manager.lock().await.flush_deferred_work().await;
+                    terminal_manager.lock().await.flush_usage();
+                });
+            }
+            // So would stores still waiting to be written in the background
+            if let tauri::RunEvent::Exit = event {
+                crate::system::fs_tasks::flush_writes();
+            }
+        });
+}
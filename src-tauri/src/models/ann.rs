@@ -0,0 +1,198 @@
+// Approximate nearest neighbour index (HNSW) over embedding slots
+// Nodes are slot numbers in the embedding store; vectors stay in the store and are
+// looked up through a closure, so the graph only holds neighbour lists.
+// Deleted slots remain in the graph for navigation and are filtered from results.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Cosine distance: 0 for identical directions, 2 for opposite ones
+pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 2.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        1.0
+    } else {
+        1.0 - dot / (norm_a * norm_b)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    m: usize,
+    ef_construction: usize,
+    /// neighbours[node][layer]
+    neighbours: Vec<Vec<Vec<u32>>>,
+    entry_point: Option<u32>,
+    max_layer: usize,
+    rng_state: u64,
+}
+
+impl Default for HnswIndex {
+    fn default() -> Self {
+        Self::new(16, 100)
+    }
+}
+
+impl HnswIndex {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Self {
+            m: m.max(2),
+            ef_construction: ef_construction.max(m),
+            neighbours: Vec::new(),
+            entry_point: None,
+            max_layer: 0,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    fn max_neighbours(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    /// Layer for a new node, exponentially distributed with mL = 1/ln(M)
+    fn random_layer(&mut self) -> usize {
+        // xorshift64*
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let random = self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        let uniform = ((random >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let layer = (-uniform.ln() / (self.m as f64).ln()).floor() as usize;
+        layer.min(16)
+    }
+
+    fn search_layer<'a>(&self, query: &[f32], entry: &[u32], ef: usize, layer: usize, vector: &impl Fn(u32) -> &'a [f32]) -> Vec<Scored> {
+        let mut visited: HashSet<u32> = entry.iter().copied().collect();
+        // Min-heap of candidates to expand, max-heap of the best `ef` found so far
+        let mut candidates: BinaryHeap<std::cmp::Reverse<Scored>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Scored> = BinaryHeap::new();
+        for &node in entry {
+            let scored = Scored { distance: cosine_distance(query, vector(node)), node };
+            candidates.push(std::cmp::Reverse(scored));
+            found.push(scored);
+        }
+
+        while let Some(std::cmp::Reverse(current)) = candidates.pop() {
+            let worst = found.peek().map(|s| s.distance).unwrap_or(f32::MAX);
+            if current.distance > worst && found.len() >= ef {
+                break;
+            }
+            let Some(links) = self.neighbours[current.node as usize].get(layer) else { continue };
+            for &next in links {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let scored = Scored { distance: cosine_distance(query, vector(next)), node: next };
+                let worst = found.peek().map(|s| s.distance).unwrap_or(f32::MAX);
+                if found.len() < ef || scored.distance < worst {
+                    candidates.push(std::cmp::Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    fn greedy_closest<'a>(&self, query: &[f32], mut entry: u32, from_layer: usize, to_layer: usize, vector: &impl Fn(u32) -> &'a [f32]) -> u32 {
+        for layer in (to_layer..=from_layer).rev() {
+            if let Some(best) = self.search_layer(query, &[entry], 1, layer, vector).first() {
+                entry = best.node;
+            }
+        }
+        entry
+    }
+
+    /// Add the next slot; nodes must be inserted in slot order
+    pub fn insert<'a>(&mut self, node: u32, vector: &impl Fn(u32) -> &'a [f32]) {
+        debug_assert_eq!(node as usize, self.neighbours.len());
+        let layer = self.random_layer();
+        self.neighbours.push(vec![Vec::new(); layer + 1]);
+
+        let Some(entry) = self.entry_point else {
+            self.entry_point = Some(node);
+            self.max_layer = layer;
+            return;
+        };
+        let query = vector(node);
+        let mut entry = if self.max_layer > layer {
+            self.greedy_closest(query, entry, self.max_layer, layer + 1, vector)
+        } else {
+            entry
+        };
+
+        for current in (0..=layer.min(self.max_layer)).rev() {
+            let candidates = self.search_layer(query, &[entry], self.ef_construction, current, vector);
+            let limit = self.max_neighbours(current);
+            let selected: Vec<u32> = candidates.iter().filter(|s| s.node != node).take(limit).map(|s| s.node).collect();
+            for &neighbour in &selected {
+                let links = &mut self.neighbours[neighbour as usize][current];
+                links.push(node);
+                if links.len() > limit {
+                    // Keep the closest links of the neighbour
+                    let base = vector(neighbour);
+                    let mut scored: Vec<Scored> = links.iter().map(|&n| Scored { distance: cosine_distance(base, vector(n)), node: n }).collect();
+                    scored.sort();
+                    *links = scored.into_iter().take(limit).map(|s| s.node).collect();
+                }
+            }
+            self.neighbours[node as usize][current] = selected;
+            if let Some(best) = candidates.first() {
+                entry = best.node;
+            }
+        }
+
+        if layer > self.max_layer {
+            self.max_layer = layer;
+            self.entry_point = Some(node);
+        }
+    }
+
+    /// Up to `k` nearest live nodes with their cosine distance, closest first
+    pub fn search<'a>(&self, query: &[f32], k: usize, ef: usize, vector: &impl Fn(u32) -> &'a [f32], is_live: impl Fn(u32) -> bool) -> Vec<(u32, f32)> {
+        let Some(entry) = self.entry_point else { return Vec::new() };
+        let entry = self.greedy_closest(query, entry, self.max_layer, 1, vector);
+        self.search_layer(query, &[entry], ef.max(k), 0, vector)
+            .into_iter()
+            .filter(|s| is_live(s.node))
+            .take(k)
+            .map(|s| (s.node, s.distance))
+            .collect()
+    }
+}
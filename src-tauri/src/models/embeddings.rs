@@ -1,6 +1,79 @@
 // Local embeddings for semantic search and context understanding
+// Vectors are persisted to an append-only segment file (one JSON record per line,
+// vectors as base64 little-endian f32) that is replayed on open and compacted on demand
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::ann::HnswIndex;
+use super::sentence_encoder::SentenceEncoder;
+
+/// Model tag of the byte-hash fallback vectors
+pub const HASH_MODEL: &str = "hash";
+
+fn hash_model() -> String {
+    HASH_MODEL.to_string()
+}
+
+/// Byte-hash vector, used when no sentence encoder is available
+fn hash_embedding(text: &str, dimension: usize) -> Vec<f32> {
+    let mut embedding = vec![0.0; dimension];
+    
+    for (i, byte) in text.bytes().enumerate() {
+        if i >= dimension { break; }
+        embedding[i] = (byte as f32) / 255.0;
+    }
+    
+    // Normalize the vector
+    let magnitude: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if magnitude > 0.0 {
+        for val in &mut embedding {
+            *val /= magnitude;
+        }
+    }
+    
+    embedding
+}
+
+/// What turns text into vectors, taken out of the store so encoding runs without holding it
+#[derive(Clone)]
+pub struct Embedder {
+    encoder: Option<std::sync::Arc<SentenceEncoder>>,
+    dimension: usize,
+}
+
+impl Embedder {
+    /// Vectors and model tags for texts, falling back to the hash method if encoding fails
+    pub fn embed_batch(&self, texts: &[String]) -> Vec<(Vec<f32>, String)> {
+        if let Some(encoder) = &self.encoder {
+            match encoder.encode_batch(texts) {
+                Ok(vectors) if vectors.len() == texts.len() && vectors.iter().all(|v| v.len() == self.dimension) => {
+                    return vectors.into_iter().map(|vector| (vector, super::sentence_encoder::MODEL_NAME.to_string())).collect();
+                }
+                Ok(_) => println!("⚠️ Embedding model returned an unexpected shape, using hashed vectors"),
+                Err(e) => println!("⚠️ {}, using hashed vectors", e),
+            }
+        }
+        texts.iter().map(|text| (hash_embedding(text, self.dimension), HASH_MODEL.to_string())).collect()
+    }
+
+    /// `embed_batch` on a blocking thread, since inference keeps a core busy
+    pub async fn embed_batch_blocking(self, texts: Vec<String>) -> Vec<(Vec<f32>, String)> {
+        let fallback = self.clone();
+        let hashed = texts.clone();
+        tokio::task::spawn_blocking(move || self.embed_batch(&texts))
+            .await
+            .unwrap_or_else(|_| hashed.iter().map(|text| (hash_embedding(text, fallback.dimension), HASH_MODEL.to_string())).collect())
+    }
+}
+
+/// Commands indexed before entries had a type count as commands
+pub fn is_command(embedding: &EmbeddingVector) -> bool {
+    embedding.metadata.get("type").map_or(true, |kind| kind == "command")
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingVector {
@@ -9,6 +82,9 @@ pub struct EmbeddingVector {
     pub vector: Vec<f32>,
     pub metadata: HashMap<String, String>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Model that produced the vector; vectors from different models are not comparable
+    #[serde(default = "hash_model")]
+    pub model: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,43 +105,446 @@ pub enum ContextType {
     SystemInfo,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingIndexStats {
+    pub entries: usize,
+    pub dimension: usize,
+    pub segment_path: Option<String>,
+    pub segment_bytes: u64,
+    /// Records in the segment file, including replaced and deleted ones
+    pub segment_records: usize,
+    /// Records compaction would drop
+    pub dead_records: usize,
+    pub index_nodes: usize,
+    /// Whether searches go through the HNSW graph rather than a linear scan
+    pub uses_ann: bool,
+    pub model: String,
+    /// Entries embedded by another model, waiting to be re-embedded
+    pub stale_entries: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredEmbedding {
+    id: String,
+    text: String,
+    vector: String,
+    metadata: HashMap<String, String>,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default = "hash_model")]
+    model: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum SegmentRecord {
+    Add(StoredEmbedding),
+    Delete { id: String },
+}
+
+impl From<&EmbeddingVector> for StoredEmbedding {
+    fn from(embedding: &EmbeddingVector) -> Self {
+        let bytes: Vec<u8> = embedding.vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+        StoredEmbedding {
+            id: embedding.id.clone(),
+            text: embedding.text.clone(),
+            vector: STANDARD.encode(bytes),
+            metadata: embedding.metadata.clone(),
+            timestamp: embedding.timestamp,
+            model: embedding.model.clone(),
+        }
+    }
+}
+
+impl StoredEmbedding {
+    fn into_embedding(self) -> Option<EmbeddingVector> {
+        let bytes = STANDARD.decode(&self.vector).ok()?;
+        let vector = bytes.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        Some(EmbeddingVector { id: self.id, text: self.text, vector, metadata: self.metadata, timestamp: self.timestamp, model: self.model })
+    }
+}
+
+/// Below this many live entries a linear scan is both exact and fast enough
+const BRUTE_FORCE_LIMIT: usize = 2_000;
+/// Inserts after which the ANN graph is written back to disk
+const INDEX_SAVE_INTERVAL: usize = 5_000;
+
+#[derive(Serialize, Deserialize)]
+struct SavedIndex {
+    /// Slots covered and the id in the last one, to detect a graph that no longer matches the segment
+    slots: usize,
+    last_id: Option<String>,
+    index: HnswIndex,
+}
+
+/// Embeddings live in append-only slots so ANN node numbers stay stable;
+/// replaced and deleted slots are marked dead until the next compaction.
 pub struct LocalEmbeddingStore {
     embeddings: Vec<EmbeddingVector>,
+    live: Vec<bool>,
+    positions: HashMap<String, usize>,
     dimension: usize,
+    segment: Option<PathBuf>,
+    segment_records: usize,
+    index: HnswIndex,
+    unsaved_inserts: usize,
+    encoder: Option<std::sync::Arc<SentenceEncoder>>,
+}
+
+impl Default for LocalEmbeddingStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LocalEmbeddingStore {
     pub fn new() -> Self {
         Self {
             embeddings: Vec::new(),
+            live: Vec::new(),
+            positions: HashMap::new(),
             dimension: 384, // Using smaller embeddings for efficiency
+            segment: None,
+            segment_records: 0,
+            index: HnswIndex::default(),
+            unsaved_inserts: 0,
+            encoder: None,
         }
     }
 
-    pub fn add_embedding(&mut self, embedding: EmbeddingVector) {
+    /// Store backed by a segment file; existing records are replayed and the saved ANN graph reused
+    pub fn open(segment: PathBuf) -> Self {
+        let mut store = Self::new();
+        if let Ok(data) = std::fs::read_to_string(&segment) {
+            for line in data.lines().filter(|l| !l.trim().is_empty()) {
+                store.segment_records += 1;
+                match serde_json::from_str::<SegmentRecord>(line) {
+                    Ok(SegmentRecord::Add(stored)) => {
+                        if let Some(embedding) = stored.into_embedding().filter(|e| e.vector.len() == store.dimension) {
+                            store.insert_slot(embedding);
+                        }
+                    }
+                    Ok(SegmentRecord::Delete { id }) => {
+                        store.remove_in_memory(&id);
+                    }
+                    // A torn final line from a crash is simply dropped at the next compaction
+                    Err(_) => {}
+                }
+            }
+        }
+        store.segment = Some(segment);
+        store.load_index();
+        store
+    }
+
+    fn index_file(&self) -> Option<PathBuf> {
+        self.segment.as_ref().map(|segment| segment.with_extension("hnsw"))
+    }
+
+    /// Reuse the saved graph when it matches the replayed slots, then index whatever is missing
+    fn load_index(&mut self) {
+        let saved = self.index_file()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .and_then(|data| serde_json::from_str::<SavedIndex>(&data).ok())
+            .filter(|saved| {
+                saved.slots == saved.index.len()
+                    && saved.slots <= self.embeddings.len()
+                    && saved.last_id == saved.slots.checked_sub(1).map(|i| self.embeddings[i].id.clone())
+            });
+        let from = match saved {
+            Some(saved) => {
+                self.index = saved.index;
+                saved.slots
+            }
+            None => {
+                self.index = HnswIndex::default();
+                0
+            }
+        };
+        for slot in from..self.embeddings.len() {
+            self.index_slot(slot);
+        }
+    }
+
+    fn save_index(&mut self) -> Result<(), String> {
+        let Some(file) = self.index_file() else { return Ok(()) };
+        let saved = SavedIndex {
+            slots: self.index.len(),
+            last_id: self.index.len().checked_sub(1).map(|i| self.embeddings[i].id.clone()),
+            index: self.index.clone(),
+        };
+        let json = serde_json::to_string(&saved).map_err(|e| e.to_string())?;
+        std::fs::write(&file, json).map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+        self.unsaved_inserts = 0;
+        Ok(())
+    }
+
+    fn index_slot(&mut self, slot: usize) {
+        let embeddings = &self.embeddings;
+        self.index.insert(slot as u32, &|node| embeddings[node as usize].vector.as_slice());
+    }
+
+    fn append(&mut self, records: &[SegmentRecord]) {
+        let Some(segment) = &self.segment else { return };
+        let result = (|| -> std::io::Result<()> {
+            if let Some(parent) = segment.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(segment)?;
+            let mut buffer = String::new();
+            for record in records {
+                buffer.push_str(&serde_json::to_string(record).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?);
+                buffer.push('\n');
+            }
+            file.write_all(buffer.as_bytes())
+        })();
+        match result {
+            Ok(()) => self.segment_records += records.len(),
+            Err(e) => println!("⚠️ Failed to write embedding segment: {}", e),
+        }
+    }
+
+    /// New slot for an embedding; an older slot with the same id is retired
+    fn insert_slot(&mut self, embedding: EmbeddingVector) -> usize {
+        if let Some(old) = self.positions.get(&embedding.id) {
+            self.live[*old] = false;
+        }
+        let slot = self.embeddings.len();
+        self.positions.insert(embedding.id.clone(), slot);
         self.embeddings.push(embedding);
+        self.live.push(true);
+        slot
     }
 
-    pub fn semantic_search(&self, query_vector: &[f32], top_k: usize) -> Vec<SemanticSearchResult> {
-        let mut results: Vec<(f32, &EmbeddingVector)> = self.embeddings
-            .iter()
+    fn remove_in_memory(&mut self, id: &str) -> bool {
+        let Some(slot) = self.positions.remove(id) else { return false };
+        self.live[slot] = false;
+        true
+    }
+
+    /// Add or replace an embedding (by id)
+    pub fn add_embedding(&mut self, embedding: EmbeddingVector) {
+        self.append(&[SegmentRecord::Add(StoredEmbedding::from(&embedding))]);
+        let slot = self.insert_slot(embedding);
+        self.index_slot(slot);
+        self.unsaved_inserts += 1;
+        if self.unsaved_inserts >= INDEX_SAVE_INTERVAL {
+            if let Err(e) = self.save_index() {
+                println!("⚠️ {}", e);
+            }
+        }
+    }
+
+    /// Delete embeddings matching a predicate; returns how many were removed
+    pub fn remove_where(&mut self, predicate: impl Fn(&EmbeddingVector) -> bool) -> usize {
+        let ids: Vec<String> = self.live_embeddings().filter(|e| predicate(e)).map(|e| e.id.clone()).collect();
+        for id in &ids {
+            self.remove_in_memory(id);
+        }
+        let records: Vec<SegmentRecord> = ids.iter().map(|id| SegmentRecord::Delete { id: id.clone() }).collect();
+        self.append(&records);
+        ids.len()
+    }
+
+    fn live_embeddings(&self) -> impl Iterator<Item = &EmbeddingVector> {
+        self.embeddings.iter().zip(&self.live).filter(|(_, live)| **live).map(|(e, _)| e)
+    }
+
+    /// Most recent live commands embedded by `model`, newest first
+    pub fn recent_entries(&self, model: &str, limit: usize) -> Vec<EmbeddingVector> {
+        let mut entries: Vec<&EmbeddingVector> = self.live_embeddings().filter(|e| e.model == model && is_command(e)).collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        entries.into_iter().take(limit).cloned().collect()
+    }
+
+    /// Embed and store an executed command, keyed by its execution id
+    pub fn index_command(&mut self, id: &str, command: &str, metadata: HashMap<String, String>, timestamp: chrono::DateTime<chrono::Utc>) {
+        self.index_document(id, command, "command", metadata, timestamp);
+    }
+
+    /// Embed and store any text; `kind` goes into the `type` metadata so searches can tell entries apart
+    pub fn index_document(&mut self, id: &str, text: &str, kind: &str, metadata: HashMap<String, String>, timestamp: chrono::DateTime<chrono::Utc>) {
+        let embedded = self.embed(text);
+        self.insert_document(id, text, kind, metadata, timestamp, embedded);
+    }
+
+    /// Store text with a vector computed beforehand, e.g. by an `Embedder` outside the store's lock
+    pub fn insert_document(
+        &mut self,
+        id: &str,
+        text: &str,
+        kind: &str,
+        metadata: HashMap<String, String>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        (vector, model): (Vec<f32>, String),
+    ) {
+        let mut metadata = metadata;
+        metadata.insert("type".to_string(), kind.to_string());
+        let embedding = EmbeddingVector {
+            id: id.to_string(),
+            text: text.to_string(),
+            vector,
+            metadata,
+            timestamp,
+            model,
+        };
+        self.add_embedding(embedding);
+    }
+
+    /// Rewrite the segment file with only live entries and rebuild the ANN graph to match
+    pub fn compact(&mut self) -> Result<EmbeddingIndexStats, String> {
+        let embeddings: Vec<EmbeddingVector> = self.live_embeddings().cloned().collect();
+        self.embeddings.clear();
+        self.live.clear();
+        self.positions.clear();
+        for embedding in embeddings {
+            self.insert_slot(embedding);
+        }
+
+        if let Some(segment) = self.segment.clone() {
+            let temp = segment.with_extension("compacting");
+            let mut buffer = String::new();
+            for embedding in &self.embeddings {
+                let record = SegmentRecord::Add(StoredEmbedding::from(embedding));
+                buffer.push_str(&serde_json::to_string(&record).map_err(|e| e.to_string())?);
+                buffer.push('\n');
+            }
+            if let Some(parent) = segment.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(&temp, buffer).map_err(|e| format!("Failed to write {}: {}", temp.display(), e))?;
+            std::fs::rename(&temp, &segment).map_err(|e| format!("Failed to replace {}: {}", segment.display(), e))?;
+            self.segment_records = self.embeddings.len();
+        }
+        self.rebuild_index()
+    }
+
+    /// Build the ANN graph from scratch over the current slots
+    pub fn rebuild_index(&mut self) -> Result<EmbeddingIndexStats, String> {
+        self.index = HnswIndex::default();
+        for slot in 0..self.embeddings.len() {
+            self.index_slot(slot);
+        }
+        self.save_index()?;
+        Ok(self.stats())
+    }
+
+    pub fn stats(&self) -> EmbeddingIndexStats {
+        EmbeddingIndexStats {
+            entries: self.positions.len(),
+            dimension: self.dimension,
+            segment_path: self.segment.as_ref().map(|p| p.display().to_string()),
+            segment_bytes: self.segment.as_ref().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len()).unwrap_or(0),
+            segment_records: self.segment_records,
+            dead_records: self.segment_records.saturating_sub(self.positions.len()),
+            index_nodes: self.index.len(),
+            uses_ann: self.positions.len() > BRUTE_FORCE_LIMIT,
+            model: self.active_model().to_string(),
+            stale_entries: self.live_embeddings().filter(|e| e.model != self.active_model()).count(),
+        }
+    }
+
+    fn search_result(&self, embedding: &EmbeddingVector, similarity: f32) -> SemanticSearchResult {
+        SemanticSearchResult {
+            text: embedding.text.clone(),
+            similarity,
+            context_type: self.infer_context_type(&embedding.text),
+            metadata: embedding.metadata.clone(),
+        }
+    }
+
+    /// Most similar live entries embedded by `model` and accepted by `filter`;
+    /// HNSW for large stores, an exact scan for small ones
+    pub fn semantic_search_where(
+        &self,
+        query_vector: &[f32],
+        model: &str,
+        top_k: usize,
+        filter: impl Fn(&EmbeddingVector) -> bool,
+    ) -> Vec<SemanticSearchResult> {
+        if self.positions.len() > BRUTE_FORCE_LIMIT && self.index.len() == self.embeddings.len() {
+            let embeddings = &self.embeddings;
+            // Dead slots are skipped after the graph search, so look a little wider
+            let ef = (top_k * 4).max(64);
+            let usable = |node: u32| {
+                let embedding = &embeddings[node as usize];
+                self.live[node as usize] && embedding.model == model && filter(embedding)
+            };
+            return self.index
+                .search(query_vector, top_k, ef, &|node| embeddings[node as usize].vector.as_slice(), usable)
+                .into_iter()
+                .map(|(node, distance)| self.search_result(&self.embeddings[node as usize], 1.0 - distance))
+                .collect();
+        }
+
+        let mut results: Vec<(f32, &EmbeddingVector)> = self.live_embeddings()
+            .filter(|emb| emb.model == model && filter(emb))
             .map(|emb| (cosine_similarity(query_vector, &emb.vector), emb))
             .collect();
 
-        results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        results.sort_by(|a, b| b.0.total_cmp(&a.0));
         
         results
             .into_iter()
             .take(top_k)
-            .map(|(similarity, emb)| SemanticSearchResult {
-                text: emb.text.clone(),
-                similarity,
-                context_type: self.infer_context_type(&emb.text),
-                metadata: emb.metadata.clone(),
-            })
+            .map(|(similarity, emb)| self.search_result(emb, similarity))
             .collect()
     }
 
+    /// Embed a query and search entries accepted by `filter`
+    pub fn search_text_where(&self, query: &str, top_k: usize, filter: impl Fn(&EmbeddingVector) -> bool) -> Vec<SemanticSearchResult> {
+        let (vector, model) = self.embed(query);
+        self.semantic_search_where(&vector, &model, top_k, filter)
+    }
+
+    /// Use a sentence encoder for new vectors; existing hash vectors become stale
+    pub fn set_encoder(&mut self, encoder: std::sync::Arc<SentenceEncoder>) {
+        self.encoder = Some(encoder);
+    }
+
+    pub fn encoder(&self) -> Option<std::sync::Arc<SentenceEncoder>> {
+        self.encoder.clone()
+    }
+
+    pub fn active_model(&self) -> &str {
+        if self.encoder.is_some() { super::sentence_encoder::MODEL_NAME } else { HASH_MODEL }
+    }
+
+    pub fn embedder(&self) -> Embedder {
+        Embedder { encoder: self.encoder.clone(), dimension: self.dimension }
+    }
+
+    /// Vector and model tag for a text, falling back to the hash method if encoding fails
+    pub fn embed(&self, text: &str) -> (Vec<f32>, String) {
+        self.embedder().embed_batch(&[text.to_string()]).remove(0)
+    }
+
+    /// Up to `limit` live entries embedded by another model than the active one
+    pub fn stale_entries(&self, limit: usize) -> Vec<(String, String)> {
+        let active = self.active_model();
+        self.live_embeddings()
+            .filter(|e| e.model != active)
+            .take(limit)
+            .map(|e| (e.id.clone(), e.text.clone()))
+            .collect()
+    }
+
+    /// Store re-computed vectors for existing entries; returns how many were replaced
+    pub fn replace_vectors(&mut self, updates: Vec<(String, Vec<f32>)>, model: &str) -> usize {
+        let mut replaced = 0;
+        for (id, vector) in updates {
+            let Some(&slot) = self.positions.get(&id) else { continue };
+            if vector.len() != self.dimension {
+                continue;
+            }
+            let mut embedding = self.embeddings[slot].clone();
+            embedding.vector = vector;
+            embedding.model = model.to_string();
+            self.add_embedding(embedding);
+            replaced += 1;
+        }
+        replaced
+    }
+
     fn infer_context_type(&self, text: &str) -> ContextType {
         if text.starts_with("Error:") || text.contains("error") {
             ContextType::ErrorMessage
@@ -80,31 +559,21 @@ impl LocalEmbeddingStore {
         }
     }
 
-    // Simple text-to-embedding conversion (would be replaced with actual model)
+    /// Byte-hash vector, used when no sentence encoder is available
     pub fn text_to_embedding(&self, text: &str) -> Vec<f32> {
-        // Simplified hash-based embedding for demo
-        // In production, use a proper embedding model like sentence-transformers
-        let mut embedding = vec![0.0; self.dimension];
-        
-        for (i, byte) in text.bytes().enumerate() {
-            if i >= self.dimension { break; }
-            embedding[i] = (byte as f32) / 255.0;
-        }
-        
-        // Normalize the vector
-        let magnitude: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if magnitude > 0.0 {
-            for val in &mut embedding {
-                *val /= magnitude;
-            }
-        }
-        
-        embedding
+        hash_embedding(text, self.dimension)
     }
 
     pub fn index_command_history(&mut self, commands: &[String]) {
+        // Batch through the encoder when there is one
+        let encoded = self.encoder.as_ref()
+            .and_then(|encoder| encoder.encode_batch(commands).ok())
+            .filter(|vectors| vectors.len() == commands.len());
         for (i, command) in commands.iter().enumerate() {
-            let embedding_vector = self.text_to_embedding(command);
+            let (embedding_vector, model) = match &encoded {
+                Some(vectors) => (vectors[i].clone(), self.active_model().to_string()),
+                None => (self.text_to_embedding(command), HASH_MODEL.to_string()),
+            };
             let mut metadata = HashMap::new();
             metadata.insert("type".to_string(), "command".to_string());
             metadata.insert("index".to_string(), i.to_string());
@@ -115,6 +584,7 @@ impl LocalEmbeddingStore {
                 vector: embedding_vector,
                 metadata,
                 timestamp: chrono::Utc::now(),
+                model,
             };
             
             self.add_embedding(embedding);
@@ -7,6 +7,7 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use super::local_llm::{LocalModelInfo, ModelType, Capability};
+use crate::terminal::shell_quote::quote_arg;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMResponse {
@@ -612,7 +613,7 @@ impl LightweightLLM {
         
         for (keyword, path) in directory_map {
             if prompt.contains(keyword) {
-                return format!("cd {}", path);
+                return format!("cd {}", quote_arg(path));
             }
         }
         
@@ -620,7 +621,7 @@ impl LightweightLLM {
         if let Some(to_index) = prompt.find(" to ") {
             let after_to = &prompt[to_index + 4..].trim();
             if let Some(word) = after_to.split_whitespace().next() {
-                return format!("cd {}", word);
+                return format!("cd {}", quote_arg(word));
             }
         }
         
@@ -628,7 +629,7 @@ impl LightweightLLM {
         if let Some(start) = prompt.find('"') {
             if let Some(end) = prompt[start + 1..].find('"') {
                 let path = &prompt[start + 1..start + 1 + end];
-                return format!("cd \"{}\"", path);
+                return format!("cd {}", quote_arg(path));
             }
         }
         
@@ -637,7 +638,7 @@ impl LightweightLLM {
         for word in words {
             if word.starts_with('/') || word.starts_with('~') || 
                word.starts_with("./") || word.starts_with("../") {
-                return format!("cd {}", word);
+                return format!("cd {}", quote_arg(word));
             }
         }
         
@@ -651,7 +652,7 @@ impl LightweightLLM {
         if let Some(start) = prompt.find('"') {
             if let Some(end) = prompt[start + 1..].find('"') {
                 let search_term = &prompt[start + 1..start + 1 + end];
-                return format!("find . -name '*{}*'", search_term);
+                return format!("find . -name {}", quote_arg(&format!("*{}*", search_term)));
             }
         }
         
@@ -662,7 +663,7 @@ impl LightweightLLM {
                 let after_keyword = &prompt[keyword_index + keyword.len()..].trim();
                 if let Some(word) = after_keyword.split_whitespace().next() {
                     if !["file", "files", "document", "documents"].contains(&word) {
-                        return format!("find . -name '*{}*'", word);
+                        return format!("find . -name {}", quote_arg(&format!("*{}*", word)));
                     }
                 }
             }
@@ -689,7 +690,7 @@ impl LightweightLLM {
                 let after_keyword = &prompt[keyword_index + keyword.len()..].trim();
                 if let Some(word) = after_keyword.split_whitespace().next() {
                     if !["file", "folder", "directory", "document"].contains(&word) {
-                        return format!("{} {}", cmd, word);
+                        return format!("{} {}", cmd, quote_arg(word));
                     }
                 }
             }
@@ -699,7 +700,7 @@ impl LightweightLLM {
         if let Some(called_index) = prompt.find(" called ") {
             let after_called = &prompt[called_index + 8..].trim();
             if let Some(word) = after_called.split_whitespace().next() {
-                return format!("{} {}", cmd, word);
+                return format!("{} {}", cmd, quote_arg(word));
             }
         }
         
@@ -713,7 +714,7 @@ impl LightweightLLM {
             if let Some(start) = after_message.find('"') {
                 if let Some(end) = after_message[start + 1..].find('"') {
                     let message = &after_message[start + 1..start + 1 + end];
-                    return format!("git add . && git commit -m \"{}\"", message);
+                    return format!("git add . && git commit -m {}", quote_arg(message));
                 }
             }
         }
@@ -728,7 +729,7 @@ impl LightweightLLM {
             let after_install = &prompt[install_index + 7..].trim();
             if let Some(word) = after_install.split_whitespace().next() {
                 if !["package", "dependency", "module"].contains(&word) {
-                    return format!("npm install {}", word);
+                    return format!("npm install {}", quote_arg(word));
                 }
             }
         }
@@ -22,7 +22,7 @@ pub enum ModelType {
     TinyLlama,     // 1.1B parameters - Fastest
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Capability {
     CodeGeneration,
     CommandSuggestion,
@@ -32,6 +32,7 @@ pub enum Capability {
     SystemDiagnostics,
     FileSearch,
     LogAnalysis,
+    CommandExplanation,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +117,7 @@ impl LocalModelInfo {
             Capability::SystemDiagnostics => Some(ModelType::Llama32_3B),
             Capability::FileSearch => Some(ModelType::CodeQwen),
             Capability::LogAnalysis => Some(ModelType::Phi3Mini),
+            Capability::CommandExplanation => Some(ModelType::TinyLlama),
         }
     }
 }
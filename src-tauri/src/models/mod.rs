@@ -1,6 +1,10 @@
 pub mod local_llm;
 pub mod embeddings;
+pub mod ann;
+pub mod sentence_encoder;
 pub mod llm_inference;
+pub mod routing;
+pub mod usage;
 
 // Re-export for easy access
 pub use local_llm::*;
@@ -0,0 +1,370 @@
+// Per-capability model routing and A/B comparison
+// Each Capability can be served by the built-in pattern engine, a local GGUF
+// model through the llama.cpp CLI, or a remote OpenAI-compatible endpoint.
+// Compare mode runs two backends on one request and records which answer the
+// user accepted, giving per-backend quality numbers for the dashboard.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::llm_inference::{InferenceRequest, LLMResponse, LightweightLLM};
+use super::local_llm::Capability;
+use super::usage::TokenUsage;
+
+const MAX_COMPARISONS: usize = 500;
+/// How long a local model may run before it is stopped
+const GGUF_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ModelBackend {
+    #[default]
+    PatternEngine,
+    LocalGguf {
+        model_path: String,
+        /// llama.cpp CLI binary, `llama-cli` when not set
+        #[serde(default)]
+        binary: Option<String>,
+    },
+    Remote {
+        /// Base URL of an OpenAI-compatible API, e.g. http://localhost:11434/v1
+        endpoint: String,
+        model: String,
+        /// Key in the secrets store holding the API key
+        #[serde(default)]
+        api_key_secret: Option<String>,
+        /// Pricing used to estimate cost, in USD per million tokens
+        #[serde(default)]
+        input_cost_per_million: Option<f64>,
+        #[serde(default)]
+        output_cost_per_million: Option<f64>,
+    },
+}
+
+impl ModelBackend {
+    /// Stable name used in comparisons and statistics
+    pub fn label(&self) -> String {
+        match self {
+            ModelBackend::PatternEngine => "pattern-engine".to_string(),
+            ModelBackend::LocalGguf { model_path, .. } => format!(
+                "gguf:{}",
+                std::path::Path::new(model_path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| model_path.clone())
+            ),
+            ModelBackend::Remote { endpoint, model, .. } => {
+                let host = endpoint.split("://").nth(1).unwrap_or(endpoint).split('/').next().unwrap_or(endpoint);
+                format!("remote:{}@{}", model, host)
+            }
+        }
+    }
+
+    /// Estimated cost in USD of a request; only remote providers with pricing cost anything
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        match self {
+            ModelBackend::Remote { input_cost_per_million, output_cost_per_million, .. } => {
+                usage.prompt_tokens as f64 * input_cost_per_million.unwrap_or(0.0) / 1_000_000.0
+                    + usage.completion_tokens as f64 * output_cost_per_million.unwrap_or(0.0) / 1_000_000.0
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+fn instruction(capability: &Capability) -> &'static str {
+    match capability {
+        Capability::NaturalLanguageToCommand => "Translate the request into a single shell command. Reply with the command only, no explanation or code fences.",
+        Capability::CommandSuggestion => "Suggest up to five relevant shell commands as a comma-separated list, nothing else.",
+        Capability::ErrorAnalysis => "Explain the likely cause of this terminal error and how to fix it in a few sentences.",
+        Capability::CodeGeneration => "Write the requested code. Reply with code only.",
+        Capability::OutputAnalysis => "Summarize this command output in a few sentences.",
+        Capability::SystemDiagnostics => "Diagnose the system issue described and suggest commands to investigate it.",
+        Capability::FileSearch => "Reply with a single shell command that finds the requested files.",
+        Capability::LogAnalysis => "Summarize the important events and errors in these logs.",
+        Capability::CommandExplanation => "Say in one short line what this shell command will do and what it changes. Placeholders like <path> stand for the user's arguments. Reply with the line only.",
+    }
+}
+
+/// First non-empty line without code fences, for command-shaped answers
+fn clean_answer(text: &str, capability: &Capability) -> String {
+    let text = text.trim();
+    if matches!(capability, Capability::NaturalLanguageToCommand | Capability::FileSearch) {
+        return text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("```"))
+            .unwrap_or("")
+            .trim_matches('`')
+            .trim_start_matches("$ ")
+            .to_string();
+    }
+    text.to_string()
+}
+
+async fn run_gguf(model_path: &str, binary: Option<&str>, request: &InferenceRequest) -> Result<(String, TokenUsage), String> {
+    if !std::path::Path::new(model_path).is_file() {
+        return Err(format!("Model file not found: {}", model_path));
+    }
+    let mut prompt = format!("{}\n", instruction(&request.capability));
+    if let Some(context) = &request.context {
+        prompt.push_str(&format!("Context:\n{}\n", context));
+    }
+    prompt.push_str(&format!("Request: {}\nAnswer:", request.prompt));
+
+    let binary = binary.unwrap_or("llama-cli");
+    // A caller that gives up (a timed-out review, a cancelled request) stops the model with it
+    let running = tokio::process::Command::new(binary)
+        .args(["-m", model_path, "--no-display-prompt", "-no-cnv", "-n"])
+        .arg(request.max_tokens.unwrap_or(128).to_string())
+        .arg("--temp")
+        .arg(request.temperature.unwrap_or(0.3).to_string())
+        .arg("-p")
+        .arg(&prompt)
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(GGUF_TIMEOUT, running)
+        .await
+        .map_err(|_| format!("{} did not answer within {} seconds", binary, GGUF_TIMEOUT.as_secs()))?
+        .map_err(|e| format!("Cannot run {}: {}", binary, e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", binary, String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or("")));
+    }
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+    let usage = TokenUsage::estimate(&prompt, &text);
+    Ok((text, usage))
+}
+
+async fn run_remote(endpoint: &str, model: &str, api_key_secret: Option<&str>, request: &InferenceRequest) -> Result<(String, TokenUsage), String> {
+    let mut system = instruction(&request.capability).to_string();
+    if let Some(context) = &request.context {
+        system.push_str(&format!("\n\nContext:\n{}", context));
+    }
+    let body = serde_json::json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": request.prompt },
+        ],
+        "max_tokens": request.max_tokens.unwrap_or(256),
+        "temperature": request.temperature.unwrap_or(0.3),
+    });
+
+    let url = format!("{}/chat/completions", endpoint.trim_end_matches('/'));
+    let mut http = reqwest::Client::new().post(&url).timeout(std::time::Duration::from_secs(60)).json(&body);
+    if let Some(key) = api_key_secret {
+        let secrets = crate::integrations::secrets::SecretsStore::load();
+        let token = secrets.get(key).ok_or_else(|| format!("Secret '{}' is not set", key))?;
+        http = http.bearer_auth(token);
+    }
+    let response = http.send().await.map_err(|e| format!("Request to {} failed: {}", url, e))?;
+    let status = response.status();
+    let json: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from {}: {}", url, e))?;
+    if !status.is_success() {
+        let message = json["error"]["message"].as_str().unwrap_or("unknown error");
+        return Err(format!("{} returned {}: {}", url, status, message));
+    }
+    let text = json["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("No completion in response from {}", url))?;
+    let usage = match (json["usage"]["prompt_tokens"].as_u64(), json["usage"]["completion_tokens"].as_u64()) {
+        (Some(prompt_tokens), Some(completion_tokens)) => TokenUsage { prompt_tokens, completion_tokens, estimated: false },
+        _ => TokenUsage::estimate(&format!("{}{}", system, request.prompt), &text),
+    };
+    Ok((text, usage))
+}
+
+/// Run a request on a backend; the pattern engine instance is only needed for `PatternEngine`
+pub async fn run_backend(
+    backend: &ModelBackend,
+    request: InferenceRequest,
+    pattern_engine: Option<&LightweightLLM>,
+) -> Result<(LLMResponse, TokenUsage), String> {
+    let start = std::time::Instant::now();
+    let capability = request.capability.clone();
+    let (text, usage) = match backend {
+        ModelBackend::PatternEngine => {
+            let engine = pattern_engine.ok_or("Pattern engine is not loaded")?;
+            let response = engine.generate(request).await.map_err(|e| e.to_string())?;
+            return Ok((response, TokenUsage::default()));
+        }
+        ModelBackend::LocalGguf { model_path, binary } => run_gguf(model_path, binary.as_deref(), &request).await?,
+        ModelBackend::Remote { endpoint, model, api_key_secret, .. } => {
+            run_remote(endpoint, model, api_key_secret.as_deref(), &request).await?
+        }
+    };
+    let text = clean_answer(&text, &capability);
+    if text.is_empty() {
+        return Err(format!("{} returned an empty answer", backend.label()));
+    }
+    let response = LLMResponse {
+        text,
+        // External models do not report confidence; trust them like a high-confidence match
+        confidence: 0.8,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        model_used: backend.label(),
+    };
+    Ok((response, usage))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelRoutes {
+    routes: HashMap<Capability, ModelBackend>,
+}
+
+impl ModelRoutes {
+    fn routes_file() -> PathBuf {
+        crate::ai::config_directory().join("model_routes.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::routes_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::routes_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// Backend for a capability; the pattern engine unless configured otherwise
+    pub fn backend_for(&self, capability: &Capability) -> ModelBackend {
+        self.routes.get(capability).cloned().unwrap_or_default()
+    }
+
+    /// Route a capability to a backend, or back to the default with `None`
+    pub fn set_route(&mut self, capability: Capability, backend: Option<ModelBackend>) -> Result<(), String> {
+        match backend {
+            Some(ModelBackend::PatternEngine) | None => self.routes.remove(&capability),
+            Some(backend) => self.routes.insert(capability, backend),
+        };
+        self.save()
+    }
+
+    pub fn routes(&self) -> &HashMap<Capability, ModelBackend> {
+        &self.routes
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonResult {
+    pub backend: String,
+    pub text: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub id: String,
+    pub capability: Capability,
+    pub prompt: String,
+    pub results: Vec<ComparisonResult>,
+    /// Index into `results` of the answer the user went with
+    pub accepted: Option<usize>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendQuality {
+    pub backend: String,
+    pub comparisons: u32,
+    pub accepted: u32,
+    pub errors: u32,
+    pub acceptance_rate: f32,
+    pub average_latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComparisonStore {
+    comparisons: Vec<Comparison>,
+}
+
+impl ComparisonStore {
+    fn store_file() -> PathBuf {
+        crate::ai::data_directory().join("model_comparisons.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::store_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::store_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn record(&mut self, comparison: Comparison) -> Result<(), String> {
+        self.comparisons.push(comparison);
+        if self.comparisons.len() > MAX_COMPARISONS {
+            let excess = self.comparisons.len() - MAX_COMPARISONS;
+            self.comparisons.drain(..excess);
+        }
+        self.save()
+    }
+
+    pub fn accept(&mut self, comparison_id: &str, index: usize) -> Result<Comparison, String> {
+        let comparison = self
+            .comparisons
+            .iter_mut()
+            .find(|c| c.id == comparison_id)
+            .ok_or_else(|| format!("Comparison not found: {}", comparison_id))?;
+        if index >= comparison.results.len() {
+            return Err(format!("Comparison has {} results", comparison.results.len()));
+        }
+        comparison.accepted = Some(index);
+        let comparison = comparison.clone();
+        self.save()?;
+        Ok(comparison)
+    }
+
+    /// Acceptance rate, error count and latency per backend
+    pub fn quality(&self) -> Vec<BackendQuality> {
+        let mut stats: HashMap<String, (u32, u32, u32, u64)> = HashMap::new();
+        for comparison in &self.comparisons {
+            for (i, result) in comparison.results.iter().enumerate() {
+                let entry = stats.entry(result.backend.clone()).or_default();
+                entry.0 += 1;
+                if comparison.accepted == Some(i) {
+                    entry.1 += 1;
+                }
+                if result.error.is_some() {
+                    entry.2 += 1;
+                }
+                entry.3 += result.latency_ms;
+            }
+        }
+        let mut quality: Vec<BackendQuality> = stats
+            .into_iter()
+            .map(|(backend, (comparisons, accepted, errors, latency))| {
+                let decided = self
+                    .comparisons
+                    .iter()
+                    .filter(|c| c.accepted.is_some() && c.results.iter().any(|r| r.backend == backend))
+                    .count();
+                BackendQuality {
+                    backend,
+                    comparisons,
+                    accepted,
+                    errors,
+                    acceptance_rate: if decided > 0 { accepted as f32 / decided as f32 } else { 0.0 },
+                    average_latency_ms: latency / comparisons.max(1) as u64,
+                }
+            })
+            .collect();
+        quality.sort_by(|a, b| b.acceptance_rate.total_cmp(&a.acceptance_rate).then(a.backend.cmp(&b.backend)));
+        quality
+    }
+}
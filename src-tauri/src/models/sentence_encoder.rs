@@ -0,0 +1,163 @@
+// Sentence embeddings with all-MiniLM-L6-v2 running on candle (CPU)
+// Model files are downloaded from Hugging Face on first use into the AI data
+// directory, from a pinned commit of the model repository; the weights, which
+// Hugging Face serves from separate LFS storage, must also match their known
+// sha256. Builds without the `semantic-embeddings` feature get a stub whose
+// loading always fails, so callers keep the hashed vectors.
+
+use std::path::PathBuf;
+
+pub const MODEL_NAME: &str = "all-MiniLM-L6-v2";
+
+/// Files of the model with the sha256 they must have where it is pinned; the JSON files
+/// are stored in the pinned commit itself
+pub const MODEL_FILES: [(&str, Option<&str>); 3] = [
+    ("config.json", None),
+    ("tokenizer.json", None),
+    ("model.safetensors", Some("53aa51172d142c89d9012cce15ae4d6cc0ca6895895114379cacb4fab128d9db")),
+];
+/// Commit of the model repository the files are taken from, so they cannot change under the app
+#[cfg(feature = "semantic-embeddings")]
+const MODEL_REVISION: &str = "c9745ed1d9f207416be6d2e6f8de32d1f16199bf";
+#[cfg(feature = "semantic-embeddings")]
+const MODEL_REPO: &str = "https://huggingface.co/sentence-transformers/all-MiniLM-L6-v2/resolve";
+/// Longer inputs are truncated; shell commands rarely come close
+#[cfg(feature = "semantic-embeddings")]
+const MAX_TOKENS: usize = 256;
+
+/// Directory the model files are kept in
+pub fn model_directory() -> PathBuf {
+    crate::ai::models_directory().join(MODEL_NAME)
+}
+
+#[cfg(feature = "semantic-embeddings")]
+pub struct SentenceEncoder {
+    model: candle_transformers::models::bert::BertModel,
+    tokenizer: tokenizers::Tokenizer,
+    device: candle_core::Device,
+}
+
+/// Whether the file on disk has the expected sha256; anything readable passes where none is pinned
+#[cfg(feature = "semantic-embeddings")]
+async fn matches_pin(file: PathBuf, expected: Option<&'static str>) -> bool {
+    use crate::tools::checksum::{verify_checksum, HashAlgorithm};
+    tokio::task::spawn_blocking(move || match expected {
+        Some(expected) => verify_checksum(&file, Some(expected), Some(HashAlgorithm::Sha256), |_, _| {}).is_ok_and(|v| v.matches),
+        None => file.is_file(),
+    })
+    .await
+    .unwrap_or(false)
+}
+
+#[cfg(feature = "semantic-embeddings")]
+async fn download_missing(directory: &std::path::Path) -> Result<(), String> {
+    tokio::fs::create_dir_all(directory).await.map_err(|e| format!("Cannot create {}: {}", directory.display(), e))?;
+    let client = reqwest::Client::new();
+    for (file, expected) in MODEL_FILES {
+        let target = directory.join(file);
+        if target.is_file() {
+            // Files from before the pin, or altered since, are downloaded again
+            if matches_pin(target.clone(), expected).await {
+                continue;
+            }
+            println!("⚠️ {} does not match the pinned model, downloading it again", target.display());
+        }
+        let url = format!("{}/{}/{}", MODEL_REPO, MODEL_REVISION, file);
+        println!("⬇️ Downloading {}", url);
+        let response = client.get(&url).send().await.map_err(|e| format!("Download of {} failed: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("Download of {} failed: {}", url, response.status()));
+        }
+        let bytes = response.bytes().await.map_err(|e| format!("Download of {} failed: {}", url, e))?;
+        // Write beside the target first so an interrupted download is never mistaken for a model file,
+        // and only give it the real name once what landed on disk matches the pin
+        let partial = directory.join(format!("{}.partial", file));
+        tokio::fs::write(&partial, &bytes).await.map_err(|e| format!("Cannot write {}: {}", partial.display(), e))?;
+        if !matches_pin(partial.clone(), expected).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(format!("Download of {} does not match its expected sha256, not using it", url));
+        }
+        tokio::fs::rename(&partial, &target).await.map_err(|e| format!("Cannot write {}: {}", target.display(), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "semantic-embeddings")]
+impl SentenceEncoder {
+    /// Load the model, downloading it first when missing
+    pub async fn load(directory: PathBuf) -> Result<Self, String> {
+        download_missing(&directory).await?;
+        tokio::task::spawn_blocking(move || Self::load_files(&directory))
+            .await
+            .map_err(|e| format!("Embedding model loading failed: {}", e))?
+    }
+
+    fn load_files(directory: &std::path::Path) -> Result<Self, String> {
+        use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+        use tokenizers::{PaddingParams, PaddingStrategy, TruncationParams};
+
+        let device = candle_core::Device::Cpu;
+        let config: Config = std::fs::read_to_string(directory.join("config.json"))
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Invalid embedding model config: {}", e))?;
+
+        let mut tokenizer = tokenizers::Tokenizer::from_file(directory.join("tokenizer.json"))
+            .map_err(|e| format!("Invalid embedding tokenizer: {}", e))?;
+        tokenizer.with_padding(Some(PaddingParams { strategy: PaddingStrategy::BatchLongest, ..Default::default() }));
+        tokenizer
+            .with_truncation(Some(TruncationParams { max_length: MAX_TOKENS, ..Default::default() }))
+            .map_err(|e| format!("Invalid embedding tokenizer: {}", e))?;
+
+        // Safety: the weights file is only read, and is not modified while mapped
+        let weights = unsafe {
+            candle_nn::VarBuilder::from_mmaped_safetensors(&[directory.join("model.safetensors")], DTYPE, &device)
+        }
+        .map_err(|e| format!("Cannot read embedding model weights: {}", e))?;
+        let model = BertModel::load(weights, &config).map_err(|e| format!("Cannot load embedding model: {}", e))?;
+        Ok(Self { model, tokenizer, device })
+    }
+
+    /// Mean-pooled, L2-normalized sentence vectors
+    pub fn encode_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        use candle_core::{DType, Tensor};
+
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let encodings = self.tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+        let run = || -> candle_core::Result<Vec<Vec<f32>>> {
+            let ids = encodings.iter().map(|e| Tensor::new(e.get_ids(), &self.device)).collect::<candle_core::Result<Vec<_>>>()?;
+            let masks = encodings.iter().map(|e| Tensor::new(e.get_attention_mask(), &self.device)).collect::<candle_core::Result<Vec<_>>>()?;
+            let input_ids = Tensor::stack(&ids, 0)?;
+            let attention_mask = Tensor::stack(&masks, 0)?;
+            let token_type_ids = input_ids.zeros_like()?;
+            let hidden = self.model.forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+            // Average the token vectors that are not padding
+            let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+            let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+            let counts = mask.sum(1)?.clamp(1e-9, f64::MAX)?;
+            let pooled = summed.broadcast_div(&counts)?;
+            let norms = pooled.sqr()?.sum_keepdim(1)?.sqrt()?.clamp(1e-12, f64::MAX)?;
+            pooled.broadcast_div(&norms)?.to_vec2::<f32>()
+        };
+        run().map_err(|e| format!("Embedding inference failed: {}", e))
+    }
+}
+
+#[cfg(not(feature = "semantic-embeddings"))]
+pub struct SentenceEncoder;
+
+#[cfg(not(feature = "semantic-embeddings"))]
+impl SentenceEncoder {
+    pub async fn load(_directory: PathBuf) -> Result<Self, String> {
+        Err("built without the semantic-embeddings feature".to_string())
+    }
+
+    pub fn encode_batch(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Err("built without the semantic-embeddings feature".to_string())
+    }
+}
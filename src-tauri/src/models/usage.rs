@@ -0,0 +1,180 @@
+// Token and cost accounting for AI usage
+// Every backend invocation is counted per day, capability and provider.
+// Remote providers report tokens themselves; local models are estimated from
+// text length. Optional daily budgets stop paid backends once reached.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use super::local_llm::Capability;
+
+/// Days of history kept in the ledger
+const RETENTION_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// False when counted by the provider, true when estimated from text length
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    /// Rough estimate of ~4 characters per token
+    pub fn estimate(prompt: &str, completion: &str) -> Self {
+        let tokens = |text: &str| (text.chars().count() as u64 + 3) / 4;
+        TokenUsage { prompt_tokens: tokens(prompt), completion_tokens: tokens(completion), estimated: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub capability: Capability,
+    pub provider: String,
+    pub invocations: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageBudget {
+    pub daily_tokens: Option<u64>,
+    pub daily_cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageStats {
+    pub today: Vec<UsageEntry>,
+    pub today_tokens: u64,
+    pub today_cost_usd: f64,
+    /// Totals for the requested period, per capability and provider
+    pub period: Vec<UsageEntry>,
+    pub period_days: u32,
+    pub daily_totals: Vec<(String, u64, f64)>,
+    pub budget: UsageBudget,
+    pub budget_exceeded: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageLedger {
+    /// date (YYYY-MM-DD) -> entries
+    days: BTreeMap<String, Vec<UsageEntry>>,
+    #[serde(default)]
+    budget: UsageBudget,
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn merge(entries: &mut Vec<UsageEntry>, entry: &UsageEntry) {
+    match entries.iter_mut().find(|e| e.capability == entry.capability && e.provider == entry.provider) {
+        Some(existing) => {
+            existing.invocations += entry.invocations;
+            existing.prompt_tokens += entry.prompt_tokens;
+            existing.completion_tokens += entry.completion_tokens;
+            existing.estimated_cost_usd += entry.estimated_cost_usd;
+        }
+        None => entries.push(entry.clone()),
+    }
+}
+
+fn totals(entries: &[UsageEntry]) -> (u64, f64) {
+    entries.iter().fold((0, 0.0), |(tokens, cost), e| {
+        (tokens + e.prompt_tokens + e.completion_tokens, cost + e.estimated_cost_usd)
+    })
+}
+
+impl UsageLedger {
+    fn ledger_file() -> PathBuf {
+        crate::ai::data_directory().join("ai_usage.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::ledger_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::ledger_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn record(&mut self, capability: &Capability, provider: &str, usage: TokenUsage, cost_usd: f64) {
+        let entry = UsageEntry {
+            capability: capability.clone(),
+            provider: provider.to_string(),
+            invocations: 1,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            estimated_cost_usd: cost_usd,
+        };
+        merge(self.days.entry(today()).or_default(), &entry);
+
+        let cutoff = (chrono::Local::now() - chrono::Duration::days(RETENTION_DAYS)).format("%Y-%m-%d").to_string();
+        self.days.retain(|day, _| *day >= cutoff);
+        if let Err(e) = self.save() {
+            println!("⚠️ Failed to save AI usage: {}", e);
+        }
+    }
+
+    /// Error when today's usage has reached a configured budget
+    pub fn check_budget(&self) -> Result<(), String> {
+        let (tokens, cost) = self.days.get(&today()).map(|e| totals(e)).unwrap_or_default();
+        if let Some(limit) = self.budget.daily_tokens {
+            if tokens >= limit {
+                return Err(format!("Daily AI token budget reached ({} of {} tokens)", tokens, limit));
+            }
+        }
+        if let Some(limit) = self.budget.daily_cost_usd {
+            if cost >= limit {
+                return Err(format!("Daily AI cost budget reached (${:.2} of ${:.2})", cost, limit));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_budget(&mut self, budget: UsageBudget) -> Result<(), String> {
+        self.budget = budget;
+        self.save()
+    }
+
+    pub fn stats(&self, period_days: u32) -> UsageStats {
+        let today_key = today();
+        let today_entries = self.days.get(&today_key).cloned().unwrap_or_default();
+        let (today_tokens, today_cost_usd) = totals(&today_entries);
+
+        let period_days = period_days.max(1);
+        let cutoff = (chrono::Local::now() - chrono::Duration::days(period_days as i64 - 1)).format("%Y-%m-%d").to_string();
+        let mut period = Vec::new();
+        let mut daily_totals = Vec::new();
+        for (day, entries) in self.days.range(cutoff..) {
+            for entry in entries {
+                merge(&mut period, entry);
+            }
+            let (tokens, cost) = totals(entries);
+            daily_totals.push((day.clone(), tokens, cost));
+        }
+        period.sort_by_key(|usage| std::cmp::Reverse(usage.invocations));
+
+        UsageStats {
+            today: today_entries,
+            today_tokens,
+            today_cost_usd,
+            period,
+            period_days,
+            daily_totals,
+            budget: self.budget.clone(),
+            budget_exceeded: self.check_budget().is_err(),
+        }
+    }
+}
@@ -0,0 +1,411 @@
+// Dependency vulnerability and outdated-package checks
+// Runs the ecosystem audit tools (npm audit, cargo audit, pip-audit) and
+// normalizes their JSON output into structured findings
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::ai::enhanced_context::ProactiveSuggestion;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    fn parse(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "critical" => Severity::Critical,
+            "high" => Severity::High,
+            "moderate" | "medium" => Severity::Moderate,
+            _ => Severity::Low,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditFinding {
+    pub ecosystem: String,
+    pub package: String,
+    pub installed_version: Option<String>,
+    pub severity: Severity,
+    pub title: String,
+    pub advisory_id: Option<String>,
+    pub fixed_in: Option<String>,
+    pub fix_command: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutdatedPackage {
+    pub ecosystem: String,
+    pub package: String,
+    pub current: String,
+    pub latest: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub project_path: String,
+    pub ecosystems: Vec<String>,
+    pub findings: Vec<AuditFinding>,
+    pub outdated: Vec<OutdatedPackage>,
+    pub errors: Vec<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl AuditReport {
+    pub fn high_severity_findings(&self) -> Vec<&AuditFinding> {
+        self.findings.iter()
+            .filter(|finding| finding.severity >= Severity::High)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AuditSchedule {
+    interval: Duration,
+    last_run: Option<DateTime<Utc>>,
+}
+
+/// Keeps the latest audit report per project and the periodic check schedule
+pub struct DependencyAuditor {
+    reports: HashMap<String, AuditReport>,
+    schedules: HashMap<String, AuditSchedule>,
+}
+
+impl Default for DependencyAuditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DependencyAuditor {
+    pub fn new() -> Self {
+        Self {
+            reports: HashMap::new(),
+            schedules: HashMap::new(),
+        }
+    }
+
+    /// Register a project for periodic checks (default: once a day)
+    pub fn schedule(&mut self, project_path: &str, interval_hours: Option<u64>) {
+        let interval = Duration::hours(interval_hours.unwrap_or(24).max(1) as i64);
+        let last_run = self.reports.get(project_path).map(|report| report.checked_at);
+        self.schedules.insert(project_path.to_string(), AuditSchedule { interval, last_run });
+    }
+
+    /// Projects whose scheduled check is due
+    pub fn due_projects(&self) -> Vec<String> {
+        let now = Utc::now();
+        self.schedules.iter()
+            .filter(|(_, schedule)| match schedule.last_run {
+                Some(last_run) => now - last_run >= schedule.interval,
+                None => true,
+            })
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    pub fn store_report(&mut self, report: AuditReport) {
+        if let Some(schedule) = self.schedules.get_mut(&report.project_path) {
+            schedule.last_run = Some(report.checked_at);
+        }
+        self.reports.insert(report.project_path.clone(), report);
+    }
+
+    pub fn get_report(&self, project_path: &str) -> Option<&AuditReport> {
+        self.reports.get(project_path)
+    }
+
+    /// Surface high-severity findings for a project as proactive suggestions
+    pub fn proactive_suggestions(&self, project_path: &str) -> Vec<ProactiveSuggestion> {
+        let report = match self.reports.get(project_path) {
+            Some(report) => report,
+            None => return vec![],
+        };
+
+        let high = report.high_severity_findings();
+        if high.is_empty() {
+            return vec![];
+        }
+
+        let critical = high.iter().any(|finding| finding.severity == Severity::Critical);
+        let mut commands: Vec<String> = Vec::new();
+        for finding in &high {
+            if let Some(ref command) = finding.fix_command {
+                if !commands.contains(command) {
+                    commands.push(command.clone());
+                }
+            }
+        }
+        commands.truncate(5);
+
+        vec![ProactiveSuggestion {
+            id: None,
+            suggestion_type: "security".to_string(),
+            priority: if critical { 0.95 } else { 0.85 },
+            description: format!(
+                "{} high-severity vulnerabilit{} in dependencies ({})",
+                high.len(),
+                if high.len() == 1 { "y" } else { "ies" },
+                high.iter().take(3).map(|f| f.package.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+            commands,
+            trigger_condition: "dependency_audit_high_severity".to_string(),
+        }]
+    }
+}
+
+/// Run every applicable audit tool for the project and merge the results
+pub async fn run_audit(project_path: &str) -> AuditReport {
+    let path = Path::new(project_path);
+    let mut report = AuditReport {
+        project_path: project_path.to_string(),
+        ecosystems: Vec::new(),
+        findings: Vec::new(),
+        outdated: Vec::new(),
+        errors: Vec::new(),
+        checked_at: Utc::now(),
+    };
+
+    if path.join("package.json").exists() {
+        report.ecosystems.push("npm".to_string());
+        match run_tool(project_path, "npm", &["audit", "--json"]).await {
+            Ok(output) => report.findings.extend(parse_npm_audit(&output)),
+            Err(e) => report.errors.push(e),
+        }
+        if let Ok(output) = run_tool(project_path, "npm", &["outdated", "--json"]).await {
+            report.outdated.extend(parse_npm_outdated(&output));
+        }
+    }
+
+    if path.join("Cargo.toml").exists() {
+        report.ecosystems.push("cargo".to_string());
+        if path.join("Cargo.lock").exists() {
+            match run_tool(project_path, "cargo", &["audit", "--json"]).await {
+                Ok(output) => report.findings.extend(parse_cargo_audit(&output)),
+                Err(_) => report.errors.push("cargo-audit is not installed (cargo install cargo-audit)".to_string()),
+            }
+        } else {
+            report.errors.push("Cargo.lock not found; run 'cargo generate-lockfile' before auditing".to_string());
+        }
+    }
+
+    if path.join("requirements.txt").exists() || path.join("pyproject.toml").exists() {
+        report.ecosystems.push("pip".to_string());
+        let args: Vec<&str> = if path.join("requirements.txt").exists() {
+            vec!["-f", "json", "-r", "requirements.txt"]
+        } else {
+            vec!["-f", "json"]
+        };
+        match run_tool(project_path, "pip-audit", &args).await {
+            Ok(output) => report.findings.extend(parse_pip_audit(&output)),
+            Err(_) => report.errors.push("pip-audit is not installed (pip install pip-audit)".to_string()),
+        }
+        if let Ok(output) = run_tool(project_path, "pip", &["list", "--outdated", "--format=json"]).await {
+            report.outdated.extend(parse_pip_outdated(&output));
+        }
+    }
+
+    report.findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    report
+}
+
+/// Audit tools exit non-zero when vulnerabilities are found, so only a
+/// failure to spawn or an empty stdout counts as an error
+async fn run_tool(working_dir: &str, program: &str, args: &[&str]) -> Result<String, String> {
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(120),
+        tokio::process::Command::new(program)
+            .args(args)
+            .current_dir(working_dir)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("{} {} timed out", program, args.join(" ")))?
+    .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if stdout.trim().is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} produced no output: {}", program, stderr.trim()));
+    }
+    Ok(stdout)
+}
+
+fn parse_npm_audit(output: &str) -> Vec<AuditFinding> {
+    let json: Value = match serde_json::from_str(output) {
+        Ok(json) => json,
+        Err(_) => return vec![],
+    };
+
+    let mut findings = Vec::new();
+    if let Some(vulnerabilities) = json.get("vulnerabilities").and_then(|v| v.as_object()) {
+        for (name, vuln) in vulnerabilities {
+            let severity = Severity::parse(vuln.get("severity").and_then(|s| s.as_str()).unwrap_or("low"));
+
+            // "via" holds either advisory objects or names of vulnerable dependencies
+            let advisory = vuln.get("via")
+                .and_then(|via| via.as_array())
+                .and_then(|via| via.iter().find(|entry| entry.is_object()));
+            let title = advisory
+                .and_then(|a| a.get("title"))
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| format!("Vulnerable dependency chain via {}", name));
+            let advisory_id = advisory
+                .and_then(|a| a.get("url"))
+                .and_then(|u| u.as_str())
+                .map(|u| u.to_string());
+
+            let (fixed_in, fix_command) = match vuln.get("fixAvailable") {
+                Some(Value::Bool(true)) => (None, Some("npm audit fix".to_string())),
+                Some(Value::Object(fix)) => {
+                    let fix_name = fix.get("name").and_then(|n| n.as_str()).unwrap_or(name);
+                    let fix_version = fix.get("version").and_then(|v| v.as_str()).unwrap_or("latest");
+                    (Some(fix_version.to_string()), Some(format!("npm install {}@{}", fix_name, fix_version)))
+                }
+                _ => (None, None),
+            };
+
+            findings.push(AuditFinding {
+                ecosystem: "npm".to_string(),
+                package: name.clone(),
+                installed_version: vuln.get("range").and_then(|r| r.as_str()).map(|r| r.to_string()),
+                severity,
+                title,
+                advisory_id,
+                fixed_in,
+                fix_command,
+            });
+        }
+    }
+    findings
+}
+
+fn parse_npm_outdated(output: &str) -> Vec<OutdatedPackage> {
+    let json: Value = match serde_json::from_str(output) {
+        Ok(json) => json,
+        Err(_) => return vec![],
+    };
+
+    json.as_object()
+        .map(|packages| {
+            packages.iter()
+                .map(|(name, info)| OutdatedPackage {
+                    ecosystem: "npm".to_string(),
+                    package: name.clone(),
+                    current: info.get("current").and_then(|v| v.as_str()).unwrap_or("missing").to_string(),
+                    latest: info.get("latest").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_cargo_audit(output: &str) -> Vec<AuditFinding> {
+    let json: Value = match serde_json::from_str(output) {
+        Ok(json) => json,
+        Err(_) => return vec![],
+    };
+
+    let mut findings = Vec::new();
+    let list = json.pointer("/vulnerabilities/list").and_then(|l| l.as_array());
+    for entry in list.into_iter().flatten() {
+        let advisory = entry.get("advisory");
+        let name = entry.pointer("/package/name").and_then(|n| n.as_str()).unwrap_or("unknown");
+        let patched = entry.pointer("/versions/patched")
+            .and_then(|p| p.as_array())
+            .and_then(|p| p.first())
+            .and_then(|p| p.as_str())
+            .map(|p| p.to_string());
+
+        // RustSec advisories carry no severity label; informational ones
+        // (unmaintained, unsound) are low, real vulnerabilities are high
+        let severity = match advisory.and_then(|a| a.get("informational")) {
+            Some(Value::String(_)) => Severity::Low,
+            _ => Severity::High,
+        };
+
+        findings.push(AuditFinding {
+            ecosystem: "cargo".to_string(),
+            package: name.to_string(),
+            installed_version: entry.pointer("/package/version").and_then(|v| v.as_str()).map(|v| v.to_string()),
+            severity,
+            title: advisory.and_then(|a| a.get("title")).and_then(|t| t.as_str()).unwrap_or("RustSec advisory").to_string(),
+            advisory_id: advisory.and_then(|a| a.get("id")).and_then(|i| i.as_str()).map(|i| i.to_string()),
+            fix_command: patched.as_ref().map(|_| format!("cargo update -p {}", name)),
+            fixed_in: patched,
+        });
+    }
+    findings
+}
+
+fn parse_pip_audit(output: &str) -> Vec<AuditFinding> {
+    let json: Value = match serde_json::from_str(output) {
+        Ok(json) => json,
+        Err(_) => return vec![],
+    };
+
+    // Newer pip-audit wraps results in {"dependencies": [...]}, older emits the array
+    let dependencies = json.get("dependencies").and_then(|d| d.as_array())
+        .or_else(|| json.as_array());
+
+    let mut findings = Vec::new();
+    for dependency in dependencies.into_iter().flatten() {
+        let name = dependency.get("name").and_then(|n| n.as_str()).unwrap_or("unknown");
+        let version = dependency.get("version").and_then(|v| v.as_str()).map(|v| v.to_string());
+        let vulns = dependency.get("vulns").and_then(|v| v.as_array());
+
+        for vuln in vulns.into_iter().flatten() {
+            let fixed_in = vuln.get("fix_versions")
+                .and_then(|f| f.as_array())
+                .and_then(|f| f.first())
+                .and_then(|f| f.as_str())
+                .map(|f| f.to_string());
+            let title = vuln.get("description")
+                .and_then(|d| d.as_str())
+                .map(|d| d.lines().next().unwrap_or(d).chars().take(160).collect::<String>())
+                .unwrap_or_else(|| "Known vulnerability".to_string());
+
+            findings.push(AuditFinding {
+                ecosystem: "pip".to_string(),
+                package: name.to_string(),
+                installed_version: version.clone(),
+                // pip-audit does not report severity; treat every hit as high
+                severity: Severity::High,
+                title,
+                advisory_id: vuln.get("id").and_then(|i| i.as_str()).map(|i| i.to_string()),
+                fix_command: fixed_in.as_ref().map(|v| format!("pip install --upgrade '{}>={}'", name, v)),
+                fixed_in,
+            });
+        }
+    }
+    findings
+}
+
+fn parse_pip_outdated(output: &str) -> Vec<OutdatedPackage> {
+    let json: Value = match serde_json::from_str(output) {
+        Ok(json) => json,
+        Err(_) => return vec![],
+    };
+
+    json.as_array()
+        .map(|packages| {
+            packages.iter()
+                .map(|info| OutdatedPackage {
+                    ecosystem: "pip".to_string(),
+                    package: info.get("name").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+                    current: info.get("version").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+                    latest: info.get("latest_version").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
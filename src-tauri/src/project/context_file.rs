@@ -0,0 +1,317 @@
+// Per-project AI context file
+// Teams can commit a `.ph7console.toml` (or a `PH7.md`) at the project root to
+// declare preferred commands, forbidden commands, environment notes and
+// phrase -> command hints. The file is only honoured in trusted directories.
+//
+//   [commands]
+//   preferred = { test = "cargo nextest run", lint = "cargo clippy --all-targets" }
+//   forbidden = ["terraform apply", "kubectl delete"]
+//
+//   [environment]
+//   notes = ["Run `direnv allow` after cloning"]
+//
+//   [hints]
+//   "deploy staging" = "make deploy ENV=staging"
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::ai::trust::TrustStore;
+use crate::terminal::shell_quote;
+
+const TOML_FILE: &str = ".ph7console.toml";
+const MARKDOWN_FILE: &str = "PH7.md";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectContext {
+    pub root: String,
+    pub source_file: String,
+    pub preferred_commands: BTreeMap<String, String>,
+    pub forbidden_commands: Vec<String>,
+    pub environment_notes: Vec<String>,
+    pub hints: BTreeMap<String, String>,
+}
+
+/// What was found for a directory, including files ignored for lack of trust
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectContextStatus {
+    pub file: Option<String>,
+    pub trusted: bool,
+    pub context: Option<ProjectContext>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlFile {
+    #[serde(default)]
+    commands: TomlCommands,
+    #[serde(default)]
+    environment: TomlEnvironment,
+    #[serde(default)]
+    hints: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlCommands {
+    #[serde(default)]
+    preferred: BTreeMap<String, String>,
+    #[serde(default)]
+    forbidden: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlEnvironment {
+    #[serde(default)]
+    notes: Vec<String>,
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// The words of each simple command in a command line: it is split on `&&`, `||`, `;`, `|`,
+/// `&`, newlines and subshell parentheses outside quotes
+fn simple_commands(command: &str) -> Vec<Vec<String>> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (None | Some('"'), '\\') => {
+                current.push(c);
+                current.extend(chars.next());
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (None, '&' | '|' | ';' | '\n' | '(' | ')') => pieces.push(std::mem::take(&mut current)),
+            (None, c) => current.push(c),
+        }
+    }
+    pieces.push(current);
+    pieces
+        .iter()
+        .map(|piece| shell_quote::split(piece).unwrap_or_else(|_| piece.split_whitespace().map(str::to_string).collect()))
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+/// A simple command from its program on, past `sudo`, `env`, `command`, `exec`, `nohup` and
+/// `time` with their options, and past `NAME=value` assignments
+fn program_words(words: &[String]) -> &[String] {
+    let mut i = 0;
+    while let Some(word) = words.get(i) {
+        // Options of these that take a value, which is skipped with them
+        let valued: &[&str] = match word.as_str() {
+            "sudo" | "doas" => &["-u", "-g", "-C", "-D", "-h", "-p", "-r", "-t", "-U"],
+            "env" => &["-u", "-C", "-S"],
+            "command" | "exec" | "nohup" | "time" | "{" => &[],
+            word if is_assignment(word) => {
+                i += 1;
+                continue;
+            }
+            _ => break,
+        };
+        i += 1;
+        while let Some(option) = words.get(i).filter(|w| w.starts_with('-')) {
+            i += if valued.contains(&option.as_str()) { 2 } else { 1 };
+        }
+    }
+    &words[i.min(words.len())..]
+}
+
+impl ProjectContext {
+    /// Whether a command is forbidden here; returns the matching rule. Every command of a chain
+    /// or pipeline is checked, from its program on and as written
+    pub fn forbidden_rule(&self, command: &str) -> Option<&str> {
+        let commands = simple_commands(command);
+        let starts_with = |words: &[String], rule: &[&str]| words.len() >= rule.len() && words.iter().zip(rule).all(|(w, r)| w == r);
+        self.forbidden_commands
+            .iter()
+            .find(|rule| {
+                let rule: Vec<&str> = rule.split_whitespace().collect();
+                !rule.is_empty() && commands.iter().any(|words| starts_with(words, &rule) || starts_with(program_words(words), &rule))
+            })
+            .map(|rule| rule.as_str())
+    }
+
+    /// Command declared for a natural language phrase (or a preferred command name)
+    pub fn hint_for(&self, request: &str) -> Option<&str> {
+        let request = request.trim().to_lowercase();
+        self.hints
+            .iter()
+            .find(|(phrase, _)| request == phrase.to_lowercase() || request.contains(&phrase.to_lowercase()))
+            .or_else(|| {
+                self.preferred_commands.iter().find(|(name, _)| {
+                    let name = name.to_lowercase();
+                    request == name || request == format!("run {}", name) || request == format!("run the {}", name)
+                })
+            })
+            .map(|(_, command)| command.as_str())
+    }
+
+    /// Lines for the AI smart context
+    pub fn context_summary(&self) -> String {
+        let mut lines = vec![format!("Project Notes ({}):", self.source_file)];
+        for (name, command) in &self.preferred_commands {
+            lines.push(format!("- preferred {}: {}", name, command));
+        }
+        for command in &self.forbidden_commands {
+            lines.push(format!("- never run: {}", command));
+        }
+        for note in &self.environment_notes {
+            lines.push(format!("- {}", note));
+        }
+        lines.join("\n")
+    }
+}
+
+fn parse_toml(text: &str) -> Result<TomlFile, String> {
+    toml::from_str(text).map_err(|e| format!("Invalid {}: {}", TOML_FILE, e.message()))
+}
+
+/// `PH7.md`: bullets under "Preferred commands", "Forbidden commands", "Environment" and "Hints" headings.
+/// Bullets are `- name: command` (or `- "phrase" → command` for hints); backticks are stripped.
+fn parse_markdown(text: &str, context: &mut ProjectContext) {
+    #[derive(PartialEq)]
+    enum Section {
+        Other,
+        Preferred,
+        Forbidden,
+        Environment,
+        Hints,
+    }
+    let mut section = Section::Other;
+    let clean = |s: &str| s.trim().trim_matches('`').trim_matches('"').trim().to_string();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim().to_lowercase();
+            section = if heading.contains("prefer") {
+                Section::Preferred
+            } else if heading.contains("forbid") || heading.contains("never") || heading.contains("don't") {
+                Section::Forbidden
+            } else if heading.contains("environment") || heading.contains("setup") {
+                Section::Environment
+            } else if heading.contains("hint") || heading.contains("phrase") {
+                Section::Hints
+            } else {
+                Section::Other
+            };
+            continue;
+        }
+        let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) else {
+            continue;
+        };
+        let split = item.split_once("→").or_else(|| item.split_once("->")).or_else(|| item.split_once(": "));
+        match (&section, split) {
+            (Section::Preferred, Some((name, command))) => {
+                context.preferred_commands.insert(clean(name), clean(command));
+            }
+            (Section::Hints, Some((phrase, command))) => {
+                context.hints.insert(clean(phrase), clean(command));
+            }
+            (Section::Forbidden, _) => context.forbidden_commands.push(clean(item)),
+            (Section::Environment, _) => context.environment_notes.push(item.trim().to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// Nearest context file at or above a directory
+pub fn find_context_file(working_dir: &str) -> Option<PathBuf> {
+    let home = dirs::home_dir();
+    for dir in Path::new(working_dir).ancestors() {
+        for name in [TOML_FILE, MARKDOWN_FILE] {
+            let candidate = dir.join(name);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+        // Stop at the repository root or the home directory
+        if dir.join(".git").exists() || Some(dir) == home.as_deref() {
+            break;
+        }
+    }
+    None
+}
+
+fn load_file(path: &Path) -> Result<ProjectContext, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let mut context = ProjectContext {
+        root: path.parent().map(|p| p.display().to_string()).unwrap_or_default(),
+        source_file: path.display().to_string(),
+        ..Default::default()
+    };
+    if path.file_name().map(|n| n == TOML_FILE).unwrap_or(false) {
+        let file = parse_toml(&text)?;
+        context.preferred_commands = file.commands.preferred;
+        context.forbidden_commands = file.commands.forbidden;
+        context.environment_notes = file.environment.notes;
+        context.hints = file.hints;
+    } else {
+        parse_markdown(&text, &mut context);
+    }
+    Ok(context)
+}
+
+/// Load the context file for a directory if one exists and its project is trusted
+pub fn load_for(working_dir: &str) -> ProjectContextStatus {
+    let Some(file) = find_context_file(working_dir) else {
+        return ProjectContextStatus { file: None, trusted: false, context: None };
+    };
+    let root = file.parent().unwrap_or(Path::new(working_dir));
+    let trusted = TrustStore::load().is_trusted(root);
+    let context = if trusted {
+        match load_file(&file) {
+            Ok(context) => Some(context),
+            Err(e) => {
+                println!("⚠️ {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    ProjectContextStatus { file: Some(file.display().to_string()), trusted, context }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forbidding(rules: &[&str]) -> ProjectContext {
+        ProjectContext { forbidden_commands: rules.iter().map(|r| r.to_string()).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn forbidden_commands_are_found_inside_chains() {
+        let context = forbidding(&["terraform apply"]);
+        assert_eq!(context.forbidden_rule("terraform   apply -auto-approve"), Some("terraform apply"));
+        assert_eq!(context.forbidden_rule("make build && terraform apply"), Some("terraform apply"));
+        assert_eq!(context.forbidden_rule("cd infra; terraform apply | tee log"), Some("terraform apply"));
+        assert_eq!(context.forbidden_rule("(cd infra && terraform apply)"), Some("terraform apply"));
+        assert_eq!(context.forbidden_rule("echo 'terraform apply && x'"), None);
+        assert_eq!(context.forbidden_rule("terraform plan"), None);
+    }
+
+    #[test]
+    fn wrappers_and_assignments_are_skipped() {
+        let context = forbidding(&["kubectl delete", "sudo reboot"]);
+        assert_eq!(context.forbidden_rule("sudo -u admin kubectl delete pod x"), Some("kubectl delete"));
+        assert_eq!(context.forbidden_rule("KUBECONFIG=prod env -i kubectl delete ns"), Some("kubectl delete"));
+        assert_eq!(context.forbidden_rule("nohup kubectl \"delete\" pod"), Some("kubectl delete"));
+        assert_eq!(context.forbidden_rule("sudo reboot now"), Some("sudo reboot"));
+        assert_eq!(context.forbidden_rule("kubectl get pods"), None);
+    }
+}
@@ -0,0 +1,308 @@
+// Coverage report ingestion
+// After a test run, a coverage report it wrote (lcov, Cobertura XML or the JSON
+// summary of cargo-llvm-cov) is parsed into line coverage per file and attached
+// to the execution. A baseline per project is kept so a drop in coverage can be
+// pointed out as a proactive suggestion.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ai::enhanced_context::ProactiveSuggestion;
+
+/// Where common tools write their reports, relative to the project root
+const REPORT_LOCATIONS: [&str; 8] = [
+    "lcov.info",
+    "coverage/lcov.info",
+    "target/llvm-cov/lcov.info",
+    "coverage.json",
+    "target/llvm-cov/coverage.json",
+    "coverage.xml",
+    "coverage/cobertura-coverage.xml",
+    "cobertura.xml",
+];
+
+/// Percentage points coverage may fall below the baseline before it is reported
+const DROP_TOLERANCE: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageFormat {
+    Lcov,
+    Cobertura,
+    LlvmCovJson,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileCoverage {
+    pub file: String,
+    pub lines_found: u64,
+    pub lines_hit: u64,
+}
+
+impl FileCoverage {
+    pub fn percent(&self) -> f64 {
+        percent(self.lines_hit, self.lines_found)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageSummary {
+    pub format: CoverageFormat,
+    pub report: String,
+    pub lines_found: u64,
+    pub lines_hit: u64,
+    pub percent: f64,
+    pub files: Vec<FileCoverage>,
+    pub generated_at: DateTime<Utc>,
+}
+
+fn percent(hit: u64, found: u64) -> f64 {
+    if found == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / found as f64
+    }
+}
+
+fn summary(format: CoverageFormat, report: &Path, files: Vec<FileCoverage>) -> CoverageSummary {
+    let lines_found = files.iter().map(|f| f.lines_found).sum();
+    let lines_hit = files.iter().map(|f| f.lines_hit).sum();
+    CoverageSummary {
+        format,
+        report: report.to_string_lossy().to_string(),
+        lines_found,
+        lines_hit,
+        percent: percent(lines_hit, lines_found),
+        files,
+        generated_at: Utc::now(),
+    }
+}
+
+pub fn parse_lcov(text: &str) -> Vec<FileCoverage> {
+    let mut files = Vec::new();
+    let mut current: Option<FileCoverage> = None;
+    // LF/LH are optional; fall back to counting DA records
+    let (mut da_found, mut da_hit) = (0, 0);
+    for line in text.lines().map(str::trim) {
+        if let Some(file) = line.strip_prefix("SF:") {
+            current = Some(FileCoverage { file: file.to_string(), lines_found: 0, lines_hit: 0 });
+            (da_found, da_hit) = (0, 0);
+        } else if let Some(record) = line.strip_prefix("DA:") {
+            da_found += 1;
+            if record.split(',').nth(1).and_then(|hits| hits.parse::<u64>().ok()).is_some_and(|hits| hits > 0) {
+                da_hit += 1;
+            }
+        } else if let (Some(found), Some(file)) = (line.strip_prefix("LF:"), current.as_mut()) {
+            file.lines_found = found.parse().unwrap_or(0);
+        } else if let (Some(hit), Some(file)) = (line.strip_prefix("LH:"), current.as_mut()) {
+            file.lines_hit = hit.parse().unwrap_or(0);
+        } else if line == "end_of_record" {
+            if let Some(mut file) = current.take() {
+                if file.lines_found == 0 {
+                    (file.lines_found, file.lines_hit) = (da_found, da_hit);
+                }
+                files.push(file);
+            }
+        }
+    }
+    files
+}
+
+/// Line hits per `<class filename="...">`; classes of the same file are merged
+pub fn parse_cobertura(text: &str) -> Vec<FileCoverage> {
+    let class = Regex::new(r#"<class\b[^>]*\bfilename="([^"]+)"[^>]*>([\s\S]*?)</class>"#).unwrap();
+    let line = Regex::new(r#"<line\b[^>]*\bhits="(\d+)""#).unwrap();
+    let mut by_file: BTreeMap<String, FileCoverage> = BTreeMap::new();
+    for captures in class.captures_iter(text) {
+        let file = by_file
+            .entry(captures[1].to_string())
+            .or_insert_with(|| FileCoverage { file: captures[1].to_string(), lines_found: 0, lines_hit: 0 });
+        for hits in line.captures_iter(&captures[2]) {
+            file.lines_found += 1;
+            if hits[1] != *"0" {
+                file.lines_hit += 1;
+            }
+        }
+    }
+    by_file.into_values().collect()
+}
+
+/// `cargo llvm-cov --json` export: data[].files[].summary.lines
+pub fn parse_llvm_cov_json(text: &str) -> Option<Vec<FileCoverage>> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    let data = value.get("data")?.as_array()?;
+    let files = data
+        .iter()
+        .filter_map(|export| export.get("files")?.as_array())
+        .flatten()
+        .filter_map(|file| {
+            let lines = file.get("summary")?.get("lines")?;
+            Some(FileCoverage {
+                file: file.get("filename")?.as_str()?.to_string(),
+                lines_found: lines.get("count")?.as_u64()?,
+                lines_hit: lines.get("covered")?.as_u64()?,
+            })
+        })
+        .collect();
+    Some(files)
+}
+
+/// Parse a report, picking the format from its name and contents
+pub fn parse_report(path: &Path) -> Option<CoverageSummary> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let (format, files) = if name.ends_with(".json") {
+        (CoverageFormat::LlvmCovJson, parse_llvm_cov_json(&text)?)
+    } else if name.ends_with(".xml") {
+        (CoverageFormat::Cobertura, parse_cobertura(&text))
+    } else {
+        (CoverageFormat::Lcov, parse_lcov(&text))
+    };
+    if files.is_empty() {
+        return None;
+    }
+    Some(summary(format, path, files))
+}
+
+/// Whether a command runs tests, and so may have produced a report
+pub fn is_test_command(command: &str, args: &[&str]) -> bool {
+    let program = command.rsplit(['/', '\\']).next().unwrap_or(command);
+    let first = args.first().copied().unwrap_or_default();
+    match program {
+        "pytest" | "jest" | "vitest" | "coverage" | "nyc" | "c8" | "tarpaulin" => true,
+        "cargo" => matches!(first, "test" | "llvm-cov" | "tarpaulin" | "nextest"),
+        "npm" | "pnpm" | "yarn" | "bun" => first == "test" || args.iter().any(|a| a.contains("coverage")),
+        "npx" => matches!(first, "jest" | "vitest" | "nyc" | "c8"),
+        "go" => first == "test",
+        "python" | "python3" => args.windows(2).any(|w| w[0] == "-m" && matches!(w[1], "pytest" | "coverage")),
+        _ => false,
+    }
+}
+
+/// The newest known report under `root` written at or after `since`
+pub fn fresh_report(root: &Path, since: SystemTime) -> Option<CoverageSummary> {
+    REPORT_LOCATIONS
+        .iter()
+        .map(|location| root.join(location))
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            (modified >= since).then_some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .and_then(|(_, path)| parse_report(&path))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageBaseline {
+    pub percent: f64,
+    pub files: BTreeMap<String, f64>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl From<&CoverageSummary> for CoverageBaseline {
+    fn from(summary: &CoverageSummary) -> Self {
+        Self {
+            percent: summary.percent,
+            files: summary.files.iter().map(|f| (f.file.clone(), f.percent())).collect(),
+            recorded_at: Utc::now(),
+        }
+    }
+}
+
+/// Baselines by project root, persisted in the AI data directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CoverageBaselines {
+    projects: HashMap<String, CoverageBaseline>,
+}
+
+impl CoverageBaselines {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("coverage_baselines.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, root: &str) -> Option<&CoverageBaseline> {
+        self.projects.get(root)
+    }
+
+    pub fn set(&mut self, root: &str, summary: &CoverageSummary) {
+        self.projects.insert(root.to_string(), CoverageBaseline::from(summary));
+    }
+
+    /// The first report becomes the baseline and improvements raise it; drops leave it for comparison
+    pub fn observe(&mut self, root: &str, summary: &CoverageSummary) -> bool {
+        let raise = self.projects.get(root).map_or(true, |baseline| summary.percent >= baseline.percent);
+        if raise {
+            self.set(root, summary);
+        }
+        raise
+    }
+}
+
+/// What `get_coverage_summary` reports for a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageStatus {
+    pub project_root: String,
+    pub latest: Option<CoverageSummary>,
+    pub baseline: Option<CoverageBaseline>,
+    /// Percentage points relative to the baseline
+    pub change: Option<f64>,
+}
+
+impl CoverageStatus {
+    pub fn new(project_root: String, latest: Option<CoverageSummary>, baseline: Option<CoverageBaseline>) -> Self {
+        let change = latest.as_ref().zip(baseline.as_ref()).map(|(latest, baseline)| latest.percent - baseline.percent);
+        Self { project_root, latest, baseline, change }
+    }
+}
+
+/// A suggestion when the latest coverage fell below the project's baseline
+pub fn proactive_suggestions(latest: &CoverageSummary, baseline: &CoverageBaseline) -> Vec<ProactiveSuggestion> {
+    if latest.percent >= baseline.percent - DROP_TOLERANCE {
+        return Vec::new();
+    }
+    let mut dropped: Vec<(&str, f64)> = latest
+        .files
+        .iter()
+        .filter_map(|file| {
+            let before = baseline.files.get(&file.file)?;
+            let change = file.percent() - before;
+            (change < -DROP_TOLERANCE).then_some((file.file.as_str(), change))
+        })
+        .collect();
+    dropped.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let worst: Vec<String> = dropped.iter().take(3).map(|(file, change)| format!("{} {:+.1}%", file, change)).collect();
+
+    vec![ProactiveSuggestion {
+        id: None,
+        suggestion_type: "coverage".to_string(),
+        priority: 0.6,
+        description: format!(
+            "Test coverage dropped from {:.1}% to {:.1}%{}",
+            baseline.percent,
+            latest.percent,
+            if worst.is_empty() { String::new() } else { format!(" ({})", worst.join(", ")) },
+        ),
+        commands: Vec::new(),
+        trigger_condition: "coverage_drop".to_string(),
+    }]
+}
@@ -0,0 +1,9 @@
+// Project-level analysis: dependency health, repository insights and tooling
+// that operates on a whole project directory rather than a single command
+
+pub mod audit;
+pub mod context_file;
+pub mod coverage;
+pub mod search;
+pub mod symbols;
+pub mod test_runner;
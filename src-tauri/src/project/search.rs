@@ -0,0 +1,220 @@
+// Workspace-wide content search
+// A ripgrep-style search over a directory tree: .gitignore and hidden files are
+// respected, binary and oversized files are skipped, and matches come back as
+// structured file/line/column records. Large searches report matches in batches
+// while they run so a results panel can fill in before the walk ends.
+
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::{sinks::Lossy, BinaryDetection, SearcherBuilder};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Emitted with a `SearchBatch` while a search runs
+pub const MATCHES_EVENT: &str = "file-search-matches";
+
+const BATCH_SIZE: usize = 100;
+const MAX_PREVIEW_CHARS: usize = 200;
+/// Keywords that introduce a definition in common languages
+const DEFINITION_KEYWORDS: &str =
+    "fn|def|class|struct|enum|trait|impl|interface|type|function|func|const|let|var|val|module|macro_rules!";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression instead of literal text
+    pub regex: bool,
+    /// Case-sensitive only when the query has an uppercase letter
+    pub smart_case: bool,
+    pub whole_word: bool,
+    pub include_hidden: bool,
+    /// Globs to include; prefix with `!` to exclude
+    pub globs: Vec<String>,
+    pub max_results: usize,
+    pub max_file_size: u64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            regex: false,
+            smart_case: true,
+            whole_word: false,
+            include_hidden: false,
+            globs: Vec::new(),
+            max_results: 1000,
+            max_file_size: 2 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMatch {
+    /// Relative to the searched directory
+    pub path: String,
+    pub line: u64,
+    /// 1-based, in characters
+    pub column: usize,
+    pub preview: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchBatch {
+    pub search_id: Option<String>,
+    pub matches: Vec<FileMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub matches: Vec<FileMatch>,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    /// Stopped at `max_results`
+    pub truncated: bool,
+}
+
+impl SearchResults {
+    /// grep-like lines for the terminal
+    pub fn summary(&self) -> String {
+        if self.matches.is_empty() {
+            return format!("🔎 No matches in {} files", self.files_searched);
+        }
+        let mut lines: Vec<String> = self.matches
+            .iter()
+            .map(|m| format!("{}:{}:{}: {}", m.path, m.line, m.column, m.preview))
+            .collect();
+        lines.push(format!(
+            "🔎 {} matches in {} files{}",
+            self.matches.len(),
+            self.files_matched,
+            if self.truncated { " (stopped early, narrow the search for more)" } else { "" },
+        ));
+        lines.join("\n")
+    }
+}
+
+fn build_matcher(query: &str, options: &SearchOptions) -> Result<RegexMatcher, String> {
+    RegexMatcherBuilder::new()
+        .fixed_strings(!options.regex)
+        .case_smart(options.smart_case)
+        .word(options.whole_word)
+        .line_terminator(Some(b'\n'))
+        .build(query)
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+fn preview(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(MAX_PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Search every file under `root`; `on_batch` receives matches as they are found
+pub fn search_in_files(
+    query: &str,
+    root: &Path,
+    options: &SearchOptions,
+    mut on_batch: impl FnMut(&[FileMatch]),
+) -> Result<SearchResults, String> {
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let matcher = build_matcher(query, options)?;
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.globs {
+        overrides.add(glob).map_err(|e| format!("Invalid glob {}: {}", glob, e))?;
+    }
+    let overrides = overrides.build().map_err(|e| e.to_string())?;
+    let walker = WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .max_filesize(Some(options.max_file_size))
+        .overrides(overrides)
+        .build();
+    let mut searcher = SearcherBuilder::new()
+        .binary_detection(BinaryDetection::quit(b'\x00'))
+        .line_number(true)
+        .build();
+
+    let mut results = SearchResults { matches: Vec::new(), files_searched: 0, files_matched: 0, truncated: false };
+    let mut reported = 0;
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        results.files_searched += 1;
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+        let before = results.matches.len();
+        // Unreadable files are skipped like ripgrep does, without failing the search
+        let _ = searcher.search_path(&matcher, entry.path(), Lossy(|line_number, line| {
+            let column = matcher
+                .find(line.as_bytes())
+                .ok()
+                .flatten()
+                .map(|m| line[..m.start()].chars().count() + 1)
+                .unwrap_or(1);
+            results.matches.push(FileMatch { path: relative.clone(), line: line_number, column, preview: preview(line) });
+            Ok(results.matches.len() < options.max_results)
+        }));
+        if results.matches.len() > before {
+            results.files_matched += 1;
+        }
+        if results.matches.len() - reported >= BATCH_SIZE {
+            on_batch(&results.matches[reported..]);
+            reported = results.matches.len();
+        }
+        if results.matches.len() >= options.max_results {
+            results.truncated = true;
+            break;
+        }
+    }
+    if results.matches.len() > reported {
+        on_batch(&results.matches[reported..]);
+    }
+    Ok(results)
+}
+
+/// A content search asked for in plain words
+#[derive(Debug, Clone)]
+pub struct SearchRequest {
+    pub query: String,
+    pub options: SearchOptions,
+}
+
+/// "find where parse_config is defined", "where is UserStore used", "usages of FOO"
+pub fn plan_from_natural_language(request: &str) -> Option<SearchRequest> {
+    // Anchored at the start so a real command that merely contains the words is left alone
+    let lead = r"(?i)^\s*(?:please\s+)?(?:(?:find|show(?:\s+me)?|search(?:\s+for)?|list|look\s+up)\s+)?(?:the\s+|all\s+)?";
+    let identifier = r#"[`'"]?([A-Za-z_$][\w$]*)[`'"]?"#;
+    let definition = [
+        format!(r"{}where\s+(?:is\s+|are\s+)?{}\s+(?:is\s+|are\s+|gets\s+)?(?:defined|declared|implemented)\b", lead, identifier),
+        format!(r"{}(?:definition|declaration)\s+of\s+{}", lead, identifier),
+    ];
+    let usage = [
+        format!(r"{}where\s+(?:is\s+|are\s+)?{}\s+(?:is\s+|are\s+)?(?:used|called|referenced)\b", lead, identifier),
+        format!(r"{}(?:usages|uses|references|callers)\s+of\s+{}", lead, identifier),
+    ];
+    let capture = |patterns: &[String]| {
+        patterns.iter().find_map(|pattern| {
+            Regex::new(pattern).ok()?.captures(request).map(|captures| captures[1].to_string())
+        })
+    };
+
+    if let Some(name) = capture(&definition) {
+        return Some(SearchRequest {
+            query: format!(r"\b(?:{})\s+{}\b", DEFINITION_KEYWORDS, regex::escape(&name)),
+            options: SearchOptions { regex: true, smart_case: false, max_results: 200, ..Default::default() },
+        });
+    }
+    capture(&usage).map(|name| SearchRequest {
+        query: name,
+        options: SearchOptions { whole_word: true, smart_case: false, max_results: 200, ..Default::default() },
+    })
+}
@@ -0,0 +1,361 @@
+// Project index for AI context
+// Extracts top-level symbols with tree-sitter, a summary of the README and the
+// dependency manifests of a project, and turns them into short documents for the
+// embedding store, so suggestions and agents can answer questions about the
+// project ("run only the auth module tests") instead of guessing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tree_sitter::{Language, Node, Parser};
+
+const MAX_FILES: usize = 2000;
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+const MAX_SYMBOLS: usize = 1000;
+const MAX_README_CHARS: usize = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    /// Relative to the project root
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub file: String,
+    pub name: Option<String>,
+    pub dependencies: Vec<String>,
+    pub scripts: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectIndex {
+    pub root: String,
+    pub readme_summary: Option<String>,
+    pub manifests: Vec<Manifest>,
+    pub symbols: Vec<Symbol>,
+    pub files_indexed: usize,
+    /// Stopped at the file or symbol limit
+    pub truncated: bool,
+}
+
+/// A document for the embedding store: id, text and metadata
+pub type ProjectDocument = (String, String, HashMap<String, String>);
+
+fn language_for(path: &Path) -> Option<Language> {
+    let language = match path.extension()?.to_str()? {
+        "rs" => tree_sitter_rust::LANGUAGE,
+        "py" => tree_sitter_python::LANGUAGE,
+        "js" | "jsx" | "mjs" | "cjs" => tree_sitter_javascript::LANGUAGE,
+        "ts" | "mts" | "cts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        "go" => tree_sitter_go::LANGUAGE,
+        _ => return None,
+    };
+    Some(language.into())
+}
+
+/// Symbol kind for a top-level node, across the supported grammars
+fn symbol_kind(node_kind: &str) -> Option<&'static str> {
+    Some(match node_kind {
+        "function_item" | "function_declaration" | "generator_function_declaration" | "function_definition" => "function",
+        "method_declaration" => "method",
+        "struct_item" => "struct",
+        "enum_item" | "enum_declaration" => "enum",
+        "trait_item" => "trait",
+        "impl_item" => "impl",
+        "mod_item" => "module",
+        "const_item" | "static_item" => "constant",
+        "type_item" | "type_alias_declaration" => "type",
+        "macro_definition" => "macro",
+        "class_definition" | "class_declaration" | "abstract_class_declaration" => "class",
+        "interface_declaration" => "interface",
+        _ => return None,
+    })
+}
+
+fn node_text<'a>(node: Node, source: &'a str) -> &'a str {
+    source.get(node.byte_range()).unwrap_or_default()
+}
+
+/// Symbols declared by one top-level node; wrappers like `export` and decorators are looked through
+fn collect_symbols(node: Node, source: &str, file: &str, symbols: &mut Vec<Symbol>) {
+    let mut push = |name: Node, kind: &str| {
+        symbols.push(Symbol {
+            name: node_text(name, source).to_string(),
+            kind: kind.to_string(),
+            file: file.to_string(),
+            line: name.start_position().row + 1,
+        });
+    };
+    match node.kind() {
+        "export_statement" | "decorated_definition" => {
+            let inner = node.child_by_field_name("declaration").or_else(|| node.child_by_field_name("definition"));
+            if let Some(inner) = inner {
+                collect_symbols(inner, source, file, symbols);
+            }
+        }
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            for declarator in node.named_children(&mut cursor).filter(|n| n.kind() == "variable_declarator") {
+                let Some(name) = declarator.child_by_field_name("name") else { continue };
+                let is_function = declarator
+                    .child_by_field_name("value")
+                    .is_some_and(|value| matches!(value.kind(), "arrow_function" | "function_expression" | "function"));
+                push(name, if is_function { "function" } else { "constant" });
+            }
+        }
+        // Go groups `type A struct{}; type B int` under one declaration
+        "type_declaration" => {
+            let mut cursor = node.walk();
+            for spec in node.named_children(&mut cursor).filter(|n| n.kind() == "type_spec") {
+                if let Some(name) = spec.child_by_field_name("name") {
+                    push(name, "type");
+                }
+            }
+        }
+        "impl_item" => {
+            if let Some(name) = node.child_by_field_name("type") {
+                push(name, "impl");
+            }
+        }
+        kind => {
+            if let (Some(symbol_kind), Some(name)) = (symbol_kind(kind), node.child_by_field_name("name")) {
+                push(name, symbol_kind);
+            }
+        }
+    }
+}
+
+fn file_symbols(parser: &mut Parser, language: &Language, source: &str, file: &str) -> Vec<Symbol> {
+    if parser.set_language(language).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else { return Vec::new() };
+    let mut symbols = Vec::new();
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    for node in root.named_children(&mut cursor) {
+        collect_symbols(node, source, file, &mut symbols);
+    }
+    symbols
+}
+
+/// First paragraphs of the README, without headings, badges and HTML
+fn readme_summary(root: &Path) -> Option<String> {
+    let text = ["README.md", "README", "readme.md", "README.rst", "README.txt"]
+        .iter()
+        .find_map(|name| std::fs::read_to_string(root.join(name)).ok())?;
+    let mut summary = String::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with('<') || line.starts_with("[![") || line.starts_with("![") || line.starts_with("```") {
+            continue;
+        }
+        if line.is_empty() {
+            if summary.len() >= MAX_README_CHARS / 2 {
+                break;
+            }
+            continue;
+        }
+        if !summary.is_empty() {
+            summary.push(' ');
+        }
+        summary.push_str(line);
+        if summary.len() >= MAX_README_CHARS {
+            break;
+        }
+    }
+    if summary.is_empty() {
+        return None;
+    }
+    Some(match summary.char_indices().nth(MAX_README_CHARS) {
+        Some((end, _)) => format!("{}…", &summary[..end]),
+        None => summary,
+    })
+}
+
+fn cargo_manifest(text: &str) -> Option<Manifest> {
+    let value: toml::Value = toml::from_str(text).ok()?;
+    let keys = |table: Option<&toml::Value>| -> Vec<String> {
+        table.and_then(|t| t.as_table()).map(|t| t.keys().cloned().collect()).unwrap_or_default()
+    };
+    let mut dependencies = keys(value.get("dependencies"));
+    dependencies.extend(keys(value.get("dev-dependencies")));
+    dependencies.extend(keys(value.get("workspace").and_then(|w| w.get("dependencies"))));
+    Some(Manifest {
+        file: "Cargo.toml".to_string(),
+        name: value.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(str::to_string),
+        dependencies,
+        scripts: BTreeMap::new(),
+    })
+}
+
+fn package_json_manifest(text: &str) -> Option<Manifest> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    let keys = |field: &str| -> Vec<String> {
+        value.get(field).and_then(|d| d.as_object()).map(|d| d.keys().cloned().collect()).unwrap_or_default()
+    };
+    let mut dependencies = keys("dependencies");
+    dependencies.extend(keys("devDependencies"));
+    let scripts = value
+        .get("scripts")
+        .and_then(|s| s.as_object())
+        .map(|s| s.iter().filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect())
+        .unwrap_or_default();
+    Some(Manifest {
+        file: "package.json".to_string(),
+        name: value.get("name").and_then(|n| n.as_str()).map(str::to_string),
+        dependencies,
+        scripts,
+    })
+}
+
+fn pyproject_manifest(text: &str) -> Option<Manifest> {
+    let value: toml::Value = toml::from_str(text).ok()?;
+    let project = value.get("project");
+    let poetry = value.get("tool").and_then(|t| t.get("poetry"));
+    // PEP 621 lists requirement strings such as "requests>=2"; keep the distribution name
+    let mut dependencies: Vec<String> = project
+        .and_then(|p| p.get("dependencies"))
+        .and_then(|d| d.as_array())
+        .map(|d| {
+            d.iter()
+                .filter_map(|r| r.as_str())
+                .filter_map(|r| r.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.')).next())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(table) = poetry.and_then(|p| p.get("dependencies")).and_then(|d| d.as_table()) {
+        dependencies.extend(table.keys().filter(|k| k.as_str() != "python").cloned());
+    }
+    let scripts = project
+        .or(poetry)
+        .and_then(|p| p.get("scripts"))
+        .and_then(|s| s.as_table())
+        .map(|s| s.iter().filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect())
+        .unwrap_or_default();
+    Some(Manifest {
+        file: "pyproject.toml".to_string(),
+        name: project.or(poetry).and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(str::to_string),
+        dependencies,
+        scripts,
+    })
+}
+
+fn go_manifest(text: &str) -> Option<Manifest> {
+    let mut manifest = Manifest { file: "go.mod".to_string(), ..Default::default() };
+    let mut in_require = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(module) = line.strip_prefix("module ") {
+            manifest.name = Some(module.trim().to_string());
+        } else if line.starts_with("require (") {
+            in_require = true;
+        } else if in_require && line == ")" {
+            in_require = false;
+        } else if let Some(requirement) = line.strip_prefix("require ").or(in_require.then_some(line)) {
+            if let Some(path) = requirement.split_whitespace().next().filter(|p| !p.starts_with("//")) {
+                manifest.dependencies.push(path.to_string());
+            }
+        }
+    }
+    Some(manifest)
+}
+
+/// Manifest file name and the parser for its contents
+type ManifestParser = (&'static str, fn(&str) -> Option<Manifest>);
+
+fn manifests(root: &Path) -> Vec<Manifest> {
+    let parsers: [ManifestParser; 4] = [
+        ("Cargo.toml", cargo_manifest),
+        ("package.json", package_json_manifest),
+        ("pyproject.toml", pyproject_manifest),
+        ("go.mod", go_manifest),
+    ];
+    parsers
+        .iter()
+        .filter_map(|(file, parse)| parse(&std::fs::read_to_string(root.join(file)).ok()?))
+        .collect()
+}
+
+/// Nearest ancestor that looks like a project root, or the directory itself
+pub fn project_root(working_dir: &Path) -> PathBuf {
+    let markers = [".git", "Cargo.toml", "package.json", "pyproject.toml", "go.mod"];
+    working_dir
+        .ancestors()
+        .find(|dir| markers.iter().any(|marker| dir.join(marker).exists()))
+        .unwrap_or(working_dir)
+        .to_path_buf()
+}
+
+/// Index a project directory; files ignored by git are skipped
+pub fn index_project(root: &Path) -> Result<ProjectIndex, String> {
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let mut index = ProjectIndex {
+        root: root.to_string_lossy().to_string(),
+        readme_summary: readme_summary(root),
+        manifests: manifests(root),
+        symbols: Vec::new(),
+        files_indexed: 0,
+        truncated: false,
+    };
+
+    let mut parser = Parser::new();
+    let walker = ignore::WalkBuilder::new(root).max_filesize(Some(MAX_FILE_BYTES)).build();
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        let Some(language) = language_for(entry.path()) else { continue };
+        if index.files_indexed >= MAX_FILES || index.symbols.len() >= MAX_SYMBOLS {
+            index.truncated = true;
+            break;
+        }
+        let Ok(source) = std::fs::read_to_string(entry.path()) else { continue };
+        let file = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+        index.symbols.extend(file_symbols(&mut parser, &language, &source, &file));
+        index.files_indexed += 1;
+    }
+    index.symbols.truncate(MAX_SYMBOLS);
+    Ok(index)
+}
+
+impl ProjectIndex {
+    /// Short texts worth embedding; ids are stable so re-indexing replaces them
+    pub fn documents(&self) -> Vec<ProjectDocument> {
+        let metadata = |kind: &str, extra: &[(&str, String)]| {
+            let mut metadata: HashMap<String, String> = extra.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+            metadata.insert("project_kind".to_string(), kind.to_string());
+            metadata
+        };
+        let id = |suffix: String| format!("project:{}:{}", self.root, suffix);
+        let mut documents = Vec::new();
+
+        if let Some(summary) = &self.readme_summary {
+            documents.push((id("readme".to_string()), format!("README: {}", summary), metadata("readme", &[])));
+        }
+        for manifest in &self.manifests {
+            let name = manifest.name.as_deref().unwrap_or("unnamed");
+            let text = format!("{} package {} depends on {}", manifest.file, name, manifest.dependencies.join(", "));
+            documents.push((id(manifest.file.clone()), text, metadata("manifest", &[("file", manifest.file.clone())])));
+            for (script, command) in &manifest.scripts {
+                documents.push((
+                    id(format!("{}#{}", manifest.file, script)),
+                    format!("{} script {}: {}", manifest.file, script, command),
+                    metadata("script", &[("file", manifest.file.clone()), ("script", script.clone())]),
+                ));
+            }
+        }
+        for symbol in &self.symbols {
+            documents.push((
+                id(format!("{}:{}:{}", symbol.file, symbol.line, symbol.name)),
+                format!("{} {} in {}:{}", symbol.kind, symbol.name, symbol.file, symbol.line),
+                metadata("symbol", &[("file", symbol.file.clone()), ("line", symbol.line.to_string()), ("symbol", symbol.name.clone())]),
+            ));
+        }
+        documents
+    }
+}
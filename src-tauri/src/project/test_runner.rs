@@ -0,0 +1,281 @@
+// Test discovery and selective test runs
+// Finds tests in a project without running it (Rust #[test] functions, Jest and
+// Vitest test files, pytest node ids), builds the filtered invocation for a
+// selection of them, and reads failing tests back out of a run's output so a
+// failure can be re-run on its own.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::terminal::shell_quote::quote_arg;
+
+const MAX_FILES: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestFramework {
+    Cargo,
+    Jest,
+    Vitest,
+    Pytest,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestCase {
+    pub framework: TestFramework,
+    /// What the runner filters on: a Rust test's full path, `file::name` for JS, a pytest node id
+    pub id: String,
+    pub name: String,
+    /// Relative to the project root
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSelection {
+    pub framework: TestFramework,
+    pub tests: Vec<TestCase>,
+}
+
+/// Jest or Vitest, from the project's package.json
+fn javascript_framework(root: &Path) -> TestFramework {
+    let manifest = std::fs::read_to_string(root.join("package.json")).unwrap_or_default();
+    if manifest.contains("\"vitest\"") || manifest.contains("vitest ") {
+        TestFramework::Vitest
+    } else {
+        TestFramework::Jest
+    }
+}
+
+fn is_javascript_test(file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let script = [".js", ".jsx", ".ts", ".tsx", ".mjs", ".cjs"].iter().any(|ext| name.ends_with(ext));
+    script && (name.contains(".test.") || name.contains(".spec.") || file.contains("__tests__/"))
+}
+
+fn is_python_test(file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    name.ends_with(".py") && (name.starts_with("test_") || name.ends_with("_test.py"))
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+/// Module path a Rust file gives its items within its crate, e.g. `parser::lexer` for src/parser/lexer.rs.
+/// Files under src/bin, tests, benches and examples are crate roots of their own, as is a main.rs beside them
+fn rust_module_path(file: &str) -> Vec<String> {
+    let parts: Vec<&str> = file.trim_end_matches(".rs").split('/').collect();
+    let start = parts.iter().rposition(|p| matches!(*p, "src" | "tests" | "benches" | "examples")).map_or(0, |i| i + 1);
+    let mut path = parts[start..].to_vec();
+    let in_library = start > 0 && parts[start - 1] == "src" && path.first() != Some(&"bin");
+    if !in_library {
+        if path.first() == Some(&"bin") {
+            path.remove(0);
+        }
+        // `tests/it.rs` is a root, as is `tests/it/main.rs`, whose siblings are its modules
+        if !path.is_empty() {
+            path.remove(0);
+        }
+    }
+    if path.last() == Some(&"mod") {
+        path.pop();
+    }
+    if matches!(path.as_slice(), ["lib"] | ["main"]) {
+        path.clear();
+    }
+    path.into_iter().map(str::to_string).collect()
+}
+
+/// Inline `mod name { ... }` blocks around an offset, outermost first
+fn enclosing_modules(source: &str, offset: usize) -> Vec<String> {
+    let pattern = Regex::new(r"\bmod\s+(\w+)\s*\{").unwrap();
+    pattern
+        .captures_iter(source)
+        .filter_map(|captures| {
+            let open = captures.get(0).unwrap().end() - 1;
+            let mut depth = 0;
+            let close = source[open..]
+                .char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map_or(source.len(), |(i, _)| open + i);
+            (open < offset && offset < close).then(|| captures[1].to_string())
+        })
+        .collect()
+}
+
+fn rust_tests(source: &str, file: &str) -> Vec<TestCase> {
+    let pattern = Regex::new(r"#\[(?:[\w:]+::)?test\b[^\]]*\]\s*(?:#\[[^\]]*\]\s*)*(?:pub\s+)?(?:async\s+)?fn\s+(\w+)").unwrap();
+    let module = rust_module_path(file);
+    pattern
+        .captures_iter(source)
+        .map(|captures| {
+            let name = captures[1].to_string();
+            let offset = captures.get(1).unwrap().start();
+            // The full path, which `--exact` matches against
+            let mut path = module.clone();
+            path.extend(enclosing_modules(source, offset));
+            path.push(name.clone());
+            TestCase {
+                framework: TestFramework::Cargo,
+                id: path.join("::"),
+                name,
+                file: Some(file.to_string()),
+                line: Some(line_of(source, offset)),
+            }
+        })
+        .collect()
+}
+
+fn javascript_tests(source: &str, file: &str, framework: TestFramework) -> Vec<TestCase> {
+    // One alternative per quote character, since the regex crate has no backreferences
+    let pattern = Regex::new(r#"\b(?:it|test)(?:\.\w+)?\s*\(\s*(?:'([^'\n]+)'|"([^"\n]+)"|`([^`]+)`)"#).unwrap();
+    pattern
+        .captures_iter(source)
+        .filter_map(|captures| {
+            let quoted = captures.get(1).or_else(|| captures.get(2)).or_else(|| captures.get(3))?;
+            let name = quoted.as_str().to_string();
+            Some(TestCase {
+                framework,
+                id: format!("{}::{}", file, name),
+                name,
+                file: Some(file.to_string()),
+                line: Some(line_of(source, quoted.start())),
+            })
+        })
+        .collect()
+}
+
+/// Module-level `test_*` functions and methods of `Test*` classes, as pytest node ids
+fn python_tests(source: &str, file: &str) -> Vec<TestCase> {
+    let class_pattern = Regex::new(r"^class\s+(Test\w*)").unwrap();
+    let function_pattern = Regex::new(r"^(\s*)(?:async\s+)?def\s+(test\w*)").unwrap();
+    let mut tests = Vec::new();
+    let mut class: Option<String> = None;
+    for (index, line) in source.lines().enumerate() {
+        if let Some(captures) = class_pattern.captures(line) {
+            class = Some(captures[1].to_string());
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) && !line.trim().is_empty() && !line.trim_start().starts_with('@') {
+            class = None;
+        }
+        let Some(captures) = function_pattern.captures(line) else { continue };
+        let indented = !captures[1].is_empty();
+        let name = captures[2].to_string();
+        let id = match (&class, indented) {
+            (Some(class), true) => format!("{}::{}::{}", file, class, name),
+            (_, false) => format!("{}::{}", file, name),
+            // A nested helper inside some other block
+            (None, true) => continue,
+        };
+        tests.push(TestCase { framework: TestFramework::Pytest, id, name, file: Some(file.to_string()), line: Some(index + 1) });
+    }
+    tests
+}
+
+/// Every test found under the project root
+pub fn discover(root: &Path) -> Vec<TestCase> {
+    let javascript = javascript_framework(root);
+    let mut tests = Vec::new();
+    let walker = ignore::WalkBuilder::new(root).build();
+    for entry in walker.filter_map(|entry| entry.ok()).filter(|e| e.file_type().is_some_and(|t| t.is_file())).take(MAX_FILES) {
+        let file = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().replace('\\', "/");
+        let is_rust = file.ends_with(".rs");
+        if !(is_rust || is_javascript_test(&file) || is_python_test(&file)) {
+            continue;
+        }
+        let Ok(source) = std::fs::read_to_string(entry.path()) else { continue };
+        if is_rust {
+            tests.extend(rust_tests(&source, &file));
+        } else if is_python_test(&file) {
+            tests.extend(python_tests(&source, &file));
+        } else {
+            tests.extend(javascript_tests(&source, &file, javascript));
+        }
+    }
+    tests
+}
+
+/// Command line running just the selected tests; paths are made absolute so it works from any directory
+pub fn invocation(selection: &TestSelection, root: &Path) -> Result<String, String> {
+    if selection.tests.is_empty() {
+        return Err("No tests selected".to_string());
+    }
+    if let Some(other) = selection.tests.iter().find(|t| t.framework != selection.framework) {
+        return Err(format!("{} is not a {:?} test", other.id, selection.framework));
+    }
+    let quoted = |values: Vec<&str>| values.into_iter().map(|v| quote_arg(v).into_owned()).collect::<Vec<_>>().join(" ");
+    let names_pattern = || {
+        let names: Vec<String> = selection.tests.iter().map(|t| regex::escape(&t.name)).collect();
+        // Runners match against "describe blocks + test name", so anchor only the end
+        format!("({})$", names.join("|"))
+    };
+    let absolute = |path: &str| root.join(path).to_string_lossy().to_string();
+    let mut files: Vec<String> = selection.tests.iter().filter_map(|t| t.file.as_deref()).map(absolute).collect();
+    files.sort();
+    files.dedup();
+    let files = files.iter().map(String::as_str).collect();
+
+    Ok(match selection.framework {
+        // libtest takes several name filters after `--`; `--exact` keeps `parse` from also running `parse_empty`
+        TestFramework::Cargo => format!("cargo test -- --exact {}", quoted(selection.tests.iter().map(|t| t.id.as_str()).collect())),
+        TestFramework::Pytest => {
+            let ids: Vec<String> = selection.tests.iter().map(|t| absolute(&t.id)).collect();
+            format!("pytest {}", quoted(ids.iter().map(String::as_str).collect()))
+        }
+        TestFramework::Jest => format!("npx jest {} -t {}", quoted(files), quote_arg(&names_pattern())),
+        TestFramework::Vitest => format!("npx vitest run {} -t {}", quoted(files), quote_arg(&names_pattern())),
+    })
+}
+
+/// Failing tests reported in a test run's output
+pub fn failed_tests(output: &str) -> Vec<TestCase> {
+    let cargo = Regex::new(r"^test (\S+) \.\.\. FAILED$").unwrap();
+    let pytest = Regex::new(r"^FAILED (\S+?\.py::\S+)").unwrap();
+    let vitest = Regex::new(r"^\s*(?:FAIL|×|✗)\s+(\S+\.(?:test|spec)\.[cm]?[jt]sx?)\s+>\s+(.+?)(?:\s+\d+m?s)?$").unwrap();
+    let jest_file = Regex::new(r"^\s*FAIL\s+(\S+)").unwrap();
+    let jest_test = Regex::new(r"^\s*●\s+(.+)$").unwrap();
+
+    let mut failed: Vec<TestCase> = Vec::new();
+    let mut push = |test: TestCase| {
+        if !failed.contains(&test) {
+            failed.push(test);
+        }
+    };
+    let mut jest_current_file: Option<String> = None;
+    for line in output.lines() {
+        if let Some(captures) = cargo.captures(line) {
+            let id = captures[1].to_string();
+            let name = id.rsplit("::").next().unwrap_or(&id).to_string();
+            push(TestCase { framework: TestFramework::Cargo, id, name, file: None, line: None });
+        } else if let Some(captures) = pytest.captures(line) {
+            let id = captures[1].to_string();
+            let name = id.rsplit("::").next().unwrap_or(&id).to_string();
+            let file = id.split("::").next().map(str::to_string);
+            push(TestCase { framework: TestFramework::Pytest, id, name, file, line: None });
+        } else if let Some(captures) = vitest.captures(line) {
+            let file = captures[1].to_string();
+            let name = captures[2].rsplit(" > ").next().unwrap_or(&captures[2]).trim().to_string();
+            push(TestCase { framework: TestFramework::Vitest, id: format!("{}::{}", file, name), name, file: Some(file), line: None });
+        } else if let Some(captures) = jest_file.captures(line) {
+            jest_current_file = Some(captures[1].to_string());
+        } else if let (Some(captures), Some(file)) = (jest_test.captures(line), &jest_current_file) {
+            // "● describe › test name"
+            let name = captures[1].rsplit(" › ").next().unwrap_or(&captures[1]).trim().to_string();
+            if name == "Test suite failed to run" {
+                continue;
+            }
+            push(TestCase { framework: TestFramework::Jest, id: format!("{}::{}", file, name), name, file: Some(file.clone()), line: None });
+        }
+    }
+    failed
+}
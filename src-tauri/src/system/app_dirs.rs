@@ -0,0 +1,182 @@
+// Where the app keeps its files
+// State and models go in the platform's app data directory and settings in its
+// app config directory (e.g. ~/Library/Application Support/com.efficienttools.ph7console
+// on macOS, ~/.local/share/... and ~/.config/... on Linux, %APPDATA%\... on Windows).
+// Older builds wrote an `ai_data` folder into whatever directory the app was
+// started from; its contents are moved over once on the first start. Profiles
+// other than the default get their own directories beneath these (see `profiles`).
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const IDENTIFIER: &str = "com.efficienttools.ph7console";
+const LEGACY_DIRECTORY: &str = "ai_data";
+
+/// Settings files, which belong in the config directory rather than with the data
+pub const CONFIG_FILES: [&str; 26] = [
+    "suggestion_settings.json",
+    "translation_guard.json",
+    "directory_trust.json",
+    "notification_settings.json",
+    "path_display_settings.json",
+    "power_policy.json",
+    "model_routes.json",
+    "db_connections.json",
+    "launch_profiles.json",
+    "structured_output.json",
+    "prompt.json",
+    "backup_jobs.json",
+    "scratch.json",
+    "suggestion_throttle.json",
+    "voice.json",
+    "speech_settings.json",
+    "latency_budgets.json",
+    "remote_access.json",
+    "automation.json",
+    "webhooks.json",
+    "notification_sinks.json",
+    "history_settings.json",
+    "child_policies.json",
+    "dev_services.json",
+    "aliases.json",
+    "shell_history_settings.json",
+];
+
+struct AppDirs {
+    data: PathBuf,
+    config: PathBuf,
+    base_data: PathBuf,
+    base_config: PathBuf,
+    profile: String,
+}
+
+impl AppDirs {
+    fn for_profile(base_data: PathBuf, base_config: PathBuf) -> Self {
+        let profile = super::profiles::ProfileRegistry::startup_profile(&base_config);
+        Self {
+            data: super::profiles::profile_dir(&base_data, &profile),
+            config: super::profiles::profile_dir(&base_config, &profile),
+            base_data,
+            base_config,
+            profile,
+        }
+    }
+}
+
+fn dirs() -> &'static OnceLock<AppDirs> {
+    static DIRS: OnceLock<AppDirs> = OnceLock::new();
+    &DIRS
+}
+
+/// Resolve the directories through tauri; call first thing in setup
+pub fn init(app: &tauri::AppHandle) {
+    use tauri::Manager;
+
+    let resolver = app.path();
+    let (Ok(data), Ok(config)) = (resolver.app_data_dir(), resolver.app_config_dir()) else {
+        println!("⚠️ Could not resolve the app directories, using the defaults");
+        return;
+    };
+    let _ = dirs().set(AppDirs::for_profile(data, config));
+}
+
+/// Same locations tauri resolves, for anything that runs before `init`
+fn fallback() -> AppDirs {
+    let base = |dir: Option<PathBuf>| dir.or_else(dirs::home_dir).unwrap_or_else(|| PathBuf::from(".")).join(IDENTIFIER);
+    AppDirs::for_profile(base(dirs::data_dir()), base(dirs::config_dir()))
+}
+
+pub fn data_dir() -> PathBuf {
+    dirs().get_or_init(fallback).data.clone()
+}
+
+pub fn config_dir() -> PathBuf {
+    dirs().get_or_init(fallback).config.clone()
+}
+
+/// The app directories themselves, where the default profile and the profile registry live
+pub fn base_dirs() -> (PathBuf, PathBuf) {
+    let dirs = dirs().get_or_init(fallback);
+    (dirs.base_data.clone(), dirs.base_config.clone())
+}
+
+/// The profile whose files are in use
+pub fn active_profile() -> String {
+    dirs().get_or_init(fallback).profile.clone()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacyMove {
+    pub from: String,
+    pub moved: Vec<String>,
+    /// Entries already present at the new location, left where they were
+    pub skipped: Vec<String>,
+}
+
+/// Move `ai_data` folders left by older builds (in the current or home directory) to the new locations
+pub fn move_legacy_data() -> Vec<LegacyMove> {
+    // Data from before profiles belongs to the default profile
+    if active_profile() != super::profiles::DEFAULT_PROFILE {
+        return Vec::new();
+    }
+    let mut candidates: Vec<PathBuf> = [std::env::current_dir().ok(), dirs::home_dir()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(LEGACY_DIRECTORY))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    candidates.dedup();
+
+    let mut moves = Vec::new();
+    for legacy in candidates {
+        match move_directory(&legacy) {
+            Ok(moved) => {
+                println!("🗄️ Moved {} entries from {} to {}", moved.moved.len(), legacy.display(), data_dir().display());
+                moves.push(moved);
+            }
+            Err(e) => println!("⚠️ Could not move {}: {}", legacy.display(), e),
+        }
+    }
+    moves
+}
+
+fn move_directory(legacy: &Path) -> Result<LegacyMove, String> {
+    let (data, config) = (data_dir(), config_dir());
+    std::fs::create_dir_all(&data).map_err(|e| format!("Cannot create {}: {}", data.display(), e))?;
+    std::fs::create_dir_all(&config).map_err(|e| format!("Cannot create {}: {}", config.display(), e))?;
+
+    let mut report = LegacyMove { from: legacy.to_string_lossy().to_string(), moved: Vec::new(), skipped: Vec::new() };
+    let entries = std::fs::read_dir(legacy).map_err(|e| e.to_string())?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let target = if CONFIG_FILES.contains(&name.as_str()) { config.join(&name) } else { data.join(&name) };
+        if target.exists() {
+            report.skipped.push(name);
+            continue;
+        }
+        move_entry(&entry.path(), &target).map_err(|e| format!("Cannot move {}: {}", name, e))?;
+        report.moved.push(name);
+    }
+    // Only an emptied folder goes; anything skipped stays for the user to look at
+    let _ = std::fs::remove_dir(legacy);
+    Ok(report)
+}
+
+/// Rename, or copy and delete when the new location is on another file system
+fn move_entry(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            move_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        std::fs::remove_dir(from)
+    } else {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)
+    }
+}
@@ -0,0 +1,479 @@
+// Local automation API
+// Editors, scripts and test harnesses drive the console through a local socket
+// (a Unix socket, or 127.0.0.1 on Windows) speaking JSON-RPC 2.0, one message
+// per line. A client first calls `initialize` with the API version it was
+// written for and the token from the connection file; everything else is
+// refused until then. The connection file (`automation/connection.json` in the
+// config directory, readable by the user alone) says where to connect and holds
+// a token that changes every time the server starts. Calls go through the same
+// commands as the app's own, so commands queue per session and translated
+// commands are checked by the same guard as when typed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, watch};
+
+use crate::AppState;
+
+/// The version of the method set below; clients name the version they expect
+pub const API_VERSION: u32 = 1;
+/// Longest request line accepted; longer ones close the connection
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+/// Methods available once initialized
+const METHODS: [&str; 11] = [
+    "session.list",
+    "session.create",
+    "session.close",
+    "session.status",
+    "command.run",
+    "command.input",
+    "command.running",
+    "command.terminate",
+    "history.search",
+    "history.page",
+    "output.page",
+];
+
+// JSON-RPC error codes; the -320xx ones are this API's
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const COMMAND_FAILED: i64 = -32000;
+const NOT_INITIALIZED: i64 = -32001;
+const UNSUPPORTED_VERSION: i64 = -32002;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomationSettings {
+    /// Start the server when the app starts
+    pub start_on_launch: bool,
+}
+
+impl AutomationSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("automation.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+/// What clients read to find and authenticate with the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    pub api_version: u32,
+    /// `unix` (a socket path) or `tcp` (host:port)
+    pub transport: String,
+    pub address: String,
+    pub token: String,
+    pub pid: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationStatus {
+    pub running: bool,
+    pub api_version: u32,
+    pub transport: Option<String>,
+    pub address: Option<String>,
+    /// Where clients find the address and token
+    pub connection_file: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub connected_clients: usize,
+    pub settings: AutomationSettings,
+}
+
+fn automation_directory() -> PathBuf {
+    crate::ai::config_directory().join("automation")
+}
+
+fn connection_file() -> PathBuf {
+    automation_directory().join("connection.json")
+}
+
+fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.trim().as_bytes()).to_vec()
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> Stream for T {}
+
+enum Listener {
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+    #[cfg(not(unix))]
+    Tcp(tokio::net::TcpListener),
+}
+
+impl Listener {
+    /// A socket in the automation directory; a stale one left by a crash is replaced,
+    /// one another running console answers on is not
+    #[cfg(unix)]
+    async fn bind() -> Result<Self, String> {
+        let path = automation_directory().join("ph7.sock");
+        if tokio::net::UnixStream::connect(&path).await.is_ok() {
+            return Err(format!("Another pH7Console is already serving automation at {}", path.display()));
+        }
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).map_err(|e| format!("Cannot listen on {}: {}", path.display(), e))?;
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+        }
+        Ok(Self::Unix(listener, path))
+    }
+
+    #[cfg(not(unix))]
+    async fn bind() -> Result<Self, String> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.map_err(|e| format!("Cannot listen on 127.0.0.1: {}", e))?;
+        Ok(Self::Tcp(listener))
+    }
+
+    fn describe(&self) -> (String, String) {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(_, path) => ("unix".to_string(), path.display().to_string()),
+            #[cfg(not(unix))]
+            Self::Tcp(listener) => ("tcp".to_string(), listener.local_addr().map(|a| a.to_string()).unwrap_or_default()),
+        }
+    }
+
+    async fn accept(&self) -> std::io::Result<Box<dyn Stream>> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(listener, _) => Ok(Box::new(listener.accept().await?.0)),
+            #[cfg(not(unix))]
+            Self::Tcp(listener) => Ok(Box::new(listener.accept().await?.0)),
+        }
+    }
+}
+
+struct Running {
+    transport: String,
+    address: String,
+    started_at: DateTime<Utc>,
+    shutdown: watch::Sender<bool>,
+    clients: Arc<AtomicUsize>,
+}
+
+/// Shared by every connection of a running server
+struct Context {
+    app: AppHandle,
+    token_hash: Vec<u8>,
+    clients: Arc<AtomicUsize>,
+    shutdown: watch::Receiver<bool>,
+}
+
+pub struct AutomationServer {
+    settings: AutomationSettings,
+    running: Option<Running>,
+}
+
+impl Default for AutomationServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutomationServer {
+    pub fn new() -> Self {
+        Self { settings: AutomationSettings::load(), running: None }
+    }
+
+    pub fn starts_on_launch(&self) -> bool {
+        self.settings.start_on_launch
+    }
+
+    pub fn status(&self) -> AutomationStatus {
+        let running = self.running.as_ref();
+        AutomationStatus {
+            running: running.is_some(),
+            api_version: API_VERSION,
+            transport: running.map(|r| r.transport.clone()),
+            address: running.map(|r| r.address.clone()),
+            connection_file: connection_file().display().to_string(),
+            started_at: running.map(|r| r.started_at),
+            connected_clients: running.map_or(0, |r| r.clients.load(Ordering::Relaxed)),
+            settings: self.settings.clone(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: AutomationSettings) -> Result<(), String> {
+        settings.save()?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    pub async fn start(&mut self, app: AppHandle) -> Result<AutomationStatus, String> {
+        if self.running.is_some() {
+            return Ok(self.status());
+        }
+        let directory = automation_directory();
+        std::fs::create_dir_all(&directory).map_err(|e| format!("Cannot create {}: {}", directory.display(), e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o700));
+        }
+        let listener = Listener::bind().await?;
+        let (transport, address) = listener.describe();
+
+        let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        write_connection_file(&ConnectionInfo {
+            api_version: API_VERSION,
+            transport: transport.clone(),
+            address: address.clone(),
+            token: token.clone(),
+            pid: std::process::id(),
+        })?;
+
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let clients = Arc::new(AtomicUsize::new(0));
+        let context = Arc::new(Context { app, token_hash: hash_token(&token), clients: clients.clone(), shutdown: shutdown_rx });
+        tokio::spawn(serve(listener, context));
+
+        println!("🔌 Automation API listening on {} {}", transport, address);
+        self.running = Some(Running { transport, address, started_at: Utc::now(), shutdown, clients });
+        Ok(self.status())
+    }
+
+    /// Stop serving, drop every connection and remove the connection file; false when it was not running
+    pub fn stop(&mut self) -> bool {
+        let Some(running) = self.running.take() else { return false };
+        let _ = running.shutdown.send(true);
+        let _ = std::fs::remove_file(connection_file());
+        if running.transport == "unix" {
+            let _ = std::fs::remove_file(&running.address);
+        }
+        println!("🔌 Automation API stopped");
+        true
+    }
+}
+
+fn write_connection_file(info: &ConnectionInfo) -> Result<(), String> {
+    let file = connection_file();
+    let json = serde_json::to_string_pretty(info).map_err(|e| e.to_string())?;
+    // Created private rather than narrowed afterwards, so the token is never readable by others
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut handle = options.open(&file).map_err(|e| format!("Cannot write {}: {}", file.display(), e))?;
+    std::io::Write::write_all(&mut handle, json.as_bytes()).map_err(|e| format!("Cannot write {}: {}", file.display(), e))
+}
+
+async fn serve(listener: Listener, context: Arc<Context>) {
+    let mut shutdown = context.shutdown.clone();
+    loop {
+        let stream = tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            },
+        };
+        let context = context.clone();
+        tokio::spawn(async move {
+            context.clients.fetch_add(1, Ordering::Relaxed);
+            connection(stream, &context).await;
+            context.clients.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
+
+fn success(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn failure(id: &Value, code: i64, message: impl Into<String>) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message.into() } })
+}
+
+/// Requests in, replies out, until the client leaves or the server stops.
+/// Calls after `initialize` run side by side; replies carry the request's id
+async fn connection(stream: Box<dyn Stream>, context: &Arc<Context>) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = tokio::io::BufReader::new(reader);
+    let (outgoing, mut queued) = mpsc::unbounded_channel::<Value>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(reply) = queued.recv().await {
+            let line = format!("{}\n", reply);
+            if writer.write_all(line.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut initialized = false;
+    let mut shutdown = context.shutdown.clone();
+    loop {
+        let mut line = Vec::new();
+        let mut limited = (&mut reader).take(MAX_REQUEST_BYTES);
+        let read = tokio::select! {
+            _ = shutdown.changed() => break,
+            read = limited.read_until(b'\n', &mut line) => read,
+        };
+        match read {
+            Ok(0) | Err(_) => break,
+            Ok(_) if !line.ends_with(b"\n") && line.len() as u64 >= MAX_REQUEST_BYTES => {
+                let _ = outgoing.send(failure(&Value::Null, INVALID_REQUEST, "Request too large"));
+                break;
+            }
+            Ok(_) => {}
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_slice(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = outgoing.send(failure(&Value::Null, PARSE_ERROR, e.to_string()));
+                continue;
+            }
+        };
+        // Notifications (no id) are run but not answered
+        let id = request.get("id").cloned();
+        let reply_id = id.clone().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str).map(str::to_string) else {
+            let _ = outgoing.send(failure(&reply_id, INVALID_REQUEST, "Expected a JSON-RPC 2.0 request object with a method"));
+            continue;
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        if method == "initialize" {
+            let reply = match initialize(context, &params) {
+                Ok(result) => {
+                    initialized = true;
+                    success(&reply_id, result)
+                }
+                Err((code, message)) => failure(&reply_id, code, message),
+            };
+            if id.is_some() {
+                let _ = outgoing.send(reply);
+            }
+            continue;
+        }
+        if !initialized {
+            let _ = outgoing.send(failure(&reply_id, NOT_INITIALIZED, "Call `initialize` with the token from the connection file first"));
+            continue;
+        }
+
+        let (app, outgoing) = (context.app.clone(), outgoing.clone());
+        tokio::spawn(async move {
+            let reply = match call(&app, &method, &params).await {
+                Ok(result) => success(&reply_id, result),
+                Err((code, message)) => failure(&reply_id, code, message),
+            };
+            if id.is_some() {
+                let _ = outgoing.send(reply);
+            }
+        });
+    }
+    drop(outgoing);
+    let _ = writer_task.await;
+}
+
+type CallError = (i64, String);
+
+fn initialize(context: &Context, params: &Value) -> Result<Value, CallError> {
+    let token = params.get("token").and_then(Value::as_str).unwrap_or_default();
+    if token.is_empty() || hash_token(token) != context.token_hash {
+        return Err((NOT_INITIALIZED, "The token does not match the connection file".to_string()));
+    }
+    let version = params.get("api_version").and_then(Value::as_u64).unwrap_or(API_VERSION as u64);
+    if version != API_VERSION as u64 {
+        return Err((UNSUPPORTED_VERSION, format!("API version {} is not supported; this console speaks version {}", version, API_VERSION)));
+    }
+    Ok(json!({
+        "api_version": API_VERSION,
+        "server": format!("pH7Console {}", env!("CARGO_PKG_VERSION")),
+        "methods": METHODS,
+    }))
+}
+
+/// A named parameter
+fn param<T: serde::de::DeserializeOwned>(params: &Value, name: &str) -> Result<T, CallError> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null)).map_err(|e| (INVALID_PARAMS, format!("Invalid `{}`: {}", name, e)))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, CallError> {
+    serde_json::to_value(value).map_err(|e| (COMMAND_FAILED, e.to_string()))
+}
+
+fn failed(error: String) -> CallError {
+    (COMMAND_FAILED, error)
+}
+
+async fn call(app: &AppHandle, method: &str, params: &Value) -> Result<Value, CallError> {
+    let state = app.state::<AppState>();
+    match method {
+        "session.list" => to_json(crate::commands::get_all_sessions(state).await.map_err(failed)?),
+        "session.create" => to_json(
+            crate::commands::create_terminal(
+                app.clone(),
+                state,
+                param(params, "title")?,
+                param(params, "working_directory")?,
+                param(params, "shell")?,
+                param(params, "env")?,
+                param(params, "profile_id")?,
+            )
+            .await
+            .map_err(failed)?,
+        ),
+        "session.close" => to_json(crate::commands::close_terminal_session(app.clone(), state, param(params, "session_id")?).await.map_err(failed)?),
+        "session.status" => to_json(crate::commands::get_session_status(state, param(params, "session_id")?).await.map_err(failed)?),
+        "command.run" => {
+            let (session_id, line): (String, String) = (param(params, "session_id")?, param(params, "command")?);
+            println!("🔌 Automation client runs in {}: {}", session_id, line);
+            to_json(crate::commands::execute_command(app.clone(), state, session_id, line, None, param(params, "concurrently")?, param(params, "options")?).await.map_err(failed)?)
+        }
+        "command.input" => to_json(
+            crate::commands::write_to_terminal(state, param(params, "session_id")?, param(params, "data")?).await.map_err(failed)?,
+        ),
+        "command.running" => to_json(crate::commands::get_running_commands(state, param(params, "session_id")?).await.map_err(failed)?),
+        "command.terminate" => to_json(
+            crate::commands::terminate_command(state, param(params, "session_id")?, param(params, "execution_id")?, param(params, "signal")?)
+                .await
+                .map_err(failed)?,
+        ),
+        "history.search" => to_json(crate::commands::search_command_history(state, param(params, "pattern")?).await.map_err(failed)?),
+        "history.page" => {
+            let limit: Option<usize> = param(params, "limit")?;
+            let page = state.terminal_manager.lock().await.history_page(param(params, "before")?, limit.unwrap_or(100));
+            let mut pages = state.output_pages.lock().await;
+            to_json(page.map(|execution| pages.cap(execution)))
+        }
+        "output.page" => {
+            let offset: Option<usize> = param(params, "offset")?;
+            let compress: Option<bool> = param(params, "compress")?;
+            let handle: String = param(params, "handle")?;
+            to_json(state.output_pages.lock().await.page(&handle, offset.unwrap_or(0), param(params, "length")?, compress.unwrap_or(false)).map_err(failed)?)
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("No method `{}` in API version {}", method, API_VERSION))),
+    }
+}
@@ -0,0 +1,259 @@
+// Bug report bundles
+// Everything needed to report a failing command in one place: the command, its
+// exit status and output, the OS, the project type, versions of the usual
+// runtimes and the commands run just before it in its session. The report is markdown ready to
+// paste into an issue; the zip variant adds the full output and the versions as
+// JSON. Before anything is written the home directory, user and host names,
+// values of secret-looking environment variables and well-known token shapes
+// are masked.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::terminal::outcome::{signal_name, ExecutionOutcome, SpawnErrorKind};
+use crate::terminal::CommandExecution;
+
+/// Output lines quoted in the markdown; the archive has all of it
+const OUTPUT_LINES: usize = 80;
+const RELATED_COMMANDS: usize = 10;
+/// Commands further back than this are unlikely to be related
+const RELATED_WINDOW_MINUTES: i64 = 30;
+/// Shorter secret values would mask ordinary words
+const MIN_SECRET_LEN: usize = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedCommand {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub timestamp: DateTime<Utc>,
+    /// Runs the same program as the failing command
+    pub same_program: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BugReport {
+    pub execution_id: String,
+    pub created_at: DateTime<Utc>,
+    pub markdown: String,
+    /// Zip with the report, the full output and environment details, when one was asked for
+    pub archive_path: Option<String>,
+}
+
+/// What the caller knows about where the command ran
+pub struct ReportContext {
+    pub working_directory: Option<String>,
+    pub shell: Option<String>,
+    pub project_type: Option<String>,
+    pub environment: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct EnvironmentDetails<'a> {
+    app_version: &'a str,
+    os: String,
+    os_version: Option<String>,
+    shell: Option<String>,
+    project_type: Option<String>,
+    tool_versions: BTreeMap<String, String>,
+}
+
+/// Replaces what identifies the user or could give access to their accounts
+struct Redactor {
+    patterns: Vec<(Regex, &'static str)>,
+}
+
+impl Redactor {
+    fn new(environment: &HashMap<String, String>) -> Self {
+        // (text, whole words only, replacement)
+        let mut literals: Vec<(String, bool, &'static str)> = environment
+            .iter()
+            .filter(|(name, value)| super::env_diff::is_secret(name) && value.len() >= MIN_SECRET_LEN)
+            .map(|(_, value)| (value.clone(), false, "[redacted]"))
+            .collect();
+        if let Some(home) = dirs::home_dir().map(|home| home.to_string_lossy().to_string()).filter(|home| home.len() > 1) {
+            literals.push((home, false, "~"));
+        }
+        // Names can be ordinary words too ("build", "dev"), so only whole words are masked
+        if let Some(host) = sysinfo::System::host_name().filter(|host| !host.is_empty()) {
+            literals.push((host, true, "<host>"));
+        }
+        if let Some(user) = environment.get("USER").or_else(|| environment.get("USERNAME")).filter(|user| user.len() > 2) {
+            literals.push((user.clone(), true, "<user>"));
+        }
+        // Longest first, so a home directory is replaced before the user name inside it
+        literals.sort_by_key(|(literal, _, _)| std::cmp::Reverse(literal.len()));
+        let patterns = literals
+            .into_iter()
+            .filter_map(|(literal, whole_word, replacement)| {
+                let pattern = regex::escape(&literal);
+                let pattern = if whole_word { format!(r"\b{}\b", pattern) } else { pattern };
+                Regex::new(&pattern).ok().map(|regex| (regex, replacement))
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut text = crate::integrations::sinks::mask_known_secrets(text);
+        for (pattern, replacement) in &self.patterns {
+            text = pattern.replace_all(&text, regex::NoExpand(replacement)).into_owned();
+        }
+        text
+    }
+}
+
+fn program(command: &str) -> &str {
+    command.split_whitespace().next().map(|p| p.rsplit('/').next().unwrap_or(p)).unwrap_or_default()
+}
+
+/// Commands run shortly before the failing one in the same session, newest first; `history`
+/// is newest first too
+fn related_commands(history: &[Arc<CommandExecution>], execution: &CommandExecution) -> Vec<RelatedCommand> {
+    let failing_program = program(&execution.command);
+    let since = execution.timestamp - chrono::Duration::minutes(RELATED_WINDOW_MINUTES);
+    history
+        .iter()
+        .skip_while(|entry| entry.id != execution.id)
+        .skip(1)
+        .filter(|entry| entry.session_id == execution.session_id)
+        .take_while(|entry| entry.timestamp >= since)
+        .take(RELATED_COMMANDS)
+        .map(|entry| RelatedCommand {
+            command: entry.command.clone(),
+            exit_code: entry.exit_code,
+            timestamp: entry.timestamp,
+            same_program: program(&entry.command) == failing_program,
+        })
+        .collect()
+}
+
+fn describe(outcome: ExecutionOutcome) -> String {
+    match outcome {
+        ExecutionOutcome::Exited(code) => format!("exited with {}", code),
+        ExecutionOutcome::Signaled(signal) => format!("killed by {}", signal_name(signal)),
+        ExecutionOutcome::TimedOut => "timed out".to_string(),
+        ExecutionOutcome::SpawnError(SpawnErrorKind::NotFound) => "command not found".to_string(),
+        ExecutionOutcome::SpawnError(SpawnErrorKind::PermissionDenied) => "permission denied".to_string(),
+        ExecutionOutcome::SpawnError(SpawnErrorKind::Other) => "could not be started".to_string(),
+    }
+}
+
+fn markdown(execution: &CommandExecution, details: &EnvironmentDetails, related: &[RelatedCommand], working_directory: Option<&str>) -> String {
+    let mut report = String::from("## Bug report\n\n");
+    report.push_str(&format!("**Command:** `{}`\n\n", execution.command.replace('`', "'")));
+    report.push_str(&format!("**Result:** {} after {} ms\n\n", describe(execution.outcome), execution.duration_ms));
+    if let Some(directory) = working_directory {
+        report.push_str(&format!("**Directory:** `{}`\n\n", directory));
+    }
+
+    let lines: Vec<&str> = execution.output.lines().collect();
+    let shown = &lines[lines.len().saturating_sub(OUTPUT_LINES)..];
+    report.push_str("### Output\n\n");
+    if lines.len() > shown.len() {
+        report.push_str(&format!("_Last {} of {} lines_\n\n", shown.len(), lines.len()));
+    }
+    report.push_str(&format!("```\n{}\n```\n\n", shown.join("\n").replace("```", "'''")));
+
+    report.push_str("### Environment\n\n");
+    report.push_str(&format!("- pH7Console {}\n", details.app_version));
+    report.push_str(&format!("- OS: {}{}\n", details.os, details.os_version.as_deref().map(|v| format!(" ({})", v)).unwrap_or_default()));
+    if let Some(shell) = &details.shell {
+        report.push_str(&format!("- Shell: {}\n", shell));
+    }
+    if let Some(project_type) = &details.project_type {
+        report.push_str(&format!("- Project: {}\n", project_type));
+    }
+    for (tool, version) in &details.tool_versions {
+        report.push_str(&format!("- {}: {}\n", tool, version));
+    }
+
+    if !related.is_empty() {
+        report.push_str("\n### Commands before it (newest first)\n\n");
+        for command in related {
+            let status = match command.exit_code {
+                Some(0) => "ok".to_string(),
+                Some(code) => format!("exit {}", code),
+                None => "no status".to_string(),
+            };
+            let marker = if command.same_program { " ←" } else { "" };
+            report.push_str(&format!("- `{}` ({}){}\n", command.command.replace('`', "'"), status, marker));
+        }
+    }
+    report
+}
+
+fn reports_directory() -> PathBuf {
+    crate::ai::data_directory().join("bug_reports")
+}
+
+fn write_archive(path: &Path, files: &[(&str, &str)]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let file = std::fs::File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+    let mut archive = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in files {
+        archive.start_file(*name, options).map_err(|e| e.to_string())?;
+        archive.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    archive.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Build the redacted report for a command, and a zip next to the app data when `archive` is set
+pub async fn create(
+    execution: &CommandExecution,
+    history: &[Arc<CommandExecution>],
+    context: ReportContext,
+    archive: bool,
+) -> Result<BugReport, String> {
+    let working_directory = context.working_directory.clone().unwrap_or_default();
+    let snapshot = super::env_diff::capture("bug report", &context.environment, &working_directory).await;
+    let redactor = Redactor::new(&context.environment);
+
+    let details = EnvironmentDetails {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: snapshot.os,
+        os_version: sysinfo::System::long_os_version(),
+        shell: context.shell.as_deref().map(|shell| redactor.apply(shell)),
+        project_type: context.project_type,
+        tool_versions: snapshot.tool_versions.into_iter().map(|(tool, version)| (tool, redactor.apply(&version))).collect(),
+    };
+    let related: Vec<RelatedCommand> = related_commands(history, execution)
+        .into_iter()
+        .map(|command| RelatedCommand { command: redactor.apply(&command.command), ..command })
+        .collect();
+    let redacted = CommandExecution {
+        command: redactor.apply(&execution.command),
+        output: redactor.apply(&execution.output),
+        styled: None,
+        ..execution.clone()
+    };
+    let directory = context.working_directory.as_deref().map(|dir| redactor.apply(dir));
+    let markdown = markdown(&redacted, &details, &related, directory.as_deref());
+
+    let created_at = Utc::now();
+    let archive_path = if archive {
+        let path = reports_directory().join(format!("bug-report-{}.zip", created_at.format("%Y%m%d-%H%M%S")));
+        let environment = serde_json::to_string_pretty(&details).map_err(|e| e.to_string())?;
+        let commands = serde_json::to_string_pretty(&related).map_err(|e| e.to_string())?;
+        let files = [
+            ("report.md", markdown.as_str()),
+            ("output.txt", redacted.output.as_str()),
+            ("environment.json", environment.as_str()),
+            ("related_commands.json", commands.as_str()),
+        ];
+        write_archive(&path, &files)?;
+        Some(path.to_string_lossy().to_string())
+    } else {
+        None
+    };
+
+    Ok(BugReport { execution_id: execution.id.clone(), created_at, markdown, archive_path })
+}
@@ -0,0 +1,321 @@
+// Reclaimable disk space
+// Build output, installed dependencies and virtualenvs in known projects, the
+// user's cache directories and dangling Docker images are found and sized into
+// a cleanup plan. Items untouched for a while start selected; the rest wait for
+// the user. Executing the plan re-checks every path before removing it and
+// moves it to the trash when asked, so it can be restored.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+/// Emitted with a `CleanupProgress` after each item of a plan
+pub const PROGRESS_EVENT: &str = "cleanup-progress";
+
+/// Files counted per item before its size is reported as an estimate
+const MAX_ENTRIES_PER_ITEM: usize = 200_000;
+/// Cache directories smaller than this are not worth listing
+const MIN_CACHE_BYTES: u64 = 50 * 1024 * 1024;
+/// Items untouched for this long are selected by default
+const STALE_AFTER: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+const DOCKER_ID: &str = "docker:dangling-images";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupCategory {
+    BuildArtifacts,
+    Dependencies,
+    VirtualEnv,
+    Cache,
+    DockerImages,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupItem {
+    /// The path, or a pseudo-id for items that are not a directory
+    pub id: String,
+    pub category: CleanupCategory,
+    pub path: Option<String>,
+    pub project: Option<String>,
+    pub description: String,
+    pub size_bytes: u64,
+    /// The walk stopped before counting everything
+    pub size_estimated: bool,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// How to get it back after a permanent delete
+    pub regenerate_with: Option<String>,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupPlan {
+    pub items: Vec<CleanupItem>,
+    pub total_bytes: u64,
+    pub selected_bytes: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupProgress {
+    pub item_id: String,
+    pub completed: usize,
+    pub total: usize,
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupOutcome {
+    pub item_id: String,
+    pub freed_bytes: u64,
+    /// Where to look to undo it, when it can be undone
+    pub undo: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub outcomes: Vec<CleanupOutcome>,
+    pub freed_bytes: u64,
+    pub failed: usize,
+}
+
+/// Project artifact directories: name, the manifest that must sit next to it, category, regeneration command
+const PROJECT_ARTIFACTS: [(&str, &str, CleanupCategory, &str); 4] = [
+    ("target", "Cargo.toml", CleanupCategory::BuildArtifacts, "cargo build"),
+    ("node_modules", "package.json", CleanupCategory::Dependencies, "npm install"),
+    (".venv", "pyvenv.cfg", CleanupCategory::VirtualEnv, "python -m venv .venv"),
+    ("venv", "pyvenv.cfg", CleanupCategory::VirtualEnv, "python -m venv venv"),
+];
+
+/// Per-user cache directories whose children are listed individually
+fn cache_roots() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else { return Vec::new() };
+    let mut roots = Vec::new();
+    if cfg!(target_os = "macos") {
+        roots.push(home.join("Library/Caches"));
+    } else if let Some(cache) = dirs::cache_dir() {
+        roots.push(cache);
+    }
+    roots.push(home.join(".cargo/registry/cache"));
+    roots.push(home.join(".npm/_cacache"));
+    roots.retain(|root| root.is_dir());
+    roots
+}
+
+/// Which artifact a project directory is, if any. Virtualenvs carry their marker inside
+fn classify_artifact(path: &Path) -> Option<(CleanupCategory, &'static str)> {
+    let name = path.file_name()?.to_str()?;
+    let parent = path.parent()?;
+    PROJECT_ARTIFACTS.iter().find_map(|(artifact, marker, category, regenerate)| {
+        let marked = if *category == CleanupCategory::VirtualEnv {
+            path.join(marker).is_file()
+        } else {
+            parent.join(marker).is_file()
+        };
+        (name == *artifact && marked).then_some((*category, *regenerate))
+    })
+}
+
+/// Whether `path` is something a scan would offer, checked again right before removing it
+fn recognized(path: &Path, category: CleanupCategory) -> bool {
+    let is_real_dir = std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir());
+    if !is_real_dir {
+        return false;
+    }
+    match category {
+        CleanupCategory::Cache => path.parent().is_some_and(|parent| cache_roots().iter().any(|root| root == parent)),
+        CleanupCategory::DockerImages => false,
+        _ => classify_artifact(path).is_some_and(|(found, _)| found == category),
+    }
+}
+
+/// Bytes under `path` without following links; the flag is set when the count stopped early
+fn directory_size(path: &Path) -> (u64, bool, Option<SystemTime>) {
+    let mut size = 0;
+    let mut newest = std::fs::symlink_metadata(path).and_then(|m| m.modified()).ok();
+    let walker = ignore::WalkBuilder::new(path).standard_filters(false).follow_links(false).build();
+    for (count, entry) in walker.filter_map(|entry| entry.ok()).enumerate() {
+        if count >= MAX_ENTRIES_PER_ITEM {
+            return (size, true, newest);
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+        // Directory mtimes change whenever something is added or removed below them
+        if metadata.is_dir() {
+            newest = newest.max(metadata.modified().ok());
+        }
+    }
+    (size, false, newest)
+}
+
+fn is_stale(last_modified: Option<SystemTime>) -> bool {
+    last_modified
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= STALE_AFTER)
+}
+
+fn path_item(
+    path: &Path,
+    category: CleanupCategory,
+    project: Option<&Path>,
+    description: String,
+    regenerate_with: Option<&str>,
+) -> CleanupItem {
+    let (size_bytes, size_estimated, last_modified) = directory_size(path);
+    CleanupItem {
+        id: path.to_string_lossy().to_string(),
+        category,
+        path: Some(path.to_string_lossy().to_string()),
+        project: project.map(|p| p.to_string_lossy().to_string()),
+        description,
+        size_bytes,
+        size_estimated,
+        last_modified: last_modified.map(DateTime::<Utc>::from),
+        regenerate_with: regenerate_with.map(str::to_string),
+        selected: is_stale(last_modified),
+    }
+}
+
+/// Artifact directories at the top of each project
+fn project_items(projects: &[PathBuf]) -> Vec<CleanupItem> {
+    let mut items = Vec::new();
+    for project in projects {
+        for (artifact, _, _, _) in PROJECT_ARTIFACTS {
+            let path = project.join(artifact);
+            let Some((category, regenerate)) = classify_artifact(&path) else { continue };
+            if !recognized(&path, category) {
+                continue;
+            }
+            let name = project.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            items.push(path_item(&path, category, Some(project), format!("{} in {}", artifact, name), Some(regenerate)));
+        }
+    }
+    items
+}
+
+fn cache_items() -> Vec<CleanupItem> {
+    let mut items = Vec::new();
+    for root in cache_roots() {
+        let Ok(entries) = std::fs::read_dir(&root) else { continue };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if !recognized(&path, CleanupCategory::Cache) {
+                continue;
+            }
+            let item = path_item(&path, CleanupCategory::Cache, None, format!("Cache {}", path.display()), None);
+            if item.size_bytes >= MIN_CACHE_BYTES {
+                // Caches are shared by running programs, so they wait for the user to pick them
+                items.push(CleanupItem { selected: false, ..item });
+            }
+        }
+    }
+    items
+}
+
+/// "1.23GB", "512MB", "12.5kB" as printed by `docker images`
+fn parse_docker_size(size: &str) -> u64 {
+    let size = size.trim();
+    let split = size.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(size.len());
+    let value: f64 = size[..split].parse().unwrap_or(0.0);
+    let unit = match size[split..].to_ascii_lowercase().as_str() {
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "tb" => 1e12,
+        _ => 1.0,
+    };
+    (value * unit) as u64
+}
+
+/// Dangling images as one item; none when Docker is not installed or not running
+fn docker_item() -> Option<CleanupItem> {
+    let output = Command::new("docker")
+        .args(["images", "--filter", "dangling=true", "--format", "{{.Size}}"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let sizes: Vec<u64> = String::from_utf8_lossy(&output.stdout).lines().map(parse_docker_size).collect();
+    if sizes.is_empty() {
+        return None;
+    }
+    Some(CleanupItem {
+        id: DOCKER_ID.to_string(),
+        category: CleanupCategory::DockerImages,
+        path: None,
+        project: None,
+        description: format!("{} dangling Docker images", sizes.len()),
+        size_bytes: sizes.iter().sum(),
+        size_estimated: true,
+        last_modified: None,
+        regenerate_with: None,
+        selected: true,
+    })
+}
+
+/// Sized cleanup candidates across `projects`, caches and Docker, largest first
+pub fn scan(projects: &[PathBuf]) -> CleanupPlan {
+    let mut items = project_items(projects);
+    items.extend(cache_items());
+    items.extend(docker_item());
+    items.sort_by_key(|item| std::cmp::Reverse(item.size_bytes));
+    plan(items)
+}
+
+/// Totals for a plan, e.g. after the user changed which items are selected
+pub fn plan(items: Vec<CleanupItem>) -> CleanupPlan {
+    CleanupPlan {
+        total_bytes: items.iter().map(|item| item.size_bytes).sum(),
+        selected_bytes: items.iter().filter(|item| item.selected).map(|item| item.size_bytes).sum(),
+        items,
+        created_at: Utc::now(),
+    }
+}
+
+fn remove(item: &CleanupItem, use_trash: bool) -> Result<Option<String>, String> {
+    if item.category == CleanupCategory::DockerImages {
+        let output = Command::new("docker").args(["image", "prune", "--force"]).output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        return Ok(None);
+    }
+    let path = Path::new(item.path.as_deref().ok_or("Item has no path")?);
+    // The plan came back from the user; only remove what a scan would still offer
+    if !recognized(path, item.category) {
+        return Err(format!("{} is no longer a recognized {:?} directory", path.display(), item.category));
+    }
+    if use_trash {
+        trash::delete(path).map_err(|e| format!("Could not move to trash: {}", e))?;
+        // Space is only given back once the trash is emptied
+        Ok(Some(format!("Restore {} from the trash", path.display())))
+    } else {
+        std::fs::remove_dir_all(path).map_err(|e| e.to_string())?;
+        Ok(item.regenerate_with.as_ref().map(|command| format!("Recreate with `{}`", command)))
+    }
+}
+
+/// Remove the selected items, reporting progress after each one
+pub fn execute(plan: &CleanupPlan, use_trash: bool, mut on_progress: impl FnMut(CleanupProgress)) -> CleanupReport {
+    let selected: Vec<&CleanupItem> = plan.items.iter().filter(|item| item.selected).collect();
+    let mut report = CleanupReport { outcomes: Vec::new(), freed_bytes: 0, failed: 0 };
+    for (index, item) in selected.iter().enumerate() {
+        let outcome = match remove(item, use_trash) {
+            Ok(undo) => CleanupOutcome { item_id: item.id.clone(), freed_bytes: item.size_bytes, undo, error: None },
+            Err(error) => CleanupOutcome { item_id: item.id.clone(), freed_bytes: 0, undo: None, error: Some(error) },
+        };
+        report.freed_bytes += outcome.freed_bytes;
+        report.failed += usize::from(outcome.error.is_some());
+        report.outcomes.push(outcome);
+        on_progress(CleanupProgress {
+            item_id: item.id.clone(),
+            completed: index + 1,
+            total: selected.len(),
+            freed_bytes: report.freed_bytes,
+        });
+    }
+    report
+}
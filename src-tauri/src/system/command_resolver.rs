@@ -0,0 +1,216 @@
+// Command resolution with provenance ("which" that explains itself)
+// Walks the same order the terminal uses - alias, builtin, shell function,
+// PATH binary - and records why each step did or did not match
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::path_inspector;
+
+/// Shell builtins that exist without any binary on PATH
+const SHELL_BUILTINS: [&str; 38] = [
+    "alias", "bg", "bind", "break", "builtin", "command", "continue", "declare", "dirs", "echo",
+    "eval", "exec", "export", "false", "fg", "getopts", "hash", "help", "jobs", "kill", "let",
+    "local", "popd", "printf", "pushd", "read", "return", "set", "shift", "source", "test",
+    "trap", "true", "type", "ulimit", "umask", "unalias", "unset",
+];
+
+const SHELL_CONFIG_FILES: [&str; 6] = [".bashrc", ".bash_aliases", ".bash_profile", ".zshrc", ".profile", ".config/fish/config.fish"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionKind {
+    Alias,
+    Builtin,
+    ShellBuiltin,
+    ShellFunction,
+    Binary,
+    NotFound,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionStep {
+    pub kind: ResolutionKind,
+    pub matched: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResolution {
+    pub name: String,
+    pub kind: ResolutionKind,
+    /// Alias expansion, function source or binary path
+    pub target: Option<String>,
+    pub source: Option<String>,
+    pub reason: String,
+    /// Definitions further down the chain that this one hides
+    pub shadowed: Vec<String>,
+    pub steps: Vec<ResolutionStep>,
+}
+
+struct ShellDefinition {
+    kind: ResolutionKind,
+    body: String,
+    source: String,
+}
+
+/// Aliases and functions defined in the user's shell startup files
+fn shell_definitions(name: &str) -> Vec<ShellDefinition> {
+    let home = match dirs::home_dir() {
+        Some(home) => home,
+        None => return vec![],
+    };
+    let escaped = regex::escape(name);
+    let alias = Regex::new(&format!(r#"^\s*alias\s+{}=(['"]?)(.*)$"#, escaped)).unwrap();
+    let fish_alias = Regex::new(&format!(r#"^\s*alias\s+{}\s+(['"]?)(.*)$"#, escaped)).unwrap();
+    let function = Regex::new(&format!(r"^\s*(?:function\s+{0}\b|{0}\s*\(\s*\))", escaped)).unwrap();
+
+    let mut definitions = Vec::new();
+    for file in SHELL_CONFIG_FILES {
+        let path: PathBuf = home.join(file);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        for (index, line) in content.lines().enumerate() {
+            let source = format!("{}:{}", path.display(), index + 1);
+            if let Some(caps) = alias.captures(line).or_else(|| fish_alias.captures(line)) {
+                let quote = caps.get(1).map(|q| q.as_str()).unwrap_or("");
+                let body = caps[2].trim_end();
+                let body = if quote.is_empty() { body } else { body.trim_end_matches(quote) };
+                definitions.push(ShellDefinition {
+                    kind: ResolutionKind::Alias,
+                    body: body.to_string(),
+                    source,
+                });
+            } else if function.is_match(line) {
+                definitions.push(ShellDefinition {
+                    kind: ResolutionKind::ShellFunction,
+                    body: line.trim().to_string(),
+                    source,
+                });
+            }
+        }
+    }
+    definitions
+}
+
+/// Resolve a command name the way the terminal would run it
+pub fn resolve_command(name: &str, path_var: &str) -> CommandResolution {
+    let name = name.trim();
+    let definitions = shell_definitions(name);
+    let alias = definitions.iter().rev().find(|d| d.kind == ResolutionKind::Alias);
+    let function = definitions.iter().rev().find(|d| d.kind == ResolutionKind::ShellFunction);
+    let is_builtin = crate::terminal::BUILTIN_COMMANDS.contains(&name);
+    let is_shell_builtin = SHELL_BUILTINS.contains(&name);
+    let binaries = path_inspector::find_all(name, path_var);
+
+    let steps = vec![
+        ResolutionStep {
+            kind: ResolutionKind::Alias,
+            matched: alias.is_some(),
+            detail: match alias {
+                Some(alias) => format!("alias {}='{}' ({})", name, alias.body, alias.source),
+                None => "no alias in shell config".to_string(),
+            },
+        },
+        ResolutionStep {
+            kind: ResolutionKind::Builtin,
+            matched: is_builtin,
+            detail: if is_builtin {
+                "handled by pH7Console itself".to_string()
+            } else {
+                "not a console builtin".to_string()
+            },
+        },
+        ResolutionStep {
+            kind: ResolutionKind::ShellBuiltin,
+            matched: is_shell_builtin,
+            detail: if is_shell_builtin { "shell builtin".to_string() } else { "not a shell builtin".to_string() },
+        },
+        ResolutionStep {
+            kind: ResolutionKind::ShellFunction,
+            matched: function.is_some(),
+            detail: match function {
+                Some(function) => format!("function defined at {}", function.source),
+                None => "no shell function in shell config".to_string(),
+            },
+        },
+        ResolutionStep {
+            kind: ResolutionKind::Binary,
+            matched: !binaries.is_empty(),
+            detail: match binaries.first() {
+                Some(binary) => format!("{} (first of {} on PATH)", binary.display(), binaries.len()),
+                None => "not found on PATH".to_string(),
+            },
+        },
+    ];
+
+    let mut shadowed: Vec<String> = Vec::new();
+    let (kind, target, source, reason) = if let Some(alias) = alias {
+        (
+            ResolutionKind::Alias,
+            Some(alias.body.clone()),
+            Some(alias.source.clone()),
+            "aliases expand before anything else".to_string(),
+        )
+    } else if is_builtin {
+        (
+            ResolutionKind::Builtin,
+            None,
+            None,
+            "console builtins run before PATH lookup".to_string(),
+        )
+    } else if is_shell_builtin {
+        (ResolutionKind::ShellBuiltin, None, None, "shell builtins take precedence over PATH".to_string())
+    } else if let Some(function) = function {
+        (
+            ResolutionKind::ShellFunction,
+            Some(function.body.clone()),
+            Some(function.source.clone()),
+            "shell functions take precedence over PATH".to_string(),
+        )
+    } else if let Some(binary) = binaries.first() {
+        let entry = binary.parent().map(|p| p.display().to_string()).unwrap_or_default();
+        (
+            ResolutionKind::Binary,
+            Some(binary.display().to_string()),
+            Some(entry.clone()),
+            format!("{} is the first PATH directory containing '{}'", entry, name),
+        )
+    } else {
+        (ResolutionKind::NotFound, None, None, format!("'{}' is not an alias, builtin, function or binary on PATH", name))
+    };
+
+    // Everything that would have run if the winner did not exist
+    if kind != ResolutionKind::Binary {
+        shadowed.extend(binaries.iter().map(|b| b.display().to_string()));
+    } else {
+        shadowed.extend(binaries.iter().skip(1).map(|b| b.display().to_string()));
+    }
+    if kind == ResolutionKind::Alias {
+        if let Some(function) = function {
+            shadowed.insert(0, format!("function at {}", function.source));
+        }
+    }
+
+    CommandResolution {
+        name: name.to_string(),
+        kind,
+        target,
+        source,
+        reason,
+        shadowed,
+        steps,
+    }
+}
+
+/// Cheap check used by natural-language detection: is this word something runnable?
+pub fn is_known_command(name: &str) -> bool {
+    let path_var = std::env::var("PATH").unwrap_or_default();
+    crate::terminal::BUILTIN_COMMANDS.contains(&name)
+        || SHELL_BUILTINS.contains(&name)
+        || !path_inspector::find_all(name, &path_var).is_empty()
+        || !shell_definitions(name).is_empty()
+}
@@ -0,0 +1,206 @@
+// Guest / demo mode
+// Runs the app in a throwaway `demo` profile filled with synthetic data: a small
+// workspace of made-up projects, learning data and per-directory usage built
+// from a scripted command history, and that history itself. Commands are not
+// run; the executor answers from canned output instead, so agent mode, the
+// analytics views and suggestions can be shown or captured without any of the
+// user's real commands, paths or files appearing. Each start of demo mode
+// begins from fresh data.
+
+use chrono::{Duration, Utc};
+use std::path::{Path, PathBuf};
+
+use crate::terminal::outcome::ExecutionOutcome;
+use crate::terminal::CommandExecution;
+
+pub const DEMO_PROFILE: &str = "demo";
+const SESSION_ID: &str = "demo";
+/// Written last when seeding, so an interrupted seed is redone
+const SEEDED_MARKER: &str = ".seeded";
+
+/// A scripted step: the command, the project it runs in, whether it succeeds and how long it takes
+struct Step {
+    command: &'static str,
+    project: &'static str,
+    success: bool,
+    duration_ms: u64,
+}
+
+const fn step(command: &'static str, project: &'static str, success: bool, duration_ms: u64) -> Step {
+    Step { command, project, success, duration_ms }
+}
+
+/// One working day, repeated with small variations to build up the synthetic history
+const SCRIPT: &[Step] = &[
+    step("git status", "acme-web", true, 40),
+    step("git pull", "acme-web", true, 900),
+    step("npm install", "acme-web", true, 8_200),
+    step("npm run dev", "acme-web", true, 2_100),
+    step("npm test", "acme-web", false, 6_400),
+    step("npm test", "acme-web", true, 6_100),
+    step("git add .", "acme-web", true, 30),
+    step("git commit -m \"Fix checkout form validation\"", "acme-web", true, 120),
+    step("git push", "acme-web", true, 1_500),
+    step("cargo build", "acme-api", true, 14_000),
+    step("cargo test", "acme-api", false, 9_800),
+    step("cargo test", "acme-api", true, 9_300),
+    step("cargo clippy", "acme-api", true, 7_200),
+    step("docker compose up -d", "acme-api", true, 3_300),
+    step("docker ps", "acme-api", true, 80),
+    step("git status", "acme-api", true, 40),
+    step("git commit -am \"Add order export endpoint\"", "acme-api", true, 110),
+    step("npm run build", "acme-web", true, 11_500),
+    step("ls -la", "acme-web", true, 20),
+];
+const DAYS: usize = 5;
+
+pub fn is_active() -> bool {
+    super::app_dirs::active_profile() == DEMO_PROFILE
+}
+
+/// The made-up projects demo sessions start in
+pub fn workspace() -> PathBuf {
+    crate::ai::data_directory().join("demo-workspace")
+}
+
+fn project_dir(project: &str) -> PathBuf {
+    workspace().join(project)
+}
+
+/// Where new sessions open in demo mode
+pub fn start_directory() -> PathBuf {
+    project_dir("acme-web")
+}
+
+/// Fill the demo profile with synthetic data; call at startup in demo mode, before anything loads
+pub fn seed() -> Result<(), String> {
+    let workspace = workspace();
+    if workspace.join(SEEDED_MARKER).exists() {
+        return Ok(());
+    }
+    write_workspace(&workspace)?;
+
+    let mut learning = crate::ai::learning_engine::LearningEngine::new(crate::ai::data_directory());
+    let mut usage = crate::terminal::dir_usage::DirectoryUsageStore::load();
+    for step in scripted_steps() {
+        let dir = project_dir(step.project).to_string_lossy().to_string();
+        let context = format!("Working Directory: {}\nShell: /bin/zsh\n", dir);
+        learning.learn_from_interaction(step.command.to_string(), canned(step.command, step.success), context, step.success, Some(step.duration_ms));
+        learning.track_session_workflow(SESSION_ID, step.command);
+        usage.record(SESSION_ID, &dir, step.command, step.success);
+    }
+    learning.save_data();
+    usage.flush();
+    crate::system::fs_tasks::flush_writes();
+
+    std::fs::write(workspace.join(SEEDED_MARKER), Utc::now().to_rfc3339()).map_err(|e| e.to_string())
+}
+
+fn scripted_steps() -> impl Iterator<Item = &'static Step> {
+    (0..DAYS).flat_map(|day| SCRIPT.iter().enumerate().filter(move |(i, _)| (i + day) % 7 != 0).map(|(_, step)| step))
+}
+
+/// The scripted history as executions, oldest first, ending a few minutes ago
+pub fn history() -> Vec<CommandExecution> {
+    let steps: Vec<&Step> = scripted_steps().collect();
+    let start = Utc::now() - Duration::minutes(5) - Duration::minutes(12 * steps.len() as i64);
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let exit_code = if step.success { 0 } else { 1 };
+            CommandExecution {
+                id: uuid::Uuid::new_v4().to_string(),
+                command: step.command.to_string(),
+                output: canned(step.command, step.success),
+                exit_code: Some(exit_code),
+                duration_ms: step.duration_ms,
+                timestamp: start + Duration::minutes(12 * i as i64),
+                outcome: ExecutionOutcome::Exited(exit_code),
+                coverage: None,
+                structured: None,
+                overrides: None,
+                styled: None,
+                session_id: None,
+            }
+        })
+        .collect()
+}
+
+/// Stand-in for running a command: canned output, or the synthetic files inside the demo workspace
+pub fn execute(program: &str, args: &[&str], working_dir: &str) -> (String, String, ExecutionOutcome) {
+    let command = std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+    let inside = Path::new(working_dir).starts_with(workspace());
+    let listing = match program {
+        "ls" if inside => std::fs::read_dir(working_dir).ok().map(|entries| {
+            let mut names: Vec<String> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| args.iter().any(|a| a.starts_with('-') && a.contains('a')) || !name.starts_with('.'))
+                .collect();
+            names.sort();
+            names.join("\n")
+        }),
+        "cat" if inside => args
+            .iter()
+            .filter(|a| !a.starts_with('-'))
+            .map(|file| Path::new(working_dir).join(file))
+            .filter(|path| path.starts_with(workspace()))
+            .map(|path| std::fs::read_to_string(path).ok())
+            .collect::<Option<Vec<_>>>()
+            .map(|files| files.concat()),
+        "cd" => Some(String::new()),
+        _ => None,
+    };
+    let output = listing.unwrap_or_else(|| canned(&command, true));
+    (output, String::new(), ExecutionOutcome::Exited(0))
+}
+
+/// Output for a scripted command, or a note that nothing was run
+fn canned(command: &str, success: bool) -> String {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let output = match words.as_slice() {
+        ["git", "status", ..] => "On branch main\nYour branch is up to date with 'origin/main'.\n\nChanges not staged for commit:\n  modified:   src/checkout/Form.tsx\n\nno changes added to commit (use \"git add\" and/or \"git commit -a\")",
+        ["git", "pull", ..] => "Updating 3f2a9c1..8b41d07\nFast-forward\n src/checkout/Form.tsx | 12 ++++++++----\n 1 file changed, 8 insertions(+), 4 deletions(-)",
+        ["git", "push", ..] => "To github.com:acme/acme-web.git\n   8b41d07..c9e3f12  main -> main",
+        ["git", "commit", ..] => "[main c9e3f12] Fix checkout form validation\n 2 files changed, 18 insertions(+), 5 deletions(-)",
+        ["git", "log", ..] => "c9e3f12 Fix checkout form validation\n8b41d07 Add order export endpoint\n3f2a9c1 Update dependencies",
+        ["git", "branch", ..] => "* main\n  feature/order-export\n  fix/checkout-validation",
+        ["git", ..] => "",
+        ["npm", "install", ..] => "added 1284 packages, and audited 1285 packages in 8s\n\nfound 0 vulnerabilities",
+        ["npm", "test", ..] | ["npm", "run", "test", ..] if !success => "FAIL src/checkout/Form.test.tsx\n  ● validates the postcode\n\nTests: 1 failed, 47 passed, 48 total",
+        ["npm", "test", ..] | ["npm", "run", "test", ..] => "PASS src/checkout/Form.test.tsx\n\nTests: 48 passed, 48 total\nTime: 6.1 s",
+        ["npm", "run", "dev", ..] => "VITE v5.4.2  ready in 412 ms\n\n  ➜  Local:   http://localhost:5173/",
+        ["npm", "run", "build", ..] => "vite v5.4.2 building for production...\n✓ 412 modules transformed.\ndist/index.html  0.46 kB\ndist/assets/index.js  182.31 kB\n✓ built in 11.50s",
+        ["cargo", "test", ..] if !success => "running 24 tests\ntest orders::export::csv_escapes_quotes ... FAILED\n\ntest result: FAILED. 23 passed; 1 failed",
+        ["cargo", "test", ..] => "running 24 tests\n\ntest result: ok. 24 passed; 0 failed; 0 ignored",
+        ["cargo", "build" | "check" | "clippy", ..] => "   Compiling acme-api v0.3.0\n    Finished `dev` profile [unoptimized + debuginfo] target(s) in 14.02s",
+        ["docker", "compose", ..] => "[+] Running 2/2\n ✔ Container acme-api-db-1     Started\n ✔ Container acme-api-redis-1  Started",
+        ["docker", "ps", ..] => "CONTAINER ID   IMAGE         STATUS         PORTS                    NAMES\n4c1d2e3f4a5b   postgres:16   Up 2 minutes   0.0.0.0:5432->5432/tcp   acme-api-db-1\n9a8b7c6d5e4f   redis:7       Up 2 minutes   0.0.0.0:6379->6379/tcp   acme-api-redis-1",
+        ["ls", ..] => "README.md\npackage.json\nsrc",
+        _ => return format!("🎭 Demo mode: `{}` was not run", command),
+    };
+    output.to_string()
+}
+
+fn write_workspace(workspace: &Path) -> Result<(), String> {
+    let files: [(&str, &str); 9] = [
+        ("acme-web/package.json", "{\n  \"name\": \"acme-web\",\n  \"version\": \"1.4.0\",\n  \"scripts\": { \"dev\": \"vite\", \"build\": \"vite build\", \"test\": \"vitest run\" }\n}\n"),
+        ("acme-web/README.md", "# acme-web\n\nStorefront for the Acme demo shop.\n"),
+        ("acme-web/src/App.tsx", "export default function App() {\n  return <h1>Acme</h1>;\n}\n"),
+        ("acme-web/.git/HEAD", "ref: refs/heads/main\n"),
+        ("acme-api/Cargo.toml", "[package]\nname = \"acme-api\"\nversion = \"0.3.0\"\nedition = \"2021\"\n"),
+        ("acme-api/src/main.rs", "fn main() {\n    println!(\"acme-api\");\n}\n"),
+        ("acme-api/docker-compose.yml", "services:\n  db:\n    image: postgres:16\n    ports:\n      - \"5432:5432\"\n  redis:\n    image: redis:7\n    ports:\n      - \"6379:6379\"\n"),
+        ("acme-api/.git/HEAD", "ref: refs/heads/main\n"),
+        ("notes.md", "# Demo notes\n\nEverything here is made up for demos and screenshots.\n"),
+    ];
+    for (name, content) in files {
+        let path = workspace.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
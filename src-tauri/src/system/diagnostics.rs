@@ -0,0 +1,312 @@
+// Self-diagnostics
+// Checks everything the terminal depends on, end to end: the login shell runs,
+// a PTY can be opened, the data directory is writable, model files are intact,
+// saved learning data and settings still parse and there is room for models.
+// Each check reports what it found and, when it did not pass, what to do about it.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Below this much free space, downloading or updating models will fail
+const MIN_FREE_BYTES: u64 = 200 * 1024 * 1024;
+/// Below this much, warn before it gets to that
+const LOW_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+const SHELL_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub remediation: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    /// The worst status of any check
+    pub status: CheckStatus,
+    pub checks: Vec<DiagnosticCheck>,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DiagnosticsReport {
+    pub fn summary(&self) -> String {
+        let mut lines = vec![match self.status {
+            CheckStatus::Pass => "🩺 All checks passed".to_string(),
+            CheckStatus::Warn => "🩺 Everything works, with warnings".to_string(),
+            CheckStatus::Fail => "🩺 Some checks failed".to_string(),
+        }];
+        for check in &self.checks {
+            let icon = match check.status {
+                CheckStatus::Pass => "✅",
+                CheckStatus::Warn => "⚠️",
+                CheckStatus::Fail => "❌",
+            };
+            lines.push(format!("{} {}: {}", icon, check.name, check.detail));
+            if let Some(remediation) = &check.remediation {
+                lines.push(format!("   → {}", remediation));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+struct Outcome {
+    status: CheckStatus,
+    detail: String,
+    remediation: Option<String>,
+}
+
+impl Outcome {
+    fn pass(detail: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Pass, detail: detail.into(), remediation: None }
+    }
+
+    fn warn(detail: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Warn, detail: detail.into(), remediation: Some(remediation.into()) }
+    }
+
+    fn fail(detail: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Fail, detail: detail.into(), remediation: Some(remediation.into()) }
+    }
+}
+
+fn timed(name: &str, check: impl FnOnce() -> Outcome) -> DiagnosticCheck {
+    let start = Instant::now();
+    let outcome = check();
+    DiagnosticCheck {
+        name: name.to_string(),
+        status: outcome.status,
+        detail: outcome.detail,
+        remediation: outcome.remediation,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// "run diagnostics", "health check", "doctor"
+pub fn is_diagnostics_request(request: &str) -> bool {
+    let pattern = regex::Regex::new(r"(?i)^\s*(?:please\s+)?(?:run\s+(?:a\s+|the\s+)?)?(?:self[- ]?)?(?:diagnostics|diagnose|health[- ]?check|self[- ]?test|doctor)\s*[.!?]?\s*$").unwrap();
+    pattern.is_match(request)
+}
+
+/// Run every check. Blocking; `gguf_models` are the local model files routes point at
+pub fn run(shell: &str, gguf_models: &[String]) -> DiagnosticsReport {
+    let data_dir = crate::ai::data_directory();
+    let mut checks = vec![
+        timed("Shell", || check_shell(shell)),
+        timed("PTY", check_pty),
+        timed("Data directory", || check_writable(&data_dir)),
+        timed("Config directory", || check_writable(&crate::ai::config_directory())),
+        timed("Disk space", || check_disk_space(&data_dir)),
+        timed("Learning data", || check_learning_data(&data_dir)),
+        timed("Settings files", || check_settings(&crate::ai::config_directory())),
+        timed("Embedding model", || check_model_directory(&crate::models::sentence_encoder::model_directory())),
+    ];
+    for model in gguf_models {
+        checks.push(timed(&format!("Model {}", model), || check_gguf(Path::new(model))));
+    }
+    DiagnosticsReport {
+        status: checks.iter().map(|check| check.status).max().unwrap_or(CheckStatus::Pass),
+        checks,
+        generated_at: chrono::Utc::now(),
+    }
+}
+
+fn check_shell(shell: &str) -> Outcome {
+    let (program, args) = crate::terminal::shell_quote::ShellKind::from_shell(shell).invocation(shell, "echo ok");
+    let mut command = Command::new(program);
+    command.args(args);
+    let child = command.stdout(std::process::Stdio::piped()).stderr(std::process::Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return Outcome::fail(format!("{} could not be started: {}", shell, e), "Set SHELL to an installed shell, e.g. /bin/bash"),
+    };
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Outcome::pass(format!("{} answers", shell)),
+            Ok(Some(status)) => {
+                return Outcome::fail(
+                    format!("{} exited with {}", shell, status),
+                    "Check the shell's startup files for errors by running it in another terminal",
+                )
+            }
+            Ok(None) if start.elapsed() > SHELL_TIMEOUT => {
+                let _ = child.kill();
+                return Outcome::fail(
+                    format!("{} did not answer within {}s", shell, SHELL_TIMEOUT.as_secs()),
+                    "A startup file may be waiting for input; check .bashrc/.zshrc for prompts",
+                );
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Outcome::fail(format!("Could not wait for {}: {}", shell, e), "Try restarting pH7Console"),
+        }
+    }
+}
+
+fn check_pty() -> Outcome {
+    let size = portable_pty::PtySize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0 };
+    match portable_pty::native_pty_system().openpty(size) {
+        Ok(_) => Outcome::pass("A pseudo terminal could be opened"),
+        Err(e) => Outcome::fail(
+            format!("Opening a pseudo terminal failed: {}", e),
+            if cfg!(windows) {
+                "ConPTY needs Windows 10 1809 or later"
+            } else {
+                "The PTY limit may be reached (see /proc/sys/kernel/pty/max) or /dev/pts is not mounted"
+            },
+        ),
+    }
+}
+
+fn check_writable(dir: &Path) -> Outcome {
+    let probe = dir.join(".write-check");
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Outcome::pass(format!("{} is writable", dir.display())),
+        Err(e) => Outcome::fail(
+            format!("Cannot write to {}: {}", dir.display(), e),
+            format!("Fix the permissions of {} or free up space on its disk", dir.display()),
+        ),
+    }
+}
+
+fn check_disk_space(dir: &Path) -> Outcome {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    // The disk holding the directory is the one with the longest matching mount point
+    let Some(disk) = disks
+        .list()
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+    else {
+        return Outcome::warn("Could not tell which disk holds the data directory", "Make sure at least 1 GB is free for models");
+    };
+    let free = disk.available_space();
+    let free_text = format!("{:.1} GB free on {}", free as f64 / 1e9, disk.mount_point().display());
+    if free < MIN_FREE_BYTES {
+        Outcome::fail(free_text, "Free up space; model downloads and learning data saves will fail")
+    } else if free < LOW_FREE_BYTES {
+        Outcome::warn(free_text, "Free up some space before adding larger local models")
+    } else {
+        Outcome::pass(free_text)
+    }
+}
+
+fn check_learning_data(dir: &Path) -> Outcome {
+    let file = dir.join("learning_data.json");
+    let Ok(data) = std::fs::read_to_string(&file) else {
+        return Outcome::pass("No learning data yet");
+    };
+    match serde_json::from_str::<serde_json::Value>(&data) {
+        Ok(_) => Outcome::pass(format!("{} parses ({} KB)", file.display(), data.len() / 1024)),
+        Err(e) => Outcome::fail(
+            format!("{} is corrupt: {}", file.display(), e),
+            format!("Move {} aside; learning starts over and the rest keeps working", file.display()),
+        ),
+    }
+}
+
+fn check_settings(dir: &Path) -> Outcome {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Outcome::pass("No settings saved yet");
+    };
+    let mut checked = 0;
+    let mut broken = Vec::new();
+    for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+        if path.extension().map_or(true, |e| e != "json") || path.file_name().is_some_and(|n| n == "learning_data.json") {
+            continue;
+        }
+        checked += 1;
+        let parses = std::fs::read_to_string(&path).ok().is_some_and(|data| serde_json::from_str::<serde_json::Value>(&data).is_ok());
+        if !parses {
+            broken.push(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+        }
+    }
+    if broken.is_empty() {
+        Outcome::pass(format!("{} settings files parse", checked))
+    } else {
+        Outcome::warn(
+            format!("Unreadable, defaults are used instead: {}", broken.join(", ")),
+            format!("Delete or fix these files in {}", dir.display()),
+        )
+    }
+}
+
+/// Pinned files are compared against the sha256 the app ships for them; the others come from
+/// the same pinned model revision and only need to parse
+fn check_model_directory(dir: &Path) -> Outcome {
+    use crate::tools::checksum::{verify_checksum, HashAlgorithm};
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Outcome::pass("Not downloaded yet; it is fetched on first use");
+    };
+    let mut files: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).filter(|p| p.is_file()).collect();
+    files.sort();
+
+    if let Some(partial) = files.iter().find(|p| p.extension().is_some_and(|e| e == "partial")) {
+        return Outcome::warn(
+            format!("An interrupted download was left behind: {}", partial.display()),
+            "Delete the .partial file; it is downloaded again on next use",
+        );
+    }
+    for file in files.iter().filter(|p| p.extension().is_some_and(|e| e == "json")) {
+        let parses = std::fs::read_to_string(file).ok().is_some_and(|data| serde_json::from_str::<serde_json::Value>(&data).is_ok());
+        if !parses {
+            return Outcome::fail(format!("{} is corrupt", file.display()), format!("Delete {} to download the model again", dir.display()));
+        }
+    }
+
+    let mut changed = Vec::new();
+    let mut checked = 0;
+    for (name, expected) in crate::models::sentence_encoder::MODEL_FILES {
+        let Some(expected) = expected else { continue };
+        let file = dir.join(name);
+        if !file.is_file() {
+            changed.push(name);
+            continue;
+        }
+        match verify_checksum(&file, Some(expected), Some(HashAlgorithm::Sha256), |_, _| {}) {
+            Ok(verification) if verification.matches => checked += 1,
+            Ok(_) => changed.push(name),
+            Err(e) => return Outcome::fail(format!("Cannot read {}: {}", file.display(), e), "Check the file's permissions"),
+        }
+    }
+    if changed.is_empty() {
+        Outcome::pass(format!("{} files match their pinned checksums", checked))
+    } else {
+        Outcome::fail(
+            format!("Missing or not the pinned model: {}", changed.join(", ")),
+            format!("Delete {} to download the model again", dir.display()),
+        )
+    }
+}
+
+/// GGUF files start with the "GGUF" magic
+fn check_gguf(path: &Path) -> Outcome {
+    use std::io::Read;
+
+    let mut magic = [0u8; 4];
+    match std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic)) {
+        Ok(()) if &magic == b"GGUF" => Outcome::pass(format!("{} is a GGUF model", path.display())),
+        Ok(()) => Outcome::fail(
+            format!("{} is not a GGUF file", path.display()),
+            "Download the model again or point the route at the right file",
+        ),
+        Err(e) => Outcome::fail(format!("Cannot read {}: {}", path.display(), e), "Fix the model path in the model routes"),
+    }
+}
@@ -0,0 +1,128 @@
+// Shared cache of directory listings
+// Path completions, smart context and the file sidebar list the same few
+// directories over and over. Listings are kept for a short time, dropped when the
+// directory's mtime changes, and invalidated right away by a file system watcher
+// on every cached directory.
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+const TTL: Duration = Duration::from_secs(10);
+const CAPACITY: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct DirEntryInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+struct Listing {
+    entries: Arc<Vec<DirEntryInfo>>,
+    modified: Option<SystemTime>,
+    loaded: Instant,
+}
+
+pub struct DirectoryCache {
+    listings: Mutex<HashMap<PathBuf, Listing>>,
+    /// None when the platform watcher could not be started; mtime checks still apply
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+/// The process-wide cache
+pub fn cache() -> &'static DirectoryCache {
+    static CACHE: OnceLock<DirectoryCache> = OnceLock::new();
+    CACHE.get_or_init(DirectoryCache::new)
+}
+
+fn modified(dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(dir).and_then(|m| m.modified()).ok()
+}
+
+impl DirectoryCache {
+    fn new() -> Self {
+        let watcher = notify::recommended_watcher(|event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            for path in &event.paths {
+                cache().invalidate(path);
+                if let Some(parent) = path.parent() {
+                    cache().invalidate(parent);
+                }
+            }
+        });
+        if let Err(e) = &watcher {
+            println!("⚠️ Directory watcher unavailable, relying on mtime checks: {}", e);
+        }
+        Self {
+            listings: Mutex::new(HashMap::new()),
+            watcher: Mutex::new(watcher.ok()),
+        }
+    }
+
+    fn listings(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Listing>> {
+        self.listings.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Entries of a directory, unsorted, from cache when still valid
+    pub fn list(&self, dir: &Path) -> std::io::Result<Arc<Vec<DirEntryInfo>>> {
+        let current_modified = modified(dir);
+        if let Some(listing) = self.listings().get(dir) {
+            if listing.loaded.elapsed() < TTL && listing.modified == current_modified {
+                return Ok(listing.entries.clone());
+            }
+        }
+
+        let entries: Vec<DirEntryInfo> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let path = entry.path();
+                // file_type() does not follow symlinks; a link to a directory should count as one.
+                // Only links get the extra stat, so listing a huge directory stays cheap
+                let is_dir = entry.file_type().map(|t| t.is_dir() || (t.is_symlink() && path.is_dir())).unwrap_or(false);
+                DirEntryInfo { name: entry.file_name().to_string_lossy().to_string(), path, is_dir }
+            })
+            .collect();
+        let entries = Arc::new(entries);
+
+        let (is_new, evicted) = {
+            let mut listings = self.listings();
+            let is_new = !listings.contains_key(dir);
+            let mut evicted = None;
+            if is_new && listings.len() >= CAPACITY {
+                evicted = listings.iter().min_by_key(|(_, l)| l.loaded).map(|(p, _)| p.clone());
+                if let Some(oldest) = &evicted {
+                    listings.remove(oldest);
+                }
+            }
+            listings.insert(dir.to_path_buf(), Listing { entries: entries.clone(), modified: current_modified, loaded: Instant::now() });
+            (is_new, evicted)
+        };
+        // Outside the listings lock: the watcher's event thread takes it to invalidate
+        if let Some(oldest) = evicted {
+            self.unwatch(&oldest);
+        }
+        if is_new {
+            self.watch(dir);
+        }
+        Ok(entries)
+    }
+
+    pub fn invalidate(&self, dir: &Path) {
+        self.listings().remove(dir);
+    }
+
+    fn watch(&self, dir: &Path) {
+        if let Some(watcher) = self.watcher.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+            let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+        }
+    }
+
+    fn unwatch(&self, dir: &Path) {
+        if let Some(watcher) = self.watcher.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+            let _ = watcher.unwatch(dir);
+        }
+    }
+}
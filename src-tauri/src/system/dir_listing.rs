@@ -0,0 +1,142 @@
+// Paged directory listings
+// Directories like node_modules or /usr/lib hold tens of thousands of entries.
+// Listings come back a page at a time with a cursor for the next one, and file
+// metadata is only read for the entries on the page unless sorting needs it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::system::dir_cache::DirEntryInfo;
+
+/// No page is larger than this, whatever the caller asks for
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    Name,
+    /// File extension, then name
+    Kind,
+    Size,
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListingOptions {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<usize>,
+    pub limit: usize,
+    pub sort_by: SortBy,
+    pub descending: bool,
+    pub directories_first: bool,
+    pub include_hidden: bool,
+    /// Read size and modification time for the entries on the page
+    pub include_metadata: bool,
+}
+
+impl Default for ListingOptions {
+    fn default() -> Self {
+        Self {
+            cursor: None,
+            limit: 200,
+            sort_by: SortBy::Name,
+            descending: false,
+            directories_first: true,
+            include_hidden: false,
+            include_metadata: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListedEntry {
+    pub name: String,
+    pub path: String,
+    pub is_directory: bool,
+    pub size: Option<u64>,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryPage {
+    pub path: String,
+    pub entries: Vec<ListedEntry>,
+    /// Entries in the directory after the hidden-file filter
+    pub total: usize,
+    /// Pass back as `cursor` to load more; None on the last page
+    pub next_cursor: Option<usize>,
+}
+
+struct Metadata {
+    size: Option<u64>,
+    modified: Option<DateTime<Utc>>,
+}
+
+fn metadata(entry: &DirEntryInfo) -> Metadata {
+    let metadata = std::fs::metadata(&entry.path).ok();
+    Metadata {
+        size: metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len()),
+        modified: metadata.and_then(|m| m.modified().ok()).map(DateTime::<Utc>::from),
+    }
+}
+
+fn extension(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => extension,
+        _ => "",
+    }
+}
+
+fn by_name(a: &DirEntryInfo, b: &DirEntryInfo) -> Ordering {
+    a.name.to_lowercase().cmp(&b.name.to_lowercase()).then_with(|| a.name.cmp(&b.name))
+}
+
+/// One page of `dir`, read through the shared listing cache
+pub fn list_page(dir: &Path, options: &ListingOptions) -> std::io::Result<DirectoryPage> {
+    let listing = crate::system::dir_cache::cache().list(dir)?;
+    let entries: Vec<&DirEntryInfo> = listing.iter().filter(|e| options.include_hidden || !e.name.starts_with('.')).collect();
+
+    // Size and date sorts need every entry's metadata; name sorts read it only for the page
+    let needs_metadata = matches!(options.sort_by, SortBy::Size | SortBy::Modified);
+    let mut sorted: Vec<(&DirEntryInfo, Option<Metadata>)> =
+        entries.into_iter().map(|e| (e, needs_metadata.then(|| metadata(e)))).collect();
+    sorted.sort_by(|(a, a_meta), (b, b_meta)| {
+        let kind = if options.directories_first { b.is_dir.cmp(&a.is_dir) } else { Ordering::Equal };
+        let key = match (options.sort_by, a_meta, b_meta) {
+            (SortBy::Size, Some(a_meta), Some(b_meta)) => a_meta.size.cmp(&b_meta.size),
+            (SortBy::Modified, Some(a_meta), Some(b_meta)) => a_meta.modified.cmp(&b_meta.modified),
+            (SortBy::Kind, _, _) => extension(&a.name).to_lowercase().cmp(&extension(&b.name).to_lowercase()),
+            _ => Ordering::Equal,
+        };
+        let key = if options.descending { key.reverse() } else { key };
+        let name = if options.descending && options.sort_by == SortBy::Name { by_name(b, a) } else { by_name(a, b) };
+        kind.then(key).then(name)
+    });
+
+    let total = sorted.len();
+    let start = options.cursor.unwrap_or(0).min(total);
+    let end = (start + options.limit.clamp(1, MAX_PAGE_SIZE)).min(total);
+    let entries = sorted
+        .drain(start..end)
+        .map(|(entry, known)| {
+            let metadata = known.or_else(|| options.include_metadata.then(|| metadata(entry)));
+            ListedEntry {
+                name: entry.name.clone(),
+                path: entry.path.to_string_lossy().to_string(),
+                is_directory: entry.is_dir,
+                size: metadata.as_ref().and_then(|m| m.size),
+                modified: metadata.and_then(|m| m.modified),
+            }
+        })
+        .collect();
+
+    Ok(DirectoryPage {
+        path: dir.to_string_lossy().to_string(),
+        entries,
+        total,
+        next_cursor: (end < total).then_some(end),
+    })
+}
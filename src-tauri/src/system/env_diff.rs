@@ -0,0 +1,154 @@
+// Environment snapshots and diffs between sessions or machines
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Variables that differ between any two shells and are never interesting
+const VOLATILE_VARS: [&str; 10] = [
+    "PWD", "OLDPWD", "SHLVL", "_", "TERM_SESSION_ID", "SECURITYSESSIONID",
+    "WINDOWID", "SSH_AUTH_SOCK", "TMPDIR", "ITERM_SESSION_ID",
+];
+
+const TRACKED_TOOLS: [&str; 12] = [
+    "node", "npm", "python3", "pip3", "cargo", "rustc", "go", "java", "docker", "git", "ruby", "kubectl",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentSnapshot {
+    pub label: String,
+    pub hostname: String,
+    pub os: String,
+    pub captured_at: DateTime<Utc>,
+    pub working_directory: String,
+    pub variables: BTreeMap<String, String>,
+    pub path_entries: Vec<String>,
+    pub tool_versions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueChange {
+    pub name: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathDiff {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    pub order_differs: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentDiff {
+    pub a: String,
+    pub b: String,
+    pub variables: Vec<ValueChange>,
+    pub path: PathDiff,
+    pub tools: Vec<ValueChange>,
+}
+
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_uppercase();
+    ["TOKEN", "SECRET", "PASSWORD", "PASSWD", "API_KEY", "PRIVATE_KEY", "CREDENTIAL"]
+        .iter()
+        .any(|marker| name.contains(marker))
+}
+
+/// Secret-looking values are replaced by a fingerprint so they can still be compared
+fn mask(name: &str, value: &str) -> String {
+    if is_secret(name) {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        format!("<redacted:{:08x}>", hasher.finish() as u32)
+    } else {
+        value.to_string()
+    }
+}
+
+async fn tool_version(tool: &str, path: &str) -> Option<String> {
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        tokio::process::Command::new(tool).arg("--version").env("PATH", path).output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+
+    // Some tools (java) print their version on stderr
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    String::from_utf8_lossy(&text).lines().next().map(|line| line.trim().to_string())
+}
+
+/// Capture the environment of a session (or process) with tool versions resolved against its PATH
+pub async fn capture(label: &str, variables: &HashMap<String, String>, working_directory: &str) -> EnvironmentSnapshot {
+    let path = variables.get("PATH").cloned().unwrap_or_default();
+    let separator = if cfg!(target_os = "windows") { ';' } else { ':' };
+
+    let versions = futures::future::join_all(TRACKED_TOOLS.iter().map(|tool| tool_version(tool, &path))).await;
+    let tool_versions = TRACKED_TOOLS.iter()
+        .zip(versions)
+        .filter_map(|(tool, version)| version.map(|v| (tool.to_string(), v)))
+        .collect();
+
+    EnvironmentSnapshot {
+        label: label.to_string(),
+        hostname: sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+        os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        captured_at: Utc::now(),
+        working_directory: working_directory.to_string(),
+        variables: variables.iter()
+            .filter(|(name, _)| !VOLATILE_VARS.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), mask(name, value)))
+            .collect(),
+        path_entries: path.split(separator).filter(|e| !e.is_empty()).map(|e| e.to_string()).collect(),
+        tool_versions,
+    }
+}
+
+fn diff_maps(a: &BTreeMap<String, String>, b: &BTreeMap<String, String>, skip: &[&str]) -> Vec<ValueChange> {
+    let names: HashSet<&String> = a.keys().chain(b.keys()).collect();
+    let mut changes: Vec<ValueChange> = names.into_iter()
+        .filter(|name| !skip.contains(&name.as_str()))
+        .filter(|name| a.get(*name) != b.get(*name))
+        .map(|name| ValueChange {
+            name: name.clone(),
+            a: a.get(name).cloned(),
+            b: b.get(name).cloned(),
+        })
+        .collect();
+    changes.sort_by(|x, y| x.name.cmp(&y.name));
+    changes
+}
+
+pub fn diff(a: &EnvironmentSnapshot, b: &EnvironmentSnapshot) -> EnvironmentDiff {
+    let in_a: HashSet<&String> = a.path_entries.iter().collect();
+    let in_b: HashSet<&String> = b.path_entries.iter().collect();
+    let common_a: Vec<&String> = a.path_entries.iter().filter(|e| in_b.contains(e)).collect();
+    let common_b: Vec<&String> = b.path_entries.iter().filter(|e| in_a.contains(e)).collect();
+
+    EnvironmentDiff {
+        a: a.label.clone(),
+        b: b.label.clone(),
+        // PATH is compared entry by entry below
+        variables: diff_maps(&a.variables, &b.variables, &["PATH"]),
+        path: PathDiff {
+            only_in_a: a.path_entries.iter().filter(|e| !in_b.contains(e)).cloned().collect(),
+            only_in_b: b.path_entries.iter().filter(|e| !in_a.contains(e)).cloned().collect(),
+            order_differs: common_a != common_b,
+        },
+        tools: diff_maps(&a.tool_versions, &b.tool_versions, &[]),
+    }
+}
+
+pub fn export(snapshot: &EnvironmentSnapshot, path: &str) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write snapshot: {}", e))
+}
+
+pub fn import(path: &str) -> Result<EnvironmentSnapshot, String> {
+    let data = std::fs::read_to_string(path).map_err(|e| format!("Failed to read snapshot: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid environment snapshot: {}", e))
+}
@@ -0,0 +1,104 @@
+// Blocking file system work for async commands
+// Directory scans in completion and context paths run on the blocking pool, at
+// most a few at a time, and give up after a timeout so a slow network mount
+// cannot hold up the command that asked for them. Stores that change with every
+// command are written by a background thread instead of under their lock, with
+// repeated writes to the same file coalesced.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+const MAX_CONCURRENT: usize = 4;
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a background write waits for newer contents of the same file
+const WRITE_DELAY: Duration = Duration::from_secs(2);
+
+fn permits() -> Arc<Semaphore> {
+    static PERMITS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    PERMITS.get_or_init(|| Arc::new(Semaphore::new(MAX_CONCURRENT))).clone()
+}
+
+/// Run `work` on the blocking pool, waiting at most `TIMEOUT` for a slot and the result
+pub async fn run<T, F>(work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    run_with_timeout(TIMEOUT, work).await
+}
+
+/// Like `run`, for scans that are expected to take longer
+pub async fn run_with_timeout<T, F>(timeout: Duration, work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let task = async {
+        let permit = permits().acquire_owned().await.map_err(|e| e.to_string())?;
+        tokio::task::spawn_blocking(move || {
+            // The slot stays taken until the work really ends, even after a timeout
+            let _permit = permit;
+            work()
+        })
+        .await
+        .map_err(|e| format!("File system task failed: {}", e))
+    };
+    tokio::time::timeout(timeout, task)
+        .await
+        .map_err(|_| format!("File system access timed out after {}s", timeout.as_secs()))?
+}
+
+struct PendingWrites {
+    files: Mutex<HashMap<PathBuf, String>>,
+    ready: Condvar,
+    /// Held while writing, so a flush never races an older write of the same file
+    writing: Mutex<()>,
+}
+
+fn pending_writes() -> &'static PendingWrites {
+    static PENDING: OnceLock<PendingWrites> = OnceLock::new();
+    static WRITER: OnceLock<()> = OnceLock::new();
+    let pending = PENDING.get_or_init(|| PendingWrites { files: Mutex::new(HashMap::new()), ready: Condvar::new(), writing: Mutex::new(()) });
+    WRITER.get_or_init(|| {
+        std::thread::spawn(move || loop {
+            let mut files = pending.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            while files.is_empty() {
+                files = pending.ready.wait(files).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            drop(files);
+            std::thread::sleep(WRITE_DELAY);
+            write_pending(pending);
+        });
+    });
+    pending
+}
+
+fn write_pending(pending: &PendingWrites) {
+    let _writing = pending.writing.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let files = std::mem::take(&mut *pending.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    for (file, contents) in files {
+        let result = file
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&file, contents));
+        if let Err(e) = result {
+            println!("⚠️ Failed to write {}: {}", file.display(), e);
+        }
+    }
+}
+
+/// Write `contents` to `file` shortly, off the caller's thread; only the last of several
+/// writes to the same file in that time is done
+pub fn write_later(file: PathBuf, contents: String) {
+    let pending = pending_writes();
+    pending.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(file, contents);
+    pending.ready.notify_one();
+}
+
+/// Do the writes still waiting, before the process exits
+pub fn flush_writes() {
+    write_pending(pending_writes());
+}
@@ -0,0 +1,185 @@
+// Size caps for large invoke responses
+// A build log or `cat` of a big file can be many megabytes; serializing that in
+// one invoke response stalls both serde and the webview. Outputs over the cap
+// are sent as their head and tail with a handle, and the full text is fetched
+// in pages through that handle, gzip-compressed when the client asks for it.
+
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::terminal::CommandExecution;
+
+/// Outputs longer than this are capped in responses
+pub const MAX_INLINE_BYTES: usize = 256 * 1024;
+/// Of a capped output, this much of the start is kept; the rest of the cap goes to the end
+const HEAD_BYTES: usize = 32 * 1024;
+/// Largest page `page` returns
+pub const MAX_PAGE_BYTES: usize = 1024 * 1024;
+/// Pages smaller than this are not worth compressing
+const COMPRESS_MIN_BYTES: usize = 16 * 1024;
+/// Full outputs kept for paging, oldest dropped first
+const MAX_STORED: usize = 32;
+const MAX_STORED_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputHandle {
+    pub handle: String,
+    pub total_bytes: usize,
+    /// Bytes of the output left out of the inline text
+    pub omitted_bytes: usize,
+}
+
+/// A command execution as sent to the webview; `output` is capped when `truncated` is set
+#[derive(Debug, Clone, Serialize)]
+pub struct CappedExecution {
+    #[serde(flatten)]
+    pub execution: Arc<CommandExecution>,
+    pub truncated: Option<OutputHandle>,
+}
+
+/// A session's executions, newest first
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrollbackPage {
+    pub executions: Vec<CappedExecution>,
+    /// Pass back as `offset` for older executions
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageEncoding {
+    Plain,
+    /// Base64 of gzip, readable with the browser's DecompressionStream("gzip")
+    GzipBase64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputPage {
+    pub handle: String,
+    pub offset: usize,
+    pub next_offset: Option<usize>,
+    pub total_bytes: usize,
+    pub encoding: PageEncoding,
+    pub data: String,
+}
+
+/// Recently capped executions, whose full output can still be paged in; the handle is the execution id
+pub struct OutputPages {
+    stored: VecDeque<Arc<CommandExecution>>,
+    stored_bytes: usize,
+}
+
+impl Default for OutputPages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputPages {
+    pub fn new() -> Self {
+        Self { stored: VecDeque::new(), stored_bytes: 0 }
+    }
+
+    fn store(&mut self, execution: Arc<CommandExecution>) {
+        if self.stored.iter().any(|stored| stored.id == execution.id) {
+            return;
+        }
+        self.stored_bytes += execution.output.len();
+        self.stored.push_back(execution);
+        while self.stored.len() > 1 && (self.stored.len() > MAX_STORED || self.stored_bytes > MAX_STORED_BYTES) {
+            if let Some(dropped) = self.stored.pop_front() {
+                self.stored_bytes -= dropped.output.len();
+            }
+        }
+    }
+
+    /// The execution as it should be sent: shared as is, or a copy with the output capped
+    pub fn cap(&mut self, execution: Arc<CommandExecution>) -> CappedExecution {
+        let text = &execution.output;
+        let total_bytes = text.len();
+        if total_bytes <= MAX_INLINE_BYTES {
+            return CappedExecution { execution, truncated: None };
+        }
+        let head_end = floor_boundary(text, HEAD_BYTES);
+        let tail_start = ceil_boundary(text, total_bytes - (MAX_INLINE_BYTES - HEAD_BYTES));
+        let omitted_bytes = tail_start - head_end;
+        let capped = execution.with_output(format!(
+            "{}\n… {} of output omitted …\n{}",
+            &text[..head_end],
+            format_bytes(omitted_bytes),
+            &text[tail_start..],
+        ));
+        let handle = execution.id.clone();
+        self.store(execution);
+        CappedExecution { execution: Arc::new(capped), truncated: Some(OutputHandle { handle, total_bytes, omitted_bytes }) }
+    }
+
+    /// Up to `length` bytes of a stored output from `offset`, snapped to character boundaries
+    pub fn page(&self, handle: &str, offset: usize, length: Option<usize>, compress: bool) -> Result<OutputPage, String> {
+        let text = &self
+            .stored
+            .iter()
+            .find(|stored| stored.id == handle)
+            .ok_or_else(|| "This output is no longer available; run the command again to see all of it".to_string())?
+            .output;
+        let start = floor_boundary(text, offset.min(text.len()));
+        let end = floor_boundary(text, start + length.unwrap_or(MAX_PAGE_BYTES).clamp(1, MAX_PAGE_BYTES));
+        // A single character longer than the page still has to fit
+        let end = if end == start && start < text.len() { ceil_boundary(text, start + 1) } else { end };
+        let chunk = &text[start..end];
+
+        let (encoding, data) = if compress && chunk.len() >= COMPRESS_MIN_BYTES {
+            (PageEncoding::GzipBase64, base64::engine::general_purpose::STANDARD.encode(gzip(chunk.as_bytes())?))
+        } else {
+            (PageEncoding::Plain, chunk.to_string())
+        };
+        Ok(OutputPage {
+            handle: handle.to_string(),
+            offset: start,
+            next_offset: (end < text.len()).then_some(end),
+            total_bytes: text.len(),
+            encoding,
+            data,
+        })
+    }
+}
+
+/// JSON built up front and handed to the webview as is, skipping tauri's own serialization pass
+pub fn json_response<T: Serialize>(value: &T) -> Result<tauri::ipc::Response, String> {
+    serde_json::to_string(value).map(tauri::ipc::Response::new).map_err(|e| format!("Could not encode response: {}", e))
+}
+
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(bytes).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+fn floor_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} bytes", b),
+    }
+}
@@ -0,0 +1,293 @@
+// Command latency budgets
+// Every command is timed twice. Its body holds a `LatencyGuard` from `track` for
+// the whole run, which is what the UI waits for. The invoke wrapper around the
+// command handler times dispatch: Tauri spawns async commands and answers the
+// webview when they finish, so the wrapper only sees argument decoding and
+// getting the command started. The two are reported side by side, never mixed.
+// Each command keeps a window of recent timings for percentiles; runs over
+// budget are counted and logged, and `get_performance_report` lists the
+// commands that make the UI feel slow.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Timings kept per command for percentiles
+const WINDOW: usize = 500;
+/// Decoding arguments and spawning a command should never be noticeable
+const DISPATCH_BUDGET_MS: f64 = 5.0;
+/// Full-run budgets for the commands the UI waits on while the user types
+const DEFAULT_BUDGETS: [(&str, f64); 9] = [
+    ("get_smart_completions", 50.0),
+    ("get_path_completions", 50.0),
+    ("get_sql_completions", 50.0),
+    ("get_command_history_for_navigation", 30.0),
+    ("search_command_history", 100.0),
+    ("get_context_suggestions", 100.0),
+    ("get_session_prompt", 50.0),
+    ("validate_and_correct_path", 50.0),
+    ("get_proactive_suggestions", 250.0),
+];
+
+/// Budget overrides in milliseconds, by command name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LatencyBudgets {
+    pub budgets: HashMap<String, f64>,
+}
+
+impl LatencyBudgets {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("latency_budgets.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// Full-run budget of a command, if it has one
+    pub fn budget(&self, command: &str) -> Option<f64> {
+        self.budgets
+            .get(command)
+            .copied()
+            .or_else(|| DEFAULT_BUDGETS.iter().find(|(name, _)| *name == command).map(|(_, ms)| *ms))
+    }
+}
+
+/// The app's one monitor, shared by the invoke wrapper and the commands
+pub fn monitor() -> &'static Arc<LatencyMonitor> {
+    static MONITOR: OnceLock<Arc<LatencyMonitor>> = OnceLock::new();
+    MONITOR.get_or_init(|| Arc::new(LatencyMonitor::new()))
+}
+
+/// Time the calling command's whole run; the timing is recorded when the guard drops
+pub fn track(command: &'static str) -> LatencyGuard {
+    monitor().track(command)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Measured {
+    /// Handler call only; an async command's work happens after it
+    Dispatch,
+    /// The command from start to answer
+    FullRun,
+}
+
+#[derive(Debug, Default)]
+struct Timings {
+    recent: VecDeque<f64>,
+    calls: u64,
+    over_budget: u64,
+    max_ms: f64,
+    total_ms: f64,
+}
+
+impl Timings {
+    fn record(&mut self, ms: f64, budget: f64) -> bool {
+        self.recent.push_back(ms);
+        if self.recent.len() > WINDOW {
+            self.recent.pop_front();
+        }
+        self.calls += 1;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+        let over = ms > budget;
+        if over {
+            self.over_budget += 1;
+        }
+        over
+    }
+
+    fn stats(&self, measured: Measured, budget: f64) -> LatencyStats {
+        let mut sorted: Vec<f64> = self.recent.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| sorted.get(((sorted.len() as f64 - 1.0) * p).round() as usize).copied().unwrap_or(0.0);
+        LatencyStats {
+            measured,
+            calls: self.calls,
+            mean_ms: if self.calls > 0 { self.total_ms / self.calls as f64 } else { 0.0 },
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: self.max_ms,
+            budget_ms: budget,
+            over_budget: self.over_budget,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub measured: Measured,
+    pub calls: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub budget_ms: f64,
+    pub over_budget: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandLatency {
+    pub command: String,
+    pub dispatch: LatencyStats,
+    pub full_run: Option<LatencyStats>,
+    /// p95 over budget, in dispatch or in the full run
+    pub slow: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub since: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    /// Slow commands first, then by full-run (or dispatch) p95
+    pub commands: Vec<CommandLatency>,
+    pub slow_commands: Vec<String>,
+}
+
+#[derive(Default)]
+struct CommandTimings {
+    dispatch: Timings,
+    full_run: Option<Timings>,
+}
+
+pub struct LatencyMonitor {
+    timings: Mutex<HashMap<String, CommandTimings>>,
+    budgets: Mutex<LatencyBudgets>,
+    since: Mutex<DateTime<Utc>>,
+}
+
+impl Default for LatencyMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyMonitor {
+    pub fn new() -> Self {
+        Self {
+            timings: Mutex::new(HashMap::new()),
+            budgets: Mutex::new(LatencyBudgets::default()),
+            since: Mutex::new(Utc::now()),
+        }
+    }
+
+    /// Read saved budgets; the monitor exists before the app directories are known
+    pub fn load_budgets(&self) {
+        *self.budgets.lock().unwrap() = LatencyBudgets::load();
+    }
+
+    /// Called by the invoke wrapper once the handler returns
+    pub fn record_dispatch(&self, command: &str, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let over = self.timings.lock().unwrap().entry(command.to_string()).or_default().dispatch.record(ms, DISPATCH_BUDGET_MS);
+        if over {
+            println!("🐢 Dispatching {} took {:.1}ms (budget {}ms)", command, ms, DISPATCH_BUDGET_MS);
+        }
+    }
+
+    fn record_full_run(&self, command: &str, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let budget = self.budgets.lock().unwrap().budget(command).unwrap_or(f64::INFINITY);
+        let over = self
+            .timings
+            .lock()
+            .unwrap()
+            .entry(command.to_string())
+            .or_default()
+            .full_run
+            .get_or_insert_with(Timings::default)
+            .record(ms, budget);
+        if over {
+            println!("🐢 {} took {:.1}ms (budget {}ms)", command, ms, budget);
+        }
+    }
+
+    /// Time a command's whole run; the timing is recorded when the guard drops
+    pub fn track(self: &Arc<Self>, command: &'static str) -> LatencyGuard {
+        LatencyGuard { monitor: self.clone(), command, started: Instant::now() }
+    }
+
+    pub fn report(&self) -> PerformanceReport {
+        let budgets = self.budgets.lock().unwrap();
+        let mut commands: Vec<CommandLatency> = self
+            .timings
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(command, timings)| {
+                let dispatch = timings.dispatch.stats(Measured::Dispatch, DISPATCH_BUDGET_MS);
+                let full_run = timings
+                    .full_run
+                    .as_ref()
+                    .map(|full| full.stats(Measured::FullRun, budgets.budget(command).unwrap_or(f64::INFINITY)));
+                let slow = dispatch.p95_ms > dispatch.budget_ms || full_run.as_ref().is_some_and(|f| f.p95_ms > f.budget_ms);
+                CommandLatency { command: command.clone(), dispatch, full_run, slow }
+            })
+            .collect();
+        let p95 = |c: &CommandLatency| c.full_run.as_ref().map_or(c.dispatch.p95_ms, |f| f.p95_ms);
+        commands.sort_by(|a, b| b.slow.cmp(&a.slow).then(p95(b).total_cmp(&p95(a))));
+        PerformanceReport {
+            since: *self.since.lock().unwrap(),
+            generated_at: Utc::now(),
+            slow_commands: commands.iter().filter(|c| c.slow).map(|c| c.command.clone()).collect(),
+            commands,
+        }
+    }
+
+    pub fn reset(&self) {
+        self.timings.lock().unwrap().clear();
+        *self.since.lock().unwrap() = Utc::now();
+    }
+
+    pub fn budgets(&self) -> LatencyBudgets {
+        self.budgets.lock().unwrap().clone()
+    }
+
+    /// Set a command's full-run budget, or with `None` go back to its default
+    pub fn set_budget(&self, command: &str, budget_ms: Option<f64>) -> Result<(), String> {
+        if budget_ms.is_some_and(|ms| !ms.is_finite() || ms <= 0.0) {
+            return Err("A budget must be a positive number of milliseconds".to_string());
+        }
+        let mut budgets = self.budgets.lock().unwrap();
+        let mut updated = budgets.clone();
+        match budget_ms {
+            Some(ms) => updated.budgets.insert(command.to_string(), ms),
+            None => updated.budgets.remove(command),
+        };
+        updated.save()?;
+        *budgets = updated;
+        Ok(())
+    }
+}
+
+/// Records a command's full run when dropped, on success, error or early return alike
+pub struct LatencyGuard {
+    monitor: Arc<LatencyMonitor>,
+    command: &'static str,
+    started: Instant,
+}
+
+impl Drop for LatencyGuard {
+    fn drop(&mut self) {
+        self.monitor.record_full_run(self.command, self.started.elapsed());
+    }
+}
@@ -0,0 +1,26 @@
+// Host system inspection: environment, PATH and command resolution helpers
+// used to debug "works in my other terminal" problems
+
+pub mod env_diff;
+pub mod path_inspector;
+pub mod command_resolver;
+pub mod dir_cache;
+pub mod dir_listing;
+pub mod fs_tasks;
+pub mod path_search;
+pub mod cleanup;
+pub mod permissions;
+pub mod volumes;
+pub mod power;
+pub mod ipc;
+pub mod diagnostics;
+pub mod app_dirs;
+pub mod profiles;
+pub mod demo;
+pub mod speech;
+pub mod latency;
+pub mod websocket;
+pub mod remote;
+pub mod session_share;
+pub mod automation;
+pub mod bug_report;
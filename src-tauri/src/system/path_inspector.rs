@@ -0,0 +1,208 @@
+// PATH inspection: entry order, duplicates, shadowed binaries and broken symlinks
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Binaries where picking the wrong copy commonly breaks builds
+const NOTABLE_BINARIES: [&str; 16] = [
+    "python", "python3", "pip", "pip3", "node", "npm", "npx", "ruby", "gem",
+    "java", "go", "cargo", "rustc", "git", "docker", "kubectl",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathEntry {
+    pub index: usize,
+    pub path: String,
+    pub exists: bool,
+    pub duplicate_of: Option<usize>,
+    pub executable_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowedBinary {
+    pub name: String,
+    pub resolved: String,
+    pub shadowed: Vec<String>,
+    pub notable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenSymlink {
+    pub path: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathAnalysis {
+    pub entries: Vec<PathEntry>,
+    pub shadowed: Vec<ShadowedBinary>,
+    pub broken_symlinks: Vec<BrokenSymlink>,
+    pub cleanup_commands: Vec<String>,
+}
+
+fn separator() -> char {
+    if cfg!(target_os = "windows") { ';' } else { ':' }
+}
+
+pub fn split_path(path_var: &str) -> Vec<String> {
+    path_var.split(separator()).filter(|entry| !entry.is_empty()).map(|entry| entry.to_string()).collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    path.is_file() && ["exe", "cmd", "bat", "com", "ps1"].contains(&extension.as_str())
+}
+
+/// Executable name as typed by the user (without .exe on Windows)
+fn command_name(path: &Path) -> Option<String> {
+    if cfg!(target_os = "windows") {
+        path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_lowercase())
+    } else {
+        path.file_name().and_then(|s| s.to_str()).map(|s| s.to_string())
+    }
+}
+
+/// Every executable named `name` on PATH, in resolution order
+pub fn find_all(name: &str, path_var: &str) -> Vec<PathBuf> {
+    let candidates: Vec<String> = if cfg!(target_os = "windows") {
+        ["", ".exe", ".cmd", ".bat", ".com"].iter().map(|ext| format!("{}{}", name, ext)).collect()
+    } else {
+        vec![name.to_string()]
+    };
+
+    split_path(path_var).iter()
+        .flat_map(|dir| candidates.iter().map(move |candidate| Path::new(dir).join(candidate)))
+        .filter(|path| is_executable(path))
+        .collect()
+}
+
+/// Warning when `name` resolves to one binary but PATH holds other, different copies
+pub fn shadow_warning(name: &str, path_var: &str) -> Option<String> {
+    let found = find_all(name, path_var);
+    let resolved = found.first()?;
+    let resolved_target = resolved.canonicalize().unwrap_or_else(|_| resolved.clone());
+
+    let others: Vec<String> = found.iter()
+        .skip(1)
+        .filter(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()) != resolved_target)
+        .map(|path| path.display().to_string())
+        .collect();
+    if others.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "'{}' resolves to {}, but PATH also contains {} - you may be running an unexpected binary",
+        name,
+        resolved.display(),
+        others.join(", ")
+    ))
+}
+
+pub fn analyze_path(path_var: &str) -> PathAnalysis {
+    let mut entries = Vec::new();
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+    let mut binaries: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut broken_symlinks = Vec::new();
+
+    for (index, dir) in split_path(path_var).into_iter().enumerate() {
+        let path = PathBuf::from(&dir);
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        let duplicate_of = seen.get(&canonical).copied();
+        seen.entry(canonical).or_insert(index);
+
+        let mut executable_count = 0;
+        if duplicate_of.is_none() {
+            if let Ok(read_dir) = std::fs::read_dir(&path) {
+                for file in read_dir.flatten() {
+                    let file_path = file.path();
+                    let is_symlink = file.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+                    if is_symlink && !file_path.exists() {
+                        broken_symlinks.push(BrokenSymlink {
+                            path: file_path.display().to_string(),
+                            target: std::fs::read_link(&file_path)
+                                .map(|t| t.display().to_string())
+                                .unwrap_or_default(),
+                        });
+                        continue;
+                    }
+                    if is_executable(&file_path) {
+                        executable_count += 1;
+                        if let Some(name) = command_name(&file_path) {
+                            binaries.entry(name).or_default().push(file_path);
+                        }
+                    }
+                }
+            }
+        }
+
+        entries.push(PathEntry {
+            index,
+            exists: path.is_dir(),
+            path: dir,
+            duplicate_of,
+            executable_count,
+        });
+    }
+
+    let mut shadowed: Vec<ShadowedBinary> = binaries.into_iter()
+        .filter_map(|(name, paths)| {
+            // Symlinks to the same file (python3 -> python3.12) are not real conflicts
+            let first = paths.first()?.canonicalize().ok()?;
+            let different: Vec<String> = paths.iter()
+                .skip(1)
+                .filter(|path| path.canonicalize().map(|c| c != first).unwrap_or(true))
+                .map(|path| path.display().to_string())
+                .collect();
+            if different.is_empty() {
+                return None;
+            }
+            Some(ShadowedBinary {
+                notable: NOTABLE_BINARIES.contains(&name.as_str()),
+                resolved: paths[0].display().to_string(),
+                name,
+                shadowed: different,
+            })
+        })
+        .collect();
+    shadowed.sort_by(|a, b| b.notable.cmp(&a.notable).then(a.name.cmp(&b.name)));
+
+    let cleanup_commands = cleanup_commands(&entries);
+    PathAnalysis {
+        entries,
+        shadowed,
+        broken_symlinks,
+        cleanup_commands,
+    }
+}
+
+/// Suggest a deduplicated PATH without missing directories
+fn cleanup_commands(entries: &[PathEntry]) -> Vec<String> {
+    let needs_cleanup = entries.iter().any(|entry| entry.duplicate_of.is_some() || !entry.exists);
+    if !needs_cleanup {
+        return vec![];
+    }
+
+    let kept: Vec<&str> = entries.iter()
+        .filter(|entry| entry.duplicate_of.is_none() && entry.exists)
+        .map(|entry| entry.path.as_str())
+        .collect();
+
+    let mut commands = Vec::new();
+    if cfg!(target_os = "windows") {
+        commands.push(format!("$env:Path = \"{}\"", kept.join(";")));
+    } else {
+        commands.push(format!("export PATH=\"{}\"", kept.join(":")));
+        for entry in entries.iter().filter(|entry| !entry.exists) {
+            commands.push(format!("grep -n '{}' ~/.bashrc ~/.zshrc ~/.profile 2>/dev/null", entry.path));
+        }
+    }
+    commands
+}
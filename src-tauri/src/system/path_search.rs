@@ -0,0 +1,216 @@
+// Finding a directory by name
+// Directories the user visited are checked first, then the search roots are
+// walked (respecting .gitignore and hidden files, with bounded depth and entry
+// count), and when that finds little the system's file index is asked: locate on
+// Linux, Spotlight on macOS, Everything on Windows. Results are ranked by how
+// well the name matches, the directory's frecency and how deep it was found.
+
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Entries visited per search before the walk gives up
+const MAX_VISITED: usize = 20_000;
+/// Time the walk and the system index may take together
+pub const SEARCH_BUDGET: Duration = Duration::from_secs(4);
+/// Never worth descending into, even outside a git repository
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "__pycache__", ".git"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathSearchOptions {
+    pub max_depth: usize,
+    pub limit: usize,
+    pub include_hidden: bool,
+    pub use_system_index: bool,
+}
+
+impl Default for PathSearchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            limit: 20,
+            include_hidden: false,
+            use_system_index: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchSource {
+    /// A directory the user has visited before
+    History,
+    Walk,
+    SystemIndex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathMatch {
+    pub path: String,
+    pub score: f64,
+    pub source: MatchSource,
+}
+
+/// 1 for the exact name, less for prefixes and substrings, case-insensitive
+fn name_score(name: &str, query: &str) -> f64 {
+    let name = name.to_lowercase();
+    if name == query {
+        1.0
+    } else if name.starts_with(query) {
+        0.6
+    } else if name.contains(query) {
+        0.3
+    } else {
+        0.0
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+struct Ranking<'a> {
+    query: String,
+    frecency: &'a HashMap<String, f64>,
+    found: HashMap<String, PathMatch>,
+}
+
+impl Ranking<'_> {
+    fn consider(&mut self, path: &Path, depth: usize, source: MatchSource) {
+        let matched = name_score(&file_name(path), &self.query);
+        if matched == 0.0 {
+            return;
+        }
+        let path = path.to_string_lossy().to_string();
+        let frecency = self.frecency.get(&path).copied().unwrap_or(0.0);
+        let score = matched * (1.0 + (1.0 + frecency).ln()) / (1.0 + 0.1 * depth as f64);
+        let candidate = PathMatch { path: path.clone(), score, source };
+        match self.found.get(&path) {
+            Some(existing) if existing.score >= score => {}
+            _ => {
+                self.found.insert(path, candidate);
+            }
+        }
+    }
+}
+
+/// Directories named like `query` under `roots`, best first
+pub fn search(
+    query: &str,
+    roots: &[PathBuf],
+    frecency: &HashMap<String, f64>,
+    options: &PathSearchOptions,
+) -> Vec<PathMatch> {
+    let deadline = Instant::now() + SEARCH_BUDGET;
+    let mut ranking = Ranking { query: query.to_lowercase(), frecency, found: HashMap::new() };
+    if ranking.query.is_empty() {
+        return Vec::new();
+    }
+
+    // Visited directories need no walk
+    for dir in frecency.keys() {
+        let path = Path::new(dir);
+        if name_score(&file_name(path), &ranking.query) > 0.0 && path.is_dir() {
+            ranking.consider(path, 0, MatchSource::History);
+        }
+    }
+
+    let mut visited = 0;
+    'roots: for root in roots.iter().filter(|root| root.is_dir()) {
+        let walker = WalkBuilder::new(root)
+            .max_depth(Some(options.max_depth))
+            .hidden(!options.include_hidden)
+            .follow_links(false)
+            .filter_entry(|entry| !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+            .build();
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            visited += 1;
+            if visited > MAX_VISITED || Instant::now() > deadline {
+                break 'roots;
+            }
+            if entry.depth() > 0 && entry.file_type().is_some_and(|t| t.is_dir()) {
+                ranking.consider(entry.path(), entry.depth(), MatchSource::Walk);
+            }
+        }
+    }
+
+    if options.use_system_index && ranking.found.len() < options.limit {
+        for path in system_index(query, options.limit, deadline) {
+            if path.is_dir() {
+                // Found outside the roots, so ranked like a deep walk result
+                ranking.consider(&path, 3, MatchSource::SystemIndex);
+            }
+        }
+    }
+
+    let mut matches: Vec<PathMatch> = ranking.found.into_values().collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(options.limit);
+    matches
+}
+
+/// A directory called `name`, ignoring ASCII case, in `start` or the nearest of its ancestors.
+/// Used to correct `cd`, which should never land in an unrelated project found further away
+pub fn nearest_named(name: &str, start: &Path) -> Option<PathBuf> {
+    if matches!(name, "" | "." | "..") || name.contains(['/', '\\']) {
+        return None;
+    }
+    start.ancestors().find_map(|dir| {
+        let exact = dir.join(name);
+        if exact.is_dir() {
+            return Some(exact);
+        }
+        std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(name) && entry.path().is_dir())
+            .map(|entry| entry.path())
+    })
+}
+
+/// Paths from the platform's file index; empty when none is installed
+fn system_index(query: &str, limit: usize, deadline: Instant) -> Vec<PathBuf> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("mdfind");
+        command.args(["-name", query]);
+        command
+    } else if cfg!(windows) {
+        // Everything's command line client, /ad limits results to folders
+        let mut command = Command::new("es");
+        command.args(["-n", &(limit * 5).to_string(), "/ad", query]);
+        command
+    } else {
+        let mut command = Command::new("locate");
+        command.args(["-i", "-b", "-l", &(limit * 5).to_string(), query]);
+        command
+    };
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+
+    let Ok(mut child) = command.spawn() else { return Vec::new() };
+    // Read while waiting, or a long result list fills the pipe and the index blocks
+    let reader = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = std::io::Read::read_to_string(&mut stdout, &mut output);
+            output
+        })
+    });
+    // Wait for the index without outliving the search budget
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Vec::new();
+            }
+        }
+    }
+    let output = reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    output.lines().filter(|line| !line.is_empty()).map(PathBuf::from).collect()
+}
@@ -0,0 +1,279 @@
+// File permissions: inspection and chmod-style changes
+// Owner, group, mode bits and ACL entries of a path in structured form, and a
+// mode builder that understands both numeric ("755") and symbolic ("u+x,go-w")
+// specs. Every change is computed as a preview first so the exact old and new
+// modes can be shown before anything is applied.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::terminal::shell_quote::quote_arg;
+
+/// File kinds "this script" may refer to
+const SCRIPT_EXTENSIONS: [&str; 7] = ["sh", "bash", "zsh", "py", "rb", "pl", "js"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AclEntry {
+    /// e.g. "user:alice", "group::", "0: group:staff"
+    pub principal: String,
+    pub permissions: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionInfo {
+    pub path: String,
+    pub is_directory: bool,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Octal with the special bits, e.g. "0755"
+    pub mode: Option<String>,
+    /// As `ls -l` shows it, e.g. "rwxr-xr-x"
+    pub symbolic: Option<String>,
+    pub setuid: bool,
+    pub setgid: bool,
+    pub sticky: bool,
+    pub readonly: bool,
+    pub acl: Vec<AclEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeChange {
+    pub path: String,
+    pub spec: String,
+    pub from: String,
+    pub to: String,
+    pub from_symbolic: String,
+    pub to_symbolic: String,
+    pub applied: bool,
+}
+
+impl ModeChange {
+    pub fn summary(&self) -> String {
+        if self.from == self.to {
+            return format!("{}: already {} ({})", self.path, self.to_symbolic, self.to);
+        }
+        format!("{}: {} ({}) → {} ({})", self.path, self.from_symbolic, self.from, self.to_symbolic, self.to)
+    }
+
+    /// The chmod invocation that makes this change
+    pub fn command(&self) -> String {
+        format!("chmod {} {}", self.to, quote_arg(&self.path))
+    }
+}
+
+/// "rwxr-xr-x" with s/S and t/T for the special bits
+pub fn symbolic(mode: u32) -> String {
+    let mut out = String::with_capacity(9);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+fn octal(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+/// Apply a numeric or symbolic chmod spec to `current`.
+/// `X` sets execute only for directories or files that are already executable by someone
+pub fn apply_spec(spec: &str, current: u32, is_directory: bool) -> Result<u32, String> {
+    let spec = spec.trim();
+    if !spec.is_empty() && spec.chars().all(|c| c.is_digit(8)) {
+        if spec.len() > 4 {
+            return Err(format!("'{}' is not a valid numeric mode", spec));
+        }
+        return u32::from_str_radix(spec, 8).map_err(|e| e.to_string());
+    }
+
+    let clause = Regex::new(r"^([ugoa]*)([+\-=])([rwxXst]*)$").unwrap();
+    let mut mode = current & 0o7777;
+    for part in spec.split(',') {
+        let captures = clause.captures(part).ok_or_else(|| format!("'{}' is not a valid mode (try 755 or u+x,go-w)", part))?;
+        let who = if captures[1].is_empty() || captures[1].contains('a') { "ugo" } else { &captures[1] };
+        let operator = &captures[2];
+        let executable = is_directory || mode & 0o111 != 0;
+
+        let mut bits = 0;
+        let mut mask = 0;
+        for class in who.chars() {
+            let (shift, special) = match class {
+                'u' => (6, 0o4000),
+                'g' => (3, 0o2000),
+                _ => (0, 0o1000),
+            };
+            mask |= 0o7 << shift;
+            for permission in captures[3].chars() {
+                bits |= match permission {
+                    'r' => 4 << shift,
+                    'w' => 2 << shift,
+                    'x' => 1 << shift,
+                    'X' if executable => 1 << shift,
+                    // setuid/setgid only mean something for the owner and group, sticky only for others
+                    's' if class != 'o' => special,
+                    't' if class == 'o' => special,
+                    _ => 0,
+                };
+            }
+        }
+        mode = match operator {
+            "+" => mode | bits,
+            "-" => mode & !bits,
+            _ => {
+                // "=" clears the listed classes (and their special bits) before setting
+                let specials = who.chars().map(|c| match c { 'u' => 0o4000, 'g' => 0o2000, _ => 0o1000 }).fold(0, |a, b| a | b);
+                (mode & !(mask | specials)) | bits
+            }
+        };
+    }
+    Ok(mode)
+}
+
+/// Owner and group names from `ls -ld`, which resolves them the platform's way
+fn owner_names(path: &Path) -> (Option<String>, Option<String>) {
+    let Ok(output) = Command::new("ls").arg("-ld").arg(path).output() else { return (None, None) };
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let mut columns = listing.split_whitespace().skip(2);
+    (columns.next().map(str::to_string), columns.next().map(str::to_string))
+}
+
+/// Extended ACL entries: getfacl on Linux, `ls -le` on macOS, icacls on Windows
+fn acl_entries(path: &Path) -> Vec<AclEntry> {
+    if cfg!(windows) {
+        let Ok(output) = Command::new("icacls").arg(path).output() else { return Vec::new() };
+        let path_text = path.to_string_lossy().to_string();
+        return String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim_start_matches(path_text.as_str()).trim())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(principal, permissions)| AclEntry { principal: principal.to_string(), permissions: permissions.to_string() })
+            .collect();
+    }
+    if cfg!(target_os = "macos") {
+        let Ok(output) = Command::new("ls").arg("-led").arg(path).output() else { return Vec::new() };
+        // " 0: group:staff allow list,add_file"
+        let entry = Regex::new(r"^\s*(\d+:\s*\S+)\s+(.+)$").unwrap();
+        return String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(1)
+            .filter_map(|line| entry.captures(line))
+            .map(|c| AclEntry { principal: c[1].to_string(), permissions: c[2].to_string() })
+            .collect();
+    }
+    let Ok(output) = Command::new("getfacl").args(["-p", "--omit-header"]).arg(path).output() else { return Vec::new() };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.rsplit_once(':'))
+        .map(|(principal, permissions)| AclEntry { principal: principal.to_string(), permissions: permissions.to_string() })
+        .collect()
+}
+
+pub fn get_permissions(path: &Path) -> Result<PermissionInfo, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let (owner, group) = owner_names(path);
+    #[cfg(unix)]
+    let (uid, gid, mode) = {
+        use std::os::unix::fs::MetadataExt;
+        (Some(metadata.uid()), Some(metadata.gid()), Some(metadata.mode() & 0o7777))
+    };
+    #[cfg(not(unix))]
+    let (uid, gid, mode): (Option<u32>, Option<u32>, Option<u32>) = (None, None, None);
+
+    Ok(PermissionInfo {
+        path: path.to_string_lossy().to_string(),
+        is_directory: metadata.is_dir(),
+        owner,
+        group,
+        uid,
+        gid,
+        mode: mode.map(octal),
+        symbolic: mode.map(symbolic),
+        setuid: mode.is_some_and(|m| m & 0o4000 != 0),
+        setgid: mode.is_some_and(|m| m & 0o2000 != 0),
+        sticky: mode.is_some_and(|m| m & 0o1000 != 0),
+        readonly: metadata.permissions().readonly(),
+        acl: acl_entries(path),
+    })
+}
+
+/// The change `spec` would make to `path`, applied only when `apply` is set
+pub fn set_permissions(path: &Path, spec: &str, apply: bool) -> Result<ModeChange, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let from = metadata.mode() & 0o7777;
+        let to = apply_spec(spec, from, metadata.is_dir())?;
+        if apply && to != from {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(to))
+                .map_err(|e| format!("Cannot change permissions of {}: {}", path.display(), e))?;
+        }
+        Ok(ModeChange {
+            path: path.to_string_lossy().to_string(),
+            spec: spec.to_string(),
+            from: octal(from),
+            to: octal(to),
+            from_symbolic: symbolic(from),
+            to_symbolic: symbolic(to),
+            applied: apply,
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (metadata, spec, apply);
+        Err("Mode bits are not supported on this platform; use icacls to change ACLs".to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PermissionRequest {
+    pub path: PathBuf,
+    pub spec: String,
+}
+
+/// "make this script executable", "make deploy.sh read-only", "make secrets.env private"
+pub fn plan_from_natural_language(request: &str, working_dir: &str) -> Option<PermissionRequest> {
+    let pattern = Regex::new(r#"(?i)^\s*(?:please\s+)?make\s+(.+?)\s+(executable|runnable|read[- ]?only|writable|writeable|private)\s*[.!]?\s*$"#).ok()?;
+    let captures = pattern.captures(request)?;
+    let target = captures[1].trim().trim_matches(|c| c == '"' || c == '\'' || c == '`');
+    let spec = match captures[2].to_lowercase().replace([' ', '-'], "").as_str() {
+        "executable" | "runnable" => "+x",
+        "readonly" => "a-w",
+        "writable" | "writeable" => "u+w",
+        _ => "go-rwx",
+    };
+
+    let dir = Path::new(working_dir);
+    let lower = target.to_lowercase();
+    let path = if matches!(lower.as_str(), "this script" | "the script" | "this file" | "it") {
+        // Only when there is exactly one script here to mean
+        let scripts: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|e| SCRIPT_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str())))
+            .collect();
+        match scripts.as_slice() {
+            [only] => only.clone(),
+            _ => return None,
+        }
+    } else {
+        let path = dir.join(target);
+        if !path.exists() {
+            return None;
+        }
+        path
+    };
+    Some(PermissionRequest { path, spec: spec.to_string() })
+}
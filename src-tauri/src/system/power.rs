@@ -0,0 +1,254 @@
+// Battery and power source awareness
+// On battery with a low charge, background work that can wait (saving learning
+// data, embedding new history, scheduled monitors, loading the sentence model)
+// is put off until the machine is plugged in or charged again, and commands
+// known to be power hungry get a lighter alternative suggested. What is deferred
+// and the threshold are user settings.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Emitted with a `PowerState` when power saving starts or ends
+pub const POWER_EVENT: &str = "power-state-changed";
+
+/// How long a reading is reused before the battery is asked again
+const STATUS_TTL: Duration = Duration::from_secs(60);
+
+/// Command prefixes that keep the CPU busy for a while, and what to run instead on battery
+const POWER_HEAVY: [(&str, &str); 8] = [
+    ("cargo build --release", "cargo check, or a debug `cargo build`"),
+    ("cargo build", "cargo check"),
+    ("cargo test", "cargo test <name> for just the tests you are working on"),
+    ("npm run build", "the dev server, which rebuilds incrementally"),
+    ("yarn build", "the dev server, which rebuilds incrementally"),
+    ("docker build", "waiting until you are plugged in, or reusing a pulled image"),
+    ("docker compose up", "starting only the services you need"),
+    ("make -j", "make -j2 to use fewer cores"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    /// Desktops without a battery, or no way to tell
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundWork {
+    LearningPersistence,
+    EmbeddingIndexing,
+    Monitors,
+    ModelWarmup,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerStatus {
+    pub source: PowerSource,
+    pub battery_percent: Option<f32>,
+    pub charging: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerState {
+    pub status: PowerStatus,
+    /// Background work is being deferred
+    pub saving: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerPolicy {
+    pub enabled: bool,
+    /// Power saving starts at or below this charge while on battery
+    pub low_battery_percent: f32,
+    pub deferred: Vec<BackgroundWork>,
+    pub suggest_alternatives: bool,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            low_battery_percent: 30.0,
+            deferred: vec![
+                BackgroundWork::LearningPersistence,
+                BackgroundWork::EmbeddingIndexing,
+                BackgroundWork::Monitors,
+                BackgroundWork::ModelWarmup,
+            ],
+            suggest_alternatives: true,
+        }
+    }
+}
+
+impl PowerPolicy {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("power_policy.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    fn saving(&self, status: &PowerStatus) -> bool {
+        self.enabled
+            && status.source == PowerSource::Battery
+            && !status.charging
+            && status.battery_percent.is_some_and(|percent| percent <= self.low_battery_percent)
+    }
+}
+
+struct Cached {
+    policy: PowerPolicy,
+    status: PowerStatus,
+    read_at: Instant,
+}
+
+fn cached() -> &'static Mutex<Option<Cached>> {
+    static CACHE: OnceLock<Mutex<Option<Cached>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(None))
+}
+
+/// Current power state, read again at most once a minute
+pub fn state() -> PowerState {
+    let mut cache = cached().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if cache.as_ref().map_or(true, |c| c.read_at.elapsed() >= STATUS_TTL) {
+        let policy = cache.take().map(|c| c.policy).unwrap_or_else(PowerPolicy::load);
+        *cache = Some(Cached { policy, status: read_status(), read_at: Instant::now() });
+    }
+    let cached = cache.as_ref().expect("power cache filled above");
+    PowerState { status: cached.status.clone(), saving: cached.policy.saving(&cached.status) }
+}
+
+/// Read the battery now instead of waiting for the cached reading to expire
+pub fn refresh() -> PowerState {
+    if let Some(cached) = cached().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+        cached.read_at = Instant::now() - STATUS_TTL;
+    }
+    state()
+}
+
+pub fn policy() -> PowerPolicy {
+    state();
+    cached().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_ref().map(|c| c.policy.clone()).unwrap_or_default()
+}
+
+pub fn set_policy(policy: PowerPolicy) -> Result<PowerState, String> {
+    policy.save()?;
+    if let Some(cached) = cached().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+        cached.policy = policy;
+    }
+    Ok(state())
+}
+
+/// Whether `work` should wait for better power. Goes by the last reading, kept current by
+/// the app's power watcher, so async callers never wait on `pmset` or PowerShell; before
+/// the first reading nothing is deferred
+pub fn should_defer(work: BackgroundWork) -> bool {
+    let cache = cached().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.as_ref().is_some_and(|c| c.policy.saving(&c.status) && c.policy.deferred.contains(&work))
+}
+
+/// A lighter way to do what `command` does, when it is known to be power hungry and power saving is on
+pub fn alternative_for(command: &str) -> Option<String> {
+    let state = state();
+    if !state.saving || !policy().suggest_alternatives {
+        return None;
+    }
+    let command = command.trim();
+    let (_, alternative) = POWER_HEAVY.iter().find(|(heavy, _)| command.starts_with(heavy))?;
+    Some(format!(
+        "🔋 On battery at {:.0}%: consider {} instead",
+        state.status.battery_percent.unwrap_or(0.0),
+        alternative,
+    ))
+}
+
+fn read_status() -> PowerStatus {
+    let unknown = PowerStatus { source: PowerSource::Unknown, battery_percent: None, charging: false };
+    if cfg!(target_os = "macos") {
+        pmset_status().unwrap_or(unknown)
+    } else if cfg!(windows) {
+        windows_status().unwrap_or(unknown)
+    } else {
+        sysfs_status().unwrap_or(unknown)
+    }
+}
+
+/// "Now drawing from 'Battery Power'" / " -InternalBattery-0 (id=...)    45%; discharging; 3:12 remaining"
+fn pmset_status() -> Option<PowerStatus> {
+    let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let percent = text
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .find_map(|word| word.strip_suffix('%')?.parse::<f32>().ok());
+    let source = if text.contains("'Battery Power'") {
+        PowerSource::Battery
+    } else if text.contains("'AC Power'") {
+        PowerSource::Ac
+    } else {
+        PowerSource::Unknown
+    };
+    let charging = text.contains("; charging") || text.contains("charged");
+    Some(PowerStatus { source, battery_percent: percent, charging })
+}
+
+/// /sys/class/power_supply: batteries report capacity and status, adapters `online`
+fn sysfs_status() -> Option<PowerStatus> {
+    let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |path: &std::path::Path, file: &str| std::fs::read_to_string(path.join(file)).map(|s| s.trim().to_string()).ok();
+    let mut status = PowerStatus { source: PowerSource::Unknown, battery_percent: None, charging: false };
+    let mut on_ac = false;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        match read(&path, "type").as_deref() {
+            Some("Battery") if read(&path, "scope").as_deref() != Some("Device") => {
+                status.battery_percent = read(&path, "capacity").and_then(|c| c.parse().ok());
+                status.charging = matches!(read(&path, "status").as_deref(), Some("Charging") | Some("Full"));
+            }
+            Some("Mains") | Some("USB") => on_ac |= read(&path, "online").as_deref() == Some("1"),
+            _ => {}
+        }
+    }
+    status.source = match (status.battery_percent.is_some(), on_ac) {
+        (_, true) => PowerSource::Ac,
+        (true, false) => PowerSource::Battery,
+        (false, false) => PowerSource::Unknown,
+    };
+    Some(status)
+}
+
+/// Win32_Battery: BatteryStatus 1 is discharging, 2 is on AC
+fn windows_status() -> Option<PowerStatus> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", "Get-CimInstance Win32_Battery | ForEach-Object { \"$($_.EstimatedChargeRemaining) $($_.BatteryStatus)\" }"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.split_whitespace();
+    let percent: f32 = fields.next()?.parse().ok()?;
+    let battery_status: u32 = fields.next()?.parse().ok()?;
+    Some(PowerStatus {
+        source: if battery_status == 1 { PowerSource::Battery } else { PowerSource::Ac },
+        battery_percent: Some(percent),
+        charging: matches!(battery_status, 6..=9),
+    })
+}
@@ -0,0 +1,303 @@
+// User profiles
+// People sharing a machine each get a profile with its own history, learning
+// data, secrets and settings: every file the app keeps, apart from downloaded
+// models, lives under the active profile's directories. The default profile uses
+// the app directories themselves, so data from before profiles existed stays
+// where it is. A profile can have a passphrase, asked for when switching to it.
+// Its secrets are then sealed with a key only the passphrase unlocks; its history
+// and settings are kept apart but not encrypted. Switching restarts the app so
+// every store loads afresh, and the unlocked key crosses the restart through the
+// OS keychain, once. Without it a protected profile is not reopened: the app
+// starts in the default profile instead.
+
+use chrono::{DateTime, Utc};
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use subtle::ConstantTimeEq;
+
+use super::demo::DEMO_PROFILE;
+use crate::integrations::secrets;
+
+pub const DEFAULT_PROFILE: &str = "default";
+/// PBKDF2-HMAC-SHA256 rounds, so a copied registry is slow to guess passphrases from
+const KDF_ROUNDS: u32 = 600_000;
+const KEYCHAIN_SERVICE: &str = "pH7Console";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Passphrase {
+    salt: String,
+    /// First half of the derived key, compared to check a passphrase
+    verifier: String,
+    /// The profile's secrets key, sealed with the second half
+    sealed_key: String,
+    /// SHA-256 of the secrets key, to recognise it when it comes back from the keychain
+    key_id: String,
+}
+
+/// A verifier and the key that seals the secrets key, derived from a passphrase
+fn derive(salt: &[u8], passphrase: &str) -> ([u8; 32], [u8; 32]) {
+    let mut derived = [0u8; 64];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KDF_ROUNDS, &mut derived);
+    let (verifier, wrapping) = derived.split_at(32);
+    (verifier.try_into().expect("32 bytes"), wrapping.try_into().expect("32 bytes"))
+}
+
+fn key_id(key: &[u8; 32]) -> String {
+    hex::encode(Sha256::digest(key))
+}
+
+impl Passphrase {
+    fn new(passphrase: &str, key: &[u8; 32]) -> Self {
+        let salt = secrets::random_bytes::<16>();
+        let (verifier, wrapping) = derive(&salt, passphrase);
+        Self {
+            salt: hex::encode(salt),
+            verifier: hex::encode(verifier),
+            sealed_key: hex::encode(secrets::seal(&wrapping, key)),
+            key_id: key_id(key),
+        }
+    }
+
+    /// The secrets key, when the passphrase is the right one
+    fn unlock(&self, passphrase: &str) -> Option<[u8; 32]> {
+        let salt = hex::decode(&self.salt).ok()?;
+        let (verifier, wrapping) = derive(&salt, passphrase);
+        let stored = hex::decode(&self.verifier).ok()?;
+        if !bool::from(verifier.as_slice().ct_eq(&stored)) {
+            return None;
+        }
+        secrets::open(&wrapping, &hex::decode(&self.sealed_key).ok()?).ok()?.try_into().ok()
+    }
+}
+
+fn unlock_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &format!("profile-unlock:{}", name)).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+/// Take the key a switch left in the keychain for this start; it is removed either way
+fn take_unlocked_key(name: &str) -> Option<[u8; 32]> {
+    let entry = unlock_entry(name).ok()?;
+    let key = entry.get_secret().ok();
+    let _ = entry.delete_credential();
+    key?.try_into().ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileRecord {
+    name: String,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    passphrase: Option<Passphrase>,
+    #[serde(default)]
+    last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_used: Option<DateTime<Utc>>,
+    pub protected: bool,
+    pub active: bool,
+}
+
+/// The profiles on this machine, kept beside (not inside) the profile directories
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileRegistry {
+    profiles: Vec<ProfileRecord>,
+    /// Profile to start in; the default profile when unset
+    active: Option<String>,
+}
+
+impl ProfileRegistry {
+    fn data_file(base_config: &Path) -> PathBuf {
+        base_config.join("profiles.json")
+    }
+
+    pub fn load(base_config: &Path) -> Self {
+        std::fs::read_to_string(Self::data_file(base_config))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, base_config: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(base_config).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(Self::data_file(base_config), json).map_err(|e| e.to_string())
+    }
+
+    fn find(&self, name: &str) -> Option<&ProfileRecord> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// The profile's secrets key when it is protected, checking the passphrase
+    fn verify(&self, name: &str, passphrase: Option<&str>) -> Result<Option<[u8; 32]>, String> {
+        let Some(stored) = self.find(name).and_then(|profile| profile.passphrase.as_ref()) else { return Ok(None) };
+        passphrase
+            .and_then(|p| stored.unlock(p))
+            .map(Some)
+            .ok_or_else(|| format!("Wrong passphrase for profile '{}'", name))
+    }
+
+    /// The profile to open at start, taking up the key a switch to a protected profile left
+    pub fn startup_profile(base_config: &Path) -> String {
+        let registry = Self::load(base_config);
+        let Some(name) = registry.active.clone() else { return DEFAULT_PROFILE.to_string() };
+        if name == DEMO_PROFILE {
+            return name;
+        }
+        let Some(profile) = registry.find(&name) else { return DEFAULT_PROFILE.to_string() };
+        if let Some(passphrase) = &profile.passphrase {
+            match take_unlocked_key(&name).filter(|key| key_id(key) == passphrase.key_id) {
+                Some(key) => secrets::set_key(Some(key)),
+                None => {
+                    println!("🔒 Profile '{}' is protected, starting in the default profile", name);
+                    return DEFAULT_PROFILE.to_string();
+                }
+            }
+        }
+        name
+    }
+
+    pub fn list(&self, active: &str) -> Vec<ProfileInfo> {
+        let builtin = |name: &str| ProfileInfo {
+            name: name.to_string(),
+            created_at: None,
+            last_used: None,
+            protected: false,
+            active: active == name,
+        };
+        // The demo profile is listed only while it is in use
+        std::iter::once(builtin(DEFAULT_PROFILE))
+            .chain(self.profiles.iter().map(|profile| ProfileInfo {
+                name: profile.name.clone(),
+                created_at: Some(profile.created_at),
+                last_used: profile.last_used,
+                protected: profile.passphrase.is_some(),
+                active: active == profile.name,
+            }))
+            .chain((active == DEMO_PROFILE).then(|| builtin(DEMO_PROFILE)))
+            .collect()
+    }
+
+    pub fn create(&mut self, base_config: &Path, name: &str, passphrase: Option<&str>) -> Result<ProfileInfo, String> {
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_')) {
+            return Err(format!("'{}' is not a valid profile name; use letters, digits, '-' and '_'", name));
+        }
+        if name == DEMO_PROFILE {
+            return Err(format!("'{}' is reserved for demo mode", name));
+        }
+        if name == DEFAULT_PROFILE || self.find(name).is_some() {
+            return Err(format!("Profile '{}' already exists", name));
+        }
+        self.profiles.push(ProfileRecord {
+            name: name.to_string(),
+            created_at: Utc::now(),
+            passphrase: passphrase.filter(|p| !p.is_empty()).map(|p| Passphrase::new(p, &secrets::random_bytes())),
+            last_used: None,
+        });
+        self.save(base_config)?;
+        Ok(self.list("").pop().expect("profile just added"))
+    }
+
+    /// Set, change or (with `None`) remove a profile's passphrase, sealing or unsealing its secrets
+    pub fn set_passphrase(&mut self, base_data: &Path, base_config: &Path, name: &str, current: Option<&str>, new: Option<&str>, active: &str) -> Result<(), String> {
+        if name == DEFAULT_PROFILE {
+            return Err("The default profile cannot have a passphrase; the app starts in it when a profile is locked".to_string());
+        }
+        let key = self.verify(name, current)?;
+        let profile = self.profiles.iter_mut().find(|profile| profile.name == name).ok_or_else(|| format!("No profile '{}'", name))?;
+        let data = profile_dir(base_data, name);
+        let new = new.filter(|p| !p.is_empty());
+        // A new passphrase only seals the same key anew; the secrets themselves change hands only
+        // when protection is turned on or off
+        let key = match (key, new) {
+            (Some(key), Some(_)) => Some(key),
+            (None, Some(_)) => {
+                let key = secrets::random_bytes();
+                secrets::seal_file(&data, &key)?;
+                Some(key)
+            }
+            (Some(key), None) => {
+                secrets::unseal_file(&data, &key)?;
+                None
+            }
+            (None, None) => None,
+        };
+        profile.passphrase = new.zip(key.as_ref()).map(|(passphrase, key)| Passphrase::new(passphrase, key));
+        if name == active {
+            secrets::set_key(key);
+        }
+        self.save(base_config)
+    }
+
+    /// Make `name` the profile to start in; the caller restarts the app
+    pub fn switch(&mut self, base_config: &Path, name: &str, passphrase: Option<&str>) -> Result<(), String> {
+        if name == DEFAULT_PROFILE {
+            self.active = None;
+            return self.save(base_config);
+        }
+        let key = self.verify(name, passphrase)?;
+        let profile = self.profiles.iter_mut().find(|profile| profile.name == name).ok_or_else(|| format!("No profile '{}'", name))?;
+        if let Some(key) = key {
+            unlock_entry(name)?
+                .set_secret(&key)
+                .map_err(|e| format!("Failed to hand the unlocked profile to the keychain: {}", e))?;
+        }
+        profile.last_used = Some(Utc::now());
+        self.active = Some(name.to_string());
+        self.save(base_config)
+    }
+
+    /// Start in demo mode, with its synthetic data made afresh; the caller restarts the app
+    pub fn enter_demo(&mut self, base_data: &Path, base_config: &Path) -> Result<(), String> {
+        for dir in [profile_dir(base_data, DEMO_PROFILE), profile_dir(base_config, DEMO_PROFILE)] {
+            match std::fs::remove_dir_all(&dir) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(format!("Cannot reset {}: {}", dir.display(), e)),
+                _ => {}
+            }
+        }
+        self.active = Some(DEMO_PROFILE.to_string());
+        self.save(base_config)
+    }
+
+    /// Remove a profile that is not in use, with everything it kept
+    pub fn delete(&mut self, base_data: &Path, base_config: &Path, name: &str, passphrase: Option<&str>, active: &str) -> Result<(), String> {
+        if name == DEFAULT_PROFILE {
+            return Err("The default profile cannot be deleted".to_string());
+        }
+        if name == active {
+            return Err(format!("Profile '{}' is in use; switch to another profile first", name));
+        }
+        self.find(name).ok_or_else(|| format!("No profile '{}'", name))?;
+        self.verify(name, passphrase)?;
+        for dir in [profile_dir(base_data, name), profile_dir(base_config, name)] {
+            match std::fs::remove_dir_all(&dir) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(format!("Cannot remove {}: {}", dir.display(), e)),
+                _ => {}
+            }
+        }
+        if let Ok(entry) = unlock_entry(name) {
+            let _ = entry.delete_credential();
+        }
+        self.profiles.retain(|profile| profile.name != name);
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.save(base_config)
+    }
+}
+
+/// Where a profile keeps its files under one of the app directories
+pub fn profile_dir(base: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        base.to_path_buf()
+    } else {
+        base.join("profiles").join(name)
+    }
+}
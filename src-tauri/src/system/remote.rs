@@ -0,0 +1,824 @@
+// Remote access from a browser
+// An optional HTTPS server lets the user reach the running console from another
+// device, once its bind address opens it beyond this machine. It serves a small web page and exposes the session API: `POST
+// /api/invoke` takes `{command, args}` like a Tauri invoke, and `/ws` carries the
+// same calls over a WebSocket together with the session events the app emits.
+// Every request needs an access token; a token is either read-only (sessions,
+// status, history and output) or full control (also opening and closing
+// sessions and running commands). Tokens are stored hashed and shown only when
+// created. Without a configured certificate a self-signed one is made with
+// openssl; its fingerprint is shown so the browser warning can be checked.
+// Single sessions can also be shared through it with a link of their own (see
+// `session_share`); those links open `/share` and reach nothing else.
+
+use chrono::{DateTime, Utc};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{HeaderValue, AUTHORIZATION, CONNECTION, CONTENT_TYPE, UPGRADE};
+use hyper::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Listener, Manager};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_rustls::rustls;
+
+use super::session_share::{shares_changed, CreatedShare, SessionShare, SessionShares, DEFAULT_SHARE_MINUTES};
+use super::websocket::{self, Message};
+use crate::AppState;
+
+const PAGE: &str = include_str!("remote.html");
+const SHARE_PAGE: &str = include_str!("share.html");
+/// How often a viewer's share is checked for expiry, revocation and input grants
+const SHARE_CHECK_SECS: u64 = 2;
+/// How often an open WebSocket's token is checked, so a revoked one stops receiving events
+const TOKEN_CHECK_SECS: u64 = 2;
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// App events forwarded to WebSocket clients
+const FORWARDED_EVENTS: [&str; 6] = [
+    crate::terminal::status::STATUS_EVENT,
+    crate::terminal::prompt::PROMPT_EVENT,
+    crate::terminal::command_queue::QUEUE_EVENT,
+    crate::terminal::notifications::NOTIFICATION_EVENT,
+    crate::terminal::DIRECTORY_RECOVERED_EVENT,
+    crate::terminal::DIRECTORY_CHANGED_EVENT,
+];
+/// Commands a read-only token may call
+const READ_ONLY_COMMANDS: [&str; 7] = [
+    "get_all_sessions",
+    "get_session_status",
+    "get_running_commands",
+    "get_terminal_output",
+    "get_history_page",
+    "get_output_page",
+    "search_command_history",
+];
+/// Commands that also need a full-control token
+const CONTROL_COMMANDS: [&str; 5] = ["create_terminal", "execute_command", "write_to_terminal", "terminate_command", "close_terminal_session"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteMode {
+    ReadOnly,
+    FullControl,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteToken {
+    pub id: String,
+    pub name: String,
+    pub mode: RemoteMode,
+    /// SHA-256 of the token; the token itself is only shown when created
+    #[serde(skip_serializing, default)]
+    hash: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteOptions {
+    /// Start the server when the app starts
+    pub start_on_launch: bool,
+    /// Only this machine by default; e.g. "0.0.0.0" makes the server reachable from other devices
+    pub bind_address: String,
+    pub port: u16,
+    /// PEM certificate chain and key; a self-signed pair is made when unset
+    pub certificate_path: Option<String>,
+    pub key_path: Option<String>,
+}
+
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        Self {
+            start_on_launch: false,
+            bind_address: "127.0.0.1".to_string(),
+            port: 7443,
+            certificate_path: None,
+            key_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteSettings {
+    #[serde(flatten)]
+    pub options: RemoteOptions,
+    pub tokens: Vec<RemoteToken>,
+}
+
+impl RemoteSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("remote_access.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Hashes are written here, unlike in what the frontend is sent
+        let mut value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        if let Some(tokens) = value.get_mut("tokens").and_then(Value::as_array_mut) {
+            for (token, stored) in tokens.iter_mut().zip(&self.tokens) {
+                token["hash"] = Value::String(stored.hash.clone());
+            }
+        }
+        let json = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    fn authenticate(&mut self, token: &str) -> Option<RemoteMode> {
+        let hash = hash_token(token);
+        let stored = self.tokens.iter_mut().find(|t| !t.hash.is_empty() && t.hash == hash)?;
+        stored.last_used = Some(Utc::now());
+        Some(stored.mode)
+    }
+}
+
+pub(super) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// A new token, shown to the user this once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedToken {
+    pub id: String,
+    pub name: String,
+    pub mode: RemoteMode,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteStatus {
+    pub running: bool,
+    pub url: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub connected_clients: usize,
+    /// SHA-256 fingerprint of the certificate in use, to compare with what the browser shows
+    pub certificate_fingerprint: Option<String>,
+    pub self_signed: bool,
+    pub options: RemoteOptions,
+    pub tokens: Vec<RemoteToken>,
+    /// Sessions shared through the server right now
+    pub shares: Vec<SessionShare>,
+}
+
+struct Running {
+    address: SocketAddr,
+    started_at: DateTime<Utc>,
+    fingerprint: String,
+    self_signed: bool,
+    shutdown: watch::Sender<bool>,
+    listeners: Vec<tauri::EventId>,
+    clients: Arc<AtomicUsize>,
+}
+
+/// Shared by every connection of a running server
+struct Context {
+    app: AppHandle,
+    settings: Arc<RwLock<RemoteSettings>>,
+    shares: Arc<RwLock<SessionShares>>,
+    events: broadcast::Sender<String>,
+    clients: Arc<AtomicUsize>,
+    shutdown: watch::Receiver<bool>,
+}
+
+pub struct RemoteServer {
+    settings: Arc<RwLock<RemoteSettings>>,
+    shares: Arc<RwLock<SessionShares>>,
+    running: Option<Running>,
+}
+
+impl Default for RemoteServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RemoteServer {
+    pub fn new() -> Self {
+        Self { settings: Arc::new(RwLock::new(RemoteSettings::load())), shares: Arc::default(), running: None }
+    }
+
+    pub fn starts_on_launch(&self) -> bool {
+        self.settings.read().unwrap().options.start_on_launch
+    }
+
+    pub fn status(&self) -> RemoteStatus {
+        let settings = self.settings.read().unwrap();
+        let running = self.running.as_ref();
+        RemoteStatus {
+            running: running.is_some(),
+            url: running.map(|r| {
+                let host = if r.address.ip().is_unspecified() { local_hostname() } else { r.address.ip().to_string() };
+                format!("https://{}:{}/", host, r.address.port())
+            }),
+            started_at: running.map(|r| r.started_at),
+            connected_clients: running.map_or(0, |r| r.clients.load(Ordering::Relaxed)),
+            certificate_fingerprint: running.map(|r| r.fingerprint.clone()),
+            self_signed: running.is_some_and(|r| r.self_signed),
+            options: settings.options.clone(),
+            tokens: settings.tokens.clone(),
+            shares: self.shares.write().unwrap().active(),
+        }
+    }
+
+    pub fn set_options(&mut self, options: RemoteOptions) -> Result<(), String> {
+        if options.certificate_path.is_some() != options.key_path.is_some() {
+            return Err("Set both the certificate and the key, or neither".to_string());
+        }
+        let mut settings = self.settings.write().unwrap();
+        let mut updated = settings.clone();
+        updated.options = options;
+        updated.save()?;
+        *settings = updated;
+        Ok(())
+    }
+
+    pub fn create_token(&mut self, name: &str, mode: RemoteMode) -> Result<CreatedToken, String> {
+        let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let record = RemoteToken {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: if name.trim().is_empty() { "Remote device".to_string() } else { name.trim().to_string() },
+            mode,
+            hash: hash_token(&token),
+            created_at: Utc::now(),
+            last_used: None,
+        };
+        let created = CreatedToken { id: record.id.clone(), name: record.name.clone(), mode, token };
+        let mut settings = self.settings.write().unwrap();
+        settings.tokens.push(record);
+        settings.save()?;
+        Ok(created)
+    }
+
+    /// Revoked tokens stop working at once; open WebSocket connections using one close within `TOKEN_CHECK_SECS`
+    pub fn revoke_token(&mut self, id: &str) -> Result<(), String> {
+        let mut settings = self.settings.write().unwrap();
+        let before = settings.tokens.len();
+        settings.tokens.retain(|t| t.id != id);
+        if settings.tokens.len() == before {
+            return Err(format!("No remote access token {}", id));
+        }
+        settings.save()
+    }
+
+    pub async fn start(&mut self, app: AppHandle) -> Result<RemoteStatus, String> {
+        if self.running.is_some() {
+            return Ok(self.status());
+        }
+        let options = self.settings.read().unwrap().options.clone();
+        if self.settings.read().unwrap().tokens.is_empty() && self.shares.write().unwrap().is_empty() {
+            return Err("Create an access token before starting remote access".to_string());
+        }
+        let self_signed = options.certificate_path.is_none();
+        let (certificate, key) = match (&options.certificate_path, &options.key_path) {
+            (Some(certificate), Some(key)) => (PathBuf::from(certificate), PathBuf::from(key)),
+            _ => self_signed_certificate().await?,
+        };
+        let (tls, fingerprint) = tls_config(&certificate, &key)?;
+
+        let listener = tokio::net::TcpListener::bind((options.bind_address.as_str(), options.port))
+            .await
+            .map_err(|e| format!("Cannot listen on {}:{}: {}", options.bind_address, options.port, e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+
+        let (events, _) = broadcast::channel(256);
+        let listeners = FORWARDED_EVENTS
+            .iter()
+            .map(|&name| {
+                let events = events.clone();
+                app.listen_any(name, move |event| {
+                    let payload: Value = serde_json::from_str(event.payload()).unwrap_or(Value::Null);
+                    let _ = events.send(json!({ "event": name, "payload": payload }).to_string());
+                })
+            })
+            .collect();
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let clients = Arc::new(AtomicUsize::new(0));
+        let context = Arc::new(Context {
+            app,
+            settings: self.settings.clone(),
+            shares: self.shares.clone(),
+            events,
+            clients: clients.clone(),
+            shutdown: shutdown_rx,
+        });
+        tokio::spawn(serve(listener, tokio_rustls::TlsAcceptor::from(Arc::new(tls)), context));
+
+        println!("🌐 Remote access listening on https://{}", address);
+        self.running = Some(Running { address, started_at: Utc::now(), fingerprint, self_signed, shutdown, listeners, clients });
+        Ok(self.status())
+    }
+
+    /// Stop serving and drop every connection; false when it was not running
+    pub fn stop(&mut self, app: &AppHandle) -> bool {
+        let Some(running) = self.running.take() else { return false };
+        let _ = running.shutdown.send(true);
+        for id in running.listeners {
+            app.unlisten(id);
+        }
+        // Share links go through the server, so they end with it
+        self.shares.write().unwrap().clear();
+        shares_changed(app, &self.shares);
+        println!("🌐 Remote access stopped");
+        true
+    }
+
+    /// A time-limited link to a live view of one session, starting the server when it is not running
+    pub async fn share_session(&mut self, app: AppHandle, session_id: &str, minutes: Option<u32>, allow_input: bool) -> Result<CreatedShare, String> {
+        let minutes = minutes.unwrap_or(DEFAULT_SHARE_MINUTES);
+        let (share, token) = self.shares.write().unwrap().create(session_id, minutes, allow_input)?;
+        let status = match self.start(app.clone()).await {
+            Ok(status) => status,
+            Err(e) => {
+                let _ = self.shares.write().unwrap().revoke(&share.id);
+                return Err(e);
+            }
+        };
+        // The fragment stays in the browser; only the page's WebSocket presents the token
+        let url = format!("{}share#{}", status.url.unwrap_or_default(), token);
+        shares_changed(&app, &self.shares);
+        let (expiry_app, shares) = (app.clone(), self.shares.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(minutes as u64 * 60)).await;
+            shares_changed(&expiry_app, &shares);
+        });
+        println!("🔗 Session {} shared for {} minutes{}", session_id, minutes, if allow_input { " with input" } else { "" });
+        Ok(CreatedShare { share, url })
+    }
+
+    pub fn shares(&self) -> Vec<SessionShare> {
+        self.shares.write().unwrap().active()
+    }
+
+    /// Grant or take back a viewer's input; open views follow within a few seconds
+    pub fn set_share_input(&mut self, app: &AppHandle, id: &str, allow_input: bool) -> Result<SessionShare, String> {
+        let share = self.shares.write().unwrap().set_input(id, allow_input)?;
+        shares_changed(app, &self.shares);
+        Ok(share)
+    }
+
+    pub fn stop_share(&mut self, app: &AppHandle, id: &str) -> Result<(), String> {
+        self.shares.write().unwrap().revoke(id)?;
+        shares_changed(app, &self.shares);
+        Ok(())
+    }
+}
+
+fn local_hostname() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "localhost".to_string())
+}
+
+/// The self-signed pair kept in the config directory, made with openssl the first time
+async fn self_signed_certificate() -> Result<(PathBuf, PathBuf), String> {
+    let dir = crate::ai::config_directory().join("remote");
+    let (certificate, key) = (dir.join("certificate.pem"), dir.join("key.pem"));
+    if certificate.is_file() && key.is_file() {
+        return Ok((certificate, key));
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let host = local_hostname();
+    let output = tokio::process::Command::new("openssl")
+        .args(["req", "-x509", "-newkey", "rsa:2048", "-nodes", "-days", "825", "-subj", "/CN=pH7Console remote access", "-keyout"])
+        .arg(&key)
+        .arg("-out")
+        .arg(&certificate)
+        .arg("-addext")
+        .arg(format!("subjectAltName=DNS:{},DNS:localhost,IP:127.0.0.1", host))
+        .output()
+        .await
+        .map_err(|_| "openssl is needed to make a certificate; install it or set a certificate and key in the remote access settings".to_string())?;
+    if !output.status.success() {
+        return Err(format!("openssl could not make a certificate: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok((certificate, key))
+}
+
+fn tls_config(certificate: &Path, key: &Path) -> Result<(rustls::ServerConfig, String), String> {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+    let chain: Vec<CertificateDer<'static>> = CertificateDer::pem_file_iter(certificate)
+        .and_then(|certs| certs.collect())
+        .map_err(|e| format!("Cannot read certificate {}: {}", certificate.display(), e))?;
+    let first = chain.first().ok_or_else(|| format!("No certificate in {}", certificate.display()))?;
+    let fingerprint = Sha256::digest(first.as_ref()).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":");
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| format!("Cannot read key {}: {}", key.display(), e))?;
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .map_err(|e| format!("Certificate and key do not work together: {}", e))?;
+    Ok((config, fingerprint))
+}
+
+async fn serve(listener: tokio::net::TcpListener, acceptor: tokio_rustls::TlsAcceptor, context: Arc<Context>) {
+    let mut shutdown = context.shutdown.clone();
+    loop {
+        let stream = tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            },
+        };
+        let (acceptor, context) = (acceptor.clone(), context.clone());
+        tokio::spawn(async move {
+            let Ok(tls) = acceptor.accept(stream).await else { return };
+            let mut shutdown = context.shutdown.clone();
+            let service_context = context.clone();
+            let service = hyper::service::service_fn(move |request| handle(request, service_context.clone()));
+            let connection = hyper::server::conn::http1::Builder::new()
+                .serve_connection(hyper_util::rt::TokioIo::new(tls), service)
+                .with_upgrades();
+            tokio::select! {
+                _ = connection => {}
+                _ = shutdown.changed() => {}
+            }
+        });
+    }
+}
+
+fn respond(status: StatusCode, content_type: &str, body: impl Into<Bytes>) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_str(content_type).unwrap_or(HeaderValue::from_static("text/plain")));
+    response
+}
+
+fn respond_json(status: StatusCode, value: Value) -> Response<Full<Bytes>> {
+    respond(status, "application/json", value.to_string())
+}
+
+/// The token from `Authorization: Bearer`, or from `?token=` where browsers cannot set headers (WebSocket)
+fn request_token(request: &Request<Incoming>) -> Option<String> {
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| {
+            request.uri().query()?.split('&').find_map(|pair| pair.strip_prefix("token=")).map(str::to_string)
+        })
+}
+
+fn authenticate(context: &Context, token: Option<&str>) -> Option<RemoteMode> {
+    context.settings.write().unwrap().authenticate(token?)
+}
+
+async fn handle(request: Request<Incoming>, context: Arc<Context>) -> Result<Response<Full<Bytes>>, Infallible> {
+    if request.method() == Method::GET && request.uri().path() == "/" {
+        return Ok(respond(StatusCode::OK, "text/html; charset=utf-8", PAGE));
+    }
+    // Share links reach their own session's view and nothing else
+    if request.method() == Method::GET && request.uri().path() == "/share" {
+        return Ok(respond(StatusCode::OK, "text/html; charset=utf-8", SHARE_PAGE));
+    }
+    if request.method() == Method::GET && request.uri().path() == "/share/ws" {
+        let share = request_token(&request).and_then(|token| context.shares.read().unwrap().authenticate(&token));
+        return Ok(match share {
+            Some(share) => upgrade(request, context, Client::Viewer(share)),
+            None => respond_json(StatusCode::UNAUTHORIZED, json!({ "error": "The share link has expired or was stopped" })),
+        });
+    }
+    let token = request_token(&request);
+    let Some(mode) = authenticate(&context, token.as_deref()) else {
+        return Ok(respond_json(StatusCode::UNAUTHORIZED, json!({ "error": "A valid access token is required" })));
+    };
+
+    Ok(match (request.method().clone(), request.uri().path()) {
+        (Method::GET, "/api/access") => respond_json(StatusCode::OK, json!({ "mode": mode, "commands": allowed_commands(mode) })),
+        (Method::POST, "/api/invoke") => {
+            let body = match http_body_util::Limited::new(request.into_body(), MAX_BODY_BYTES).collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(e) => return Ok(respond_json(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() }))),
+            };
+            let call: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            let command = call.get("command").and_then(Value::as_str).unwrap_or_default();
+            match invoke(&context.app, mode, command, call.get("args").unwrap_or(&Value::Null)).await {
+                Ok(result) => respond_json(StatusCode::OK, json!({ "result": result })),
+                Err(RemoteError::Forbidden(e)) => respond_json(StatusCode::FORBIDDEN, json!({ "error": e })),
+                Err(RemoteError::Failed(e)) => respond_json(StatusCode::BAD_REQUEST, json!({ "error": e })),
+            }
+        }
+        (Method::GET, "/ws") => upgrade(request, context, Client::Token(token.unwrap_or_default())),
+        _ => respond_json(StatusCode::NOT_FOUND, json!({ "error": "Not found" })),
+    })
+}
+
+fn allowed_commands(mode: RemoteMode) -> Vec<&'static str> {
+    let control: &[&str] = if mode == RemoteMode::FullControl { &CONTROL_COMMANDS } else { &[] };
+    READ_ONLY_COMMANDS.iter().chain(control).copied().collect()
+}
+
+enum RemoteError {
+    Forbidden(String),
+    Failed(String),
+}
+
+impl From<String> for RemoteError {
+    fn from(error: String) -> Self {
+        Self::Failed(error)
+    }
+}
+
+/// A camelCase argument, as the webview passes them
+fn arg<T: serde::de::DeserializeOwned>(args: &Value, name: &str) -> Result<T, String> {
+    serde_json::from_value(args.get(name).cloned().unwrap_or(Value::Null)).map_err(|e| format!("Invalid `{}`: {}", name, e))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, RemoteError> {
+    serde_json::to_value(value).map_err(|e| RemoteError::Failed(e.to_string()))
+}
+
+/// Run one of the app's commands for a remote client
+async fn invoke(app: &AppHandle, mode: RemoteMode, command: &str, args: &Value) -> Result<Value, RemoteError> {
+    if !allowed_commands(mode).contains(&command) {
+        return Err(RemoteError::Forbidden(if CONTROL_COMMANDS.contains(&command) {
+            format!("`{}` needs a full-control token", command)
+        } else {
+            format!("`{}` is not available remotely", command)
+        }));
+    }
+    let state = app.state::<AppState>();
+    match command {
+        "get_all_sessions" => to_json(crate::commands::get_all_sessions(state).await?),
+        "get_session_status" => to_json(crate::commands::get_session_status(state, arg(args, "sessionId")?).await?),
+        "get_running_commands" => to_json(crate::commands::get_running_commands(state, arg(args, "sessionId")?).await?),
+        "get_terminal_output" => {
+            let history = state.terminal_manager.lock().await.get_command_history(arg(args, "limit")?);
+            let mut pages = state.output_pages.lock().await;
+            to_json(history.into_iter().map(|execution| pages.cap(execution)).collect::<Vec<_>>())
+        }
+        "get_history_page" => {
+            let limit: Option<usize> = arg(args, "limit")?;
+            let page = state.terminal_manager.lock().await.history_page(arg(args, "before")?, limit.unwrap_or(100));
+            let mut pages = state.output_pages.lock().await;
+            to_json(page.map(|execution| pages.cap(execution)))
+        }
+        "get_output_page" => {
+            let offset: Option<usize> = arg(args, "offset")?;
+            let compress: Option<bool> = arg(args, "compress")?;
+            let handle: String = arg(args, "handle")?;
+            to_json(state.output_pages.lock().await.page(&handle, offset.unwrap_or(0), arg(args, "length")?, compress.unwrap_or(false))?)
+        }
+        "search_command_history" => to_json(crate::commands::search_command_history(state, arg(args, "pattern")?).await?),
+        "create_terminal" => to_json(
+            crate::commands::create_terminal(app.clone(), state, arg(args, "title")?, arg(args, "workingDirectory")?, None, None, None).await?,
+        ),
+        "execute_command" => {
+            let (session_id, line): (String, String) = (arg(args, "sessionId")?, arg(args, "command")?);
+            println!("🌐 Remote client runs in {}: {}", session_id, line);
+            to_json(crate::commands::execute_command(app.clone(), state, session_id, line, None, arg(args, "concurrently")?, arg(args, "options")?).await?)
+        }
+        "write_to_terminal" => to_json(crate::commands::write_to_terminal(state, arg(args, "sessionId")?, arg(args, "data")?).await?),
+        "terminate_command" => to_json(
+            crate::commands::terminate_command(state, arg(args, "sessionId")?, arg(args, "executionId")?, arg(args, "signal")?).await?,
+        ),
+        "close_terminal_session" => to_json(crate::commands::close_terminal_session(app.clone(), state, arg(args, "sessionId")?).await?),
+        _ => Err(RemoteError::Forbidden(format!("`{}` is not available remotely", command))),
+    }
+}
+
+/// Who a WebSocket connection is for
+enum Client {
+    /// A token holder, checked again on every call
+    Token(String),
+    /// A viewer of one shared session
+    Viewer(SessionShare),
+}
+
+fn upgrade(mut request: Request<Incoming>, context: Arc<Context>, client: Client) -> Response<Full<Bytes>> {
+    let is_websocket = request
+        .headers()
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let Some(key) = request.headers().get("sec-websocket-key").and_then(|value| value.to_str().ok()).map(str::to_string) else {
+        return respond_json(StatusCode::BAD_REQUEST, json!({ "error": "Expected a WebSocket upgrade" }));
+    };
+    if !is_websocket {
+        return respond_json(StatusCode::BAD_REQUEST, json!({ "error": "Expected a WebSocket upgrade" }));
+    }
+    let upgrading = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        match upgrading.await {
+            Ok(upgraded) => {
+                let stream = hyper_util::rt::TokioIo::new(upgraded);
+                match client {
+                    Client::Token(token) => {
+                        context.clients.fetch_add(1, Ordering::Relaxed);
+                        websocket_session(stream, &context, &token).await;
+                        context.clients.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    Client::Viewer(share) => share_viewer(stream, &context, share).await,
+                }
+            }
+            Err(e) => println!("⚠️ Remote WebSocket upgrade failed: {}", e),
+        }
+    });
+    let mut response = respond(StatusCode::SWITCHING_PROTOCOLS, "text/plain", Bytes::new());
+    let headers = response.headers_mut();
+    headers.remove(CONTENT_TYPE);
+    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    if let Ok(accept) = HeaderValue::from_str(&websocket::accept_key(&key)) {
+        headers.insert("sec-websocket-accept", accept);
+    }
+    response
+}
+
+/// Messages queued for a client, written in order by a task of their own
+fn spawn_writer<W>(mut writer: W) -> (mpsc::UnboundedSender<Message>, tokio::task::JoinHandle<()>)
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let (outgoing, mut queued) = mpsc::unbounded_channel::<Message>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = queued.recv().await {
+            let closing = message == Message::Close;
+            if websocket::write_message(&mut writer, &message).await.is_err() || closing {
+                break;
+            }
+        }
+    });
+    (outgoing, writer_task)
+}
+
+/// Messages from a client, read by a task of their own: a read raced against events in
+/// `select!` could be dropped halfway through a frame. The channel closes when the client
+/// leaves or breaks the protocol.
+fn spawn_reader<R>(reader: R) -> (mpsc::Receiver<Message>, tokio::task::JoinHandle<()>)
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    let (incoming, received) = mpsc::channel::<Message>(16);
+    let reader_task = tokio::spawn(async move {
+        let mut reader = websocket::MessageReader::new(reader);
+        while let Ok(Some(message)) = reader.read_message().await {
+            let closing = message == Message::Close;
+            if incoming.send(message).await.is_err() || closing {
+                break;
+            }
+        }
+    });
+    (received, reader_task)
+}
+
+/// Calls in, replies and app events out, until the client leaves, its token is revoked or the server stops
+async fn websocket_session<S>(stream: S, context: &Arc<Context>, token: &str)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+    let (outgoing, writer_task) = spawn_writer(writer);
+    let (mut incoming, reader_task) = spawn_reader(reader);
+
+    let mut events = context.events.subscribe();
+    let mut shutdown = context.shutdown.clone();
+    let mut check = tokio::time::interval(std::time::Duration::from_secs(TOKEN_CHECK_SECS));
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = check.tick() => {
+                if authenticate(context, Some(token)).is_none() {
+                    break;
+                }
+            }
+            event = events.recv() => match event {
+                Ok(event) => { let _ = outgoing.send(Message::Text(event)); }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = incoming.recv() => match message {
+                Some(Message::Text(text)) => {
+                    let Some(mode) = authenticate(context, Some(token)) else { break };
+                    let call: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+                    let (app, outgoing) = (context.app.clone(), outgoing.clone());
+                    tokio::spawn(async move {
+                        let command = call.get("command").and_then(Value::as_str).unwrap_or_default();
+                        let reply = match invoke(&app, mode, command, call.get("args").unwrap_or(&Value::Null)).await {
+                            Ok(result) => json!({ "id": call.get("id"), "result": result }),
+                            Err(RemoteError::Forbidden(e) | RemoteError::Failed(e)) => json!({ "id": call.get("id"), "error": e }),
+                        };
+                        let _ = outgoing.send(Message::Text(reply.to_string()));
+                    });
+                }
+                Some(Message::Ping(data)) => { let _ = outgoing.send(Message::Pong(data)); }
+                Some(Message::Binary(_) | Message::Pong(_)) => {}
+                Some(Message::Close) | None => break,
+            },
+        }
+    }
+    reader_task.abort();
+    let _ = outgoing.send(Message::Close);
+    drop(outgoing);
+    let _ = writer_task.await;
+}
+
+/// A shared session's live output out, and the viewer's typing in once granted, until the viewer
+/// leaves or the share ends
+async fn share_viewer<S>(stream: S, context: &Arc<Context>, share: SessionShare)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let state = context.app.state::<AppState>();
+    let (mut output, title) = {
+        let terminal_manager = state.terminal_manager.lock().await;
+        (terminal_manager.subscribe_output(), terminal_manager.get_session(&share.session_id).map(|session| session.title.clone()))
+    };
+    let Some(title) = title else { return };
+    let (reader, writer) = tokio::io::split(stream);
+    let (outgoing, writer_task) = spawn_writer(writer);
+    let (mut incoming, reader_task) = spawn_reader(reader);
+    let announce = |share: &SessionShare| {
+        let payload = json!({ "title": title, "allowInput": share.allow_input, "expiresAt": share.expires_at });
+        Message::Text(json!({ "event": "share", "payload": payload }).to_string())
+    };
+    let _ = outgoing.send(announce(&share));
+    context.shares.write().unwrap().viewer_joined(&share.id);
+    shares_changed(&context.app, &context.shares);
+    println!("👀 A viewer joined the shared session {}", share.session_id);
+
+    let mut allow_input = share.allow_input;
+    let mut check = tokio::time::interval(std::time::Duration::from_secs(SHARE_CHECK_SECS));
+    let mut shutdown = context.shutdown.clone();
+    // Why the view ended, for the viewer; `None` when they left
+    let ended = loop {
+        tokio::select! {
+            _ = shutdown.changed() => break Some("Sharing was stopped"),
+            _ = check.tick() => {
+                let current = context.shares.read().unwrap().get(&share.id).cloned();
+                match current {
+                    None if Utc::now() >= share.expires_at => break Some("The share link has expired"),
+                    None => break Some("Sharing was stopped"),
+                    Some(current) if current.allow_input != allow_input => {
+                        allow_input = current.allow_input;
+                        let _ = outgoing.send(announce(&current));
+                    }
+                    Some(_) => {}
+                }
+                if state.terminal_manager.lock().await.get_session(&share.session_id).is_none() {
+                    break Some("The session was closed");
+                }
+            }
+            chunk = output.recv() => match chunk {
+                Ok(chunk) if chunk.session_id == share.session_id => {
+                    let _ = outgoing.send(Message::Text(json!({ "event": "output", "payload": chunk.data }).to_string()));
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break Some("Sharing was stopped"),
+            },
+            message = incoming.recv() => match message {
+                Some(Message::Text(text)) => {
+                    let input = serde_json::from_str::<Value>(&text).ok().and_then(|call| call.get("input").and_then(Value::as_str).map(str::to_string));
+                    let allowed = context.shares.read().unwrap().get(&share.id).is_some_and(|current| current.allow_input);
+                    let error = match input {
+                        Some(data) if allowed => state.terminal_manager.lock().await.write_input(&share.session_id, &data).err(),
+                        Some(_) => Some("Input has not been granted".to_string()),
+                        None => Some("Expected {\"input\": \"...\"}".to_string()),
+                    };
+                    if let Some(e) = error {
+                        let _ = outgoing.send(Message::Text(json!({ "error": e }).to_string()));
+                    }
+                }
+                Some(Message::Ping(data)) => { let _ = outgoing.send(Message::Pong(data)); }
+                Some(Message::Binary(_) | Message::Pong(_)) => {}
+                Some(Message::Close) | None => break None,
+            },
+        }
+    };
+    reader_task.abort();
+    if let Some(reason) = ended {
+        let _ = outgoing.send(Message::Text(json!({ "event": "share-ended", "payload": reason }).to_string()));
+    }
+    let _ = outgoing.send(Message::Close);
+    drop(outgoing);
+    let _ = writer_task.await;
+    context.shares.write().unwrap().viewer_left(&share.id);
+    shares_changed(&context.app, &context.shares);
+    println!("👀 A viewer left the shared session {}", share.session_id);
+}
@@ -0,0 +1,128 @@
+// Shared sessions
+// A session can be shared with a collaborator through the remote access server:
+// `share_session` makes a link that opens a live, read-only view of the
+// session's output, valid for a limited time. The owner can grant (and take
+// back) input control while the link is in use, and stop sharing at any time.
+// A share token works for its one session only and nothing else the server
+// offers; it sits in the link's fragment, so it is not sent with the page
+// request. Shares live in memory: they end when the server stops or the app
+// quits. The list of active shares is emitted whenever it changes, so the app
+// can show which sessions are being watched and by how many viewers.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Emitter};
+
+use super::remote::hash_token;
+
+/// Emitted with the active `SessionShare`s whenever one starts, ends or gains or loses a viewer
+pub const SHARES_EVENT: &str = "session-shares-changed";
+pub const DEFAULT_SHARE_MINUTES: u32 = 60;
+pub const MAX_SHARE_MINUTES: u32 = 24 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionShare {
+    pub id: String,
+    pub session_id: String,
+    /// The viewer may type into the session
+    pub allow_input: bool,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Browsers watching right now
+    pub viewers: usize,
+    #[serde(skip)]
+    hash: String,
+}
+
+/// A new share; the link carries its token and is shown this once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedShare {
+    pub share: SessionShare,
+    pub url: String,
+}
+
+#[derive(Default)]
+pub struct SessionShares {
+    shares: Vec<SessionShare>,
+}
+
+impl SessionShares {
+    /// A share and its token, valid for `minutes`
+    pub fn create(&mut self, session_id: &str, minutes: u32, allow_input: bool) -> Result<(SessionShare, String), String> {
+        if minutes == 0 || minutes > MAX_SHARE_MINUTES {
+            return Err(format!("A share lasts between 1 and {} minutes", MAX_SHARE_MINUTES));
+        }
+        let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let now = Utc::now();
+        let share = SessionShare {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            allow_input,
+            created_at: now,
+            expires_at: now + Duration::minutes(minutes as i64),
+            viewers: 0,
+            hash: hash_token(&token),
+        };
+        self.shares.push(share.clone());
+        Ok((share, token))
+    }
+
+    /// Shares still valid, the expired ones dropped
+    pub fn active(&mut self) -> Vec<SessionShare> {
+        let now = Utc::now();
+        self.shares.retain(|share| share.expires_at > now);
+        self.shares.clone()
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        self.active().is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SessionShare> {
+        self.shares.iter().find(|share| share.id == id && share.expires_at > Utc::now())
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<SessionShare> {
+        let hash = hash_token(token);
+        self.shares.iter().find(|share| share.hash == hash && share.expires_at > Utc::now()).cloned()
+    }
+
+    pub fn set_input(&mut self, id: &str, allow_input: bool) -> Result<SessionShare, String> {
+        let share = self.shares.iter_mut().find(|share| share.id == id).ok_or_else(|| format!("No shared session {}", id))?;
+        share.allow_input = allow_input;
+        Ok(share.clone())
+    }
+
+    /// Viewers are told and disconnected within a few seconds
+    pub fn revoke(&mut self, id: &str) -> Result<(), String> {
+        let before = self.shares.len();
+        self.shares.retain(|share| share.id != id);
+        if self.shares.len() == before {
+            return Err(format!("No shared session {}", id));
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.shares.clear();
+    }
+
+    pub fn viewer_joined(&mut self, id: &str) {
+        if let Some(share) = self.shares.iter_mut().find(|share| share.id == id) {
+            share.viewers += 1;
+        }
+    }
+
+    pub fn viewer_left(&mut self, id: &str) {
+        if let Some(share) = self.shares.iter_mut().find(|share| share.id == id) {
+            share.viewers = share.viewers.saturating_sub(1);
+        }
+    }
+}
+
+/// Tell the app which sessions are shared now
+pub fn shares_changed(app: &AppHandle, shares: &Arc<RwLock<SessionShares>>) {
+    let active = shares.write().unwrap().active();
+    let _ = app.emit(SHARES_EVENT, active);
+}
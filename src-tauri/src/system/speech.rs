@@ -0,0 +1,305 @@
+// Text-to-speech
+// AI explanations, error summaries and long-command completion alerts can be
+// read aloud with the OS speech engine: `say` on macOS, System.Speech through
+// PowerShell on Windows and espeak-ng or speech-dispatcher on Linux. Each
+// category has its own switch, voice and rate. Speech follows the notification
+// settings: focus mode and quiet hours that keep a notification out of the
+// system tray keep it silent too. A new utterance interrupts the one playing.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::path_inspector;
+
+/// Longer texts are cut, at a sentence end where possible
+const MAX_SPOKEN_CHARS: usize = 1500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechCategory {
+    Explanation,
+    ErrorSummary,
+    CompletionAlert,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CategoryVoice {
+    pub enabled: bool,
+    /// Engine voice name; the system default when unset
+    pub voice: Option<String>,
+    /// Words per minute
+    pub rate: Option<u32>,
+}
+
+impl Default for CategoryVoice {
+    fn default() -> Self {
+        Self { enabled: true, voice: None, rate: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeechSettings {
+    /// Off until the user turns speech on
+    pub enabled: bool,
+    pub explanation: CategoryVoice,
+    pub error_summary: CategoryVoice,
+    pub completion_alert: CategoryVoice,
+    /// Stay silent when notifications are suppressed by focus mode or quiet hours
+    pub follow_notification_settings: bool,
+}
+
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            explanation: CategoryVoice::default(),
+            error_summary: CategoryVoice::default(),
+            completion_alert: CategoryVoice::default(),
+            follow_notification_settings: true,
+        }
+    }
+}
+
+impl SpeechSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("speech_settings.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn voice(&self, category: SpeechCategory) -> &CategoryVoice {
+        match category {
+            SpeechCategory::Explanation => &self.explanation,
+            SpeechCategory::ErrorSummary => &self.error_summary,
+            SpeechCategory::CompletionAlert => &self.completion_alert,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechResult {
+    pub spoken: bool,
+    /// Why nothing was said (speech off, category off, quiet hours...)
+    pub skipped: Option<String>,
+}
+
+impl SpeechResult {
+    pub fn skipped(reason: impl Into<String>) -> Self {
+        Self { spoken: false, skipped: Some(reason.into()) }
+    }
+}
+
+/// Speech settings and the utterance playing
+pub struct Speaker {
+    settings: SpeechSettings,
+    current: Option<tokio::process::Child>,
+}
+
+impl Default for Speaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Speaker {
+    pub fn new() -> Self {
+        Self { settings: SpeechSettings::load(), current: None }
+    }
+
+    pub fn settings(&self) -> &SpeechSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: SpeechSettings) -> Result<(), String> {
+        settings.save()?;
+        if !settings.enabled {
+            self.stop();
+        }
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Why a category should stay silent, before notification settings are considered
+    pub fn muted(&self, category: SpeechCategory) -> Option<String> {
+        if !self.settings.enabled {
+            Some("speech is off".to_string())
+        } else if !self.settings.voice(category).enabled {
+            Some(format!("{:?} speech is off", category))
+        } else {
+            None
+        }
+    }
+
+    pub fn follows_notifications(&self) -> bool {
+        self.settings.follow_notification_settings
+    }
+
+    /// Start reading `text`, interrupting whatever is being said
+    pub fn speak(&mut self, category: SpeechCategory, text: &str) -> Result<SpeechResult, String> {
+        if let Some(reason) = self.muted(category) {
+            return Ok(SpeechResult::skipped(reason));
+        }
+        let text = speakable(text);
+        if text.is_empty() {
+            return Ok(SpeechResult::skipped("nothing to say"));
+        }
+        let voice = self.settings.voice(category).clone();
+        let mut command = engine_command(&text, voice.voice.as_deref(), voice.rate)?;
+        self.stop();
+        let child = command
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Cannot start speech: {}", e))?;
+        self.current = Some(child);
+        Ok(SpeechResult { spoken: true, skipped: None })
+    }
+
+    /// Stop the utterance playing; false when nothing was
+    pub fn stop(&mut self) -> bool {
+        let Some(mut child) = self.current.take() else { return false };
+        child.try_wait().is_ok_and(|status| status.is_none()) && child.start_kill().is_ok()
+    }
+}
+
+/// Text as it should be heard: no escape codes, code fences, markdown marks or emoji
+fn speakable(text: &str) -> String {
+    let plain = crate::terminal::ansi::strip(text);
+    let spoken: String = plain
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .map(|line| line.trim().trim_start_matches(['#', '>', '-', '*']).trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || (c.is_ascii_punctuation() && !matches!(c, '`' | '*' | '_' | '#')))
+        .collect();
+    let spoken = spoken.split_whitespace().collect::<Vec<_>>().join(" ").trim_start_matches('-').trim_start().to_string();
+    if spoken.chars().count() <= MAX_SPOKEN_CHARS {
+        return spoken;
+    }
+    let cut: String = spoken.chars().take(MAX_SPOKEN_CHARS).collect();
+    match cut.rfind(". ") {
+        Some(end) => cut[..=end].to_string(),
+        None => cut,
+    }
+}
+
+fn on_path(name: &str) -> bool {
+    !path_inspector::find_all(name, &std::env::var("PATH").unwrap_or_default()).is_empty()
+}
+
+/// The OS speech engine invocation for `text`
+fn engine_command(text: &str, voice: Option<&str>, rate: Option<u32>) -> Result<tokio::process::Command, String> {
+    if cfg!(target_os = "macos") {
+        let mut command = tokio::process::Command::new("say");
+        if let Some(voice) = voice {
+            command.args(["-v", voice]);
+        }
+        if let Some(rate) = rate {
+            command.args(["-r", &rate.to_string()]);
+        }
+        // `speakable` text never starts with '-', so it is not taken for an option
+        command.arg(text);
+        return Ok(command);
+    }
+    if cfg!(windows) {
+        // The text goes through the environment so nothing in it is read as script
+        let mut script = String::from("Add-Type -AssemblyName System.Speech; $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; ");
+        if voice.is_some() {
+            script.push_str("$s.SelectVoice($env:PH7_SPEECH_VOICE); ");
+        }
+        if let Some(rate) = rate {
+            // System.Speech rates run from -10 to 10 around roughly 180 words per minute
+            script.push_str(&format!("$s.Rate = {}; ", ((rate as i32 - 180) / 20).clamp(-10, 10)));
+        }
+        script.push_str("$s.Speak($env:PH7_SPEECH_TEXT)");
+        let mut command = tokio::process::Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]).env("PH7_SPEECH_TEXT", text);
+        if let Some(voice) = voice {
+            command.env("PH7_SPEECH_VOICE", voice);
+        }
+        return Ok(command);
+    }
+    if let Some(binary) = ["espeak-ng", "espeak"].into_iter().find(|b| on_path(b)) {
+        let mut command = tokio::process::Command::new(binary);
+        if let Some(voice) = voice {
+            command.args(["-v", voice]);
+        }
+        if let Some(rate) = rate {
+            command.args(["-s", &rate.to_string()]);
+        }
+        command.arg("--").arg(text);
+        return Ok(command);
+    }
+    if on_path("spd-say") {
+        let mut command = tokio::process::Command::new("spd-say");
+        command.arg("--wait");
+        if let Some(voice) = voice {
+            command.args(["-y", voice]);
+        }
+        if let Some(rate) = rate {
+            // speech-dispatcher rates run from -100 to 100 around roughly 180 words per minute
+            command.args(["-r", &((rate as i32 - 180) / 2).clamp(-100, 100).to_string()]);
+        }
+        command.arg("--").arg(text);
+        return Ok(command);
+    }
+    Err("No speech engine found; install espeak-ng or speech-dispatcher".to_string())
+}
+
+/// Voice names the speech engine offers, for the per-category settings
+pub async fn list_voices() -> Result<Vec<String>, String> {
+    let (program, args): (&str, Vec<&str>) = if cfg!(target_os = "macos") {
+        ("say", vec!["-v", "?"])
+    } else if cfg!(windows) {
+        (
+            "powershell",
+            vec!["-NoProfile", "-Command", "Add-Type -AssemblyName System.Speech; (New-Object System.Speech.Synthesis.SpeechSynthesizer).GetInstalledVoices() | ForEach-Object { $_.VoiceInfo.Name }"],
+        )
+    } else if let Some(binary) = ["espeak-ng", "espeak"].into_iter().find(|b| on_path(b)) {
+        (binary, vec!["--voices"])
+    } else if on_path("spd-say") {
+        ("spd-say", vec!["-L"])
+    } else {
+        return Err("No speech engine found; install espeak-ng or speech-dispatcher".to_string());
+    };
+    let output = tokio::process::Command::new(program)
+        .args(&args)
+        .output()
+        .await
+        .map_err(|e| format!("Cannot run {}: {}", program, e))?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let voices: Vec<String> = if cfg!(target_os = "macos") {
+        // "Alex                en_US    # Most people recognize me by my voice."
+        listing.lines().filter_map(|line| line.split("  ").next()).map(|name| name.trim().to_string()).collect()
+    } else if cfg!(windows) {
+        listing.lines().map(|line| line.trim().to_string()).collect()
+    } else if program == "spd-say" {
+        // "NAME  LANGUAGE  VARIANT" rows after a header
+        listing.lines().skip(1).filter_map(|line| line.split_whitespace().next()).map(str::to_string).collect()
+    } else {
+        // "Pty Language       Age/Gender VoiceName          File  ..." rows after a header
+        listing.lines().skip(1).filter_map(|line| line.split_whitespace().nth(1)).map(str::to_string).collect()
+    };
+    Ok(voices.into_iter().filter(|v| !v.is_empty()).collect())
+}
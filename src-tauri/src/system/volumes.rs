@@ -0,0 +1,180 @@
+// Mounted volumes and external drives
+// Lists mounted file systems with capacity and whether they are removable,
+// notices volumes appearing and disappearing, and ejects a volume once no
+// process (or session) is still using it. Only removable and external volumes
+// can be ejected; the system disk and its mounts never are.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Emitted with a `Volume` when a file system is mounted
+pub const MOUNTED_EVENT: &str = "volume-mounted";
+/// Emitted with a `Volume` when a file system goes away
+pub const UNMOUNTED_EVENT: &str = "volume-unmounted";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Volume {
+    /// Device or volume name, e.g. "/dev/sdb1" or "Untitled"
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub removable: bool,
+    /// "SSD", "HDD" or "Unknown"
+    pub kind: String,
+    /// Removable, or an external drive mounted where the system mounts those
+    pub ejectable: bool,
+}
+
+/// Whether a volume is a drive the user attached rather than part of the system
+fn is_ejectable(mount_point: &str, removable: bool) -> bool {
+    if cfg!(windows) {
+        return removable;
+    }
+    let external = if cfg!(target_os = "macos") {
+        ["/Volumes/"].as_slice()
+    } else {
+        ["/media/", "/run/media/", "/mnt/"].as_slice()
+    };
+    (removable && mount_point != "/") || external.iter().any(|prefix| mount_point.starts_with(prefix))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusyProcess {
+    pub pid: u32,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EjectReport {
+    pub mount_point: String,
+    pub ejected: bool,
+    pub busy_processes: Vec<BusyProcess>,
+    /// Sessions whose working directory is on the volume
+    pub sessions_inside: Vec<String>,
+    pub message: String,
+}
+
+pub fn list() -> Vec<Volume> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let mut volumes: Vec<Volume> = disks
+        .list()
+        .iter()
+        .map(|disk| Volume {
+            name: disk.name().to_string_lossy().to_string(),
+            mount_point: disk.mount_point().to_string_lossy().to_string(),
+            file_system: disk.file_system().to_string_lossy().to_string(),
+            total_bytes: disk.total_space(),
+            available_bytes: disk.available_space(),
+            removable: disk.is_removable(),
+            kind: match disk.kind() {
+                sysinfo::DiskKind::SSD => "SSD".to_string(),
+                sysinfo::DiskKind::HDD => "HDD".to_string(),
+                sysinfo::DiskKind::Unknown(_) => "Unknown".to_string(),
+            },
+            ejectable: is_ejectable(&disk.mount_point().to_string_lossy(), disk.is_removable()),
+        })
+        .collect();
+    volumes.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    volumes.dedup_by(|a, b| a.mount_point == b.mount_point);
+    volumes
+}
+
+/// Volumes that appeared and disappeared between two listings
+pub fn changes(before: &[Volume], after: &[Volume]) -> (Vec<Volume>, Vec<Volume>) {
+    let before_points: HashMap<&str, &Volume> = before.iter().map(|v| (v.mount_point.as_str(), v)).collect();
+    let after_points: HashMap<&str, &Volume> = after.iter().map(|v| (v.mount_point.as_str(), v)).collect();
+    let mounted = after.iter().filter(|v| !before_points.contains_key(v.mount_point.as_str())).cloned().collect();
+    let unmounted = before.iter().filter(|v| !after_points.contains_key(v.mount_point.as_str())).cloned().collect();
+    (mounted, unmounted)
+}
+
+/// Follows volume listings over time. A volume counts as gone only once two listings in a
+/// row miss it, since a slow or waking disk can drop out of a single one
+pub struct MountWatch {
+    known: Vec<Volume>,
+    /// Mount points the last listing missed
+    missing: HashSet<String>,
+}
+
+impl MountWatch {
+    pub fn new(volumes: Vec<Volume>) -> Self {
+        Self { known: volumes, missing: HashSet::new() }
+    }
+
+    /// Volumes mounted and unmounted since the previous listing
+    pub fn update(&mut self, current: Vec<Volume>) -> (Vec<Volume>, Vec<Volume>) {
+        let (mounted, absent) = changes(&self.known, &current);
+        let (unmounted, pending): (Vec<Volume>, Vec<Volume>) = absent.into_iter().partition(|v| self.missing.contains(&v.mount_point));
+        self.missing = pending.iter().map(|v| v.mount_point.clone()).collect();
+        self.known = current;
+        self.known.extend(pending);
+        (mounted, unmounted)
+    }
+}
+
+/// Processes with files open on the volume, from `lsof`; empty where lsof is missing
+pub fn busy_processes(mount_point: &Path) -> Vec<BusyProcess> {
+    if cfg!(windows) {
+        return Vec::new();
+    }
+    // +f -- treats the argument as a file system; -F pc prints "p<pid>" and "c<command>" lines
+    let Ok(output) = Command::new("lsof").args(["-F", "pc", "+f", "--"]).arg(mount_point).output() else {
+        return Vec::new();
+    };
+    let own_pid = std::process::id();
+    let mut processes: Vec<BusyProcess> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(pid) = line.strip_prefix('p').and_then(|pid| pid.parse().ok()) {
+            processes.push(BusyProcess { pid, command: String::new() });
+        } else if let (Some(command), Some(process)) = (line.strip_prefix('c'), processes.last_mut()) {
+            process.command = command.to_string();
+        }
+    }
+    processes.retain(|process| process.pid != own_pid);
+    processes
+}
+
+fn run(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(program).args(args).output().map_err(|e| format!("Cannot run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Unmount the volume and power it down where the platform supports it
+pub fn eject(volume: &Volume) -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        return run("diskutil", &["eject", &volume.mount_point]);
+    }
+    if cfg!(windows) {
+        let drive = volume.mount_point.trim_end_matches('\\');
+        let script = format!("(New-Object -ComObject Shell.Application).Namespace(17).ParseName('{}').InvokeVerb('Eject')", drive.replace('\'', "''"));
+        return run("powershell", &["-NoProfile", "-Command", &script]);
+    }
+    // udisks unmounts without root; plain umount for systems without it
+    match run("udisksctl", &["unmount", "-b", &volume.name]) {
+        Ok(()) => {
+            // Powering off only works for whole USB devices; the unmount is what matters
+            let _ = run("udisksctl", &["power-off", "-b", &volume.name]);
+            Ok(())
+        }
+        Err(_) => run("umount", &[&volume.mount_point]),
+    }
+}
+
+/// Where sessions on an ejected volume should go: the parent of its mount point, if that still exists
+pub fn fallback_directory(mount_point: &Path) -> PathBuf {
+    mount_point
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .map(PathBuf::from)
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("/"))
+}
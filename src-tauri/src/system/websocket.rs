@@ -0,0 +1,264 @@
+// Minimal WebSocket (RFC 6455) framing for the remote access server
+// Only what a browser client needs: the handshake key, text and binary
+// messages (reassembled from fragments), ping/pong and close. Client frames are
+// masked, server frames are not; extensions such as compression are not offered.
+// hyper already serves the HTTP side and hands over the upgraded connection, and
+// this subset is small enough that a WebSocket crate (and a second HTTP stack
+// alongside hyper) would add more than it replaces.
+
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Messages larger than this close the connection
+const MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+/// The `Sec-WebSocket-Accept` value answering a client's `Sec-WebSocket-Key`
+pub fn accept_key(client_key: &str) -> String {
+    let digest = Sha1::new().chain_update(client_key.trim()).chain_update(HANDSHAKE_GUID).finalize();
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+fn protocol_error(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads messages from the client, keeping a fragmented message across the
+/// control frames that may arrive between its fragments
+pub struct MessageReader<R> {
+    reader: R,
+    fragments: Vec<u8>,
+    fragmented_opcode: Option<u8>,
+}
+
+impl<R: AsyncRead + Unpin> MessageReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, fragments: Vec::new(), fragmented_opcode: None }
+    }
+
+    /// Next message from the client; `None` when the connection ended
+    pub async fn read_message(&mut self) -> std::io::Result<Option<Message>> {
+        loop {
+            let mut head = [0u8; 2];
+            match self.reader.read_exact(&mut head).await {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            let fin = head[0] & 0x80 != 0;
+            let opcode = head[0] & 0x0F;
+            if head[1] & 0x80 == 0 {
+                return Err(protocol_error("client frames must be masked"));
+            }
+            let len = match head[1] & 0x7F {
+                126 => self.reader.read_u16().await? as usize,
+                127 => usize::try_from(self.reader.read_u64().await?).map_err(|_| protocol_error("frame too large"))?,
+                len => len as usize,
+            };
+            if self.fragments.len().saturating_add(len) > MAX_MESSAGE_BYTES {
+                return Err(protocol_error("message too large"));
+            }
+            let mut mask = [0u8; 4];
+            self.reader.read_exact(&mut mask).await?;
+            let mut payload = vec![0u8; len];
+            self.reader.read_exact(&mut payload).await?;
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+
+            match opcode {
+                // Control frames may arrive between the fragments of a message
+                0x8 => return Ok(Some(Message::Close)),
+                0x9 => return Ok(Some(Message::Ping(payload))),
+                0xA => return Ok(Some(Message::Pong(payload))),
+                0x0 if self.fragmented_opcode.is_none() => return Err(protocol_error("continuation without a message")),
+                0x1 | 0x2 if self.fragmented_opcode.is_some() => return Err(protocol_error("new message before the last one ended")),
+                0x0..=0x2 => {
+                    let message_opcode = *self.fragmented_opcode.get_or_insert(opcode);
+                    self.fragments.extend_from_slice(&payload);
+                    if !fin {
+                        continue;
+                    }
+                    self.fragmented_opcode = None;
+                    let data = std::mem::take(&mut self.fragments);
+                    return Ok(Some(if message_opcode == 0x1 {
+                        Message::Text(String::from_utf8(data).map_err(|_| protocol_error("text message is not UTF-8"))?)
+                    } else {
+                        Message::Binary(data)
+                    }));
+                }
+                _ => return Err(protocol_error("unknown opcode")),
+            }
+        }
+    }
+}
+
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> std::io::Result<()> {
+    let (opcode, payload): (u8, &[u8]) = match message {
+        Message::Text(text) => (0x1, text.as_bytes()),
+        Message::Binary(data) => (0x2, data),
+        Message::Close => (0x8, &[]),
+        Message::Ping(data) => (0x9, data),
+        Message::Pong(data) => (0xA, data),
+    };
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame as a browser sends it: masked, with the length in the shortest form
+    fn client_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![if fin { 0x80 } else { 0 } | opcode];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        frame
+    }
+
+    async fn read_one(bytes: &[u8]) -> std::io::Result<Option<Message>> {
+        MessageReader::new(bytes).read_message().await
+    }
+
+    async fn read_all(bytes: &[u8]) -> Vec<std::io::Result<Option<Message>>> {
+        let mut reader = MessageReader::new(bytes);
+        let mut messages = Vec::new();
+        loop {
+            let message = reader.read_message().await;
+            let done = !matches!(message, Ok(Some(_)));
+            messages.push(message);
+            if done {
+                return messages;
+            }
+        }
+    }
+
+    #[test]
+    fn accept_key_matches_the_rfc_example() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[tokio::test]
+    async fn reads_masked_text_and_binary() {
+        let mut bytes = client_frame(true, 0x1, "Hello ✓".as_bytes());
+        bytes.extend(client_frame(true, 0x2, &[0, 1, 2, 255]));
+        let mut reader = MessageReader::new(bytes.as_slice());
+        assert_eq!(reader.read_message().await.unwrap(), Some(Message::Text("Hello ✓".into())));
+        assert_eq!(reader.read_message().await.unwrap(), Some(Message::Binary(vec![0, 1, 2, 255])));
+        assert_eq!(reader.read_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn reads_extended_lengths() {
+        for len in [125, 126, 65_535, 65_536, 200_000] {
+            let payload = vec![b'x'; len];
+            let bytes = client_frame(true, 0x2, &payload);
+            assert_eq!(read_one(&bytes).await.unwrap(), Some(Message::Binary(payload)), "length {}", len);
+        }
+    }
+
+    #[tokio::test]
+    async fn reassembles_fragments_around_control_frames() {
+        let mut bytes = client_frame(false, 0x1, b"ls ");
+        bytes.extend(client_frame(true, 0x9, b"ping"));
+        bytes.extend(client_frame(false, 0x0, b"-la "));
+        bytes.extend(client_frame(true, 0x0, b"/tmp"));
+        bytes.extend(client_frame(true, 0x8, &[]));
+        let messages: Vec<_> = read_all(&bytes).await.into_iter().map(|m| m.unwrap()).collect();
+        // The ping is delivered as it arrives; the fragments that came before it are kept
+        assert_eq!(messages[0], Some(Message::Ping(b"ping".to_vec())));
+        assert_eq!(messages[1], Some(Message::Text("ls -la /tmp".into())));
+        assert_eq!(messages[2], Some(Message::Close));
+    }
+
+    #[tokio::test]
+    async fn rejects_protocol_errors() {
+        let mut unmasked = client_frame(true, 0x1, b"hi");
+        unmasked[1] &= 0x7F;
+        let cases = [
+            ("unmasked", unmasked),
+            ("stray continuation", client_frame(true, 0x0, b"hi")),
+            ("unknown opcode", client_frame(true, 0x3, b"hi")),
+            ("interleaved messages", [client_frame(false, 0x1, b"a"), client_frame(true, 0x1, b"b")].concat()),
+            ("invalid UTF-8", client_frame(true, 0x1, &[0xC3, 0x28])),
+        ];
+        for (name, bytes) in cases {
+            let error = read_one(&bytes).await.expect_err(name);
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_messages_before_reading_them() {
+        // Only the header is sent: the length alone must be enough to refuse the frame
+        let mut header = vec![0x82, 0x80 | 127];
+        header.extend_from_slice(&(MAX_MESSAGE_BYTES as u64 + 1).to_be_bytes());
+        let error = read_one(&header).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        // Nor may fragments add up past the limit
+        let half = vec![0u8; MAX_MESSAGE_BYTES / 2 + 1];
+        let mut bytes = client_frame(false, 0x2, &half);
+        bytes.extend(client_frame(true, 0x0, &half));
+        assert!(read_one(&bytes).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn truncated_frames_are_errors_not_clean_ends() {
+        let bytes = client_frame(true, 0x1, b"hello");
+        let error = read_one(&bytes[..bytes.len() - 2]).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn writes_unmasked_frames() {
+        let mut written = Vec::new();
+        write_message(&mut written, &Message::Text("hi".into())).await.unwrap();
+        write_message(&mut written, &Message::Close).await.unwrap();
+        assert_eq!(written, [0x81, 0x02, b'h', b'i', 0x88, 0x00]);
+
+        for (len, header) in [(126, vec![0x82, 126, 0, 126]), (65_536, vec![0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0])] {
+            let mut written = Vec::new();
+            write_message(&mut written, &Message::Binary(vec![7; len])).await.unwrap();
+            assert_eq!(written[..header.len()], header[..]);
+            assert_eq!(written.len(), header.len() + len);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Screen-reader friendly output
+// Sessions with accessible output get command output without escape codes,
+// progress redraws or box-drawing rules, and with long tables replaced by a
+// spoken-style summary ("23 rows, columns: NAME, STATUS, AGE") followed by the
+// first few rows read out as "NAME web, STATUS Running". History keeps the
+// original output; only what the session is shown changes. `spoken_summary` is
+// the fallback for `ai_summarize_output` when no model is routed.
+
+use super::ansi;
+use super::structured::StructuredOutput;
+
+/// Tables longer than this are summarized
+const TABLE_ROW_LIMIT: usize = 8;
+/// Rows read out after a table summary
+const ROWS_READ: usize = 3;
+/// Lines quoted from the end of the output in a spoken summary
+const SUMMARY_TAIL_LINES: usize = 2;
+
+/// Lines made only of box drawing and rule characters, which screen readers spell out
+fn is_decoration(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty()
+        && trimmed.chars().all(|c| matches!(c, '\u{2500}'..='\u{257F}' | '-' | '=' | '+' | '|' | '_' | '*' | ' '))
+}
+
+/// Columns of a whitespace-aligned table: a header of words separated by two or more spaces
+fn split_columns(line: &str) -> Vec<String> {
+    line.split("  ").map(str::trim).filter(|cell| !cell.is_empty()).map(str::to_string).collect()
+}
+
+struct TextTable {
+    start: usize,
+    end: usize,
+    columns: Vec<String>,
+}
+
+/// A header line followed by rows with the same number of cells, like `ps`, `docker ps` or `kubectl get`
+fn find_tables(lines: &[&str]) -> Vec<TextTable> {
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let columns = split_columns(lines[i]);
+        let is_header = columns.len() >= 2
+            && columns.iter().all(|c| c.chars().any(char::is_alphabetic) && !c.chars().any(char::is_lowercase));
+        if !is_header {
+            i += 1;
+            continue;
+        }
+        let rows = lines[i + 1..]
+            .iter()
+            .take_while(|line| !line.trim().is_empty() && split_columns(line).len() + 1 >= columns.len())
+            .count();
+        if rows > TABLE_ROW_LIMIT {
+            tables.push(TextTable { start: i, end: i + 1 + rows, columns });
+        }
+        i += 1 + rows;
+    }
+    tables
+}
+
+fn read_row(columns: &[String], cells: &[String]) -> String {
+    columns
+        .iter()
+        .zip(cells)
+        .map(|(column, cell)| format!("{} {}", column, cell))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn table_summary(rows: usize, columns: &[String], first_rows: Vec<String>) -> String {
+    let mut summary = format!("Table: {} rows, columns: {}.", rows, columns.join(", "));
+    for (n, row) in first_rows.iter().enumerate() {
+        summary.push_str(&format!("\nRow {}: {}.", n + 1, row));
+    }
+    if rows > first_rows.len() {
+        summary.push_str(&format!("\n{} more rows not read.", rows - first_rows.len()));
+    }
+    summary
+}
+
+/// Output as a screen reader should get it
+pub fn render(output: &str, structured: Option<&StructuredOutput>) -> String {
+    // Parsed JSON output already knows its columns
+    if let Some(table) = structured.and_then(|s| s.table.as_ref()).filter(|t| t.rows.len() > TABLE_ROW_LIMIT) {
+        let columns: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+        let first_rows = table
+            .rows
+            .iter()
+            .take(ROWS_READ)
+            .map(|row| {
+                let cells: Vec<String> = row.iter().map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())).collect();
+                read_row(&columns, &cells)
+            })
+            .collect();
+        return table_summary(table.total_rows.unwrap_or(table.rows.len()), &columns, first_rows);
+    }
+
+    let plain = ansi::strip(output);
+    let lines: Vec<&str> = plain.lines().filter(|line| !is_decoration(line)).collect();
+    let mut rendered = Vec::new();
+    let mut next = 0;
+    for table in find_tables(&lines) {
+        rendered.extend(lines[next..table.start].iter().map(|line| line.trim_end().to_string()));
+        let first_rows = lines[table.start + 1..table.end]
+            .iter()
+            .take(ROWS_READ)
+            .map(|line| read_row(&table.columns, &split_columns(line)))
+            .collect();
+        rendered.push(table_summary(table.end - table.start - 1, &table.columns, first_rows));
+        next = table.end;
+    }
+    rendered.extend(lines[next..].iter().map(|line| line.trim_end().to_string()));
+    // Runs of blank lines read as long silences
+    rendered.dedup_by(|a, b| a.is_empty() && b.is_empty());
+    rendered.join("\n").trim().to_string()
+}
+
+/// A few sentences for listening rather than reading: how it ended, how much output, what stood out
+pub fn spoken_summary(command: &str, output: &str, exit_code: Option<i32>) -> String {
+    let plain = render(output, None);
+    let lines: Vec<&str> = plain.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    let program = command.split_whitespace().next().unwrap_or(command);
+    let mut sentences = vec![match exit_code {
+        Some(0) => format!("{} succeeded.", program),
+        Some(code) => format!("{} failed with exit code {}.", program, code),
+        None => format!("{} did not finish.", program),
+    }];
+    sentences.push(match lines.len() {
+        0 => "There was no output.".to_string(),
+        1 => "One line of output.".to_string(),
+        n => format!("{} lines of output.", n),
+    });
+    let problems: Vec<&&str> = lines
+        .iter()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            lower.contains("error") || lower.contains("failed") || lower.contains("warning")
+        })
+        .collect();
+    if let Some(first) = problems.first() {
+        sentences.push(format!("{} lines mention errors or warnings; the first says: {}", problems.len(), first));
+    }
+    let tail: Vec<&str> = lines.iter().rev().take(SUMMARY_TAIL_LINES).rev().map(|line| line.trim_end_matches('.')).collect();
+    if !tail.is_empty() && lines.len() > 1 {
+        sentences.push(format!("It ends with: {}", tail.join(". ")));
+    } else if let Some(only) = lines.first() {
+        sentences.push(format!("It says: {}", only));
+    }
+    sentences.join(" ")
+}
+
+/// Prompt asking a model for a summary meant to be heard
+pub fn summary_prompt(command: &str, output: &str, exit_code: Option<i32>) -> String {
+    let plain = render(output, None);
+    // Models are given the end of long outputs, where results and errors usually are
+    let excerpt: String = match plain.char_indices().rev().nth(6000) {
+        Some((cut, _)) => format!("…{}", &plain[cut..]),
+        None => plain,
+    };
+    format!(
+        "Summarize the output of `{}` (exit code {}) in at most three short spoken sentences for someone using a screen reader. \
+         Say whether it worked, give the key numbers or errors, and do not use symbols, tables or code formatting.\n\n{}",
+        command,
+        exit_code.map(|c| c.to_string()).unwrap_or_else(|| "none".to_string()),
+        excerpt
+    )
+}
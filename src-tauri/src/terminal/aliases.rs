@@ -0,0 +1,153 @@
+// Aliases
+// Short names for longer commands, defined with the `alias` built-in (`alias
+// gs='git status'`) and kept in the config directory, so they work in every
+// session and every shell, including sessions without a live shell. The first
+// word of a command line is expanded before anything else looks at it; like a
+// shell, an expansion ending in a space has its next word expanded too, and an
+// alias is not expanded inside its own expansion (`alias ls='ls -G'`). History
+// keeps what was typed; what the command ran as is what the AI learns from.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Built-ins an alias may not take the name of
+const RESERVED: [&str; 2] = ["alias", "unalias"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alias {
+    pub name: String,
+    pub expansion: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Aliases {
+    aliases: BTreeMap<String, String>,
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !RESERVED.contains(&name)
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '@' | '%' | '+' | ':' | ',' | '!'))
+}
+
+/// `'…'` quoting as the shell would print it back
+fn single_quoted(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+impl Aliases {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("aliases.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> Vec<Alias> {
+        self.aliases.iter().map(|(name, expansion)| Alias { name: name.clone(), expansion: expansion.clone() }).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn set(&mut self, name: &str, expansion: &str) -> Result<(), String> {
+        if !valid_name(name) {
+            return Err(format!("'{}' cannot be an alias name", name));
+        }
+        if expansion.trim().is_empty() {
+            return Err(format!("The alias '{}' needs a command", name));
+        }
+        self.aliases.insert(name.to_string(), expansion.to_string());
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<String, String> {
+        let expansion = self.aliases.remove(name).ok_or_else(|| format!("No alias named '{}'", name))?;
+        self.save()?;
+        Ok(expansion)
+    }
+
+    pub fn clear(&mut self) -> Result<usize, String> {
+        let removed = self.aliases.len();
+        self.aliases.clear();
+        self.save()?;
+        Ok(removed)
+    }
+
+    /// The command line with its aliases expanded; `None` when it uses none
+    pub fn expand(&self, command: &str) -> Option<String> {
+        let line = command.trim_start();
+        let leading = &command[..command.len() - line.len()];
+        self.expand_first(line, &mut Vec::new()).map(|expanded| format!("{}{}", leading, expanded))
+    }
+
+    /// `line` with its first word expanded, and the words the expansion leads to
+    fn expand_first(&self, line: &str, used: &mut Vec<String>) -> Option<String> {
+        let (word, rest) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+        if used.iter().any(|done| done == word) {
+            return None;
+        }
+        let expansion = self.aliases.get(word)?;
+        used.push(word.to_string());
+        let expansion = self.expand_first(expansion, used).unwrap_or_else(|| expansion.clone());
+        let rest = rest.trim_start();
+        if expansion.ends_with(' ') {
+            let next = self.expand_first(rest, used).unwrap_or_else(|| rest.to_string());
+            return Some(format!("{}{}", expansion, next));
+        }
+        Some(if rest.is_empty() { expansion } else { format!("{} {}", expansion, rest) })
+    }
+
+    /// The `alias` built-in: list, show one, or define with `name=command`
+    pub fn builtin_alias(&mut self, args: &[&str]) -> Result<String, String> {
+        if args.is_empty() {
+            if self.aliases.is_empty() {
+                return Ok("No aliases; define one with alias name='command'".to_string());
+            }
+            return Ok(self.aliases.iter().map(|(name, expansion)| format!("alias {}={}", name, single_quoted(expansion))).collect::<Vec<_>>().join("\n"));
+        }
+        let mut output = Vec::new();
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, expansion)) => {
+                    self.set(name, expansion)?;
+                    output.push(format!("🔗 {} → {}", name, expansion));
+                }
+                None => match self.get(arg) {
+                    Some(expansion) => output.push(format!("alias {}={}", arg, single_quoted(expansion))),
+                    None => return Err(format!("No alias named '{}'", arg)),
+                },
+            }
+        }
+        Ok(output.join("\n"))
+    }
+
+    /// The `unalias` built-in: remove the named aliases, or all with `-a`
+    pub fn builtin_unalias(&mut self, args: &[&str]) -> Result<String, String> {
+        match args {
+            [] => Err("Usage: unalias [-a] name ...".to_string()),
+            ["-a"] => self.clear().map(|removed| format!("🗑️ Removed {} alias{}", removed, if removed == 1 { "" } else { "es" })),
+            names => {
+                for name in names {
+                    self.remove(name)?;
+                }
+                Ok(format!("🗑️ Removed {}", names.join(", ")))
+            }
+        }
+    }
+}
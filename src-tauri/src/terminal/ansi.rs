@@ -0,0 +1,480 @@
+// Terminal output processing
+// Programs write escape codes for the screen: colours and text attributes
+// (SGR), cursor moves and erasures that redraw progress bars and spinners,
+// hyperlinks (OSC 8) and window titles. Output goes through a small screen
+// model here, which applies the moves and erasures the way a terminal would and
+// keeps each character's style. What comes out is the plain text, as history,
+// search and the AI should see it (a progress bar is its last state, not every
+// frame), and the same lines as styled spans the frontend can draw. Full-screen
+// programs are not modelled: their alternate screen is dropped before this.
+
+use serde::{Deserialize, Serialize};
+
+/// Output larger than this keeps its plain text only
+const MAX_STYLED_OUTPUT: usize = 64 * 1024;
+/// Columns a tab moves to multiples of
+const TAB_WIDTH: usize = 8;
+/// Where cursor moves stop, as at the edge of a terminal, unless the output already reaches
+/// further: far past any real screen, while `ESC[99999999B` cannot allocate millions of lines
+const MAX_ROWS: usize = 4096;
+const MAX_COLS: usize = 4096;
+
+/// A palette index (0-15 are the theme's colours) or a 24-bit colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Color {
+    Indexed(u8),
+    Rgb([u8; 3]),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<Color>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dim: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub underline: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inverse: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub strikethrough: bool,
+    /// Target of an OSC 8 hyperlink
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl Style {
+    fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub text: String,
+    #[serde(default)]
+    pub style: Style,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StyledLine {
+    pub spans: Vec<Span>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessedOutput {
+    /// Without escape codes, with redraws applied
+    pub plain: String,
+    /// The same lines with their styles; empty when nothing was styled or the output is too large
+    pub lines: Vec<StyledLine>,
+    /// Window title the program set (OSC 0 or 2)
+    pub title: Option<String>,
+    /// Cursor moves, carriage returns and erasures applied
+    pub cursor_moves: usize,
+    /// The program cleared the screen; only what came after is kept
+    pub cleared: bool,
+}
+
+impl ProcessedOutput {
+    /// The styled lines worth keeping with a command's output, when any are
+    pub fn styled(&self) -> Option<Vec<StyledLine>> {
+        (!self.lines.is_empty()).then(|| self.lines.clone())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    /// Index into the screen's styles
+    style: usize,
+}
+
+/// Lines of cells and a cursor; rows count from the first line of the output
+struct Screen {
+    lines: Vec<Vec<Cell>>,
+    row: usize,
+    col: usize,
+    saved: (usize, usize),
+    styles: Vec<Style>,
+    style: Style,
+    title: Option<String>,
+    cursor_moves: usize,
+    cleared: bool,
+}
+
+impl Screen {
+    fn new() -> Self {
+        Self {
+            lines: vec![Vec::new()],
+            row: 0,
+            col: 0,
+            saved: (0, 0),
+            styles: vec![Style::default()],
+            style: Style::default(),
+            title: None,
+            cursor_moves: 0,
+            cleared: false,
+        }
+    }
+
+    fn style_index(&mut self) -> usize {
+        match self.styles.iter().position(|style| *style == self.style) {
+            Some(index) => index,
+            None => {
+                self.styles.push(self.style.clone());
+                self.styles.len() - 1
+            }
+        }
+    }
+
+    fn line(&mut self) -> &mut Vec<Cell> {
+        if self.row >= self.lines.len() {
+            self.lines.resize_with(self.row + 1, Vec::new);
+        }
+        &mut self.lines[self.row]
+    }
+
+    fn put(&mut self, ch: char) {
+        let style = self.style_index();
+        let col = self.col;
+        let line = self.line();
+        if col >= line.len() {
+            line.resize(col, Cell { ch: ' ', style: 0 });
+            line.push(Cell { ch, style });
+        } else {
+            line[col] = Cell { ch, style };
+        }
+        self.col += 1;
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.row = row.min(MAX_ROWS.max(self.lines.len()));
+        let written = self.lines.get(self.row).map_or(0, Vec::len);
+        self.col = col.min(MAX_COLS.max(written));
+        self.cursor_moves += 1;
+    }
+
+    /// ESC[K: 0 from the cursor to the end of the line, 1 from its start to the cursor, 2 all of it
+    fn erase_line(&mut self, mode: usize) {
+        let col = self.col;
+        let line = self.line();
+        match mode {
+            0 => line.truncate(col),
+            1 => line.iter_mut().take(col + 1).for_each(|cell| *cell = Cell { ch: ' ', style: 0 }),
+            _ => line.clear(),
+        }
+        self.cursor_moves += 1;
+    }
+
+    /// ESC[J: 0 from the cursor down, 1 from the top to the cursor, 2 and 3 everything
+    fn erase_display(&mut self, mode: usize) {
+        match mode {
+            0 => {
+                self.erase_line(0);
+                self.lines.truncate(self.row + 1);
+            }
+            1 => {
+                for line in self.lines.iter_mut().take(self.row) {
+                    line.clear();
+                }
+                self.erase_line(1);
+            }
+            _ => {
+                self.lines = vec![Vec::new()];
+                self.row = 0;
+                self.col = 0;
+                self.cleared = true;
+                self.cursor_moves += 1;
+            }
+        }
+    }
+
+    fn csi(&mut self, params: &str, intermediates: &str, action: char) {
+        // Private modes (`ESC[?25l`) and sequences with intermediates change nothing kept here
+        if params.starts_with(['?', '>', '<', '=']) || !intermediates.is_empty() {
+            return;
+        }
+        if action == 'm' {
+            apply_sgr(&mut self.style, params);
+            return;
+        }
+        let numbers: Vec<usize> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+        let first = numbers.first().copied().unwrap_or(0);
+        let count = first.max(1);
+        match action {
+            'A' => self.move_to(self.row.saturating_sub(count), self.col),
+            'B' => self.move_to(self.row.saturating_add(count), self.col),
+            'C' => self.move_to(self.row, self.col.saturating_add(count)),
+            'D' => self.move_to(self.row, self.col.saturating_sub(count)),
+            'E' => self.move_to(self.row.saturating_add(count), 0),
+            'F' => self.move_to(self.row.saturating_sub(count), 0),
+            'G' | '`' => self.move_to(self.row, count - 1),
+            'd' => self.move_to(count - 1, self.col),
+            'H' | 'f' => {
+                let col = numbers.get(1).copied().unwrap_or(0).max(1);
+                self.move_to(count - 1, col - 1);
+            }
+            'K' => self.erase_line(first),
+            'J' => self.erase_display(first),
+            's' => self.saved = (self.row, self.col),
+            'u' => self.move_to(self.saved.0, self.saved.1),
+            _ => {}
+        }
+    }
+
+    fn osc(&mut self, body: &str) {
+        let (code, rest) = body.split_once(';').unwrap_or((body, ""));
+        match code {
+            "0" | "2" => self.title = Some(rest.to_string()),
+            // 8;params;uri opens a link, and an empty uri closes it
+            "8" => {
+                let uri = rest.split_once(';').map_or("", |(_, uri)| uri);
+                self.style.link = (!uri.is_empty()).then(|| uri.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self, styled: bool) -> ProcessedOutput {
+        while self.lines.len() > 1 && self.lines.last().is_some_and(Vec::is_empty) {
+            self.lines.pop();
+        }
+        let plain = self
+            .lines
+            .iter()
+            .map(|line| line.iter().map(|cell| cell.ch).collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let has_style = self.lines.iter().flatten().any(|cell| !self.styles[cell.style].is_plain());
+        let lines = if styled && has_style {
+            self.lines.iter().map(|line| spans(line, &self.styles)).collect()
+        } else {
+            Vec::new()
+        };
+        ProcessedOutput { plain, lines, title: self.title, cursor_moves: self.cursor_moves, cleared: self.cleared }
+    }
+}
+
+/// Runs of cells with the same style
+fn spans(line: &[Cell], styles: &[Style]) -> StyledLine {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut last = None;
+    for cell in line {
+        match spans.last_mut() {
+            Some(span) if last == Some(cell.style) => span.text.push(cell.ch),
+            _ => spans.push(Span { text: cell.ch.to_string(), style: styles[cell.style].clone() }),
+        }
+        last = Some(cell.style);
+    }
+    // Trailing blanks without a background are not worth drawing
+    if let Some(span) = spans.last_mut().filter(|span| span.style.bg.is_none()) {
+        span.text.truncate(span.text.trim_end().len());
+    }
+    spans.retain(|span| !span.text.is_empty());
+    StyledLine { spans }
+}
+
+/// `38;5;n` / `38;2;r;g;b` and their colon forms (`38:2::r:g:b`); `None` when malformed
+fn extended_color(values: &[u16]) -> Option<(Color, usize)> {
+    match values {
+        [5, index, ..] => Some((Color::Indexed((*index).min(255) as u8), 2)),
+        [2, r, g, b, ..] => Some((Color::Rgb([(*r).min(255) as u8, (*g).min(255) as u8, (*b).min(255) as u8]), 4)),
+        _ => None,
+    }
+}
+
+/// Select Graphic Rendition: colours and attributes
+fn apply_sgr(style: &mut Style, params: &str) {
+    let params: Vec<&str> = if params.is_empty() { vec!["0"] } else { params.split(';').collect() };
+    let mut i = 0;
+    while i < params.len() {
+        if params[i].contains(':') {
+            let mut values: Vec<u16> = params[i].split(':').map(|n| n.parse().unwrap_or(0)).collect();
+            // 38:2:<colour space>:r:g:b has one more field than the semicolon form
+            if values.get(1) == Some(&2) && values.len() >= 6 {
+                values.remove(2);
+            }
+            let color = extended_color(&values[1..]).map(|(color, _)| color);
+            match values[0] {
+                38 => style.fg = color,
+                48 => style.bg = color,
+                // 4:0 ends underlining; 4:1 to 4:5 are its kinds
+                4 => style.underline = values.get(1) != Some(&0),
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        let code: u16 = params[i].parse().unwrap_or(0);
+        match code {
+            // A reset ends attributes, not an open hyperlink
+            0 => *style = Style { link: style.link.take(), ..Style::default() },
+            1 => style.bold = true,
+            2 => style.dim = true,
+            3 => style.italic = true,
+            4 => style.underline = true,
+            7 => style.inverse = true,
+            8 => style.hidden = true,
+            9 => style.strikethrough = true,
+            22 => {
+                style.bold = false;
+                style.dim = false;
+            }
+            23 => style.italic = false,
+            24 => style.underline = false,
+            27 => style.inverse = false,
+            28 => style.hidden = false,
+            29 => style.strikethrough = false,
+            30..=37 => style.fg = Some(Color::Indexed((code - 30) as u8)),
+            39 => style.fg = None,
+            40..=47 => style.bg = Some(Color::Indexed((code - 40) as u8)),
+            49 => style.bg = None,
+            90..=97 => style.fg = Some(Color::Indexed((code - 90 + 8) as u8)),
+            100..=107 => style.bg = Some(Color::Indexed((code - 100 + 8) as u8)),
+            38 | 48 => {
+                let values: Vec<u16> = params[i + 1..].iter().take(4).map(|n| n.parse().unwrap_or(0)).collect();
+                if let Some((color, used)) = extended_color(&values) {
+                    if code == 38 {
+                        style.fg = Some(color);
+                    } else {
+                        style.bg = Some(color);
+                    }
+                    i += used;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+enum State {
+    Ground,
+    Escape,
+    /// ESC ( B and the like: one more character, then done
+    Charset,
+    Csi { params: String, intermediates: String },
+    Osc(String),
+    /// ESC inside an OSC, which a `\` turns into its terminator
+    OscEscape(String),
+}
+
+/// Apply the escape codes in `text` and split it into plain text and styled lines
+pub fn process(text: &str) -> ProcessedOutput {
+    // Most output has no escape codes, and no redraws to apply
+    if !text.contains(['\x1b', '\r', '\x08']) {
+        let plain = text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n");
+        return ProcessedOutput { plain: plain.trim_end_matches('\n').to_string(), ..ProcessedOutput::default() };
+    }
+    let mut screen = Screen::new();
+    let mut state = State::Ground;
+    for ch in text.chars() {
+        state = match state {
+            State::Ground => match ch {
+                '\x1b' => State::Escape,
+                '\n' => {
+                    screen.row += 1;
+                    screen.col = 0;
+                    State::Ground
+                }
+                '\r' => {
+                    screen.move_to(screen.row, 0);
+                    State::Ground
+                }
+                '\x08' => {
+                    screen.move_to(screen.row, screen.col.saturating_sub(1));
+                    State::Ground
+                }
+                '\t' => {
+                    let next = (screen.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                    while screen.col < next {
+                        screen.put(' ');
+                    }
+                    State::Ground
+                }
+                ch if ch.is_control() => State::Ground,
+                ch => {
+                    screen.put(ch);
+                    State::Ground
+                }
+            },
+            State::Escape => match ch {
+                '[' => State::Csi { params: String::new(), intermediates: String::new() },
+                ']' => State::Osc(String::new()),
+                '(' | ')' | '*' | '+' => State::Charset,
+                '7' => {
+                    screen.saved = (screen.row, screen.col);
+                    State::Ground
+                }
+                '8' => {
+                    screen.move_to(screen.saved.0, screen.saved.1);
+                    State::Ground
+                }
+                // Reverse index: up a line
+                'M' => {
+                    screen.move_to(screen.row.saturating_sub(1), screen.col);
+                    State::Ground
+                }
+                'c' => {
+                    screen.style = Style::default();
+                    screen.erase_display(2);
+                    State::Ground
+                }
+                _ => State::Ground,
+            },
+            State::Charset => State::Ground,
+            State::Csi { mut params, mut intermediates } => match ch {
+                '0'..='?' => {
+                    params.push(ch);
+                    State::Csi { params, intermediates }
+                }
+                ' '..='/' => {
+                    intermediates.push(ch);
+                    State::Csi { params, intermediates }
+                }
+                '@'..='~' => {
+                    screen.csi(&params, &intermediates, ch);
+                    State::Ground
+                }
+                // Not a CSI after all; drop it
+                _ => State::Ground,
+            },
+            State::Osc(mut body) => match ch {
+                '\x07' => {
+                    screen.osc(&body);
+                    State::Ground
+                }
+                '\x1b' => State::OscEscape(body),
+                ch => {
+                    body.push(ch);
+                    State::Osc(body)
+                }
+            },
+            State::OscEscape(body) => {
+                screen.osc(&body);
+                // ESC \ ends the OSC; any other escape starts over
+                if ch == '\\' {
+                    State::Ground
+                } else {
+                    State::Escape
+                }
+            }
+        };
+    }
+    screen.finish(text.len() <= MAX_STYLED_OUTPUT)
+}
+
+/// Text without terminal escape codes, with redraws applied
+pub fn strip(text: &str) -> String {
+    process(text).plain
+}
@@ -0,0 +1,150 @@
+// Named captures from command output
+// `capture NAME from "cmd"` runs cmd and binds its output (or a regex / jq-style
+// extraction of it) to session variables usable later as {{NAME}}:
+//
+//   capture CONTAINER_ID from "docker run -d redis"
+//   capture PORT from "docker port web" with regex ":(\d+)"
+//   capture ID, NAME from "gh api user" with jq ".id, .login"
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Extractor {
+    WholeOutput,
+    Regex(String),
+    JsonPath(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureRequest {
+    pub names: Vec<String>,
+    pub command: String,
+    pub extractor: Extractor,
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a `capture ... from "..."` statement; `None` if the command is not one
+pub fn parse_capture(command: &str) -> Option<Result<CaptureRequest, String>> {
+    let rest = command.trim().strip_prefix("capture ")?;
+    let pattern = fancy_regex::Regex::new(
+        r#"^\s*(.+?)\s+from\s+(["'])(.*?)\2(?:\s+with\s+(regex|jq)\s+(["'])(.*)\5)?\s*$"#,
+    )
+    .unwrap();
+    let usage = "Usage: capture NAME from \"command\" [with regex \"pattern\" | with jq \".path\"]";
+    let caps = match pattern.captures(rest) {
+        Ok(Some(caps)) => caps,
+        _ => return Some(Err(usage.to_string())),
+    };
+
+    let names: Vec<String> = caps[1].split(',').map(|n| n.trim().to_string()).collect();
+    if let Some(bad) = names.iter().find(|n| !is_variable_name(n)) {
+        return Some(Err(format!("Invalid variable name '{}'", bad)));
+    }
+    let command = caps[3].trim().to_string();
+    if command.is_empty() {
+        return Some(Err(usage.to_string()));
+    }
+
+    let extractor = match caps.get(4).map(|m| m.as_str()) {
+        Some("regex") => {
+            let pattern = caps[6].to_string();
+            if let Err(e) = regex::Regex::new(&pattern) {
+                return Some(Err(format!("Invalid capture regex: {}", e)));
+            }
+            Extractor::Regex(pattern)
+        }
+        Some(_) => Extractor::JsonPath(caps[6].split(',').map(|p| p.trim().to_string()).collect()),
+        None => Extractor::WholeOutput,
+    };
+    if let Extractor::JsonPath(paths) = &extractor {
+        if paths.len() != names.len() {
+            return Some(Err(format!("{} names but {} jq paths", names.len(), paths.len())));
+        }
+    }
+
+    Some(Ok(CaptureRequest { names, command, extractor }))
+}
+
+/// Evaluate a jq-style path (`.items[0].id`, `."some key"`) against a JSON value
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Result<&'a Value, String> {
+    let mut current = value;
+    let mut rest = path.trim().strip_prefix('.').ok_or_else(|| format!("jq path must start with '.': {}", path))?;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(|| format!("Unclosed '[' in {}", path))?;
+            let index: i64 = after[..end].trim().parse().map_err(|_| format!("Invalid index in {}", path))?;
+            let items = current.as_array().ok_or_else(|| format!("Cannot index non-array in {}", path))?;
+            let position = if index < 0 { items.len() as i64 + index } else { index };
+            current = items.get(position as usize).ok_or_else(|| format!("Index {} out of range in {}", index, path))?;
+            rest = &after[end + 1..];
+        } else {
+            let rest_trimmed = rest.strip_prefix('.').unwrap_or(rest);
+            let (key, after) = if let Some(quoted) = rest_trimmed.strip_prefix('"') {
+                let end = quoted.find('"').ok_or_else(|| format!("Unclosed quote in {}", path))?;
+                (&quoted[..end], &quoted[end + 1..])
+            } else {
+                let end = rest_trimmed.find(['.', '[']).unwrap_or(rest_trimmed.len());
+                (&rest_trimmed[..end], &rest_trimmed[end..])
+            };
+            if !key.is_empty() {
+                current = current.get(key).ok_or_else(|| format!("No key '{}' in output", key))?;
+            }
+            rest = after;
+        }
+    }
+    Ok(current)
+}
+
+impl CaptureRequest {
+    /// Extract one value per name from the command output
+    pub fn extract(&self, output: &str) -> Result<Vec<(String, String)>, String> {
+        let values: Vec<String> = match &self.extractor {
+            Extractor::WholeOutput => {
+                let trimmed = output.trim();
+                if self.names.len() == 1 {
+                    vec![trimmed.to_string()]
+                } else {
+                    trimmed.lines().map(|l| l.trim().to_string()).collect()
+                }
+            }
+            Extractor::Regex(pattern) => {
+                let regex = regex::Regex::new(pattern).map_err(|e| e.to_string())?;
+                let caps = regex.captures(output).ok_or("Capture regex did not match the output")?;
+                self.names
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, name)| {
+                        caps.name(name)
+                            .or_else(|| caps.get(i + 1))
+                            .or_else(|| if i == 0 { caps.get(0) } else { None })
+                            .map(|m| m.as_str().to_string())
+                    })
+                    .collect()
+            }
+            Extractor::JsonPath(paths) => {
+                let json: Value = serde_json::from_str(output.trim())
+                    .map_err(|e| format!("Output is not valid JSON: {}", e))?;
+                paths
+                    .iter()
+                    .map(|path| {
+                        json_path(&json, path).map(|value| match value {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        })
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        if values.len() < self.names.len() || values.iter().take(self.names.len()).any(|v| v.is_empty()) {
+            return Err("Output did not contain a value for every variable".to_string());
+        }
+        Ok(self.names.iter().cloned().zip(values).collect())
+    }
+}
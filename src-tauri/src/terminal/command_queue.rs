@@ -0,0 +1,126 @@
+// Per-session command queues
+// Commands sent to the same session run one at a time in the order they were
+// submitted, so a `cd` always lands before the command typed after it. A command
+// can opt out and run alongside the queue instead. Pending commands can be
+// cancelled before they start; the queue is published as it changes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+use uuid::Uuid;
+
+/// Emitted with a `QueueState` whenever a session's queue changes
+pub const QUEUE_EVENT: &str = "command-queue-changed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedCommand {
+    pub id: String,
+    pub command: String,
+    pub queued_at: chrono::DateTime<chrono::Utc>,
+    pub running: bool,
+    /// Runs alongside the queue instead of waiting its turn
+    pub concurrent: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueState {
+    pub session_id: String,
+    pub running: Vec<QueuedCommand>,
+    /// In the order they will run
+    pub pending: Vec<QueuedCommand>,
+}
+
+/// A place in a session's queue
+pub struct QueueTicket {
+    pub session_id: String,
+    pub id: String,
+    concurrent: bool,
+}
+
+#[derive(Default)]
+struct SessionQueue {
+    /// tokio's mutex hands out the lock in request order
+    turn: Arc<tokio::sync::Mutex<()>>,
+    entries: Vec<QueuedCommand>,
+}
+
+#[derive(Default)]
+pub struct CommandQueues {
+    sessions: Mutex<HashMap<String, SessionQueue>>,
+}
+
+impl CommandQueues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_session<T>(&self, session_id: &str, f: impl FnOnce(&mut SessionQueue) -> T) -> T {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(sessions.entry(session_id.to_string()).or_default())
+    }
+
+    fn snapshot(session_id: &str, queue: &SessionQueue) -> QueueState {
+        let (running, pending) = queue.entries.iter().cloned().partition(|entry| entry.running);
+        QueueState { session_id: session_id.to_string(), running, pending }
+    }
+
+    pub fn enqueue(&self, session_id: &str, command: &str, concurrent: bool) -> (QueueTicket, QueueState) {
+        let id = Uuid::new_v4().to_string();
+        let state = self.with_session(session_id, |queue| {
+            queue.entries.push(QueuedCommand {
+                id: id.clone(),
+                command: command.to_string(),
+                queued_at: chrono::Utc::now(),
+                running: false,
+                concurrent,
+            });
+            Self::snapshot(session_id, queue)
+        });
+        (QueueTicket { session_id: session_id.to_string(), id, concurrent }, state)
+    }
+
+    /// Wait for the ticket's turn; the returned guard holds it until dropped.
+    /// Fails when the command was cancelled while it waited
+    pub async fn wait(&self, ticket: &QueueTicket) -> Result<(Option<OwnedMutexGuard<()>>, QueueState), String> {
+        let guard = if ticket.concurrent {
+            None
+        } else {
+            let turn = self.with_session(&ticket.session_id, |queue| queue.turn.clone());
+            Some(turn.lock_owned().await)
+        };
+        self.with_session(&ticket.session_id, |queue| {
+            let entry = queue.entries.iter_mut().find(|entry| entry.id == ticket.id).ok_or("Cancelled before it started")?;
+            entry.running = true;
+            Ok((guard, Self::snapshot(&ticket.session_id, queue)))
+        })
+    }
+
+    /// The ticket's command is done, however it ended
+    pub fn finish(&self, ticket: &QueueTicket) -> QueueState {
+        self.with_session(&ticket.session_id, |queue| {
+            queue.entries.retain(|entry| entry.id != ticket.id);
+            Self::snapshot(&ticket.session_id, queue)
+        })
+    }
+
+    /// Drop a command that has not started yet
+    pub fn cancel(&self, session_id: &str, command_id: &str) -> Result<QueueState, String> {
+        self.with_session(session_id, |queue| {
+            let entry = queue.entries.iter().find(|entry| entry.id == command_id).ok_or("No such queued command")?;
+            if entry.running {
+                return Err("The command is already running".to_string());
+            }
+            queue.entries.retain(|entry| entry.id != command_id);
+            Ok(Self::snapshot(session_id, queue))
+        })
+    }
+
+    pub fn state(&self, session_id: &str) -> QueueState {
+        self.with_session(session_id, |queue| Self::snapshot(session_id, queue))
+    }
+
+    pub fn remove_session(&self, session_id: &str) {
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(session_id);
+    }
+}
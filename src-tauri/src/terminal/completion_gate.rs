@@ -0,0 +1,116 @@
+// Debouncing, coalescing and caching for per-keystroke requests
+// Completions and context suggestions are requested while the user types. Each
+// request waits a short debounce; if a newer request for the same session and
+// kind arrived meanwhile it is dropped as superseded. Results are cached by
+// session, input and working directory (including its mtime, so adding or
+// removing files invalidates them).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+const DEBOUNCE: Duration = Duration::from_millis(75);
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const CACHE_CAPACITY: usize = 256;
+
+/// Error returned to requests replaced by a newer one; the frontend ignores it
+pub const SUPERSEDED: &str = "superseded";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestKey {
+    session_id: String,
+    kind: &'static str,
+    input: String,
+    working_directory: String,
+    directory_modified: Option<SystemTime>,
+}
+
+impl RequestKey {
+    pub fn new(session_id: &str, kind: &'static str, input: &str, working_directory: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            kind,
+            input: input.to_string(),
+            working_directory: working_directory.to_string(),
+            directory_modified: std::fs::metadata(working_directory).and_then(|m| m.modified()).ok(),
+        }
+    }
+}
+
+/// Handle of an admitted request
+#[derive(Debug, Clone, Copy)]
+pub struct Ticket(u64);
+
+pub enum Admission {
+    Cached(Vec<String>),
+    Run(Ticket),
+    Superseded,
+}
+
+#[derive(Default)]
+struct GateState {
+    next_ticket: u64,
+    /// (session, kind) -> newest ticket
+    latest: HashMap<(String, &'static str), u64>,
+    cache: HashMap<RequestKey, (Instant, Vec<String>)>,
+}
+
+#[derive(Default)]
+pub struct CompletionGate {
+    state: Mutex<GateState>,
+}
+
+impl CompletionGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Serve from cache, or wait out the debounce and run unless a newer request came in
+    pub async fn admit(&self, key: &RequestKey) -> Admission {
+        let ticket = {
+            let mut state = self.lock();
+            state.next_ticket += 1;
+            let ticket = state.next_ticket;
+            state.latest.insert((key.session_id.clone(), key.kind), ticket);
+            if let Some((stored, results)) = state.cache.get(key) {
+                if stored.elapsed() < CACHE_TTL {
+                    return Admission::Cached(results.clone());
+                }
+            }
+            Ticket(ticket)
+        };
+        tokio::time::sleep(DEBOUNCE).await;
+        if self.is_current(key, ticket) {
+            Admission::Run(ticket)
+        } else {
+            Admission::Superseded
+        }
+    }
+
+    /// Whether no newer request arrived; long computations check this to stop early
+    pub fn is_current(&self, key: &RequestKey, ticket: Ticket) -> bool {
+        self.lock().latest.get(&(key.session_id.clone(), key.kind)) == Some(&ticket.0)
+    }
+
+    pub fn store(&self, key: RequestKey, results: &[String]) {
+        let mut state = self.lock();
+        if state.cache.len() >= CACHE_CAPACITY {
+            state.cache.retain(|_, (stored, _)| stored.elapsed() < CACHE_TTL);
+            if state.cache.len() >= CACHE_CAPACITY {
+                if let Some(oldest) = state.cache.iter().min_by_key(|(_, (stored, _))| *stored).map(|(k, _)| k.clone()) {
+                    state.cache.remove(&oldest);
+                }
+            }
+        }
+        state.cache.insert(key, (Instant::now(), results.to_vec()));
+    }
+
+    /// Forget cached results for a session, e.g. after a command changed its state
+    pub fn invalidate_session(&self, session_id: &str) {
+        self.lock().cache.retain(|key, _| key.session_id != session_id);
+    }
+}
@@ -0,0 +1,663 @@
+// Dev services
+// Long-running commands such as `npm run dev`, `cargo run` or `docker compose up`
+// run as named services instead of occupying a terminal. Each service keeps the
+// last lines of its output, is health-checked through its port or a health URL,
+// is restarted with a growing delay when it crashes, and registers the port it
+// listens on, either configured or read from its output ("Local:
+// http://localhost:5173/"). Definitions are saved in the config directory so a
+// service can be started again by name; the processes are tracked in the
+// process registry like other background jobs, so one left running by a crash
+// of the app shows up as an orphan. A service that fails because its port is
+// taken is not restarted; the conflict and its remedies are kept with it (see
+// `port_conflicts`).
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use super::lifecycle::{ChildPolicy, ProcessRegistry, TrackedProcess};
+use super::port_conflicts::{PortConflict, PortRemedy};
+
+/// Emitted with a `ServiceInfo` whenever a service changes state
+pub const SERVICES_EVENT: &str = "dev-services-changed";
+const LOG_LINES: usize = 2000;
+const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+/// A service still failing its health check this long after starting is unhealthy
+const STARTUP_GRACE: Duration = Duration::from_secs(60);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+const EVENT_BACKLOG: usize = 64;
+/// Output lines searched for the reason a service failed
+const FAILURE_LINES: usize = 50;
+/// How long a freed port is waited for before the service starts again
+const PORT_RELEASE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceDefinition {
+    pub name: String,
+    pub command: String,
+    pub working_directory: String,
+    /// Set for the service; `null` removes an inherited variable
+    #[serde(default)]
+    pub env: HashMap<String, Option<String>>,
+    /// The port it listens on; read from its output when not set
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Checked instead of the port when set
+    #[serde(default)]
+    pub health_url: Option<String>,
+    #[serde(default = "default_true")]
+    pub restart_on_crash: bool,
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceStatus {
+    Stopped,
+    /// Waiting for its first passing health check
+    Starting,
+    /// Running without anything to check
+    Running,
+    Healthy,
+    Unhealthy,
+    /// Exited on its own and is not restarted
+    Crashed,
+    /// Exited on its own and starts again shortly
+    Restarting,
+}
+
+impl ServiceStatus {
+    pub fn is_alive(self) -> bool {
+        matches!(self, Self::Starting | Self::Running | Self::Healthy | Self::Unhealthy)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceInfo {
+    pub name: String,
+    pub command: String,
+    pub working_directory: String,
+    pub status: ServiceStatus,
+    pub pid: Option<u32>,
+    /// Configured, or the one it reported listening on
+    pub port: Option<u16>,
+    pub restarts: u32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_exit_code: Option<i32>,
+    pub last_health_check: Option<DateTime<Utc>>,
+    /// Why it last failed, when that was a taken port
+    pub conflict: Option<PortConflict>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ServicesFile {
+    services: Vec<ServiceDefinition>,
+}
+
+fn settings_file() -> PathBuf {
+    crate::ai::config_directory().join("dev_services.json")
+}
+
+fn load_definitions() -> Vec<ServiceDefinition> {
+    std::fs::read_to_string(settings_file())
+        .ok()
+        .and_then(|data| serde_json::from_str::<ServicesFile>(&data).ok())
+        .unwrap_or_default()
+        .services
+}
+
+fn save_definitions(services: Vec<ServiceDefinition>) -> Result<(), String> {
+    let file = settings_file();
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&ServicesFile { services }).map_err(|e| e.to_string())?;
+    std::fs::write(file, json).map_err(|e| e.to_string())
+}
+
+struct Service {
+    definition: ServiceDefinition,
+    info: ServiceInfo,
+    logs: VecDeque<String>,
+    stop_requested: bool,
+    /// Bumped on every start, so tasks of an earlier run leave the new one alone
+    generation: u64,
+}
+
+impl Service {
+    fn new(definition: ServiceDefinition) -> Self {
+        let info = ServiceInfo {
+            name: definition.name.clone(),
+            command: definition.command.clone(),
+            working_directory: definition.working_directory.clone(),
+            status: ServiceStatus::Stopped,
+            pid: None,
+            port: definition.port,
+            restarts: 0,
+            started_at: None,
+            last_exit_code: None,
+            last_health_check: None,
+            conflict: None,
+        };
+        Self { definition, info, logs: VecDeque::new(), stop_requested: false, generation: 0 }
+    }
+
+    fn log(&mut self, line: String) {
+        if self.logs.len() == LOG_LINES {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(line);
+    }
+}
+
+fn registry_id(name: &str) -> String {
+    format!("service-{}", name)
+}
+
+/// A port the line reports listening on, as dev servers print it
+fn reported_port(line: &str) -> Option<u16> {
+    static PORT: OnceLock<Regex> = OnceLock::new();
+    let pattern = PORT.get_or_init(|| {
+        Regex::new(r"(?i)(?:localhost|127\.0\.0\.1|0\.0\.0\.0|\[::\]|\[::1\]):(\d{2,5})\b|\b(?:listening|running|started|serving)\b.*?\bport\s+(\d{2,5})\b").unwrap()
+    });
+    let captures = pattern.captures(line)?;
+    captures.get(1).or_else(|| captures.get(2))?.as_str().parse().ok().filter(|port| *port > 0)
+}
+
+/// Named dev services of this run, cheap to clone
+#[derive(Clone)]
+pub struct ServiceManager {
+    services: Arc<Mutex<BTreeMap<String, Service>>>,
+    processes: ProcessRegistry,
+    events: tokio::sync::broadcast::Sender<ServiceInfo>,
+}
+
+impl ServiceManager {
+    /// With the saved definitions, all stopped
+    pub fn new(processes: ProcessRegistry) -> Self {
+        let services = load_definitions().into_iter().map(|definition| (definition.name.clone(), Service::new(definition))).collect();
+        Self {
+            services: Arc::new(Mutex::new(services)),
+            processes,
+            events: tokio::sync::broadcast::channel(EVENT_BACKLOG).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<ServiceInfo> {
+        self.events.subscribe()
+    }
+
+    /// Apply a change to a service and announce its new state
+    fn update(&self, name: &str, generation: Option<u64>, change: impl FnOnce(&mut Service)) -> Option<ServiceInfo> {
+        let info = {
+            let mut services = self.services.lock().unwrap();
+            let service = services.get_mut(name)?;
+            if generation.is_some_and(|generation| generation != service.generation) {
+                return None;
+            }
+            change(service);
+            service.info.clone()
+        };
+        let _ = self.events.send(info.clone());
+        Some(info)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let definitions = self.services.lock().unwrap().values().map(|service| service.definition.clone()).collect();
+        save_definitions(definitions)
+    }
+
+    pub fn list(&self) -> Vec<ServiceInfo> {
+        self.services.lock().unwrap().values().map(|service| service.info.clone()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<ServiceInfo> {
+        self.services.lock().unwrap().get(name).map(|service| service.info.clone())
+    }
+
+    pub fn definition(&self, name: &str) -> Option<ServiceDefinition> {
+        self.services.lock().unwrap().get(name).map(|service| service.definition.clone())
+    }
+
+    /// The last `lines` lines of a service's output
+    pub fn logs(&self, name: &str, lines: usize) -> Result<Vec<String>, String> {
+        let services = self.services.lock().unwrap();
+        let service = services.get(name).ok_or_else(|| format!("No service named '{}'", name))?;
+        Ok(service.logs.iter().skip(service.logs.len().saturating_sub(lines)).cloned().collect())
+    }
+
+    /// Ports registered by running services, with the service holding each
+    pub fn ports(&self) -> BTreeMap<u16, String> {
+        self.services
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|service| service.info.status.is_alive())
+            .filter_map(|service| service.info.port.map(|port| (port, service.info.name.clone())))
+            .collect()
+    }
+
+    /// Save a definition, replacing one of the same name; a running service keeps running as it was
+    pub fn define(&self, definition: ServiceDefinition) -> Result<(), String> {
+        if definition.name.trim().is_empty() || definition.command.trim().is_empty() {
+            return Err("A service needs a name and a command".to_string());
+        }
+        {
+            let mut services = self.services.lock().unwrap();
+            match services.get_mut(&definition.name) {
+                Some(service) => {
+                    service.definition = definition;
+                }
+                None => {
+                    services.insert(definition.name.clone(), Service::new(definition));
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// Forget a stopped service's definition
+    pub fn remove(&self, name: &str) -> Result<(), String> {
+        {
+            let mut services = self.services.lock().unwrap();
+            let service = services.get(name).ok_or_else(|| format!("No service named '{}'", name))?;
+            if service.info.status.is_alive() || service.info.status == ServiceStatus::Restarting {
+                return Err(format!("Service '{}' is running; stop it first", name));
+            }
+            services.remove(name);
+        }
+        self.save()
+    }
+
+    /// Start a defined service
+    pub fn start(&self, name: &str) -> Result<ServiceInfo, String> {
+        if crate::system::demo::is_active() {
+            return Err("Services are not run in demo mode".to_string());
+        }
+        let (definition, generation) = {
+            let mut services = self.services.lock().unwrap();
+            let taken = |port: u16| {
+                services
+                    .values()
+                    .find(|other| other.info.name != name && other.info.status.is_alive() && other.info.port == Some(port))
+                    .map(|other| other.info.name.clone())
+            };
+            let service = services.get(name).ok_or_else(|| format!("No service named '{}'", name))?;
+            if service.info.status.is_alive() || service.info.status == ServiceStatus::Restarting {
+                return Err(format!("Service '{}' is already running", name));
+            }
+            if let Some(holder) = service.definition.port.and_then(taken) {
+                return Err(format!("Port {} is registered to service '{}'", service.definition.port.unwrap_or_default(), holder));
+            }
+            let service = services.get_mut(name).ok_or_else(|| format!("No service named '{}'", name))?;
+            service.generation += 1;
+            service.stop_requested = false;
+            service.info.restarts = 0;
+            service.info.last_exit_code = None;
+            service.info.conflict = None;
+            service.info.port = service.definition.port;
+            (service.definition.clone(), service.generation)
+        };
+        self.launch(&definition, generation)
+    }
+
+    fn launch(&self, definition: &ServiceDefinition, generation: u64) -> Result<ServiceInfo, String> {
+        let shell = super::launch_profiles::default_shell();
+        let (program, args) = super::shell_quote::ShellKind::from_shell(&shell).invocation(&shell, &definition.command);
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(args)
+            .current_dir(&definition.working_directory)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        for (key, value) in &definition.env {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        // Its own process group, so stopping it also stops what it started
+        #[cfg(unix)]
+        command.process_group(0);
+        #[cfg(windows)]
+        {
+            const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+            command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                let message = format!("Cannot start '{}': {}", definition.command, e);
+                self.update(&definition.name, Some(generation), |service| {
+                    service.log(message.clone());
+                    service.info.status = ServiceStatus::Crashed;
+                    service.info.pid = None;
+                });
+                return Err(message);
+            }
+        };
+        let pid = child.id().unwrap_or_default();
+        self.processes.track(TrackedProcess {
+            id: registry_id(&definition.name),
+            pid,
+            session_id: None,
+            command: definition.command.clone(),
+            working_directory: definition.working_directory.clone(),
+            policy: ChildPolicy::Adopt,
+            started_at: Utc::now(),
+            process_start_time: super::lifecycle::process_start_time(pid).unwrap_or_default(),
+            log_file: None,
+            orphaned: false,
+        });
+        let checked = definition.port.is_some() || definition.health_url.is_some();
+        let info = self
+            .update(&definition.name, Some(generation), |service| {
+                service.log(format!("▶ {}", definition.command));
+                service.info.status = if checked { ServiceStatus::Starting } else { ServiceStatus::Running };
+                service.info.pid = Some(pid);
+                service.info.started_at = Some(Utc::now());
+            })
+            .ok_or("The service was changed while it started")?;
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let manager = self.clone();
+        let name = definition.name.clone();
+        tauri::async_runtime::spawn(async move {
+            let readers = [
+                stdout.map(|stdout| tauri::async_runtime::spawn(manager.clone().follow(name.clone(), generation, stdout))),
+                stderr.map(|stderr| tauri::async_runtime::spawn(manager.clone().follow(name.clone(), generation, stderr))),
+            ];
+            let status = child.wait().await;
+            for reader in readers.into_iter().flatten() {
+                let _ = reader.await;
+            }
+            manager.exited(&name, generation, status.ok().and_then(|status| status.code())).await;
+        });
+        let manager = self.clone();
+        let name = definition.name.clone();
+        tauri::async_runtime::spawn(async move { manager.check_health(name, generation).await });
+        Ok(info)
+    }
+
+    /// Keep the output of a stream, registering the port the service reports
+    async fn follow(self, name: String, generation: u64, stream: impl tokio::io::AsyncRead + Unpin + Send + 'static) {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            // A port named in a bind failure is someone else's
+            let port = reported_port(&line).filter(|_| super::port_conflicts::detect(&line).is_none());
+            let registered = {
+                let mut services = self.services.lock().unwrap();
+                let Some(service) = services.get_mut(&name).filter(|service| service.generation == generation) else { return };
+                service.log(line);
+                // Only a newly registered port is announced, not every line
+                let registered = service.info.port.is_none() && port.is_some();
+                if registered {
+                    service.info.port = port;
+                }
+                registered.then(|| service.info.clone())
+            };
+            if let Some(info) = registered {
+                let _ = self.events.send(info);
+            }
+        }
+    }
+
+    async fn exited(&self, name: &str, generation: u64, exit_code: Option<i32>) {
+        self.processes.untrack(&registry_id(name));
+        let conflict = match self.port_conflict(name, generation, exit_code) {
+            Some(investigation) => tokio::task::spawn_blocking(investigation).await.ok(),
+            None => None,
+        };
+        let mut delay = None;
+        self.update(name, Some(generation), |service| {
+            service.info.pid = None;
+            service.info.last_exit_code = exit_code;
+            let described = exit_code.map_or("was killed".to_string(), |code| format!("exited with {}", code));
+            if service.stop_requested {
+                service.info.status = ServiceStatus::Stopped;
+                service.log("■ Stopped".to_string());
+            } else if let Some(conflict) = conflict {
+                // Restarting would only hit the same port
+                service.info.status = ServiceStatus::Crashed;
+                service.log(format!("✖ {}", conflict.summary()));
+                service.info.conflict = Some(conflict);
+            } else if service.definition.restart_on_crash && service.info.restarts < service.definition.max_restarts {
+                service.info.status = ServiceStatus::Restarting;
+                service.info.restarts += 1;
+                let wait = Duration::from_secs(1 << service.info.restarts.min(5)).min(MAX_RESTART_DELAY);
+                service.log(format!("↻ {}, restarting in {}s", described, wait.as_secs()));
+                delay = Some(wait);
+            } else {
+                service.info.status = if exit_code == Some(0) { ServiceStatus::Stopped } else { ServiceStatus::Crashed };
+                service.log(format!("✖ {}", described));
+            }
+        });
+        let Some(delay) = delay else { return };
+        tokio::time::sleep(delay).await;
+        let definition = {
+            let services = self.services.lock().unwrap();
+            services
+                .get(name)
+                .filter(|service| service.generation == generation && service.info.status == ServiceStatus::Restarting)
+                .map(|service| service.definition.clone())
+        };
+        if let Some(definition) = definition {
+            let _ = self.launch(&definition, generation);
+        }
+    }
+
+    /// When a failed run's output reports a taken port, the look into it, to run off the async runtime
+    fn port_conflict(&self, name: &str, generation: u64, exit_code: Option<i32>) -> Option<impl FnOnce() -> PortConflict + Send + 'static> {
+        if exit_code == Some(0) {
+            return None;
+        }
+        let (port, definition) = {
+            let services = self.services.lock().unwrap();
+            let service = services.get(name).filter(|service| service.generation == generation && !service.stop_requested)?;
+            let tail: Vec<&str> = service.logs.iter().skip(service.logs.len().saturating_sub(FAILURE_LINES)).map(String::as_str).collect();
+            let reported = super::port_conflicts::detect(&tail.join("\n"))?;
+            (reported.or(service.info.port).or(service.definition.port)?, service.definition.clone())
+        };
+        let mut registered = self.ports();
+        registered.retain(|_, service| service.as_str() != name);
+        Some(move || super::port_conflicts::investigate(port, &definition.command, &definition.env, &registered, Some(&definition.name)))
+    }
+
+    /// Apply a remedy to a service that failed on a taken port, and start it again. Only the
+    /// kind of remedy is taken from the caller: the port is looked into again, so the process
+    /// stopped is the one holding the port now, and the new command is built here
+    pub async fn resolve_conflict(&self, name: &str, remedy: PortRemedy) -> Result<ServiceInfo, String> {
+        let info = self.get(name).ok_or_else(|| format!("No service named '{}'", name))?;
+        let definition = self.definition(name).ok_or_else(|| format!("No service named '{}'", name))?;
+        let port = info.conflict.as_ref().map(|conflict| conflict.port).or(info.port).or(definition.port)
+            .ok_or_else(|| format!("The service '{}' has no port to free", name))?;
+        let mut registered = self.ports();
+        registered.retain(|_, service| service.as_str() != name);
+        let conflict = {
+            let definition = definition.clone();
+            tokio::task::spawn_blocking(move || {
+                super::port_conflicts::investigate(port, &definition.command, &definition.env, &registered, Some(&definition.name))
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        };
+        let changed = || format!("{}; look at the conflict again before choosing", conflict.summary());
+        match remedy {
+            PortRemedy::KillHolder { pid, .. } => {
+                let holder = conflict.holder.as_ref().filter(|_| conflict.held_by_service.is_none());
+                match holder {
+                    Some(holder) if holder.pid == pid => super::port_conflicts::kill_holder(holder.pid)?,
+                    Some(_) => return Err(changed()),
+                    None if super::port_conflicts::is_free(port) => {}
+                    None => return Err(changed()),
+                }
+            }
+            PortRemedy::StopService { name: holder } => {
+                if holder == name {
+                    return Err("A service cannot make way for itself".to_string());
+                }
+                if conflict.held_by_service.as_deref() != Some(holder.as_str()) {
+                    return Err(changed());
+                }
+                self.stop(&holder)?;
+            }
+            PortRemedy::UseNextPort { .. } => {
+                let Some((free, command, env)) = conflict.remedies.into_iter().find_map(|remedy| match remedy {
+                    PortRemedy::UseNextPort { port, command, env } => Some((port, command, env)),
+                    _ => None,
+                }) else {
+                    return Err(format!("No free port found after {}", port));
+                };
+                self.define(ServiceDefinition { command, env, port: Some(free), ..definition })?;
+                return self.start(name);
+            }
+        }
+        let waited = tokio::time::Instant::now();
+        while !super::port_conflicts::is_free(port) && waited.elapsed() < PORT_RELEASE {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        self.start(name)
+    }
+
+    /// Check the service while this run of it lasts
+    async fn check_health(&self, name: String, generation: u64) {
+        let started = tokio::time::Instant::now();
+        loop {
+            tokio::time::sleep(HEALTH_INTERVAL).await;
+            let (definition, port, status) = {
+                let services = self.services.lock().unwrap();
+                match services.get(&name).filter(|service| service.generation == generation) {
+                    Some(service) => (service.definition.clone(), service.info.port, service.info.status),
+                    None => return,
+                }
+            };
+            // A restarted run checks itself
+            if !status.is_alive() {
+                return;
+            }
+            let healthy = match (&definition.health_url, port) {
+                (Some(url), _) => http_ok(url).await,
+                (None, Some(port)) => port_open(port).await,
+                (None, None) => continue,
+            };
+            let status = match (healthy, status) {
+                (true, _) => ServiceStatus::Healthy,
+                (false, ServiceStatus::Starting) if started.elapsed() < STARTUP_GRACE => ServiceStatus::Starting,
+                (false, _) => ServiceStatus::Unhealthy,
+            };
+            self.update(&name, Some(generation), |service| {
+                if service.info.status.is_alive() {
+                    service.info.status = status;
+                }
+                service.info.last_health_check = Some(Utc::now());
+            });
+        }
+    }
+
+    /// Stop a service and what it started; it is killed if it does not exit in time
+    pub fn stop(&self, name: &str) -> Result<ServiceInfo, String> {
+        let info = self
+            .update(name, None, |service| {
+                service.stop_requested = true;
+                if service.info.status == ServiceStatus::Restarting {
+                    service.info.status = ServiceStatus::Stopped;
+                }
+            })
+            .ok_or_else(|| format!("No service named '{}'", name))?;
+        let id = registry_id(name);
+        if let Some(process) = self.processes.list().into_iter().find(|process| process.id == id) {
+            super::lifecycle::stop(process);
+        }
+        Ok(info)
+    }
+
+    /// Suggested services for a directory that are not defined yet
+    pub fn suggestions(&self, dir: &Path) -> Vec<ServiceDefinition> {
+        let services = self.services.lock().unwrap();
+        detect(dir)
+            .into_iter()
+            .filter(|suggested| {
+                !services.values().any(|service| service.definition.working_directory == suggested.working_directory && service.definition.command == suggested.command)
+            })
+            .collect()
+    }
+
+    /// Services defined for a directory
+    pub fn in_directory(&self, dir: &str) -> Vec<ServiceInfo> {
+        self.services.lock().unwrap().values().filter(|service| service.definition.working_directory == dir).map(|service| service.info.clone()).collect()
+    }
+}
+
+async fn port_open(port: u16) -> bool {
+    matches!(tokio::time::timeout(HEALTH_TIMEOUT, tokio::net::TcpStream::connect(("127.0.0.1", port))).await, Ok(Ok(_)))
+}
+
+async fn http_ok(url: &str) -> bool {
+    let Ok(client) = reqwest::Client::builder().timeout(HEALTH_TIMEOUT).build() else { return false };
+    matches!(client.get(url).send().await, Ok(response) if response.status().is_success() || response.status().is_redirection())
+}
+
+/// The usual dev server of a project directory: its package.json dev or start script,
+/// `cargo run` for a binary crate, its compose file
+pub fn detect(dir: &Path) -> Vec<ServiceDefinition> {
+    let project = dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "project".to_string());
+    let working_directory = dir.to_string_lossy().to_string();
+    let service = |suffix: &str, command: String| ServiceDefinition {
+        name: format!("{} {}", project, suffix),
+        command,
+        working_directory: working_directory.clone(),
+        env: HashMap::new(),
+        port: None,
+        health_url: None,
+        restart_on_crash: true,
+        max_restarts: default_max_restarts(),
+    };
+
+    let mut found = Vec::new();
+    let scripts = std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+        .and_then(|package| package.get("scripts").cloned());
+    if let Some(scripts) = scripts {
+        let runner = if dir.join("pnpm-lock.yaml").exists() {
+            "pnpm"
+        } else if dir.join("yarn.lock").exists() {
+            "yarn"
+        } else if dir.join("bun.lockb").exists() {
+            "bun"
+        } else {
+            "npm"
+        };
+        if scripts.get("dev").is_some() {
+            found.push(service("dev", format!("{} run dev", runner)));
+        } else if scripts.get("start").is_some() {
+            found.push(service("start", format!("{} start", runner)));
+        }
+    }
+    let cargo = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap_or_default();
+    if cargo.contains("[package]") && (dir.join("src/main.rs").exists() || cargo.contains("[[bin]]")) {
+        found.push(service("run", "cargo run".to_string()));
+    }
+    if crate::tools::compose::find_compose_file(dir).is_some() {
+        found.push(service("compose", "docker compose up".to_string()));
+    }
+    found
+}
@@ -0,0 +1,273 @@
+// Command usage by directory
+// Counts which commands run in which directory, how often they fail there and
+// what usually follows them. A directory's profile puts its habitual commands
+// first among the suggestions after a `cd`, and the per-directory totals feed
+// the analytics dashboard's heatmap. Commands are stored with known secrets
+// masked, and the store is written at most every half minute, in the background.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Least recently used directories are forgotten beyond this
+const MAX_DIRECTORIES: usize = 500;
+/// Commands remembered per directory
+const MAX_COMMANDS: usize = 200;
+/// Runs before a failure rate counts as a hotspot
+const MIN_RUNS_FOR_HOTSPOT: u32 = 3;
+const PROFILE_LIMIT: usize = 10;
+/// Least time between two saves; `flush` writes whatever is left
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandStats {
+    pub runs: u32,
+    pub failures: u32,
+    pub last_run: DateTime<Utc>,
+    /// Secrets were masked out, so the stored command cannot be run again as it is
+    #[serde(default)]
+    pub masked: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DirectoryUsage {
+    commands: HashMap<String, CommandStats>,
+    /// Command -> the commands run right after it in this directory, with counts
+    next: HashMap<String, HashMap<String, u32>>,
+    #[serde(default)]
+    last_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandUsage {
+    pub command: String,
+    pub runs: u32,
+    pub failures: u32,
+    pub last_run: DateTime<Utc>,
+    pub masked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureHotspot {
+    /// Program and subcommand (`cargo test`), so failures of its variants add up
+    pub topic: String,
+    pub runs: u32,
+    pub failures: u32,
+    pub failure_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NextStep {
+    pub after: String,
+    pub command: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryProfile {
+    pub path: String,
+    pub total_runs: u32,
+    pub top_commands: Vec<CommandUsage>,
+    pub failure_hotspots: Vec<FailureHotspot>,
+    pub next_steps: Vec<NextStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryHeat {
+    pub path: String,
+    pub runs: u32,
+    pub failures: u32,
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DirectoryUsageStore {
+    directories: HashMap<String, DirectoryUsage>,
+    /// Last command and directory per session, to link a command to the one before it
+    #[serde(skip)]
+    previous: HashMap<String, (String, String)>,
+    #[serde(skip)]
+    last_save: Option<Instant>,
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+impl DirectoryUsageStore {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("directory_usage.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write what changed since the last save
+    pub fn flush(&mut self) {
+        if !self.unsaved {
+            return;
+        }
+        match serde_json::to_string(self) {
+            Ok(json) => crate::system::fs_tasks::write_later(Self::data_file(), json),
+            Err(e) => println!("⚠️ Failed to save directory usage: {}", e),
+        }
+        self.unsaved = false;
+        self.last_save = Some(Instant::now());
+    }
+
+    /// Count a command run by a session in a directory
+    pub fn record(&mut self, session_id: &str, directory: &str, command: &str, success: bool) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        let masked_command = crate::integrations::sinks::mask_known_secrets(command);
+        let masked = masked_command != command;
+        let command = masked_command.as_str();
+        let now = Utc::now();
+        let usage = self.directories.entry(directory.to_string()).or_default();
+        let stats = usage
+            .commands
+            .entry(command.to_string())
+            .or_insert(CommandStats { runs: 0, failures: 0, last_run: now, masked });
+        stats.runs += 1;
+        stats.failures += u32::from(!success);
+        stats.last_run = now;
+        usage.last_run = Some(now);
+
+        if let Some((previous_dir, previous)) = self.previous.get(session_id) {
+            if previous_dir == directory && previous != command {
+                *usage.next.entry(previous.clone()).or_default().entry(command.to_string()).or_insert(0) += 1;
+            }
+        }
+        self.previous.insert(session_id.to_string(), (directory.to_string(), command.to_string()));
+
+        if usage.commands.len() > MAX_COMMANDS {
+            let mut commands: Vec<(String, DateTime<Utc>)> = usage.commands.iter().map(|(c, s)| (c.clone(), s.last_run)).collect();
+            commands.sort_by_key(|command| Reverse(command.1));
+            for (dropped, _) in commands.into_iter().skip(MAX_COMMANDS * 9 / 10) {
+                usage.commands.remove(&dropped);
+                usage.next.remove(&dropped);
+            }
+        }
+        if self.directories.len() > MAX_DIRECTORIES {
+            let mut directories: Vec<(String, Option<DateTime<Utc>>)> = self.directories.iter().map(|(d, u)| (d.clone(), u.last_run)).collect();
+            directories.sort_by_key(|directory| Reverse(directory.1));
+            for (dropped, _) in directories.into_iter().skip(MAX_DIRECTORIES * 9 / 10) {
+                self.directories.remove(&dropped);
+            }
+        }
+        self.unsaved = true;
+        if self.last_save.map_or(true, |saved| saved.elapsed() >= SAVE_INTERVAL) {
+            self.flush();
+        }
+    }
+
+    /// Top commands, failure hotspots and usual next steps in a directory
+    pub fn profile(&self, directory: &str) -> DirectoryProfile {
+        let Some(usage) = self.directories.get(directory) else {
+            return DirectoryProfile {
+                path: directory.to_string(),
+                total_runs: 0,
+                top_commands: Vec::new(),
+                failure_hotspots: Vec::new(),
+                next_steps: Vec::new(),
+            };
+        };
+
+        let mut top_commands: Vec<CommandUsage> = usage
+            .commands
+            .iter()
+            .map(|(command, stats)| CommandUsage {
+                command: command.clone(),
+                runs: stats.runs,
+                failures: stats.failures,
+                last_run: stats.last_run,
+                masked: stats.masked,
+            })
+            .collect();
+        top_commands.sort_by(|a, b| b.runs.cmp(&a.runs).then(b.last_run.cmp(&a.last_run)));
+
+        let mut topics: HashMap<String, (u32, u32)> = HashMap::new();
+        for (command, stats) in &usage.commands {
+            let topic = crate::tools::help_docs::help_topic(command).join(" ");
+            let totals = topics.entry(topic).or_default();
+            totals.0 += stats.runs;
+            totals.1 += stats.failures;
+        }
+        let mut failure_hotspots: Vec<FailureHotspot> = topics
+            .into_iter()
+            .filter(|(_, (runs, failures))| *runs >= MIN_RUNS_FOR_HOTSPOT && *failures > 0)
+            .map(|(topic, (runs, failures))| FailureHotspot { topic, runs, failures, failure_rate: failures as f64 / runs as f64 })
+            .collect();
+        failure_hotspots.sort_by(|a, b| b.failure_rate.total_cmp(&a.failure_rate).then(b.failures.cmp(&a.failures)));
+        failure_hotspots.truncate(PROFILE_LIMIT);
+
+        let mut next_steps: Vec<NextStep> = usage
+            .next
+            .iter()
+            .flat_map(|(after, followers)| {
+                followers.iter().map(move |(command, count)| NextStep { after: after.clone(), command: command.clone(), count: *count })
+            })
+            .collect();
+        next_steps.sort_by_key(|step| Reverse(step.count));
+        next_steps.truncate(PROFILE_LIMIT);
+
+        DirectoryProfile {
+            path: directory.to_string(),
+            total_runs: top_commands.iter().map(|c| c.runs).sum(),
+            top_commands: top_commands.into_iter().take(PROFILE_LIMIT).collect(),
+            failure_hotspots,
+            next_steps,
+        }
+    }
+
+    pub fn command_stats(&self, directory: &str, command: &str) -> Option<&CommandStats> {
+        let command = crate::integrations::sinks::mask_known_secrets(command.trim());
+        self.directories.get(directory)?.commands.get(&command)
+    }
+
+    /// What usually follows `command` in the directory and can be run again, most frequent first
+    pub fn usual_next(&self, directory: &str, command: &str) -> Vec<String> {
+        let command = crate::integrations::sinks::mask_known_secrets(command.trim());
+        let Some(usage) = self.directories.get(directory) else { return Vec::new() };
+        let Some(followers) = usage.next.get(&command) else { return Vec::new() };
+        let mut followers: Vec<(&String, &u32)> = followers
+            .iter()
+            .filter(|(follower, _)| !usage.commands.get(*follower).is_some_and(|stats| stats.masked))
+            .collect();
+        followers.sort_by_key(|follower| Reverse(*follower.1));
+        followers.into_iter().map(|(command, _)| command.clone()).collect()
+    }
+
+    /// The session's last command, when it ran in `directory`
+    pub fn previous_command(&self, session_id: &str, directory: &str) -> Option<&str> {
+        self.previous.get(session_id).filter(|(dir, _)| dir == directory).map(|(_, command)| command.as_str())
+    }
+
+    /// Runs and failures per directory, busiest first
+    pub fn heatmap(&self, limit: usize) -> Vec<DirectoryHeat> {
+        let mut heat: Vec<DirectoryHeat> = self
+            .directories
+            .iter()
+            .map(|(path, usage)| DirectoryHeat {
+                path: path.clone(),
+                runs: usage.commands.values().map(|s| s.runs).sum(),
+                failures: usage.commands.values().map(|s| s.failures).sum(),
+                last_run: usage.last_run,
+            })
+            .collect();
+        heat.sort_by_key(|directory| Reverse(directory.runs));
+        heat.truncate(limit);
+        heat
+    }
+
+    pub fn forget_session(&mut self, session_id: &str) {
+        self.previous.remove(session_id);
+    }
+}
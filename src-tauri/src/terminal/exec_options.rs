@@ -0,0 +1,94 @@
+// One-off execution overrides
+// A single command can run with extra or removed environment variables, in
+// another directory, under another shell or locale, or from a clean environment
+// ("does it build with a clean PATH?") without opening a new session. Such a
+// command runs as its own process, never in the session's live shell, so the
+// session keeps its directory and environment. The overrides are kept with the
+// command in history.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Variables a clean environment keeps from the session, so programs still find the user and a terminal
+const KEPT_IN_CLEAN: [&str; 11] = [
+    "HOME", "USER", "LOGNAME", "TERM", "SHELL", "TMPDIR",
+    "SystemRoot", "SystemDrive", "USERPROFILE", "COMSPEC", "PATHEXT",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionOptions {
+    /// Set for this command; `null` removes the variable
+    pub env: HashMap<String, Option<String>>,
+    /// Absolute, or relative to the session's directory
+    pub working_directory: Option<String>,
+    /// Shell that runs the line instead of the session's
+    pub shell: Option<String>,
+    /// Such as `C.UTF-8` or `de_DE.UTF-8`; sets LANG and LC_ALL
+    pub locale: Option<String>,
+    /// Start from the system PATH and a few identity variables instead of the session's environment
+    pub clean_environment: bool,
+    /// Run in the background (detach, adopt) or tie to the session, whatever the session and rules say
+    pub child_policy: Option<super::lifecycle::ChildPolicy>,
+}
+
+/// PATH of a freshly installed system
+fn clean_path(session: &HashMap<String, String>) -> String {
+    if cfg!(windows) {
+        let root = session.get("SystemRoot").map(String::as_str).unwrap_or(r"C:\Windows");
+        format!(r"{root}\system32;{root};{root}\System32\Wbem", root = root)
+    } else {
+        "/usr/local/bin:/usr/bin:/bin:/usr/sbin:/sbin".to_string()
+    }
+}
+
+impl ExecutionOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The session's environment with these overrides applied
+    pub fn environment(&self, session: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut environment: HashMap<String, String> = if self.clean_environment {
+            let mut kept: HashMap<String, String> = session
+                .iter()
+                .filter(|(name, _)| KEPT_IN_CLEAN.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            kept.insert("PATH".to_string(), clean_path(session));
+            kept
+        } else {
+            session.clone()
+        };
+        if let Some(locale) = &self.locale {
+            environment.insert("LANG".to_string(), locale.clone());
+            environment.insert("LC_ALL".to_string(), locale.clone());
+        }
+        for (name, value) in &self.env {
+            match value {
+                Some(value) => environment.insert(name.clone(), value.clone()),
+                None => environment.remove(name),
+            };
+        }
+        environment
+    }
+
+    /// The directory to run in, which must exist
+    pub fn directory(&self, session_directory: &str) -> Result<String, String> {
+        let Some(requested) = &self.working_directory else {
+            return Ok(session_directory.to_string());
+        };
+        let expanded = match requested.strip_prefix("~") {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => dirs::home_dir()
+                .map(|home| home.join(rest.trim_start_matches('/')))
+                .ok_or("No home directory")?,
+            _ => Path::new(session_directory).join(requested),
+        };
+        let directory = super::path_display::lexical_normalize(&expanded);
+        if !directory.is_dir() {
+            return Err(format!("Directory '{}' not found", directory.display()));
+        }
+        Ok(directory.to_string_lossy().to_string())
+    }
+}
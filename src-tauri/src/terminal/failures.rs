@@ -0,0 +1,92 @@
+// Failures that were never fixed
+// A failed command stays unresolved until a similar command succeeds after it:
+// same program and subcommand, or mostly the same arguments. Repeated failures of
+// one command are folded into a single entry.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::CommandExecution;
+
+/// Trailing output lines kept as the error excerpt
+const EXCERPT_LINES: usize = 12;
+/// Minimum shared-token ratio for two commands to count as variants of each other
+const SIMILARITY_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnresolvedFailure {
+    /// Execution id of the latest failed attempt
+    pub execution_id: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// End of the output, ready to pass to `ai_fix_error`
+    pub error_excerpt: String,
+    pub attempts: usize,
+    pub first_failed_at: chrono::DateTime<chrono::Utc>,
+    pub last_failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+fn failed(execution: &CommandExecution) -> bool {
+    execution.exit_code.map(|code| code != 0).unwrap_or(false)
+}
+
+fn tokens(command: &str) -> Vec<&str> {
+    command.split_whitespace().filter(|t| *t != "sudo").collect()
+}
+
+/// First non-flag argument, e.g. `build` in `cargo --locked build`
+fn subcommand<'a>(tokens: &[&'a str]) -> Option<&'a str> {
+    tokens.iter().skip(1).find(|t| !t.starts_with('-')).copied()
+}
+
+/// Whether a successful command plausibly fixes a failed one
+pub fn is_similar(failed_command: &str, succeeded_command: &str) -> bool {
+    let a = tokens(failed_command);
+    let b = tokens(succeeded_command);
+    if a.is_empty() || b.is_empty() || a[0] != b[0] {
+        return false;
+    }
+    if subcommand(&a).is_some() && subcommand(&a) == subcommand(&b) {
+        return true;
+    }
+    let a: HashSet<&str> = a.into_iter().collect();
+    let b: HashSet<&str> = b.into_iter().collect();
+    let shared = a.intersection(&b).count() as f32;
+    shared / a.union(&b).count() as f32 >= SIMILARITY_THRESHOLD
+}
+
+fn excerpt(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(EXCERPT_LINES)..].join("\n")
+}
+
+/// Unresolved failures in chronological history, most recent first
+pub fn unresolved_failures<'a>(history: impl IntoIterator<Item = &'a CommandExecution>) -> Vec<UnresolvedFailure> {
+    let mut open: Vec<UnresolvedFailure> = Vec::new();
+    for execution in history {
+        if failed(execution) {
+            match open.iter_mut().find(|f| f.command.trim() == execution.command.trim()) {
+                Some(existing) => {
+                    existing.execution_id = execution.id.clone();
+                    existing.exit_code = execution.exit_code;
+                    existing.error_excerpt = excerpt(&execution.output);
+                    existing.attempts += 1;
+                    existing.last_failed_at = execution.timestamp;
+                }
+                None => open.push(UnresolvedFailure {
+                    execution_id: execution.id.clone(),
+                    command: execution.command.clone(),
+                    exit_code: execution.exit_code,
+                    error_excerpt: excerpt(&execution.output),
+                    attempts: 1,
+                    first_failed_at: execution.timestamp,
+                    last_failed_at: execution.timestamp,
+                }),
+            }
+        } else if execution.exit_code == Some(0) {
+            open.retain(|f| !is_similar(&f.command, &execution.command));
+        }
+    }
+    open.sort_by_key(|failure| std::cmp::Reverse(failure.last_failed_at));
+    open
+}
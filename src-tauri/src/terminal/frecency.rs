@@ -0,0 +1,85 @@
+// Directory frecency: how often and how recently sessions entered a directory
+// Scored like zoxide: visits weighted by how long ago the last one was. Used to
+// rank path search results so the folders a user actually works in come first.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Least-scored directories are forgotten beyond this
+const MAX_DIRECTORIES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryVisits {
+    pub visits: u32,
+    pub last_visit: DateTime<Utc>,
+}
+
+impl DirectoryVisits {
+    pub fn score(&self, now: DateTime<Utc>) -> f64 {
+        let age = now.signed_duration_since(self.last_visit);
+        let weight = if age.num_hours() < 1 {
+            4.0
+        } else if age.num_days() < 1 {
+            2.0
+        } else if age.num_weeks() < 1 {
+            0.5
+        } else {
+            0.25
+        };
+        self.visits as f64 * weight
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DirectoryFrecency {
+    directories: HashMap<String, DirectoryVisits>,
+}
+
+impl DirectoryFrecency {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("directory_frecency.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Written in the background, since visits are recorded under the terminal manager's lock
+    fn save(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => crate::system::fs_tasks::write_later(Self::data_file(), json),
+            Err(e) => println!("⚠️ Failed to save directory frecency: {}", e),
+        }
+    }
+
+    pub fn record(&mut self, directory: &str) {
+        let now = Utc::now();
+        self.directories
+            .entry(directory.to_string())
+            .and_modify(|visits| {
+                visits.visits += 1;
+                visits.last_visit = now;
+            })
+            .or_insert(DirectoryVisits { visits: 1, last_visit: now });
+
+        if self.directories.len() > MAX_DIRECTORIES {
+            let mut scored: Vec<(String, f64)> = self.scores().into_iter().collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let keep: std::collections::HashSet<String> =
+                scored.into_iter().take(MAX_DIRECTORIES * 9 / 10).map(|(dir, _)| dir).collect();
+            self.directories.retain(|dir, _| keep.contains(dir));
+        }
+        self.save();
+    }
+
+    /// Current score of every known directory
+    pub fn scores(&self) -> HashMap<String, f64> {
+        let now = Utc::now();
+        self.directories.iter().map(|(dir, visits)| (dir.clone(), visits.score(now))).collect()
+    }
+}
@@ -0,0 +1,341 @@
+// Command history storage
+// Entries are shared behind `Arc` so history views hand out pointers instead of
+// cloning outputs, and every entry gets a sequence number that stays valid as
+// old entries fall off the end. Views page backwards from a cursor and poll for
+// entries newer than the last one they have.
+//
+// History survives restarts: every entry is appended to a JSON-lines file in
+// the data directory as it is recorded, and the newest entries are read back at
+// startup. An updated entry is appended again and the later line wins. Removing
+// entries, or the file growing well past the entries kept, rewrites it. Saved
+// commands and outputs have secrets of well-known shapes masked, and outputs
+// are only saved while the setting for it is on.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::CommandExecution;
+
+/// Largest page `page` returns
+pub const MAX_PAGE: usize = 500;
+/// Output saved per entry; the end is kept, where errors and summaries are
+const MAX_SAVED_OUTPUT: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// Keep history between runs
+    pub persist: bool,
+    /// Entries kept, oldest dropped first
+    pub max_entries: usize,
+    /// Save each command's output with it, not just the command
+    pub persist_output: bool,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self { persist: true, max_entries: 1000, persist_output: true }
+    }
+}
+
+impl HistorySettings {
+    fn settings_file() -> PathBuf {
+        crate::ai::config_directory().join("history_settings.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::settings_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::settings_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+fn history_file() -> PathBuf {
+    crate::ai::data_directory().join("command_history.jsonl")
+}
+
+/// The line saved for an entry: known secrets masked, and the output left out or, when very
+/// long, cut to its end
+fn saved_line(execution: &CommandExecution, settings: &HistorySettings) -> Result<String, String> {
+    use crate::integrations::sinks::mask_known_secrets;
+    let output = if settings.persist_output { mask_known_secrets(&execution.output) } else { String::new() };
+    let output = if output.len() <= MAX_SAVED_OUTPUT {
+        output
+    } else {
+        let mut start = output.len() - MAX_SAVED_OUTPUT;
+        while !output.is_char_boundary(start) {
+            start += 1;
+        }
+        format!("[… earlier output not saved]\n{}", &output[start..])
+    };
+    let mut saved = execution.with_output(output);
+    saved.command = mask_known_secrets(&execution.command);
+    serde_json::to_string(&saved).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage<T = Arc<CommandExecution>> {
+    /// Newest first
+    pub entries: Vec<T>,
+    /// Pass back as `before` for the next, older page
+    pub next_cursor: Option<u64>,
+    /// Sequence number of the newest entry in the whole history
+    pub latest_cursor: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryDelta<T = Arc<CommandExecution>> {
+    /// Oldest first, everything recorded after the given entry
+    pub entries: Vec<T>,
+    pub latest_cursor: Option<u64>,
+    /// The given entry was purged or aged out; the view should reload from `page`
+    pub reset: bool,
+}
+
+impl<T> HistoryPage<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> HistoryPage<U> {
+        HistoryPage { entries: self.entries.into_iter().map(f).collect(), next_cursor: self.next_cursor, latest_cursor: self.latest_cursor }
+    }
+}
+
+impl<T> HistoryDelta<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> HistoryDelta<U> {
+        HistoryDelta { entries: self.entries.into_iter().map(f).collect(), latest_cursor: self.latest_cursor, reset: self.reset }
+    }
+}
+
+#[derive(Default)]
+pub struct CommandHistory {
+    entries: VecDeque<(u64, Arc<CommandExecution>)>,
+    next_seq: u64,
+    settings: HistorySettings,
+    /// Lines in the history file, to know when rewriting it pays off; `None` when nothing is saved
+    saved_lines: Option<usize>,
+}
+
+impl CommandHistory {
+    /// History kept in memory only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History saved in the data directory, with the newest saved entries read back
+    pub fn load() -> Self {
+        let settings = HistorySettings::load();
+        if !settings.persist {
+            return Self { settings, ..Self::default() };
+        }
+        let mut history = Self { settings, saved_lines: Some(0), ..Self::default() };
+        let Ok(data) = std::fs::read_to_string(history_file()) else { return history };
+
+        let mut executions: Vec<CommandExecution> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut lines = 0;
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            lines += 1;
+            // A line cut short by a crash is skipped, not fatal
+            let Ok(execution) = serde_json::from_str::<CommandExecution>(line) else { continue };
+            match positions.get(&execution.id) {
+                Some(&position) => executions[position] = execution,
+                None => {
+                    positions.insert(execution.id.clone(), executions.len());
+                    executions.push(execution);
+                }
+            }
+        }
+        let skip = executions.len().saturating_sub(history.settings.max_entries);
+        for execution in executions.into_iter().skip(skip) {
+            history.entries.push_back((history.next_seq, Arc::new(execution)));
+            history.next_seq += 1;
+        }
+        history.saved_lines = Some(lines);
+        history.compact_if_needed();
+        history
+    }
+
+    pub fn settings(&self) -> &HistorySettings {
+        &self.settings
+    }
+
+    /// Apply new settings: a smaller limit drops the oldest entries, turning persistence off deletes the file
+    pub fn set_settings(&mut self, settings: HistorySettings) -> Result<(), String> {
+        if settings.max_entries == 0 {
+            return Err("History must keep at least one entry".to_string());
+        }
+        settings.save()?;
+        self.settings = settings;
+        while self.entries.len() > self.settings.max_entries {
+            self.entries.pop_front();
+        }
+        if self.settings.persist {
+            self.saved_lines = Some(0);
+            self.rewrite();
+        } else {
+            self.saved_lines = None;
+            if let Err(e) = std::fs::remove_file(history_file()) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(format!("Could not delete saved history: {}", e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, execution: CommandExecution) {
+        self.append(&execution);
+        self.entries.push_back((self.next_seq, Arc::new(execution)));
+        self.next_seq += 1;
+        if self.entries.len() > self.settings.max_entries {
+            self.entries.pop_front();
+        }
+        self.compact_if_needed();
+    }
+
+    /// Swap in an updated version of an entry, keeping its place
+    pub fn replace(&mut self, execution: CommandExecution) {
+        if let Some((_, entry)) = self.entries.iter_mut().rev().find(|(_, e)| e.id == execution.id) {
+            let execution = Arc::new(execution);
+            *entry = execution.clone();
+            self.append(&execution);
+        }
+    }
+
+    fn append(&mut self, execution: &CommandExecution) {
+        let Some(lines) = self.saved_lines else { return };
+        let result = saved_line(execution, &self.settings).and_then(|line| {
+            let file = history_file();
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(file).map_err(|e| e.to_string())?;
+            writeln!(file, "{}", line).map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(()) => self.saved_lines = Some(lines + 1),
+            Err(e) => println!("⚠️ Failed to save command history: {}", e),
+        }
+    }
+
+    /// Rewrite the file once it holds twice as many lines as entries kept
+    fn compact_if_needed(&mut self) {
+        if self.saved_lines.is_some_and(|lines| lines > self.settings.max_entries * 2) {
+            self.rewrite();
+        }
+    }
+
+    /// Replace the file with the entries in memory
+    fn rewrite(&mut self) {
+        if self.saved_lines.is_none() {
+            return;
+        }
+        let file = history_file();
+        let temp = file.with_extension("jsonl.tmp");
+        let result = (|| {
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut data = String::new();
+            for (_, execution) in &self.entries {
+                data.push_str(&saved_line(execution, &self.settings)?);
+                data.push('\n');
+            }
+            std::fs::write(&temp, data).map_err(|e| e.to_string())?;
+            std::fs::rename(&temp, &file).map_err(|e| e.to_string())
+        })();
+        match result {
+            Ok(()) => self.saved_lines = Some(self.entries.len()),
+            Err(e) => println!("⚠️ Failed to rewrite command history: {}", e),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<CommandExecution>> {
+        self.entries.iter().rev().find(|(_, e)| e.id == id).map(|(_, e)| e.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn retain(&mut self, keep: impl Fn(&CommandExecution) -> bool) {
+        self.entries.retain(|(_, execution)| keep(execution));
+        self.rewrite();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.rewrite();
+    }
+
+    /// Oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &CommandExecution> {
+        self.entries.iter().map(|(_, execution)| execution.as_ref())
+    }
+
+    /// Shared handles to the newest `limit` entries, newest first
+    pub fn recent(&self, limit: Option<usize>) -> Vec<Arc<CommandExecution>> {
+        self.entries.iter().rev().take(limit.unwrap_or(usize::MAX)).map(|(_, e)| e.clone()).collect()
+    }
+
+    /// The newest `limit` entries of one session, newest first
+    pub fn recent_in_session(&self, session_id: &str, limit: Option<usize>) -> Vec<Arc<CommandExecution>> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|(_, e)| e.session_id.as_deref() == Some(session_id))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(_, e)| e.clone())
+            .collect()
+    }
+
+    fn latest_cursor(&self) -> Option<u64> {
+        self.entries.back().map(|(seq, _)| *seq)
+    }
+
+    /// Up to `limit` entries older than the `before` cursor (or the newest ones), newest first
+    pub fn page(&self, before: Option<u64>, limit: usize) -> HistoryPage {
+        let limit = limit.clamp(1, MAX_PAGE);
+        let mut older = self.entries.iter().rev().filter(|(seq, _)| before.map_or(true, |before| *seq < before)).peekable();
+        let mut entries = Vec::with_capacity(limit.min(self.entries.len()));
+        let mut last_seq = None;
+        while entries.len() < limit {
+            let Some((seq, execution)) = older.next() else { break };
+            entries.push(execution.clone());
+            last_seq = Some(*seq);
+        }
+        HistoryPage {
+            entries,
+            next_cursor: older.peek().and(last_seq),
+            latest_cursor: self.latest_cursor(),
+        }
+    }
+
+    /// Entries recorded after the one with id `after_id`; all of them when it is `None`
+    pub fn since(&self, after_id: Option<&str>) -> HistoryDelta {
+        let start = match after_id {
+            None => Some(0),
+            Some(id) => self.entries.iter().rposition(|(_, e)| e.id == id).map(|position| position + 1),
+        };
+        HistoryDelta {
+            entries: start.map(|start| self.entries.range(start..).map(|(_, e)| e.clone()).collect()).unwrap_or_default(),
+            latest_cursor: self.latest_cursor(),
+            reset: start.is_none(),
+        }
+    }
+}
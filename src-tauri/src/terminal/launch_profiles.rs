@@ -0,0 +1,154 @@
+// Session launch options and saved launch profiles
+// A session can start in a given directory, with a chosen shell and extra or
+// removed environment variables, either directly or from a named profile. The
+// requested directory and shell are checked: a directory that is gone falls back
+// to its nearest existing parent, an unknown shell to the login shell.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// What a session starts with; explicit options win over the profile's
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionOptions {
+    pub title: Option<String>,
+    pub working_directory: Option<String>,
+    pub shell: Option<String>,
+    /// `null` removes an inherited variable
+    pub env: HashMap<String, Option<String>>,
+    pub profile_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, Option<String>>,
+}
+
+impl SessionOptions {
+    /// Fill what the options leave open from the profile
+    pub fn with_profile(mut self, profile: &LaunchProfile) -> Self {
+        self.title = self.title.or_else(|| profile.title.clone());
+        self.working_directory = self.working_directory.or_else(|| profile.working_directory.clone());
+        self.shell = self.shell.or_else(|| profile.shell.clone());
+        for (key, value) in &profile.env {
+            self.env.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LaunchProfiles {
+    profiles: Vec<LaunchProfile>,
+}
+
+impl LaunchProfiles {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("launch_profiles.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> &[LaunchProfile] {
+        &self.profiles
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LaunchProfile> {
+        self.profiles.iter().find(|profile| profile.id == id)
+    }
+
+    /// Add the profile, or replace the one with the same id
+    pub fn upsert(&mut self, profile: LaunchProfile) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("A profile needs a name".to_string());
+        }
+        match self.profiles.iter_mut().find(|existing| existing.id == profile.id) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        self.save()
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        let before = self.profiles.len();
+        self.profiles.retain(|profile| profile.id != id);
+        if self.profiles.len() == before {
+            return Err(format!("No profile {}", id));
+        }
+        self.save()
+    }
+}
+
+/// The login shell, or the platform default
+pub fn default_shell() -> String {
+    std::env::var("SHELL")
+        .or_else(|_| std::env::var("COMSPEC"))
+        .unwrap_or_else(|_| if cfg!(windows) { "cmd.exe".to_string() } else { "/bin/bash".to_string() })
+}
+
+/// The requested shell if it can be run: an existing path, or a name found on PATH
+pub fn resolve_shell(requested: &str) -> Option<String> {
+    let path = Path::new(requested);
+    if path.is_absolute() {
+        return path.is_file().then(|| requested.to_string());
+    }
+    let path_var = std::env::var("PATH").unwrap_or_default();
+    crate::system::path_inspector::find_all(requested, &path_var)
+        .into_iter()
+        .next()
+        .map(|found| found.to_string_lossy().to_string())
+}
+
+/// Directory to start in: `~` expanded, a file's parent for a file, else the nearest existing parent.
+/// Returns the directory and whether it differs from what was asked for
+pub fn resolve_directory(requested: &str, fallback: &Path) -> (PathBuf, bool) {
+    let expanded = match requested.strip_prefix('~') {
+        Some(rest) => match dirs::home_dir() {
+            Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+            None => PathBuf::from(requested),
+        },
+        None => PathBuf::from(requested),
+    };
+    let absolute = if expanded.is_absolute() { expanded } else { fallback.join(expanded) };
+    if absolute.is_dir() {
+        return (absolute, false);
+    }
+    // "Open terminal here" on a file starts in the file's directory, which is what was meant
+    if absolute.is_file() {
+        if let Some(parent) = absolute.parent() {
+            return (parent.to_path_buf(), false);
+        }
+    }
+    let nearest = absolute
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .map(Path::to_path_buf)
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| fallback.to_path_buf());
+    (nearest, true)
+}
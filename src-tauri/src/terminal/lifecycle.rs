@@ -0,0 +1,342 @@
+// What happens to the processes the console starts
+// Each command runs under a policy, chosen by the one-off execution options,
+// else the session's setting, else the first matching rule, else the default:
+// - kill on close: the command belongs to its session and is terminated when the
+//   session closes (the default, and what a terminal tab normally does)
+// - detach: the command is started in the background in a session of its own
+//   and left alone; its output goes to a log file
+// - adopt: like detach, but the command is listed as a background job that can
+//   be followed, stopped, and outlives the session that started it
+// Every process started on its own is written to a registry in the data
+// directory while it runs. At startup, entries still alive (same pid and start
+// time) are processes the console left running, listed as orphans until they
+// are adopted or stopped.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::running::{signal_process, TerminationSignal};
+
+/// How long a terminated command gets to exit before it is killed
+pub const KILL_GRACE: std::time::Duration = std::time::Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildPolicy {
+    #[default]
+    KillOnClose,
+    Detach,
+    Adopt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// A program name (`npm`) or the start of a command line (`npm run dev`)
+    pub command: String,
+    pub policy: ChildPolicy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifecycleSettings {
+    pub default_policy: ChildPolicy,
+    /// First match wins
+    pub rules: Vec<PolicyRule>,
+}
+
+impl LifecycleSettings {
+    fn settings_file() -> PathBuf {
+        crate::ai::config_directory().join("child_policies.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::settings_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::settings_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// The policy a command runs under; `one_off` and `session` are the more specific settings
+    pub fn policy_for(&self, command: &str, one_off: Option<ChildPolicy>, session: Option<ChildPolicy>) -> ChildPolicy {
+        let command = command.trim();
+        let program = command.split_whitespace().next().unwrap_or_default();
+        one_off.or(session).unwrap_or_else(|| {
+            self.rules
+                .iter()
+                .find(|rule| {
+                    let rule_command = rule.command.trim();
+                    program == rule_command || (rule_command.contains(' ') && command.starts_with(rule_command))
+                })
+                .map_or(self.default_policy, |rule| rule.policy)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedProcess {
+    /// Execution id of the command that started it
+    pub id: String,
+    pub pid: u32,
+    /// Cleared when the session closes; adopted jobs live on without one
+    pub session_id: Option<String>,
+    pub command: String,
+    pub working_directory: String,
+    pub policy: ChildPolicy,
+    pub started_at: DateTime<Utc>,
+    /// As the OS reports it, so a reused pid is not taken for this process
+    pub process_start_time: u64,
+    /// Where a background command's output goes
+    pub log_file: Option<String>,
+    /// Left running by an earlier run of the console
+    #[serde(default)]
+    pub orphaned: bool,
+}
+
+/// Start time of a live process, `None` when there is no such process
+pub fn process_start_time(pid: u32) -> Option<u64> {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    if !system.refresh_process(pid) {
+        return None;
+    }
+    system.process(pid).map(|process| process.start_time())
+}
+
+fn is_alive(process: &TrackedProcess) -> bool {
+    process_start_time(process.pid) == Some(process.process_start_time)
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RegistryFile {
+    processes: Vec<TrackedProcess>,
+}
+
+/// Processes started on their own, saved while they run
+#[derive(Clone, Default)]
+pub struct ProcessRegistry {
+    processes: Arc<Mutex<Vec<TrackedProcess>>>,
+    /// Not saved, for tools that only borrow the core
+    persist: bool,
+}
+
+impl ProcessRegistry {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("child_processes.json")
+    }
+
+    /// The saved registry; entries whose process is gone are dropped, live ones become orphans
+    pub fn load() -> Self {
+        let saved: RegistryFile = std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let processes: Vec<TrackedProcess> = saved
+            .processes
+            .into_iter()
+            .filter(is_alive)
+            .map(|process| TrackedProcess { orphaned: true, session_id: None, ..process })
+            .collect();
+        if !processes.is_empty() {
+            println!("👻 {} process(es) from an earlier run are still running", processes.len());
+        }
+        let registry = Self { processes: Arc::new(Mutex::new(processes)), persist: true };
+        registry.save();
+        registry
+    }
+
+    fn save(&self) {
+        if !self.persist {
+            return;
+        }
+        let processes = self.processes.lock().unwrap().clone();
+        let result = serde_json::to_string_pretty(&RegistryFile { processes }).map_err(|e| e.to_string()).and_then(|json| {
+            let file = Self::data_file();
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(file, json).map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            println!("⚠️ Failed to save the process registry: {}", e);
+        }
+    }
+
+    pub fn track(&self, process: TrackedProcess) {
+        self.processes.lock().unwrap().push(process);
+        self.save();
+    }
+
+    pub fn untrack(&self, id: &str) {
+        let removed = {
+            let mut processes = self.processes.lock().unwrap();
+            let before = processes.len();
+            processes.retain(|process| process.id != id);
+            processes.len() != before
+        };
+        if removed {
+            self.save();
+        }
+    }
+
+    /// Tracked processes that are still alive: background jobs, orphans and running commands
+    pub fn list(&self) -> Vec<TrackedProcess> {
+        let mut processes = self.processes.lock().unwrap().clone();
+        processes.retain(is_alive);
+        processes
+    }
+
+    pub fn orphans(&self) -> Vec<TrackedProcess> {
+        self.list().into_iter().filter(|process| process.orphaned).collect()
+    }
+
+    /// Keep an orphan as a background job of this run
+    pub fn adopt(&self, id: &str) -> Result<TrackedProcess, String> {
+        let adopted = {
+            let mut processes = self.processes.lock().unwrap();
+            let process = processes.iter_mut().find(|process| process.id == id).ok_or("No such process")?;
+            process.orphaned = false;
+            process.policy = ChildPolicy::Adopt;
+            process.clone()
+        };
+        self.save();
+        Ok(adopted)
+    }
+
+    /// Signal a tracked process and its group; a stopped orphan or job is forgotten once it exits
+    pub fn signal(&self, id: &str, signal: TerminationSignal) -> Result<TrackedProcess, String> {
+        let process = self.processes.lock().unwrap().iter().find(|process| process.id == id).cloned().ok_or("No such process")?;
+        if !is_alive(&process) {
+            self.untrack(id);
+            return Err("The process has already exited".to_string());
+        }
+        signal_process(process.pid, signal)?;
+        Ok(process)
+    }
+
+    /// A session closed: its kill-on-close processes are returned to be stopped, its adopted jobs stay listed
+    pub fn session_closed(&self, session_id: &str) -> Vec<TrackedProcess> {
+        let to_stop = {
+            let mut processes = self.processes.lock().unwrap();
+            let mut to_stop = Vec::new();
+            for process in processes.iter_mut().filter(|process| process.session_id.as_deref() == Some(session_id)) {
+                if process.policy == ChildPolicy::KillOnClose {
+                    to_stop.push(process.clone());
+                }
+                process.session_id = None;
+            }
+            to_stop
+        };
+        self.save();
+        to_stop
+    }
+}
+
+/// Terminate a process group, and kill it if it is still there after the grace period
+pub fn stop(process: TrackedProcess) {
+    if signal_process(process.pid, TerminationSignal::Terminate).is_err() {
+        return;
+    }
+    std::thread::spawn(move || {
+        std::thread::sleep(KILL_GRACE);
+        if is_alive(&process) {
+            let _ = signal_process(process.pid, TerminationSignal::Kill);
+        }
+    });
+}
+
+fn logs_directory() -> PathBuf {
+    crate::ai::data_directory().join("job_logs")
+}
+
+/// A command line to start in the background, and where
+pub struct BackgroundCommand<'a> {
+    pub id: &'a str,
+    pub session_id: &'a str,
+    pub command_line: &'a str,
+    pub working_directory: &'a str,
+    pub environment: &'a std::collections::HashMap<String, String>,
+    pub shell: &'a str,
+}
+
+/// Start a command in the background under `policy` (detach or adopt), with its output in a log file
+pub fn spawn_background(registry: &ProcessRegistry, background: BackgroundCommand, policy: ChildPolicy) -> Result<TrackedProcess, String> {
+    let BackgroundCommand { id, session_id, command_line, working_directory, environment, shell } = background;
+    let log_file = logs_directory().join(format!("{}.log", id));
+    std::fs::create_dir_all(logs_directory()).map_err(|e| e.to_string())?;
+    let log = std::fs::File::create(&log_file).map_err(|e| format!("Cannot create {}: {}", log_file.display(), e))?;
+    let log_err = log.try_clone().map_err(|e| e.to_string())?;
+
+    let (program, args) = super::shell_quote::ShellKind::from_shell(shell).invocation(shell, command_line);
+    let mut command = std::process::Command::new(program);
+    command
+        .args(args)
+        .current_dir(working_directory)
+        .env_clear()
+        .envs(environment)
+        .stdin(std::process::Stdio::null())
+        .stdout(log)
+        .stderr(log_err);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        if policy == ChildPolicy::Detach {
+            // SAFETY: setsid only moves the child into a new session, before it execs
+            unsafe {
+                command.pre_exec(|| {
+                    if libc::setsid() == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        } else {
+            command.process_group(0);
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(if policy == ChildPolicy::Detach { DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP } else { CREATE_NEW_PROCESS_GROUP });
+    }
+
+    let mut child = command.spawn().map_err(|e| format!("Cannot start '{}': {}", command_line, e))?;
+    let pid = child.id();
+    let process = TrackedProcess {
+        id: id.to_string(),
+        pid,
+        session_id: Some(session_id.to_string()),
+        command: command_line.to_string(),
+        working_directory: working_directory.to_string(),
+        policy,
+        started_at: Utc::now(),
+        process_start_time: process_start_time(pid).unwrap_or_default(),
+        log_file: Some(log_file.to_string_lossy().to_string()),
+        orphaned: false,
+    };
+    registry.track(process.clone());
+
+    // Reap it when it exits, so it leaves the registry instead of lingering as a zombie
+    let reaper = registry.clone();
+    let job_id = id.to_string();
+    std::thread::Builder::new()
+        .name(format!("job-{}", &id[..8.min(id.len())]))
+        .spawn(move || {
+            let _ = child.wait();
+            reaper.untrack(&job_id);
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(process)
+}
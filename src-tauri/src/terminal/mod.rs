@@ -1,18 +1,94 @@
 use std::collections::HashMap;
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::project::context_file::{self, ProjectContext, ProjectContextStatus};
+use outcome::{ExecutionOutcome, SpawnErrorKind};
+
+pub mod templates;
+pub mod capture;
+pub mod parallel;
+pub mod failures;
+pub mod status;
+pub mod outcome;
+pub mod path_display;
+pub mod shell_quote;
+pub mod completion_gate;
+pub mod frecency;
+pub mod notifications;
+pub mod windows;
+pub mod history;
+pub mod command_queue;
+pub mod launch_profiles;
+pub mod shells;
+pub mod structured;
+pub mod prompt;
+pub mod what_if;
+pub mod scratch;
+pub mod dir_usage;
+pub mod accessible;
+pub mod ansi;
+pub mod pty;
+pub mod running;
+pub mod recent_files;
+pub mod exec_options;
+pub mod lifecycle;
+pub mod saved_sessions;
+pub mod dev_services;
+pub mod ssh;
+pub mod port_conflicts;
+pub mod aliases;
+pub mod shell_history;
+
+/// Emitted when a session had to leave a working directory that disappeared
+pub const DIRECTORY_RECOVERED_EVENT: &str = "working-directory-recovered";
+/// Emitted with a `pty::DirectoryReport` when a session followed its shell to another directory
+pub const DIRECTORY_CHANGED_EVENT: &str = "working-directory-changed";
+
+/// Commands handled by the terminal itself instead of being spawned
+pub const BUILTIN_COMMANDS: [&str; 8] = ["cd", "pwd", "history", "clear", "exit", "scratch", "alias", "unalias"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerminalSession {
     pub id: String,
     pub title: String,
     pub working_directory: String,
+    /// Working directory as configured for display (home abbreviated, collapsed)
+    #[serde(default)]
+    pub display_directory: String,
     pub is_active: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub environment_vars: HashMap<String, String>,
     pub shell: String,
     pub pty_size: (u16, u16), // cols, rows
+    #[serde(default)]
+    pub variables: HashMap<String, String>, // captured values, usable as {{NAME}}
+    /// Output is shown screen-reader friendly (see `accessible`)
+    #[serde(default)]
+    pub accessible_output: bool,
+    /// What happens to this session's commands when it closes, over the configured rules
+    #[serde(default)]
+    pub child_policy: Option<lifecycle::ChildPolicy>,
+    /// Overrides of the inherited environment it was started with, kept to restore it
+    #[serde(default)]
+    pub env_overrides: HashMap<String, Option<String>>,
+    /// The machine an SSH session is connected to; its directory and shell are that machine's
+    #[serde(default)]
+    pub remote: Option<ssh::SshSessionConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryRecovery {
+    pub session_id: String,
+    pub missing: String,
+    pub recovered: String,
+}
+
+impl DirectoryRecovery {
+    pub fn notice(&self) -> String {
+        format!("⚠️ Working directory {} no longer exists, moved to {}", self.missing, self.recovered)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,57 +99,748 @@ pub struct CommandExecution {
     pub exit_code: Option<i32>,
     pub duration_ms: u64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub outcome: ExecutionOutcome,
+    /// Coverage report written by a test run
+    #[serde(default)]
+    pub coverage: Option<crate::project::coverage::CoverageSummary>,
+    /// Parsed JSON output, laid out as a table where it fits one
+    #[serde(default)]
+    pub structured: Option<structured::StructuredOutput>,
+    /// One-off environment, directory or shell the command ran with
+    #[serde(default)]
+    pub overrides: Option<exec_options::ExecutionOptions>,
+    /// The output's lines with their colours and links, for display; absent when it had none
+    #[serde(default)]
+    pub styled: Option<Vec<ansi::StyledLine>>,
+    /// Session the command ran in; entries saved before sessions were recorded have none
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+impl CommandExecution {
+    /// A copy with different output, without copying the original output first
+    pub fn with_output(&self, output: String) -> Self {
+        Self {
+            id: self.id.clone(),
+            command: self.command.clone(),
+            output,
+            exit_code: self.exit_code,
+            duration_ms: self.duration_ms,
+            timestamp: self.timestamp,
+            outcome: self.outcome,
+            coverage: self.coverage.clone(),
+            structured: self.structured.clone(),
+            overrides: self.overrides.clone(),
+            // Styles belong to the output they were parsed from
+            styled: None,
+            session_id: self.session_id.clone(),
+        }
+    }
+}
+
+/// How `prepare_command` left a command
+enum PreparedCommand {
+    /// Answered by the terminal itself
+    Finished(CommandExecution),
+    Ready(PendingCommand),
+}
+
+/// A command past the project rules and built-ins, ready to run without the manager
+struct PendingCommand {
+    session_id: String,
+    execution_id: String,
+    start_time: std::time::Instant,
+    started_at: std::time::SystemTime,
+    ran_in: Option<String>,
+    command_to_execute: String,
+    command_for_history: String,
+    cmd: String,
+    args: Vec<String>,
+    program: String,
+    program_args: Vec<String>,
+    structured_source: Option<String>,
+    working_dir: String,
+    env_vars: HashMap<String, String>,
+    /// The session's live shell, when it has one
+    shell: Option<std::sync::Arc<pty::PtyShell>>,
+    /// Lists the command as running until it is finished with
+    registration: running::Registration,
+    overrides: Option<exec_options::ExecutionOptions>,
+    /// Runs in an SSH session, and so never on this machine
+    remote: bool,
+}
+
+impl PendingCommand {
+    /// Output, error output and how the command ended; the pty merges the two outputs
+    async fn run(&self) -> (String, String, ExecutionOutcome) {
+        if let Some(shell) = &self.shell {
+            self.registration.in_shell(shell);
+            match shell.run(&self.command_to_execute).await {
+                Ok((output, outcome)) => return (output, String::new(), outcome),
+                Err(e) if self.remote => return (String::new(), format!("Not connected: {}", e), ExecutionOutcome::SpawnError(SpawnErrorKind::Other)),
+                Err(e) => println!("⚠️ Running '{}' on its own, {}", self.cmd, e),
+            }
+        }
+        let args: Vec<&str> = self.program_args.iter().map(String::as_str).collect();
+        execute_system_command(&self.program, &args, &self.working_dir, &self.env_vars, &self.registration).await
+    }
+}
+
+/// Execute system command with enhanced features
+async fn execute_system_command(
+    cmd: &str,
+    args: &[&str],
+    working_dir: &str,
+    env_vars: &HashMap<String, String>,
+    registration: &running::Registration,
+) -> (String, String, ExecutionOutcome) {
+    // Demo mode shows canned output; nothing of the user's is run
+    if crate::system::demo::is_active() {
+        return crate::system::demo::execute(cmd, args, working_dir);
+    }
+    let mut command = tokio::process::Command::new(cmd);
+    command.args(args);
+    command.current_dir(working_dir);
+    command.stdin(std::process::Stdio::null());
+    command.stdout(std::process::Stdio::piped());
+    command.stderr(std::process::Stdio::piped());
+    // A command whose caller went away must not keep running
+    command.kill_on_drop(true);
+    // Its own process group, so stopping it reaches what it started too
+    #[cfg(unix)]
+    command.process_group(0);
+    
+    // Set environment variables
+    for (key, value) in env_vars {
+        command.env(key, value);
+    }
+    
+    // No time limit: a runaway command is stopped with terminate_command instead
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return (String::new(), e.to_string(), ExecutionOutcome::from_spawn_error(&e)),
+    };
+    if let Some(pid) = child.id() {
+        registration.in_process(pid);
+    }
+    let output = match child.wait_with_output().await {
+        Ok(output) => output,
+        Err(e) => return (String::new(), e.to_string(), ExecutionOutcome::from_spawn_error(&e)),
+    };
+    
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    (stdout, stderr, ExecutionOutcome::from_status(&output.status))
+}
+
+/// `execute_command_with_history` that lets go of the manager while the command runs,
+/// so a long build or an editor in one session does not hold up the others
+pub async fn execute_unlocked(
+    manager: &tokio::sync::Mutex<TerminalManager>,
+    session_id: &str,
+    command_to_execute: &str,
+    command_for_history: &str,
+    options: &exec_options::ExecutionOptions,
+) -> Result<CommandExecution, Box<dyn std::error::Error + Send + Sync>> {
+    let prepared = manager.lock().await.prepare_command(session_id, command_to_execute, command_for_history, options).await?;
+    let pending = match prepared {
+        PreparedCommand::Finished(execution) => return Ok(execution),
+        PreparedCommand::Ready(pending) => pending,
+    };
+    let result = pending.run().await;
+    Ok(manager.lock().await.finish_command(pending, result).await)
 }
 
 pub struct TerminalManager {
     sessions: HashMap<String, TerminalSession>,
-    command_history: Vec<CommandExecution>,
+    command_history: history::CommandHistory,
+    ci_status: HashMap<String, crate::integrations::ci::CiStatus>, // by working directory
+    project_contexts: HashMap<String, ProjectContextStatus>, // by working directory
+    path_display: path_display::PathDisplaySettings,
+    frecency: frecency::DirectoryFrecency,
+    coverage: HashMap<String, crate::project::coverage::CoverageSummary>, // latest by project root
+    launch_profiles: launch_profiles::LaunchProfiles,
+    structured: structured::StructuredCapabilities,
+    scratch: scratch::ScratchDirs,
+    dir_usage: dir_usage::DirectoryUsageStore,
+    /// Live shells by session
+    ptys: HashMap<String, std::sync::Arc<pty::PtyShell>>,
+    pty_output: tokio::sync::broadcast::Sender<pty::PtyOutput>,
+    pty_directories: tokio::sync::broadcast::Sender<pty::DirectoryReport>,
+    /// Sessions get a live shell; off for tools without a terminal view
+    use_pty: bool,
+    running: running::RunningCommands,
+    recent_files: recent_files::RecentFiles,
+    lifecycle: lifecycle::LifecycleSettings,
+    /// Background jobs, orphans and commands running on their own
+    processes: lifecycle::ProcessRegistry,
+    saved_sessions: saved_sessions::SavedSessions,
+    services: dev_services::ServiceManager,
+    aliases: aliases::Aliases,
+    shell_history: shell_history::ShellHistoryWriter,
+}
+
+impl Default for TerminalManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TerminalManager {
     pub fn new() -> Self {
+        Self::with_processes(lifecycle::ProcessRegistry::load())
+    }
+
+    fn with_processes(processes: lifecycle::ProcessRegistry) -> Self {
         Self {
             sessions: HashMap::new(),
-            command_history: Vec::new(),
+            // Demo mode shows its scripted history and keeps nothing
+            command_history: if crate::system::demo::is_active() { history::CommandHistory::new() } else { history::CommandHistory::load() },
+            ci_status: HashMap::new(),
+            project_contexts: HashMap::new(),
+            path_display: path_display::PathDisplaySettings::load(),
+            frecency: frecency::DirectoryFrecency::load(),
+            coverage: HashMap::new(),
+            launch_profiles: launch_profiles::LaunchProfiles::load(),
+            structured: structured::StructuredCapabilities::load(),
+            scratch: scratch::ScratchDirs::load(),
+            dir_usage: dir_usage::DirectoryUsageStore::load(),
+            ptys: HashMap::new(),
+            pty_output: tokio::sync::broadcast::channel(pty::OUTPUT_BACKLOG).0,
+            pty_directories: tokio::sync::broadcast::channel(64).0,
+            use_pty: true,
+            running: running::RunningCommands::new(processes.clone()),
+            recent_files: recent_files::RecentFiles::new(),
+            lifecycle: lifecycle::LifecycleSettings::load(),
+            services: dev_services::ServiceManager::new(processes.clone()),
+            aliases: aliases::Aliases::load(),
+            shell_history: shell_history::ShellHistoryWriter::load(),
+            processes,
+            saved_sessions: saved_sessions::SavedSessions::for_app(),
+        }
+    }
+
+    /// Commands run as their own processes, for tools such as `ph7` that have no terminal view;
+    /// the app's process registry is left to the app
+    pub fn new_headless() -> Self {
+        Self {
+            use_pty: false,
+            saved_sessions: saved_sessions::SavedSessions::default(),
+            ..Self::with_processes(lifecycle::ProcessRegistry::default())
+        }
+    }
+
+    /// Live output of every session's shell
+    pub fn subscribe_output(&self) -> tokio::sync::broadcast::Receiver<pty::PtyOutput> {
+        self.pty_output.subscribe()
+    }
+
+    /// Directories sessions' shells moved to, to be passed to `follow_shell_directory`
+    pub fn subscribe_directories(&self) -> tokio::sync::broadcast::Receiver<pty::DirectoryReport> {
+        self.pty_directories.subscribe()
+    }
+
+    /// Give a session a live shell, where its shell supports one; replaces a shell it had
+    fn start_shell(&mut self, session_id: &str) {
+        self.ptys.remove(session_id);
+        if !self.use_pty || crate::system::demo::is_active() {
+            return;
+        }
+        let Some(session) = self.sessions.get(session_id) else { return };
+        match pty::PtyShell::spawn(session, self.pty_output.clone(), self.pty_directories.clone()) {
+            Ok(Some(shell)) => {
+                self.ptys.insert(session_id.to_string(), shell);
+            }
+            Ok(None) => {}
+            Err(e) => println!("⚠️ {}; commands in this session run on their own", e),
+        }
+    }
+
+    /// Keep a session's live shell in step with a change the terminal made itself
+    fn sync_shell(&self, session_id: &str, line: &str) {
+        if let Some(shell) = self.ptys.get(session_id) {
+            if let Err(e) = shell.send_quiet(line) {
+                println!("⚠️ {}", e);
+            }
+        }
+    }
+
+    fn set_session_directory(&mut self, session_id: &str, directory: String) {
+        let display_directory = self.path_display.display(&directory);
+        let Some(session) = self.sessions.get_mut(session_id) else { return };
+        let cd = format!("cd {}", shell_quote::quote(&directory, shell_quote::ShellKind::from_shell(&session.shell)));
+        session.working_directory = directory;
+        session.display_directory = display_directory;
+        self.sync_shell(session_id, &cd);
+        self.save_sessions();
+    }
+
+    /// Take the directory a session's shell says it is in, wherever a command or the user took it
+    /// (`cd foo && ls`, `pushd`, a sourced script); true when the session moved
+    pub fn follow_shell_directory(&mut self, session_id: &str, directory: &str) -> bool {
+        let Some(session) = self.sessions.get(session_id) else { return false };
+        // A local path that is not there is not where this machine's shell is
+        if session.working_directory == directory || (session.remote.is_none() && !Path::new(directory).is_dir()) {
+            return false;
+        }
+        let local = session.remote.is_none();
+        let display_directory = self.path_display.display(directory);
+        if let Some(session) = self.sessions.get_mut(session_id) {
+            // The shell is there already, so unlike `set_session_directory` nothing is sent to it
+            session.working_directory = directory.to_string();
+            session.display_directory = display_directory;
+        }
+        if local {
+            self.frecency.record(directory);
+        }
+        self.sync_project_context(session_id);
+        self.save_sessions();
+        true
+    }
+
+    /// Write the open sessions, to be reopened on the next launch
+    fn save_sessions(&self) {
+        self.saved_sessions.save(self.sessions.values());
+    }
+
+    /// Frecency score of every directory a session has changed into
+    pub fn directory_scores(&self) -> HashMap<String, f64> {
+        self.frecency.scores()
+    }
+
+    pub fn path_display_settings(&self) -> &path_display::PathDisplaySettings {
+        &self.path_display
+    }
+
+    /// Save new display settings and re-render every session's directory with them
+    pub fn set_path_display_settings(&mut self, settings: path_display::PathDisplaySettings) -> Result<(), String> {
+        settings.save()?;
+        self.path_display = settings;
+        for session in self.sessions.values_mut() {
+            session.display_directory = self.path_display.display(&session.working_directory);
+        }
+        Ok(())
+    }
+
+    /// Latest coverage of the project containing `working_dir`, if a test run reported any
+    pub fn latest_coverage(&self, working_dir: &str) -> Option<&crate::project::coverage::CoverageSummary> {
+        let root = crate::project::symbols::project_root(Path::new(working_dir));
+        self.coverage.get(root.to_string_lossy().as_ref())
+    }
+
+    /// Read a report written since `since`, remember it and move the project's baseline up if it improved
+    async fn ingest_coverage(&mut self, working_dir: &str, since: std::time::SystemTime) -> Option<crate::project::coverage::CoverageSummary> {
+        let root = crate::project::symbols::project_root(Path::new(working_dir));
+        let key = root.to_string_lossy().to_string();
+        let summary = crate::system::fs_tasks::run(move || crate::project::coverage::fresh_report(&root, since)).await.ok()??;
+        // The baseline file is updated in the background, not while the manager is locked
+        let (baseline_key, observed) = (key.clone(), summary.clone());
+        tokio::task::spawn_blocking(move || {
+            let mut baselines = crate::project::coverage::CoverageBaselines::load();
+            if baselines.observe(&baseline_key, &observed) {
+                if let Err(e) = baselines.save() {
+                    println!("⚠️ Failed to save coverage baseline: {}", e);
+                }
+            }
+        });
+        self.coverage.insert(key, summary.clone());
+        Some(summary)
+    }
+
+    /// Remember the latest CI status for a directory so prompts can include it
+    pub fn update_ci_status(&mut self, working_dir: &str, status: Option<crate::integrations::ci::CiStatus>) {
+        match status {
+            Some(status) => { self.ci_status.insert(working_dir.to_string(), status); }
+            None => { self.ci_status.remove(working_dir); }
         }
     }
 
-    pub fn create_session(&mut self, title: Option<String>) -> Result<String, Box<dyn std::error::Error>> {
-        let session_id = Uuid::new_v4().to_string();
-        let working_directory = std::env::current_dir()?.to_string_lossy().to_string();
+    /// Load the project context file for a session's directory the first time it is entered
+    fn sync_project_context(&mut self, session_id: &str) {
+        if let Some(dir) = self.sessions.get(session_id).filter(|s| s.remote.is_none()).map(|s| s.working_directory.clone()) {
+            self.project_contexts.entry(dir).or_insert_with_key(|dir| context_file::load_for(dir));
+        }
+    }
+
+    /// Move the session to the nearest existing ancestor when its directory was deleted or unmounted
+    pub fn recover_working_directory(&mut self, session_id: &str) -> Option<DirectoryRecovery> {
+        let session = self.sessions.get(session_id).filter(|session| session.remote.is_none())?;
+        let missing = PathBuf::from(&session.working_directory);
+        if missing.is_dir() {
+            return None;
+        }
+        let recovered = missing
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.is_dir())
+            .map(PathBuf::from)
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("/"));
+        let recovery = DirectoryRecovery {
+            session_id: session_id.to_string(),
+            missing: missing.to_string_lossy().to_string(),
+            recovered: recovered.to_string_lossy().to_string(),
+        };
+        self.set_session_directory(session_id, recovery.recovered.clone());
+        self.sync_project_context(session_id);
+        Some(recovery)
+    }
+
+    /// Sessions whose working directory is at or below `dir`
+    pub fn sessions_under(&self, dir: &Path) -> Vec<String> {
+        self.sessions
+            .values()
+            .filter(|session| Path::new(&session.working_directory).starts_with(dir))
+            .map(|session| session.id.clone())
+            .collect()
+    }
+
+    /// Move every session out of `dir` before it goes away, e.g. when its volume is ejected
+    pub fn leave_directory(&mut self, dir: &Path) -> Vec<DirectoryRecovery> {
+        let target = crate::system::volumes::fallback_directory(dir).to_string_lossy().to_string();
+        let mut recoveries = Vec::new();
+        for session_id in self.sessions_under(dir) {
+            let missing = self.sessions[&session_id].working_directory.clone();
+            self.set_session_directory(&session_id, target.clone());
+            self.sync_project_context(&session_id);
+            recoveries.push(DirectoryRecovery { session_id, missing, recovered: target.clone() });
+        }
+        recoveries
+    }
+
+    /// Re-read the project context file, e.g. after the project was trusted or the file edited
+    pub fn reload_project_context(&mut self, session_id: &str) -> Result<ProjectContextStatus, String> {
+        let dir = self.sessions.get(session_id).map(|s| s.working_directory.clone()).ok_or("Session not found")?;
+        let status = context_file::load_for(&dir);
+        self.project_contexts.insert(dir, status.clone());
+        Ok(status)
+    }
+
+    /// Trusted project context for a session, if any
+    pub fn project_context(&self, session_id: &str) -> Option<&ProjectContext> {
+        let session = self.sessions.get(session_id)?;
+        self.project_contexts.get(&session.working_directory)?.context.as_ref()
+    }
+
+    /// Start a session. A missing start directory falls back to its nearest existing parent,
+    /// reported as a recovery; an unknown shell or profile falls back to the defaults
+    pub fn create_session(&mut self, options: launch_profiles::SessionOptions) -> Result<(String, Option<DirectoryRecovery>), String> {
+        self.open_session(Uuid::new_v4().to_string(), options)
+    }
+
+    fn open_session(&mut self, session_id: String, options: launch_profiles::SessionOptions) -> Result<(String, Option<DirectoryRecovery>), String> {
+        let process_directory = std::env::current_dir().map_err(|e| e.to_string())?;
         
-        // Get default shell
-        let shell = std::env::var("SHELL")
-            .or_else(|_| std::env::var("COMSPEC"))
-            .unwrap_or_else(|_| {
-                if cfg!(windows) {
-                    "cmd.exe".to_string()
-                } else {
-                    "/bin/bash".to_string()
+        let options = match options.profile_id.as_deref() {
+            Some(id) => match self.launch_profiles.get(id) {
+                Some(profile) => options.clone().with_profile(profile),
+                None => {
+                    println!("⚠️ Launch profile {} not found, starting with the defaults", id);
+                    options
                 }
-            });
+            },
+            None => options,
+        };
+        
+        let (directory, recovery) = match options.working_directory.as_deref() {
+            Some(requested) => {
+                let (directory, moved) = launch_profiles::resolve_directory(requested, &process_directory);
+                let directory = self.path_display.normalize(&directory).to_string_lossy().to_string();
+                let recovery = moved.then(|| DirectoryRecovery {
+                    session_id: session_id.clone(),
+                    missing: requested.to_string(),
+                    recovered: directory.clone(),
+                });
+                (directory, recovery)
+            }
+            None if crate::system::demo::is_active() => (crate::system::demo::start_directory().to_string_lossy().to_string(), None),
+            None => (process_directory.to_string_lossy().to_string(), None),
+        };
+        
+        let shell = match options.shell.as_deref() {
+            Some(requested) => launch_profiles::resolve_shell(requested).unwrap_or_else(|| {
+                println!("⚠️ Shell {} not found, using the login shell", requested);
+                launch_profiles::default_shell()
+            }),
+            None => launch_profiles::default_shell(),
+        };
 
-        // Get environment variables
-        let mut environment_vars = HashMap::new();
-        for (key, value) in std::env::vars() {
-            environment_vars.insert(key, value);
+        // Inherited environment with the overrides applied
+        let mut environment_vars: HashMap<String, String> = std::env::vars().collect();
+        for (key, value) in options.env.clone() {
+            match value {
+                Some(value) => environment_vars.insert(key, value),
+                None => environment_vars.remove(&key),
+            };
         }
+        environment_vars.insert("SHELL".to_string(), shell.clone());
         
         let session = TerminalSession {
             id: session_id.clone(),
-            title: title.unwrap_or_else(|| format!("Terminal {}", session_id[..8].to_string())),
-            working_directory,
+            title: options.title.unwrap_or_else(|| format!("Terminal {}", &session_id[..8])),
+            display_directory: self.path_display.display(&directory),
+            working_directory: directory,
             is_active: true,
             created_at: chrono::Utc::now(),
             environment_vars,
             shell,
             pty_size: (80, 24), // Default terminal size
+            variables: HashMap::new(),
+            accessible_output: false,
+            child_policy: None,
+            env_overrides: options.env,
+            remote: None,
         };
         
         self.sessions.insert(session_id.clone(), session);
+        self.sync_project_context(&session_id);
+        self.start_shell(&session_id);
+        self.save_sessions();
+        Ok((session_id, recovery))
+    }
+
+    /// Open a terminal on another machine over SSH; it connects in the background, and its
+    /// commands fail rather than run here while it is not connected
+    pub fn create_remote_session(&mut self, config: ssh::SshSessionConfig, title: Option<String>) -> Result<String, String> {
+        self.open_remote_session(Uuid::new_v4().to_string(), config, title)
+    }
+
+    fn open_remote_session(&mut self, session_id: String, config: ssh::SshSessionConfig, title: Option<String>) -> Result<String, String> {
+        if crate::system::demo::is_active() || !self.use_pty {
+            return Err("SSH sessions need a live terminal, which demo mode and headless tools do not have".to_string());
+        }
+        config.validate()?;
+        ssh::client()?;
+        let session = TerminalSession {
+            id: session_id.clone(),
+            title: title.unwrap_or_else(|| config.label()),
+            working_directory: "~".to_string(),
+            display_directory: format!("{}:~", config.label()),
+            is_active: true,
+            created_at: chrono::Utc::now(),
+            // The ssh client's environment; SSH_AUTH_SOCK is how it reaches the agent
+            environment_vars: std::env::vars().collect(),
+            shell: config.remote_shell().to_string(),
+            pty_size: (80, 24),
+            variables: HashMap::new(),
+            accessible_output: false,
+            child_policy: None,
+            env_overrides: HashMap::new(),
+            remote: Some(config),
+        };
+        self.sessions.insert(session_id.clone(), session);
+        self.start_shell(&session_id);
+        if !self.ptys.contains_key(&session_id) {
+            self.sessions.remove(&session_id);
+            return Err("Could not start the ssh client".to_string());
+        }
+        self.save_sessions();
         Ok(session_id)
     }
 
+    /// Reopen the sessions open when the app last ran, under their old ids; sessions already
+    /// open are left alone. A directory that is gone falls back to its nearest parent.
+    pub fn restore_sessions(&mut self) -> (Vec<TerminalSession>, Vec<DirectoryRecovery>) {
+        let mut restored = Vec::new();
+        let mut recoveries = Vec::new();
+        for saved in self.saved_sessions.load() {
+            if self.sessions.contains_key(&saved.id) {
+                continue;
+            }
+            if let Some(remote) = saved.remote.clone() {
+                match self.open_remote_session(saved.id.clone(), remote, Some(saved.title.clone())) {
+                    Ok(session_id) => {
+                        let (cols, rows) = saved.pty_size;
+                        if let Some(session) = self.sessions.get_mut(&session_id) {
+                            session.created_at = saved.created_at;
+                            session.accessible_output = saved.accessible_output;
+                        }
+                        let _ = self.resize_terminal(&session_id, rows, cols);
+                        restored.extend(self.sessions.get(&session_id).cloned());
+                    }
+                    Err(e) => println!("⚠️ Could not reconnect session '{}': {}", saved.title, e),
+                }
+                continue;
+            }
+            let options = launch_profiles::SessionOptions {
+                title: Some(saved.title.clone()),
+                working_directory: Some(saved.working_directory.clone()),
+                shell: Some(saved.shell.clone()),
+                env: saved.env.clone(),
+                profile_id: None,
+            };
+            let (session_id, recovery) = match self.open_session(saved.id.clone(), options) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    println!("⚠️ Could not restore session '{}': {}", saved.title, e);
+                    continue;
+                }
+            };
+            let (cols, rows) = saved.pty_size;
+            if let Some(session) = self.sessions.get_mut(&session_id) {
+                session.created_at = saved.created_at;
+                session.accessible_output = saved.accessible_output;
+                session.child_policy = saved.child_policy;
+            }
+            if let Err(e) = self.resize_terminal(&session_id, rows, cols) {
+                println!("⚠️ {}", e);
+            }
+            if let Some(session) = self.sessions.get(&session_id) {
+                restored.push(session.clone());
+            }
+            recoveries.extend(recovery);
+        }
+        if !restored.is_empty() {
+            println!("🔁 Restored {} session(s) from the last run", restored.len());
+        }
+        self.save_sessions();
+        (restored, recoveries)
+    }
+
+    /// Switch the shell a session's syntax follows; the shell must be installed
+    pub fn set_session_shell(&mut self, session_id: &str, shell: &str) -> Result<String, String> {
+        let session = self.sessions.get_mut(session_id).ok_or("Session not found")?;
+        let resolved = launch_profiles::resolve_shell(shell).ok_or_else(|| format!("Shell {} not found", shell))?;
+        session.environment_vars.insert("SHELL".to_string(), resolved.clone());
+        session.shell = resolved.clone();
+        self.start_shell(session_id);
+        self.save_sessions();
+        Ok(resolved)
+    }
+
+    /// A generated POSIX command rewritten for the session's shell
+    pub fn adapt_to_shell(&self, session_id: &str, command: &str) -> String {
+        match self.sessions.get(session_id) {
+            Some(session) => shell_quote::ShellKind::from_shell(&session.shell).adapt(command),
+            None => command.to_string(),
+        }
+    }
+
+    /// Setting or removing an environment variable, in the session shell's syntax
+    fn env_builtin(&mut self, session_id: &str, command: &str) -> Option<(String, i32)> {
+        let session = self.sessions.get_mut(session_id)?;
+        let change = shell_quote::ShellKind::from_shell(&session.shell).parse_env_change(command, Some(&session.environment_vars))?;
+        Some(match change {
+            shells::EnvChange::Set { name, value } => {
+                let output = format!("🔧 {}={}", name, value);
+                session.environment_vars.insert(name, value);
+                (output, 0)
+            }
+            shells::EnvChange::Unset { name } => {
+                let output = format!("🔧 {} removed", name);
+                session.environment_vars.remove(&name);
+                (output, 0)
+            }
+        })
+    }
+
+    pub fn structured_settings(&self) -> &structured::StructuredSettings {
+        self.structured.settings()
+    }
+
+    pub fn set_structured_settings(&mut self, settings: structured::StructuredSettings) -> Result<(), String> {
+        self.structured.set_settings(settings)
+    }
+
+    /// Evaluate a jq-style path (`.items[0].name`) against a command's JSON output
+    pub fn query_structured_output(&self, execution_id: &str, path: &str) -> Result<serde_json::Value, String> {
+        let execution = self.command_history.get(execution_id).ok_or("Command not found in history")?;
+        let value = structured::parse(&execution.output).ok_or("The command's output is not JSON")?;
+        capture::json_path(&value, path).cloned()
+    }
+
+    pub fn launch_profiles(&self) -> &launch_profiles::LaunchProfiles {
+        &self.launch_profiles
+    }
+
+    pub fn launch_profiles_mut(&mut self) -> &mut launch_profiles::LaunchProfiles {
+        &mut self.launch_profiles
+    }
+
+    pub fn scratch_dirs(&self) -> &scratch::ScratchDirs {
+        &self.scratch
+    }
+
+    pub fn scratch_dirs_mut(&mut self) -> &mut scratch::ScratchDirs {
+        &mut self.scratch
+    }
+
+    /// Make a scratch directory for the session and, when `enter` is set, change into it
+    pub fn create_scratch_dir(
+        &mut self,
+        session_id: Option<&str>,
+        name: Option<&str>,
+        policy: Option<scratch::CleanupPolicy>,
+        enter: bool,
+    ) -> Result<scratch::ScratchDir, String> {
+        let dir = self.scratch.create(session_id, name, policy)?;
+        if let (Some(session_id), true) = (session_id, enter) {
+            self.set_session_directory(session_id, dir.path.clone());
+        }
+        Ok(dir)
+    }
+
+    /// Remove a scratch directory; a session working in it goes back home, unless it is another session's
+    pub fn remove_scratch_dir(&mut self, session_id: Option<&str>, id: &str) -> Result<scratch::ScratchDir, String> {
+        let path = self.scratch.list(None).into_iter().find(|dir| dir.id == id).map(|dir| dir.path.clone()).ok_or("Scratch directory not found")?;
+        let inside = self.sessions_under(Path::new(&path));
+        if inside.iter().any(|other| Some(other.as_str()) != session_id) {
+            return Err("Another session is working in this scratch directory".to_string());
+        }
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/")).to_string_lossy().to_string();
+        for session in inside {
+            self.set_session_directory(&session, home.clone());
+        }
+        self.scratch.remove(id)
+    }
+
+    /// Remove scratch directories whose policy says they are done and that no session is in
+    pub fn sweep_scratch_dirs(&mut self) -> Vec<String> {
+        let live: Vec<String> = self.sessions.keys().cloned().collect();
+        let in_use: Vec<String> = self.sessions.values().map(|session| session.working_directory.clone()).collect();
+        self.scratch.sweep(&live, &in_use)
+    }
+
+    /// `scratch new [name] [--keep | --hours N]`, `scratch list`, `scratch rm <name>`, `scratch keep <name>`, `scratch clean`
+    fn scratch_builtin(&mut self, session_id: &str, args: &[&str]) -> (String, i32) {
+        let result = match args.first().copied().unwrap_or("list") {
+            "new" => scratch::parse_new_args(&args[1..])
+                .and_then(|(name, policy)| self.create_scratch_dir(Some(session_id), name.as_deref(), policy, true))
+                .map(|dir| format!("🧪 Scratch directory {} ({})\n📁 Changed directory to {}", dir.name, scratch::describe_policy(dir.policy), dir.path)),
+            "list" | "ls" => {
+                let dirs = self.scratch.list(Some(session_id));
+                if dirs.is_empty() {
+                    Ok("No scratch directories in this session; make one with `scratch new`".to_string())
+                } else {
+                    Ok(dirs
+                        .iter()
+                        .map(|dir| format!("🧪 {:<20} {}  ({})", dir.name, dir.path, scratch::describe_policy(dir.policy)))
+                        .collect::<Vec<_>>()
+                        .join("\n"))
+                }
+            }
+            "rm" | "remove" | "keep" if args.len() < 2 => Err(format!("Usage: scratch {} <name>", args[0])),
+            "rm" | "remove" => match self.scratch.find(Some(session_id), args[1]).map(|dir| dir.id.clone()) {
+                Some(id) => self.remove_scratch_dir(Some(session_id), &id).map(|dir| format!("🗑️ Removed scratch directory {}", dir.name)),
+                None => Err(format!("No scratch directory named {}", args[1])),
+            },
+            "keep" => match self.scratch.find(Some(session_id), args[1]).map(|dir| dir.id.clone()) {
+                Some(id) => self.scratch.set_policy(&id, scratch::CleanupPolicy::Keep).map(|dir| format!("📌 {} will be kept at {}", dir.name, dir.path)),
+                None => Err(format!("No scratch directory named {}", args[1])),
+            },
+            "clean" => {
+                let removed = self.sweep_scratch_dirs();
+                Ok(format!("🗑️ Removed {} expired scratch director{}", removed.len(), if removed.len() == 1 { "y" } else { "ies" }))
+            }
+            other => Err(format!("Unknown scratch command '{}'; use new, list, rm, keep or clean", other)),
+        };
+        match result {
+            Ok(output) => (output, 0),
+            Err(e) => (format!("❌ {}", e), 1),
+        }
+    }
+
     pub async fn execute_command(
         &mut self,
         session_id: &str,
@@ -89,20 +856,77 @@ impl TerminalManager {
         command_to_execute: &str,
         command_for_history: &str,
     ) -> Result<CommandExecution, Box<dyn std::error::Error + Send + Sync>> {
+        match self.prepare_command(session_id, command_to_execute, command_for_history, &exec_options::ExecutionOptions::default()).await? {
+            PreparedCommand::Finished(execution) => Ok(execution),
+            PreparedCommand::Ready(pending) => {
+                let result = pending.run().await;
+                Ok(self.finish_command(pending, result).await)
+            }
+        }
+    }
+
+    /// Everything before a command runs: project rules, built-ins and how to run it.
+    /// A command with `options` skips the built-ins and runs as its own process
+    async fn prepare_command(
+        &mut self,
+        session_id: &str,
+        command_to_execute: &str,
+        command_for_history: &str,
+        options: &exec_options::ExecutionOptions,
+    ) -> Result<PreparedCommand, Box<dyn std::error::Error + Send + Sync>> {
         let start_time = std::time::Instant::now();
         let execution_id = Uuid::new_v4().to_string();
+        // Aliases first, so one standing for a built-in (`alias ..='cd ..'`) is handled as that built-in
+        let expanded = self.aliases.expand(command_to_execute);
+        let command_to_execute = expanded.as_deref().unwrap_or(command_to_execute);
+        // A `cd` is counted in the directory it left
+        let ran_in = self.sessions.get(session_id).map(|session| session.working_directory.clone());
         
         // Parse command and arguments for execution
-        let parts: Vec<&str> = command_to_execute.split_whitespace().collect();
+        let parts = shell_quote::split(command_to_execute)?;
         if parts.is_empty() {
             return Err("Empty command".into());
         }
 
-        let cmd = parts[0];
-        let args = &parts[1..];
+        // Shell-specific spellings of the built-ins (Set-Location, cls) map onto them
+        let cmd = match self.sessions.get(session_id) {
+            Some(session) => shell_quote::ShellKind::from_shell(&session.shell).builtin_name(parts[0].as_str()),
+            None => parts[0].as_str(),
+        };
+        let args: Vec<&str> = parts[1..].iter().map(String::as_str).collect();
+        let args = args.as_slice();
+        
+        // Enforce the project's forbidden commands
+        if let Some(project) = self.project_context(session_id) {
+            if let Some(rule) = project.forbidden_rule(command_to_execute) {
+                return Err(format!("⛔ '{}' is forbidden in this project ({})", rule, project.source_file).into());
+            }
+        }
+        
+        // Pipes, chains and redirections belong to the shell, built-ins included
+        let features = shell_quote::shell_features(command_to_execute);
+        let overridden = !options.is_empty();
+        // An SSH session's shell is on the other machine; `cd` and the rest go to it
+        let remote = self.sessions.get(session_id).is_some_and(|session| session.remote.is_some());
+        if remote && overridden {
+            return Err("One-off execution options are not available in SSH sessions".into());
+        }
+        let skip_builtins = features.operators || overridden || remote;
         
         // Handle built-in commands
-        if let Some(result) = self.handle_builtin_command(session_id, cmd, args).await? {
+        let env_change = if skip_builtins { None } else { self.env_builtin(session_id, command_to_execute) };
+        let changed_env = env_change.is_some();
+        let builtin = match env_change {
+            Some(result) => Some(result),
+            None if skip_builtins => None,
+            None => self.handle_builtin_command(session_id, cmd, args).await?,
+        };
+        if let Some(result) = builtin {
+            // The session's live shell keeps the same environment (directories follow in `set_session_directory`)
+            if changed_env && result.1 == 0 {
+                self.sync_shell(session_id, command_to_execute);
+            }
+            self.sync_project_context(session_id);
             let duration = start_time.elapsed();
             let execution = CommandExecution {
                 id: execution_id,
@@ -111,17 +935,19 @@ impl TerminalManager {
                 exit_code: Some(result.1),
                 duration_ms: duration.as_millis() as u64,
                 timestamp: chrono::Utc::now(),
+                outcome: ExecutionOutcome::Exited(result.1),
+                coverage: None,
+                structured: None,
+                overrides: None,
+                styled: None,
+                session_id: Some(session_id.to_string()),
             };
             
             // IMPORTANT: Add built-in commands to history too!
             self.command_history.push(execution.clone());
+            self.record_usage(session_id, ran_in.as_deref(), &execution);
             
-            // Limit history size
-            if self.command_history.len() > 1000 {
-                self.command_history.remove(0);
-            }
-            
-            return Ok(execution);
+            return Ok(PreparedCommand::Finished(execution));
         }
         
         // Set working directory and environment if session exists
@@ -130,13 +956,126 @@ impl TerminalManager {
         } else {
             (std::env::current_dir()?.to_string_lossy().to_string(), std::env::vars().collect())
         };
+        let (working_dir, env_vars) = if overridden {
+            (options.directory(&working_dir)?, options.environment(&env_vars))
+        } else {
+            (working_dir, env_vars)
+        };
         
-        // Execute command with enhanced error handling
-        let output_result = self.execute_system_command(cmd, args, &working_dir, &env_vars).await;
+        // Detached and adopted commands start in the background and answer at once
+        let session_policy = self.sessions.get(session_id).and_then(|session| session.child_policy);
+        let policy = self.lifecycle.policy_for(command_to_execute, options.child_policy, session_policy);
+        if policy != lifecycle::ChildPolicy::KillOnClose && !remote && !crate::system::demo::is_active() {
+            let shell = match &options.shell {
+                Some(requested) => launch_profiles::resolve_shell(requested).ok_or_else(|| format!("Shell {} not found", requested))?,
+                None => self.sessions.get(session_id).map(|session| session.shell.clone()).unwrap_or_else(launch_profiles::default_shell),
+            };
+            let background = lifecycle::BackgroundCommand {
+                id: &execution_id,
+                session_id,
+                command_line: command_to_execute,
+                working_directory: &working_dir,
+                environment: &env_vars,
+                shell: &shell,
+            };
+            let process = lifecycle::spawn_background(&self.processes, background, policy)?;
+            let how = if policy == lifecycle::ChildPolicy::Adopt { "as a background job" } else { "detached" };
+            let execution = CommandExecution {
+                id: execution_id,
+                command: command_for_history.to_string(),
+                output: format!("🚀 Started {} (pid {}), output in {}", how, process.pid, process.log_file.unwrap_or_default()),
+                exit_code: Some(0),
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                timestamp: chrono::Utc::now(),
+                outcome: ExecutionOutcome::Exited(0),
+                coverage: None,
+                structured: None,
+                overrides: overridden.then(|| options.clone()),
+                styled: None,
+                session_id: Some(session_id.to_string()),
+            };
+            self.command_history.push(execution.clone());
+            self.record_usage(session_id, ran_in.as_deref(), &execution);
+            return Ok(PreparedCommand::Finished(execution));
+        }
         
-        let (output, exit_code) = match output_result {
-            Ok((stdout, stderr, exit_code)) => {
-                if exit_code.unwrap_or(0) == 0 || stderr.is_empty() {
+        // Ask for JSON where the session's shell or the command can give it
+        let nushell = self.sessions.get(session_id)
+            .filter(|session| shell_quote::ShellKind::from_shell(&session.shell) == shell_quote::ShellKind::Nushell)
+            .map(|session| session.shell.clone());
+        let path_var = env_vars.get("PATH").cloned().unwrap_or_default();
+        let session_shell = match &options.shell {
+            Some(requested) => Some(launch_profiles::resolve_shell(requested).ok_or_else(|| format!("Shell {} not found", requested))?),
+            None => self.sessions.get(session_id).map(|session| session.shell.clone()),
+        };
+        let (program, program_args, structured_source) = match nushell {
+            _ if remote => (cmd.to_string(), args.iter().map(|arg| arg.to_string()).collect(), None),
+            Some(nu) if self.structured.settings().enabled && !overridden => (nu, structured::nushell_wrapper(command_to_execute), Some("nushell".to_string())),
+            // Without a live shell, a line using shell syntax goes to a one-off shell; demo mode runs nothing
+            _ if (features.any() || options.shell.is_some()) && !crate::system::demo::is_active() => {
+                let shell = session_shell.unwrap_or_else(launch_profiles::default_shell);
+                let (program, program_args) = shell_quote::ShellKind::from_shell(&shell).invocation(&shell, command_to_execute);
+                (program, program_args, None)
+            }
+            _ => {
+                let mut program_args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                let flags = if overridden { None } else { self.structured.json_flags(cmd, args, &path_var) };
+                let source = flags.map(|flags| {
+                    let source = flags.join(" ");
+                    program_args.extend(flags);
+                    source
+                });
+                (cmd.to_string(), program_args, source)
+            }
+        };
+        
+        // Commands asked for JSON run on their own, so the JSON never shows in the terminal
+        // and so do commands with overrides, which must leave the session as it was
+        let shell = self.ptys.get(session_id)
+            .filter(|shell| shell.is_alive() && structured_source.is_none() && !overridden)
+            .cloned();
+        if remote && shell.is_none() {
+            return Err("The SSH connection is closed; reconnect the session".into());
+        }
+        
+        let registration = self.running.register(&execution_id, session_id, command_to_execute, &working_dir);
+        Ok(PreparedCommand::Ready(PendingCommand {
+            session_id: session_id.to_string(),
+            execution_id,
+            start_time,
+            started_at: std::time::SystemTime::now(),
+            ran_in,
+            command_to_execute: command_to_execute.to_string(),
+            command_for_history: command_for_history.to_string(),
+            cmd: cmd.to_string(),
+            args: parts[1..].to_vec(),
+            program,
+            program_args,
+            structured_source,
+            working_dir,
+            env_vars,
+            shell,
+            registration,
+            overrides: overridden.then(|| options.clone()),
+            remote,
+        }))
+    }
+
+    /// Everything after a command ran: error explanations, directory and coverage updates, history
+    async fn finish_command(&mut self, pending: PendingCommand, result: (String, String, ExecutionOutcome)) -> CommandExecution {
+        let (stdout, stderr, outcome) = result;
+        let session_id = pending.session_id.as_str();
+        let cmd = pending.cmd.as_str();
+        let args: Vec<&str> = pending.args.iter().map(String::as_str).collect();
+        let args = args.as_slice();
+        let exit_code = Some(outcome.exit_code());
+        let structured = pending.structured_source
+            .filter(|_| outcome.is_success())
+            .and_then(|source| structured::attach(&ansi::strip(&stdout), &source));
+        
+        let output = match outcome {
+            ExecutionOutcome::Exited(_) => {
+                if outcome.is_success() || stderr.is_empty() {
                     // Success or no errors - combine stdout/stderr normally
                     let combined = if stderr.is_empty() {
                         stdout
@@ -145,48 +1084,107 @@ impl TerminalManager {
                     } else {
                         format!("{}\n{}", stdout, stderr)
                     };
-                    (combined, exit_code)
+                    combined
                 } else {
                     // Error case - enhance the error message
-                    let enhanced_error = self.enhance_error_message(command_to_execute, &stderr, exit_code);
+                    let enhanced_error = self.enhance_error_message(&pending.command_to_execute, &ansi::strip(&stderr), outcome, &pending.env_vars);
                     let combined = if stdout.is_empty() {
                         enhanced_error
                     } else {
                         format!("{}\n\n{}", stdout, enhanced_error)
                     };
-                    (combined, exit_code)
+                    combined
                 }
             },
-            Err(e) => {
-                let enhanced_error = self.enhance_error_message(command_to_execute, &e.to_string(), Some(1));
-                (enhanced_error, Some(1))
+            // Killed, timed out or never started: explain how it ended, keeping any partial output
+            _ => {
+                let enhanced_error = self.enhance_error_message(&pending.command_to_execute, &ansi::strip(&stderr), outcome, &pending.env_vars);
+                if stdout.is_empty() {
+                    enhanced_error
+                } else {
+                    format!("{}\n\n{}", stdout, enhanced_error)
+                }
             }
         };
+        // History and the AI get the text; the display gets it with its colours
+        let processed = ansi::process(&output);
+        let styled = processed.styled();
+        let output = processed.plain;
         
-        let duration = start_time.elapsed();
+        let duration = pending.start_time.elapsed();
         
         // Update working directory if command was 'cd'
-        if cmd == "cd" && exit_code == Some(0) {
+        if cmd == "cd" && exit_code == Some(0) && pending.overrides.is_none() && !pending.remote && !shell_quote::shell_features(&pending.command_to_execute).operators {
             self.update_session_directory(session_id, args);
+            self.sync_project_context(session_id);
+        }
+        // The live shell knows where it ended up, whatever the command did
+        if let Some(directory) = pending.shell.as_ref().and_then(|shell| shell.current_directory()) {
+            self.follow_shell_directory(session_id, &directory);
         }
         
+        // A test run that wrote a coverage report gets it attached
+        let coverage = if crate::project::coverage::is_test_command(cmd, args) && !pending.remote {
+            self.ingest_coverage(&pending.working_dir, pending.started_at).await
+        } else {
+            None
+        };
+        
         let execution = CommandExecution {
-            id: execution_id,
-            command: command_for_history.to_string(), // Store the original command in history
+            id: pending.execution_id,
+            command: pending.command_for_history, // Store the original command in history
             output,
             exit_code,
             duration_ms: duration.as_millis() as u64,
             timestamp: chrono::Utc::now(),
+            outcome,
+            coverage,
+            structured,
+            overrides: pending.overrides,
+            styled,
+            session_id: Some(session_id.to_string()),
         };
         
         self.command_history.push(execution.clone());
+        self.record_usage(session_id, pending.ran_in.as_deref(), &execution);
         
-        // Limit history size
-        if self.command_history.len() > 1000 {
-            self.command_history.remove(0);
+        execution
+    }
+
+    fn record_usage(&mut self, session_id: &str, directory: Option<&str>, execution: &CommandExecution) {
+        if let Some(directory) = directory {
+            self.dir_usage.record(session_id, directory, &execution.command, execution.outcome.is_success());
+            self.recent_files.record(session_id, directory, &execution.command, &execution.output);
         }
-        
-        Ok(execution)
+        // An SSH session's commands belong to the other machine's history
+        if let Some(session) = self.sessions.get(session_id).filter(|session| session.remote.is_none()) {
+            // The shell knows nothing of the app's aliases, so what they stand for is written
+            let command = self.expand_aliases(&execution.command);
+            self.shell_history.record(&session.shell, &session.environment_vars, &command, execution);
+        }
+    }
+
+    /// Files the session's commands named or printed, most recent first
+    pub fn recent_files(&self, session_id: &str, limit: usize) -> Vec<recent_files::RecentFile> {
+        self.recent_files.list(session_id, limit)
+    }
+
+    /// Top commands, failure hotspots and usual next steps in a directory
+    pub fn directory_profile(&self, directory: &str) -> dir_usage::DirectoryProfile {
+        self.dir_usage.profile(directory)
+    }
+
+    pub fn directory_command_stats(&self, directory: &str, command: &str) -> Option<dir_usage::CommandStats> {
+        self.dir_usage.command_stats(directory, command).cloned()
+    }
+
+    pub fn directory_heatmap(&self, limit: usize) -> Vec<dir_usage::DirectoryHeat> {
+        self.dir_usage.heatmap(limit)
+    }
+
+    /// Queue what the per-directory usage store has not saved yet
+    pub fn flush_usage(&mut self) {
+        self.dir_usage.flush();
     }
 
     /// Handle built-in terminal commands
@@ -198,7 +1196,9 @@ impl TerminalManager {
     ) -> Result<Option<(String, i32)>, Box<dyn std::error::Error + Send + Sync>> {
         match cmd {
             "cd" => {
-                let target_dir = if args.is_empty() {
+                let target_dir = if args.is_empty() && crate::system::demo::is_active() {
+                    crate::system::demo::workspace()
+                } else if args.is_empty() {
                     // Go to home directory
                     dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
                 } else {
@@ -227,39 +1227,18 @@ impl TerminalManager {
                         }
                     };
 
-                    // Try to canonicalize the path to resolve .. and . components
-                    match expanded_path.canonicalize() {
-                        Ok(canonical) => canonical,
-                        Err(_) => {
-                            // If canonicalize fails, try to resolve manually
-                            let mut components = Vec::new();
-                            for component in expanded_path.components() {
-                                match component {
-                                    std::path::Component::ParentDir => {
-                                        components.pop();
-                                    },
-                                    std::path::Component::CurDir => {
-                                        // Skip current directory
-                                    },
-                                    _ => {
-                                        components.push(component.as_os_str());
-                                    }
-                                }
-                            }
-                            let mut result = PathBuf::new();
-                            for component in components {
-                                result.push(component);
-                            }
-                            result
-                        }
-                    }
+                    // Resolve .. and . components, following or keeping symlinks as configured
+                    self.path_display.normalize(&expanded_path)
                 };
 
+                // Demo mode shows only its made-up projects, never the user's own directories
+                if crate::system::demo::is_active() && !target_dir.starts_with(crate::system::demo::workspace()) {
+                    return Ok(Some(("🎭 Demo mode stays inside the demo workspace".to_string(), 1)));
+                }
                 if target_dir.exists() && target_dir.is_dir() {
-                    if let Some(session) = self.sessions.get_mut(session_id) {
-                        session.working_directory = target_dir.to_string_lossy().to_string();
-                    }
-                    Ok(Some((format!("📁 Changed directory to {}", target_dir.display()), 0)))
+                    self.set_session_directory(session_id, target_dir.to_string_lossy().to_string());
+                    self.frecency.record(&target_dir.to_string_lossy());
+                    Ok(Some((format!("📁 Changed directory to {}", self.path_display.display_full(&target_dir.to_string_lossy())), 0)))
                 } else {
                     // Enhanced error message with suggestions
                     let suggestion = if !target_dir.exists() {
@@ -267,11 +1246,11 @@ impl TerminalManager {
                         let suggestions = if let Some(parent_dir) = parent {
                             if parent_dir.exists() {
                                 // List similar directories in parent
-                                if let Ok(entries) = std::fs::read_dir(parent_dir) {
+                                if let Ok(entries) = crate::system::dir_cache::cache().list(parent_dir) {
                                     let similar_dirs: Vec<String> = entries
-                                        .filter_map(|entry| entry.ok())
-                                        .filter(|entry| entry.path().is_dir())
-                                        .map(|entry| entry.file_name().to_string_lossy().to_string())
+                                        .iter()
+                                        .filter(|entry| entry.is_dir)
+                                        .map(|entry| entry.name.clone())
                                         .filter(|name| {
                                             if let Some(target_name) = target_dir.file_name() {
                                                 let target_str = target_name.to_string_lossy().to_lowercase();
@@ -307,7 +1286,7 @@ impl TerminalManager {
             },
             "pwd" => {
                 if let Some(session) = self.sessions.get(session_id) {
-                    Ok(Some((session.working_directory.clone(), 0)))
+                    Ok(Some((self.path_display.display_full(&session.working_directory), 0)))
                 } else {
                     Ok(Some((std::env::current_dir()?.to_string_lossy().to_string(), 0)))
                 }
@@ -324,6 +1303,15 @@ impl TerminalManager {
             "clear" => {
                 Ok(Some(("\x1b[2J\x1b[H".to_string(), 0))) // ANSI clear screen
             },
+            "scratch" if crate::system::demo::is_active() => Ok(Some(("🎭 Demo mode does not make scratch directories".to_string(), 1))),
+            "scratch" => Ok(Some(self.scratch_builtin(session_id, args))),
+            "alias" | "unalias" => {
+                let result = if cmd == "alias" { self.aliases.builtin_alias(args) } else { self.aliases.builtin_unalias(args) };
+                Ok(Some(match result {
+                    Ok(output) => (output, 0),
+                    Err(e) => (format!("❌ {}", e), 1),
+                }))
+            },
             "exit" => {
                 if let Some(session) = self.sessions.get_mut(session_id) {
                     session.is_active = false;
@@ -334,44 +1322,49 @@ impl TerminalManager {
         }
     }
 
-    /// Execute system command with enhanced features
-    async fn execute_system_command(
-        &self,
-        cmd: &str,
-        args: &[&str],
-        working_dir: &str,
-        env_vars: &HashMap<String, String>,
-    ) -> Result<(String, String, Option<i32>), Box<dyn std::error::Error + Send + Sync>> {
-        let mut command = tokio::process::Command::new(cmd);
-        command.args(args);
-        command.current_dir(working_dir);
+    /// Enhance error messages with user-friendly explanations and suggestions
+    fn enhance_error_message(&self, command: &str, stderr: &str, outcome: ExecutionOutcome, env_vars: &HashMap<String, String>) -> String {
+        let message = self.describe_error(command, stderr, outcome);
         
-        // Set environment variables
-        for (key, value) in env_vars {
-            command.env(key, value);
+        // Point out when the failing command may not be the binary the user expects
+        let base_cmd = command.split_whitespace().next().unwrap_or("");
+        let path_var = env_vars.get("PATH").map(|p| p.as_str()).unwrap_or("");
+        match crate::system::path_inspector::shadow_warning(base_cmd, path_var) {
+            Some(warning) => format!("{}\n⚠️ {}", message, warning),
+            None => message,
         }
-        
-        // Execute with timeout and better error handling
-        let output = tokio::time::timeout(
-            std::time::Duration::from_secs(30), // 30 second timeout
-            command.output()
-        ).await?;
-        
-        let output = output?;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let exit_code = output.status.code();
-        
-        Ok((stdout, stderr, exit_code))
     }
 
-    /// Enhance error messages with user-friendly explanations and suggestions
-    fn enhance_error_message(&self, command: &str, stderr: &str, exit_code: Option<i32>) -> String {
+    fn describe_error(&self, command: &str, stderr: &str, outcome: ExecutionOutcome) -> String {
         let cmd_parts: Vec<&str> = command.split_whitespace().collect();
         let base_cmd = cmd_parts.get(0).unwrap_or(&"unknown");
         
+        match outcome {
+            ExecutionOutcome::TimedOut => {
+                return format!("⏱️ '{}' timed out and was stopped\n💡 Long-running commands (servers, watchers) are better started in a separate terminal", base_cmd);
+            }
+            ExecutionOutcome::Signaled(signal) => {
+                let hint = match signal {
+                    9 => "\n💡 SIGKILL usually means the system ran out of memory or another process killed it",
+                    11 => "\n💡 SIGSEGV is a crash in the program itself",
+                    _ => "",
+                };
+                return format!("❌ '{}' was terminated by {} ({}){}", base_cmd, outcome::signal_name(signal), signal, hint);
+            }
+            ExecutionOutcome::SpawnError(SpawnErrorKind::NotFound) => {
+                return format!("❌ Command '{}' not found\n💡 Try:\n  • Check spelling: did you mean a similar command?\n  • Install the command if it's a package\n  • Use 'which {}' to see if it's in PATH", base_cmd, base_cmd);
+            }
+            ExecutionOutcome::SpawnError(SpawnErrorKind::PermissionDenied) => {
+                return format!("❌ '{}' could not be started: permission denied\n💡 Check that it is executable ('chmod +x {}')", base_cmd, base_cmd);
+            }
+            ExecutionOutcome::SpawnError(SpawnErrorKind::Other) => {
+                return format!("❌ '{}' could not be started\n{}", base_cmd, stderr.trim());
+            }
+            ExecutionOutcome::Exited(_) => {}
+        }
+        
         // If stderr is empty but exit code indicates error, provide generic help
-        if stderr.trim().is_empty() && exit_code.unwrap_or(0) != 0 {
+        if stderr.trim().is_empty() && !outcome.is_success() {
             return match base_cmd {
                 &"ls" | &"dir" => "❌ Unable to list directory contents\n💡 Check if the directory exists or if you have permission to access it".to_string(),
                 &"cat" | &"less" | &"more" => "❌ Unable to read file\n💡 Check if the file exists and you have read permissions".to_string(),
@@ -382,6 +1375,12 @@ impl TerminalManager {
             };
         }
         
+        // A taken port, with who holds it and the ways out
+        if let Some(port) = port_conflicts::detect(stderr).flatten() {
+            let conflict = port_conflicts::investigate(port, command, &HashMap::new(), &self.services.ports(), None);
+            return format!("❌ {}\n{}\n{}", conflict.summary(), stderr.trim(), port_conflicts::remedy_hints(&conflict));
+        }
+        
         let error_lower = stderr.to_lowercase();
         
         // Enhanced error patterns with helpful suggestions
@@ -405,17 +1404,17 @@ impl TerminalManager {
             // For other errors, just format them nicely
             format!("❌ Error:\n{}", stderr.trim())
         } else {
-            format!("❌ Command failed with exit code {}", exit_code.unwrap_or(-1))
+            format!("❌ Command failed with exit code {}", outcome.exit_code())
         }
     }
 
     /// Update session working directory
     fn update_session_directory(&mut self, session_id: &str, args: &[&str]) {
-        if let Some(session) = self.sessions.get_mut(session_id) {
+        if let Some(session) = self.sessions.get(session_id) {
             if !args.is_empty() {
-                let new_dir = PathBuf::from(&session.working_directory).join(args[0]);
-                if let Ok(canonical) = new_dir.canonicalize() {
-                    session.working_directory = canonical.to_string_lossy().to_string();
+                let new_dir = self.path_display.normalize(&PathBuf::from(&session.working_directory).join(args[0]));
+                if new_dir.is_dir() {
+                    self.set_session_directory(session_id, new_dir.to_string_lossy().to_string());
                 }
             }
         }
@@ -429,58 +1428,52 @@ impl TerminalManager {
         self.sessions.values().collect()
     }
 
-    pub fn get_command_history(&self, limit: Option<usize>) -> Vec<&CommandExecution> {
-        let history = &self.command_history;
-        match limit {
-            Some(n) => history.iter().rev().take(n).collect(),
-            None => history.iter().rev().collect(),
-        }
+    /// Newest first, shared with the history rather than copied
+    pub fn get_command_history(&self, limit: Option<usize>) -> Vec<std::sync::Arc<CommandExecution>> {
+        self.command_history.recent(limit)
+    }
+
+    pub fn history_page(&self, before: Option<u64>, limit: usize) -> history::HistoryPage {
+        self.command_history.page(before, limit)
     }
 
-    pub fn get_smart_context(&self, session_id: &str) -> String {
+    pub fn history_since(&self, after_id: Option<&str>) -> history::HistoryDelta {
+        self.command_history.since(after_id)
+    }
+
+    /// Context for the AI about a session. What the manager knows is copied out here; the
+    /// returned future looks at the working directory, so it can run after the lock is released
+    pub fn get_smart_context(&self, session_id: &str) -> impl std::future::Future<Output = String> + Send + 'static {
         let mut context = String::new();
+        let mut scanned_directory = None;
+        let mut rest = String::new();
         
         if let Some(session) = self.sessions.get(session_id) {
             context.push_str(&format!("Working Directory: {}\n", session.working_directory));
             context.push_str(&format!("Shell: {}\n", session.shell));
+            context.push_str(&format!("Shell Syntax: {}\n", shell_quote::ShellKind::from_shell(&session.shell).syntax_hint()));
             
-            // Add file type context
-            if let Ok(entries) = std::fs::read_dir(&session.working_directory) {
-                let mut file_types = Vec::new();
-                for entry in entries.take(20) { // Limit to avoid performance issues
-                    if let Ok(entry) = entry {
-                        if let Some(ext) = entry.path().extension() {
-                            if let Some(ext_str) = ext.to_str() {
-                                file_types.push(ext_str.to_string());
-                            }
-                        }
-                    }
-                }
-                
-                if !file_types.is_empty() {
-                    file_types.sort();
-                    file_types.dedup();
-                    context.push_str(&format!("File Types: {}\n", file_types.join(", ")));
-                }
+            // Nothing on this machine describes a remote session's files or tools
+            if let Some(remote) = &session.remote {
+                context.push_str(&format!(
+                    "Remote Host: {} over SSH; commands run on that machine, whose OS, files and installed tools may differ from this one\n",
+                    remote.label()
+                ));
+                return futures::future::Either::Left(std::future::ready(context));
             }
             
-            // Check for common project files
-            let project_indicators = [
-                ("package.json", "Node.js"),
-                ("Cargo.toml", "Rust"),
-                ("pyproject.toml", "Python"),
-                ("pom.xml", "Java/Maven"),
-                ("build.gradle", "Java/Gradle"),
-                (".git", "Git Repository"),
-                ("docker-compose.yml", "Docker"),
-                ("Dockerfile", "Docker"),
-            ];
+            scanned_directory = Some(session.working_directory.clone());
             
-            for (file, tech) in &project_indicators {
-                let file_path = PathBuf::from(&session.working_directory).join(file);
-                if file_path.exists() {
-                    context.push_str(&format!("Project Type: {}\n", tech));
-                }
+            if let Some(ci_status) = self.ci_status.get(&session.working_directory) {
+                rest.push_str(&format!("{}\n", ci_status.summary()));
+            }
+            
+            if let Some(project) = self.project_context(session_id) {
+                rest.push_str(&format!("{}\n", project.context_summary()));
+            }
+            
+            if let Some(recent) = self.recent_files.context_summary(session_id, Path::new(&session.working_directory)) {
+                rest.push_str(&recent);
             }
         }
         
@@ -493,24 +1486,188 @@ impl TerminalManager {
             .collect();
         
         if !recent_commands.is_empty() {
-            context.push_str("Recent Commands:\n");
-            context.push_str(&recent_commands.join("\n"));
+            rest.push_str("Recent Commands:\n");
+            rest.push_str(&recent_commands.join("\n"));
         }
         
-        context
+        futures::future::Either::Right(async move {
+            if let Some(working_directory) = scanned_directory {
+                if let Ok(directory) = crate::system::fs_tasks::run(move || directory_context(&working_directory)).await {
+                    context.push_str(&directory);
+                }
+            }
+            context.push_str(&rest);
+            context
+        })
+    }
+
+    /// Values available to `{{...}}` placeholders in this session's commands
+    pub fn template_context(&self, session_id: &str) -> templates::TemplateContext {
+        let last = self.get_session_history(session_id, Some(1)).into_iter().next();
+        let last = last.as_deref();
+        let session = self.sessions.get(session_id);
+        templates::TemplateContext {
+            working_directory: session.map(|s| s.working_directory.clone()).unwrap_or_default(),
+            environment: session.map(|s| s.environment_vars.clone()).unwrap_or_default(),
+            last_command: last.map(|e| e.command.clone()),
+            last_output: last.map(|e| e.output.clone()),
+            last_exit_code: last.and_then(|e| e.exit_code),
+            variables: session.map(|s| s.variables.clone()).unwrap_or_default(),
+            remote: session.is_some_and(|s| s.remote.is_some()),
+            shell: session.map(|s| shell_quote::ShellKind::from_shell(&s.shell)).unwrap_or_default(),
+        }
+    }
+
+    /// Run the command of a `capture` statement and bind the extracted values
+    pub async fn execute_capture(
+        &mut self,
+        session_id: &str,
+        request: &capture::CaptureRequest,
+        command_for_history: &str,
+    ) -> Result<CommandExecution, Box<dyn std::error::Error + Send + Sync>> {
+        let mut execution = self.execute_command_with_history(session_id, &request.command, command_for_history).await?;
+
+        if execution.exit_code.unwrap_or(0) != 0 {
+            execution.output.push_str("\n\n⚠️ Command failed, nothing was captured");
+        } else {
+            match request.extract(&execution.output) {
+                Ok(values) => {
+                    let lines: Vec<String> = values.iter().map(|(name, value)| format!("📌 {} = {}", name, value)).collect();
+                    if let Some(session) = self.sessions.get_mut(session_id) {
+                        session.variables.extend(values);
+                    }
+                    execution.output = format!("{}\n\n{}", execution.output.trim_end(), lines.join("\n"));
+                }
+                Err(e) => {
+                    execution.output.push_str(&format!("\n\n⚠️ Capture failed: {}", e));
+                    execution.exit_code = Some(1);
+                    execution.outcome = ExecutionOutcome::Exited(1);
+                }
+            }
+        }
+
+        self.command_history.replace(execution.clone());
+        Ok(execution)
+    }
+
+    /// Add an execution produced outside the normal spawn path (e.g. parallel runs) to history
+    pub fn record_execution(&mut self, execution: CommandExecution) {
+        self.command_history.push(execution);
+    }
+
+    /// Remove history entries older than `before`, or all of them; returns how many were removed
+    pub fn purge_history(&mut self, before: Option<chrono::DateTime<chrono::Utc>>) -> usize {
+        let count = self.command_history.len();
+        match before {
+            Some(cutoff) => self.command_history.retain(|execution| execution.timestamp >= cutoff),
+            None => self.command_history.clear(),
+        }
+        count - self.command_history.len()
+    }
+
+    pub fn history_settings(&self) -> &history::HistorySettings {
+        self.command_history.settings()
+    }
+
+    pub fn set_history_settings(&mut self, settings: history::HistorySettings) -> Result<(), String> {
+        self.command_history.set_settings(settings)
+    }
+
+    pub fn shell_history_settings(&self) -> &shell_history::ShellHistorySettings {
+        self.shell_history.settings()
+    }
+
+    pub fn set_shell_history_settings(&mut self, settings: shell_history::ShellHistorySettings) -> Result<(), String> {
+        self.shell_history.set_settings(settings)
+    }
+
+    pub fn lifecycle_settings(&self) -> &lifecycle::LifecycleSettings {
+        &self.lifecycle
+    }
+
+    pub fn set_lifecycle_settings(&mut self, settings: lifecycle::LifecycleSettings) -> Result<(), String> {
+        settings.save()?;
+        self.lifecycle = settings;
+        Ok(())
+    }
+
+    /// A session's own child policy, or `None` to follow the rules
+    pub fn set_session_child_policy(&mut self, session_id: &str, policy: Option<lifecycle::ChildPolicy>) -> Result<(), String> {
+        let session = self.sessions.get_mut(session_id).ok_or("Session not found")?;
+        session.child_policy = policy;
+        self.save_sessions();
+        Ok(())
+    }
+
+    pub fn processes(&self) -> &lifecycle::ProcessRegistry {
+        &self.processes
+    }
+
+    pub fn services(&self) -> &dev_services::ServiceManager {
+        &self.services
+    }
+
+    pub fn aliases(&self) -> Vec<aliases::Alias> {
+        self.aliases.list()
+    }
+
+    /// A command line as it runs, with its aliases expanded
+    pub fn expand_aliases(&self, command: &str) -> String {
+        self.aliases.expand(command).unwrap_or_else(|| command.to_string())
+    }
+
+    pub fn get_session_variables(&self, session_id: &str) -> Option<&HashMap<String, String>> {
+        self.sessions.get(session_id).map(|s| &s.variables)
+    }
+
+    /// Remove one captured variable, or all of them when no name is given
+    pub fn clear_session_variables(&mut self, session_id: &str, name: Option<&str>) -> Result<(), String> {
+        let session = self.sessions.get_mut(session_id).ok_or("Session not found")?;
+        match name {
+            Some(name) => { session.variables.remove(name); }
+            None => session.variables.clear(),
+        }
+        Ok(())
+    }
+
+    /// How recent commands ended, counted by outcome category
+    pub fn outcome_counts(&self) -> std::collections::BTreeMap<String, usize> {
+        let mut counts = std::collections::BTreeMap::new();
+        for execution in self.command_history.iter() {
+            *counts.entry(execution.outcome.category().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Failed commands not yet followed by a successful variant, most recent first
+    pub fn unresolved_failures(&self, limit: Option<usize>) -> Vec<failures::UnresolvedFailure> {
+        let mut unresolved = failures::unresolved_failures(self.command_history.iter());
+        unresolved.truncate(limit.unwrap_or(usize::MAX));
+        unresolved
     }
 
     /// Get session-specific command history
-    pub fn get_session_history(&self, session_id: &str, limit: Option<usize>) -> Vec<&CommandExecution> {
-        // For now, return global history. In a full implementation, 
-        // we'd track per-session history
-        self.get_command_history(limit)
+    pub fn get_session_history(&self, session_id: &str, limit: Option<usize>) -> Vec<std::sync::Arc<CommandExecution>> {
+        self.command_history.recent_in_session(session_id, limit)
+    }
+
+    /// Give the session screen-reader friendly output, or its usual output again
+    pub fn set_accessible_output(&mut self, session_id: &str, enabled: bool) -> Result<(), String> {
+        let session = self.sessions.get_mut(session_id).ok_or("Session not found")?;
+        session.accessible_output = enabled;
+        self.save_sessions();
+        Ok(())
+    }
+
+    pub fn get_execution(&self, execution_id: &str) -> Option<std::sync::Arc<CommandExecution>> {
+        self.command_history.get(execution_id)
     }
 
     /// Update session title
     pub fn update_session_title(&mut self, session_id: &str, title: String) -> Result<(), String> {
         if let Some(session) = self.sessions.get_mut(session_id) {
             session.title = title;
+            self.save_sessions();
             Ok(())
         } else {
             Err("Session not found".to_string())
@@ -521,6 +1678,15 @@ impl TerminalManager {
     pub fn close_session(&mut self, session_id: &str) -> Result<(), String> {
         if let Some(mut session) = self.sessions.remove(session_id) {
             session.is_active = false;
+            self.ptys.remove(session_id);
+            for process in self.processes.session_closed(session_id) {
+                println!("🛑 Stopping '{}' with its session", process.command);
+                lifecycle::stop(process);
+            }
+            self.sweep_scratch_dirs();
+            self.dir_usage.forget_session(session_id);
+            self.recent_files.forget_session(session_id);
+            self.save_sessions();
             Ok(())
         } else {
             Err("Session not found".to_string())
@@ -531,12 +1697,42 @@ impl TerminalManager {
     pub fn resize_terminal(&mut self, session_id: &str, rows: u16, cols: u16) -> Result<(), String> {
         if let Some(session) = self.sessions.get_mut(session_id) {
             session.pty_size = (cols, rows);
-            Ok(())
+            self.save_sessions();
+            match self.ptys.get(session_id) {
+                Some(shell) => shell.resize(rows, cols),
+                None => Ok(()),
+            }
         } else {
             Err("Session not found".to_string())
         }
     }
 
+    /// Stop a running command of the session; the one started last without an execution id
+    pub fn terminate_command(&self, session_id: &str, execution_id: Option<&str>, signal: running::TerminationSignal) -> Result<running::RunningCommand, String> {
+        if !self.sessions.contains_key(session_id) {
+            return Err("Session not found".to_string());
+        }
+        self.running.signal(session_id, execution_id, signal)
+    }
+
+    /// Commands running now, oldest first; a session's, or every session's
+    pub fn running_commands(&self, session_id: Option<&str>) -> Vec<running::RunningCommand> {
+        self.running.list(session_id)
+    }
+
+    /// Send keystrokes or text to whatever runs in the session's shell: a password
+    /// prompt, an `rm -i` confirmation, an editor. Control characters go through as
+    /// they are, so "\x03" interrupts and "\x04" ends input
+    pub fn write_input(&self, session_id: &str, data: &str) -> Result<(), String> {
+        if !self.sessions.contains_key(session_id) {
+            return Err("Session not found".to_string());
+        }
+        match self.ptys.get(session_id).filter(|shell| shell.is_alive()) {
+            Some(shell) => shell.write(data.as_bytes()),
+            None => Err("This session runs each command on its own, without a terminal to type into".to_string()),
+        }
+    }
+
     /// Get system information
     pub fn get_system_info(&self) -> HashMap<String, String> {
         let mut info = HashMap::new();
@@ -560,136 +1756,104 @@ impl TerminalManager {
                 .unwrap_or_else(|_| "unknown".to_string())
         );
         
+        let volumes: Vec<String> = crate::system::volumes::list()
+            .iter()
+            .map(|v| format!("{} ({}{})", v.mount_point, v.file_system, if v.removable { ", removable" } else { "" }))
+            .collect();
+        info.insert("volumes".to_string(), volumes.join(", "));
+        info.insert("data_directory".to_string(), crate::ai::data_directory().to_string_lossy().to_string());
+        info.insert("config_directory".to_string(), crate::ai::config_directory().to_string_lossy().to_string());
+        
         info
     }
 
     /// Get command suggestions based on current context
     pub fn get_context_suggestions(&self, session_id: &str) -> Vec<String> {
-        let mut suggestions = Vec::new();
+        self.context_suggestions_with_triggers(session_id).into_iter().map(|(suggestion, _)| suggestion).collect()
+    }
+
+    /// Context suggestions, each with what in the session or directory brought it up
+    pub fn context_suggestions_with_triggers(&self, session_id: &str) -> Vec<(String, String)> {
+        let mut suggestions: Vec<(String, String)> = Vec::new();
+        let mut extend = |commands: &[&str], trigger: &str| {
+            suggestions.extend(commands.iter().map(|command| (command.to_string(), trigger.to_string())));
+        };
         
         if let Some(session) = self.sessions.get(session_id) {
             let work_dir = PathBuf::from(&session.working_directory);
             
+            // What usually comes next here, then what is usually run here
+            if let Some(previous) = self.dir_usage.previous_command(session_id, &session.working_directory) {
+                let trigger = format!("Usually follows `{}` in this directory", previous);
+                for command in self.dir_usage.usual_next(&session.working_directory, previous).into_iter().take(3) {
+                    extend(&[&command], &trigger);
+                }
+            }
+            for usage in self.dir_usage.profile(&session.working_directory).top_commands.into_iter().filter(|usage| !usage.masked).take(5) {
+                extend(&[&usage.command], &format!("Run {} times in this directory", usage.runs));
+            }
+            
             // Suggest based on files in current directory
             if work_dir.join("package.json").exists() {
-                suggestions.extend(vec![
-                    "npm install".to_string(),
-                    "npm run dev".to_string(),
-                    "npm test".to_string(),
-                    "npm run build".to_string(),
-                ]);
+                extend(&["npm install", "npm run dev", "npm test", "npm run build"], "package.json in this directory");
             }
             
             if work_dir.join("Cargo.toml").exists() {
-                suggestions.extend(vec![
-                    "cargo build".to_string(),
-                    "cargo test".to_string(),
-                    "cargo run".to_string(),
-                    "cargo check".to_string(),
-                ]);
+                extend(&["cargo build", "cargo test", "cargo run", "cargo check"], "Cargo.toml in this directory");
             }
             
             if work_dir.join(".git").exists() {
-                suggestions.extend(vec![
-                    "git status".to_string(),
-                    "git add .".to_string(),
-                    "git commit".to_string(),
-                    "git push".to_string(),
-                ]);
+                extend(&["git status", "git add .", "git commit", "git push"], "This directory is a git repository");
             }
             
-            // Always include basic commands
-            suggestions.extend(vec![
-                "ls -la".to_string(),
-                "pwd".to_string(),
-                "cd ..".to_string(),
-            ]);
-        }
-        
-        suggestions
-    }
-
-    /// Get file and directory completions for a given partial path
-    pub fn get_path_completions(&self, session_id: &str, partial_path: &str) -> Vec<String> {
-        let mut completions = Vec::new();
-        
-        let (search_dir, prefix) = if partial_path.is_empty() {
-            // No path provided, search current directory
-            if let Some(session) = self.sessions.get(session_id) {
-                (PathBuf::from(&session.working_directory), String::new())
-            } else {
-                (std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")), String::new())
+            // Inspectors for Redis and queues the project runs in docker compose
+            for service in crate::tools::compose::detect_services(&work_dir) {
+                let commands = crate::tools::queue_inspector::suggested_commands(&service);
+                let commands: Vec<&str> = commands.iter().map(String::as_str).collect();
+                extend(&commands, &format!("Service `{}` in the docker compose file", service.name));
             }
-        } else if partial_path.ends_with('/') {
-            // Path ends with /, search in that directory
-            let path = self.expand_path(session_id, partial_path);
-            (path, String::new())
-        } else {
-            // Partial filename, search in parent directory
-            let path_buf = PathBuf::from(partial_path);
-            if let Some(parent) = path_buf.parent() {
-                let expanded_parent = self.expand_path(session_id, &parent.to_string_lossy());
-                let prefix = path_buf.file_name()
-                    .map(|name| name.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                (expanded_parent, prefix)
-            } else {
-                // No parent, search current directory
-                if let Some(session) = self.sessions.get(session_id) {
-                    (PathBuf::from(&session.working_directory), partial_path.to_string())
-                } else {
-                    (std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")), partial_path.to_string())
+            
+            // Dev servers: a check on the ones running here, the usual one when none is defined
+            for service in self.services.in_directory(&session.working_directory) {
+                if let Some(port) = service.port.filter(|_| service.status.is_alive()) {
+                    extend(&[&format!("curl -I http://localhost:{}", port)], &format!("Service `{}` listens on port {}", service.name, port));
                 }
             }
-        };
-
-        if let Ok(entries) = std::fs::read_dir(&search_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    
-                    // Skip hidden files unless prefix starts with .
-                    if name.starts_with('.') && !prefix.starts_with('.') {
-                        continue;
-                    }
-                    
-                    // Check if name starts with prefix (case-insensitive)
-                    if name.to_lowercase().starts_with(&prefix.to_lowercase()) {
-                        if entry.path().is_dir() {
-                            completions.push(format!("{}/", name));
-                        } else {
-                            completions.push(name);
-                        }
-                    }
-                }
+            for service in self.services.suggestions(&work_dir) {
+                extend(&[&service.command], &format!("Dev server of this project; can run as the service `{}`", service.name));
             }
+            
+            // Always include basic commands
+            extend(&["ls -la", "pwd", "cd .."], "Always offered");
         }
-
-        completions.sort();
-        completions
+        
+        let mut seen = std::collections::HashSet::new();
+        suggestions.retain(|(suggestion, _)| seen.insert(suggestion.clone()));
+        suggestions
     }
 
-    /// Expand path relative to session working directory
-    fn expand_path(&self, session_id: &str, path: &str) -> PathBuf {
-        if path.starts_with('~') {
-            if let Some(home) = dirs::home_dir() {
-                if path == "~" {
-                    home
-                } else {
-                    home.join(&path[2..]) // Skip "~/"
-                }
-            } else {
-                PathBuf::from(path)
-            }
-        } else if path.starts_with('/') {
-            PathBuf::from(path)
-        } else {
-            if let Some(session) = self.sessions.get(session_id) {
-                PathBuf::from(&session.working_directory).join(path)
-            } else {
-                PathBuf::from(path)
-            }
+    /// Get file and directory completions for a given partial path; files the session
+    /// worked with recently come first, and `@recent` completes to them alone
+    /// Tab completions for a path; like `get_smart_context`, the returned future lists
+    /// directories without borrowing the manager
+    pub fn get_path_completions(&self, session_id: &str, partial_path: &str) -> impl std::future::Future<Output = Vec<String>> + Send + 'static {
+        let working_directory = match self.sessions.get(session_id) {
+            // The remote machine's files cannot be listed from here
+            Some(session) if session.remote.is_some() => return futures::future::Either::Left(std::future::ready(Vec::new())),
+            Some(session) => PathBuf::from(&session.working_directory),
+            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        };
+        if partial_path.starts_with('@') {
+            return futures::future::Either::Left(std::future::ready(self.recent_files.complete(session_id, &working_directory, partial_path)));
         }
+        let recent: Vec<String> = self.recent_files.list(session_id, usize::MAX).into_iter().map(|file| file.path).collect();
+        let path_display = self.path_display.clone();
+        let partial_path = partial_path.to_string();
+        futures::future::Either::Right(async move {
+            crate::system::fs_tasks::run(move || path_completions(&working_directory, &path_display, &partial_path, &recent))
+                .await
+                .unwrap_or_default()
+        })
     }
 
     /// Get command history for arrow key navigation
@@ -715,7 +1879,7 @@ impl TerminalManager {
     }
 
     /// Store a command in history without executing it (for natural language commands)
-    pub fn store_command_in_history(&mut self, _session_id: &str, command: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub fn store_command_in_history(&mut self, session_id: &str, command: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Create a minimal command execution entry for history storage
         let execution = CommandExecution {
             id: uuid::Uuid::new_v4().to_string(),
@@ -724,15 +1888,135 @@ impl TerminalManager {
             exit_code: Some(0), // Mark as successful since it's just being stored
             duration_ms: 0, // No actual execution time
             timestamp: chrono::Utc::now(),
+            outcome: ExecutionOutcome::Exited(0),
+            coverage: None,
+            structured: None,
+            overrides: None,
+            styled: None,
+            session_id: Some(session_id.to_string()),
         };
 
         self.command_history.push(execution);
         
-        // Keep only the last 1000 commands
-        if self.command_history.len() > 1000 {
-            self.command_history.remove(0);
+        Ok(())
+    }
+}
+
+/// File types and project markers of a directory, for the model's context
+fn directory_context(working_directory: &str) -> String {
+    let mut context = String::new();
+    
+    // Add file type context
+    if let Ok(entries) = crate::system::dir_cache::cache().list(Path::new(working_directory)) {
+        let mut file_types = Vec::new();
+        for entry in entries.iter().take(20) { // Limit to avoid performance issues
+            if let Some(ext) = entry.path.extension() {
+                if let Some(ext_str) = ext.to_str() {
+                    file_types.push(ext_str.to_string());
+                }
+            }
         }
         
-        Ok(())
+        if !file_types.is_empty() {
+            file_types.sort();
+            file_types.dedup();
+            context.push_str(&format!("File Types: {}\n", file_types.join(", ")));
+        }
     }
+    
+    // Check for common project files
+    let project_indicators = [
+        ("package.json", "Node.js"),
+        ("Cargo.toml", "Rust"),
+        ("pyproject.toml", "Python"),
+        ("pom.xml", "Java/Maven"),
+        ("build.gradle", "Java/Gradle"),
+        (".git", "Git Repository"),
+        ("docker-compose.yml", "Docker"),
+        ("Dockerfile", "Docker"),
+    ];
+    
+    for (file, tech) in &project_indicators {
+        let file_path = PathBuf::from(working_directory).join(file);
+        if file_path.exists() {
+            context.push_str(&format!("Project Type: {}\n", tech));
+        }
+    }
+    
+    context
+}
+
+/// Completions for a partial path typed in `working_directory`
+fn path_completions(working_directory: &Path, path_display: &path_display::PathDisplaySettings, partial_path: &str, recent: &[String]) -> Vec<String> {
+    let mut completions = Vec::new();
+    
+    let (search_dir, prefix) = if partial_path.is_empty() {
+        // No path provided, search current directory
+        (working_directory.to_path_buf(), String::new())
+    } else if partial_path.ends_with('/') {
+        // Path ends with /, search in that directory
+        let path = expand_path(working_directory, path_display, partial_path);
+        (path, String::new())
+    } else {
+        // Partial filename, search in parent directory
+        let path_buf = PathBuf::from(partial_path);
+        if let Some(parent) = path_buf.parent() {
+            let expanded_parent = expand_path(working_directory, path_display, &parent.to_string_lossy());
+            let prefix = path_buf.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (expanded_parent, prefix)
+        } else {
+            // No parent, search current directory
+            (working_directory.to_path_buf(), partial_path.to_string())
+        }
+    };
+
+    if let Ok(entries) = crate::system::dir_cache::cache().list(&search_dir) {
+        for entry in entries.iter() {
+            let name = &entry.name;
+            
+            // Skip hidden files unless prefix starts with .
+            if name.starts_with('.') && !prefix.starts_with('.') {
+                continue;
+            }
+            
+            // Check if name starts with prefix (case-insensitive)
+            if name.to_lowercase().starts_with(&prefix.to_lowercase()) {
+                if entry.is_dir {
+                    completions.push(format!("{}/", name));
+                } else {
+                    completions.push(name.clone());
+                }
+            }
+        }
+    }
+
+    completions.sort();
+    completions.sort_by_key(|name| {
+        let path = path_display::lexical_normalize(&search_dir.join(name)).to_string_lossy().to_string();
+        recent.iter().position(|recent| *recent == path).unwrap_or(usize::MAX)
+    });
+    completions
+}
+
+/// Expand path relative to the session working directory
+fn expand_path(working_directory: &Path, path_display: &path_display::PathDisplaySettings, path: &str) -> PathBuf {
+    let expanded = if path.starts_with('~') {
+        if let Some(home) = dirs::home_dir() {
+            if path == "~" {
+                home
+            } else {
+                home.join(&path[2..]) // Skip "~/"
+            }
+        } else {
+            PathBuf::from(path)
+        }
+    } else if path.starts_with('/') {
+        PathBuf::from(path)
+    } else {
+        working_directory.join(path)
+    };
+    // `link/..` must lead where `cd` would take the user
+    path_display.normalize(&expanded)
 }
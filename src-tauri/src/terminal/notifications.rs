@@ -0,0 +1,264 @@
+// Command completion notifications
+// A long command that finishes while the window is in the background raises a
+// system notification, unless the OS is in a Focus / do-not-disturb mode or the
+// global or session quiet hours apply. Session quiet hours only last as long as
+// the session, since session ids do not survive a restart. Suppressed notifications
+// are not lost: everything lands in an in-app notification center with read/unread state.
+
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use uuid::Uuid;
+
+/// Emitted with an `AppNotification` whenever one is added to the center
+pub const NOTIFICATION_EVENT: &str = "notification-added";
+
+/// Notifications kept in the center, oldest dropped first
+const MAX_NOTIFICATIONS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// "22:00"
+    pub start: String,
+    /// "07:30"; earlier than `start` means the range crosses midnight
+    pub end: String,
+}
+
+impl QuietHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        let (Ok(start), Ok(end)) = (NaiveTime::parse_from_str(&self.start, "%H:%M"), NaiveTime::parse_from_str(&self.end, "%H:%M")) else {
+            return false;
+        };
+        if start <= end {
+            time >= start && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for value in [&self.start, &self.end] {
+            NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("'{}' is not a HH:MM time", value))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Only commands running at least this long notify
+    pub min_duration_secs: u64,
+    pub respect_focus_mode: bool,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_secs: 10,
+            respect_focus_mode: true,
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("notification_settings.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(hours) = &self.quiet_hours {
+            hours.validate()?;
+        }
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppNotification {
+    pub id: String,
+    pub session_id: Option<String>,
+    pub title: String,
+    pub body: String,
+    pub success: bool,
+    pub created_at: DateTime<Utc>,
+    pub read: bool,
+    /// Why no system notification was shown (focus mode, quiet hours...)
+    pub suppressed: Option<String>,
+}
+
+/// In-app list of notifications with read state
+pub struct NotificationCenter {
+    notifications: Vec<AppNotification>,
+    settings: NotificationSettings,
+    /// Per session, in memory only; `None` mutes the session entirely
+    session_quiet_hours: HashMap<String, Option<QuietHours>>,
+}
+
+impl Default for NotificationCenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationCenter {
+    pub fn new() -> Self {
+        Self { notifications: Vec::new(), settings: NotificationSettings::load(), session_quiet_hours: HashMap::new() }
+    }
+
+    pub fn settings(&self) -> &NotificationSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: NotificationSettings) -> Result<(), String> {
+        settings.save()?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    pub fn session_quiet_hours(&self) -> &HashMap<String, Option<QuietHours>> {
+        &self.session_quiet_hours
+    }
+
+    /// Override the quiet hours of one session, `Some(None)` muting it; `None` returns it to the global ones
+    pub fn set_session_quiet_hours(&mut self, session_id: &str, quiet_hours: Option<Option<QuietHours>>) -> Result<(), String> {
+        match quiet_hours {
+            Some(hours) => {
+                if let Some(hours) = &hours {
+                    hours.validate()?;
+                }
+                self.session_quiet_hours.insert(session_id.to_string(), hours);
+            }
+            None => {
+                self.session_quiet_hours.remove(session_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop a closed session's override
+    pub fn remove_session(&mut self, session_id: &str) {
+        self.session_quiet_hours.remove(session_id);
+    }
+
+    /// Why a notification for `session_id` should stay in the center, if it should
+    fn quiet_reason(&self, session_id: &str, now: NaiveTime) -> Option<String> {
+        match self.session_quiet_hours.get(session_id) {
+            Some(None) => return Some("session muted".to_string()),
+            Some(Some(hours)) if hours.contains(now) => return Some("session quiet hours".to_string()),
+            _ => {}
+        }
+        self.settings.quiet_hours.as_ref().filter(|hours| hours.contains(now)).map(|_| "quiet hours".to_string())
+    }
+
+    /// Whether a command that ran this long is worth a notification at all
+    pub fn wants(&self, duration_ms: u64) -> bool {
+        self.settings.enabled && duration_ms >= self.settings.min_duration_secs * 1000
+    }
+
+    /// Why the OS should not interrupt the user right now (focus mode, quiet hours...), if it should not
+    pub fn suppression(&self, session_id: Option<&str>, focus_mode: bool) -> Option<String> {
+        if self.settings.respect_focus_mode && focus_mode {
+            Some("focus mode".to_string())
+        } else {
+            session_id.and_then(|id| self.quiet_reason(id, Local::now().time()))
+        }
+    }
+
+    /// Store a notification, deciding whether the OS may show it.
+    /// `focus_mode` is whether the OS is in do-not-disturb, checked by the caller off the async runtime
+    pub fn add(&mut self, session_id: Option<&str>, title: String, body: String, success: bool, focus_mode: bool) -> AppNotification {
+        let suppressed = self.suppression(session_id, focus_mode);
+        let notification = AppNotification {
+            id: Uuid::new_v4().to_string(),
+            session_id: session_id.map(str::to_string),
+            title,
+            body,
+            success,
+            created_at: Utc::now(),
+            read: false,
+            suppressed,
+        };
+        self.notifications.push(notification.clone());
+        if self.notifications.len() > MAX_NOTIFICATIONS {
+            self.notifications.remove(0);
+        }
+        notification
+    }
+
+    /// Newest first
+    pub fn list(&self, unread_only: bool) -> Vec<AppNotification> {
+        self.notifications.iter().rev().filter(|n| !unread_only || !n.read).cloned().collect()
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.notifications.iter().filter(|n| !n.read).count()
+    }
+
+    /// Mark the given notifications, or all with `None`; returns how many changed
+    pub fn mark_read(&mut self, ids: Option<&[String]>, read: bool) -> usize {
+        let mut changed = 0;
+        for notification in &mut self.notifications {
+            if ids.map_or(true, |ids| ids.contains(&notification.id)) && notification.read != read {
+                notification.read = read;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    pub fn clear(&mut self, read_only: bool) -> usize {
+        let before = self.notifications.len();
+        self.notifications.retain(|n| read_only && !n.read);
+        before - self.notifications.len()
+    }
+}
+
+/// Whether the OS is in Focus / Focus Assist / do-not-disturb mode
+pub fn focus_mode_active() -> bool {
+    if cfg!(target_os = "macos") {
+        // Focus writes an assertion record while a mode is on (macOS 12+)
+        let Some(home) = dirs::home_dir() else { return false };
+        let assertions = std::fs::read_to_string(home.join("Library/DoNotDisturb/DB/Assertions.json")).unwrap_or_default();
+        return serde_json::from_str::<serde_json::Value>(&assertions)
+            .ok()
+            .and_then(|value| {
+                let records = value.get("data")?.as_array()?;
+                Some(records.iter().any(|record| {
+                    record.get("storeAssertionRecords").and_then(|r| r.as_array()).is_some_and(|r| !r.is_empty())
+                }))
+            })
+            .unwrap_or(false);
+    }
+    if cfg!(windows) {
+        // Do not disturb turns toast notifications off globally
+        let output = Command::new("reg")
+            .args(["query", r"HKCU\Software\Microsoft\Windows\CurrentVersion\Notifications\Settings", "/v", "NOC_GLOBAL_SETTING_TOASTS_ENABLED"])
+            .output();
+        return output.is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).contains("0x0"));
+    }
+    // GNOME's do-not-disturb hides banners
+    Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+        .output()
+        .is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == "false")
+}
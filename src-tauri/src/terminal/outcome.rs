@@ -0,0 +1,108 @@
+// How a command ended
+// `exit_code` alone cannot tell a SIGKILL from a timeout from a missing binary,
+// so every execution also carries a typed outcome. `exit_code` follows shell
+// conventions for the non-exit cases: 128+signal, 124 timeout, 126/127 spawn errors.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnErrorKind {
+    NotFound,
+    PermissionDenied,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum ExecutionOutcome {
+    Exited(i32),
+    Signaled(i32),
+    TimedOut,
+    SpawnError(SpawnErrorKind),
+}
+
+impl Default for ExecutionOutcome {
+    fn default() -> Self {
+        ExecutionOutcome::Exited(0)
+    }
+}
+
+impl ExecutionOutcome {
+    pub fn from_status(status: &std::process::ExitStatus) -> Self {
+        if let Some(code) = status.code() {
+            return ExecutionOutcome::Exited(code);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return ExecutionOutcome::Signaled(signal);
+            }
+        }
+        ExecutionOutcome::Exited(1)
+    }
+
+    /// A status as a shell reports it (`$?`), where the non-exit cases are encoded
+    pub fn from_shell_status(status: i32) -> Self {
+        match status {
+            126 => ExecutionOutcome::SpawnError(SpawnErrorKind::PermissionDenied),
+            127 => ExecutionOutcome::SpawnError(SpawnErrorKind::NotFound),
+            129..=159 if cfg!(unix) => ExecutionOutcome::Signaled(status - 128),
+            _ => ExecutionOutcome::Exited(status),
+        }
+    }
+
+    pub fn from_spawn_error(error: &std::io::Error) -> Self {
+        ExecutionOutcome::SpawnError(match error.kind() {
+            std::io::ErrorKind::NotFound => SpawnErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => SpawnErrorKind::PermissionDenied,
+            _ => SpawnErrorKind::Other,
+        })
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ExecutionOutcome::Exited(code) => *code,
+            ExecutionOutcome::Signaled(signal) => 128 + signal,
+            ExecutionOutcome::TimedOut => 124,
+            ExecutionOutcome::SpawnError(SpawnErrorKind::NotFound) => 127,
+            ExecutionOutcome::SpawnError(SpawnErrorKind::PermissionDenied) => 126,
+            ExecutionOutcome::SpawnError(SpawnErrorKind::Other) => 1,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == ExecutionOutcome::Exited(0)
+    }
+
+    /// Stable key for analytics
+    pub fn category(&self) -> &'static str {
+        match self {
+            ExecutionOutcome::Exited(0) => "success",
+            ExecutionOutcome::Exited(_) => "exit_code",
+            ExecutionOutcome::Signaled(_) => "signaled",
+            ExecutionOutcome::TimedOut => "timed_out",
+            ExecutionOutcome::SpawnError(SpawnErrorKind::NotFound) => "not_found",
+            ExecutionOutcome::SpawnError(_) => "spawn_error",
+        }
+    }
+}
+
+/// Conventional name of a signal number
+pub fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        _ => "signal",
+    }
+}
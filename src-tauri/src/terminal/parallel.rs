@@ -0,0 +1,284 @@
+// Parallel execution of a command template over a list of items (xargs/parallel-like)
+// Placeholders follow GNU parallel: {} item, {.} without extension, {/} basename,
+// {//} directory, {/.} basename without extension
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use super::shell_quote::{self, ShellKind};
+
+const ITEM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MAX_OUTPUT_PER_ITEM: usize = 4000;
+const MAX_ITEMS: usize = 10_000;
+/// Programs "run <tool> on every <ext> file" may start: formatters and linters that work file by file
+const RUNNABLE_TOOLS: [&str; 18] = [
+    "black", "isort", "ruff", "flake8", "pylint", "mypy", "prettier", "eslint", "rustfmt", "gofmt", "clang-format",
+    "shellcheck", "shfmt", "rubocop", "yamllint", "optipng", "jpegoptim", "pngquant",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParallelItemResult {
+    pub item: String,
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub output: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParallelRunReport {
+    pub command_template: String,
+    pub concurrency: usize,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub duration_ms: u64,
+    pub results: Vec<ParallelItemResult>,
+}
+
+impl ParallelRunReport {
+    /// Terminal-style summary listing failures first
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "⚡ Ran '{}' on {} item(s) with concurrency {}: {} succeeded, {} failed ({:.1}s)",
+            self.command_template,
+            self.total,
+            self.concurrency,
+            self.succeeded,
+            self.failed,
+            self.duration_ms as f64 / 1000.0
+        )];
+        for result in self.results.iter().filter(|r| !r.success) {
+            let first_line = result.output.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+            lines.push(format!("❌ {}: {}", result.item, first_line));
+        }
+        lines.join("\n")
+    }
+}
+
+/// A parallel run proposed from a natural language request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParallelPlan {
+    pub command_template: String,
+    pub glob: String,
+    pub description: String,
+}
+
+/// Substitute the item placeholders in one argument
+fn substitute(arg: &str, item: &str) -> String {
+    let path = Path::new(item);
+    let without_ext = match path.extension() {
+        Some(ext) => item[..item.len() - ext.len() - 1].to_string(),
+        None => item.to_string(),
+    };
+    let basename = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| item.to_string());
+    let stem = path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| basename.clone());
+    let dirname = path
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+
+    arg.replace("{/.}", &stem)
+        .replace("{//}", &dirname)
+        .replace("{/}", &basename)
+        .replace("{.}", &without_ext)
+        .replace("{}", item)
+}
+
+/// Expand the template for one item; the item is appended when the template has no placeholder
+pub fn expand_for_item(template_args: &[String], item: &str) -> Vec<String> {
+    let has_placeholder = template_args.iter().any(|a| a.contains("{}") || a.contains("{.}") || a.contains("{/"));
+    let mut args: Vec<String> = template_args.iter().map(|a| substitute(a, item)).collect();
+    if !has_placeholder {
+        args.push(item.to_string());
+    }
+    args
+}
+
+/// Files matching a glob, relative to the working directory
+pub fn items_from_glob(pattern: &str, working_dir: &str) -> Result<Vec<String>, String> {
+    let absolute = Path::new(pattern).is_absolute();
+    let full_pattern = if absolute { pattern.to_string() } else { format!("{}/{}", working_dir.trim_end_matches('/'), pattern) };
+    let entries = glob::glob(&full_pattern).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+
+    let mut items: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .map(|path| {
+            if absolute {
+                path.to_string_lossy().to_string()
+            } else {
+                path.strip_prefix(working_dir).unwrap_or(&path).to_string_lossy().to_string()
+            }
+        })
+        .collect();
+    items.sort();
+    Ok(items)
+}
+
+/// Non-empty lines of a previous command's output
+pub fn items_from_output(output: &str) -> Vec<String> {
+    output.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect()
+}
+
+async fn run_item(args: Vec<String>, item: String, working_dir: String, env_vars: Arc<HashMap<String, String>>) -> ParallelItemResult {
+    let start = Instant::now();
+    let command_line = shell_quote::join(&args, ShellKind::Posix);
+    if crate::system::demo::is_active() {
+        let rest: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+        let (output, _, outcome) = crate::system::demo::execute(&args[0], &rest, &working_dir);
+        return ParallelItemResult {
+            item,
+            command: command_line,
+            success: outcome.is_success(),
+            exit_code: Some(outcome.exit_code()),
+            output,
+            duration_ms: start.elapsed().as_millis() as u64,
+        };
+    }
+    let mut command = tokio::process::Command::new(&args[0]);
+    command
+        .args(&args[1..])
+        .current_dir(&working_dir)
+        .envs(env_vars.iter())
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+
+    let (success, exit_code, mut output) = match tokio::time::timeout(ITEM_TIMEOUT, command.output()).await {
+        Ok(Ok(out)) => {
+            let mut text = String::from_utf8_lossy(&out.stdout).to_string();
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            if !stderr.trim().is_empty() {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&stderr);
+            }
+            (out.status.success(), out.status.code(), text)
+        }
+        Ok(Err(e)) => (false, None, format!("Failed to start '{}': {}", args[0], e)),
+        Err(_) => (false, None, format!("Timed out after {}s", ITEM_TIMEOUT.as_secs())),
+    };
+    if output.len() > MAX_OUTPUT_PER_ITEM {
+        let mut cut = MAX_OUTPUT_PER_ITEM;
+        while !output.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        output.truncate(cut);
+        output.push_str("\n… (truncated)");
+    }
+
+    ParallelItemResult {
+        item,
+        command: command_line,
+        success,
+        exit_code,
+        output,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// Run the template once per item with at most `concurrency` commands in flight
+pub async fn run_parallel(
+    command_template: &str,
+    items: Vec<String>,
+    concurrency: usize,
+    working_dir: &str,
+    env_vars: HashMap<String, String>,
+) -> Result<ParallelRunReport, String> {
+    let template_args = shell_quote::split(command_template)?;
+    if template_args.is_empty() {
+        return Err("Empty command template".to_string());
+    }
+    if items.is_empty() {
+        return Err("No items to run the command on".to_string());
+    }
+    if items.len() > MAX_ITEMS {
+        return Err(format!("Too many items ({}), the limit is {}", items.len(), MAX_ITEMS));
+    }
+
+    let concurrency = concurrency.clamp(1, 64);
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let env_vars = Arc::new(env_vars);
+    let start = Instant::now();
+
+    let mut handles = Vec::with_capacity(items.len());
+    for item in items {
+        let args = expand_for_item(&template_args, &item);
+        let semaphore = semaphore.clone();
+        let working_dir = working_dir.to_string();
+        let env_vars = env_vars.clone();
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            run_item(args, item, working_dir, env_vars).await
+        }));
+    }
+
+    // Collected in input order regardless of completion order
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.map_err(|e| format!("Parallel task failed: {}", e))?);
+    }
+
+    let succeeded = results.iter().filter(|r| r.success).count();
+    Ok(ParallelRunReport {
+        command_template: command_template.to_string(),
+        concurrency,
+        total: results.len(),
+        succeeded,
+        failed: results.len() - succeeded,
+        duration_ms: start.elapsed().as_millis() as u64,
+        results,
+    })
+}
+
+fn extension_after(words: &[&str], keyword: &str) -> Option<String> {
+    let index = words.iter().position(|w| *w == keyword)?;
+    let ext = words.get(index + 1)?.trim_start_matches("*.").trim_start_matches('.');
+    let ext = ext.trim_end_matches([',', '.']);
+    (!ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric())).then(|| ext.to_lowercase())
+}
+
+/// Recognise "<verb> every/all <ext> (files) ..." requests
+pub fn plan_from_natural_language(request: &str) -> Option<ParallelPlan> {
+    let lower = request.to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let quantifier = ["every", "each", "all"].into_iter().find(|q| words.contains(q))?;
+    let source_ext = extension_after(&words, quantifier)?;
+    let recursive = lower.contains("recursively") || lower.contains("subfolder") || lower.contains("subdirector");
+    let glob = if recursive { format!("**/*.{}", source_ext) } else { format!("*.{}", source_ext) };
+
+    let (command_template, description) = if words.first() == Some(&"convert") {
+        let target_ext = extension_after(&words, "to")?;
+        // -n: a conversion that already exists fails for that file instead of being overwritten
+        (
+            format!("ffmpeg -n -loglevel error -i {{}} {{.}}.{}", target_ext),
+            format!("Convert each .{} file to .{}", source_ext, target_ext),
+        )
+    } else if words.first() == Some(&"compress") || words.first() == Some(&"gzip") {
+        ("gzip -k {}".to_string(), format!("Compress each .{} file (keeping originals)", source_ext))
+    } else if lower.starts_with("count lines") {
+        ("wc -l {}".to_string(), format!("Count lines in each .{} file", source_ext))
+    } else if lower.starts_with("checksum") || lower.starts_with("hash") {
+        // shasum comes with Perl, which minimal Linux systems leave out; coreutils is always there
+        let tool = if cfg!(target_os = "linux") { "sha256sum {}" } else { "shasum -a 256 {}" };
+        (tool.to_string(), format!("SHA-256 of each .{} file", source_ext))
+    } else if let Some(rest) = lower.strip_prefix("run ") {
+        // "run black on every py file"
+        let tool = rest.split(" on ").next()?.trim();
+        if !RUNNABLE_TOOLS.contains(&tool) {
+            return None;
+        }
+        (format!("{} {{}}", tool), format!("Run '{}' on each .{} file", tool, source_ext))
+    } else {
+        return None;
+    };
+
+    Some(ParallelPlan { command_template, glob, description })
+}
@@ -0,0 +1,104 @@
+// How paths are shown and how `cd` targets are normalized
+// Users choose whether $HOME is shown as ~, whether long paths are collapsed in
+// the prompt, and whether symlinks are resolved (physical paths, like `pwd -P`)
+// or preserved (logical paths, like `pwd -L`).
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    Resolve,
+    Preserve,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathDisplaySettings {
+    pub abbreviate_home: bool,
+    /// Show only the last N components of longer paths, e.g. `…/src/terminal`
+    pub collapse_after: Option<usize>,
+    pub symlinks: SymlinkPolicy,
+}
+
+impl Default for PathDisplaySettings {
+    fn default() -> Self {
+        Self {
+            abbreviate_home: true,
+            collapse_after: None,
+            symlinks: SymlinkPolicy::Resolve,
+        }
+    }
+}
+
+/// Resolve `.` and `..` without touching the file system, so symlinks stay in the path
+pub fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                result.pop();
+            }
+            Component::CurDir => {}
+            other => result.push(other.as_os_str()),
+        }
+    }
+    result
+}
+
+impl PathDisplaySettings {
+    fn settings_file() -> PathBuf {
+        crate::ai::config_directory().join("path_display_settings.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::settings_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::settings_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// Absolute path a session should store for `path`, following the symlink policy
+    pub fn normalize(&self, path: &Path) -> PathBuf {
+        match self.symlinks {
+            SymlinkPolicy::Resolve => path.canonicalize().unwrap_or_else(|_| lexical_normalize(path)),
+            SymlinkPolicy::Preserve => lexical_normalize(path),
+        }
+    }
+
+    /// Full path for output such as `pwd`: home abbreviated, never collapsed
+    pub fn display_full(&self, path: &str) -> String {
+        if !self.abbreviate_home {
+            return path.to_string();
+        }
+        let Some(home) = dirs::home_dir() else { return path.to_string() };
+        match Path::new(path).strip_prefix(&home) {
+            Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+            Ok(rest) => format!("~/{}", rest.display()),
+            Err(_) => path.to_string(),
+        }
+    }
+
+    /// Short form for prompts and tabs
+    pub fn display(&self, path: &str) -> String {
+        let full = self.display_full(path);
+        let Some(limit) = self.collapse_after.filter(|limit| *limit > 0) else { return full };
+        let components: Vec<&str> = full.split('/').filter(|c| !c.is_empty()).collect();
+        // The leading `~` or root does not count towards the limit
+        let anchored = full.starts_with('~');
+        let countable = if anchored { components.len() - 1 } else { components.len() };
+        if countable <= limit {
+            return full;
+        }
+        format!("…/{}", components[components.len() - limit..].join("/"))
+    }
+}
@@ -0,0 +1,181 @@
+// Port conflicts
+// A dev server that cannot listen because its port is taken says so in many
+// ways (EADDRINUSE, "address already in use", "Port 3000 is in use"). Such a
+// failure is recognised in the output, the process holding the port is looked
+// up, and the ways out are offered as remedies that can be applied as they are:
+// stop the holder (or the service holding it), or move to the next free port by
+// rewriting the port in the command and setting PORT. A dev service that fails
+// this way is not restarted, since it would only fail again.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+/// How far past the taken port to look for a free one
+const PORT_SEARCH: u16 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortHolder {
+    pub pid: u32,
+    pub name: String,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PortRemedy {
+    /// Terminate the process listening on the port
+    KillHolder { pid: u32, name: String },
+    /// The port belongs to another dev service; stop that one
+    StopService { name: String },
+    /// Run on a free port instead, with the command and environment rewritten for it
+    UseNextPort { port: u16, command: String, env: HashMap<String, Option<String>> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortConflict {
+    pub port: u16,
+    pub holder: Option<PortHolder>,
+    pub held_by_service: Option<String>,
+    pub remedies: Vec<PortRemedy>,
+}
+
+impl PortConflict {
+    /// One line for logs and error messages
+    pub fn summary(&self) -> String {
+        match (&self.held_by_service, &self.holder) {
+            (Some(service), _) => format!("Port {} is in use by the service '{}'", self.port, service),
+            (None, Some(holder)) => format!("Port {} is in use by {} (pid {})", self.port, holder.name, holder.pid),
+            (None, None) => format!("Port {} is already in use", self.port),
+        }
+    }
+}
+
+/// Whether the output reports a taken port, and which one when it says
+pub fn detect(output: &str) -> Option<Option<u16>> {
+    static IN_USE: OnceLock<Regex> = OnceLock::new();
+    static PORT: OnceLock<Regex> = OnceLock::new();
+    let in_use = IN_USE.get_or_init(|| {
+        Regex::new(r"(?i)eaddrinuse|address already in use|port \d+ is (?:already )?in use|only one usage of each socket address|address in use").unwrap()
+    });
+    let port = PORT.get_or_init(|| {
+        Regex::new(r"(?i)(?:port\s+|:{1,3})(\d{2,5})\b").unwrap()
+    });
+    let line = output.lines().find(|line| in_use.is_match(line))?;
+    Some(port.captures(line).and_then(|captures| captures[1].parse().ok()).filter(|port| *port > 0))
+}
+
+/// Whether nothing listens on the port
+pub fn is_free(port: u16) -> bool {
+    std::net::TcpListener::bind(("127.0.0.1", port)).is_ok() && std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// The first free port after `port` that no service has registered
+pub fn next_free_port(port: u16, registered: &BTreeMap<u16, String>) -> Option<u16> {
+    (1..=PORT_SEARCH)
+        .filter_map(|offset| port.checked_add(offset))
+        .find(|candidate| !registered.contains_key(candidate) && is_free(*candidate))
+}
+
+/// Pid of the process listening on a TCP port, as lsof, ss or netstat report it
+fn listening_pid(port: u16) -> Option<u32> {
+    let run = |program: &str, args: &[&str]| {
+        std::process::Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    if cfg!(windows) {
+        let suffix = format!(":{}", port);
+        return run("netstat", &["-ano", "-p", "TCP"])?.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            (fields.len() == 5 && fields[1].ends_with(&suffix) && fields[3] == "LISTENING").then(|| fields[4].parse().ok()).flatten()
+        });
+    }
+    let lsof = run("lsof", &["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-t"]);
+    if let Some(pid) = lsof.and_then(|out| out.lines().next().and_then(|line| line.trim().parse().ok())) {
+        return Some(pid);
+    }
+    // ss prints users:(("node",pid=1234,fd=23))
+    let ss = run("ss", &["-ltnpH", &format!("sport = :{}", port)])?;
+    let start = ss.find("pid=")? + "pid=".len();
+    ss[start..].split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+pub fn holder(port: u16) -> Option<PortHolder> {
+    let pid = listening_pid(port)?;
+    let mut system = sysinfo::System::new();
+    let sys_pid = sysinfo::Pid::from_u32(pid);
+    system.refresh_process(sys_pid);
+    let (name, command) = match system.process(sys_pid) {
+        Some(process) => (process.name().to_string(), process.cmd().join(" ")),
+        None => (format!("pid {}", pid), String::new()),
+    };
+    Some(PortHolder { pid, name, command })
+}
+
+/// The command and environment moved from `old` to `new`: the port is replaced where the
+/// command names it, and PORT is set for servers that read it
+pub fn rewrite_for_port(command: &str, env: &HashMap<String, Option<String>>, old: u16, new: u16) -> (String, HashMap<String, Option<String>>) {
+    let pattern = Regex::new(&format!(r"\b{}\b", old)).unwrap();
+    let command = pattern.replace_all(command, new.to_string().as_str()).to_string();
+    let mut env = env.clone();
+    env.insert("PORT".to_string(), Some(new.to_string()));
+    (command, env)
+}
+
+/// Look into a port conflict: who holds the port and what can be done about it
+pub fn investigate(
+    port: u16,
+    command: &str,
+    env: &HashMap<String, Option<String>>,
+    registered: &BTreeMap<u16, String>,
+    requester: Option<&str>,
+) -> PortConflict {
+    let held_by_service = registered.get(&port).filter(|service| Some(service.as_str()) != requester).cloned();
+    let holder = holder(port);
+    let mut remedies = Vec::new();
+    if let Some(service) = &held_by_service {
+        remedies.push(PortRemedy::StopService { name: service.clone() });
+    } else if let Some(holder) = &holder {
+        remedies.push(PortRemedy::KillHolder { pid: holder.pid, name: holder.name.clone() });
+    }
+    if let Some(free) = next_free_port(port, registered) {
+        let (command, env) = rewrite_for_port(command, env, port, free);
+        remedies.push(PortRemedy::UseNextPort { port: free, command, env });
+    }
+    PortConflict { port, holder, held_by_service, remedies }
+}
+
+/// The remedies as hints under an error message
+pub fn remedy_hints(conflict: &PortConflict) -> String {
+    let mut hints = String::from("💡 Try:");
+    for remedy in &conflict.remedies {
+        let hint = match remedy {
+            PortRemedy::KillHolder { pid, name } => format!("Stop {}: kill {}", name, pid),
+            PortRemedy::StopService { name } => format!("Stop the service '{}'", name),
+            PortRemedy::UseNextPort { port, command, .. } => format!("Use the free port {}: PORT={} {}", port, port, command),
+        };
+        hints.push_str(&format!("\n  • {}", hint));
+    }
+    if conflict.remedies.is_empty() {
+        hints.push_str("\n  • Stop whatever listens on the port, or pick another one");
+    }
+    hints
+}
+
+/// Terminate the process holding a port
+pub fn kill_holder(pid: u32) -> Result<(), String> {
+    let mut system = sysinfo::System::new();
+    let sys_pid = sysinfo::Pid::from_u32(pid);
+    system.refresh_process(sys_pid);
+    let process = system.process(sys_pid).ok_or("The process holding the port has already exited")?;
+    let sent = process.kill_with(sysinfo::Signal::Term).unwrap_or_else(|| process.kill());
+    if !sent {
+        return Err(format!("Cannot stop {} (pid {})", process.name(), pid));
+    }
+    Ok(())
+}
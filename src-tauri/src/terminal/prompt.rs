@@ -0,0 +1,315 @@
+// Prompt rendering
+// Builds each session's prompt in the backend from segments: directory, git,
+// runtime versions of the project, last exit code and duration, and the active
+// Kubernetes and AWS contexts. Runtime versions and the kube context are cached,
+// so a prompt costs no process launches when nothing changed. The frontend
+// renders the segments it receives as `session-prompt` events.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::status::SessionStatus;
+
+pub const PROMPT_EVENT: &str = "session-prompt";
+
+/// How long a runtime version is trusted before asking again
+const RUNTIME_TTL: Duration = Duration::from_secs(10 * 60);
+/// How far up from the working directory project markers are looked for
+const MAX_PROJECT_DEPTH: usize = 8;
+
+/// Marker files, runtime name, and the command that prints its version
+const RUNTIMES: [(&[&str], &str, &str, &[&str]); 6] = [
+    (&["package.json", ".nvmrc"], "node", "node", &["--version"]),
+    (&["Cargo.toml", "rust-toolchain.toml"], "rust", "rustc", &["--version"]),
+    (&["pyproject.toml", "requirements.txt", "setup.py"], "python", "python3", &["--version"]),
+    (&["go.mod"], "go", "go", &["version"]),
+    (&["Gemfile"], "ruby", "ruby", &["--version"]),
+    (&["pom.xml", "build.gradle"], "java", "java", &["-version"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentKind {
+    Directory,
+    Git,
+    Runtime,
+    ExitCode,
+    Duration,
+    Kubernetes,
+    Aws,
+}
+
+/// A hint for the frontend's colours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentStyle {
+    Normal,
+    Muted,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptSegment {
+    pub kind: SegmentKind,
+    pub text: String,
+    pub style: SegmentStyle,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptModel {
+    pub session_id: String,
+    pub segments: Vec<PromptSegment>,
+    /// The segments as plain text, for places that cannot render them
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptSettings {
+    /// Segments to show, in order
+    pub segments: Vec<SegmentKind>,
+    /// Durations shorter than this are left out
+    pub min_duration_ms: u64,
+}
+
+impl Default for PromptSettings {
+    fn default() -> Self {
+        Self {
+            segments: vec![
+                SegmentKind::Directory,
+                SegmentKind::Git,
+                SegmentKind::Runtime,
+                SegmentKind::Kubernetes,
+                SegmentKind::Aws,
+                SegmentKind::Duration,
+                SegmentKind::ExitCode,
+            ],
+            min_duration_ms: 2000,
+        }
+    }
+}
+
+impl PromptSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("prompt.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Default)]
+struct Caches {
+    /// (program, PATH) -> version
+    runtimes: HashMap<(String, String), (Option<String>, Instant)>,
+    /// Kube config file, its modification time and current context
+    kube: Option<(PathBuf, SystemTime, Option<String>)>,
+    /// Last prompt sent per session
+    sent: HashMap<String, PromptModel>,
+}
+
+/// Renders prompts; rendering may launch processes, so call it off the async runtime
+pub struct PromptEngine {
+    settings: Mutex<PromptSettings>,
+    caches: Mutex<Caches>,
+}
+
+impl Default for PromptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptEngine {
+    pub fn new() -> Self {
+        Self { settings: Mutex::new(PromptSettings::load()), caches: Mutex::new(Caches::default()) }
+    }
+
+    pub fn settings(&self) -> PromptSettings {
+        self.settings.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    pub fn set_settings(&self, settings: PromptSettings) -> Result<(), String> {
+        settings.save()?;
+        *self.settings.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = settings;
+        Ok(())
+    }
+
+    /// The session's prompt for its status and environment
+    pub fn render(&self, status: &SessionStatus, env: &HashMap<String, String>) -> PromptModel {
+        let settings = self.settings();
+        let mut caches = self.caches.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut segments = Vec::new();
+        for kind in &settings.segments {
+            match kind {
+                SegmentKind::Directory => segments.push(segment(*kind, home_relative(&status.working_directory), SegmentStyle::Normal)),
+                SegmentKind::Git => {
+                    if let Some(git) = &status.git {
+                        let mut text = format!("{}{}", git.branch, if git.dirty { "*" } else { "" });
+                        if git.ahead > 0 {
+                            text.push_str(&format!(" ⇡{}", git.ahead));
+                        }
+                        if git.behind > 0 {
+                            text.push_str(&format!(" ⇣{}", git.behind));
+                        }
+                        let style = if git.dirty { SegmentStyle::Warning } else { SegmentStyle::Muted };
+                        segments.push(segment(*kind, text, style));
+                    }
+                }
+                SegmentKind::Runtime => {
+                    let path_var = env.get("PATH").cloned().unwrap_or_default();
+                    for (name, program, args) in project_runtimes(Path::new(&status.working_directory)) {
+                        if let Some(version) = caches.runtime_version(program, args, &path_var) {
+                            segments.push(segment(*kind, format!("{} {}", name, version), SegmentStyle::Muted));
+                        }
+                    }
+                }
+                SegmentKind::ExitCode => {
+                    if let Some(code) = status.last_exit_code.filter(|code| *code != 0) {
+                        segments.push(segment(*kind, format!("✘ {}", code), SegmentStyle::Error));
+                    }
+                }
+                SegmentKind::Duration => {
+                    if let Some(ms) = status.last_duration_ms.filter(|ms| *ms >= settings.min_duration_ms) {
+                        segments.push(segment(*kind, format!("took {}", format_duration(ms)), SegmentStyle::Warning));
+                    }
+                }
+                SegmentKind::Kubernetes => {
+                    if let Some(context) = caches.kube_context(env) {
+                        segments.push(segment(*kind, format!("☸ {}", context), SegmentStyle::Normal));
+                    }
+                }
+                SegmentKind::Aws => {
+                    let profile = env.get("AWS_PROFILE").or_else(|| env.get("AWS_DEFAULT_PROFILE"));
+                    let region = env.get("AWS_REGION").or_else(|| env.get("AWS_DEFAULT_REGION"));
+                    let text = match (profile, region) {
+                        (Some(profile), Some(region)) => format!("aws {} ({})", profile, region),
+                        (Some(profile), None) => format!("aws {}", profile),
+                        (None, Some(region)) => format!("aws ({})", region),
+                        (None, None) => continue,
+                    };
+                    segments.push(segment(*kind, text, SegmentStyle::Normal));
+                }
+            }
+        }
+        let text = segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ");
+        PromptModel { session_id: status.session_id.clone(), segments, text }
+    }
+
+    /// Render and return the prompt only when it differs from the last one sent
+    pub fn update(&self, status: &SessionStatus, env: &HashMap<String, String>) -> Option<PromptModel> {
+        let prompt = self.render(status, env);
+        let mut caches = self.caches.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if caches.sent.get(&prompt.session_id) == Some(&prompt) {
+            return None;
+        }
+        caches.sent.insert(prompt.session_id.clone(), prompt.clone());
+        Some(prompt)
+    }
+
+    pub fn remove_session(&self, session_id: &str) {
+        self.caches.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).sent.remove(session_id);
+    }
+}
+
+impl Caches {
+    fn runtime_version(&mut self, program: &str, args: &[&str], path_var: &str) -> Option<String> {
+        let key = (program.to_string(), path_var.to_string());
+        if let Some((version, checked)) = self.runtimes.get(&key) {
+            if checked.elapsed() < RUNTIME_TTL {
+                return version.clone();
+            }
+        }
+        let version = std::process::Command::new(program)
+            .args(args)
+            .env("PATH", path_var)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            // `java -version` prints to stderr
+            .and_then(|output| {
+                let text = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+                super::shells::version_in(&text)
+            });
+        self.runtimes.insert(key, (version.clone(), Instant::now()));
+        version
+    }
+
+    /// `current-context` of the kube config, re-read only when the file changes
+    fn kube_context(&mut self, env: &HashMap<String, String>) -> Option<String> {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let path = env
+            .get("KUBECONFIG")
+            .and_then(|paths| paths.split(separator).find(|p| !p.is_empty()).map(PathBuf::from))
+            .or_else(|| dirs::home_dir().map(|home| home.join(".kube").join("config")))?;
+        let modified = std::fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+        if let Some((cached_path, cached_modified, context)) = &self.kube {
+            if *cached_path == path && *cached_modified == modified {
+                return context.clone();
+            }
+        }
+        let context = std::fs::read_to_string(&path).ok().and_then(|config| {
+            config.lines().find_map(|line| {
+                line.strip_prefix("current-context:")
+                    .map(|value| value.trim().trim_matches(['"', '\'']).to_string())
+                    .filter(|value| !value.is_empty())
+            })
+        });
+        self.kube = Some((path, modified, context.clone()));
+        context
+    }
+}
+
+fn segment(kind: SegmentKind, text: String, style: SegmentStyle) -> PromptSegment {
+    PromptSegment { kind, text, style }
+}
+
+/// Runtimes of the nearest project around the directory
+fn project_runtimes(dir: &Path) -> Vec<(&'static str, &'static str, &'static [&'static str])> {
+    for ancestor in dir.ancestors().take(MAX_PROJECT_DEPTH) {
+        let found: Vec<_> = RUNTIMES
+            .iter()
+            .filter(|(markers, ..)| markers.iter().any(|marker| ancestor.join(marker).exists()))
+            .map(|(_, name, program, args)| (*name, *program, *args))
+            .collect();
+        if !found.is_empty() {
+            return found;
+        }
+    }
+    Vec::new()
+}
+
+fn home_relative(path: &str) -> String {
+    match dirs::home_dir().and_then(|home| Path::new(path).strip_prefix(&home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.to_string(),
+    }
+}
+
+fn format_duration(ms: u64) -> String {
+    match ms {
+        ms if ms < 60_000 => format!("{:.1}s", ms as f64 / 1000.0),
+        ms if ms < 3_600_000 => format!("{}m{}s", ms / 60_000, (ms % 60_000) / 1000),
+        ms => format!("{}h{}m", ms / 3_600_000, (ms % 3_600_000) / 60_000),
+    }
+}
@@ -0,0 +1,554 @@
+// Live shells on pseudo terminals
+// Each session keeps its shell running on a pty, so interactive programs (vim,
+// htop, ssh, REPLs) get a real terminal and what the shell remembers (exports,
+// functions, aliases, job control) carries over between commands. Hooks
+// installed when the shell starts mark where a command's output begins (OSC
+// 133;C, once the shell has read the line) and ends (OSC 133;D with the exit
+// status, before the next prompt). Before each prompt the hooks also report
+// the shell's directory (OSC 7), so a session follows the shell wherever it
+// went: `cd foo && ls`, `pushd`, a sourced script. Everything the shell writes,
+// without our marks, is also broadcast as it arrives (`terminal-output`) for a
+// live view, and each new directory is broadcast as a `DirectoryReport`.
+// bash, zsh and fish get a pty; other shells, demo mode and sessions whose pty
+// could not be opened run each command as its own process instead. An SSH
+// session's pty runs the ssh client, with the hooks going to the remote shell.
+
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, Notify};
+
+use super::outcome::ExecutionOutcome;
+use super::running::TerminationSignal;
+use super::TerminalSession;
+
+/// Emitted with every chunk a session's shell writes
+pub const OUTPUT_EVENT: &str = "terminal-output";
+
+const MARKER: &str = "\x1b]133;";
+const MARKER_END: char = '\x07';
+/// OSC 7, `file://host/path`, from our hooks or anything else reporting the shell's directory
+const DIRECTORY_MARKER: &str = "\x1b]7;";
+/// String terminator, which OSC 7 may end with instead of BEL
+const STRING_TERMINATOR: &str = "\x1b\\";
+/// An unfinished mark longer than this is not going to be finished, and is let through as text
+const MAX_MARK_LEN: usize = 4096;
+/// Our marks carry this, so prompt frameworks sending their own OSC 133 cannot end a capture
+const MARKER_TAG: &str = ";ph7";
+/// A new shell gets this long to read its startup files and report the first status
+const STARTUP_TIMEOUT_SECS: u64 = 10;
+/// Chunks kept for slow subscribers of the live output
+pub const OUTPUT_BACKLOG: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyOutput {
+    pub session_id: String,
+    pub data: String,
+}
+
+/// The directory a session's shell moved to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryReport {
+    pub session_id: String,
+    pub directory: String,
+}
+
+/// The line that makes a shell mark where each command's output starts and report its exit
+/// status and directory (percent-encoded byte by byte, as OSC 7 wants); the leading space keeps
+/// it out of shells' history where they honour that. bash before 4.4 has no PS0, so there the
+/// echoed command line is cut by counting lines.
+fn shell_hooks(shell: &str) -> Option<&'static str> {
+    let name = shell.rsplit(['/', '\\']).next().unwrap_or(shell).to_lowercase();
+    match name.strip_suffix(".exe").unwrap_or(&name) {
+        "bash" => Some(concat!(
+            r#" __ph7_cwd() { local LC_ALL=C path="$PWD" encoded= char i; for ((i = 0; i < ${#path}; i++)); do char=${path:i:1}; "#,
+            r#"case "$char" in [-/._~A-Za-z0-9]) encoded+=$char ;; *) printf -v char '%%%02X' "'$char"; encoded+=$char ;; esac; done; "#,
+            r#"printf '\033]7;file://%s%s\007' "$HOSTNAME" "$encoded"; }; "#,
+            r#"__ph7_status() { local code=$?; __ph7_cwd; printf '\033]133;D;%s;ph7\007' "$code"; }; "#,
+            r#"PROMPT_COMMAND="__ph7_status${PROMPT_COMMAND:+;$PROMPT_COMMAND}"; PS0="${PS0}\e]133;C;ph7\a""#,
+        )),
+        "zsh" => Some(concat!(
+            r#" __ph7_cwd() { emulate -L zsh; setopt extendedglob nomultibyte; "#,
+            r#"printf '\033]7;file://%s%s\007' "$HOST" "${PWD//(#m)[^-\/._~A-Za-z0-9]/%${(l:2::0:)$(([##16]#MATCH))}}"; }; "#,
+            r#"__ph7_status() { local code=$?; __ph7_cwd; printf '\033]133;D;%s;ph7\007' "$code"; }; __ph7_start() { printf '\033]133;C;ph7\007'; }; "#,
+            r#"precmd_functions=(__ph7_status $precmd_functions); preexec_functions+=(__ph7_start)"#,
+        )),
+        "fish" => Some(concat!(
+            r#" function __ph7_status --on-event fish_postexec; set -l code $status; "#,
+            r#"printf '\e]7;file://%s%s\a' $hostname (string escape --style=url -- $PWD); printf '\e]133;D;%s;ph7\a' $code; end; "#,
+            r#"function __ph7_start --on-event fish_preexec; printf '\e]133;C;ph7\a'; end"#,
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Reading startup files; nothing is shown until the hook reports in
+    Starting,
+    Idle,
+    Running,
+    /// The shell is gone
+    Exited,
+}
+
+struct Capture {
+    phase: Phase,
+    /// Output of the running command; echo included until the start mark arrives
+    output: String,
+    started: bool,
+    status: Option<i32>,
+    /// Statuses still to come from lines sent quietly, before the running command's
+    quiet_lines: usize,
+    /// Where the shell last said it is
+    directory: Option<String>,
+}
+
+struct Shared {
+    capture: Mutex<Capture>,
+    changed: Notify,
+}
+
+enum Piece {
+    Text(String),
+    /// The shell read the command line and is about to run it
+    Start,
+    Status(i32),
+    /// The shell's directory, and the host it says it is on
+    Directory { host: String, path: String },
+}
+
+fn parse_marker(body: &str) -> Option<Piece> {
+    let body = body.strip_suffix(MARKER_TAG)?;
+    if body == "C" {
+        return Some(Piece::Start);
+    }
+    body.strip_prefix("D;")?.parse().ok().map(Piece::Status)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%').then(|| text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())).flatten();
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// An OSC 7 report, `file://host/path` with the path percent-encoded
+fn parse_directory(body: &str) -> Option<Piece> {
+    let location = body.strip_prefix("file://")?;
+    let (host, path) = location.split_at(location.find('/')?);
+    Some(Piece::Directory { host: host.to_string(), path: percent_decode(path) })
+}
+
+/// Whether a report comes from the session's own shell rather than, say, an ssh run inside it;
+/// `None` takes any host, for SSH sessions whose shell is on another machine anyway
+fn own_host(host: &str, local_host: Option<&str>) -> bool {
+    let Some(local_host) = local_host else { return true };
+    let short = |name: &str| name.split('.').next().unwrap_or(name).to_lowercase();
+    host.is_empty() || host.eq_ignore_ascii_case("localhost") || short(host) == short(local_host)
+}
+
+/// Start of the next of our marks or directory reports
+fn next_mark(text: &str) -> Option<usize> {
+    [MARKER, DIRECTORY_MARKER].iter().filter_map(|mark| text.find(mark)).min()
+}
+
+/// Split our marks and directory reports out of the stream; the start of a mark cut off at the
+/// end of a read waits in `carry`
+fn scan(carry: &mut String, text: &str) -> Vec<Piece> {
+    let mut input = std::mem::take(carry);
+    input.push_str(text);
+    let mut pieces = Vec::new();
+    let mut rest = input.as_str();
+    let mut text = String::new();
+    while let Some(start) = next_mark(rest) {
+        let mark = &rest[start..];
+        let directory = mark.starts_with(DIRECTORY_MARKER);
+        let prefix = if directory { DIRECTORY_MARKER.len() } else { MARKER.len() };
+        let bell = mark.find(MARKER_END).map(|at| (at, at + MARKER_END.len_utf8()));
+        let terminator = match directory {
+            true => [bell, mark.find(STRING_TERMINATOR).map(|at| (at, at + STRING_TERMINATOR.len()))].into_iter().flatten().min(),
+            false => bell,
+        };
+        let Some((length, end)) = terminator else {
+            if mark.len() <= MAX_MARK_LEN {
+                break;
+            }
+            text.push_str(&rest[..start + prefix]);
+            rest = &mark[prefix..];
+            continue;
+        };
+        text.push_str(&rest[..start]);
+        let body = &mark[prefix..length];
+        match if directory { parse_directory(body) } else { parse_marker(body) } {
+            Some(piece) => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(piece);
+            }
+            // Someone else's OSC 133 passes through
+            None => text.push_str(&mark[..end]),
+        }
+        rest = &mark[end..];
+    }
+    // An unfinished mark, or what may become one, is held back for the next read
+    let held = match next_mark(rest) {
+        Some(start) => start,
+        None => [MARKER, DIRECTORY_MARKER]
+            .iter()
+            .filter_map(|mark| (1..mark.len()).rev().find(|&n| rest.ends_with(&mark[..n])).map(|n| rest.len() - n))
+            .min()
+            .unwrap_or(rest.len()),
+    };
+    text.push_str(&rest[..held]);
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    *carry = rest[held..].to_string();
+    pieces
+}
+
+/// Text from the front of `bytes`, leaving a character cut off by the read for the next one
+fn take_text(bytes: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => bytes.len(),
+    };
+    let text = String::from_utf8_lossy(&bytes[..complete]).into_owned();
+    bytes.drain(..complete);
+    text
+}
+
+/// What a command printed, without the first `echo_lines` lines (the echoed command)
+/// or full-screen program output; escape codes are left for `ansi::process`
+fn command_output(raw: &str, echo_lines: usize) -> String {
+    let mut text = raw.replace("\r\n", "\n");
+    // vim, htop and the like draw on the alternate screen, which is gone once they exit
+    while let Some(start) = text.find("\x1b[?1049h") {
+        let end = text[start..].find("\x1b[?1049l").map_or(text.len(), |end| start + end + "\x1b[?1049l".len());
+        text.replace_range(start..end, "");
+    }
+    let output = text.splitn(echo_lines + 1, '\n').nth(echo_lines).unwrap_or_default();
+    output.trim_end().to_string()
+}
+
+/// A session's shell, running on its own pty
+pub struct PtyShell {
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    child: Mutex<Box<dyn Child + Send + Sync>>,
+    shared: Arc<Shared>,
+    /// Held while a command runs
+    busy: tokio::sync::Mutex<()>,
+    spawned_at: std::time::Instant,
+    startup_timeout: std::time::Duration,
+    /// Runs the ssh client; the command is on the other machine
+    remote: bool,
+}
+
+impl PtyShell {
+    /// Start the session's shell; `None` when the shell has no status hook
+    pub fn spawn(
+        session: &TerminalSession,
+        output: broadcast::Sender<PtyOutput>,
+        directories: broadcast::Sender<DirectoryReport>,
+    ) -> Result<Option<Arc<Self>>, String> {
+        let Some(hooks) = shell_hooks(&session.shell) else { return Ok(None) };
+        let (cols, rows) = session.pty_size;
+        let pair = native_pty_system()
+            .openpty(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 })
+            .map_err(|e| format!("Cannot open a pty: {}", e))?;
+
+        let (mut command, startup_timeout) = match &session.remote {
+            Some(remote) => {
+                let mut command = CommandBuilder::new(super::ssh::client()?);
+                command.args(remote.client_args());
+                if let Some(home) = dirs::home_dir() {
+                    command.cwd(home);
+                }
+                (command, super::ssh::STARTUP_TIMEOUT_SECS)
+            }
+            None => {
+                let mut command = CommandBuilder::new(&session.shell);
+                command.cwd(&session.working_directory);
+                (command, STARTUP_TIMEOUT_SECS)
+            }
+        };
+        command.env_clear();
+        for (key, value) in &session.environment_vars {
+            command.env(key, value);
+        }
+        command.env("TERM", "xterm-256color");
+        let child = pair.slave.spawn_command(command).map_err(|e| format!("Cannot start {}: {}", session.shell, e))?;
+        drop(pair.slave);
+
+        let reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+        let mut writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+        writer.write_all(format!("{}\r", hooks).as_bytes()).map_err(|e| e.to_string())?;
+        let _ = writer.flush();
+
+        let shared = Arc::new(Shared {
+            capture: Mutex::new(Capture {
+                phase: Phase::Starting,
+                output: String::new(),
+                started: false,
+                status: None,
+                quiet_lines: 0,
+                directory: None,
+            }),
+            changed: Notify::new(),
+        });
+        let session_id = session.id.clone();
+        let reader_shared = shared.clone();
+        let local_host = match session.remote {
+            Some(_) => None,
+            None => Some(sysinfo::System::host_name().unwrap_or_default()),
+        };
+        std::thread::Builder::new()
+            .name(format!("pty-{}", &session.id[..8.min(session.id.len())]))
+            .spawn(move || read_loop(reader, reader_shared, session_id, output, directories, local_host))
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some(Arc::new(Self {
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            child: Mutex::new(child),
+            shared,
+            busy: tokio::sync::Mutex::new(()),
+            spawned_at: std::time::Instant::now(),
+            startup_timeout: std::time::Duration::from_secs(startup_timeout),
+            remote: session.remote.is_some(),
+        })))
+    }
+
+    /// Send bytes to the shell (or whatever program has the terminal)
+    pub fn write(&self, data: &[u8]) -> Result<(), String> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(data).and_then(|_| writer.flush()).map_err(|e| format!("Cannot write to the terminal: {}", e))
+    }
+
+    /// Run a line whose output nobody waits for, such as keeping the shell in the session's directory
+    pub fn send_quiet(&self, line: &str) -> Result<(), String> {
+        let mut capture = self.shared.capture.lock().unwrap();
+        if !matches!(capture.phase, Phase::Idle | Phase::Running) {
+            return Ok(());
+        }
+        capture.quiet_lines += 1;
+        drop(capture);
+        self.write(format!(" {}\r", line).as_bytes())
+    }
+
+    /// Signal the program in the terminal's foreground; the shell itself is left alone
+    #[cfg(unix)]
+    pub fn signal(&self, signal: TerminationSignal) -> Result<(), String> {
+        // Over SSH only Ctrl-C reaches the remote command
+        if self.remote {
+            return match signal {
+                TerminationSignal::Interrupt => self.write(b"\x03"),
+                _ => Err("Commands in an SSH session can only be interrupted".to_string()),
+            };
+        }
+        let group = self.master.lock().unwrap().process_group_leader();
+        let shell = self.child.lock().unwrap().process_id();
+        match group {
+            Some(group) if group > 0 && u32::try_from(group).ok() != shell => {
+                // SAFETY: killpg only sends a signal, to the group the terminal runs in the foreground
+                if unsafe { libc::killpg(group, signal.number()) } != 0 {
+                    return Err(format!("Cannot signal the command: {}", std::io::Error::last_os_error()));
+                }
+                Ok(())
+            }
+            _ => Err("Nothing is running in the terminal's foreground".to_string()),
+        }
+    }
+
+    /// Windows terminals only pass on Ctrl-C
+    #[cfg(not(unix))]
+    pub fn signal(&self, signal: TerminationSignal) -> Result<(), String> {
+        match signal {
+            TerminationSignal::Interrupt => self.write(b"\x03"),
+            _ if self.remote => Err("Commands in an SSH session can only be interrupted".to_string()),
+            _ => Err("Commands in a Windows terminal can only be interrupted".to_string()),
+        }
+    }
+
+    pub fn resize(&self, rows: u16, cols: u16) -> Result<(), String> {
+        self.master
+            .lock()
+            .unwrap()
+            .resize(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 })
+            .map_err(|e| format!("Cannot resize the terminal: {}", e))
+    }
+
+    /// Wait for the state the reader reports to satisfy `done`
+    async fn wait_for(&self, done: impl Fn(&Capture) -> bool) {
+        loop {
+            let changed = self.shared.changed.notified();
+            let finished = done(&self.shared.capture.lock().unwrap());
+            if finished {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// Run a command line and wait, however long it takes, for the shell to report back.
+    /// `Err` means the shell cannot take it (still starting, gone, or busy with another
+    /// command) and the command should run on its own instead.
+    pub async fn run(&self, command_line: &str) -> Result<(String, ExecutionOutcome), String> {
+        // Counted from the start, so a shell that never reports in is only waited for once
+        let startup = self.startup_timeout.saturating_sub(self.spawned_at.elapsed());
+        if tokio::time::timeout(startup, self.wait_for(|capture| capture.phase != Phase::Starting)).await.is_err() {
+            return Err("the shell did not start in time".to_string());
+        }
+        let Ok(_busy) = self.busy.try_lock() else { return Err("the shell is busy".to_string()) };
+        {
+            let mut capture = self.shared.capture.lock().unwrap();
+            match capture.phase {
+                Phase::Idle => {}
+                Phase::Exited => return Err("the shell has exited".to_string()),
+                _ => return Err("the shell is busy".to_string()),
+            }
+            capture.phase = Phase::Running;
+            capture.output.clear();
+            capture.started = false;
+            capture.status = None;
+        }
+        self.write(format!("{}\r", command_line).as_bytes())?;
+        self.wait_for(|capture| capture.phase != Phase::Running).await;
+
+        let mut capture = self.shared.capture.lock().unwrap();
+        let echo_lines = if capture.started { 0 } else { command_line.lines().count().max(1) };
+        let output = command_output(&std::mem::take(&mut capture.output), echo_lines);
+        let outcome = match capture.status.take() {
+            Some(status) => ExecutionOutcome::from_shell_status(status),
+            // The shell exited under the command (`exec`, a crash)
+            None => self.child.lock().unwrap().try_wait().ok().flatten().map_or(ExecutionOutcome::Exited(1), |status| {
+                ExecutionOutcome::Exited(status.exit_code() as i32)
+            }),
+        };
+        Ok((output, outcome))
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.shared.capture.lock().unwrap().phase != Phase::Exited
+    }
+
+    /// Where the shell is: what it last reported, or for a local shell that never reports
+    /// (its hook replaced by the user's own), the directory of its process
+    pub fn current_directory(&self) -> Option<String> {
+        if let Some(directory) = self.shared.capture.lock().unwrap().directory.clone() {
+            return Some(directory);
+        }
+        if self.remote {
+            return None;
+        }
+        let pid = self.child.lock().unwrap().process_id()?;
+        process_directory(pid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_directory(pid: u32) -> Option<String> {
+    std::fs::read_link(format!("/proc/{}/cwd", pid)).ok().map(|path| path.to_string_lossy().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn process_directory(pid: u32) -> Option<String> {
+    // lsof -F prints fields one per line, the name prefixed with `n`
+    let output = std::process::Command::new("lsof").args(["-a", "-p", &pid.to_string(), "-d", "cwd", "-Fn"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).lines().find_map(|line| line.strip_prefix('n').map(str::to_string))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_directory(_pid: u32) -> Option<String> {
+    None
+}
+
+impl Drop for PtyShell {
+    fn drop(&mut self) {
+        // The reader thread ends once the pty closes
+        let _ = self.child.lock().unwrap().kill();
+    }
+}
+
+fn read_loop(
+    mut reader: Box<dyn Read + Send>,
+    shared: Arc<Shared>,
+    session_id: String,
+    output: broadcast::Sender<PtyOutput>,
+    directories: broadcast::Sender<DirectoryReport>,
+    local_host: Option<String>,
+) {
+    let mut buffer = [0u8; 8192];
+    let mut pending = Vec::new();
+    let mut carry = String::new();
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        pending.extend_from_slice(&buffer[..read]);
+        let text = take_text(&mut pending);
+        let mut shown = String::new();
+        let mut moved_to = None;
+        {
+            let mut capture = shared.capture.lock().unwrap();
+            for piece in scan(&mut carry, &text) {
+                match piece {
+                    Piece::Text(text) => {
+                        if capture.phase == Phase::Running && capture.quiet_lines == 0 {
+                            capture.output.push_str(&text);
+                        }
+                        if capture.phase != Phase::Starting {
+                            shown.push_str(&text);
+                        }
+                    }
+                    Piece::Start if capture.phase == Phase::Running && capture.quiet_lines == 0 => {
+                        capture.output.clear();
+                        capture.started = true;
+                    }
+                    Piece::Start => {}
+                    Piece::Status(_) if capture.phase == Phase::Starting => capture.phase = Phase::Idle,
+                    Piece::Status(_) if capture.quiet_lines > 0 => capture.quiet_lines -= 1,
+                    Piece::Status(status) if capture.phase == Phase::Running => {
+                        capture.status = Some(status);
+                        capture.phase = Phase::Idle;
+                    }
+                    Piece::Status(_) => {}
+                    Piece::Directory { host, path } => {
+                        if own_host(&host, local_host.as_deref()) && capture.directory.as_ref() != Some(&path) {
+                            capture.directory = Some(path.clone());
+                            moved_to = Some(path);
+                        }
+                    }
+                }
+            }
+        }
+        shared.changed.notify_waiters();
+        if !shown.is_empty() {
+            // Nobody listening is fine
+            let _ = output.send(PtyOutput { session_id: session_id.clone(), data: shown });
+        }
+        if let Some(directory) = moved_to {
+            let _ = directories.send(DirectoryReport { session_id: session_id.clone(), directory });
+        }
+    }
+    shared.capture.lock().unwrap().phase = Phase::Exited;
+    shared.changed.notify_waiters();
+}
@@ -0,0 +1,184 @@
+// Files the user is working with
+// Files a session's commands name (`vim src/main.rs`, `cat --file=notes.md`) or
+// print (`src/lib.rs:12:5` in a compiler error, `modified: README.md` from git)
+// are remembered per session, most recent first; only files that exist are
+// kept. The list goes into the smart context, so "open the file I just edited"
+// has something to go on, path completions offer these files first, and the
+// `@recent` token completes to them.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::path_display::lexical_normalize;
+
+/// Typed in place of a path to complete to a recent file
+pub const RECENT_TOKEN: &str = "@recent";
+const MAX_FILES: usize = 50;
+/// Paths looked at per command, so a huge listing does not mean thousands of lookups
+const MAX_CANDIDATES: usize = 20;
+/// Only the end of long output is searched; errors and summaries are there
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+const EDITORS: [&str; 12] = ["vim", "nvim", "vi", "nano", "emacs", "code", "subl", "hx", "micro", "kak", "gedit", "zed"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileUse {
+    /// Printed by a command
+    Mentioned,
+    /// Named in a command's arguments
+    Used,
+    /// Opened in an editor
+    Edited,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: String,
+    /// The strongest use so far
+    pub last_use: FileUse,
+    pub last_used: DateTime<Utc>,
+    pub uses: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct RecentFiles {
+    sessions: HashMap<String, Vec<RecentFile>>,
+}
+
+fn location_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    // `path/to/file.ext:12` as compilers, linters and grep -n print them
+    PATTERN.get_or_init(|| Regex::new(r#"(?:^|[\s'"(\[])((?:~|\.{1,2})?/?(?:[\w.@+-]+/)*[\w@+-][\w.@+-]*\.[A-Za-z0-9]{1,10}):\d+"#).unwrap())
+}
+
+fn git_status_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    // `git status` in long (`modified:   a.rs`) and short (` M a.rs`) form
+    PATTERN.get_or_init(|| Regex::new(r"(?m)^(?:\s+(?:modified|new file|deleted|renamed|both modified):\s+(?:.+ -> )?(\S+)|[ MADRCU?]{2} (?:.+ -> )?(\S+))\s*$").unwrap())
+}
+
+/// Words of the line that may be paths: arguments that are not flags, and the values of `--flag=value`
+fn argument_paths(command: &str) -> Vec<String> {
+    let words = super::shell_quote::split(command).unwrap_or_else(|_| command.split_whitespace().map(str::to_string).collect());
+    words
+        .into_iter()
+        .skip(1)
+        .filter_map(|word| match word.strip_prefix('-') {
+            Some(flag) => flag.split_once('=').map(|(_, value)| value.to_string()),
+            None => Some(word),
+        })
+        .filter(|word| !word.is_empty() && !word.contains(['*', '?', '$', '|', '>', '<', '&', ';']))
+        .collect()
+}
+
+fn output_paths(output: &str) -> Vec<String> {
+    let mut start = output.len().saturating_sub(MAX_OUTPUT_BYTES);
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    let tail = &output[start..];
+    let mut paths: Vec<String> = location_pattern().captures_iter(tail).map(|c| c[1].to_string()).collect();
+    paths.extend(git_status_pattern().captures_iter(tail).filter_map(|c| c.get(1).or_else(|| c.get(2)).map(|m| m.as_str().to_string())));
+    paths
+}
+
+fn resolve(directory: &Path, path: &str) -> PathBuf {
+    let expanded = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|home| home.join(rest)).unwrap_or_else(|| PathBuf::from(path)),
+        None => directory.join(path),
+    };
+    lexical_normalize(&expanded)
+}
+
+impl RecentFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the existing files a command named or printed
+    pub fn record(&mut self, session_id: &str, directory: &str, command: &str, output: &str) {
+        let directory = Path::new(directory);
+        let program = command.split_whitespace().next().map(|p| p.rsplit('/').next().unwrap_or(p)).unwrap_or_default();
+        let argument_use = if EDITORS.contains(&program) { FileUse::Edited } else { FileUse::Used };
+
+        let mut candidates: Vec<(String, FileUse)> = Vec::new();
+        // Output first, so the arguments, the stronger use, end up most recent
+        for path in output_paths(output).into_iter().rev().take(MAX_CANDIDATES) {
+            candidates.push((path, FileUse::Mentioned));
+        }
+        for path in argument_paths(command).into_iter().take(MAX_CANDIDATES) {
+            candidates.push((path, argument_use));
+        }
+
+        let now = Utc::now();
+        let files = self.sessions.entry(session_id.to_string()).or_default();
+        for (path, file_use) in candidates {
+            let resolved = resolve(directory, &path);
+            if !resolved.is_file() {
+                continue;
+            }
+            let path = resolved.to_string_lossy().to_string();
+            let (uses, strongest) = match files.iter().position(|file| file.path == path) {
+                Some(index) => {
+                    let file = files.remove(index);
+                    (file.uses + 1, file.last_use.max(file_use))
+                }
+                None => (1, file_use),
+            };
+            files.insert(0, RecentFile { path, last_use: strongest, last_used: now, uses });
+        }
+        files.truncate(MAX_FILES);
+    }
+
+    /// Most recent first
+    pub fn list(&self, session_id: &str, limit: usize) -> Vec<RecentFile> {
+        self.sessions.get(session_id).map(|files| files.iter().take(limit).cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn forget_session(&mut self, session_id: &str) {
+        self.sessions.remove(session_id);
+    }
+
+    /// Completions of `@recent`, optionally narrowed as in `@recent:main`, written relative to the
+    /// working directory where they are inside it
+    pub fn complete(&self, session_id: &str, working_directory: &Path, partial: &str) -> Vec<String> {
+        let Some(rest) = partial.strip_prefix(RECENT_TOKEN) else {
+            // `@re` completes to the token itself
+            return if partial.len() > 1 && RECENT_TOKEN.starts_with(partial) { vec![RECENT_TOKEN.to_string()] } else { Vec::new() };
+        };
+        let filter = rest.trim_start_matches(':').to_lowercase();
+        self.list(session_id, MAX_FILES)
+            .into_iter()
+            .filter(|file| file.path.to_lowercase().contains(&filter))
+            .map(|file| match Path::new(&file.path).strip_prefix(working_directory) {
+                Ok(relative) => relative.to_string_lossy().to_string(),
+                Err(_) => file.path,
+            })
+            .collect()
+    }
+
+    /// Lines for the smart context
+    pub fn context_summary(&self, session_id: &str, working_directory: &Path) -> Option<String> {
+        let files = self.list(session_id, 10);
+        if files.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = files
+            .iter()
+            .map(|file| {
+                let path = Path::new(&file.path).strip_prefix(working_directory).map(|p| p.to_string_lossy().to_string()).unwrap_or_else(|_| file.path.clone());
+                let how = match file.last_use {
+                    FileUse::Edited => "edited",
+                    FileUse::Used => "used",
+                    FileUse::Mentioned => "in output",
+                };
+                format!("- {} ({})", path, how)
+            })
+            .collect();
+        Some(format!("Recent Files (most recent first):\n{}\n", lines.join("\n")))
+    }
+}
@@ -0,0 +1,182 @@
+// Commands running now
+// Every command is registered while it runs, so it can be interrupted,
+// terminated or killed from the UI instead of being waited out. In a session's
+// shell the signal goes to the terminal's foreground process group (the
+// command and what it started, never the shell); a command run on its own gets
+// a process group of its own for the same reason. Commands run on their own
+// are also kept in the process registry (see `lifecycle`) while they run.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::lifecycle::{ChildPolicy, ProcessRegistry, TrackedProcess};
+use super::pty::PtyShell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TerminationSignal {
+    /// Ctrl-C: programs may clean up or ignore it
+    #[serde(rename = "SIGINT", alias = "interrupt")]
+    Interrupt,
+    #[serde(rename = "SIGTERM", alias = "terminate")]
+    Terminate,
+    /// Cannot be caught or ignored
+    #[serde(rename = "SIGKILL", alias = "kill")]
+    Kill,
+}
+
+impl TerminationSignal {
+    #[cfg(unix)]
+    pub fn number(self) -> i32 {
+        match self {
+            TerminationSignal::Interrupt => libc::SIGINT,
+            TerminationSignal::Terminate => libc::SIGTERM,
+            TerminationSignal::Kill => libc::SIGKILL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningCommand {
+    pub execution_id: String,
+    pub session_id: String,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Where a running command can be reached
+enum Target {
+    /// Not started yet
+    Starting,
+    Shell(Arc<PtyShell>),
+    Process(u32),
+}
+
+#[derive(Clone, Default)]
+pub struct RunningCommands {
+    entries: Arc<Mutex<HashMap<String, (RunningCommand, Target)>>>,
+    processes: ProcessRegistry,
+}
+
+impl RunningCommands {
+    pub fn new(processes: ProcessRegistry) -> Self {
+        Self { entries: Arc::default(), processes }
+    }
+
+    pub fn register(&self, execution_id: &str, session_id: &str, command: &str, working_directory: &str) -> Registration {
+        let info = RunningCommand {
+            execution_id: execution_id.to_string(),
+            session_id: session_id.to_string(),
+            command: command.to_string(),
+            started_at: Utc::now(),
+        };
+        self.entries.lock().unwrap().insert(execution_id.to_string(), (info, Target::Starting));
+        Registration { commands: self.clone(), execution_id: execution_id.to_string(), working_directory: working_directory.to_string() }
+    }
+
+    /// A session's running commands, oldest first; all sessions' without one
+    pub fn list(&self, session_id: Option<&str>) -> Vec<RunningCommand> {
+        let mut running: Vec<RunningCommand> = self
+            .entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(info, _)| session_id.map_or(true, |id| info.session_id == id))
+            .map(|(info, _)| info.clone())
+            .collect();
+        running.sort_by_key(|info| info.started_at);
+        running
+    }
+
+    /// Signal a command of the session; without an execution id, the one started last
+    pub fn signal(&self, session_id: &str, execution_id: Option<&str>, signal: TerminationSignal) -> Result<RunningCommand, String> {
+        let entries = self.entries.lock().unwrap();
+        let found = match execution_id {
+            Some(id) => entries.get(id).filter(|(info, _)| info.session_id == session_id),
+            None => entries.values().filter(|(info, _)| info.session_id == session_id).max_by_key(|(info, _)| info.started_at),
+        };
+        let Some((info, target)) = found else {
+            return Err("That command is not running".to_string());
+        };
+        match target {
+            Target::Starting => return Err("The command has not started yet".to_string()),
+            Target::Shell(shell) => shell.signal(signal)?,
+            Target::Process(pid) => signal_process(*pid, signal)?,
+        }
+        println!("🛑 Sent {:?} to '{}'", signal, info.command);
+        Ok(info.clone())
+    }
+}
+
+/// Keeps a command listed while it runs; dropping it takes the command off the list
+pub struct Registration {
+    commands: RunningCommands,
+    execution_id: String,
+    working_directory: String,
+}
+
+impl Registration {
+    fn set_target(&self, target: Target) {
+        if let Some(entry) = self.commands.entries.lock().unwrap().get_mut(&self.execution_id) {
+            entry.1 = target;
+        }
+    }
+
+    pub fn in_shell(&self, shell: &Arc<PtyShell>) {
+        self.set_target(Target::Shell(shell.clone()));
+    }
+
+    pub fn in_process(&self, pid: u32) {
+        self.set_target(Target::Process(pid));
+        let info = self.commands.entries.lock().unwrap().get(&self.execution_id).map(|(info, _)| info.clone());
+        if let Some(info) = info {
+            self.commands.processes.track(TrackedProcess {
+                id: info.execution_id,
+                pid,
+                session_id: Some(info.session_id),
+                command: info.command,
+                working_directory: self.working_directory.clone(),
+                policy: ChildPolicy::KillOnClose,
+                started_at: info.started_at,
+                process_start_time: super::lifecycle::process_start_time(pid).unwrap_or_default(),
+                log_file: None,
+                orphaned: false,
+            });
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.commands.entries.lock().unwrap().remove(&self.execution_id);
+        self.commands.processes.untrack(&self.execution_id);
+    }
+}
+
+/// Signal a command run on its own, and whatever it started in its process group
+#[cfg(unix)]
+pub fn signal_process(pid: u32, signal: TerminationSignal) -> Result<(), String> {
+    let pid = libc::pid_t::try_from(pid).map_err(|e| e.to_string())?;
+    // SAFETY: killpg only sends a signal; the group is the command's own
+    if unsafe { libc::killpg(pid, signal.number()) } != 0 {
+        return Err(format!("Cannot signal the command: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Windows has no signals to send another process; terminating and killing end its process tree
+#[cfg(not(unix))]
+pub fn signal_process(pid: u32, signal: TerminationSignal) -> Result<(), String> {
+    if signal == TerminationSignal::Interrupt {
+        return Err("Commands run on their own cannot be interrupted on Windows; terminate them instead".to_string());
+    }
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .status()
+        .map_err(|e| format!("Cannot run taskkill: {}", e))?;
+    if !status.success() {
+        return Err("taskkill could not end the command".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+// Sessions kept across restarts
+// What is needed to reopen each open session is written to the data directory
+// whenever a session opens, closes or changes: its title, directory, shell, the
+// environment overrides it was started with and the size of its terminal. The
+// full environment is not kept; a restored session inherits the environment of
+// the new run with the same overrides applied, the way it was first started.
+// On launch the saved sessions are reopened under their old ids, so windows and
+// anything else that refers to a session find it again.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::lifecycle::ChildPolicy;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub id: String,
+    pub title: String,
+    pub working_directory: String,
+    pub shell: String,
+    /// Overrides of the inherited environment; `null` removes a variable
+    #[serde(default)]
+    pub env: HashMap<String, Option<String>>,
+    pub pty_size: (u16, u16), // cols, rows
+    #[serde(default)]
+    pub accessible_output: bool,
+    #[serde(default)]
+    pub child_policy: Option<ChildPolicy>,
+    pub created_at: DateTime<Utc>,
+    /// SSH sessions reconnect to the same machine
+    #[serde(default)]
+    pub remote: Option<super::ssh::SshSessionConfig>,
+}
+
+impl SavedSession {
+    pub fn from_session(session: &super::TerminalSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            title: session.title.clone(),
+            working_directory: session.working_directory.clone(),
+            shell: session.shell.clone(),
+            env: session.env_overrides.clone(),
+            pty_size: session.pty_size,
+            accessible_output: session.accessible_output,
+            child_policy: session.child_policy,
+            created_at: session.created_at,
+            remote: session.remote.clone(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SessionsFile {
+    sessions: Vec<SavedSession>,
+}
+
+/// Where the open sessions are written
+#[derive(Default)]
+pub struct SavedSessions {
+    /// Off for demo mode and tools that only borrow the core
+    persist: bool,
+}
+
+impl SavedSessions {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("sessions.json")
+    }
+
+    /// Saving sessions of the app; demo mode keeps nothing
+    pub fn for_app() -> Self {
+        Self { persist: !crate::system::demo::is_active() }
+    }
+
+    /// The sessions open when the app last ran, oldest first
+    pub fn load(&self) -> Vec<SavedSession> {
+        if !self.persist {
+            return Vec::new();
+        }
+        let saved: SessionsFile = std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let mut sessions = saved.sessions;
+        sessions.sort_by_key(|session| session.created_at);
+        sessions
+    }
+
+    pub fn save<'a>(&self, sessions: impl Iterator<Item = &'a super::TerminalSession>) {
+        if !self.persist {
+            return;
+        }
+        let mut sessions: Vec<SavedSession> = sessions.map(SavedSession::from_session).collect();
+        sessions.sort_by_key(|session| session.created_at);
+        let result = serde_json::to_string_pretty(&SessionsFile { sessions }).map_err(|e| e.to_string()).and_then(|json| {
+            let file = Self::data_file();
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(file, json).map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            println!("⚠️ Failed to save the open sessions: {}", e);
+        }
+    }
+}
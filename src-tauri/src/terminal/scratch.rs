@@ -0,0 +1,237 @@
+// Scratch directories
+// Temporary workspaces a session can make for experiments (`scratch new`), so
+// trying things out does not leave files in the user's real directories. Each
+// one is removed by its cleanup policy: when its session closes, after a number
+// of hours, or never. Directories still in use by another session are kept until
+// it leaves, and anything left over from an earlier run is swept on start.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CleanupPolicy {
+    OnSessionClose,
+    AfterHours { hours: u64 },
+    Keep,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScratchDir {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    /// The session that made it; `None` for directories made by agent tasks outside a session
+    pub session_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub policy: CleanupPolicy,
+}
+
+impl ScratchDir {
+    fn expired(&self, now: DateTime<Utc>, live_sessions: &[String]) -> bool {
+        match self.policy {
+            CleanupPolicy::OnSessionClose => self.session_id.as_ref().map_or(true, |id| !live_sessions.contains(id)),
+            CleanupPolicy::AfterHours { hours } => now - self.created_at >= chrono::Duration::hours(hours as i64),
+            CleanupPolicy::Keep => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScratchSettings {
+    /// Policy for directories made without one
+    pub default_policy: CleanupPolicy,
+}
+
+impl Default for ScratchSettings {
+    fn default() -> Self {
+        Self { default_policy: CleanupPolicy::OnSessionClose }
+    }
+}
+
+impl ScratchSettings {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("scratch.json")
+    }
+
+    fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+}
+
+/// The scratch directories that exist, and the settings they are made with
+#[derive(Debug, Default)]
+pub struct ScratchDirs {
+    dirs: Vec<ScratchDir>,
+    settings: ScratchSettings,
+}
+
+impl ScratchDirs {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("scratch_dirs.json")
+    }
+
+    /// Load the registry and remove what expired while the app was closed; no session is live yet
+    pub fn load() -> Self {
+        let dirs = std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let mut scratch = Self { dirs, settings: ScratchSettings::load() };
+        scratch.sweep(&[], &[]);
+        scratch
+    }
+
+    fn save(&self) {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&self.dirs) {
+            let _ = std::fs::write(file, json);
+        }
+    }
+
+    fn root() -> PathBuf {
+        std::env::temp_dir().join("ph7console-scratch")
+    }
+
+    pub fn settings(&self) -> &ScratchSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: ScratchSettings) -> Result<(), String> {
+        settings.save()?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Make a new directory; the name defaults to `scratch-<n>`
+    pub fn create(&mut self, session_id: Option<&str>, name: Option<&str>, policy: Option<CleanupPolicy>) -> Result<ScratchDir, String> {
+        let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+            Some(name) if name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.')) && !name.starts_with('.') => name.to_string(),
+            Some(name) => return Err(format!("'{}' is not a valid name; use letters, digits, '-', '_' and '.'", name)),
+            None => format!("scratch-{}", self.dirs.len() + 1),
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        // The id keeps two directories with the same name apart
+        let path = Self::root().join(format!("{}-{}", name, &id[..8]));
+        std::fs::create_dir_all(&path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+        let dir = ScratchDir {
+            id,
+            name,
+            path: path.to_string_lossy().to_string(),
+            session_id: session_id.map(str::to_string),
+            created_at: Utc::now(),
+            policy: policy.unwrap_or(self.settings.default_policy),
+        };
+        self.dirs.push(dir.clone());
+        self.save();
+        Ok(dir)
+    }
+
+    /// All directories, or those of one session
+    pub fn list(&self, session_id: Option<&str>) -> Vec<&ScratchDir> {
+        self.dirs.iter().filter(|dir| session_id.is_none() || dir.session_id.as_deref() == session_id).collect()
+    }
+
+    /// A session's directory by name or id, falling back to any session's
+    pub fn find(&self, session_id: Option<&str>, name_or_id: &str) -> Option<&ScratchDir> {
+        let matches = |dir: &&ScratchDir| dir.id == name_or_id || dir.name == name_or_id;
+        self.dirs
+            .iter()
+            .filter(|dir| dir.session_id.as_deref() == session_id)
+            .find(matches)
+            .or_else(|| self.dirs.iter().find(matches))
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<ScratchDir, String> {
+        let index = self.dirs.iter().position(|dir| dir.id == id).ok_or_else(|| format!("No scratch directory {}", id))?;
+        delete(&self.dirs[index].path)?;
+        let dir = self.dirs.remove(index);
+        self.save();
+        Ok(dir)
+    }
+
+    pub fn set_policy(&mut self, id: &str, policy: CleanupPolicy) -> Result<ScratchDir, String> {
+        let dir = self.dirs.iter_mut().find(|dir| dir.id == id).ok_or_else(|| format!("No scratch directory {}", id))?;
+        dir.policy = policy;
+        let dir = dir.clone();
+        self.save();
+        Ok(dir)
+    }
+
+    /// Remove expired directories that no session is working in; returns their paths
+    pub fn sweep(&mut self, live_sessions: &[String], in_use: &[String]) -> Vec<String> {
+        let now = Utc::now();
+        let mut removed = Vec::new();
+        self.dirs.retain(|dir| {
+            // The system may have cleared its temp directory already
+            if !Path::new(&dir.path).is_dir() {
+                return false;
+            }
+            let busy = in_use.iter().any(|cwd| Path::new(cwd).starts_with(&dir.path));
+            if busy || !dir.expired(now, live_sessions) || delete(&dir.path).is_err() {
+                return true;
+            }
+            removed.push(dir.path.clone());
+            false
+        });
+        self.save();
+        removed
+    }
+}
+
+/// Remove a scratch directory, refusing anything outside the scratch root
+fn delete(path: &str) -> Result<(), String> {
+    let path = Path::new(path);
+    if !path.starts_with(ScratchDirs::root()) || path == ScratchDirs::root() {
+        return Err(format!("{} is not a scratch directory", path.display()));
+    }
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Cannot remove {}: {}", path.display(), e)),
+        _ => Ok(()),
+    }
+}
+
+/// `--keep`, `--hours N` and `--on-close` from `scratch new` arguments, with the remaining name
+pub fn parse_new_args(args: &[&str]) -> Result<(Option<String>, Option<CleanupPolicy>), String> {
+    let mut name = None;
+    let mut policy = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--keep" => policy = Some(CleanupPolicy::Keep),
+            "--on-close" => policy = Some(CleanupPolicy::OnSessionClose),
+            "--hours" => {
+                let hours = args.next().and_then(|h| h.parse().ok()).ok_or("--hours needs a number")?;
+                policy = Some(CleanupPolicy::AfterHours { hours });
+            }
+            other if other.starts_with("--") => return Err(format!("Unknown option {}", other)),
+            other => name = Some(other.to_string()),
+        }
+    }
+    Ok((name, policy))
+}
+
+pub fn describe_policy(policy: CleanupPolicy) -> String {
+    match policy {
+        CleanupPolicy::OnSessionClose => "removed when the session closes".to_string(),
+        CleanupPolicy::AfterHours { hours } => format!("removed after {}h", hours),
+        CleanupPolicy::Keep => "kept".to_string(),
+    }
+}
@@ -0,0 +1,434 @@
+// Shell history write-back
+// Commands run in pH7Console can also be appended to the history file of the
+// session's shell, so Ctrl-R in a plain terminal still finds them. It is off
+// unless turned on, and only a history file the shell already keeps is added
+// to. Each file is written the way its shell writes it: bash lines (preceded by
+// `#` timestamps when the file has them), zsh's extended `: start:elapsed;`
+// lines with its metafied bytes, fish's YAML-like entries and PSReadLine's
+// backtick-continued lines. Each is locked the way its shell locks it: zsh's
+// `.LOCK` file and fcntl lock, fish's flock; bash and PSReadLine only append.
+//
+// Before anything is written the command goes through the redaction rules: a
+// command matching an exclusion is left out, and secrets of well-known shapes
+// and the user's own patterns are masked. Commands starting with a space are
+// left out, as HISTCONTROL=ignorespace and HIST_IGNORE_SPACE do. Writes happen
+// on one thread, in the order the commands finished.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, OnceLock};
+use std::time::Duration;
+
+use super::CommandExecution;
+
+const REDACTED: &str = "[redacted]";
+/// How long a locked history file is waited for before the entry is dropped
+const LOCK_ATTEMPTS: u32 = 20;
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+/// zsh takes over a `.LOCK` file older than this
+const STALE_LOCK: Duration = Duration::from_secs(10);
+/// End of a bash history read to tell whether it has timestamps
+const BASH_TAIL: u64 = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShellHistorySettings {
+    /// Append commands to the session shell's own history file
+    pub enabled: bool,
+    /// Leave out commands that failed
+    pub only_successful: bool,
+    /// Mask tokens, keys and passwords of well-known shapes
+    pub mask_known_secrets: bool,
+    /// Regexes; what they match is written as [redacted]
+    pub redactions: Vec<String>,
+    /// Regexes; a command matching any is not written at all
+    pub exclude: Vec<String>,
+}
+
+impl Default for ShellHistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            only_successful: false,
+            mask_known_secrets: true,
+            redactions: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl ShellHistorySettings {
+    fn settings_file() -> PathBuf {
+        crate::ai::config_directory().join("shell_history_settings.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::settings_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::settings_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for pattern in self.redactions.iter().chain(&self.exclude) {
+            Regex::new(pattern).map_err(|e| format!("Invalid pattern `{}`: {}", pattern, e))?;
+        }
+        Ok(())
+    }
+
+    /// The command as it may be written, or `None` when it must stay out of the history
+    fn redact(&self, command: &str) -> Option<String> {
+        if command.starts_with(' ') || command.trim().is_empty() {
+            return None;
+        }
+        // A rule that no longer compiles must not let everything through
+        for pattern in &self.exclude {
+            if Regex::new(pattern).map_or(true, |exclude| exclude.is_match(command)) {
+                return None;
+            }
+        }
+        let mut command = if self.mask_known_secrets {
+            crate::integrations::sinks::mask_known_secrets(command)
+        } else {
+            command.to_string()
+        };
+        for pattern in &self.redactions {
+            command = Regex::new(pattern).ok()?.replace_all(&command, REDACTED).into_owned();
+        }
+        Some(command)
+    }
+}
+
+/// Shells whose history files can be written
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HistoryShell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl HistoryShell {
+    fn from_shell(shell: &str) -> Option<Self> {
+        let name = shell.rsplit(['/', '\\']).next().unwrap_or(shell).to_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            "pwsh" | "powershell" => Some(Self::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// Where the shell keeps its history, given the session's environment
+    fn history_file(self, environment: &HashMap<String, String>) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        let var = |name: &str| environment.get(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        let data_dir = || var("XDG_DATA_HOME").unwrap_or_else(|| home.join(".local").join("share"));
+        match self {
+            Self::Bash => Some(var("HISTFILE").unwrap_or_else(|| home.join(".bash_history"))),
+            Self::Zsh => Some(var("HISTFILE").unwrap_or_else(|| var("ZDOTDIR").unwrap_or_else(|| home.clone()).join(".zsh_history"))),
+            Self::Fish => {
+                // `fish_history` names another history; set empty, it turns history off
+                let name = match environment.get("fish_history").map(String::as_str) {
+                    Some("") => return None,
+                    None | Some("default") => "fish",
+                    Some(name) => name,
+                };
+                Some(data_dir().join("fish").join(format!("{}_history", name)))
+            }
+            Self::PowerShell if cfg!(windows) => {
+                Some(dirs::config_dir()?.join("Microsoft").join("Windows").join("PowerShell").join("PSReadLine").join("ConsoleHost_history.txt"))
+            }
+            Self::PowerShell => Some(data_dir().join("powershell").join("PSReadLine").join("ConsoleHost_history.txt")),
+        }
+    }
+
+    /// The entry as the shell would append it
+    fn format(self, entry: &Entry, bash_timestamps: bool) -> Vec<u8> {
+        let command = &entry.command;
+        match self {
+            Self::Bash if bash_timestamps => format!("#{}\n{}\n", entry.started, command).into_bytes(),
+            Self::Bash => format!("{}\n", command).into_bytes(),
+            Self::Zsh => metafy(&format!(": {}:{};{}\n", entry.started, entry.elapsed_secs, command.replace('\n', "\\\n"))),
+            Self::Fish => {
+                let escaped = command.replace('\\', "\\\\").replace('\n', "\\n");
+                format!("- cmd: {}\n  when: {}\n", escaped, entry.started).into_bytes()
+            }
+            Self::PowerShell => {
+                let newline = if cfg!(windows) { "\r\n" } else { "\n" };
+                format!("{}{}", command.lines().collect::<Vec<_>>().join(&format!("`{}", newline)), newline).into_bytes()
+            }
+        }
+    }
+}
+
+/// zsh keeps the bytes it uses internally (NUL and 0x83 to 0xa2) as Meta followed by the byte xor 32
+fn metafy(text: &str) -> Vec<u8> {
+    const META: u8 = 0x83;
+    let mut bytes = Vec::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte == 0 || (META..=0xa2).contains(&byte) {
+            bytes.extend([META, byte ^ 32]);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    bytes
+}
+
+/// Whether a bash history already has `#<seconds>` lines, which bash only reads back when they are throughout
+fn has_bash_timestamps(file: &mut File) -> bool {
+    let length = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let mut tail = Vec::new();
+    if file.seek(SeekFrom::Start(length.saturating_sub(BASH_TAIL))).and_then(|_| file.read_to_end(&mut tail)).is_err() {
+        return false;
+    }
+    String::from_utf8_lossy(&tail)
+        .lines()
+        .any(|line| line.len() > 1 && line.starts_with('#') && line[1..].bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+/// zsh's own lock: `<file>.LOCK`, created exclusively and removed when done
+struct DotLock(PathBuf);
+
+impl DotLock {
+    fn acquire(file: &Path) -> Option<Self> {
+        let mut path = file.as_os_str().to_owned();
+        path.push(".LOCK");
+        let path = PathBuf::from(path);
+        for _ in 0..LOCK_ATTEMPTS {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut lock) => {
+                    let _ = writeln!(lock, "{} {}", std::process::id(), sysinfo::System::host_name().unwrap_or_default());
+                    return Some(Self(path));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let age = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok().and_then(|modified| modified.elapsed().ok());
+                    if age.is_some_and(|age| age > STALE_LOCK) {
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                }
+                Err(_) => return None,
+            }
+            std::thread::sleep(LOCK_RETRY);
+        }
+        None
+    }
+}
+
+impl Drop for DotLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// The lock the shell takes on the file itself; released when the file is closed
+#[cfg(unix)]
+fn lock_file(file: &File, shell: HistoryShell) -> bool {
+    use std::os::unix::io::AsRawFd;
+    let fd = file.as_raw_fd();
+    for _ in 0..LOCK_ATTEMPTS {
+        let locked = match shell {
+            HistoryShell::Zsh => {
+                let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+                lock.l_type = libc::F_WRLCK as _;
+                lock.l_whence = libc::SEEK_SET as _;
+                let result = unsafe { libc::fcntl(fd, libc::F_SETLK, &lock) };
+                result == 0
+            }
+            HistoryShell::Fish => {
+                let result = unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) };
+                result == 0
+            }
+            HistoryShell::Bash | HistoryShell::PowerShell => true,
+        };
+        if locked {
+            return true;
+        }
+        std::thread::sleep(LOCK_RETRY);
+    }
+    false
+}
+
+#[cfg(not(unix))]
+fn lock_file(_file: &File, _shell: HistoryShell) -> bool {
+    true
+}
+
+struct Entry {
+    shell: HistoryShell,
+    file: PathBuf,
+    command: String,
+    /// Unix seconds
+    started: i64,
+    elapsed_secs: u64,
+    /// The session exports HISTTIMEFORMAT, so its bash writes timestamps
+    bash_timestamps: bool,
+}
+
+fn append(entry: &Entry) -> Result<(), String> {
+    // Only a history the shell already keeps is added to
+    if !entry.file.is_file() {
+        return Ok(());
+    }
+    let _dot_lock = match entry.shell {
+        HistoryShell::Zsh => Some(DotLock::acquire(&entry.file).ok_or("zsh is holding the history lock")?),
+        _ => None,
+    };
+    let mut file = OpenOptions::new().read(true).append(true).open(&entry.file).map_err(|e| e.to_string())?;
+    if !lock_file(&file, entry.shell) {
+        return Err("the shell is holding the history lock".to_string());
+    }
+    let bash_timestamps = entry.shell == HistoryShell::Bash && (entry.bash_timestamps || has_bash_timestamps(&mut file));
+    // One write, so appends of shells running at the same time do not interleave
+    file.write_all(&entry.shell.format(entry, bash_timestamps)).map_err(|e| e.to_string())
+}
+
+/// Appends commands to their shells' history files
+pub struct ShellHistoryWriter {
+    settings: ShellHistorySettings,
+    queue: OnceLock<mpsc::Sender<Entry>>,
+}
+
+impl ShellHistoryWriter {
+    pub fn load() -> Self {
+        Self { settings: ShellHistorySettings::load(), queue: OnceLock::new() }
+    }
+
+    pub fn settings(&self) -> &ShellHistorySettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: ShellHistorySettings) -> Result<(), String> {
+        settings.validate()?;
+        settings.save()?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Queue a command a session ran for the history file of the session's shell
+    pub fn record(&self, shell: &str, environment: &HashMap<String, String>, command: &str, execution: &CommandExecution) {
+        if !self.settings.enabled || crate::system::demo::is_active() {
+            return;
+        }
+        if self.settings.only_successful && !execution.outcome.is_success() {
+            return;
+        }
+        let Some(history_shell) = HistoryShell::from_shell(shell) else { return };
+        let Some(file) = history_shell.history_file(environment) else { return };
+        let Some(command) = self.settings.redact(command) else { return };
+        let elapsed_secs = execution.duration_ms / 1000;
+        let entry = Entry {
+            shell: history_shell,
+            file,
+            command,
+            started: execution.timestamp.timestamp() - elapsed_secs as i64,
+            elapsed_secs,
+            bash_timestamps: environment.contains_key("HISTTIMEFORMAT"),
+        };
+        let queue = self.queue.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Entry>();
+            std::thread::spawn(move || {
+                for entry in receiver {
+                    if let Err(e) = append(&entry) {
+                        println!("⚠️ Failed to add a command to {}: {}", entry.file.display(), e);
+                    }
+                }
+            });
+            sender
+        });
+        let _ = queue.send(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(shell: HistoryShell, command: &str) -> Entry {
+        Entry {
+            shell,
+            file: PathBuf::new(),
+            command: command.to_string(),
+            started: 1_700_000_000,
+            elapsed_secs: 3,
+            bash_timestamps: false,
+        }
+    }
+
+    fn formatted(shell: HistoryShell, command: &str, bash_timestamps: bool) -> String {
+        String::from_utf8(shell.format(&entry(shell, command), bash_timestamps)).unwrap()
+    }
+
+    #[test]
+    fn redact_leaves_out_and_masks() {
+        let settings = ShellHistorySettings {
+            redactions: vec![r"acme-\d+".to_string()],
+            exclude: vec![r"^vault\b".to_string()],
+            ..ShellHistorySettings::default()
+        };
+        assert_eq!(settings.redact("ls -la").as_deref(), Some("ls -la"));
+        assert_eq!(settings.redact(" secret-command"), None);
+        assert_eq!(settings.redact("   "), None);
+        assert_eq!(settings.redact("vault login"), None);
+        assert_eq!(settings.redact("deploy acme-42 now").as_deref(), Some("deploy [redacted] now"));
+        assert_eq!(
+            settings.redact("curl -H 'Authorization: Bearer abc.def' https://x").as_deref(),
+            Some("curl -H 'Authorization: Bearer [redacted]' https://x")
+        );
+
+        let unmasked = ShellHistorySettings { mask_known_secrets: false, ..ShellHistorySettings::default() };
+        assert_eq!(unmasked.redact("export TOKEN=abc123").as_deref(), Some("export TOKEN=abc123"));
+    }
+
+    #[test]
+    fn redact_drops_everything_when_a_rule_is_invalid() {
+        let broken_exclusion = ShellHistorySettings { exclude: vec!["(".to_string()], ..ShellHistorySettings::default() };
+        assert_eq!(broken_exclusion.redact("ls"), None);
+        let broken_redaction = ShellHistorySettings { redactions: vec!["(".to_string()], ..ShellHistorySettings::default() };
+        assert_eq!(broken_redaction.redact("ls"), None);
+    }
+
+    #[test]
+    fn metafy_escapes_zsh_internal_bytes() {
+        assert_eq!(metafy("ls"), b"ls");
+        assert_eq!(metafy("a\0b"), [b'a', 0x83, 0x20, b'b']);
+        // ă is C4 83; 0x83 is Meta itself
+        assert_eq!(metafy("ă"), [0xC4, 0x83, 0xA3]);
+        // é is C3 A9, outside the range
+        assert_eq!(metafy("é"), "é".as_bytes());
+    }
+
+    #[test]
+    fn format_matches_each_shell() {
+        assert_eq!(formatted(HistoryShell::Bash, "ls -la", false), "ls -la\n");
+        assert_eq!(formatted(HistoryShell::Bash, "ls -la", true), "#1700000000\nls -la\n");
+        assert_eq!(formatted(HistoryShell::Zsh, "echo a\nb", false), ": 1700000000:3;echo a\\\nb\n");
+        assert_eq!(formatted(HistoryShell::Fish, "echo a\nb\\c", false), "- cmd: echo a\\nb\\\\c\n  when: 1700000000\n");
+        let newline = if cfg!(windows) { "\r\n" } else { "\n" };
+        assert_eq!(formatted(HistoryShell::PowerShell, "echo a\necho b", false), format!("echo a`{0}echo b{0}", newline));
+    }
+
+    #[test]
+    fn from_shell_reads_paths_and_extensions() {
+        assert_eq!(HistoryShell::from_shell("/bin/bash"), Some(HistoryShell::Bash));
+        assert_eq!(HistoryShell::from_shell("/usr/local/bin/zsh"), Some(HistoryShell::Zsh));
+        assert_eq!(HistoryShell::from_shell("C:\\Program Files\\PowerShell\\7\\pwsh.exe"), Some(HistoryShell::PowerShell));
+        assert_eq!(HistoryShell::from_shell("/bin/dash"), None);
+    }
+}
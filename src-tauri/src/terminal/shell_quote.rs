@@ -0,0 +1,338 @@
+// Quoting and splitting of command arguments
+// Commands built in code (cd into a folder, open a file, commit with a message)
+// must quote their arguments, or names with spaces, quotes or `$` break the
+// command or inject into it. Commands run by the terminal are split with POSIX
+// rules; the other shells' rules are for commands handed to that shell. A line
+// with pipes, redirection, chains, globs or variables is run by a shell whole.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShellKind {
+    /// sh, bash, zsh, dash, ksh, and the terminal's own command parser
+    #[default]
+    Posix,
+    Fish,
+    PowerShell,
+    Cmd,
+    Nushell,
+}
+
+impl ShellKind {
+    /// Shell family from a shell path or name such as `/bin/zsh` or `pwsh.exe`
+    pub fn from_shell(shell: &str) -> Self {
+        let name = shell.rsplit(['/', '\\']).next().unwrap_or(shell).to_lowercase();
+        let name = name.strip_suffix(".exe").unwrap_or(&name);
+        match name {
+            "fish" => ShellKind::Fish,
+            "pwsh" | "powershell" => ShellKind::PowerShell,
+            "cmd" => ShellKind::Cmd,
+            "nu" | "nushell" => ShellKind::Nushell,
+            _ => ShellKind::Posix,
+        }
+    }
+}
+
+/// Characters that never need quoting in any supported shell; `~` is left bare so home paths still expand
+fn is_plain(arg: &str) -> bool {
+    !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '@' | '+' | ',' | '=' | '~'))
+}
+
+/// Quote one argument so the given shell passes it through unchanged
+pub fn quote(arg: &str, shell: ShellKind) -> Cow<'_, str> {
+    if is_plain(arg) {
+        return Cow::Borrowed(arg);
+    }
+    Cow::Owned(match shell {
+        // Nothing is special inside single quotes; a quote closes, escapes and reopens
+        ShellKind::Posix => format!("'{}'", arg.replace('\'', r"'\''")),
+        ShellKind::Fish => format!("'{}'", arg.replace('\\', r"\\").replace('\'', r"\'")),
+        ShellKind::PowerShell => format!("'{}'", arg.replace('\'', "''")),
+        // Raw strings cannot contain their own delimiter, so pick one that does not occur
+        ShellKind::Nushell if !arg.contains('\'') => format!("'{}'", arg),
+        ShellKind::Nushell => {
+            let hashes = "#".repeat((1..).find(|n| !arg.contains(&format!("'{}", "#".repeat(*n)))).unwrap_or(1));
+            format!("r{}'{}'{}", hashes, arg, hashes)
+        }
+        // cmd.exe has no single quotes; double the quotes and keep `%` from expanding
+        ShellKind::Cmd => format!("\"{}\"", arg.replace('"', "\"\"").replace('%', "%%")),
+    })
+}
+
+/// A command line from a program and its arguments, each quoted as needed
+pub fn join<S: AsRef<str>>(args: &[S], shell: ShellKind) -> String {
+    args.iter().map(|arg| quote(arg.as_ref(), shell)).collect::<Vec<_>>().join(" ")
+}
+
+/// Quote for the terminal's own command parser
+pub fn quote_arg(arg: &str) -> Cow<'_, str> {
+    quote(arg, ShellKind::Posix)
+}
+
+/// Split a command line with POSIX rules: '...' is literal, "..." allows \" \\ \$ \`,
+/// and a backslash outside quotes escapes the next character
+pub fn split(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("Unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if matches!(c, '"' | '\\' | '$' | '`') => current.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("Unterminated double quote".to_string()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("Unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => current.push(c),
+                    None => current.push('\\'),
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    Ok(words)
+}
+
+/// What a command line asks of a shell beyond running one program with plain words
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShellFeatures {
+    /// Pipes, `&&`/`||`/`;` chains, redirection, background jobs, subshells and command substitution
+    pub operators: bool,
+    /// `$VAR`, globs such as `*.rs`, and `NAME=value` prefixes
+    pub expansions: bool,
+}
+
+impl ShellFeatures {
+    pub fn any(self) -> bool {
+        self.operators || self.expansions
+    }
+}
+
+/// Scan a command line with the quoting rules of `split`; `~` is left to the terminal's `cd`
+pub fn shell_features(command: &str) -> ShellFeatures {
+    let mut features = ShellFeatures::default();
+    let mut chars = command.chars().peekable();
+    let mut at_word_start = true;
+    let mut first_word = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if !at_word_start {
+                    first_word = false;
+                }
+                at_word_start = true;
+                continue;
+            }
+            '\'' => {
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            chars.next();
+                        }
+                        '`' => features.operators = true,
+                        '$' if chars.peek() == Some(&'(') => features.operators = true,
+                        '$' => features.expansions = true,
+                        _ => {}
+                    }
+                }
+            }
+            '\\' => {
+                chars.next();
+            }
+            '|' | '&' | ';' | '<' | '>' | '(' | ')' | '`' => features.operators = true,
+            '$' if chars.peek() == Some(&'(') => features.operators = true,
+            '$' | '*' | '?' | '[' => features.expansions = true,
+            '=' if first_word && !at_word_start => features.expansions = true,
+            _ => {}
+        }
+        at_word_start = false;
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AWKWARD: [&str; 10] = [
+        "plain",
+        "two words",
+        "it's",
+        "say \"hi\"",
+        "$HOME and `date` and $(id)",
+        "line one\nline two",
+        "back\\slash",
+        "50% off %PATH%",
+        "'#quoted#'",
+        "",
+    ];
+
+    /// Undo `quote` the way each shell reads a single quoted word
+    fn unquote(word: &str, shell: ShellKind) -> String {
+        if is_plain(word) {
+            return word.to_string();
+        }
+        match shell {
+            ShellKind::Posix => split(word).unwrap().concat(),
+            ShellKind::Fish => {
+                let inner = &word[1..word.len() - 1];
+                let mut out = String::new();
+                let mut chars = inner.chars();
+                while let Some(c) = chars.next() {
+                    match (c, chars.clone().next()) {
+                        ('\\', Some(next @ ('\\' | '\''))) => {
+                            out.push(next);
+                            chars.next();
+                        }
+                        _ => out.push(c),
+                    }
+                }
+                out
+            }
+            ShellKind::PowerShell => word[1..word.len() - 1].replace("''", "'"),
+            ShellKind::Nushell => match word.strip_prefix('r') {
+                Some(raw) => {
+                    let hashes = raw.chars().take_while(|c| *c == '#').count();
+                    raw[hashes + 1..raw.len() - hashes - 1].to_string()
+                }
+                None => word[1..word.len() - 1].to_string(),
+            },
+            ShellKind::Cmd => word[1..word.len() - 1].replace("\"\"", "\"").replace("%%", "%"),
+        }
+    }
+
+    #[test]
+    fn quote_round_trips_for_every_shell() {
+        for shell in [ShellKind::Posix, ShellKind::Fish, ShellKind::PowerShell, ShellKind::Cmd, ShellKind::Nushell] {
+            for arg in AWKWARD {
+                let quoted = quote(arg, shell);
+                assert_eq!(unquote(&quoted, shell), arg, "{:?} quoted {:?} as {}", shell, arg, quoted);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sh_reads_posix_quoting_back() {
+        for arg in AWKWARD.iter().filter(|arg| !arg.is_empty()) {
+            let script = format!("printf %s {}", quote(arg, ShellKind::Posix));
+            let output = std::process::Command::new("sh").arg("-c").arg(&script).output().unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), *arg, "sh -c {:?}", script);
+        }
+    }
+
+    #[test]
+    fn plain_arguments_are_left_alone() {
+        assert_eq!(quote("src/main.rs", ShellKind::Posix), "src/main.rs");
+        assert_eq!(quote("~/work", ShellKind::Posix), "~/work");
+        assert_eq!(quote("--name=value", ShellKind::Cmd), "--name=value");
+    }
+
+    #[test]
+    fn posix_quoting_stops_expansion() {
+        assert_eq!(quote("$HOME", ShellKind::Posix), "'$HOME'");
+        assert_eq!(quote("it's", ShellKind::Posix), r"'it'\''s'");
+        assert_eq!(quote("a\nb", ShellKind::Posix), "'a\nb'");
+    }
+
+    #[test]
+    fn join_then_split_gives_the_arguments_back() {
+        let args = ["git", "commit", "-m", "fix: it's \"done\" $now", "--", "a file.txt"];
+        assert_eq!(split(&join(&args, ShellKind::Posix)).unwrap(), args);
+    }
+
+    #[test]
+    fn split_follows_posix_quoting() {
+        assert_eq!(split(r#"echo 'a b' "c \"d\" \$e" f\ g"#).unwrap(), ["echo", "a b", "c \"d\" $e", "f g"]);
+        assert_eq!(split(r#"echo "keep \n""#).unwrap(), ["echo", "keep \\n"]);
+        assert_eq!(split("echo '' \"\"").unwrap(), ["echo", "", ""]);
+        assert_eq!(split("echo a\\\nb").unwrap(), ["echo", "ab"]);
+        assert!(split("echo 'open").is_err());
+        assert!(split("echo \"open").is_err());
+    }
+
+    #[test]
+    fn nushell_raw_strings_avoid_their_delimiter() {
+        assert_eq!(quote("it's", ShellKind::Nushell), "r#'it's'#");
+        assert_eq!(quote("'#x", ShellKind::Nushell), "r##''#x'##");
+        assert_eq!(quote("a b", ShellKind::Nushell), "'a b'");
+    }
+
+    #[test]
+    fn cmd_doubles_quotes_and_percent_signs() {
+        assert_eq!(quote("50% \"off\"", ShellKind::Cmd), "\"50%% \"\"off\"\"\"");
+        assert_eq!(quote("%PATH%", ShellKind::Cmd), "\"%%PATH%%\"");
+    }
+
+    #[test]
+    fn shell_is_detected_from_its_path() {
+        assert_eq!(ShellKind::from_shell("/usr/bin/fish"), ShellKind::Fish);
+        assert_eq!(ShellKind::from_shell("C:\\Program Files\\PowerShell\\7\\pwsh.exe"), ShellKind::PowerShell);
+        assert_eq!(ShellKind::from_shell("cmd.exe"), ShellKind::Cmd);
+        assert_eq!(ShellKind::from_shell("nu"), ShellKind::Nushell);
+        assert_eq!(ShellKind::from_shell("/bin/zsh"), ShellKind::Posix);
+    }
+
+    #[test]
+    fn features_ignore_quoted_operators() {
+        assert!(!shell_features("git commit -m 'a | b; c > d'").any());
+        assert!(shell_features("ls | wc -l").operators);
+        assert!(shell_features("echo \"$(id)\"").operators);
+        assert!(shell_features("echo $HOME").expansions);
+        assert!(shell_features("FOO=1 make").expansions);
+        assert!(!shell_features("make FOO=1").expansions);
+    }
+}
@@ -0,0 +1,381 @@
+// Installed shells and per-shell syntax
+// Finds the shells available on this machine (bash, zsh, fish, nushell, PowerShell
+// and friends) with their versions. A session's shell decides how environment
+// variables are set and removed: the terminal handles `export`, `set -gx`,
+// `$env.NAME = ...` and `$env:NAME = ...` itself, and commands generated in POSIX
+// syntax are rewritten for the session's shell before they run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+
+use super::shell_quote::{self, ShellKind};
+
+/// Shell names looked up on PATH, in the order they are listed
+const CANDIDATES: [&str; 10] = ["bash", "zsh", "fish", "nu", "pwsh", "powershell", "sh", "dash", "ksh", "cmd"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledShell {
+    pub name: String,
+    pub path: String,
+    pub kind: ShellKind,
+    pub version: Option<String>,
+    /// The login shell new sessions start with
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvChange {
+    Set { name: String, value: String },
+    Unset { name: String },
+}
+
+/// Shells on PATH and in /etc/shells; runs each one for its version, so call off the async runtime
+pub fn detect() -> Vec<InstalledShell> {
+    let path_var = std::env::var("PATH").unwrap_or_default();
+    let mut paths: Vec<PathBuf> = CANDIDATES
+        .iter()
+        .filter_map(|name| crate::system::path_inspector::find_all(name, &path_var).into_iter().next())
+        .collect();
+    if let Ok(listed) = std::fs::read_to_string("/etc/shells") {
+        paths.extend(
+            listed.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(PathBuf::from)
+                .filter(|path| path.is_file()),
+        );
+    }
+
+    // The same binary is often reachable through several paths (/bin and /usr/bin)
+    let mut seen = Vec::new();
+    paths.retain(|path| {
+        let real = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        let new = !seen.contains(&real);
+        seen.push(real);
+        new
+    });
+
+    let default_shell = super::launch_profiles::default_shell();
+    let default_real = std::fs::canonicalize(&default_shell).ok();
+    paths
+        .into_iter()
+        .map(|path| {
+            let path_string = path.to_string_lossy().to_string();
+            let is_default = path_string == default_shell
+                || (default_real.is_some() && std::fs::canonicalize(&path).ok() == default_real);
+            InstalledShell {
+                name: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+                kind: ShellKind::from_shell(&path_string),
+                version: version(&path),
+                path: path_string,
+                is_default,
+            }
+        })
+        .collect()
+}
+
+/// Version reported by `<shell> --version`; stdin is closed so a shell that ignores the flag exits
+fn version(shell: &Path) -> Option<String> {
+    let mut command = std::process::Command::new(shell);
+    match ShellKind::from_shell(&shell.to_string_lossy()) {
+        ShellKind::Cmd => command.args(["/c", "ver"]),
+        _ => command.arg("--version"),
+    };
+    let output = command
+        .stdin(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    version_in(&format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)))
+}
+
+/// First dotted version number in a `--version` banner, such as "GNU bash, version 5.2.15(1)-release",
+/// "zsh 5.9 (x86_64-apple-darwin23.0)" or "go version go1.22.0 linux/amd64"
+pub fn version_in(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']' | '"'))
+        .map(|word| word.trim_start_matches(|c: char| c.is_ascii_alphabetic()))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.'))
+        .map(|word| word.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect::<String>())
+        .map(|version| version.trim_end_matches('.').to_string())
+}
+
+impl ShellKind {
+    /// Command that sets an environment variable in this shell
+    pub fn set_env(self, name: &str, value: &str) -> String {
+        match self {
+            ShellKind::Posix => format!("export {}={}", name, shell_quote::quote(value, self)),
+            ShellKind::Fish => format!("set -gx {} {}", name, shell_quote::quote(value, self)),
+            // A bare word is a command call in nushell and PowerShell, so the value is always quoted
+            ShellKind::Nushell => format!("$env.{} = {}", name, always_quoted(value, self)),
+            ShellKind::PowerShell => format!("$env:{} = {}", name, always_quoted(value, self)),
+            ShellKind::Cmd => format!("set \"{}={}\"", name, value.replace('%', "%%")),
+        }
+    }
+
+    /// Command that removes an environment variable in this shell
+    pub fn unset_env(self, name: &str) -> String {
+        match self {
+            ShellKind::Posix => format!("unset {}", name),
+            ShellKind::Fish => format!("set -e {}", name),
+            ShellKind::Nushell => format!("hide-env {}", name),
+            ShellKind::PowerShell => format!("Remove-Item Env:{}", name),
+            ShellKind::Cmd => format!("set {}=", name),
+        }
+    }
+
+    /// One line on this shell's syntax, for the model's context
+    pub fn syntax_hint(self) -> &'static str {
+        match self {
+            ShellKind::Posix => "POSIX (export NAME=value, unset NAME)",
+            ShellKind::Fish => "fish (set -gx NAME value, set -e NAME)",
+            ShellKind::Nushell => "nushell ($env.NAME = \"value\", hide-env NAME)",
+            ShellKind::PowerShell => "PowerShell ($env:NAME = \"value\", Remove-Item Env:NAME)",
+            ShellKind::Cmd => "cmd.exe (set NAME=value)",
+        }
+    }
+
+    /// The terminal's built-in for a shell-specific spelling of it (`Set-Location` is `cd`)
+    pub fn builtin_name(self, command: &str) -> &str {
+        match self {
+            ShellKind::PowerShell => match command.to_lowercase().as_str() {
+                "set-location" | "sl" | "chdir" => "cd",
+                "get-location" | "gl" => "pwd",
+                "clear-host" | "cls" => "clear",
+                "get-history" | "h" => "history",
+                _ => command,
+            },
+            ShellKind::Cmd => match command.to_lowercase().as_str() {
+                "chdir" => "cd",
+                "cls" => "clear",
+                _ => command,
+            },
+            _ => command,
+        }
+    }
+
+    /// An environment change written in this shell's syntax. With `env`, variables in the
+    /// value are replaced by their values there, as the shell would (`PATH=$PATH:/opt/bin`);
+    /// without, they are kept as written
+    pub fn parse_env_change(self, command: &str, env: Option<&HashMap<String, String>>) -> Option<EnvChange> {
+        let command = command.trim();
+        match self {
+            ShellKind::Posix => {
+                if let Some(rest) = command.strip_prefix("export ") {
+                    let (name, value) = rest.trim().split_once('=')?;
+                    return set(name, &self.evaluate(value, env));
+                }
+                unset(command.strip_prefix("unset ")?)
+            }
+            ShellKind::Fish => {
+                let words = shell_quote::split(command).ok()?;
+                let (first, flags) = (words.first()?, words.get(1)?);
+                if first != "set" || !flags.starts_with('-') {
+                    return None;
+                }
+                if flags.contains('e') {
+                    return unset(words.get(2)?);
+                }
+                if !flags.contains('x') {
+                    return None;
+                }
+                let name = words.get(2)?;
+                let value = command[first.len()..].trim_start().strip_prefix(flags.as_str())?.trim_start().strip_prefix(name.as_str())?;
+                set(name, &self.evaluate(value, env))
+            }
+            ShellKind::Nushell => {
+                if let Some(rest) = command.strip_prefix("$env.") {
+                    let (name, value) = rest.split_once('=')?;
+                    return set(name, &self.evaluate(value, env));
+                }
+                unset(command.strip_prefix("hide-env ")?)
+            }
+            ShellKind::PowerShell => {
+                if let Some(rest) = strip_prefix_ignore_case(command, "$env:") {
+                    let (name, value) = rest.split_once('=')?;
+                    return set(name, &self.evaluate(value, env));
+                }
+                let rest = strip_prefix_ignore_case(command, "remove-item ")?;
+                let name = strip_prefix_ignore_case(rest.trim(), "env:")?;
+                unset(name.trim_start_matches('\\'))
+            }
+            ShellKind::Cmd => {
+                let rest = strip_prefix_ignore_case(command, "set ")?.trim().trim_matches('"');
+                let (name, value) = rest.split_once('=')?;
+                if value.is_empty() {
+                    unset(name)
+                } else {
+                    set(name, &self.evaluate(value, env))
+                }
+            }
+        }
+    }
+
+    /// An assigned value as the shell stores it: quotes removed and variables outside single
+    /// quotes replaced by their value in `env`, unset ones by nothing
+    fn evaluate(self, raw: &str, env: Option<&HashMap<String, String>>) -> String {
+        let mut value = String::new();
+        let mut chars = raw.trim().chars().peekable();
+        let mut quote = None;
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' | '"' if quote.is_none() && self != ShellKind::Cmd => quote = Some(c),
+                c if Some(c) == quote => quote = None,
+                '\\' if quote.is_none() && matches!(self, ShellKind::Posix | ShellKind::Fish) => value.extend(chars.next()),
+                '\\' if quote == Some('"') && self == ShellKind::Posix && chars.peek().is_some_and(|next| matches!(next, '$' | '`' | '"' | '\\')) => {
+                    value.extend(chars.next())
+                }
+                // Nushell only interpolates in `$"..."` strings, which are left as written
+                '$' if quote.is_none() || (quote == Some('"') && self != ShellKind::Nushell) => match self.variable(&mut chars) {
+                    Some((name, written)) => match env {
+                        Some(env) => value.push_str(env.get(&name).map_or("", String::as_str)),
+                        None => value.push_str(&written),
+                    },
+                    None => value.push('$'),
+                },
+                '%' if self == ShellKind::Cmd => {
+                    let name: String = chars.clone().take_while(|c| *c != '%').collect();
+                    let closed = chars.clone().nth(name.chars().count()) == Some('%');
+                    match env {
+                        Some(env) if closed && is_variable_name(&name) => {
+                            chars.nth(name.chars().count());
+                            // cmd looks variables up without regard to case
+                            let found = env.iter().find(|(key, _)| key.eq_ignore_ascii_case(&name));
+                            value.push_str(found.map_or("", |(_, value)| value.as_str()));
+                        }
+                        _ => value.push('%'),
+                    }
+                }
+                _ => value.push(c),
+            }
+        }
+        value
+    }
+
+    /// The variable a `$` starts in this shell (`$NAME`, `${NAME}`, `$env.NAME`, `$env:NAME`):
+    /// its name and how it was written, consumed from `chars`; `None` for anything else
+    fn variable(self, chars: &mut Peekable<Chars>) -> Option<(String, String)> {
+        let mut lookahead = chars.clone();
+        let prefix: String = match self {
+            ShellKind::Posix if lookahead.peek() == Some(&'{') => "{".to_string(),
+            ShellKind::Posix | ShellKind::Fish => String::new(),
+            ShellKind::Nushell | ShellKind::PowerShell => lookahead.clone().take(4).collect(),
+            ShellKind::Cmd => return None,
+        };
+        let expected = match self {
+            ShellKind::Nushell => prefix == "env.",
+            ShellKind::PowerShell => prefix.eq_ignore_ascii_case("env:"),
+            _ => true,
+        };
+        if !expected {
+            return None;
+        }
+        let braced = prefix == "{";
+        let skip = prefix.chars().count();
+        for _ in 0..skip {
+            lookahead.next();
+        }
+        let name: String = std::iter::from_fn(|| lookahead.next_if(|c| c.is_ascii_alphanumeric() || *c == '_')).collect();
+        if !is_variable_name(&name) || (braced && lookahead.next() != Some('}')) {
+            return None;
+        }
+        let written = if braced { format!("${{{}}}", name) } else { format!("${}{}", prefix, name) };
+        *chars = lookahead;
+        Some((name, written))
+    }
+
+    /// Program and arguments that have `shell` run one command line and exit
+    pub fn invocation(self, shell: &str, line: &str) -> (String, Vec<String>) {
+        let flags: &[&str] = match self {
+            ShellKind::Posix | ShellKind::Fish | ShellKind::Nushell => &["-c"],
+            ShellKind::PowerShell => &["-NoProfile", "-Command"],
+            ShellKind::Cmd => &["/C"],
+        };
+        let args = flags.iter().map(|flag| flag.to_string()).chain(std::iter::once(line.to_string())).collect();
+        (shell.to_string(), args)
+    }
+
+    /// Rewrite a POSIX `export`/`unset` line, as generated commands are written, for this shell
+    pub fn adapt(self, command: &str) -> String {
+        if self == ShellKind::Posix {
+            return command.to_string();
+        }
+        match ShellKind::Posix.parse_env_change(command, None) {
+            Some(EnvChange::Set { name, value }) => self.set_env(&name, &value),
+            Some(EnvChange::Unset { name }) => self.unset_env(&name),
+            None => command.to_string(),
+        }
+    }
+}
+
+fn always_quoted(value: &str, shell: ShellKind) -> String {
+    let quoted = shell_quote::quote(value, shell);
+    if quoted == value {
+        format!("'{}'", value)
+    } else {
+        quoted.into_owned()
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn set(name: &str, value: &str) -> Option<EnvChange> {
+    let name = name.trim();
+    is_variable_name(name).then(|| EnvChange::Set { name: name.to_string(), value: value.to_string() })
+}
+
+fn unset(name: &str) -> Option<EnvChange> {
+    let name = name.trim();
+    is_variable_name(name).then(|| EnvChange::Unset { name: name.to_string() })
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> HashMap<String, String> {
+        HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string()), ("HOME".to_string(), "/home/me".to_string())])
+    }
+
+    fn set_to(shell: ShellKind, command: &str) -> String {
+        match shell.parse_env_change(command, Some(&env())) {
+            Some(EnvChange::Set { value, .. }) => value,
+            other => panic!("{:?} for {}", other, command),
+        }
+    }
+
+    #[test]
+    fn variables_are_expanded_against_the_session() {
+        assert_eq!(set_to(ShellKind::Posix, "export PATH=$PATH:/opt/bin"), "/usr/bin:/bin:/opt/bin");
+        assert_eq!(set_to(ShellKind::Posix, r#"export PATH="${HOME}/bin:$PATH""#), "/home/me/bin:/usr/bin:/bin");
+        assert_eq!(set_to(ShellKind::Fish, "set -gx PATH $HOME/bin $PATH"), "/home/me/bin /usr/bin:/bin");
+        assert_eq!(set_to(ShellKind::Nushell, "$env.EDITOR = $env.HOME"), "/home/me");
+        assert_eq!(set_to(ShellKind::PowerShell, r#"$env:PATH = "$env:PATH;C:\tools""#), r"/usr/bin:/bin;C:\tools");
+        assert_eq!(set_to(ShellKind::Cmd, "set PATH=%path%;C:\\tools"), r"/usr/bin:/bin;C:\tools");
+    }
+
+    #[test]
+    fn quoting_is_respected() {
+        assert_eq!(set_to(ShellKind::Posix, "export GREETING='$HOME stays'"), "$HOME stays");
+        assert_eq!(set_to(ShellKind::Posix, r#"export PRICE="\$5 at $HOME""#), "$5 at /home/me");
+        assert_eq!(set_to(ShellKind::Posix, "export EMPTY=$MISSING"), "");
+        assert_eq!(set_to(ShellKind::Posix, "export COST=$5"), "$5");
+        assert_eq!(set_to(ShellKind::Nushell, r#"$env.LITERAL = "$env.HOME""#), "$env.HOME");
+    }
+
+    #[test]
+    fn without_an_environment_variables_are_kept() {
+        let change = ShellKind::Posix.parse_env_change("export PATH=\"$PATH:/opt/bin\"", None);
+        assert!(matches!(change, Some(EnvChange::Set { value, .. }) if value == "$PATH:/opt/bin"));
+    }
+}
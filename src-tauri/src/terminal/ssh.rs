@@ -0,0 +1,204 @@
+// SSH sessions
+// A session can be a terminal on another machine. The system's ssh client runs
+// on the session's pty and starts the remote login shell, which gets the same
+// status hooks as a local one, so commands, live output, resizing and signals
+// go through the connection exactly as they would locally. Only key and agent
+// authentication are used (BatchMode): a password prompt nobody sees would
+// hang the session. Commands of an SSH session never fall back to running on
+// this machine; when the connection is down they fail instead. Host keys are
+// checked strictly: a host not yet in known_hosts is only added once the user
+// has seen its fingerprint and confirmed it.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+/// Remote shell started when the config names none; it has status hooks on most hosts
+const DEFAULT_REMOTE_SHELL: &str = "bash";
+/// Connecting and logging in take longer than starting a local shell
+pub const STARTUP_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SshAuth {
+    /// Keys held by the running ssh-agent
+    Agent,
+    /// A private key file; its passphrase, if any, must be in the agent
+    Key { path: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SshSessionConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// The ssh config's or the local user name when not set
+    #[serde(default)]
+    pub user: Option<String>,
+    pub auth: SshAuth,
+    /// bash, zsh or fish on the remote machine
+    #[serde(default)]
+    pub remote_shell: Option<String>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+/// A key the host offers, for the user to compare before the first connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostKey {
+    /// `ssh-ed25519`, `ecdsa-sha2-nistp256`, …
+    pub key_type: String,
+    /// As ssh prints it: `SHA256:` and the unpadded base64 digest of the key
+    pub fingerprint: String,
+    /// The known_hosts line that trusts this key
+    #[serde(skip)]
+    known_hosts_line: String,
+}
+
+impl HostKey {
+    /// A line of `ssh-keyscan` output: `host key-type base64-key`
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let (_, key_type, key) = (fields.next()?, fields.next()?, fields.next()?);
+        let blob = base64::engine::general_purpose::STANDARD.decode(key).ok()?;
+        let digest = base64::engine::general_purpose::STANDARD_NO_PAD.encode(Sha256::digest(&blob));
+        Some(Self { key_type: key_type.to_string(), fingerprint: format!("SHA256:{}", digest), known_hosts_line: line.trim().to_string() })
+    }
+}
+
+impl SshSessionConfig {
+    /// `user@host`, or the host alone
+    pub fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// What the session shows: `user@host`, with the port when it is not 22
+    pub fn label(&self) -> String {
+        match self.port {
+            22 => self.destination(),
+            port => format!("{}:{}", self.destination(), port),
+        }
+    }
+
+    pub fn remote_shell(&self) -> &str {
+        self.remote_shell.as_deref().unwrap_or(DEFAULT_REMOTE_SHELL)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let plain = |value: &str| !value.is_empty() && !value.starts_with('-') && !value.chars().any(|c| c.is_whitespace() || c == '@');
+        if !plain(&self.host) {
+            return Err(format!("'{}' is not a host name", self.host));
+        }
+        if self.user.as_deref().is_some_and(|user| !plain(user)) {
+            return Err("The user name cannot contain spaces or '@' or start with '-'".to_string());
+        }
+        if let SshAuth::Key { path } = &self.auth {
+            if !Path::new(path).is_file() {
+                return Err(format!("Key file {} not found", path));
+            }
+        }
+        let shell = self.remote_shell();
+        if !shell.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-')) {
+            return Err(format!("'{}' is not a shell", shell));
+        }
+        Ok(())
+    }
+
+    /// How known_hosts names the host: `host`, or `[host]:port` off port 22
+    fn known_hosts_name(&self) -> String {
+        match self.port {
+            22 => self.host.clone(),
+            port => format!("[{}]:{}", self.host, port),
+        }
+    }
+
+    /// Whether the user's known_hosts already has a key for the host
+    pub fn is_known_host(&self) -> bool {
+        Command::new("ssh-keygen")
+            .args(["-F", &self.known_hosts_name()])
+            .output()
+            .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+    }
+
+    /// The keys the host offers, asked for without logging in
+    pub fn scan_host_keys(&self) -> Result<Vec<HostKey>, String> {
+        let output = Command::new("ssh-keyscan")
+            .args(["-T", "10", "-p", &self.port.to_string(), &self.host])
+            .output()
+            .map_err(|e| format!("Cannot run ssh-keyscan: {}", e))?;
+        let keys: Vec<HostKey> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(HostKey::parse)
+            .collect();
+        if keys.is_empty() {
+            return Err(format!("{} did not answer with a host key", self.label()));
+        }
+        Ok(keys)
+    }
+
+    /// Add the host's key with this fingerprint to known_hosts, after the user confirmed it;
+    /// the key is scanned again, so a key that changed in the meantime is refused
+    pub fn trust_host_key(&self, fingerprint: &str) -> Result<(), String> {
+        let key = self
+            .scan_host_keys()?
+            .into_iter()
+            .find(|key| key.fingerprint == fingerprint.trim())
+            .ok_or_else(|| format!("{} no longer offers the key {}; check its fingerprint again", self.label(), fingerprint))?;
+        let ssh_dir = dirs::home_dir().ok_or("No home directory for known_hosts")?.join(".ssh");
+        if !ssh_dir.exists() {
+            std::fs::create_dir_all(&ssh_dir).map_err(|e| format!("Cannot create {}: {}", ssh_dir.display(), e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = std::fs::set_permissions(&ssh_dir, std::fs::Permissions::from_mode(0o700));
+            }
+        }
+        let known_hosts = ssh_dir.join("known_hosts");
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&known_hosts)
+            .map_err(|e| format!("Cannot open {}: {}", known_hosts.display(), e))?;
+        writeln!(file, "{}", key.known_hosts_line).map_err(|e| format!("Cannot write {}: {}", known_hosts.display(), e))
+    }
+
+    /// Arguments of the ssh client that connects and starts the remote login shell
+    pub fn client_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "-tt".into(),
+            "-p".into(),
+            self.port.to_string(),
+            "-o".into(),
+            "BatchMode=yes".into(),
+            "-o".into(),
+            "StrictHostKeyChecking=yes".into(),
+            "-o".into(),
+            "ServerAliveInterval=30".into(),
+        ];
+        if let SshAuth::Key { path } = &self.auth {
+            args.extend(["-i".into(), path.clone(), "-o".into(), "IdentitiesOnly=yes".into()]);
+        }
+        args.push("--".into());
+        args.push(self.destination());
+        args.push(format!("exec {} -l", self.remote_shell()));
+        args
+    }
+}
+
+/// The ssh client on PATH
+pub fn client() -> Result<String, String> {
+    let name = if cfg!(windows) { "ssh.exe" } else { "ssh" };
+    std::env::var_os("PATH")
+        .and_then(|path| std::env::split_paths(&path).map(|dir| dir.join(name)).find(|candidate| candidate.is_file()))
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or_else(|| "No ssh client found on PATH".to_string())
+}
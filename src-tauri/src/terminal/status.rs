@@ -0,0 +1,116 @@
+// Per-session status for the prompt and status bar
+// Kept outside the terminal manager so it stays readable while a command holds
+// the manager lock. Every change is published as a `session-status` event.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+pub const STATUS_EVENT: &str = "session-status";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitSummary {
+    pub branch: String,
+    pub dirty: bool,
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionStatus {
+    pub session_id: String,
+    pub last_exit_code: Option<i32>,
+    pub last_duration_ms: Option<u64>,
+    /// Program of the foreground command while one runs
+    pub running_process: Option<String>,
+    pub working_directory: String,
+    pub git: Option<GitSummary>,
+    /// Parallel runs and other work started in the session that is still going
+    pub background_jobs: usize,
+}
+
+impl SessionStatus {
+    fn new(session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            last_exit_code: None,
+            last_duration_ms: None,
+            running_process: None,
+            working_directory: String::new(),
+            git: None,
+            background_jobs: 0,
+        }
+    }
+
+    /// Record the working directory with its git summary, from `load_git_summary`
+    pub fn set_working_directory(&mut self, working_directory: &str, git: Option<GitSummary>) {
+        self.working_directory = working_directory.to_string();
+        self.git = git;
+    }
+}
+
+/// `git_summary` on the blocking pool, so no caller waits on git while holding a lock
+pub async fn load_git_summary(working_directory: &str) -> Option<GitSummary> {
+    let working_directory = working_directory.to_string();
+    tokio::task::spawn_blocking(move || git_summary(&working_directory)).await.ok().flatten()
+}
+
+/// Branch, dirtiness and upstream distance from a single `git status` call; git is not run
+/// at all outside a repository
+pub fn git_summary(working_directory: &str) -> Option<GitSummary> {
+    if !std::path::Path::new(working_directory).ancestors().any(|dir| dir.join(".git").exists()) {
+        return None;
+    }
+    let output = Command::new("git")
+        .args(["status", "--porcelain=v2", "--branch"])
+        .current_dir(working_directory)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let mut summary = GitSummary { branch: String::new(), dirty: false, ahead: 0, behind: 0 };
+    for line in stdout.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            summary.branch = head.to_string();
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            for part in ab.split_whitespace() {
+                if let Some(ahead) = part.strip_prefix('+') {
+                    summary.ahead = ahead.parse().unwrap_or(0);
+                } else if let Some(behind) = part.strip_prefix('-') {
+                    summary.behind = behind.parse().unwrap_or(0);
+                }
+            }
+        } else if !line.starts_with('#') {
+            summary.dirty = true;
+        }
+    }
+    Some(summary)
+}
+
+#[derive(Debug, Default)]
+pub struct SessionStatusBoard {
+    statuses: HashMap<String, SessionStatus>,
+}
+
+impl SessionStatusBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<&SessionStatus> {
+        self.statuses.get(session_id)
+    }
+
+    /// Apply a change; returns the new status only when something changed
+    pub fn update(&mut self, session_id: &str, change: impl FnOnce(&mut SessionStatus)) -> Option<SessionStatus> {
+        let status = self.statuses.entry(session_id.to_string()).or_insert_with(|| SessionStatus::new(session_id));
+        let before = status.clone();
+        change(status);
+        (*status != before).then(|| status.clone())
+    }
+
+    pub fn remove(&mut self, session_id: &str) {
+        self.statuses.remove(session_id);
+    }
+}
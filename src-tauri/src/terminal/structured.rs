@@ -0,0 +1,213 @@
+// Structured command output
+// Once turned on in the settings (it changes the commands that run, so it is
+// off by default), the terminal asks for JSON where a command can print it:
+// known commands get their JSON flag (`npm ls --json`, `kubectl get -o json`),
+// other commands are checked once, in the background, for a plain `--json`
+// flag in their man page, and in a nushell session commands run through nu with
+// tables converted to JSON. The parsed output is attached to the execution as
+// a table for the renderer and can be queried with jq-style paths.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::tools::data_preview::{DataColumn, DataTable};
+
+/// Rows kept in the attached table; the full data stays in the output
+const MAX_TABLE_ROWS: usize = 1000;
+
+/// Command topics whose JSON mode needs more than a bare `--json`
+const KNOWN_FORMATS: [(&str, &[&str]); 10] = [
+    ("docker ps", &["--format", "json"]),
+    ("docker images", &["--format", "json"]),
+    ("kubectl get", &["-o", "json"]),
+    ("cargo metadata", &["--format-version", "1"]),
+    ("pip list", &["--format", "json"]),
+    ("pip3 list", &["--format", "json"]),
+    ("lsblk", &["-J"]),
+    ("npm ls", &["--json"]),
+    ("npm outdated", &["--json"]),
+    ("npm audit", &["--json"]),
+];
+
+/// Arguments that already pick an output format, left as the user wrote them
+const FORMAT_FLAGS: [&str; 6] = ["--json", "-j", "-J", "--format", "-o", "--output"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredOutput {
+    /// How the JSON was obtained: the flags added, or `nushell`
+    pub source: String,
+    pub table: Option<DataTable>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StructuredSettings {
+    pub enabled: bool,
+}
+
+/// The settings and what each command was found to support
+#[derive(Debug, Default)]
+pub struct StructuredCapabilities {
+    settings: StructuredSettings,
+    /// Flags that make the command print JSON; `None` when it cannot or is still being checked
+    detected: Arc<Mutex<HashMap<String, Option<Vec<String>>>>>,
+}
+
+impl StructuredCapabilities {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("structured_output.json")
+    }
+
+    pub fn load() -> Self {
+        let settings = std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self { settings, detected: Arc::default() }
+    }
+
+    pub fn settings(&self) -> &StructuredSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: StructuredSettings) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Flags to add for JSON output. A command seen for the first time runs as written
+    /// while its help is checked in the background, so nothing waits on the check
+    pub fn json_flags(&self, cmd: &str, args: &[&str], path_var: &str) -> Option<Vec<String>> {
+        if !self.settings.enabled || args.iter().any(|arg| picks_format(arg)) {
+            return None;
+        }
+        let line = std::iter::once(cmd).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+        let topic = crate::tools::help_docs::help_topic(&line).join(" ");
+        if let Some((_, flags)) = KNOWN_FORMATS.iter().find(|(known, _)| *known == topic) {
+            return Some(flags.iter().map(|flag| flag.to_string()).collect());
+        }
+        // Detection looks at the command's own help, so `cat a` and `cat b` share one lookup
+        let mut detected = self.detected.lock().unwrap();
+        if let Some(cached) = detected.get(cmd) {
+            return cached.clone();
+        }
+        detected.insert(cmd.to_string(), None);
+        let (cache, cmd, path_var) = (self.detected.clone(), cmd.to_string(), path_var.to_string());
+        tokio::spawn(async move {
+            // Only the man page: running the program's `--help` is left to the user asking for help
+            let flags = crate::tools::help_docs::get_help(&cmd, &path_var, false)
+                .await
+                .ok()
+                .filter(|doc| doc.flags.iter().any(|flag| flag.argument.is_none() && flag.names.iter().any(|name| name == "--json")))
+                .map(|_| vec!["--json".to_string()]);
+            cache.lock().unwrap().insert(cmd, flags);
+        });
+        None
+    }
+}
+
+fn picks_format(arg: &str) -> bool {
+    FORMAT_FLAGS.iter().any(|flag| arg == *flag || arg.starts_with(&format!("{}=", flag)))
+        || arg.starts_with("--json")
+        || arg.starts_with("-ojson")
+}
+
+/// The nushell invocation for a command: tables, lists and records come back as JSON, anything else as is
+pub fn nushell_wrapper(command: &str) -> Vec<String> {
+    vec![
+        "-c".to_string(),
+        format!(
+            "let out = ({}); if (($out | describe) =~ '^(table|list|record)') {{ $out | to json --raw }} else {{ $out }}",
+            command
+        ),
+    ]
+}
+
+/// JSON or newline-delimited JSON output, as one value
+pub fn parse(output: &str) -> Option<Value> {
+    let trimmed = output.trim();
+    if !trimmed.starts_with(['[', '{']) {
+        return None;
+    }
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+    // `docker ps --format json` prints one object per line
+    trimmed
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).ok())
+        .collect::<Option<Vec<Value>>>()
+        .map(Value::Array)
+}
+
+/// Parse the output and lay it out as a table when it has that shape
+pub fn attach(output: &str, source: &str) -> Option<StructuredOutput> {
+    let value = parse(output)?;
+    Some(StructuredOutput { source: source.to_string(), table: to_table(&value) })
+}
+
+/// Lists of records become rows, a list of values one column, and a flat record key/value rows
+pub fn to_table(value: &Value) -> Option<DataTable> {
+    match value {
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+            let mut names: Vec<String> = Vec::new();
+            for item in items.iter().take(MAX_TABLE_ROWS) {
+                for key in item.as_object().into_iter().flat_map(Map::keys) {
+                    if !names.contains(key) {
+                        names.push(key.clone());
+                    }
+                }
+            }
+            let rows: Vec<Vec<Value>> = items
+                .iter()
+                .take(MAX_TABLE_ROWS)
+                .map(|item| names.iter().map(|name| item.get(name).cloned().unwrap_or(Value::Null)).collect())
+                .collect();
+            Some(build(names, rows, items.len()))
+        }
+        Value::Array(items) if !items.is_empty() => {
+            let rows = items.iter().take(MAX_TABLE_ROWS).map(|item| vec![item.clone()]).collect();
+            Some(build(vec!["value".to_string()], rows, items.len()))
+        }
+        Value::Object(record) if !record.is_empty() && record.values().all(|v| !v.is_object() && !v.is_array()) => {
+            let rows = record.iter().map(|(key, value)| vec![Value::String(key.clone()), value.clone()]).collect();
+            Some(build(vec!["key".to_string(), "value".to_string()], rows, record.len()))
+        }
+        // Wrapped lists, as in `kubectl get -o json` ({"items": [...]})
+        Value::Object(record) => record.get("items").and_then(to_table),
+        _ => None,
+    }
+}
+
+fn build(names: Vec<String>, rows: Vec<Vec<Value>>, total_rows: usize) -> DataTable {
+    let columns = names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| DataColumn {
+            dtype: rows.iter().map(|row| &row[index]).find(|value| !value.is_null()).map(dtype).unwrap_or("null").to_string(),
+            name,
+        })
+        .collect();
+    DataTable { columns, truncated: total_rows > rows.len(), rows, total_rows: Some(total_rows), exported_to: None }
+}
+
+fn dtype(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(number) if number.is_f64() => "f64",
+        Value::Number(_) => "i64",
+        Value::String(_) => "str",
+        Value::Array(_) => "list",
+        Value::Object(_) => "struct",
+    }
+}
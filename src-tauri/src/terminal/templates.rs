@@ -0,0 +1,283 @@
+// Template variables in commands
+// Expands `{{name}}` / `{{name:arg}}` placeholders before a command runs.
+// `\{{` keeps the braces literally. Values are quoted for the session's shell,
+// taking into account quotes the template already opened around them, so a
+// branch name or clipboard text is one argument and never more shell. Secrets
+// are substituted into the command but masked in the preview shown to the user
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::process::Command;
+
+use super::shell_quote::{self, ShellKind};
+use crate::integrations::secrets::SecretsStore;
+
+const MASK: &str = "••••••";
+
+/// Placeholders filled from this machine, which say nothing about the host of an SSH session
+const LOCAL_SOURCES: [&str; 7] = ["branch", "commit", "repo", "cwd", "home", "user", "env"];
+
+/// Everything a placeholder may draw from, captured from the session
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub working_directory: String,
+    pub environment: HashMap<String, String>,
+    pub last_command: Option<String>,
+    pub last_output: Option<String>,
+    pub last_exit_code: Option<i32>,
+    /// Values bound with `capture NAME from "..."`
+    pub variables: HashMap<String, String>,
+    /// An SSH session, where placeholders that read this machine are left unresolved
+    pub remote: bool,
+    /// Shell the expanded command is written for
+    pub shell: ShellKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedVariable {
+    pub placeholder: String,
+    /// Value as shown in previews (secrets masked)
+    pub display_value: String,
+    pub secret: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateExpansion {
+    pub original: String,
+    /// Command that will actually run
+    #[serde(skip_serializing, default)]
+    pub command: String,
+    /// Command with secrets masked, safe to show before running
+    pub preview: String,
+    pub variables: Vec<ResolvedVariable>,
+    pub unresolved: Vec<String>,
+}
+
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+fn split_template(template: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("\\{{") {
+            text.push_str("{{");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{{") {
+            match after.find("}}") {
+                Some(end) => {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Placeholder(after[..end].trim().to_string()));
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    text.push_str(rest);
+                    rest = "";
+                }
+            }
+        } else {
+            let mut chars = rest.chars();
+            text.push(chars.next().unwrap());
+            rest = chars.as_str();
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    segments
+}
+
+async fn command_output(program: &str, args: &[&str], dir: &str) -> Option<String> {
+    let mut command = Command::new(program);
+    command.args(args).stdin(std::process::Stdio::null()).kill_on_drop(true);
+    if !dir.is_empty() {
+        command.current_dir(dir);
+    }
+    let output = tokio::time::timeout(Duration::from_secs(3), command.output()).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim_end_matches(['\n', '\r']).to_string())
+}
+
+async fn read_clipboard() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        command_output("pbpaste", &[], "").await
+    } else if cfg!(windows) {
+        command_output("powershell", &["-NoProfile", "-Command", "Get-Clipboard"], "").await
+    } else if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        command_output("wl-paste", &["--no-newline"], "").await
+    } else {
+        match command_output("xclip", &["-o", "-selection", "clipboard"], "").await {
+            Some(text) => Some(text),
+            None => command_output("xsel", &["--clipboard", "--output"], "").await,
+        }
+    }
+}
+
+/// Resolve one placeholder; `None` means the name is unknown or has no value
+async fn resolve(name: &str, arg: Option<&str>, ctx: &TemplateContext) -> Option<(String, bool)> {
+    if ctx.remote && LOCAL_SOURCES.contains(&name) {
+        return None;
+    }
+    let now = chrono::Local::now();
+    let value = match (name, arg) {
+        ("date", None) => now.format("%Y-%m-%d").to_string(),
+        ("date", Some(format)) => now.format(format).to_string(),
+        ("time", None) => now.format("%H:%M:%S").to_string(),
+        ("datetime" | "now", None) => now.to_rfc3339(),
+        ("timestamp", None) => now.timestamp().to_string(),
+        ("branch", None) => command_output("git", &["rev-parse", "--abbrev-ref", "HEAD"], &ctx.working_directory).await?,
+        ("commit", None) => command_output("git", &["rev-parse", "--short", "HEAD"], &ctx.working_directory).await?,
+        ("repo", None) => {
+            let root = command_output("git", &["rev-parse", "--show-toplevel"], &ctx.working_directory).await?;
+            std::path::Path::new(&root).file_name()?.to_string_lossy().to_string()
+        }
+        ("cwd", None) => ctx.working_directory.clone(),
+        ("home", None) => dirs::home_dir()?.to_string_lossy().to_string(),
+        ("user", None) => ctx.environment.get("USER").or_else(|| ctx.environment.get("USERNAME"))?.clone(),
+        ("last_output", None) => ctx.last_output.as_ref()?.trim().to_string(),
+        ("last_command", None) => ctx.last_command.clone()?,
+        ("last_exit_code", None) => ctx.last_exit_code?.to_string(),
+        ("clipboard", None) => read_clipboard().await?,
+        ("env", Some(var)) => ctx.environment.get(var)?.clone(),
+        (name, None) if ctx.variables.contains_key(name) => ctx.variables[name].clone(),
+        ("secret", Some(key)) => return SecretsStore::load().get(&secret_key(key)).map(|v| (v.to_string(), true)),
+        _ => return None,
+    };
+    Some((value, false))
+}
+
+/// Secrets store key for a `{{secret:NAME}}` placeholder
+pub fn secret_key(name: &str) -> String {
+    format!("secret:{}", name)
+}
+
+/// The quote left open at the end of `text`, read with the shell's quoting rules
+fn open_quote(text: &str, shell: ShellKind) -> Option<char> {
+    let escape = match shell {
+        ShellKind::PowerShell => '`',
+        ShellKind::Cmd => '^',
+        _ => '\\',
+    };
+    let mut open = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let escapes = match open {
+            None => shell != ShellKind::Nushell,
+            Some('"') => shell != ShellKind::Cmd,
+            Some(_) => shell == ShellKind::Fish,
+        };
+        if escapes && c == escape {
+            chars.next();
+            continue;
+        }
+        match open {
+            None if c == '"' || (c == '\'' && shell != ShellKind::Cmd) => open = Some(c),
+            Some(quote) if c == quote => open = None,
+            _ => {}
+        }
+    }
+    open
+}
+
+/// `value` written so the shell reads it back unchanged where `open` is the quote open at
+/// that point; `None` when the shell has no way to write it there
+fn place(value: &str, shell: ShellKind, open: Option<char>) -> Option<String> {
+    let escaped = |special: &[char], escape: char| {
+        value.chars().fold(String::new(), |mut out, c| {
+            if special.contains(&c) {
+                out.push(escape);
+            }
+            out.push(c);
+            out
+        })
+    };
+    match (shell, open) {
+        (_, None) => Some(shell_quote::quote(value, shell).into_owned()),
+        // Close the quote, add the value quoted on its own and reopen; the shell joins the pieces
+        (ShellKind::Posix | ShellKind::Fish, Some(quote)) => Some(format!("{}{}{}", quote, shell_quote::quote(value, shell), quote)),
+        (ShellKind::PowerShell, Some('\'')) => Some(value.replace('\'', "''")),
+        (ShellKind::PowerShell, Some(_)) => Some(escaped(&['`', '"', '$'], '`')),
+        (ShellKind::Nushell, Some('"')) => Some(escaped(&['\\', '"'], '\\')),
+        (ShellKind::Cmd, Some(_)) if !value.contains('"') => Some(value.replace('%', "%%")),
+        _ if !value.contains(open?) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Expand all placeholders in a command, snippet or agent step
+pub async fn expand(template: &str, ctx: &TemplateContext) -> TemplateExpansion {
+    let mut command = String::new();
+    let mut preview = String::new();
+    let mut variables = Vec::new();
+    let mut unresolved = Vec::new();
+    let mut resolved: HashMap<String, Option<(String, bool)>> = HashMap::new();
+
+    for segment in split_template(template) {
+        let placeholder = match segment {
+            Segment::Text(text) => {
+                command.push_str(&text);
+                preview.push_str(&text);
+                continue;
+            }
+            Segment::Placeholder(placeholder) => placeholder,
+        };
+
+        if !resolved.contains_key(&placeholder) {
+            let (name, arg) = match placeholder.split_once(':') {
+                Some((name, arg)) => (name.trim(), Some(arg.trim())),
+                None => (placeholder.as_str(), None),
+            };
+            let value = resolve(name, arg, ctx).await;
+            match &value {
+                Some((value, secret)) => variables.push(ResolvedVariable {
+                    placeholder: placeholder.clone(),
+                    display_value: if *secret { MASK.to_string() } else { value.clone() },
+                    secret: *secret,
+                }),
+                None => unresolved.push(placeholder.clone()),
+            }
+            resolved.insert(placeholder.clone(), value);
+        }
+
+        let open = open_quote(&command, ctx.shell);
+        match &resolved[&placeholder] {
+            Some((value, secret)) => match place(value, ctx.shell, open) {
+                Some(placed) => {
+                    command.push_str(&placed);
+                    preview.push_str(&if *secret { place(MASK, ctx.shell, open).unwrap_or_default() } else { placed });
+                }
+                // A value the shell cannot take where the placeholder stands is not substituted
+                None => {
+                    if !unresolved.contains(&placeholder) {
+                        unresolved.push(placeholder.clone());
+                    }
+                    let literal = format!("{{{{{}}}}}", placeholder);
+                    command.push_str(&literal);
+                    preview.push_str(&literal);
+                }
+            },
+            None => {
+                let literal = format!("{{{{{}}}}}", placeholder);
+                command.push_str(&literal);
+                preview.push_str(&literal);
+            }
+        }
+    }
+
+    TemplateExpansion {
+        original: template.to_string(),
+        command,
+        preview,
+        variables,
+        unresolved,
+    }
+}
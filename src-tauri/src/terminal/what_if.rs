@@ -0,0 +1,462 @@
+// What-if simulation of file-changing commands
+// For rm, mv, cp, rsync, chmod and `find ... -delete`, expands globs and walks
+// the directories involved to list exactly which paths the command would delete,
+// move, create, overwrite or change, without touching anything. The result has
+// exact counts and a sample of paths for the confirmation dialog, and is what
+// `what-if <command>` prints.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::shell_quote;
+
+/// Paths listed individually; the rest are only counted
+const SAMPLE_SIZE: usize = 50;
+/// Entries visited before the simulation stops counting
+const MAX_VISITED: usize = 200_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PathAction {
+    Delete,
+    Move { to: String },
+    Create,
+    Overwrite,
+    Update,
+    ChangeMode { from: String, to: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AffectedPath {
+    pub path: String,
+    pub is_dir: bool,
+    pub action: PathAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    pub command: String,
+    pub program: String,
+    /// Paths the command would change
+    pub affected: usize,
+    pub files: usize,
+    pub directories: usize,
+    /// Size of the affected files
+    pub bytes: u64,
+    pub sample: Vec<AffectedPath>,
+    /// Arguments the command would fail or do nothing on, with the reason
+    pub skipped: Vec<String>,
+    /// Counting stopped early; the real numbers are larger
+    pub truncated: bool,
+}
+
+impl Simulation {
+    fn new(command: &str, program: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            program: program.to_string(),
+            affected: 0,
+            files: 0,
+            directories: 0,
+            bytes: 0,
+            sample: Vec::new(),
+            skipped: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    fn record(&mut self, path: &Path, metadata: &std::fs::Metadata, action: PathAction) {
+        self.affected += 1;
+        if metadata.is_dir() {
+            self.directories += 1;
+        } else {
+            self.files += 1;
+            self.bytes += metadata.len();
+        }
+        if self.sample.len() < SAMPLE_SIZE {
+            self.sample.push(AffectedPath { path: path.to_string_lossy().to_string(), is_dir: metadata.is_dir(), action });
+        }
+    }
+
+    fn skip(&mut self, arg: &str, reason: &str) {
+        self.skipped.push(format!("{}: {}", arg, reason));
+    }
+
+    /// Text for the terminal, as `what-if` prints it
+    pub fn report(&self) -> String {
+        let mut out = format!(
+            "🔎 {} would change {} path{} ({} files, {} directories, {}) without running:\n",
+            self.program,
+            self.affected,
+            if self.affected == 1 { "" } else { "s" },
+            self.files,
+            self.directories,
+            human_bytes(self.bytes),
+        );
+        for entry in &self.sample {
+            let action = match &entry.action {
+                PathAction::Delete => "delete".to_string(),
+                PathAction::Move { to } => format!("move → {}", to),
+                PathAction::Create => "create".to_string(),
+                PathAction::Overwrite => "overwrite".to_string(),
+                PathAction::Update => "update".to_string(),
+                PathAction::ChangeMode { from, to } => format!("mode {} → {}", from, to),
+            };
+            out.push_str(&format!("  {:<10} {}{}\n", action, entry.path, if entry.is_dir { "/" } else { "" }));
+        }
+        if self.affected > self.sample.len() {
+            out.push_str(&format!("  … and {} more\n", self.affected - self.sample.len()));
+        }
+        if self.truncated {
+            out.push_str(&format!("⚠️ Stopped counting after {} entries\n", MAX_VISITED));
+        }
+        for skipped in &self.skipped {
+            out.push_str(&format!("⚠️ {}\n", skipped));
+        }
+        out.trim_end().to_string()
+    }
+}
+
+/// Simulate the command, or `None` when it is not one that can be simulated
+pub fn simulate(command: &str, working_dir: &Path) -> Option<Result<Simulation, String>> {
+    let words = match shell_quote::split(command) {
+        Ok(words) => words,
+        Err(e) => return Some(Err(e)),
+    };
+    let words = match words.split_first() {
+        Some((first, rest)) if first == "sudo" || first == "doas" => rest.to_vec(),
+        _ => words,
+    };
+    let (program, args) = words.split_first()?;
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let args = Arguments::parse(args);
+    let mut simulation = Simulation::new(command, program);
+    let mut visited = 0;
+    let result = match program {
+        "rm" => remove(&args, working_dir, &mut simulation, &mut visited),
+        "mv" => move_paths(&args, working_dir, &mut simulation),
+        "cp" => copy(&args, working_dir, &mut simulation, &mut visited, false),
+        "rsync" => copy(&args, working_dir, &mut simulation, &mut visited, true),
+        "chmod" => change_mode(&words[1..], working_dir, &mut simulation, &mut visited),
+        "find" if words.iter().any(|w| w == "-delete") => find_delete(&words[1..], working_dir, &mut simulation, &mut visited),
+        _ => return None,
+    };
+    simulation.truncated = visited >= MAX_VISITED;
+    Some(result.map(|_| simulation))
+}
+
+/// Short flags, long flags and operands of a command line
+struct Arguments {
+    short: String,
+    long: Vec<String>,
+    operands: Vec<String>,
+}
+
+impl Arguments {
+    fn parse(args: &[String]) -> Self {
+        let mut parsed = Self { short: String::new(), long: Vec::new(), operands: Vec::new() };
+        let mut options_done = false;
+        for arg in args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                parsed.operands.push(arg.clone());
+            } else if arg == "--" {
+                options_done = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                parsed.long.push(long.split('=').next().unwrap_or(long).to_string());
+            } else {
+                parsed.short.push_str(&arg[1..]);
+            }
+        }
+        parsed
+    }
+
+    fn has(&self, short: char, long: &str) -> bool {
+        self.short.contains(short) || self.long.iter().any(|l| l == long)
+    }
+}
+
+/// Paths an operand names: globs expanded, `~` and relative paths resolved
+fn expand(operand: &str, working_dir: &Path) -> Vec<PathBuf> {
+    let expanded = match operand.strip_prefix('~') {
+        Some(rest) => dirs::home_dir().map(|home| home.join(rest.trim_start_matches('/'))).unwrap_or_else(|| PathBuf::from(operand)),
+        None => working_dir.join(operand),
+    };
+    if operand.contains(['*', '?', '[']) {
+        let mut matches: Vec<PathBuf> = glob::glob(&expanded.to_string_lossy())
+            .map(|paths| paths.filter_map(Result::ok).collect())
+            .unwrap_or_default();
+        matches.sort();
+        matches
+    } else if expanded.symlink_metadata().is_ok() {
+        vec![expanded]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Visit `root` and, when it is a directory, everything below it; children before their directory
+fn walk(root: &Path, visited: &mut usize, visit: &mut dyn FnMut(&Path, &std::fs::Metadata)) {
+    if *visited >= MAX_VISITED {
+        return;
+    }
+    let Ok(metadata) = root.symlink_metadata() else { return };
+    *visited += 1;
+    if metadata.is_dir() {
+        if let Ok(entries) = std::fs::read_dir(root) {
+            let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+            children.sort();
+            for child in children {
+                walk(&child, visited, visit);
+            }
+        }
+    }
+    visit(root, &metadata);
+}
+
+fn remove(args: &Arguments, working_dir: &Path, simulation: &mut Simulation, visited: &mut usize) -> Result<(), String> {
+    let recursive = args.has('r', "recursive") || args.short.contains('R');
+    for operand in &args.operands {
+        let paths = expand(operand, working_dir);
+        if paths.is_empty() {
+            simulation.skip(operand, "no such file or directory");
+        }
+        for path in paths {
+            let is_dir = path.symlink_metadata().map(|m| m.is_dir()).unwrap_or(false);
+            if is_dir && !recursive && !args.has('d', "dir") {
+                simulation.skip(operand, "is a directory (needs -r)");
+                continue;
+            }
+            walk(&path, visited, &mut |path, metadata| simulation.record(path, metadata, PathAction::Delete));
+        }
+    }
+    Ok(())
+}
+
+fn split_destination(args: &Arguments) -> Result<(&[String], &String), String> {
+    match args.operands.split_last() {
+        Some((destination, sources)) if !sources.is_empty() => Ok((sources, destination)),
+        _ => Err("Needs at least one source and a destination".to_string()),
+    }
+}
+
+fn move_paths(args: &Arguments, working_dir: &Path, simulation: &mut Simulation) -> Result<(), String> {
+    let (sources, destination) = split_destination(args)?;
+    let destination_path = working_dir.join(destination);
+    let into_directory = destination_path.is_dir() || sources.len() > 1;
+    for operand in sources {
+        let paths = expand(operand, working_dir);
+        if paths.is_empty() {
+            simulation.skip(operand, "no such file or directory");
+        }
+        for path in paths {
+            let target = if into_directory {
+                destination_path.join(path.file_name().unwrap_or_default())
+            } else {
+                destination_path.clone()
+            };
+            if let Ok(existing) = target.symlink_metadata() {
+                if args.has('n', "no-clobber") {
+                    simulation.skip(operand, "target exists and -n keeps it");
+                    continue;
+                }
+                simulation.record(&target, &existing, PathAction::Overwrite);
+            }
+            if let Ok(metadata) = path.symlink_metadata() {
+                simulation.record(&path, &metadata, PathAction::Move { to: target.to_string_lossy().to_string() });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// cp, and rsync with its trailing-slash rule, `--delete` and skipping of unchanged files
+fn copy(args: &Arguments, working_dir: &Path, simulation: &mut Simulation, visited: &mut usize, rsync: bool) -> Result<(), String> {
+    let (sources, destination) = split_destination(args)?;
+    if rsync && args.operands.iter().any(|operand| is_remote(operand)) {
+        return Err("Copies to or from another machine cannot be simulated".to_string());
+    }
+    let recursive = args.has('r', "recursive") || args.short.contains('R') || args.has('a', "archive");
+    let destination_path = working_dir.join(destination);
+    let into_directory = destination_path.is_dir() || sources.len() > 1 || (rsync && destination.ends_with('/'));
+    let mut copied = std::collections::HashSet::new();
+    let mut target_roots = Vec::new();
+
+    for operand in sources {
+        let paths = expand(operand, working_dir);
+        if paths.is_empty() {
+            simulation.skip(operand, "no such file or directory");
+        }
+        for path in paths {
+            let is_dir = path.is_dir();
+            if is_dir && !recursive {
+                simulation.skip(operand, "is a directory (needs -r)");
+                continue;
+            }
+            // rsync copies the contents of `src/`, and `src` itself otherwise
+            let target_root = if rsync && is_dir && operand.ends_with('/') {
+                destination_path.clone()
+            } else if into_directory {
+                destination_path.join(path.file_name().unwrap_or_default())
+            } else {
+                destination_path.clone()
+            };
+            if path.is_dir() {
+                target_roots.push(target_root.clone());
+            }
+            walk(&path, visited, &mut |source, metadata| {
+                let relative = source.strip_prefix(&path).unwrap_or(Path::new(""));
+                let target = target_root.join(relative);
+                copied.insert(target.clone());
+                match target.symlink_metadata() {
+                    Err(_) => simulation.record(&target, metadata, PathAction::Create),
+                    Ok(_) if metadata.is_dir() => {}
+                    Ok(existing) if rsync && unchanged(metadata, &existing) => {}
+                    Ok(_) if rsync => simulation.record(&target, metadata, PathAction::Update),
+                    Ok(_) => simulation.record(&target, metadata, PathAction::Overwrite),
+                }
+            });
+        }
+    }
+
+    // --delete removes what the source directories do not have, only below the directories synced
+    if rsync && args.long.iter().any(|l| l.starts_with("delete")) {
+        for root in target_roots.iter().filter(|root| root.is_dir()) {
+            walk(root, visited, &mut |target, metadata| {
+                if target != root && !copied.contains(target) {
+                    simulation.record(target, metadata, PathAction::Delete);
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+fn is_remote(operand: &str) -> bool {
+    match operand.find(':') {
+        Some(colon) => !operand[..colon].contains('/') && colon > 1,
+        None => false,
+    }
+}
+
+/// rsync's quick check: same size and modification time
+fn unchanged(source: &std::fs::Metadata, target: &std::fs::Metadata) -> bool {
+    source.len() == target.len() && source.modified().ok() == target.modified().ok()
+}
+
+#[cfg(unix)]
+fn change_mode(words: &[String], working_dir: &Path, simulation: &mut Simulation, visited: &mut usize) -> Result<(), String> {
+    use std::os::unix::fs::MetadataExt;
+
+    // A symbolic mode such as `-x` looks like a flag, so only chmod's own flags are taken as options
+    let mut recursive = false;
+    let mut operands = Vec::new();
+    for word in words {
+        match word.as_str() {
+            "-R" | "--recursive" => recursive = true,
+            "-v" | "-f" | "-c" | "--verbose" | "--silent" | "--quiet" | "--changes" | "--" => {}
+            _ => operands.push(word.as_str()),
+        }
+    }
+    let (spec, targets) = operands.split_first().ok_or("No mode given")?;
+    for operand in targets {
+        let paths = expand(operand, working_dir);
+        if paths.is_empty() {
+            simulation.skip(operand, "no such file or directory");
+        }
+        for path in paths {
+            let mut error = None;
+            let mut visit = |path: &Path, metadata: &std::fs::Metadata| {
+                let from = metadata.mode() & 0o7777;
+                match crate::system::permissions::apply_spec(spec, from, metadata.is_dir()) {
+                    Ok(to) if to != from => simulation.record(path, metadata, PathAction::ChangeMode {
+                        from: format!("{:04o}", from),
+                        to: format!("{:04o}", to),
+                    }),
+                    Ok(_) => {}
+                    Err(e) => error = Some(e),
+                }
+            };
+            if recursive {
+                walk(&path, visited, &mut visit);
+            } else if let Ok(metadata) = path.metadata() {
+                *visited += 1;
+                visit(&path, &metadata);
+            }
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn change_mode(_words: &[String], _working_dir: &Path, _simulation: &mut Simulation, _visited: &mut usize) -> Result<(), String> {
+    Err("Mode bits are not supported on this platform".to_string())
+}
+
+/// The tests `find ... -delete` can be simulated with
+enum FindTest {
+    Name(glob::Pattern),
+    IName(glob::Pattern),
+    Type(char),
+}
+
+fn find_delete(words: &[String], working_dir: &Path, simulation: &mut Simulation, visited: &mut usize) -> Result<(), String> {
+    let split = words.iter().position(|w| w.starts_with('-') || w == "(" || w == "!").unwrap_or(words.len());
+    let roots: Vec<&String> = if split == 0 { Vec::new() } else { words[..split].iter().collect() };
+    let mut tests = Vec::new();
+    let (mut min_depth, mut max_depth) = (0, usize::MAX);
+    let mut rest = words[split..].iter();
+    while let Some(word) = rest.next() {
+        let mut value = || rest.next().cloned().ok_or_else(|| format!("{} needs a value", word));
+        let pattern = |value: String| glob::Pattern::new(&value).map_err(|e| e.to_string());
+        match word.as_str() {
+            "-name" => tests.push(FindTest::Name(pattern(value()?)?)),
+            "-iname" => tests.push(FindTest::IName(pattern(value()?.to_lowercase())?)),
+            "-type" => tests.push(FindTest::Type(value()?.chars().next().unwrap_or('f'))),
+            "-maxdepth" => max_depth = value()?.parse().map_err(|_| "-maxdepth needs a number")?,
+            "-mindepth" => min_depth = value()?.parse().map_err(|_| "-mindepth needs a number")?,
+            "-delete" | "-print" | "-depth" => {}
+            other => return Err(format!("find {} cannot be simulated", other)),
+        }
+    }
+    let matches = |path: &Path, metadata: &std::fs::Metadata| {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        tests.iter().all(|test| match test {
+            FindTest::Name(pattern) => pattern.matches(&name),
+            FindTest::IName(pattern) => pattern.matches(&name.to_lowercase()),
+            FindTest::Type('f') => metadata.is_file(),
+            FindTest::Type('d') => metadata.is_dir(),
+            FindTest::Type('l') => metadata.file_type().is_symlink(),
+            FindTest::Type(_) => false,
+        })
+    };
+
+    let roots = if roots.is_empty() { vec![working_dir.to_path_buf()] } else { roots.iter().map(|r| working_dir.join(r)).collect() };
+    for root in roots {
+        walk(&root, visited, &mut |path, metadata| {
+            let depth = path.strip_prefix(&root).map(|rel| rel.components().count()).unwrap_or(0);
+            // find never deletes its starting point
+            if depth >= min_depth.max(1) && depth <= max_depth && matches(path, metadata) {
+                simulation.record(path, metadata, PathAction::Delete);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
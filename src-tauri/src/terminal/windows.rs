@@ -0,0 +1,160 @@
+// Multi-window session ownership
+// Every session belongs to exactly one window: the main window unless it was
+// detached into a window of its own. Session-scoped events go only to the owning
+// window. Detached windows take numbered slots so a window reopened in the same
+// slot comes back where it was last placed, e.g. on a second monitor.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Label of the window created from tauri.conf.json
+pub const MAIN_WINDOW: &str = "main";
+
+/// Emitted with every `WindowLayout` whenever a session changes windows
+pub const WINDOWS_CHANGED_EVENT: &str = "windows-changed";
+
+const DETACHED_PREFIX: &str = "session-window-";
+
+/// Logical pixels, so a layout survives scale factor changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub maximized: bool,
+}
+
+impl WindowGeometry {
+    pub fn of(window: &tauri::Window) -> Option<Self> {
+        let scale = window.scale_factor().ok()?;
+        let position = window.outer_position().ok()?.to_logical::<f64>(scale);
+        let size = window.inner_size().ok()?.to_logical::<f64>(scale);
+        Some(Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized: window.is_maximized().unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub label: String,
+    pub session_ids: Vec<String>,
+    pub geometry: Option<WindowGeometry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedGeometry {
+    windows: HashMap<String, WindowGeometry>,
+}
+
+pub struct WindowRegistry {
+    /// Sessions not listed here are in the main window
+    owners: HashMap<String, String>,
+    /// By window label, persisted
+    geometry: HashMap<String, WindowGeometry>,
+}
+
+impl Default for WindowRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowRegistry {
+    fn data_file() -> PathBuf {
+        crate::ai::data_directory().join("window_layout.json")
+    }
+
+    pub fn new() -> Self {
+        let saved: SavedGeometry = std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self { owners: HashMap::new(), geometry: saved.windows }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let saved = SavedGeometry { windows: self.geometry.clone() };
+        let json = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    /// Label of the window that shows `session_id`
+    pub fn window_for(&self, session_id: &str) -> &str {
+        self.owners.get(session_id).map(String::as_str).unwrap_or(MAIN_WINDOW)
+    }
+
+    pub fn sessions_in(&self, label: &str) -> Vec<String> {
+        let mut sessions: Vec<String> = self.owners.iter().filter(|(_, owner)| *owner == label).map(|(id, _)| id.clone()).collect();
+        sessions.sort();
+        sessions
+    }
+
+    /// Lowest detached window slot with no sessions in it
+    pub fn free_label(&self) -> String {
+        (1..)
+            .map(|slot| format!("{}{}", DETACHED_PREFIX, slot))
+            .find(|label| !self.owners.values().any(|owner| owner == label))
+            .expect("slots are unbounded")
+    }
+
+    pub fn assign(&mut self, session_id: &str, label: &str) {
+        if label == MAIN_WINDOW {
+            self.owners.remove(session_id);
+        } else {
+            self.owners.insert(session_id.to_string(), label.to_string());
+        }
+    }
+
+    /// Forget a closed session; returns its window if that window is now empty and should close
+    pub fn release(&mut self, session_id: &str) -> Option<String> {
+        let label = self.owners.remove(session_id)?;
+        self.sessions_in(&label).is_empty().then_some(label)
+    }
+
+    /// A detached window went away; its sessions return to the main window
+    pub fn window_closed(&mut self, label: &str) -> Vec<String> {
+        let returned = self.sessions_in(label);
+        self.owners.retain(|_, owner| owner != label);
+        returned
+    }
+
+    pub fn geometry(&self, label: &str) -> Option<&WindowGeometry> {
+        self.geometry.get(label)
+    }
+
+    /// Remembered in memory; written out by `save` when the window closes
+    pub fn set_geometry(&mut self, label: &str, geometry: WindowGeometry) {
+        self.geometry.insert(label.to_string(), geometry);
+    }
+
+    /// The main window first, then detached windows by label
+    pub fn layouts(&self, session_ids: &[String]) -> Vec<WindowLayout> {
+        let mut labels: Vec<&str> = self.owners.values().map(String::as_str).collect();
+        labels.sort();
+        labels.dedup();
+        std::iter::once(MAIN_WINDOW)
+            .chain(labels)
+            .map(|label| WindowLayout {
+                label: label.to_string(),
+                session_ids: session_ids.iter().filter(|id| self.window_for(id) == label).cloned().collect(),
+                geometry: self.geometry.get(label).cloned(),
+            })
+            .collect()
+    }
+
+    pub fn is_detached(label: &str) -> bool {
+        label.starts_with(DETACHED_PREFIX)
+    }
+}
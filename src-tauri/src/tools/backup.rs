@@ -0,0 +1,733 @@
+// Backups
+// A backup job copies its source directories into dated snapshots under its
+// destination. Each snapshot looks like a full copy, but files unchanged since
+// the previous snapshot are hard links to it, so only changes take space; rsync
+// does the copying when it is installed (with --link-dest), and a native copy
+// with the same layout is used otherwise. A verification pass compares every
+// copied file's checksum with its source. Only snapshots that finished and passed
+// verification are linked against and count towards the number kept; a run whose
+// copy fails removes its partial snapshot. Jobs can run on a schedule, and
+// snapshots can be browsed and restored from.
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::checksum::{self, HashAlgorithm};
+
+/// Emitted with a `BackupProgress` while a job copies and verifies
+pub const PROGRESS_EVENT: &str = "backup-progress";
+
+/// Written into each snapshot with its report
+const MANIFEST: &str = "backup.json";
+const SNAPSHOT_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// Files between progress reports
+const PROGRESS_EVERY: usize = 500;
+
+/// Directories that can be rebuilt and are left out of suggested jobs
+const REBUILDABLE: [(&str, &str); 8] = [
+    ("package.json", "node_modules"),
+    ("Cargo.toml", "target"),
+    ("pyproject.toml", ".venv"),
+    ("requirements.txt", ".venv"),
+    ("pyproject.toml", "__pycache__"),
+    ("build.gradle", "build"),
+    ("pom.xml", "target"),
+    ("go.mod", "vendor"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupJob {
+    pub id: String,
+    pub name: String,
+    pub sources: Vec<String>,
+    /// Snapshots go in `<destination>/<job name>/<date>`
+    pub destination: String,
+    /// Glob patterns matched against names and paths relative to the source
+    #[serde(default)]
+    pub excludes: Vec<String>,
+    /// Run every this many hours; manual only when unset
+    #[serde(default)]
+    pub interval_hours: Option<u64>,
+    /// Snapshots kept, oldest removed first; all kept when unset
+    #[serde(default)]
+    pub keep_snapshots: Option<usize>,
+    #[serde(default = "default_true")]
+    pub verify: bool,
+    /// Copy with rsync when it is installed
+    #[serde(default = "default_true")]
+    pub prefer_rsync: bool,
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupEngine {
+    Rsync,
+    Native,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupPhase {
+    Copying,
+    Verifying,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupProgress {
+    pub job_id: String,
+    pub phase: BackupPhase,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Verification {
+    pub checked: usize,
+    /// Files whose copy differs from the source, usually because they changed during the backup
+    pub mismatched: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl Verification {
+    pub fn passed(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupReport {
+    pub job_id: String,
+    pub snapshot: String,
+    pub path: String,
+    pub engine: BackupEngine,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Files copied because they are new or changed
+    pub copied: usize,
+    /// Files linked to the previous snapshot; counted only by the native copy
+    pub unchanged: usize,
+    pub bytes_copied: u64,
+    pub errors: Vec<String>,
+    pub verification: Option<Verification>,
+    /// Older snapshots removed to keep the configured number
+    pub pruned: Vec<String>,
+}
+
+impl BackupReport {
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "💾 Snapshot {} ({}): {} files copied ({}), {} unchanged\n   {}",
+            self.snapshot,
+            match self.engine {
+                BackupEngine::Rsync => "rsync",
+                BackupEngine::Native => "native copy",
+            },
+            self.copied,
+            format_bytes(self.bytes_copied),
+            self.unchanged,
+            self.path,
+        );
+        match &self.verification {
+            Some(verification) if verification.passed() => {
+                out.push_str(&format!("\n✅ Verified {} files", verification.checked));
+            }
+            Some(verification) => {
+                out.push_str(&format!(
+                    "\n❌ Verification: {} differ, {} missing",
+                    verification.mismatched.len(),
+                    verification.missing.len()
+                ));
+                for path in verification.mismatched.iter().chain(&verification.missing).take(10) {
+                    out.push_str(&format!("\n   {}", path));
+                }
+            }
+            None => {}
+        }
+        for error in self.errors.iter().take(10) {
+            out.push_str(&format!("\n⚠️ {}", error));
+        }
+        if !self.pruned.is_empty() {
+            out.push_str(&format!("\n🗑️ Removed {} old snapshot(s)", self.pruned.len()));
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub path: String,
+    /// The report written when it was taken; missing for snapshots made elsewhere
+    pub report: Option<BackupReport>,
+}
+
+impl Snapshot {
+    /// Finished, and verified when its job verifies
+    fn is_good(&self) -> bool {
+        self.report.as_ref().is_some_and(|report| report.verification.as_ref().map_or(true, Verification::passed))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub name: String,
+    /// Relative to the snapshot, for browsing deeper or restoring
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub restored: usize,
+    /// Files left alone because they exist at the target
+    pub skipped: Vec<String>,
+    pub target: String,
+}
+
+/// Saved backup jobs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BackupJobs {
+    jobs: Vec<BackupJob>,
+}
+
+impl BackupJobs {
+    fn data_file() -> PathBuf {
+        crate::ai::config_directory().join("backup_jobs.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::data_file())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = Self::data_file();
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> &[BackupJob] {
+        &self.jobs
+    }
+
+    pub fn get(&self, id: &str) -> Option<&BackupJob> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// The job backing up exactly this directory
+    pub fn for_source(&self, source: &Path) -> Option<&BackupJob> {
+        self.jobs.iter().find(|job| job.sources.iter().any(|s| Path::new(s) == source))
+    }
+
+    /// Add or replace a job; a new job gets an id
+    pub fn upsert(&mut self, mut job: BackupJob) -> Result<BackupJob, String> {
+        validate(&job)?;
+        if job.id.is_empty() {
+            job.id = uuid::Uuid::new_v4().to_string();
+        }
+        match self.jobs.iter_mut().find(|existing| existing.id == job.id) {
+            Some(existing) => *existing = job.clone(),
+            None => self.jobs.push(job.clone()),
+        }
+        self.save()?;
+        Ok(job)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        let before = self.jobs.len();
+        self.jobs.retain(|job| job.id != id);
+        if self.jobs.len() == before {
+            return Err(format!("No backup job {}", id));
+        }
+        self.save()
+    }
+
+    pub fn record_run(&mut self, id: &str, at: DateTime<Utc>) -> Result<(), String> {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.last_run = Some(at);
+        }
+        self.save()
+    }
+
+    /// Scheduled jobs whose interval has passed since they last ran
+    pub fn due(&self) -> Vec<BackupJob> {
+        let now = Utc::now();
+        self.jobs
+            .iter()
+            .filter(|job| match (job.interval_hours, job.last_run) {
+                (Some(hours), Some(last_run)) => now - last_run >= chrono::Duration::hours(hours.max(1) as i64),
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn validate(job: &BackupJob) -> Result<(), String> {
+    if job.sources.is_empty() {
+        return Err("A backup needs at least one source".to_string());
+    }
+    let destination = Path::new(&job.destination);
+    if !destination.is_absolute() {
+        return Err("The destination must be an absolute path".to_string());
+    }
+    for source in &job.sources {
+        let source = Path::new(source);
+        if !source.is_dir() {
+            return Err(format!("Source directory not found: {}", source.display()));
+        }
+        // Backing up into the source would copy every earlier snapshot again
+        if destination.starts_with(source) {
+            return Err(format!("The destination is inside {}", source.display()));
+        }
+    }
+    for pattern in &job.excludes {
+        glob::Pattern::new(pattern).map_err(|e| format!("Bad exclude '{}': {}", pattern, e))?;
+    }
+    Ok(())
+}
+
+/// Where backups go unless the user picks a destination
+pub fn default_destination() -> PathBuf {
+    crate::system::app_dirs::data_dir().join("backups")
+}
+
+/// A job for one directory, leaving out what the project can rebuild; what the wizard starts from
+pub fn suggest(source: &Path) -> BackupJob {
+    let mut excludes: Vec<String> = REBUILDABLE
+        .iter()
+        .filter(|(marker, dir)| source.join(marker).exists() && source.join(dir).is_dir())
+        .map(|(_, dir)| dir.to_string())
+        .collect();
+    excludes.dedup();
+    excludes.extend([".DS_Store".to_string(), "*.tmp".to_string()]);
+    BackupJob {
+        id: String::new(),
+        name: source.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "backup".to_string()),
+        sources: vec![source.to_string_lossy().to_string()],
+        destination: default_destination().to_string_lossy().to_string(),
+        excludes,
+        interval_hours: None,
+        keep_snapshots: Some(10),
+        verify: true,
+        prefer_rsync: true,
+        last_run: None,
+    }
+}
+
+pub fn rsync_available() -> bool {
+    Command::new("rsync").arg("--version").stdin(std::process::Stdio::null()).output().is_ok_and(|o| o.status.success())
+}
+
+fn job_directory(job: &BackupJob) -> PathBuf {
+    let name: String = job
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    Path::new(&job.destination).join(if name.trim_matches(['_', '.']).is_empty() { job.id.clone() } else { name })
+}
+
+/// Each source gets a folder named after it inside the snapshot
+fn source_folder(source: &Path) -> String {
+    source.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "root".to_string())
+}
+
+/// Snapshots of a job, oldest first
+pub fn list_snapshots(job: &BackupJob) -> Vec<Snapshot> {
+    let Ok(entries) = std::fs::read_dir(job_directory(job)) else { return Vec::new() };
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().to_string();
+            chrono::NaiveDateTime::parse_from_str(&id, SNAPSHOT_FORMAT).ok()?;
+            let report = std::fs::read_to_string(entry.path().join(MANIFEST))
+                .ok()
+                .and_then(|data| serde_json::from_str(&data).ok());
+            Some(Snapshot { id, path: entry.path().to_string_lossy().to_string(), report })
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+    snapshots
+}
+
+fn snapshot_path(job: &BackupJob, snapshot: &str) -> Result<PathBuf, String> {
+    list_snapshots(job)
+        .into_iter()
+        .find(|s| s.id == snapshot)
+        .map(|s| PathBuf::from(s.path))
+        .ok_or_else(|| format!("No snapshot {} for {}", snapshot, job.name))
+}
+
+/// A path inside a snapshot; `..` is refused so browsing cannot leave it
+fn inside(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative.trim_start_matches(['/', '\\']));
+    if relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err("Paths may not leave the snapshot".to_string());
+    }
+    Ok(root.join(relative))
+}
+
+struct Excludes(Vec<glob::Pattern>);
+
+impl Excludes {
+    fn new(patterns: &[String]) -> Self {
+        Self(patterns.iter().filter_map(|p| glob::Pattern::new(p).ok()).collect())
+    }
+
+    fn matches(&self, relative: &Path) -> bool {
+        let name = relative.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let path = relative.to_string_lossy();
+        self.0.iter().any(|pattern| pattern.matches(&name) || pattern.matches(&path))
+    }
+}
+
+/// Files and directories of a source, minus the excluded ones, as (relative path, entry)
+fn source_entries(source: &Path, excludes: &Excludes) -> impl Iterator<Item = (PathBuf, ignore::DirEntry)> {
+    let root = source.to_path_buf();
+    let filter_root = root.clone();
+    let patterns = Excludes(excludes.0.clone());
+    ignore::WalkBuilder::new(source)
+        .standard_filters(false)
+        .follow_links(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| !patterns.matches(entry.path().strip_prefix(&filter_root).unwrap_or(entry.path())))
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter_map(move |entry| {
+            let relative = entry.path().strip_prefix(&root).ok()?.to_path_buf();
+            (!relative.as_os_str().is_empty()).then_some((relative, entry))
+        })
+}
+
+/// Take a snapshot of every source, verify it when the job asks, and prune old snapshots
+pub fn run(job: &BackupJob, mut on_progress: impl FnMut(BackupProgress)) -> Result<BackupReport, String> {
+    validate(job)?;
+    let started_at = Utc::now();
+    // A snapshot that failed verification may hold bad copies, which linking would carry forward
+    let previous = list_snapshots(job).into_iter().rev().find(Snapshot::is_good);
+    let id = Local::now().format(SNAPSHOT_FORMAT).to_string();
+    let snapshot = job_directory(job).join(&id);
+    if snapshot.exists() {
+        return Err(format!("Snapshot {} already exists; try again in a second", id));
+    }
+    std::fs::create_dir_all(&snapshot).map_err(|e| format!("Cannot create {}: {}", snapshot.display(), e))?;
+
+    let engine = if job.prefer_rsync && rsync_available() { BackupEngine::Rsync } else { BackupEngine::Native };
+    let mut report = BackupReport {
+        job_id: job.id.clone(),
+        snapshot: id,
+        path: snapshot.to_string_lossy().to_string(),
+        engine,
+        started_at,
+        finished_at: started_at,
+        copied: 0,
+        unchanged: 0,
+        bytes_copied: 0,
+        errors: Vec::new(),
+        verification: None,
+        pruned: Vec::new(),
+    };
+    let excludes = Excludes::new(&job.excludes);
+    for source in &job.sources {
+        let source = Path::new(source);
+        let target = snapshot.join(source_folder(source));
+        // Unchanged files are taken from the last good snapshot
+        let previous = previous.as_ref().map(|p| (Path::new(&p.path).join(source_folder(source)), p.report.as_ref().map(|r| r.started_at)));
+        let copied = match engine {
+            BackupEngine::Rsync => copy_with_rsync(job, source, &target, previous.as_ref().map(|(p, _)| p.as_path()), &mut report),
+            BackupEngine::Native => {
+                copy_natively(job, source, &target, previous, &excludes, &mut report, &mut on_progress);
+                Ok(())
+            }
+        };
+        if let Err(e) = copied {
+            let _ = std::fs::remove_dir_all(&snapshot);
+            return Err(e);
+        }
+    }
+
+    if job.verify {
+        let mut verification = Verification::default();
+        for source in &job.sources {
+            let source = Path::new(source);
+            verify_copy(&job.id, source, &snapshot.join(source_folder(source)), &excludes, &mut verification, &mut on_progress);
+        }
+        report.verification = Some(verification);
+    }
+    report.finished_at = Utc::now();
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::write(snapshot.join(MANIFEST), json).map_err(|e| e.to_string())?;
+
+    let failed = report.verification.as_ref().is_some_and(|v| !v.passed());
+    if failed && job.keep_snapshots.is_some() {
+        report.errors.push("Old snapshots were kept since this one failed verification".to_string());
+    }
+    if let Some(keep) = job.keep_snapshots.filter(|_| !failed) {
+        // The newest good snapshots are kept; anything older than them goes, good or not
+        let snapshots = list_snapshots(job);
+        let good: Vec<&Snapshot> = snapshots.iter().filter(|s| s.is_good()).collect();
+        let oldest_kept = good.get(good.len().saturating_sub(keep.max(1))).map(|s| s.id.clone());
+        for old in snapshots.iter().filter(|s| oldest_kept.as_ref().is_some_and(|kept| s.id < *kept)) {
+            match std::fs::remove_dir_all(&old.path) {
+                Ok(()) => report.pruned.push(old.id.clone()),
+                Err(e) => report.errors.push(format!("Could not remove snapshot {}: {}", old.id, e)),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn copy_with_rsync(job: &BackupJob, source: &Path, target: &Path, previous: Option<&Path>, report: &mut BackupReport) -> Result<(), String> {
+    let mut command = Command::new("rsync");
+    command.args(["-a", "--stats"]);
+    if let Some(previous) = previous.filter(|p| p.is_dir()) {
+        command.arg(format!("--link-dest={}", previous.display()));
+    }
+    for pattern in &job.excludes {
+        command.arg(format!("--exclude={}", pattern));
+    }
+    // The trailing slashes copy the source's contents into the target folder
+    let output = command
+        .arg(format!("{}/", source.display()))
+        .arg(format!("{}/", target.display()))
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("Could not run rsync: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    report.copied += stat(&stdout, "Number of regular files transferred").unwrap_or(0) as usize;
+    report.bytes_copied += stat(&stdout, "Total transferred file size").unwrap_or(0);
+    if !output.status.success() {
+        // 23 and 24 are partial transfers (unreadable or vanished files); the rest of the snapshot is good
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let partial = matches!(output.status.code(), Some(23) | Some(24));
+        let message = format!("rsync {}: {}", source.display(), stderr.lines().last().unwrap_or("failed").trim());
+        if !partial {
+            return Err(message);
+        }
+        report.errors.push(message);
+    }
+    Ok(())
+}
+
+/// A number from rsync's `--stats` block, written with thousands separators in newer versions
+fn stat(stats: &str, label: &str) -> Option<u64> {
+    let line = stats.lines().find(|line| line.trim_start().starts_with(label))?;
+    let value = line.split(':').nth(1)?;
+    value.split_whitespace().next()?.replace([',', '.'], "").parse().ok()
+}
+
+fn copy_natively(
+    job: &BackupJob,
+    source: &Path,
+    target: &Path,
+    previous: Option<(PathBuf, Option<DateTime<Utc>>)>,
+    excludes: &Excludes,
+    report: &mut BackupReport,
+    on_progress: &mut impl FnMut(BackupProgress),
+) {
+    if let Err(e) = std::fs::create_dir_all(target) {
+        report.errors.push(format!("{}: {}", target.display(), e));
+        return;
+    }
+    let mut files = 0;
+    for (relative, entry) in source_entries(source, excludes) {
+        let destination = target.join(&relative);
+        let Some(file_type) = entry.file_type() else { continue };
+        let result = if file_type.is_dir() {
+            std::fs::create_dir_all(&destination)
+        } else if file_type.is_symlink() {
+            copy_symlink(entry.path(), &destination)
+        } else {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    report.errors.push(format!("{}: {}", entry.path().display(), e));
+                    continue;
+                }
+            };
+            // A file not modified since the last snapshot started is linked to its copy there
+            let earlier = previous.as_ref().and_then(|(dir, started)| {
+                let copy = dir.join(&relative);
+                let unchanged = started.zip(metadata.modified().ok()).is_some_and(|(started, modified)| DateTime::<Utc>::from(modified) < started)
+                    && copy.metadata().is_ok_and(|m| m.len() == metadata.len());
+                unchanged.then_some(copy)
+            });
+            files += 1;
+            match earlier.map(|copy| std::fs::hard_link(copy, &destination)) {
+                Some(Ok(())) => {
+                    report.unchanged += 1;
+                    Ok(())
+                }
+                // Linking fails across file systems and on some network drives
+                _ => std::fs::copy(entry.path(), &destination).map(|bytes| {
+                    report.copied += 1;
+                    report.bytes_copied += bytes;
+                }),
+            }
+        };
+        if let Err(e) = result {
+            report.errors.push(format!("{}: {}", entry.path().display(), e));
+        }
+        if files > 0 && files % PROGRESS_EVERY == 0 {
+            on_progress(BackupProgress { job_id: job.id.clone(), phase: BackupPhase::Copying, files, bytes: report.bytes_copied });
+        }
+    }
+    on_progress(BackupProgress { job_id: job.id.clone(), phase: BackupPhase::Copying, files, bytes: report.bytes_copied });
+}
+
+#[cfg(unix)]
+fn copy_symlink(link: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(link)?, destination)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(link: &Path, destination: &Path) -> std::io::Result<()> {
+    std::fs::copy(link, destination).map(|_| ())
+}
+
+/// Compare each source file with its copy by size and checksum
+fn verify_copy(
+    job_id: &str,
+    source: &Path,
+    copy: &Path,
+    excludes: &Excludes,
+    verification: &mut Verification,
+    on_progress: &mut impl FnMut(BackupProgress),
+) {
+    let mut bytes = 0;
+    for (relative, entry) in source_entries(source, excludes) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let copied = copy.join(&relative);
+        let shown = relative.to_string_lossy().to_string();
+        verification.checked += 1;
+        if !copied.is_file() {
+            verification.missing.push(shown);
+            continue;
+        }
+        let original = checksum::hash_file(entry.path(), HashAlgorithm::Blake3, |_, _| {});
+        let backup = checksum::hash_file(&copied, HashAlgorithm::Blake3, |_, _| {});
+        match (original, backup) {
+            (Ok(original), Ok(backup)) if original.digest == backup.digest => bytes += original.size,
+            // An unreadable source was reported while copying
+            (Err(_), _) => {}
+            _ => verification.mismatched.push(shown),
+        }
+        if verification.checked % PROGRESS_EVERY == 0 {
+            on_progress(BackupProgress { job_id: job_id.to_string(), phase: BackupPhase::Verifying, files: verification.checked, bytes });
+        }
+    }
+    on_progress(BackupProgress { job_id: job_id.to_string(), phase: BackupPhase::Verifying, files: verification.checked, bytes });
+}
+
+/// Check an existing snapshot against the current sources
+pub fn verify(job: &BackupJob, snapshot: &str) -> Result<Verification, String> {
+    let root = snapshot_path(job, snapshot)?;
+    let excludes = Excludes::new(&job.excludes);
+    let mut verification = Verification::default();
+    for source in &job.sources {
+        let source = Path::new(source);
+        verify_copy(&job.id, source, &root.join(source_folder(source)), &excludes, &mut verification, &mut |_| {});
+    }
+    Ok(verification)
+}
+
+/// Entries of a directory inside a snapshot, directories first
+pub fn browse(job: &BackupJob, snapshot: &str, relative: &str) -> Result<Vec<SnapshotEntry>, String> {
+    let root = snapshot_path(job, snapshot)?;
+    let dir = inside(&root, relative)?;
+    let entries = std::fs::read_dir(&dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+    let mut listed: Vec<SnapshotEntry> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !(dir == root && entry.file_name() == MANIFEST))
+        .filter_map(|entry| {
+            let metadata = entry.path().symlink_metadata().ok()?;
+            Some(SnapshotEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: entry.path().strip_prefix(&root).ok()?.to_string_lossy().to_string(),
+                is_dir: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+                modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            })
+        })
+        .collect();
+    listed.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(listed)
+}
+
+/// Copy a file or directory out of a snapshot into `target_dir`; existing files are kept unless `overwrite`
+pub fn restore(job: &BackupJob, snapshot: &str, relative: &str, target_dir: &Path, overwrite: bool) -> Result<RestoreReport, String> {
+    let root = snapshot_path(job, snapshot)?;
+    let from = inside(&root, relative)?;
+    if from == root {
+        return Err("Pick a folder or file inside the snapshot to restore".to_string());
+    }
+    if !from.exists() {
+        return Err(format!("{} is not in snapshot {}", relative, snapshot));
+    }
+    let to = target_dir.join(from.file_name().unwrap_or_default());
+    let mut report = RestoreReport { restored: 0, skipped: Vec::new(), target: to.to_string_lossy().to_string() };
+    let files = if from.is_dir() {
+        source_entries(&from, &Excludes(Vec::new())).map(|(relative, entry)| (to.join(relative), entry.into_path())).collect()
+    } else {
+        vec![(to.clone(), from.clone())]
+    };
+    for (destination, original) in files {
+        let metadata = original.symlink_metadata().map_err(|e| e.to_string())?;
+        if metadata.is_dir() {
+            std::fs::create_dir_all(&destination).map_err(|e| format!("{}: {}", destination.display(), e))?;
+            continue;
+        }
+        if destination.symlink_metadata().is_ok() && !overwrite {
+            report.skipped.push(destination.to_string_lossy().to_string());
+            continue;
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+        }
+        // Snapshot files are hard links shared with other snapshots, so they are copied, never moved
+        let copied = if metadata.file_type().is_symlink() {
+            let _ = std::fs::remove_file(&destination);
+            copy_symlink(&original, &destination)
+        } else {
+            std::fs::copy(&original, &destination).map(|_| ())
+        };
+        copied.map_err(|e| format!("{}: {}", destination.display(), e))?;
+        report.restored += 1;
+    }
+    Ok(report)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
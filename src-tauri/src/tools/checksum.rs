@@ -0,0 +1,342 @@
+// File checksums and integrity verification
+// Hashes are computed natively in streaming fashion so multi-GB images can be
+// checked with progress reporting; published checksum files next to a download
+// (foo.iso.sha256, SHA256SUMS, ...) are picked up automatically
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+/// Report progress roughly every this many bytes
+const PROGRESS_STEP: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            "blake3" | "b3" => Ok(HashAlgorithm::Blake3),
+            other => Err(format!("Unsupported hash algorithm: {}", other)),
+        }
+    }
+
+    /// Best guess from the length of a hex digest (64 chars is taken as SHA-256)
+    pub fn from_digest_length(hex: &str) -> Option<Self> {
+        match hex.len() {
+            32 => Some(HashAlgorithm::Md5),
+            40 => Some(HashAlgorithm::Sha1),
+            64 => Some(HashAlgorithm::Sha256),
+            128 => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashProgress {
+    pub path: String,
+    pub bytes_done: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHash {
+    pub path: String,
+    pub algorithm: HashAlgorithm,
+    pub digest: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecksumVerification {
+    pub path: String,
+    pub algorithm: HashAlgorithm,
+    pub expected: String,
+    pub actual: String,
+    pub matches: bool,
+    /// Where the expected value came from when it was not given explicitly
+    pub source: Option<String>,
+}
+
+impl ChecksumVerification {
+    pub fn summary(&self) -> String {
+        let source = self.source.as_ref().map(|s| format!(" (from {})", s)).unwrap_or_default();
+        if self.matches {
+            format!("✅ {} matches its {}{}\n{}", self.path, self.algorithm.name(), source, self.actual)
+        } else {
+            format!(
+                "❌ {} does NOT match its {}{}\nexpected: {}\nactual:   {}",
+                self.path,
+                self.algorithm.name(),
+                source,
+                self.expected,
+                self.actual
+            )
+        }
+    }
+}
+
+enum Hasher {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        use sha2::Digest;
+        match algorithm {
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+        match self {
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        use sha2::Digest;
+        match self {
+            Hasher::Md5(h) => hex::encode(h.finalize()),
+            Hasher::Sha1(h) => hex::encode(h.finalize()),
+            Hasher::Sha256(h) => hex::encode(h.finalize()),
+            Hasher::Sha512(h) => hex::encode(h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Stream a file through the hasher, calling `progress(bytes_done, total)` periodically
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm, mut progress: impl FnMut(u64, u64)) -> Result<FileHash, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let total = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut done = 0u64;
+    let mut next_report = PROGRESS_STEP;
+
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Read error in {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        done += read as u64;
+        if done >= next_report {
+            progress(done, total);
+            next_report = done + PROGRESS_STEP;
+        }
+    }
+    progress(done, total);
+
+    Ok(FileHash {
+        path: path.display().to_string(),
+        algorithm,
+        digest: hasher.finish(),
+        size: done,
+    })
+}
+
+/// Normalise an expected checksum: accepts `hex`, `sha256:hex` and upper case
+fn parse_expected(expected: &str) -> Result<(Option<HashAlgorithm>, String), String> {
+    let expected = expected.trim();
+    let (algorithm, hex) = match expected.split_once(':') {
+        Some((algo, hex)) => (Some(HashAlgorithm::parse(algo)?), hex.trim()),
+        None => (None, expected),
+    };
+    if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is not a hex checksum", expected));
+    }
+    Ok((algorithm, hex.to_lowercase()))
+}
+
+/// Find `filename`'s digest in a checksum file (GNU `hex  name`, BSD `SHA256 (name) = hex`, or a bare hex)
+pub fn parse_checksum_file(content: &str, filename: &str) -> Option<(Option<HashAlgorithm>, String)> {
+    let lines: Vec<&str> = content.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).collect();
+
+    for line in &lines {
+        // BSD style
+        if let Some((left, hex)) = line.split_once(") = ") {
+            if let Some((algo, name)) = left.split_once(" (") {
+                if name == filename {
+                    return Some((HashAlgorithm::parse(algo).ok(), hex.trim().to_lowercase()));
+                }
+            }
+            continue;
+        }
+        // GNU style; '*' marks binary mode
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let hex = parts.next()?;
+        let name = parts.next().unwrap_or("").trim().trim_start_matches('*');
+        if hex.chars().all(|c| c.is_ascii_hexdigit()) && (name == filename || name.ends_with(&format!("/{}", filename))) {
+            return Some((None, hex.to_lowercase()));
+        }
+    }
+
+    // Single-file checksum files often contain just the digest
+    match lines.as_slice() {
+        [only] if only.chars().all(|c| c.is_ascii_hexdigit()) => Some((None, only.to_lowercase())),
+        _ => None,
+    }
+}
+
+/// Look next to a file for a published checksum
+pub fn find_published_checksum(path: &Path) -> Option<(HashAlgorithm, String, PathBuf)> {
+    let filename = path.file_name()?.to_string_lossy().to_string();
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut candidates: Vec<(PathBuf, Option<HashAlgorithm>)> = Vec::new();
+    for (ext, algorithm) in [
+        ("sha256", HashAlgorithm::Sha256),
+        ("sha256sum", HashAlgorithm::Sha256),
+        ("sha512", HashAlgorithm::Sha512),
+        ("sha512sum", HashAlgorithm::Sha512),
+        ("sha1", HashAlgorithm::Sha1),
+        ("md5", HashAlgorithm::Md5),
+        ("b3", HashAlgorithm::Blake3),
+    ] {
+        candidates.push((dir.join(format!("{}.{}", filename, ext)), Some(algorithm)));
+    }
+    for (name, algorithm) in [
+        ("SHA256SUMS", Some(HashAlgorithm::Sha256)),
+        ("sha256sums.txt", Some(HashAlgorithm::Sha256)),
+        ("SHA512SUMS", Some(HashAlgorithm::Sha512)),
+        ("MD5SUMS", Some(HashAlgorithm::Md5)),
+        ("CHECKSUMS", None),
+        ("checksums.txt", None),
+    ] {
+        candidates.push((dir.join(name), algorithm));
+    }
+
+    candidates.into_iter().find_map(|(candidate, file_algorithm)| {
+        let content = std::fs::read_to_string(&candidate).ok()?;
+        let (line_algorithm, hex) = parse_checksum_file(&content, &filename)?;
+        let algorithm = line_algorithm.or(file_algorithm).or_else(|| HashAlgorithm::from_digest_length(&hex))?;
+        Some((algorithm, hex, candidate))
+    })
+}
+
+/// Verify a file against an expected digest, or against a published checksum when none is given
+pub fn verify_checksum(
+    path: &Path,
+    expected: Option<&str>,
+    algorithm: Option<HashAlgorithm>,
+    progress: impl FnMut(u64, u64),
+) -> Result<ChecksumVerification, String> {
+    let (algorithm, expected, source) = match expected {
+        Some(expected) => {
+            let (prefixed, hex) = parse_expected(expected)?;
+            let algorithm = algorithm
+                .or(prefixed)
+                .or_else(|| HashAlgorithm::from_digest_length(&hex))
+                .ok_or_else(|| format!("Cannot tell the algorithm of a {}-character checksum", hex.len()))?;
+            (algorithm, hex, None)
+        }
+        None => {
+            let (algorithm, hex, file) = find_published_checksum(path)
+                .ok_or_else(|| format!("No expected checksum given and none published next to {}", path.display()))?;
+            (algorithm, hex, Some(file.display().to_string()))
+        }
+    };
+
+    let actual = hash_file(path, algorithm, progress)?.digest;
+    Ok(ChecksumVerification {
+        path: path.display().to_string(),
+        algorithm,
+        matches: actual == expected,
+        expected,
+        actual,
+        source,
+    })
+}
+
+/// A checksum job derived from a request like "verify this ISO against its sha256"
+#[derive(Debug, Clone)]
+pub struct ChecksumRequest {
+    pub path: PathBuf,
+    pub algorithm: Option<HashAlgorithm>,
+    pub expected: Option<String>,
+    pub verify: bool,
+}
+
+pub fn plan_from_natural_language(request: &str, working_dir: &str) -> Option<ChecksumRequest> {
+    let lower = request.to_lowercase();
+    let words: Vec<&str> = request.split_whitespace().collect();
+    let verify = ["verify", "check", "validate", "compare"].iter().any(|w| lower.starts_with(w));
+    let hash = ["checksum", "hash", "sha1", "sha256", "sha512", "md5", "blake3"].iter().any(|w| lower.contains(w));
+    if !hash {
+        return None;
+    }
+    let algorithm = ["sha512", "sha256", "sha1", "md5", "blake3"]
+        .into_iter()
+        .find(|a| lower.contains(a))
+        .and_then(|a| HashAlgorithm::parse(a).ok());
+    let expected = words
+        .iter()
+        .map(|w| w.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != ':'))
+        .find(|w| w.len() >= 32 && parse_expected(w).is_ok())
+        .map(str::to_string);
+
+    // An explicit file name wins; "this ISO" means the single file of that type here
+    let dir = Path::new(working_dir);
+    let named = words
+        .iter()
+        .map(|w| w.trim_matches(|c: char| c == '"' || c == '\'' || c == ','))
+        .find(|w| w.contains('.') && dir.join(w).is_file());
+    let path = match named {
+        Some(name) => dir.join(name),
+        None => {
+            let kind = ["iso", "img", "dmg", "zip", "tar", "gz", "exe", "msi", "deb", "rpm", "appimage"]
+                .into_iter()
+                .find(|k| lower.split(|c: char| !c.is_ascii_alphanumeric()).any(|w| w == *k))?;
+            let matches: Vec<PathBuf> = std::fs::read_dir(dir)
+                .ok()?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().map(|e| e.to_string_lossy().to_lowercase() == kind).unwrap_or(false))
+                .collect();
+            match matches.as_slice() {
+                [only] => only.clone(),
+                _ => return None,
+            }
+        }
+    };
+
+    Some(ChecksumRequest { path, algorithm, expected, verify: verify || lower.contains("against") })
+}
@@ -0,0 +1,106 @@
+// docker-compose service detection
+// A light line-based reader that finds which infrastructure services
+// (Redis, RabbitMQ, Kafka) a project runs, without a full YAML parser
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const COMPOSE_FILES: [&str; 4] = ["docker-compose.yml", "docker-compose.yaml", "compose.yml", "compose.yaml"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceKind {
+    Redis,
+    RabbitMq,
+    Kafka,
+}
+
+impl ServiceKind {
+    fn detect(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        if text.contains("redis") || text.contains("valkey") {
+            Some(ServiceKind::Redis)
+        } else if text.contains("rabbitmq") {
+            Some(ServiceKind::RabbitMq)
+        } else if text.contains("kafka") || text.contains("redpanda") {
+            Some(ServiceKind::Kafka)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposeService {
+    pub name: String,
+    pub kind: ServiceKind,
+    pub image: Option<String>,
+    pub host_ports: Vec<u16>,
+}
+
+pub fn find_compose_file(dir: &Path) -> Option<std::path::PathBuf> {
+    COMPOSE_FILES.iter().map(|name| dir.join(name)).find(|path| path.is_file())
+}
+
+/// Redis and queue services declared in the project's compose file
+pub fn detect_services(dir: &Path) -> Vec<ComposeService> {
+    let content = match find_compose_file(dir).and_then(|path| std::fs::read_to_string(path).ok()) {
+        Some(content) => content,
+        None => return vec![],
+    };
+
+    let mut services = Vec::new();
+    let mut in_services = false;
+    let mut service_indent = None;
+    let mut current: Option<(String, Option<String>, Vec<u16>)> = None;
+
+    let finish = |current: Option<(String, Option<String>, Vec<u16>)>, services: &mut Vec<ComposeService>| {
+        if let Some((name, image, host_ports)) = current {
+            let kind = image.as_deref().and_then(ServiceKind::detect).or_else(|| ServiceKind::detect(&name));
+            if let Some(kind) = kind {
+                services.push(ComposeService { name, kind, image, host_ports });
+            }
+        }
+    };
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        if indent == 0 {
+            finish(current.take(), &mut services);
+            in_services = trimmed == "services:";
+            service_indent = None;
+            continue;
+        }
+        if !in_services {
+            continue;
+        }
+
+        // The first indented key under services: sets the service indentation
+        let indent_of_services = *service_indent.get_or_insert(indent);
+        if indent == indent_of_services && trimmed.ends_with(':') {
+            finish(current.take(), &mut services);
+            current = Some((trimmed.trim_end_matches(':').to_string(), None, Vec::new()));
+        } else if let Some((_, ref mut image, ref mut ports)) = current {
+            if let Some(value) = trimmed.strip_prefix("image:") {
+                *image = Some(value.trim().trim_matches(|c| c == '"' || c == '\'').to_string());
+            } else if let Some(item) = trimmed.strip_prefix("- ") {
+                // Port mappings look like "6379:6379" or "127.0.0.1:6379:6379"
+                let item = item.trim().trim_matches(|c| c == '"' || c == '\'');
+                let parts: Vec<&str> = item.split(':').collect();
+                if parts.len() >= 2 {
+                    if let Ok(port) = parts[parts.len() - 2].parse::<u16>() {
+                        ports.push(port);
+                    }
+                }
+            }
+        }
+    }
+    finish(current.take(), &mut services);
+
+    services
+}
@@ -0,0 +1,368 @@
+// JSON / YAML / TOML formatting, validation and conversion
+// Documents go through an order-preserving value so keys keep their original
+// order when re-indenting or converting; parse errors carry line and column.
+// JSON may have comments and trailing commas (jsonc). Comments do not survive
+// the round trip, so a file that has any is never written back.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim_start_matches('.') {
+            "json" | "jsonc" => Ok(ConfigFormat::Json),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            other => Err(format!("Unsupported config format: {}", other)),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|ext| Self::parse(&ext.to_string_lossy()).ok())
+    }
+
+    /// Guess from the content when there is no file extension to go by
+    pub fn sniff(text: &str) -> Self {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with('{') || (trimmed.starts_with('[') && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()) {
+            return ConfigFormat::Json;
+        }
+        let toml_like = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).all(|l| {
+            (l.starts_with('[') && l.ends_with(']')) || l.split_once('=').map(|(k, _)| !k.trim().is_empty() && !k.contains(':')).unwrap_or(false)
+        });
+        if toml_like {
+            ConfigFormat::Toml
+        } else {
+            ConfigFormat::Yaml
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigError {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// The offending line with a caret under the column
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigValidation {
+    pub format: ConfigFormat,
+    pub valid: bool,
+    pub error: Option<ConfigError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormattedConfig {
+    pub input_format: ConfigFormat,
+    pub output_format: ConfigFormat,
+    pub output: String,
+    pub changed: bool,
+    /// The input had comments, which are not in the output
+    pub dropped_comments: bool,
+}
+
+fn snippet(text: &str, line: usize, column: usize) -> Option<String> {
+    let source_line = text.lines().nth(line.checked_sub(1)?)?;
+    Some(format!("{:>4} | {}\n     | {}^", line, source_line, " ".repeat(column.saturating_sub(1))))
+}
+
+fn config_error(text: &str, message: String, line: Option<usize>, column: Option<usize>) -> ConfigError {
+    ConfigError {
+        snippet: line.and_then(|l| snippet(text, l, column.unwrap_or(1))),
+        message,
+        line,
+        column,
+    }
+}
+
+/// Line and column (1-based) of a byte offset
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// jsonc as plain JSON: `//` and `/* */` comments and trailing commas become spaces, so error
+/// positions still point into `text`; true when there were comments
+fn strip_json_extensions(text: &str) -> (String, bool) {
+    let blank = |c: char| if c == '\n' { "\n".to_string() } else { " ".repeat(c.len_utf8()) };
+    let mut chars = text.chars().peekable();
+    let mut out = String::with_capacity(text.len());
+    let mut comments = false;
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                comments = true;
+                out.push(' ');
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    out.push_str(&blank(c));
+                }
+            }
+            ('/', Some('*')) => {
+                comments = true;
+                out.push(' ');
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    out.push_str(&blank(c));
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    // Trailing commas, now that no comment can sit between one and its bracket
+    let mut bytes = out.into_bytes();
+    let mut in_string = false;
+    let mut escaped = false;
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            _ if escaped => escaped = false,
+            b'\\' if in_string => escaped = true,
+            b'"' => in_string = !in_string,
+            b',' if !in_string => {
+                if matches!(bytes[i + 1..].iter().find(|b| !b.is_ascii_whitespace()), Some(b'}' | b']')) {
+                    bytes[i] = b' ';
+                }
+            }
+            _ => {}
+        }
+    }
+    (String::from_utf8(bytes).unwrap_or_default(), comments)
+}
+
+/// Whether reformatting `text` would lose comments. For YAML and TOML any `#` that could start
+/// one counts, even inside a string, which only ever errs on the side of not writing
+pub fn has_comments(text: &str, format: ConfigFormat) -> bool {
+    match format {
+        ConfigFormat::Json => strip_json_extensions(text).1,
+        ConfigFormat::Yaml | ConfigFormat::Toml => {
+            text.lines().any(|line| line.trim_start().starts_with('#') || line.contains(" #") || line.contains("\t#"))
+        }
+    }
+}
+
+/// Parse into an order-preserving value
+fn parse(text: &str, format: ConfigFormat) -> Result<serde_yaml::Value, ConfigError> {
+    match format {
+        ConfigFormat::Json => serde_json::from_str(&strip_json_extensions(text).0)
+            .map_err(|e| config_error(text, e.to_string(), Some(e.line()), Some(e.column()))),
+        ConfigFormat::Yaml => serde_yaml::from_str(text).map_err(|e| {
+            let location = e.location();
+            config_error(text, e.to_string(), location.as_ref().map(|l| l.line()), location.as_ref().map(|l| l.column()))
+        }),
+        ConfigFormat::Toml => toml::from_str::<toml::Value>(text)
+            .map_err(|e| {
+                let (line, column) = e.span().map(|span| position(text, span.start)).unzip();
+                config_error(text, e.message().to_string(), line, column)
+            })
+            .and_then(|value| {
+                serde_yaml::to_value(value).map_err(|e| config_error(text, e.to_string(), None, None))
+            }),
+    }
+}
+
+/// serde_yaml indents by two and a `- ` takes two columns as well, so every two-column step of
+/// a line's leading indentation is widened to `indent`. Block scalar lines keep whatever they
+/// have past their block's own indentation, which is part of the text
+fn reindent_yaml(yaml: &str, indent: usize) -> String {
+    let widen = |spaces: usize| " ".repeat(spaces / 2 * indent);
+    let mut out = String::with_capacity(yaml.len() * 2);
+    // Original and widened indentation of the block scalar being copied
+    let mut block: Option<(usize, String)> = None;
+    // Indentation of a line that ended in a block scalar header
+    let mut header: Option<usize> = None;
+    for line in yaml.lines() {
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if let Some(header_spaces) = header.filter(|_| !line.trim().is_empty()) {
+            if spaces > header_spaces {
+                block = Some((spaces, widen(spaces)));
+            }
+            header = None;
+        }
+        if let Some((original, widened)) = &block {
+            if line.trim().is_empty() || spaces >= *original {
+                if !line.trim().is_empty() {
+                    out.push_str(widened);
+                    out.push_str(&line[*original..]);
+                }
+                out.push('\n');
+                continue;
+            }
+            block = None;
+        }
+        let steps = line.as_bytes().chunks(2).take_while(|step| *step == b"  " || *step == b"- ").count();
+        for step in line.as_bytes()[..steps * 2].chunks(2) {
+            out.push_str(&if step == b"- " { format!("-{}", " ".repeat(indent - 1)) } else { " ".repeat(indent) });
+        }
+        let rest = &line[steps * 2..];
+        out.push_str(rest);
+        out.push('\n');
+        let rest = rest.trim_end();
+        let (before, last) = rest.rsplit_once(' ').unwrap_or(("", rest));
+        let is_header = last.starts_with(['|', '>']) && last[1..].chars().all(|c| matches!(c, '-' | '+' | '0'..='9'));
+        if is_header && (before.is_empty() || before.ends_with(':')) {
+            header = Some(spaces);
+        }
+    }
+    out
+}
+
+/// toml indents only array elements, by four spaces, so those are widened to `indent`;
+/// multi-line strings are left as they are
+fn reindent_toml(toml: &str, indent: usize) -> String {
+    let mut out = String::with_capacity(toml.len() * 2);
+    let mut in_string = false;
+    for line in toml.lines() {
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if in_string {
+            out.push_str(line);
+        } else {
+            out.push_str(&" ".repeat(spaces / 4 * indent + spaces % 4));
+            out.push_str(&line[spaces..]);
+        }
+        out.push('\n');
+        if (line.matches("\"\"\"").count() + line.matches("'''").count()) % 2 == 1 {
+            in_string = !in_string;
+        }
+    }
+    out
+}
+
+fn render(value: &serde_yaml::Value, format: ConfigFormat, indent: usize) -> Result<String, String> {
+    match format {
+        ConfigFormat::Json => {
+            let indent_bytes = vec![b' '; indent];
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent_bytes);
+            let mut out = Vec::new();
+            let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+            value.serialize(&mut serializer).map_err(|e| format!("Cannot write JSON: {}", e))?;
+            String::from_utf8(out).map(|s| s + "\n").map_err(|e| e.to_string())
+        }
+        ConfigFormat::Yaml => {
+            let yaml = serde_yaml::to_string(value).map_err(|e| format!("Cannot write YAML: {}", e))?;
+            if indent == 2 {
+                return Ok(yaml);
+            }
+            // Re-read to be sure the wider layout still means the same document
+            let reindented = reindent_yaml(&yaml, indent);
+            match serde_yaml::from_str::<serde_yaml::Value>(&reindented) {
+                Ok(reread) if reread == *value => Ok(reindented),
+                _ => Err(format!("Cannot indent this YAML by {} spaces; it needs the default of 2", indent)),
+            }
+        }
+        ConfigFormat::Toml => {
+            if !value.is_mapping() {
+                return Err("TOML documents must be a table at the top level".to_string());
+            }
+            let toml = toml::to_string_pretty(value).map_err(|e| format!("Cannot write TOML: {}", e))?;
+            if indent == 4 {
+                return Ok(toml);
+            }
+            let reindented = reindent_toml(&toml, indent);
+            match (toml::from_str::<toml::Value>(&toml), toml::from_str::<toml::Value>(&reindented)) {
+                (Ok(original), Ok(reread)) if reread == original => Ok(reindented),
+                _ => Err(format!("Cannot indent this TOML by {} spaces", indent)),
+            }
+        }
+    }
+}
+
+pub fn validate(text: &str, format: ConfigFormat) -> ConfigValidation {
+    let error = parse(text, format).err();
+    ConfigValidation { format, valid: error.is_none(), error }
+}
+
+/// Re-indent a document, or convert it when `output_format` differs
+pub fn format(text: &str, input_format: ConfigFormat, output_format: ConfigFormat, indent: usize) -> Result<FormattedConfig, String> {
+    let value = parse(text, input_format).map_err(|e| match (e.line, e.column) {
+        (Some(line), Some(column)) => format!("{} at line {}, column {}", e.message, line, column),
+        _ => e.message,
+    })?;
+    let output = render(&value, output_format, indent.clamp(1, 8))?;
+    Ok(FormattedConfig {
+        input_format,
+        output_format,
+        changed: output.trim_end() != text.trim_end(),
+        dropped_comments: has_comments(text, input_format),
+        output,
+    })
+}
+
+/// Where a natural language request wants its input from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    File(String),
+    LastOutput,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigRequest {
+    pub source: ConfigSource,
+    pub input_format: Option<ConfigFormat>,
+    pub output_format: Option<ConfigFormat>,
+    pub validate_only: bool,
+}
+
+/// "pretty print this JSON", "convert config.yaml to toml", "is this yaml valid"
+pub fn plan_from_natural_language(request: &str) -> Option<ConfigRequest> {
+    let lower = request.to_lowercase();
+    let words: Vec<&str> = request.split_whitespace().map(|w| w.trim_matches(|c| c == '"' || c == '\'' || c == ',' || c == '?')).collect();
+    let format_word = |word: &str| ConfigFormat::parse(word).ok();
+
+    let file = words.iter().find(|w| w.contains('.') && ConfigFormat::from_path(Path::new(w)).is_some()).map(|w| w.to_string());
+    let mentioned: Vec<(usize, ConfigFormat)> = words
+        .iter()
+        .enumerate()
+        .filter_map(|(i, w)| format_word(w).map(|f| (i, f)))
+        .collect();
+    if file.is_none() && mentioned.is_empty() {
+        return None;
+    }
+    let source = file.clone().map(ConfigSource::File).unwrap_or(ConfigSource::LastOutput);
+    let input_from_file = file.as_deref().and_then(|f| ConfigFormat::from_path(Path::new(f)));
+
+    if lower.contains("convert") && lower.contains(" to ") {
+        let to = words.iter().position(|w| w.eq_ignore_ascii_case("to"))?;
+        let output = words.get(to + 1).and_then(|w| format_word(w))?;
+        let input = input_from_file.or_else(|| mentioned.iter().find(|(i, _)| *i < to).map(|(_, f)| *f));
+        return Some(ConfigRequest { source, input_format: input, output_format: Some(output), validate_only: false });
+    }
+    let input = input_from_file.or_else(|| mentioned.first().map(|(_, f)| *f));
+    if ["pretty print", "pretty-print", "prettify", "format", "reformat", "indent", "beautify"].iter().any(|k| lower.contains(k)) {
+        return Some(ConfigRequest { source, input_format: input, output_format: input, validate_only: false });
+    }
+    if ["validate", "valid", "lint", "check"].iter().any(|k| lower.contains(k)) {
+        return Some(ConfigRequest { source, input_format: input, output_format: None, validate_only: true });
+    }
+    None
+}
@@ -0,0 +1,167 @@
+// Data file preview and query
+// Loads CSV/TSV, JSON, NDJSON and Parquet files with polars and runs SQL
+// against them, returning results as JSON-friendly tables
+
+use polars::prelude::*;
+use polars::sql::SQLContext;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::path::Path;
+
+const MAX_QUERY_ROWS: u32 = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataColumn {
+    pub name: String,
+    pub dtype: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataTable {
+    pub columns: Vec<DataColumn>,
+    pub rows: Vec<Vec<Value>>,
+    pub total_rows: Option<usize>,
+    pub truncated: bool,
+    pub exported_to: Option<String>,
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+fn load_lazy(path: &Path) -> Result<LazyFrame, String> {
+    if !path.is_file() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    let location = PlPath::new(&path.to_string_lossy());
+
+    let frame = match extension(path).as_str() {
+        "csv" => LazyCsvReader::new(location).with_has_header(true).finish(),
+        "tsv" => LazyCsvReader::new(location).with_has_header(true).with_separator(b'\t').finish(),
+        "parquet" | "pq" => LazyFrame::scan_parquet(location, Default::default()),
+        "ndjson" | "jsonl" => LazyJsonLineReader::new(location).finish(),
+        "json" => {
+            let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            JsonReader::new(file).finish().map(|df| df.lazy())
+        }
+        other => return Err(format!("Unsupported data file type: .{}", other)),
+    };
+    frame.map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn any_value_to_json(value: AnyValue) -> Value {
+    if value.is_null() {
+        return Value::Null;
+    }
+    let dtype = value.dtype();
+    if dtype.is_bool() {
+        Value::Bool(matches!(value, AnyValue::Boolean(true)))
+    } else if dtype.is_integer() {
+        value.extract::<i64>().map(Value::from).unwrap_or(Value::Null)
+    } else if dtype.is_float() {
+        value.extract::<f64>()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    } else if let Some(text) = value.get_str() {
+        Value::String(text.to_string())
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+fn to_table(df: &DataFrame, total_rows: Option<usize>, truncated: bool) -> Result<DataTable, String> {
+    let columns = df.get_columns()
+        .iter()
+        .map(|column| DataColumn {
+            name: column.name().to_string(),
+            dtype: column.dtype().to_string(),
+        })
+        .collect();
+
+    let mut rows = Vec::with_capacity(df.height());
+    for index in 0..df.height() {
+        let row = df.get_columns()
+            .iter()
+            .map(|column| column.get(index).map(any_value_to_json))
+            .collect::<PolarsResult<Vec<Value>>>()
+            .map_err(|e| e.to_string())?;
+        rows.push(row);
+    }
+
+    Ok(DataTable {
+        columns,
+        rows,
+        total_rows,
+        truncated,
+        exported_to: None,
+    })
+}
+
+fn export(df: &mut DataFrame, export_path: &str) -> Result<(), String> {
+    let path = Path::new(export_path);
+    let mut file = File::create(path).map_err(|e| format!("Failed to create {}: {}", export_path, e))?;
+    match extension(path).as_str() {
+        "csv" => CsvWriter::new(&mut file).finish(df).map_err(|e| e.to_string()),
+        "tsv" => CsvWriter::new(&mut file).with_separator(b'\t').finish(df).map_err(|e| e.to_string()),
+        "parquet" | "pq" => ParquetWriter::new(&mut file).finish(df).map(|_| ()).map_err(|e| e.to_string()),
+        "json" => JsonWriter::new(&mut file).with_json_format(JsonFormat::Json).finish(df).map_err(|e| e.to_string()),
+        "ndjson" | "jsonl" => JsonWriter::new(&mut file).finish(df).map_err(|e| e.to_string()),
+        other => Err(format!("Unsupported export type: .{}", other)),
+    }
+}
+
+/// First `rows` rows of a data file plus its schema and row count
+pub fn preview(path: &str, rows: u32) -> Result<DataTable, String> {
+    let frame = load_lazy(Path::new(path))?;
+
+    let total_rows = frame.clone()
+        .select([len()])
+        .collect()
+        .ok()
+        .and_then(|df| df.get_columns().first().and_then(|c| c.get(0).ok()).and_then(|v| v.extract::<usize>()));
+
+    let df = frame.limit(rows).collect().map_err(|e| e.to_string())?;
+    let truncated = total_rows.map(|total| total > df.height()).unwrap_or(false);
+    to_table(&df, total_rows, truncated)
+}
+
+/// Run SQL against a data file. The file is available as `data` and under
+/// its file stem, e.g. `SELECT * FROM sales WHERE amount > 100` for sales.csv
+pub fn query(path: &str, sql: &str, export_path: Option<&str>) -> Result<DataTable, String> {
+    let file_path = Path::new(path);
+    let frame = load_lazy(file_path)?;
+
+    let mut context = SQLContext::new();
+    context.register("data", frame.clone());
+    if let Some(stem) = file_path.file_stem().and_then(|s| s.to_str()) {
+        let table_name: String = stem.chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        if table_name != "data" {
+            context.register(&table_name, frame);
+        }
+    }
+
+    let result = context.execute(sql).map_err(|e| format!("SQL error: {}", e))?;
+    let mut df = result.collect().map_err(|e| format!("Query failed: {}", e))?;
+
+    // Export the full result, but only return a bounded number of rows
+    let exported_to = match export_path {
+        Some(export_path) => {
+            export(&mut df, export_path)?;
+            Some(export_path.to_string())
+        }
+        None => None,
+    };
+
+    let total_rows = df.height();
+    let shown = df.head(Some(MAX_QUERY_ROWS as usize));
+    let mut table = to_table(&shown, Some(total_rows), total_rows > shown.height())?;
+    table.exported_to = exported_to;
+    Ok(table)
+}
@@ -0,0 +1,300 @@
+// Duplicate file finder
+// Files are bucketed by size, then by a BLAKE3 hash of their first block, and
+// only files still sharing a bucket get a full hash, so most of a tree is never
+// read. Hashing runs in parallel. Each group of identical files suggests which
+// copy to keep (one tracked in git, otherwise the newest) and which to delete.
+
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Bytes hashed before deciding whether a full hash is needed
+const HEAD_BYTES: usize = 64 * 1024;
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+const PHOTO_EXTENSIONS: [&str; 10] = ["jpg", "jpeg", "png", "gif", "heic", "webp", "bmp", "tiff", "raw", "dng"];
+const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mov", "mkv", "avi", "webm", "m4v"];
+const MUSIC_EXTENSIONS: [&str; 6] = ["mp3", "flac", "m4a", "wav", "ogg", "aac"];
+const DOCUMENT_EXTENSIONS: [&str; 9] = ["pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "txt", "md"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateOptions {
+    /// Smaller files are ignored; empty files are never reported
+    pub min_size: u64,
+    pub include_hidden: bool,
+    /// Only files with these extensions, lowercase without the dot; empty means all
+    pub extensions: Vec<String>,
+    pub max_files: usize,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 1,
+            include_hidden: false,
+            extensions: Vec::new(),
+            max_files: 200_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    pub modified: Option<DateTime<Utc>>,
+    pub in_git: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+    /// The copy to keep: tracked in git if one is, otherwise the newest
+    pub keep: String,
+    pub delete_suggestions: Vec<String>,
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub root: String,
+    pub groups: Vec<DuplicateGroup>,
+    pub files_scanned: usize,
+    pub reclaimable_bytes: u64,
+    /// Stopped at `max_files`
+    pub truncated: bool,
+}
+
+impl DuplicateReport {
+    /// Groups listed for the terminal, the copy to keep first
+    pub fn summary(&self) -> String {
+        if self.groups.is_empty() {
+            return format!("✅ No duplicates among {} files in {}", self.files_scanned, self.root);
+        }
+        let mut lines = Vec::new();
+        for group in &self.groups {
+            lines.push(format!("{} copies, {} each:", group.files.len(), format_size(group.size)));
+            lines.push(format!("  keep    {}", group.keep));
+            lines.extend(group.delete_suggestions.iter().map(|path| format!("  delete  {}", path)));
+        }
+        lines.push(format!(
+            "🗂️ {} duplicate groups among {} files, {} reclaimable{}",
+            self.groups.len(),
+            self.files_scanned,
+            format_size(self.reclaimable_bytes),
+            if self.truncated { " (stopped early, narrow the search for more)" } else { "" },
+        ));
+        lines.join("\n")
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+/// BLAKE3 of the first `limit` bytes, or the whole file
+fn hash_prefix(path: &Path, limit: Option<usize>) -> Option<String> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; limit.unwrap_or(BUFFER_SIZE).min(BUFFER_SIZE)];
+    let mut remaining = limit.unwrap_or(usize::MAX);
+    while remaining > 0 {
+        let want = buffer.len().min(remaining);
+        let read = file.read(&mut buffer[..want]).ok()?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        remaining -= read;
+    }
+    Some(hasher.finalize().to_hex().to_string())
+}
+
+/// Split every bucket by `key`, dropping files that end up alone
+fn refine<K: Eq + std::hash::Hash + Send>(
+    buckets: Vec<Vec<PathBuf>>,
+    key: impl Fn(&Path) -> Option<K> + Sync,
+) -> Vec<(K, Vec<PathBuf>)> {
+    buckets
+        .into_par_iter()
+        .flat_map_iter(|bucket| {
+            let mut split: HashMap<K, Vec<PathBuf>> = HashMap::new();
+            let keyed: Vec<(K, PathBuf)> = bucket.into_par_iter().filter_map(|path| Some((key(&path)?, path))).collect();
+            for (key, path) in keyed {
+                split.entry(key).or_default().push(path);
+            }
+            split.into_iter().filter(|(_, files)| files.len() > 1)
+        })
+        .collect()
+}
+
+/// Absolute paths of the files git tracks in the repository containing `root`
+fn git_tracked(root: &Path) -> HashSet<PathBuf> {
+    let output = Command::new("git").arg("-C").arg(root).args(["rev-parse", "--show-toplevel"]).output();
+    let Some(top) = output.ok().filter(|o| o.status.success()).map(|o| PathBuf::from(String::from_utf8_lossy(&o.stdout).trim())) else {
+        return HashSet::new();
+    };
+    let Ok(listing) = Command::new("git").arg("-C").arg(&top).args(["ls-files", "-z"]).output() else {
+        return HashSet::new();
+    };
+    listing.stdout.split(|b| *b == 0).filter(|p| !p.is_empty()).map(|p| top.join(String::from_utf8_lossy(p).as_ref())).collect()
+}
+
+fn group(hash: String, size: u64, paths: Vec<PathBuf>, tracked: &HashSet<PathBuf>) -> DuplicateGroup {
+    let mut files: Vec<DuplicateFile> = paths
+        .iter()
+        .map(|path| DuplicateFile {
+            path: path.to_string_lossy().to_string(),
+            modified: std::fs::metadata(path).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from),
+            in_git: tracked.contains(path),
+        })
+        .collect();
+    // Tracked first, then newest first
+    files.sort_by(|a, b| b.in_git.cmp(&a.in_git).then(b.modified.cmp(&a.modified)).then(a.path.cmp(&b.path)));
+    let keep = files[0].path.clone();
+    // Other tracked copies are left alone: deleting them would change the repository
+    let delete_suggestions: Vec<String> = files[1..].iter().filter(|f| !f.in_git).map(|f| f.path.clone()).collect();
+    DuplicateGroup {
+        hash,
+        size,
+        reclaimable_bytes: size * delete_suggestions.len() as u64,
+        files,
+        keep,
+        delete_suggestions,
+    }
+}
+
+/// Groups of identical files under `root`, most reclaimable space first
+pub fn find_duplicates(root: &Path, options: &DuplicateOptions) -> Result<DuplicateReport, String> {
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let extensions: HashSet<String> = options.extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect();
+    let walker = ignore::WalkBuilder::new(root).hidden(!options.include_hidden).follow_links(false).build();
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut files_scanned = 0;
+    let mut truncated = false;
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let wanted = extensions.is_empty()
+            || entry.path().extension().is_some_and(|e| extensions.contains(&e.to_string_lossy().to_lowercase()));
+        if !wanted {
+            continue;
+        }
+        if files_scanned >= options.max_files {
+            truncated = true;
+            break;
+        }
+        files_scanned += 1;
+        let Ok(size) = entry.metadata().map(|m| m.len()) else { continue };
+        if size >= options.min_size.max(1) {
+            by_size.entry(size).or_default().push(entry.into_path());
+        }
+    }
+
+    let sizes: HashMap<PathBuf, u64> = by_size.iter().flat_map(|(size, paths)| paths.iter().map(|p| (p.clone(), *size))).collect();
+    let candidates: Vec<Vec<PathBuf>> = by_size.into_values().filter(|paths| paths.len() > 1).collect();
+    let by_head = refine(candidates, |path| hash_prefix(path, Some(HEAD_BYTES)));
+    let by_content = refine(by_head.into_iter().map(|(_, paths)| paths).collect(), |path| hash_prefix(path, None));
+
+    let tracked = if by_content.is_empty() { HashSet::new() } else { git_tracked(root) };
+    let mut groups: Vec<DuplicateGroup> = by_content
+        .into_iter()
+        .map(|(hash, paths)| {
+            let size = sizes.get(&paths[0]).copied().unwrap_or(0);
+            group(hash, size, paths, &tracked)
+        })
+        .collect();
+    groups.sort_by(|a, b| b.reclaimable_bytes.cmp(&a.reclaimable_bytes).then(a.keep.cmp(&b.keep)));
+
+    Ok(DuplicateReport {
+        root: root.to_string_lossy().to_string(),
+        reclaimable_bytes: groups.iter().map(|g| g.reclaimable_bytes).sum(),
+        groups,
+        files_scanned,
+        truncated,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateRequest {
+    pub path: PathBuf,
+    pub options: DuplicateOptions,
+}
+
+/// "find duplicate photos in Downloads", "show duplicated files here"
+pub fn plan_from_natural_language(request: &str, working_dir: &str) -> Option<DuplicateRequest> {
+    // Anchored at the start so a real command that merely contains the words is left alone
+    let pattern = Regex::new(
+        r"(?i)^\s*(?:please\s+)?(?:find|show(?:\s+me)?|list|look\s+for|search\s+for)\s+(?:all\s+|the\s+|any\s+)*duplicate[sd]?\s*(files?|photos?|images?|pictures?|videos?|movies?|music|songs?|documents?|docs|pdfs?)?(?:\s+(?:in|under|inside|within)\s+(.+?)|\s+(here))?\s*[.?!]?\s*$",
+    )
+    .ok()?;
+    let captures = pattern.captures(request)?;
+    let kind = captures.get(1).map(|m| m.as_str().to_lowercase()).unwrap_or_default();
+    let extensions: &[&str] = match kind.trim_end_matches('s') {
+        "photo" | "image" | "picture" => &PHOTO_EXTENSIONS,
+        "video" | "movie" => &VIDEO_EXTENSIONS,
+        "music" | "song" => &MUSIC_EXTENSIONS,
+        "document" | "doc" => &DOCUMENT_EXTENSIONS,
+        "pdf" => &["pdf"],
+        _ => &[],
+    };
+    let path = match captures.get(2).map(|m| m.as_str().trim().trim_matches(|c| c == '"' || c == '\'')) {
+        Some(place) => resolve_place(place, working_dir),
+        None => PathBuf::from(working_dir),
+    };
+    Some(DuplicateRequest {
+        path,
+        options: DuplicateOptions { extensions: extensions.iter().map(|e| e.to_string()).collect(), ..Default::default() },
+    })
+}
+
+/// "Downloads", "my documents", "~/Pictures", "this folder": a directory here first, then in the home directory
+fn resolve_place(place: &str, working_dir: &str) -> PathBuf {
+    if ["here", "this folder", "this directory", "the current directory", "."].iter().any(|here| place.eq_ignore_ascii_case(here)) {
+        return PathBuf::from(working_dir);
+    }
+    // Compared without lowercasing, which can change byte lengths and so the offsets to cut at
+    let place = ["my ", "the "]
+        .iter()
+        .find_map(|article| place.get(..article.len()).filter(|start| start.eq_ignore_ascii_case(article)).map(|_| &place[article.len()..]))
+        .unwrap_or(place);
+    let home = dirs::home_dir();
+    if let (Some(rest), Some(home)) = (place.strip_prefix("~/"), &home) {
+        return home.join(rest);
+    }
+    let local = Path::new(working_dir).join(place);
+    if local.is_dir() {
+        return local;
+    }
+    // Home folders are capitalised on most systems ("Downloads")
+    if let Some(home) = home {
+        let mut capitalised = place.to_string();
+        if let Some(first) = capitalised.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        for candidate in [home.join(place), home.join(capitalised)] {
+            if candidate.is_dir() {
+                return candidate;
+            }
+        }
+    }
+    local
+}
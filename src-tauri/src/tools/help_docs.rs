@@ -0,0 +1,787 @@
+// Man page and --help rendering
+// Reads the roff source behind `man` (both man(7) and mdoc(7) macros) or falls
+// back to `--help` output, and turns either into structured sections, plain
+// text, HTML and a flag list for completions. Running a program is never a side
+// effect of typing: `--help` is only run when help is asked for, as
+// `<program> --help` without any of the words typed after it, and completions
+// reuse the cached result.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+const HELP_TIMEOUT: Duration = Duration::from_secs(3);
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HelpSource {
+    Man,
+    HelpFlag,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HelpBlock {
+    Paragraph { text: String },
+    Subheading { text: String },
+    Item { tag: String, description: String },
+    Preformatted { text: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelpSection {
+    pub title: String,
+    pub blocks: Vec<HelpBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelpFlag {
+    pub names: Vec<String>,
+    pub argument: Option<String>,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelpDocument {
+    pub command: String,
+    pub source: HelpSource,
+    pub summary: Option<String>,
+    pub synopsis: Option<String>,
+    pub sections: Vec<HelpSection>,
+    pub flags: Vec<HelpFlag>,
+    pub text: String,
+    pub html: String,
+}
+
+impl HelpDocument {
+    fn build(command: &str, source: HelpSource, sections: Vec<HelpSection>) -> Self {
+        let section_text = |names: &[&str]| {
+            sections
+                .iter()
+                .find(|s| names.iter().any(|n| s.title.eq_ignore_ascii_case(n)))
+                .map(|s| blocks_to_text(&s.blocks).trim().to_string())
+                .filter(|text| !text.is_empty())
+        };
+        let summary = section_text(&["NAME"]);
+        let synopsis = section_text(&["SYNOPSIS", "USAGE"]);
+        let flags = extract_flags(&sections);
+        let text = render_text(&sections);
+        let html = render_html(command, &sections);
+
+        Self {
+            command: command.to_string(),
+            source,
+            summary,
+            synopsis,
+            sections,
+            flags,
+            text,
+            html,
+        }
+    }
+
+    /// Flags matching the prefix being typed, most specific names first
+    pub fn complete_flags(&self, prefix: &str) -> Vec<&HelpFlag> {
+        self.flags
+            .iter()
+            .filter(|flag| flag.names.iter().any(|name| name.starts_with(prefix)))
+            .collect()
+    }
+}
+
+/// Rendered help pages keyed by program and topic, so typing does not re-run `man`
+/// and a subcommand never gets the page of its program
+#[derive(Debug, Default)]
+pub struct HelpCache {
+    entries: HashMap<(String, String), (HelpDocument, Instant)>,
+}
+
+impl HelpCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, program: &str, topic: &str) -> Option<HelpDocument> {
+        self.entries
+            .get(&(program.to_string(), topic.to_string()))
+            .filter(|(_, fetched)| fetched.elapsed() < CACHE_TTL)
+            .map(|(doc, _)| doc.clone())
+    }
+
+    pub fn insert(&mut self, program: String, topic: String, doc: HelpDocument) {
+        self.entries.insert((program, topic), (doc, Instant::now()));
+    }
+}
+
+/// Help topic for a command line: `git commit -m x` -> ["git", "commit"]
+pub fn help_topic(command: &str) -> Vec<String> {
+    let mut words = command.split_whitespace();
+    let mut topic = Vec::new();
+    if let Some(first) = words.next() {
+        topic.push(first.to_string());
+        if let Some(second) = words.next() {
+            if second.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') && !second.starts_with('-') {
+                topic.push(second.to_string());
+            }
+        }
+    }
+    topic
+}
+
+/// Fetch and render help for a command, trying man pages before `--help`, which
+/// is only run when `run_help_flag` is set
+pub async fn get_help(command: &str, path_var: &str, run_help_flag: bool) -> Result<HelpDocument, String> {
+    let topic = help_topic(command);
+    let name = topic.first().ok_or("No command given")?;
+    if name.contains('/') || name.starts_with('.') {
+        return Err("Help is only looked up for commands on PATH".to_string());
+    }
+
+    // Subcommand pages first (git-commit), then the main page
+    let mut candidates = Vec::new();
+    if topic.len() > 1 {
+        candidates.push(topic.join("-"));
+    }
+    candidates.push(name.clone());
+    for page in &candidates {
+        if let Some(source) = man_source(page).await {
+            let sections = parse_roff(&source);
+            if !sections.is_empty() {
+                return Ok(HelpDocument::build(page, HelpSource::Man, sections));
+            }
+        }
+    }
+
+    if !run_help_flag {
+        return Err(format!("No man page found for '{}'", name));
+    }
+    // Only run --help on the binary the name resolves to, never on scripts in the working
+    // directory or on what an alias or shell function of that name would run
+    let resolution = {
+        let (name, path_var) = (name.clone(), path_var.to_string());
+        tokio::task::spawn_blocking(move || crate::system::command_resolver::resolve_command(&name, &path_var))
+            .await
+            .map_err(|e| format!("Command resolution failed: {}", e))?
+    };
+    let binary = match (resolution.kind, resolution.target) {
+        (crate::system::command_resolver::ResolutionKind::Binary, Some(binary)) => binary,
+        _ => return Err(format!("No man page or executable found for '{}'", name)),
+    };
+    let output = help_flag_output(&binary, path_var).await?;
+    let sections = parse_help_output(&output);
+    if sections.is_empty() {
+        return Err(format!("'{} --help' produced no output", name));
+    }
+    Ok(HelpDocument::build(name, HelpSource::HelpFlag, sections))
+}
+
+async fn run_quiet(program: &str, args: &[&str]) -> Option<Vec<u8>> {
+    let output = tokio::time::timeout(
+        HELP_TIMEOUT,
+        Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// Roff source of a man page, decompressed and with `.so` redirects followed
+async fn man_source(page: &str) -> Option<String> {
+    let location = run_quiet("man", &["-w", page]).await?;
+    let location = String::from_utf8_lossy(&location).lines().next()?.trim().to_string();
+    let source = read_man_file(&location).await?;
+
+    // Pages like `egrep.1` are just `.so man1/grep.1`
+    if let Some(target) = source.trim().strip_prefix(".so ") {
+        let root = std::path::Path::new(&location).parent()?.parent()?;
+        let redirected = root.join(target.trim());
+        return read_man_file(&redirected.to_string_lossy()).await;
+    }
+    Some(source)
+}
+
+async fn read_man_file(path: &str) -> Option<String> {
+    let decompressor = match path.rsplit('.').next()? {
+        "gz" => Some("gzip"),
+        "bz2" => Some("bzip2"),
+        "xz" | "lzma" => Some("xz"),
+        "zst" => Some("zstd"),
+        _ => None,
+    };
+    let bytes = match decompressor {
+        Some(program) => run_quiet(program, &["-dc", path]).await?,
+        None => tokio::fs::read(path).await.ok()?,
+    };
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `<program> --help` and nothing else: words typed after the program could be
+/// taken as operands by tools that stop reading options at the first one
+async fn help_flag_output(program: &str, path_var: &str) -> Result<String, String> {
+    let output = tokio::time::timeout(
+        HELP_TIMEOUT,
+        Command::new(program)
+            .arg("--help")
+            .env("PATH", path_var)
+            .env("COLUMNS", "100")
+            .env("NO_COLOR", "1")
+            .env("PAGER", "cat")
+            .env("GIT_PAGER", "cat")
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("'{} --help' timed out", program))?
+    .map_err(|e| format!("Failed to run '{} --help': {}", program, e))?;
+
+    // Plenty of tools print usage to stderr
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    let ansi = Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]|.\x08").unwrap();
+    Ok(ansi.replace_all(&String::from_utf8_lossy(&text), "").into_owned())
+}
+
+// ---------------------------------------------------------------------------
+// roff
+
+struct RoffState {
+    sections: Vec<HelpSection>,
+    paragraph: String,
+    preformatted: Option<String>,
+    /// `.TP` takes the next input line as the item tag
+    awaiting_tag: bool,
+    item_tag: Option<String>,
+    skip_until: Option<String>,
+}
+
+impl RoffState {
+    fn current(&mut self) -> &mut HelpSection {
+        if self.sections.is_empty() {
+            self.sections.push(HelpSection { title: String::new(), blocks: vec![] });
+        }
+        self.sections.last_mut().unwrap()
+    }
+
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.paragraph).trim().to_string();
+        if let Some(tag) = self.item_tag.take() {
+            self.current().blocks.push(HelpBlock::Item { tag, description: text });
+        } else if !text.is_empty() {
+            self.current().blocks.push(HelpBlock::Paragraph { text });
+        }
+        if let Some(pre) = self.preformatted.take() {
+            if !pre.trim().is_empty() {
+                self.current().blocks.push(HelpBlock::Preformatted { text: pre.trim_end().to_string() });
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.awaiting_tag {
+            self.awaiting_tag = false;
+            self.item_tag = Some(text.trim().to_string());
+            return;
+        }
+        if let Some(pre) = self.preformatted.as_mut() {
+            pre.push_str(text);
+            pre.push('\n');
+            return;
+        }
+        if !self.paragraph.is_empty() && !self.paragraph.ends_with('\n') {
+            self.paragraph.push(' ');
+        }
+        self.paragraph.push_str(text.trim());
+    }
+
+    fn heading(&mut self, title: String) {
+        self.flush();
+        self.sections.push(HelpSection { title, blocks: vec![] });
+    }
+
+    fn item(&mut self, tag: String) {
+        self.flush();
+        self.item_tag = Some(tag);
+    }
+}
+
+/// Split macro arguments, honouring double quotes
+fn roff_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
+
+/// Resolve inline escapes (`\fB`, `\-`, `\(em`, ...) to plain text
+fn roff_inline(text: &str) -> String {
+    let fonts = Regex::new(r"\\f(?:\[[^\]]*\]|\([A-Za-z]{2}|.)|\\s[+-]?\d+|\\[&|^%c]|\\[*n](?:\([a-zA-Z]{2}|\[[^\]]*\]|[A-Za-z])").unwrap();
+    let text = fonts.replace_all(text, "");
+    let specials = Regex::new(r"\\\(([a-zA-Z]{2})|\\\[([a-zA-Z]+)\]").unwrap();
+    let text = specials.replace_all(&text, |caps: &regex::Captures| {
+        let name = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str()).unwrap_or("");
+        match name {
+            "em" => "—",
+            "en" => "–",
+            "aq" | "cq" | "oq" => "'",
+            "dq" => "\"",
+            "lq" => "“",
+            "rq" => "”",
+            "bu" => "•",
+            "co" => "©",
+            "rg" => "®",
+            "tm" => "™",
+            "hy" | "mi" => "-",
+            "ga" => "`",
+            "ti" => "~",
+            "ha" => "^",
+            "rs" => "\\",
+            "ba" | "or" => "|",
+            "<=" => "≤",
+            ">=" => "≥",
+            "->" => "→",
+            _ => "",
+        }
+        .to_string()
+    });
+    text.replace("\\-", "-")
+        .replace("\\ ", " ")
+        .replace("\\~", " ")
+        .replace("\\e", "\\")
+        .replace("\\\\", "\\")
+        .replace("\\'", "'")
+        .replace("\\`", "`")
+        .replace("\\.", ".")
+}
+
+/// mdoc macros that may appear inline (`.It Fl a Ar file`)
+const MDOC_CALLABLE: [&str; 38] = [
+    "Fl", "Ar", "Op", "Oo", "Oc", "Cm", "Li", "Ic", "Pa", "Ev", "Va", "Dv", "Em", "Sy", "Xr", "Ns",
+    "Pq", "Po", "Pc", "Dq", "Do", "Dc", "Ql", "Sq", "Qq", "Bro", "Brc", "No", "Nm", "Fn", "Fa",
+    "Ad", "An", "Er", "Ux", "Bx", "Tn", "Lk",
+];
+
+fn render_mdoc(args: &[String], name: &str) -> String {
+    let mut out = String::new();
+    let mut no_space = false;
+    let mut i = 0;
+
+    let push = |out: &mut String, text: &str, no_space: &mut bool| {
+        let closing = matches!(text, "," | "." | ";" | ":" | ")" | "]" | "?" | "!");
+        if !out.is_empty() && !*no_space && !closing && !out.ends_with(['[', '(', '"']) {
+            out.push(' ');
+        }
+        out.push_str(text);
+        *no_space = false;
+    };
+
+    while i < args.len() {
+        let arg = args[i].as_str();
+        let next_plain = args.get(i + 1).filter(|a| !MDOC_CALLABLE.contains(&a.as_str()));
+        match arg {
+            "Fl" => {
+                match next_plain {
+                    Some(flag) => {
+                        push(&mut out, &format!("-{}", flag), &mut no_space);
+                        i += 1;
+                    }
+                    None => push(&mut out, "-", &mut no_space),
+                }
+            }
+            "Ar" if next_plain.is_none() => push(&mut out, "file ...", &mut no_space),
+            "Nm" if next_plain.is_none() => push(&mut out, name, &mut no_space),
+            "Op" | "Pq" | "Dq" | "Sq" | "Qq" | "Ql" => {
+                let (open, close) = match arg {
+                    "Op" => ("[", "]"),
+                    "Pq" => ("(", ")"),
+                    "Sq" | "Ql" => ("'", "'"),
+                    _ => ("\"", "\""),
+                };
+                let inner = render_mdoc(&args[i + 1..], name);
+                push(&mut out, &format!("{}{}{}", open, inner, close), &mut no_space);
+                break;
+            }
+            "Oo" => {
+                push(&mut out, "[", &mut no_space);
+                no_space = true;
+            }
+            "Po" => {
+                push(&mut out, "(", &mut no_space);
+                no_space = true;
+            }
+            "Do" => {
+                push(&mut out, "\"", &mut no_space);
+                no_space = true;
+            }
+            "Bro" => {
+                push(&mut out, "{", &mut no_space);
+                no_space = true;
+            }
+            "Oc" => {
+                no_space = true;
+                push(&mut out, "]", &mut no_space);
+            }
+            "Pc" => {
+                no_space = true;
+                push(&mut out, ")", &mut no_space);
+            }
+            "Dc" => {
+                no_space = true;
+                push(&mut out, "\"", &mut no_space);
+            }
+            "Brc" => {
+                no_space = true;
+                push(&mut out, "}", &mut no_space);
+            }
+            "Ns" => no_space = true,
+            "Xr" => {
+                let page = args.get(i + 1).cloned().unwrap_or_default();
+                let section = args.get(i + 2).cloned().unwrap_or_default();
+                push(&mut out, &format!("{}({})", page, section), &mut no_space);
+                i += 2;
+            }
+            "Ux" => push(&mut out, "UNIX", &mut no_space),
+            "Bx" => push(&mut out, "BSD", &mut no_space),
+            _ if MDOC_CALLABLE.contains(&arg) => {}
+            _ => push(&mut out, &roff_inline(arg), &mut no_space),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Parse man(7) or mdoc(7) source into sections
+pub fn parse_roff(source: &str) -> Vec<HelpSection> {
+    let mut state = RoffState {
+        sections: vec![],
+        paragraph: String::new(),
+        preformatted: None,
+        awaiting_tag: false,
+        item_tag: None,
+        skip_until: None,
+    };
+    let mut name = String::new();
+    let mut pending_heading = false;
+
+    for raw in source.lines() {
+        if let Some(end) = &state.skip_until {
+            if raw.trim() == end {
+                state.skip_until = None;
+            }
+            continue;
+        }
+        if raw.starts_with(".\\\"") || raw.starts_with("'\\\"") || raw.starts_with("\\\"") || raw.trim() == "." {
+            continue;
+        }
+
+        if !(raw.starts_with('.') || raw.starts_with('\'')) {
+            let text = roff_inline(raw);
+            if pending_heading {
+                pending_heading = false;
+                state.heading(text.trim().to_string());
+            } else if state.preformatted.is_none() && raw.trim().is_empty() {
+                state.flush();
+            } else {
+                state.text(&text);
+            }
+            continue;
+        }
+
+        let line = raw[1..].trim_start();
+        let (macro_name, rest) = line.split_once([' ', '\t']).unwrap_or((line, ""));
+        let args = roff_args(rest);
+        let joined = roff_inline(&args.join(" "));
+
+        match macro_name {
+            // man(7)
+            "TH" => {}
+            "SH" | "Sh" => {
+                if args.is_empty() {
+                    state.flush();
+                    pending_heading = true;
+                } else {
+                    state.heading(joined.to_uppercase());
+                }
+            }
+            "SS" | "Ss" => {
+                state.flush();
+                state.current().blocks.push(HelpBlock::Subheading { text: joined });
+            }
+            "PP" | "LP" | "P" | "Pp" | "sp" | "Lp" => state.flush(),
+            "TP" | "TQ" => {
+                state.flush();
+                state.awaiting_tag = true;
+            }
+            "IP" => {
+                if args.is_empty() {
+                    state.flush();
+                } else {
+                    state.item(roff_inline(&args[0]));
+                }
+            }
+            "B" | "I" | "SM" | "SB" | "UR" | "MT" => state.text(&joined),
+            "BR" | "BI" | "IB" | "IR" | "RB" | "RI" => {
+                let alternating: String = args.iter().map(|a| roff_inline(a)).collect();
+                state.text(&alternating);
+            }
+            "br" if state.preformatted.is_none() && !state.paragraph.is_empty() => state.paragraph.push('\n'),
+            "nf" | "EX" | "Bd" => {
+                state.flush();
+                state.preformatted = Some(String::new());
+            }
+            "fi" | "EE" | "Ed" => state.flush(),
+            "SY" => {
+                state.flush();
+                state.text(&joined);
+            }
+            "OP" => state.text(&format!("[{}]", joined)),
+            "de" | "de1" | "am" => state.skip_until = Some("..".to_string()),
+            "ig" => state.skip_until = Some(args.first().map(|end| format!(".{}", end)).unwrap_or("..".to_string())),
+
+            // mdoc(7)
+            "Nm" => {
+                if name.is_empty() {
+                    name = args.first().cloned().unwrap_or_default();
+                }
+                state.text(&render_mdoc(&[vec!["Nm".to_string()], args].concat(), &name));
+            }
+            "Nd" => state.text(&format!("— {}", joined)),
+            "It" => {
+                let tag = render_mdoc(&args, &name);
+                if tag.is_empty() {
+                    state.flush();
+                } else {
+                    state.item(tag);
+                }
+            }
+            "Dd" | "Dt" | "Os" | "Bl" | "El" | "RS" | "RE" | "in" | "ad" | "na" | "hy" | "nh" | "ft"
+            | "ne" | "ps" | "ll" | "ds" | "so" | "ta" | "UE" | "ME" | "YS" | "PD" | "HP" | "ti" | "ce" | "ss" | "cs" => {}
+            _ if MDOC_CALLABLE.contains(&macro_name) => {
+                let rendered = render_mdoc(&[vec![macro_name.to_string()], args].concat(), &name);
+                state.text(&rendered);
+            }
+            _ => {}
+        }
+    }
+    state.flush();
+
+    state
+        .sections
+        .into_iter()
+        .filter(|s| !s.blocks.is_empty())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// --help output
+
+/// Split `--help` output into sections using its headers and option tables
+pub fn parse_help_output(output: &str) -> Vec<HelpSection> {
+    let option_line = Regex::new(r"^(\s{0,8})(-{1,2}[A-Za-z0-9?#][^\s]*(?:[ ,=][^\s].*?)?)(?:\s{2,}|\t)(.+)?$").unwrap();
+    let bare_option = Regex::new(r"^(\s{0,8})(-{1,2}[A-Za-z0-9?#][^\s]*(?:,? [^\s]+)*)\s*$").unwrap();
+    let mut sections = vec![HelpSection { title: String::new(), blocks: vec![] }];
+    let mut item_indent = 0usize;
+
+    for line in output.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+        let blocks = &mut sections.last_mut().unwrap().blocks;
+
+        if trimmed.is_empty() {
+            item_indent = usize::MAX;
+            continue;
+        }
+
+        if let Some(usage) = trimmed.strip_prefix("Usage:").or_else(|| trimmed.strip_prefix("usage:")) {
+            sections.push(HelpSection {
+                title: "USAGE".to_string(),
+                blocks: vec![HelpBlock::Preformatted { text: usage.trim().to_string() }],
+            });
+            item_indent = 0;
+            continue;
+        }
+
+        let is_header = indent == 0
+            && !trimmed.starts_with('-')
+            && (trimmed.ends_with(':') || (trimmed.len() > 2 && trimmed.chars().all(|c| c.is_ascii_uppercase() || c == ' ')));
+        if is_header {
+            sections.push(HelpSection {
+                title: trimmed.trim_end_matches(':').to_uppercase(),
+                blocks: vec![],
+            });
+            continue;
+        }
+
+        if let Some(caps) = option_line.captures(line).or_else(|| bare_option.captures(line)) {
+            item_indent = caps[1].len();
+            blocks.push(HelpBlock::Item {
+                tag: caps[2].trim().to_string(),
+                description: caps.get(3).map(|m| m.as_str().trim().to_string()).unwrap_or_default(),
+            });
+            continue;
+        }
+
+        // Wrapped description lines are indented deeper than their option
+        match blocks.last_mut() {
+            Some(HelpBlock::Item { description, .. }) if indent > item_indent => {
+                if !description.is_empty() {
+                    description.push(' ');
+                }
+                description.push_str(trimmed);
+            }
+            Some(HelpBlock::Preformatted { text }) if indent > 0 => {
+                text.push('\n');
+                text.push_str(trimmed);
+            }
+            Some(HelpBlock::Paragraph { text }) => {
+                text.push(' ');
+                text.push_str(trimmed);
+            }
+            _ => blocks.push(HelpBlock::Paragraph { text: trimmed.to_string() }),
+        }
+    }
+
+    sections.into_iter().filter(|s| !s.blocks.is_empty()).collect()
+}
+
+// ---------------------------------------------------------------------------
+// rendering and flags
+
+fn extract_flags(sections: &[HelpSection]) -> Vec<HelpFlag> {
+    let flag_name = Regex::new(r"^(-{1,2}[A-Za-z0-9?#][\w-]*)(.*)$").unwrap();
+    let mut flags = Vec::new();
+
+    for block in sections.iter().flat_map(|s| &s.blocks) {
+        let (tag, description) = match block {
+            HelpBlock::Item { tag, description } if tag.starts_with('-') => (tag, description),
+            _ => continue,
+        };
+        let mut names = Vec::new();
+        let mut argument = None;
+        for part in tag.split(", ") {
+            if let Some(caps) = flag_name.captures(part.trim()) {
+                names.push(caps[1].to_string());
+                let rest = caps[2].trim_start_matches(['=', ' ']).trim();
+                if !rest.is_empty() && argument.is_none() {
+                    argument = Some(rest.to_string());
+                }
+            }
+        }
+        if !names.is_empty() {
+            flags.push(HelpFlag {
+                names,
+                argument,
+                description: description.lines().next().unwrap_or("").to_string(),
+            });
+        }
+    }
+    flags
+}
+
+fn blocks_to_text(blocks: &[HelpBlock]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            HelpBlock::Paragraph { text } => out.push_str(&format!("{}\n\n", text)),
+            HelpBlock::Subheading { text } => out.push_str(&format!("{}\n", text)),
+            HelpBlock::Item { tag, description } => {
+                out.push_str(&format!("{}\n", tag));
+                if !description.is_empty() {
+                    out.push_str(&format!("    {}\n", description.replace('\n', "\n    ")));
+                }
+            }
+            HelpBlock::Preformatted { text } => {
+                for line in text.lines() {
+                    out.push_str(&format!("    {}\n", line));
+                }
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn render_text(sections: &[HelpSection]) -> String {
+    sections
+        .iter()
+        .map(|section| {
+            let body = blocks_to_text(&section.blocks);
+            if section.title.is_empty() {
+                body
+            } else {
+                let indented: Vec<String> = body.lines().map(|l| if l.is_empty() { String::new() } else { format!("  {}", l) }).collect();
+                format!("{}\n{}\n", section.title, indented.join("\n"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_html(command: &str, sections: &[HelpSection]) -> String {
+    let mut html = format!("<article class=\"help-doc\" data-command=\"{}\">\n", escape_html(command));
+    for section in sections {
+        html.push_str("<section>\n");
+        if !section.title.is_empty() {
+            html.push_str(&format!("<h2>{}</h2>\n", escape_html(&section.title)));
+        }
+        let mut in_list = false;
+        for block in &section.blocks {
+            let is_item = matches!(block, HelpBlock::Item { .. });
+            if is_item && !in_list {
+                html.push_str("<dl>\n");
+            } else if !is_item && in_list {
+                html.push_str("</dl>\n");
+            }
+            in_list = is_item;
+            match block {
+                HelpBlock::Paragraph { text } => html.push_str(&format!("<p>{}</p>\n", escape_html(text).replace('\n', "<br>"))),
+                HelpBlock::Subheading { text } => html.push_str(&format!("<h3>{}</h3>\n", escape_html(text))),
+                HelpBlock::Item { tag, description } => html.push_str(&format!(
+                    "<dt><code>{}</code></dt><dd>{}</dd>\n",
+                    escape_html(tag),
+                    escape_html(description).replace('\n', "<br>")
+                )),
+                HelpBlock::Preformatted { text } => html.push_str(&format!("<pre>{}</pre>\n", escape_html(text))),
+            }
+        }
+        if in_list {
+            html.push_str("</dl>\n");
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</article>");
+    html
+}
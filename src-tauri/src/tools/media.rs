@@ -0,0 +1,500 @@
+// Image and media helpers
+// Resize/convert images and strip their metadata natively (no ImageMagick
+// needed), and read audio/video metadata with ffprobe when installed or the
+// built-in audio demuxers otherwise
+
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub path: String,
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub color: String,
+    pub file_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageOutcome {
+    pub input: String,
+    pub output: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaStream {
+    pub kind: String,
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub bit_rate: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub path: String,
+    pub source: String,
+    pub container: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub streams: Vec<MediaStream>,
+    pub tags: HashMap<String, String>,
+}
+
+/// What a natural language media request asks for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum MediaPlan {
+    Shrink { glob: String, name_filter: Option<String>, max_dimension: u32 },
+    Convert { glob: String, target_extension: String },
+    StripMetadata { glob: String },
+}
+
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "webp", "gif", "bmp", "tif", "tiff"];
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn format_from_path(path: &Path) -> Result<ImageFormat, String> {
+    ImageFormat::from_path(path).map_err(|_| format!("Unsupported image format: {}", path.display()))
+}
+
+/// Decode an image with its EXIF orientation applied, so outputs stay upright
+fn open_upright(path: &Path) -> Result<DynamicImage, String> {
+    let reader = ImageReader::open(path)
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let mut decoder = reader.into_decoder().map_err(|e| format!("Cannot decode {}: {}", path.display(), e))?;
+    let orientation = decoder.orientation().ok();
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| format!("Cannot decode {}: {}", path.display(), e))?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok(image)
+}
+
+fn save_image(image: &DynamicImage, output: &Path, quality: u8) -> Result<(), String> {
+    let format = format_from_path(output)?;
+    let result = match format {
+        ImageFormat::Jpeg => {
+            let file = std::fs::File::create(output).map_err(|e| format!("Cannot write {}: {}", output.display(), e))?;
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(std::io::BufWriter::new(file), quality);
+            // JPEG has no alpha channel
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)
+        }
+        _ => image.save_with_format(output, format),
+    };
+    result.map_err(|e| format!("Cannot write {}: {}", output.display(), e))
+}
+
+pub fn image_info(path: &Path) -> Result<ImageInfo, String> {
+    let reader = ImageReader::open(path)
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let format = reader.format().map(|f| format!("{:?}", f)).unwrap_or_else(|| "unknown".to_string());
+    let decoder = reader.into_decoder().map_err(|e| format!("Cannot decode {}: {}", path.display(), e))?;
+    let (width, height) = decoder.dimensions();
+    Ok(ImageInfo {
+        path: path.display().to_string(),
+        format,
+        width,
+        height,
+        color: format!("{:?}", decoder.color_type()),
+        file_size: file_size(path),
+    })
+}
+
+/// Default output next to the input: `shot.png` -> `shot-resized.png`
+pub fn sibling_output(input: &Path, suffix: &str, extension: Option<&str>) -> PathBuf {
+    let stem = input.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = extension
+        .map(str::to_string)
+        .or_else(|| input.extension().map(|e| e.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "png".to_string());
+    input.with_file_name(format!("{}{}.{}", stem, suffix, extension))
+}
+
+/// Resize to fit within width x height (either may be omitted), never upscaling
+pub fn resize_image(
+    input: &Path,
+    output: &Path,
+    width: Option<u32>,
+    height: Option<u32>,
+    quality: u8,
+) -> Result<ImageOutcome, String> {
+    let image = open_upright(input)?;
+    let target_w = width.unwrap_or(u32::MAX).min(image.width());
+    let target_h = height.unwrap_or(u32::MAX).min(image.height());
+    let resized = if target_w < image.width() || target_h < image.height() {
+        image.resize(target_w, target_h, image::imageops::FilterType::Lanczos3)
+    } else {
+        image
+    };
+    save_image(&resized, output, quality)?;
+
+    let (bytes_before, bytes_after) = (file_size(input), file_size(output));
+    Ok(ImageOutcome {
+        input: input.display().to_string(),
+        output: output.display().to_string(),
+        width: resized.width(),
+        height: resized.height(),
+        bytes_before,
+        bytes_after,
+        note: (bytes_after > bytes_before).then(|| "Output is larger than the input".to_string()),
+    })
+}
+
+pub fn convert_image(input: &Path, output: &Path, quality: u8) -> Result<ImageOutcome, String> {
+    resize_image(input, output, None, None, quality)
+}
+
+/// Drop EXIF/XMP/IPTC/comment segments from a JPEG, keeping JFIF, ICC and Adobe markers
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return None;
+    }
+    let mut out = vec![0xFF, 0xD8];
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        if marker == 0xDA {
+            // Start of scan: the rest is entropy-coded data
+            out.extend_from_slice(&data[i..]);
+            return Some(out);
+        }
+        let length = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        let end = i + 2 + length;
+        if end > data.len() {
+            return None;
+        }
+        let drop = matches!(marker, 0xE1 | 0xE3..=0xED | 0xEF | 0xFE);
+        if !drop {
+            out.extend_from_slice(&data[i..end]);
+        }
+        i = end;
+    }
+    None
+}
+
+/// Drop text, timestamp and EXIF chunks from a PNG
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    if data.len() < 8 || data[..8] != SIGNATURE {
+        return None;
+    }
+    let mut out = SIGNATURE.to_vec();
+    let mut i = 8;
+    while i + 12 <= data.len() {
+        let length = u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+        let end = i + 12 + length;
+        if end > data.len() {
+            return None;
+        }
+        let chunk_type = &data[i + 4..i + 8];
+        if !matches!(chunk_type, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(&data[i..end]);
+        }
+        i = end;
+    }
+    Some(out)
+}
+
+/// Remove EXIF and similar metadata; JPEG and PNG are stripped losslessly
+pub fn strip_metadata(input: &Path, output: &Path) -> Result<ImageOutcome, String> {
+    let data = std::fs::read(input).map_err(|e| format!("Cannot read {}: {}", input.display(), e))?;
+    let format = format_from_path(input)?;
+
+    // A rotated JPEG relies on its EXIF orientation; bake it in before dropping the tag
+    let rotated = format == ImageFormat::Jpeg && {
+        let decoder = ImageReader::new(std::io::Cursor::new(&data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|r| r.into_decoder().ok());
+        matches!(decoder.map(|mut d| d.orientation()), Some(Ok(o)) if o != image::metadata::Orientation::NoTransforms)
+    };
+
+    let stripped = match format {
+        ImageFormat::Jpeg if !rotated => strip_jpeg(&data),
+        ImageFormat::Png => strip_png(&data),
+        _ => None,
+    };
+    let note = match stripped {
+        Some(bytes) => {
+            std::fs::write(output, bytes).map_err(|e| format!("Cannot write {}: {}", output.display(), e))?;
+            None
+        }
+        None => {
+            // Re-encoding through the decoder writes no metadata at all
+            let image = open_upright(input)?;
+            save_image(&image, output, 92)?;
+            Some(if rotated {
+                "Re-encoded to keep the EXIF rotation after removing metadata".to_string()
+            } else {
+                "Re-encoded without metadata".to_string()
+            })
+        }
+    };
+
+    let (width, height) = image::image_dimensions(output).map_err(|e| e.to_string())?;
+    Ok(ImageOutcome {
+        input: input.display().to_string(),
+        output: output.display().to_string(),
+        width,
+        height,
+        bytes_before: data.len() as u64,
+        bytes_after: file_size(output),
+        note,
+    })
+}
+
+async fn ffprobe_info(path: &Path) -> Option<MediaInfo> {
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(15),
+        tokio::process::Command::new("ffprobe")
+            .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams"])
+            .arg(path)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let number = |v: &serde_json::Value| v.as_str().and_then(|s| s.parse::<f64>().ok()).or_else(|| v.as_f64());
+
+    let streams = json["streams"]
+        .as_array()
+        .map(|streams| {
+            streams
+                .iter()
+                .map(|s| MediaStream {
+                    kind: s["codec_type"].as_str().unwrap_or("unknown").to_string(),
+                    codec: s["codec_name"].as_str().map(str::to_string),
+                    width: s["width"].as_u64().map(|v| v as u32),
+                    height: s["height"].as_u64().map(|v| v as u32),
+                    sample_rate: number(&s["sample_rate"]).map(|v| v as u32),
+                    channels: s["channels"].as_u64().map(|v| v as u32),
+                    bit_rate: number(&s["bit_rate"]).map(|v| v as u64),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let tags = json["format"]["tags"]
+        .as_object()
+        .map(|tags| tags.iter().map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string())).collect())
+        .unwrap_or_default();
+
+    Some(MediaInfo {
+        path: path.display().to_string(),
+        source: "ffprobe".to_string(),
+        container: json["format"]["format_long_name"].as_str().map(str::to_string),
+        duration_secs: number(&json["format"]["duration"]),
+        streams,
+        tags,
+    })
+}
+
+/// Audio metadata from the built-in demuxers (WAV, FLAC, Ogg/Vorbis, ...)
+fn symphonia_info(path: &Path) -> Result<MediaInfo, String> {
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension() {
+        hint.with_extension(&ext.to_string_lossy());
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported media file (install ffmpeg for video): {}", e))?;
+
+    let mut tags = HashMap::new();
+    if let Some(revision) = probed.format.metadata().current() {
+        for tag in revision.tags() {
+            tags.insert(tag.key.clone(), tag.value.to_string());
+        }
+    }
+
+    let mut duration_secs = None;
+    let streams = probed
+        .format
+        .tracks()
+        .iter()
+        .map(|track| {
+            let params = &track.codec_params;
+            if let (Some(time_base), Some(frames)) = (params.time_base, params.n_frames) {
+                let time = time_base.calc_time(frames);
+                duration_secs.get_or_insert(time.seconds as f64 + time.frac);
+            }
+            MediaStream {
+                kind: "audio".to_string(),
+                codec: symphonia::default::get_codecs()
+                    .get_codec(params.codec)
+                    .map(|codec| codec.short_name.to_string()),
+                width: None,
+                height: None,
+                sample_rate: params.sample_rate,
+                channels: params.channels.map(|c| c.count() as u32),
+                bit_rate: None,
+            }
+        })
+        .collect();
+
+    Ok(MediaInfo {
+        path: path.display().to_string(),
+        source: "builtin".to_string(),
+        container: path.extension().map(|e| e.to_string_lossy().to_uppercase()),
+        duration_secs,
+        streams,
+        tags,
+    })
+}
+
+pub async fn media_info(path: &Path) -> Result<MediaInfo, String> {
+    if let Some(info) = ffprobe_info(path).await {
+        return Ok(info);
+    }
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || symphonia_info(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Recognise common media asks: "shrink these screenshots", "convert all pngs to webp",
+/// "strip exif from every jpg"
+pub fn plan_from_natural_language(request: &str) -> Option<MediaPlan> {
+    let lower = request.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mentioned_ext = words
+        .iter()
+        .map(|w| w.trim_end_matches('s'))
+        .find(|w| IMAGE_EXTENSIONS.contains(w))
+        .map(|w| if w == "jpeg" { "jpg" } else { w });
+    let glob_for = |ext: Option<&str>| match ext {
+        Some(ext) => format!("*.{}", ext),
+        None => "*.{png,jpg,jpeg,webp}".to_string(),
+    };
+    let mentions_images = mentioned_ext.is_some()
+        || words.iter().any(|w| matches!(*w, "image" | "images" | "photo" | "photos" | "screenshot" | "screenshots" | "pictures"));
+    if !mentions_images {
+        return None;
+    }
+    let name_filter = words.iter().any(|w| w.starts_with("screenshot")).then(|| "screen".to_string());
+
+    if words.iter().any(|w| matches!(*w, "shrink" | "resize" | "downscale" | "smaller")) {
+        let max_dimension = words
+            .iter()
+            .filter_map(|w| w.trim_end_matches("px").parse::<u32>().ok())
+            .find(|n| (16..=10_000).contains(n))
+            .unwrap_or(1600);
+        return Some(MediaPlan::Shrink { glob: glob_for(mentioned_ext), name_filter, max_dimension });
+    }
+    if words.iter().any(|w| matches!(*w, "exif" | "metadata" | "gps")) && words.iter().any(|w| matches!(*w, "strip" | "remove" | "clean" | "delete")) {
+        return Some(MediaPlan::StripMetadata { glob: glob_for(mentioned_ext) });
+    }
+    if words.first() == Some(&"convert") {
+        let to = words.iter().position(|w| *w == "to")?;
+        let target = words.get(to + 1)?.trim_end_matches('s');
+        let source = words[..to].iter().map(|w| w.trim_end_matches('s')).find(|w| IMAGE_EXTENSIONS.contains(w));
+        if IMAGE_EXTENSIONS.contains(&target) {
+            return Some(MediaPlan::Convert { glob: glob_for(source), target_extension: target.to_string() });
+        }
+    }
+    None
+}
+
+/// Expand `{a,b}` alternatives, which the glob crate does not support
+fn expand_braces(pattern: &str) -> Vec<String> {
+    match (pattern.find('{'), pattern.find('}')) {
+        (Some(open), Some(close)) if open < close => pattern[open + 1..close]
+            .split(',')
+            .map(|alt| format!("{}{}{}", &pattern[..open], alt, &pattern[close + 1..]))
+            .collect(),
+        _ => vec![pattern.to_string()],
+    }
+}
+
+/// Run a media plan on the images in a directory, writing new files next to the originals
+pub fn run_plan(plan: &MediaPlan, working_dir: &str) -> Result<Vec<Result<ImageOutcome, String>>, String> {
+    let glob = match plan {
+        MediaPlan::Shrink { glob, .. } | MediaPlan::Convert { glob, .. } | MediaPlan::StripMetadata { glob } => glob,
+    };
+    let mut files = Vec::new();
+    for pattern in expand_braces(glob) {
+        files.extend(crate::terminal::parallel::items_from_glob(&pattern, working_dir)?);
+    }
+    if let MediaPlan::Shrink { name_filter: Some(filter), .. } = plan {
+        files.retain(|f| f.to_lowercase().contains(filter));
+    }
+    // Never feed our own outputs back in
+    files.retain(|f| !f.contains("-resized.") && !f.contains("-clean."));
+    if files.is_empty() {
+        return Err(format!("No matching images in {}", working_dir));
+    }
+
+    Ok(files
+        .iter()
+        .map(|file| {
+            let input = Path::new(working_dir).join(file);
+            match plan {
+                MediaPlan::Shrink { max_dimension, .. } => {
+                    let output = sibling_output(&input, "-resized", None);
+                    resize_image(&input, &output, Some(*max_dimension), Some(*max_dimension), 85)
+                }
+                MediaPlan::Convert { target_extension, .. } => {
+                    let output = sibling_output(&input, "", Some(target_extension));
+                    if output == input {
+                        return Err(format!("{} is already a .{} file", file, target_extension));
+                    }
+                    convert_image(&input, &output, 90)
+                }
+                MediaPlan::StripMetadata { .. } => strip_metadata(&input, &sibling_output(&input, "-clean", None)),
+            }
+        })
+        .collect())
+}
+
+/// One line per processed file for terminal output
+pub fn summarize(results: &[Result<ImageOutcome, String>]) -> String {
+    results
+        .iter()
+        .map(|result| match result {
+            Ok(r) => format!(
+                "✅ {} → {} ({}x{}, {} KB → {} KB){}",
+                r.input,
+                r.output,
+                r.width,
+                r.height,
+                r.bytes_before / 1024,
+                r.bytes_after / 1024,
+                r.note.as_ref().map(|n| format!(" — {}", n)).unwrap_or_default()
+            ),
+            Err(e) => format!("❌ {}", e),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,17 @@
+// Developer utilities exposed as console commands: script linting, text and
+// data helpers that work on local files without leaving the terminal
+
+pub mod script_lint;
+pub mod regex_tester;
+pub mod data_preview;
+pub mod compose;
+pub mod redis_inspector;
+pub mod queue_inspector;
+pub mod help_docs;
+pub mod tldr;
+pub mod media;
+pub mod checksum;
+pub mod text_transform;
+pub mod config_format;
+pub mod duplicates;
+pub mod backup;
@@ -0,0 +1,96 @@
+// Message queue inspectors for services running under docker compose
+// Runs the broker's own CLI inside its container, so no client libraries or
+// exposed management ports are needed
+
+use serde::{Deserialize, Serialize};
+
+use super::compose::{ComposeService, ServiceKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueInfo {
+    pub name: String,
+    pub messages: Option<u64>,
+    pub consumers: Option<u64>,
+}
+
+async fn compose_exec(project_dir: &str, service: &str, args: &[&str]) -> Result<String, String> {
+    let mut full_args = vec!["compose", "exec", "-T", service];
+    full_args.extend_from_slice(args);
+
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        tokio::process::Command::new("docker")
+            .args(&full_args)
+            .current_dir(project_dir)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("Timed out inspecting {}", service))?
+    .map_err(|e| format!("Failed to run docker: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Queues (RabbitMQ) or topics (Kafka) of a compose service
+pub async fn list_queues(project_dir: &str, service: &ComposeService) -> Result<Vec<QueueInfo>, String> {
+    match service.kind {
+        ServiceKind::RabbitMq => {
+            let output = compose_exec(
+                project_dir,
+                &service.name,
+                &["rabbitmqctl", "list_queues", "name", "messages", "consumers", "--formatter", "json"],
+            ).await?;
+            let json: serde_json::Value = serde_json::from_str(output.trim())
+                .map_err(|e| format!("Unexpected rabbitmqctl output: {}", e))?;
+            Ok(json.as_array()
+                .map(|queues| {
+                    queues.iter()
+                        .map(|queue| QueueInfo {
+                            name: queue.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string(),
+                            messages: queue.get("messages").and_then(|m| m.as_u64()),
+                            consumers: queue.get("consumers").and_then(|c| c.as_u64()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default())
+        }
+        ServiceKind::Kafka => {
+            // Confluent images ship kafka-topics, Apache/Bitnami ship kafka-topics.sh
+            let args = ["--bootstrap-server", "localhost:9092", "--list"];
+            let output = match compose_exec(project_dir, &service.name, &[&["kafka-topics"][..], &args[..]].concat()).await {
+                Ok(output) => output,
+                Err(_) => compose_exec(project_dir, &service.name, &[&["kafka-topics.sh"][..], &args[..]].concat()).await?,
+            };
+            Ok(output.lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|name| QueueInfo {
+                    name: name.to_string(),
+                    messages: None,
+                    consumers: None,
+                })
+                .collect())
+        }
+        ServiceKind::Redis => Err("Use the Redis inspector for Redis services".to_string()),
+    }
+}
+
+/// Shell commands worth suggesting for a detected service
+pub fn suggested_commands(service: &ComposeService) -> Vec<String> {
+    match service.kind {
+        ServiceKind::Redis => vec![
+            format!("docker compose exec {} redis-cli ping", service.name),
+            format!("docker compose exec {} redis-cli --scan --count 100", service.name),
+            format!("docker compose exec {} redis-cli info memory", service.name),
+        ],
+        ServiceKind::RabbitMq => vec![
+            format!("docker compose exec {} rabbitmqctl list_queues name messages consumers", service.name),
+        ],
+        ServiceKind::Kafka => vec![
+            format!("docker compose exec {} kafka-topics --bootstrap-server localhost:9092 --list", service.name),
+        ],
+    }
+}
@@ -0,0 +1,168 @@
+// Redis inspector: key scans, value lookups and memory statistics
+
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const MAX_COLLECTION_ITEMS: isize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisKeyInfo {
+    pub key: String,
+    pub key_type: String,
+    pub ttl: i64, // seconds, -1 = no expiry
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisValue {
+    pub key: String,
+    pub key_type: String,
+    pub ttl: i64,
+    pub value: Value,
+    pub length: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisMemoryInfo {
+    pub key_count: u64,
+    pub used_memory_human: Option<String>,
+    pub peak_memory_human: Option<String>,
+    pub maxmemory_policy: Option<String>,
+    pub fields: HashMap<String, String>,
+}
+
+async fn connect(url: &str) -> Result<MultiplexedConnection, String> {
+    let client = redis::Client::open(url).map_err(|e| format!("Invalid Redis URL: {}", e))?;
+    tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        client.get_multiplexed_async_connection(),
+    )
+    .await
+    .map_err(|_| format!("Timed out connecting to {}", url))?
+    .map_err(|e| format!("Failed to connect to {}: {}", url, e))
+}
+
+/// SCAN for keys matching a pattern, with their types and TTLs
+pub async fn scan_keys(url: &str, pattern: &str, limit: usize) -> Result<Vec<RedisKeyInfo>, String> {
+    let mut connection = connect(url).await?;
+    let mut cursor: u64 = 0;
+    let mut keys: Vec<String> = Vec::new();
+
+    loop {
+        let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(500)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+        keys.extend(batch);
+        cursor = next;
+        if cursor == 0 || keys.len() >= limit {
+            break;
+        }
+    }
+    keys.truncate(limit);
+
+    let mut pipe = redis::pipe();
+    for key in &keys {
+        pipe.cmd("TYPE").arg(key).cmd("TTL").arg(key);
+    }
+    let details: Vec<redis::Value> = pipe.query_async(&mut connection).await.map_err(|e| e.to_string())?;
+
+    Ok(keys.into_iter()
+        .zip(details.chunks(2))
+        .map(|(key, detail)| RedisKeyInfo {
+            key,
+            key_type: detail.first().and_then(|v| redis::from_redis_value::<String>(v).ok()).unwrap_or_default(),
+            ttl: detail.get(1).and_then(|v| redis::from_redis_value::<i64>(v).ok()).unwrap_or(-1),
+        })
+        .collect())
+}
+
+/// Value of a key in a shape that suits its type; collections are capped
+pub async fn get_key(url: &str, key: &str) -> Result<RedisValue, String> {
+    let mut connection = connect(url).await?;
+    let (key_type, ttl): (String, i64) = redis::pipe()
+        .cmd("TYPE").arg(key)
+        .cmd("TTL").arg(key)
+        .query_async(&mut connection)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let query_err = |e: redis::RedisError| e.to_string();
+    let (value, length) = match key_type.as_str() {
+        "none" => return Err(format!("Key not found: {}", key)),
+        "string" => {
+            let value: Option<String> = redis::cmd("GET").arg(key).query_async(&mut connection).await.map_err(query_err)?;
+            (value.map(Value::String).unwrap_or(Value::Null), None)
+        }
+        "list" => {
+            let length: u64 = redis::cmd("LLEN").arg(key).query_async(&mut connection).await.map_err(query_err)?;
+            let items: Vec<String> = redis::cmd("LRANGE").arg(key).arg(0).arg(MAX_COLLECTION_ITEMS - 1)
+                .query_async(&mut connection).await.map_err(query_err)?;
+            (Value::from(items), Some(length))
+        }
+        "set" => {
+            let length: u64 = redis::cmd("SCARD").arg(key).query_async(&mut connection).await.map_err(query_err)?;
+            let (_, members): (u64, Vec<String>) = redis::cmd("SSCAN").arg(key).arg(0).arg("COUNT").arg(MAX_COLLECTION_ITEMS)
+                .query_async(&mut connection).await.map_err(query_err)?;
+            (Value::from(members), Some(length))
+        }
+        "hash" => {
+            let length: u64 = redis::cmd("HLEN").arg(key).query_async(&mut connection).await.map_err(query_err)?;
+            let (_, fields): (u64, Vec<(String, String)>) = redis::cmd("HSCAN").arg(key).arg(0).arg("COUNT").arg(MAX_COLLECTION_ITEMS)
+                .query_async(&mut connection).await.map_err(query_err)?;
+            let map: serde_json::Map<String, Value> = fields.into_iter()
+                .map(|(field, value)| (field, Value::String(value)))
+                .collect();
+            (Value::Object(map), Some(length))
+        }
+        "zset" => {
+            let length: u64 = redis::cmd("ZCARD").arg(key).query_async(&mut connection).await.map_err(query_err)?;
+            let members: Vec<(String, f64)> = redis::cmd("ZRANGE").arg(key).arg(0).arg(MAX_COLLECTION_ITEMS - 1).arg("WITHSCORES")
+                .query_async(&mut connection).await.map_err(query_err)?;
+            let items: Vec<Value> = members.into_iter()
+                .map(|(member, score)| serde_json::json!({ "member": member, "score": score }))
+                .collect();
+            (Value::from(items), Some(length))
+        }
+        "stream" => {
+            let length: u64 = redis::cmd("XLEN").arg(key).query_async(&mut connection).await.map_err(query_err)?;
+            (Value::String(format!("stream with {} entries (use XRANGE to read)", length)), Some(length))
+        }
+        other => (Value::String(format!("<{}>", other)), None),
+    };
+
+    Ok(RedisValue {
+        key: key.to_string(),
+        key_type,
+        ttl,
+        value,
+        length,
+    })
+}
+
+/// INFO memory plus DBSIZE
+pub async fn memory_info(url: &str) -> Result<RedisMemoryInfo, String> {
+    let mut connection = connect(url).await?;
+    let info: String = redis::cmd("INFO").arg("memory").query_async(&mut connection).await.map_err(|e| e.to_string())?;
+    let key_count: u64 = redis::cmd("DBSIZE").query_async(&mut connection).await.map_err(|e| e.to_string())?;
+
+    let fields: HashMap<String, String> = info.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Ok(RedisMemoryInfo {
+        key_count,
+        used_memory_human: fields.get("used_memory_human").cloned(),
+        peak_memory_human: fields.get("used_memory_peak_human").cloned(),
+        maxmemory_policy: fields.get("maxmemory_policy").cloned(),
+        fields,
+    })
+}
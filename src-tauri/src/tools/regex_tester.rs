@@ -0,0 +1,351 @@
+// Regex builder and tester
+// Tests patterns against sample text line by line (as grep would) and turns
+// plain-language descriptions into verified patterns
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegexFlavor {
+    Rust,
+    Pcre,
+    JavaScript,
+    Posix,
+}
+
+impl RegexFlavor {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "rust" | "rg" | "ripgrep" => Ok(RegexFlavor::Rust),
+            "pcre" | "perl" | "python" | "grep -p" => Ok(RegexFlavor::Pcre),
+            "javascript" | "js" | "ecmascript" => Ok(RegexFlavor::JavaScript),
+            "posix" | "ere" | "grep" | "grep -e" | "egrep" => Ok(RegexFlavor::Posix),
+            other => Err(format!("Unsupported regex flavor: {}", other)),
+        }
+    }
+
+    /// Command that searches files with this flavor of pattern
+    pub fn grep_command(&self, pattern: &str) -> String {
+        let quoted = format!("'{}'", pattern.replace('\'', r"'\''"));
+        match self {
+            RegexFlavor::Rust => format!("rg {}", quoted),
+            RegexFlavor::Pcre | RegexFlavor::JavaScript => format!("grep -P {}", quoted),
+            RegexFlavor::Posix => format!("grep -E {}", quoted),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub groups: Vec<Option<String>>,
+    pub named_groups: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexTestResult {
+    pub valid: bool,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+    pub matches: Vec<RegexMatch>,
+    pub matched_lines: Vec<usize>,
+    pub grep_command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexTestCase {
+    pub input: String,
+    pub should_match: bool,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexSuggestion {
+    pub pattern: String,
+    pub flavor: RegexFlavor,
+    pub explanation: String,
+    pub test_cases: Vec<RegexTestCase>,
+    pub verified: bool,
+    pub grep_command: String,
+}
+
+const MAX_MATCHES: usize = 1000;
+
+/// Accept JavaScript-style literals (/pattern/flags) by folding flags inline
+fn normalize_pattern(pattern: &str, flavor: RegexFlavor) -> String {
+    if flavor == RegexFlavor::JavaScript && pattern.starts_with('/') {
+        if let Some(end) = pattern.rfind('/').filter(|end| *end > 0) {
+            let body = &pattern[1..end];
+            let flags: String = pattern[end + 1..].chars().filter(|c| "ims".contains(*c)).collect();
+            return if flags.is_empty() {
+                body.to_string()
+            } else {
+                format!("(?{}){}", flags, body)
+            };
+        }
+    }
+    pattern.to_string()
+}
+
+fn posix_warnings(pattern: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (escape, class) in [(r"\d", "[[:digit:]]"), (r"\w", "[[:alnum:]_]"), (r"\s", "[[:space:]]")] {
+        if pattern.contains(escape) {
+            warnings.push(format!("{} is not POSIX; use {} for portable grep -E", escape, class));
+        }
+    }
+    if pattern.contains("(?") {
+        warnings.push("Inline groups and lookarounds are not supported by POSIX ERE".to_string());
+    }
+    if pattern.contains("*?") || pattern.contains("+?") {
+        warnings.push("Lazy quantifiers are not supported by POSIX ERE".to_string());
+    }
+    warnings
+}
+
+enum CompiledRegex {
+    Standard(regex::Regex),
+    Fancy(fancy_regex::Regex),
+}
+
+impl CompiledRegex {
+    fn compile(pattern: &str, flavor: RegexFlavor) -> Result<Self, String> {
+        match flavor {
+            RegexFlavor::Rust | RegexFlavor::Posix => regex::Regex::new(pattern)
+                .map(CompiledRegex::Standard)
+                .map_err(|e| e.to_string()),
+            // Lookarounds and backreferences need the backtracking engine
+            RegexFlavor::Pcre | RegexFlavor::JavaScript => fancy_regex::Regex::new(pattern)
+                .map(CompiledRegex::Fancy)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn group_names(&self) -> Vec<Option<String>> {
+        match self {
+            CompiledRegex::Standard(re) => re.capture_names().map(|n| n.map(|n| n.to_string())).collect(),
+            CompiledRegex::Fancy(re) => re.capture_names().map(|n| n.map(|n| n.to_string())).collect(),
+        }
+    }
+
+    /// All matches in a line as (start, end, groups)
+    fn find_all(&self, line: &str) -> Vec<(usize, usize, Vec<Option<String>>)> {
+        let mut results = Vec::new();
+        match self {
+            CompiledRegex::Standard(re) => {
+                for caps in re.captures_iter(line) {
+                    let whole = caps.get(0).unwrap();
+                    let groups = caps.iter().skip(1).map(|g| g.map(|g| g.as_str().to_string())).collect();
+                    results.push((whole.start(), whole.end(), groups));
+                }
+            }
+            CompiledRegex::Fancy(re) => {
+                for caps in re.captures_iter(line).flatten() {
+                    let whole = caps.get(0).unwrap();
+                    let groups = caps.iter().skip(1).map(|g| g.map(|g| g.as_str().to_string())).collect();
+                    results.push((whole.start(), whole.end(), groups));
+                }
+            }
+        }
+        results
+    }
+}
+
+pub fn test_regex(pattern: &str, sample: &str, flavor: RegexFlavor) -> RegexTestResult {
+    let normalized = normalize_pattern(pattern, flavor);
+    let warnings = if flavor == RegexFlavor::Posix { posix_warnings(&normalized) } else { Vec::new() };
+    let grep_command = flavor.grep_command(&normalized);
+
+    let compiled = match CompiledRegex::compile(&normalized, flavor) {
+        Ok(compiled) => compiled,
+        Err(error) => {
+            return RegexTestResult {
+                valid: false,
+                error: Some(error),
+                warnings,
+                matches: Vec::new(),
+                matched_lines: Vec::new(),
+                grep_command,
+            };
+        }
+    };
+
+    let names: Vec<Option<String>> = compiled.group_names().into_iter().skip(1).collect();
+    let mut matches = Vec::new();
+    let mut matched_lines = Vec::new();
+
+    for (index, line) in sample.lines().enumerate() {
+        let found = compiled.find_all(line);
+        if !found.is_empty() {
+            matched_lines.push(index + 1);
+        }
+        for (start, end, groups) in found {
+            if matches.len() >= MAX_MATCHES {
+                break;
+            }
+            let named_groups = names.iter()
+                .zip(groups.iter())
+                .filter_map(|(name, value)| Some((name.clone()?, value.clone()?)))
+                .collect();
+            matches.push(RegexMatch {
+                line: index + 1,
+                start,
+                end,
+                text: line[start..end].to_string(),
+                groups,
+                named_groups,
+            });
+        }
+    }
+
+    RegexTestResult {
+        valid: true,
+        error: None,
+        warnings,
+        matches,
+        matched_lines,
+        grep_command,
+    }
+}
+
+struct KnownPattern {
+    keywords: &'static [&'static str],
+    pattern: &'static str,
+    posix_pattern: &'static str,
+    explanation: &'static str,
+    matching: &'static [&'static str],
+    non_matching: &'static [&'static str],
+}
+
+const KNOWN_PATTERNS: &[KnownPattern] = &[
+    KnownPattern {
+        keywords: &["ipv4", "ip address", "ip addresses"],
+        pattern: r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b",
+        posix_pattern: r"\b(([0-9]|[1-9][0-9]|1[0-9]{2}|2[0-4][0-9]|25[0-5])\.){3}([0-9]|[1-9][0-9]|1[0-9]{2}|2[0-4][0-9]|25[0-5])\b",
+        explanation: "Four dot-separated octets, each limited to 0-255",
+        matching: &["server at 192.168.1.10 is up", "10.0.0.1", "255.255.255.0"],
+        non_matching: &["999.1.1.1", "version 1.2.3", "no address here"],
+    },
+    KnownPattern {
+        keywords: &["email", "e-mail"],
+        pattern: r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+        posix_pattern: r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+        explanation: "Local part, @, then a domain with a top-level domain of at least two letters",
+        matching: &["contact: dev@example.com", "first.last+tag@mail.co.uk"],
+        non_matching: &["user@localhost", "not an email"],
+    },
+    KnownPattern {
+        keywords: &["url", "link", "http"],
+        pattern: r#"https?://[^\s"'<>]+"#,
+        posix_pattern: r#"https?://[^[:space:]"'<>]+"#,
+        explanation: "http or https scheme followed by any non-whitespace characters",
+        matching: &["see https://example.com/docs?x=1", "http://localhost:3000"],
+        non_matching: &["ftp://example.com", "example.com"],
+    },
+    KnownPattern {
+        keywords: &["uuid", "guid"],
+        pattern: r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b",
+        posix_pattern: r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+        explanation: "32 hex digits in 8-4-4-4-12 groups",
+        matching: &["id=550e8400-e29b-41d4-a716-446655440000"],
+        non_matching: &["550e8400-e29b-41d4-a716", "not-a-uuid"],
+    },
+    KnownPattern {
+        keywords: &["date", "iso date", "yyyy-mm-dd"],
+        pattern: r"\b\d{4}-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12]\d|3[01])\b",
+        posix_pattern: r"[0-9]{4}-(0[1-9]|1[0-2])-(0[1-9]|[12][0-9]|3[01])",
+        explanation: "ISO 8601 date: four-digit year, month 01-12, day 01-31",
+        matching: &["released 2024-03-15", "2023-12-31"],
+        non_matching: &["2024-13-01", "15/03/2024"],
+    },
+    KnownPattern {
+        keywords: &["time", "timestamp", "hh:mm"],
+        pattern: r"\b(?:[01]\d|2[0-3]):[0-5]\d(?::[0-5]\d)?\b",
+        posix_pattern: r"([01][0-9]|2[0-3]):[0-5][0-9](:[0-5][0-9])?",
+        explanation: "24-hour time with optional seconds",
+        matching: &["at 14:30", "23:59:59"],
+        non_matching: &["25:00", "12.30"],
+    },
+    KnownPattern {
+        keywords: &["version", "semver", "semantic version"],
+        pattern: r"\bv?\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?\b",
+        posix_pattern: r"v?[0-9]+\.[0-9]+\.[0-9]+(-[0-9A-Za-z.-]+)?",
+        explanation: "MAJOR.MINOR.PATCH with optional leading v and pre-release suffix",
+        matching: &["v1.2.3", "upgraded to 10.0.1-beta.2"],
+        non_matching: &["1.2", "version one"],
+    },
+    KnownPattern {
+        keywords: &["mac address", "hardware address"],
+        pattern: r"\b(?:[0-9A-Fa-f]{2}[:-]){5}[0-9A-Fa-f]{2}\b",
+        posix_pattern: r"([0-9A-Fa-f]{2}[:-]){5}[0-9A-Fa-f]{2}",
+        explanation: "Six pairs of hex digits separated by colons or dashes",
+        matching: &["ether 00:1a:2b:3c:4d:5e", "00-1A-2B-3C-4D-5E"],
+        non_matching: &["00:1a:2b:3c:4d", "zz:zz:zz:zz:zz:zz"],
+    },
+    KnownPattern {
+        keywords: &["hex color", "colour", "color code"],
+        pattern: r"#(?:[0-9a-fA-F]{3}){1,2}\b",
+        posix_pattern: r"#([0-9a-fA-F]{3}){1,2}",
+        explanation: "# followed by three or six hex digits",
+        matching: &["color: #fff;", "#1a2B3c"],
+        non_matching: &["#ggg", "fff"],
+    },
+    KnownPattern {
+        keywords: &["error", "warning", "log level"],
+        pattern: r"(?i)\b(?:error|fatal|warn(?:ing)?)\b",
+        posix_pattern: r"\b([Ee][Rr][Rr][Oo][Rr]|[Ff][Aa][Tt][Aa][Ll]|[Ww][Aa][Rr][Nn]([Ii][Nn][Gg])?)\b",
+        explanation: "Case-insensitive error, fatal, warn or warning as whole words",
+        matching: &["ERROR: disk full", "Warning: deprecated"],
+        non_matching: &["INFO: started", "errors_total=0 counter"],
+    },
+    KnownPattern {
+        keywords: &["number", "integer", "digits"],
+        pattern: r"-?\b\d+\b",
+        posix_pattern: r"-?[0-9]+",
+        explanation: "An optionally negative whole number",
+        matching: &["count 42", "-7"],
+        non_matching: &["no digits"],
+    },
+];
+
+/// Run a candidate pattern against its test cases
+pub fn verify(pattern: &str, flavor: RegexFlavor, cases: Vec<(String, bool)>) -> (Vec<RegexTestCase>, bool) {
+    let results: Vec<RegexTestCase> = cases.into_iter()
+        .map(|(input, should_match)| {
+            let matched = !test_regex(pattern, &input, flavor).matches.is_empty();
+            RegexTestCase {
+                passed: matched == should_match,
+                input,
+                should_match,
+            }
+        })
+        .collect();
+    let verified = !results.is_empty() && results.iter().all(|case| case.passed);
+    (results, verified)
+}
+
+/// Look up a well-known pattern for a description and verify it against its test cases
+pub fn build_known_regex(description: &str, flavor: RegexFlavor) -> Option<RegexSuggestion> {
+    let description = description.to_lowercase();
+    let known = KNOWN_PATTERNS.iter()
+        .find(|known| known.keywords.iter().any(|keyword| description.contains(keyword)))?;
+
+    let pattern = if flavor == RegexFlavor::Posix { known.posix_pattern } else { known.pattern };
+    let cases = known.matching.iter().map(|input| (input.to_string(), true))
+        .chain(known.non_matching.iter().map(|input| (input.to_string(), false)))
+        .collect();
+    let (test_cases, verified) = verify(pattern, flavor, cases);
+
+    Some(RegexSuggestion {
+        pattern: pattern.to_string(),
+        flavor,
+        explanation: known.explanation.to_string(),
+        test_cases,
+        verified,
+        grep_command: flavor.grep_command(pattern),
+    })
+}
@@ -0,0 +1,234 @@
+// Shell script linting
+// Uses shellcheck when it is installed and falls back to a small set of
+// embedded rules modelled on the most common shellcheck findings
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptLanguage {
+    Bash,
+    PowerShell,
+}
+
+impl ScriptLanguage {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "bash" | "sh" | "shell" | "zsh" => Ok(ScriptLanguage::Bash),
+            "powershell" | "pwsh" | "ps1" => Ok(ScriptLanguage::PowerShell),
+            other => Err(format!("Unsupported script language: {}", other)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScriptLanguage::Bash => "sh",
+            ScriptLanguage::PowerShell => "ps1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintIssue {
+    pub line: usize,
+    pub column: usize,
+    pub severity: String, // error, warning, info, style
+    pub code: String,
+    pub message: String,
+}
+
+/// Lint a script, preferring shellcheck for bash when available
+pub async fn lint_script(script: &str, language: ScriptLanguage) -> (Vec<LintIssue>, &'static str) {
+    if language == ScriptLanguage::Bash {
+        if let Some(issues) = run_shellcheck(script).await {
+            return (issues, "shellcheck");
+        }
+    }
+    (lint_embedded(script, language), "embedded")
+}
+
+async fn run_shellcheck(script: &str) -> Option<Vec<LintIssue>> {
+    let mut child = tokio::process::Command::new("shellcheck")
+        .args(["--format=json", "--shell=bash", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let mut stdin = child.stdin.take()?;
+    stdin.write_all(script.as_bytes()).await.ok()?;
+    drop(stdin);
+
+    let output = tokio::time::timeout(std::time::Duration::from_secs(15), child.wait_with_output())
+        .await
+        .ok()?
+        .ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+
+    Some(json.as_array()?
+        .iter()
+        .map(|item| LintIssue {
+            line: item.get("line").and_then(|l| l.as_u64()).unwrap_or(0) as usize,
+            column: item.get("column").and_then(|c| c.as_u64()).unwrap_or(0) as usize,
+            severity: item.get("level").and_then(|l| l.as_str()).unwrap_or("warning").to_string(),
+            code: format!("SC{}", item.get("code").and_then(|c| c.as_u64()).unwrap_or(0)),
+            message: item.get("message").and_then(|m| m.as_str()).unwrap_or("").to_string(),
+        })
+        .collect())
+}
+
+fn issue(line: usize, column: usize, severity: &str, code: &str, message: &str) -> LintIssue {
+    LintIssue {
+        line,
+        column,
+        severity: severity.to_string(),
+        code: code.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Embedded rules for when shellcheck is not installed
+pub fn lint_embedded(script: &str, language: ScriptLanguage) -> Vec<LintIssue> {
+    match language {
+        ScriptLanguage::Bash => lint_bash(script),
+        ScriptLanguage::PowerShell => lint_powershell(script),
+    }
+}
+
+fn lint_bash(script: &str) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    if !script.starts_with("#!") {
+        issues.push(issue(1, 1, "error", "SC2148", "Add a shebang (e.g. #!/usr/bin/env bash) so the target shell is known"));
+    }
+    let strict_mode = script.lines().any(|line| {
+        let line = line.trim();
+        line.starts_with("set -e") || line.starts_with("set -o errexit") || line.contains("set -euo")
+    });
+
+    let unquoted_var = Regex::new(r#"\$\{?[A-Za-z_][A-Za-z0-9_]*\}?"#).unwrap();
+    let dangerous_rm = Regex::new(r#"rm\s+-[a-zA-Z]*r[a-zA-Z]*f?\s+("?\$\{?\w+\}?"?)/"#).unwrap();
+    let for_ls = Regex::new(r#"for\s+\w+\s+in\s+\$\(ls"#).unwrap();
+    let assignment = Regex::new(r"^\s*(export\s+|local\s+)?\w+=").unwrap();
+
+    for (index, raw_line) in script.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw_line.trim_start();
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+
+        if let Some(col) = raw_line.find('`') {
+            issues.push(issue(line_no, col + 1, "style", "SC2006", "Use $(...) instead of legacy backticks"));
+        }
+
+        if (line.starts_with("read ") || line.contains("| read ")) && !line.contains(" -r") {
+            issues.push(issue(line_no, 1, "info", "SC2162", "read without -r will mangle backslashes"));
+        }
+
+        if line.starts_with("cd ") && !strict_mode && !line.contains("||") {
+            issues.push(issue(line_no, 1, "warning", "SC2164", "Use 'cd ... || exit' in case cd fails"));
+        }
+
+        if let Some(m) = for_ls.find(raw_line) {
+            issues.push(issue(line_no, m.start() + 1, "error", "SC2045", "Iterating over ls output is fragile; use globs"));
+        }
+
+        if let Some(m) = dangerous_rm.find(raw_line) {
+            issues.push(issue(
+                line_no,
+                m.start() + 1,
+                "warning",
+                "SC2115",
+                "Use \"${var:?}\" to ensure this never expands to /",
+            ));
+        }
+
+        // Variables expanded outside double quotes are subject to word splitting
+        let mut in_double = false;
+        let mut in_single = false;
+        let mut quoted = vec![false; raw_line.len()];
+        for (pos, ch) in raw_line.char_indices() {
+            match ch {
+                '"' if !in_single => in_double = !in_double,
+                '\'' if !in_double => in_single = !in_single,
+                _ => {}
+            }
+            if in_double || in_single {
+                quoted[pos] = true;
+            }
+        }
+        // Assignments and [[ ]] tests do not word-split
+        let splits = !assignment.is_match(raw_line) && !raw_line.contains("[[");
+        for m in unquoted_var.find_iter(raw_line) {
+            if splits && !quoted[m.start()] {
+                issues.push(issue(
+                    line_no,
+                    m.start() + 1,
+                    "info",
+                    "SC2086",
+                    "Double quote to prevent globbing and word splitting",
+                ));
+                break;
+            }
+        }
+    }
+
+    issues
+}
+
+fn lint_powershell(script: &str) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    if !script.contains("$ErrorActionPreference") {
+        issues.push(issue(1, 1, "info", "PSStrictErrors", "Set $ErrorActionPreference = 'Stop' so failures halt the script"));
+    }
+
+    let alias = Regex::new(r"(?i)(^|\|\s*)(ls|dir|cat|gci|%|\?|echo|rm|cp|mv)\s").unwrap();
+    for (index, line) in script.lines().enumerate() {
+        let line_no = index + 1;
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('#') || trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(m) = alias.captures(line).and_then(|c| c.get(2)) {
+            issues.push(issue(
+                line_no,
+                m.start() + 1,
+                "warning",
+                "PSAvoidUsingCmdletAliases",
+                "Use the full cmdlet name instead of an alias",
+            ));
+        }
+        if let Some(col) = line.to_lowercase().find("invoke-expression") {
+            issues.push(issue(line_no, col + 1, "warning", "PSAvoidUsingInvokeExpression", "Invoke-Expression can run injected code"));
+        }
+        if let Some(col) = line.find("Write-Host") {
+            issues.push(issue(line_no, col + 1, "style", "PSAvoidUsingWriteHost", "Prefer Write-Output so output can be piped"));
+        }
+    }
+
+    issues
+}
+
+/// Wrap generated commands into a script with a safe preamble
+pub fn build_script(description: &str, body: &str, language: ScriptLanguage) -> String {
+    let body = body.trim().replace("🤖 ", "");
+    match language {
+        ScriptLanguage::Bash => format!(
+            "#!/usr/bin/env bash\n# {}\nset -euo pipefail\n\n{}\n",
+            description.trim(),
+            body
+        ),
+        ScriptLanguage::PowerShell => format!(
+            "# {}\n$ErrorActionPreference = 'Stop'\n\n{}\n",
+            description.trim(),
+            body
+        ),
+    }
+}
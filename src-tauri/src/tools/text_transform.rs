@@ -0,0 +1,268 @@
+// Everyday developer text transforms, computed locally
+// base64, URL encoding, JWT inspection, UUIDs and epoch <-> ISO timestamps,
+// reachable as typed commands, `/slash` commands and plain-language requests
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    JwtDecode,
+    Uuid,
+    EpochToIso,
+    IsoToEpoch,
+}
+
+impl Transform {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "base64" | "base64_encode" | "b64" => Ok(Transform::Base64Encode),
+            "base64_decode" | "unbase64" | "b64d" => Ok(Transform::Base64Decode),
+            "urlencode" | "url_encode" => Ok(Transform::UrlEncode),
+            "urldecode" | "url_decode" => Ok(Transform::UrlDecode),
+            "jwt" | "jwt_decode" => Ok(Transform::JwtDecode),
+            "uuid" | "uuidgen" => Ok(Transform::Uuid),
+            "epoch" | "epoch_to_iso" | "from_epoch" => Ok(Transform::EpochToIso),
+            "iso" | "iso_to_epoch" | "to_epoch" | "timestamp" => Ok(Transform::IsoToEpoch),
+            other => Err(format!("Unknown transform: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformResult {
+    pub transform: Transform,
+    pub output: String,
+    /// Structured extras, e.g. decoded JWT claims
+    pub details: Option<Value>,
+    pub warnings: Vec<String>,
+}
+
+impl TransformResult {
+    fn text(transform: Transform, output: String) -> Self {
+        Self { transform, output, details: None, warnings: vec![] }
+    }
+}
+
+fn url_encode(input: &str) -> String {
+    let mut out = String::new();
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn url_decode(input: &str) -> Result<String, String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = input.get(i + 1..i + 3).ok_or("Truncated %-escape")?;
+                out.push(u8::from_str_radix(hex, 16).map_err(|_| format!("Invalid %-escape: %{}", hex))?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| "Decoded bytes are not valid UTF-8".to_string())
+}
+
+fn base64_decode(input: &str) -> Result<String, String> {
+    let cleaned: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    // Accept both alphabets, padded or not
+    let normalized = cleaned.replace('-', "+").replace('_', "/");
+    let padded = format!("{}{}", normalized.trim_end_matches('='), "=".repeat((4 - normalized.trim_end_matches('=').len() % 4) % 4));
+    let bytes = STANDARD.decode(padded).map_err(|e| format!("Invalid base64: {}", e))?;
+    String::from_utf8(bytes.clone()).or_else(|_| Ok(format!("(binary, {} bytes) {}", bytes.len(), hex::encode(&bytes))))
+}
+
+fn iso_from_seconds(seconds: i64) -> Option<String> {
+    Utc.timestamp_opt(seconds, 0).single().map(|t| t.to_rfc3339())
+}
+
+fn jwt_decode(token: &str) -> Result<TransformResult, String> {
+    let token = token.trim().trim_start_matches("Bearer ").trim();
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(format!("A JWT has 3 dot-separated parts, this has {}", parts.len()));
+    }
+    let decode_part = |part: &str, name: &str| -> Result<Value, String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(part.trim_end_matches('='))
+            .map_err(|e| format!("Invalid base64url in JWT {}: {}", name, e))?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("JWT {} is not JSON: {}", name, e))
+    };
+    let header = decode_part(parts[0], "header")?;
+    let payload = decode_part(parts[1], "payload")?;
+
+    let mut warnings = vec!["Signature was not verified".to_string()];
+    let mut times = serde_json::Map::new();
+    for claim in ["iat", "nbf", "exp"] {
+        if let Some(iso) = payload.get(claim).and_then(Value::as_i64).and_then(iso_from_seconds) {
+            times.insert(claim.to_string(), Value::String(iso));
+        }
+    }
+    let now = Utc::now().timestamp();
+    if let Some(exp) = payload.get("exp").and_then(Value::as_i64) {
+        if exp < now {
+            warnings.push(format!("Token expired {}", iso_from_seconds(exp).unwrap_or_default()));
+        }
+    }
+    if let Some(nbf) = payload.get("nbf").and_then(Value::as_i64) {
+        if nbf > now {
+            warnings.push("Token is not valid yet (nbf is in the future)".to_string());
+        }
+    }
+    if header.get("alg").and_then(Value::as_str) == Some("none") {
+        warnings.push("Token uses alg \"none\" (unsigned)".to_string());
+    }
+
+    let output = format!(
+        "Header:\n{}\n\nPayload:\n{}",
+        serde_json::to_string_pretty(&header).unwrap_or_default(),
+        serde_json::to_string_pretty(&payload).unwrap_or_default()
+    );
+    Ok(TransformResult {
+        transform: Transform::JwtDecode,
+        output,
+        details: Some(serde_json::json!({ "header": header, "payload": payload, "times": times })),
+        warnings,
+    })
+}
+
+fn epoch_to_iso(input: &str) -> Result<TransformResult, String> {
+    let value: i64 = input.trim().parse().map_err(|_| format!("'{}' is not an epoch timestamp", input.trim()))?;
+    // Guess the unit from the magnitude
+    let (unit, nanos) = match value.unsigned_abs() {
+        v if v >= 100_000_000_000_000_000 => ("nanoseconds", value as i128),
+        v if v >= 100_000_000_000_000 => ("microseconds", value as i128 * 1_000),
+        v if v >= 100_000_000_000 => ("milliseconds", value as i128 * 1_000_000),
+        _ => ("seconds", value as i128 * 1_000_000_000),
+    };
+    let utc = DateTime::from_timestamp((nanos / 1_000_000_000) as i64, (nanos.rem_euclid(1_000_000_000)) as u32)
+        .ok_or("Timestamp out of range")?;
+    let local = utc.with_timezone(&chrono::Local);
+    Ok(TransformResult {
+        transform: Transform::EpochToIso,
+        output: format!("{} (UTC)\n{} (local)", utc.to_rfc3339(), local.to_rfc3339()),
+        details: Some(serde_json::json!({ "unit": unit, "utc": utc.to_rfc3339(), "local": local.to_rfc3339() })),
+        warnings: vec![],
+    })
+}
+
+fn iso_to_epoch(input: &str) -> Result<TransformResult, String> {
+    let input = input.trim();
+    let mut warnings = vec![];
+    let parsed: DateTime<Utc> = if let Ok(t) = DateTime::parse_from_rfc3339(input) {
+        t.with_timezone(&Utc)
+    } else if let Ok(t) = DateTime::parse_from_rfc2822(input) {
+        t.with_timezone(&Utc)
+    } else if let Ok(t) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S"))
+    {
+        warnings.push("No timezone given, assumed UTC".to_string());
+        t.and_utc()
+    } else if let Ok(d) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        warnings.push("No time given, assumed midnight UTC".to_string());
+        d.and_hms_opt(0, 0, 0).ok_or("Invalid date")?.and_utc()
+    } else {
+        return Err(format!("Cannot parse '{}' as a date/time", input));
+    };
+    Ok(TransformResult {
+        transform: Transform::IsoToEpoch,
+        output: format!("{} (seconds)\n{} (milliseconds)", parsed.timestamp(), parsed.timestamp_millis()),
+        details: None,
+        warnings,
+    })
+}
+
+pub fn apply(transform: Transform, input: &str) -> Result<TransformResult, String> {
+    match transform {
+        Transform::Base64Encode => Ok(TransformResult::text(transform, STANDARD.encode(input.as_bytes()))),
+        Transform::Base64Decode => Ok(TransformResult::text(transform, base64_decode(input)?)),
+        Transform::UrlEncode => Ok(TransformResult::text(transform, url_encode(input))),
+        Transform::UrlDecode => Ok(TransformResult::text(transform, url_decode(input)?)),
+        Transform::JwtDecode => jwt_decode(input),
+        Transform::Uuid => {
+            let count = input.trim().parse::<usize>().unwrap_or(1).clamp(1, 100);
+            let ids: Vec<String> = (0..count).map(|_| uuid::Uuid::new_v4().to_string()).collect();
+            Ok(TransformResult::text(transform, ids.join("\n")))
+        }
+        Transform::EpochToIso => epoch_to_iso(input),
+        Transform::IsoToEpoch => iso_to_epoch(input),
+    }
+}
+
+/// `/base64 hello`, `/jwt eyJ...`, `/uuid 3`, `/epoch 1700000000`
+pub fn parse_slash_command(command: &str) -> Option<(Transform, String)> {
+    let rest = command.trim().strip_prefix('/')?;
+    let (name, input) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let transform = Transform::parse(name).ok()?;
+    Some((transform, input.trim().to_string()))
+}
+
+/// Recognise requests like "decode this JWT eyJ..." or "what date is 1700000000"
+pub fn plan_from_natural_language(request: &str) -> Option<(Transform, String)> {
+    let lower = request.to_lowercase();
+    let words: Vec<&str> = request.split_whitespace().collect();
+    // The operand is the last word that is not part of the phrasing
+    let operand = |skip: &[&str]| -> Option<String> {
+        words
+            .iter()
+            .rev()
+            .map(|w| w.trim_matches(|c: char| c == '"' || c == '\'' || c == '`'))
+            .find(|w| !skip.contains(&w.to_lowercase().as_str()))
+            .map(str::to_string)
+    };
+
+    if let Some(token) = words.iter().find(|w| w.starts_with("eyJ") && w.matches('.').count() == 2) {
+        return Some((Transform::JwtDecode, token.to_string()));
+    }
+    if lower.contains("uuid") && ["generate", "new", "give", "make", "create"].iter().any(|w| lower.contains(w)) {
+        let count = words.iter().find_map(|w| w.parse::<usize>().ok()).unwrap_or(1);
+        return Some((Transform::Uuid, count.to_string()));
+    }
+    let phrase_end = |keyword: &str| lower.find(keyword).map(|i| i + keyword.len());
+    for (keywords, transform) in [
+        (&["base64 decode", "decode base64", "decode this base64", "decode the base64"][..], Transform::Base64Decode),
+        (&["base64 encode", "encode base64", "to base64", "in base64"][..], Transform::Base64Encode),
+        (&["url decode", "urldecode", "decode url", "decode this url"][..], Transform::UrlDecode),
+        (&["url encode", "urlencode", "encode url", "percent encode", "encode this url"][..], Transform::UrlEncode),
+    ] {
+        if let Some(end) = keywords.iter().find_map(|k| phrase_end(k)) {
+            let input = request[end..].trim().trim_start_matches(':').trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+            let input = if input.is_empty() { operand(&[])? } else { input };
+            return Some((transform, input));
+        }
+    }
+    if ["epoch", "timestamp", "unix time", "what date is", "what time is", "convert"].iter().any(|k| lower.contains(k)) {
+        if let Some(number) = words.iter().map(|w| w.trim_matches(|c: char| !c.is_ascii_digit())).find(|w| w.len() >= 9 && w.len() <= 19) {
+            return Some((Transform::EpochToIso, number.to_string()));
+        }
+        if lower.contains("to epoch") || lower.contains("to timestamp") || lower.contains("to unix") {
+            let date = words.iter().find(|w| w.len() >= 10 && w.as_bytes()[4] == b'-' && w[..4].chars().all(|c| c.is_ascii_digit()))?;
+            return Some((Transform::IsoToEpoch, date.trim_matches(|c| c == '"' || c == '\'').to_string()));
+        }
+    }
+    None
+}
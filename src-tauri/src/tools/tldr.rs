@@ -0,0 +1,277 @@
+// tldr pages
+// Community-maintained examples of common commands, read from the page caches
+// that tldr clients (tealdeer, tlrc, the node and python clients) keep on disk.
+// Pages of the current platform win over the common ones. A few pages ship
+// built in so the most asked-about commands have examples without a client.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TldrExample {
+    pub description: String,
+    /// The example as written, placeholders in `{{...}}`
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TldrPage {
+    pub name: String,
+    pub description: String,
+    pub examples: Vec<TldrExample>,
+}
+
+impl TldrExample {
+    /// Placeholder names in the order they appear
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut placeholders = Vec::new();
+        let mut rest = self.command.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start + 2..].find("}}") else { break };
+            placeholders.push(rest[start + 2..start + 2 + end].to_string());
+            rest = &rest[start + 2 + end + 2..];
+        }
+        placeholders
+    }
+}
+
+const BUILTIN_PAGES: [&str; 5] = [
+    "# tar
+
+> Archiving utility.
+
+- [c]reate an archive and write it to a [f]ile:
+
+`tar cf {{path/to/target.tar}} {{path/to/file1 path/to/file2 ...}}`
+
+- [c]reate a g[z]ipped archive and write it to a [f]ile:
+
+`tar czf {{path/to/target.tar.gz}} {{path/to/file1 path/to/file2 ...}}`
+
+- E[x]tract a (compressed) archive [f]ile into the current directory [v]erbosely:
+
+`tar xvf {{path/to/source.tar[.gz|.bz2|.xz]}}`
+
+- E[x]tract a (compressed) archive [f]ile into the target directory:
+
+`tar xf {{path/to/source.tar[.gz|.bz2|.xz]}} -C {{path/to/directory}}`
+
+- Lis[t] the contents of a tar [f]ile [v]erbosely:
+
+`tar tvf {{path/to/source.tar}}`
+
+- [c]reate a compressed archive using the archive suffix to determine the compression program:
+
+`tar caf {{path/to/target.tar.xz}} {{path/to/file1 path/to/file2 ...}}`
+",
+    "# ffmpeg
+
+> Video conversion tool.
+
+- Extract the sound from a video and save it as MP3:
+
+`ffmpeg -i {{path/to/video.mp4}} -vn {{path/to/sound.mp3}}`
+
+- Convert a video to another format, re-encoding with H.264 video and AAC audio:
+
+`ffmpeg -i {{path/to/input.mov}} -c:v libx264 -c:a aac {{path/to/output.mp4}}`
+
+- Cut a part of a video without re-encoding:
+
+`ffmpeg -ss {{mm:ss}} -to {{mm2:ss2}} -i {{path/to/input.mp4}} -codec copy {{path/to/output.mp4}}`
+
+- Scale a video down to a height of 720 pixels, keeping the aspect ratio:
+
+`ffmpeg -i {{path/to/input.mp4}} -vf scale=-2:720 {{path/to/output.mp4}}`
+
+- Shrink a video with a higher constant rate factor (lower quality, smaller file):
+
+`ffmpeg -i {{path/to/input.mp4}} -c:v libx264 -crf {{28}} -preset {{slow}} {{path/to/output.mp4}}`
+
+- Turn a sequence of images into a video at 30 frames per second:
+
+`ffmpeg -framerate {{30}} -i {{path/to/frame_%03d.png}} -pix_fmt yuv420p {{path/to/output.mp4}}`
+",
+    "# find
+
+> Find files or directories under a directory tree, recursively.
+
+- Find files by extension:
+
+`find {{path/to/directory}} -name '{{*.ext}}'`
+
+- Find directories matching a given name, in case-insensitive mode:
+
+`find {{path/to/directory}} -type d -iname '{{*lib*}}'`
+
+- Find files modified in the last 7 days:
+
+`find {{path/to/directory}} -type f -mtime -{{7}}`
+
+- Find files larger than 100 megabytes:
+
+`find {{path/to/directory}} -type f -size +{{100M}}`
+
+- Find empty files or directories and delete them verbosely:
+
+`find {{path/to/directory}} -empty -delete -print`
+
+- Run a command for each file found, passing many files at once:
+
+`find {{path/to/directory}} -name '{{*.ext}}' -exec {{wc -l}} {} +`
+",
+    "# rsync
+
+> Transfer files either to or from a remote host (but not between two remote hosts), by default using SSH.
+
+- Transfer a directory recursively in archive mode, showing progress:
+
+`rsync -ah --progress {{path/to/source_directory}} {{remote_host}}:{{path/to/destination_directory}}`
+
+- Compress data during the transfer:
+
+`rsync -az {{path/to/source}} {{remote_host}}:{{path/to/destination}}`
+
+- Mirror a directory, deleting files at the destination that are gone from the source:
+
+`rsync -a --delete {{path/to/source/}} {{path/to/destination}}`
+
+- Show what would be transferred without transferring anything:
+
+`rsync -av --dry-run {{path/to/source}} {{path/to/destination}}`
+
+- Resume a partial transfer of large files:
+
+`rsync -a --partial --append-verify {{path/to/source}} {{path/to/destination}}`
+",
+    "# curl
+
+> Transfers data from or to a server.
+
+- Download the contents of a URL to a file named as in the URL, following redirects:
+
+`curl -LO {{https://example.com/file.zip}}`
+
+- Send the JSON in a file with a POST request:
+
+`curl -X POST -H 'Content-Type: application/json' -d @{{path/to/data.json}} {{https://example.com/api}}`
+
+- Show only the response headers:
+
+`curl -I {{https://example.com}}`
+
+- Fail on HTTP errors and be silent apart from them:
+
+`curl -fsS {{https://example.com}}`
+
+- Pass a bearer token in a header:
+
+`curl -H 'Authorization: Bearer {{token}}' {{https://example.com/api}}`
+
+- Resume an interrupted download:
+
+`curl -C - -O {{https://example.com/file.iso}}`
+",
+];
+
+fn platform() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "osx"
+    } else if cfg!(windows) {
+        "windows"
+    } else {
+        "linux"
+    }
+}
+
+/// Directories holding the platform folders (`common`, `linux`, ...) of the known clients
+fn page_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    for variable in ["TLDR_PAGES_DIR", "TEALDEER_CACHE_DIR", "TLDR_CACHE_DIR"] {
+        if let Some(dir) = std::env::var_os(variable) {
+            let dir = PathBuf::from(dir);
+            roots.extend([dir.join("tldr-pages").join("pages.en"), dir.join("pages.en"), dir.join("pages"), dir]);
+        }
+    }
+    if let Some(cache) = dirs::cache_dir() {
+        roots.push(cache.join("tealdeer").join("tldr-pages").join("pages.en"));
+        roots.push(cache.join("tealdeer").join("tldr-master").join("pages"));
+        roots.push(cache.join("tlrc").join("pages.en"));
+        roots.push(cache.join("tldr").join("pages"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        roots.push(home.join(".tldr").join("cache").join("pages"));
+        roots.push(home.join(".cache").join("tldr").join("pages"));
+    }
+    roots.push(PathBuf::from("/usr/share/tldr/pages"));
+    roots
+}
+
+/// Page name to file, built once from whichever caches exist
+fn index() -> &'static HashMap<String, PathBuf> {
+    static INDEX: OnceLock<HashMap<String, PathBuf>> = OnceLock::new();
+    INDEX.get_or_init(|| {
+        let mut index = HashMap::new();
+        // The platform's own pages first, so they are not replaced by common ones
+        for root in page_roots() {
+            for folder in [platform(), "common"] {
+                let Ok(entries) = std::fs::read_dir(root.join(folder)) else { continue };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|e| e == "md") {
+                        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                            index.entry(name.to_string()).or_insert(path);
+                        }
+                    }
+                }
+            }
+        }
+        index
+    })
+}
+
+/// Parse a page in the tldr markdown format
+pub fn parse_page(markdown: &str) -> Option<TldrPage> {
+    let mut name = None;
+    let mut description = Vec::new();
+    let mut examples = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in markdown.lines().map(str::trim) {
+        if let Some(title) = line.strip_prefix("# ") {
+            name = Some(title.trim().to_string());
+        } else if let Some(text) = line.strip_prefix("> ") {
+            // "More information: <url>." lines are links, not description
+            if !text.starts_with("More information") {
+                description.push(text.trim().to_string());
+            }
+        } else if let Some(text) = line.strip_prefix("- ") {
+            pending = Some(text.trim().trim_end_matches(':').to_string());
+        } else if line.len() > 1 && line.starts_with('`') && line.ends_with('`') {
+            if let Some(text) = pending.take() {
+                examples.push(TldrExample { description: text, command: line[1..line.len() - 1].to_string() });
+            }
+        }
+    }
+    Some(TldrPage { name: name?, description: description.join(" "), examples })
+}
+
+/// The page for a help topic: `["git", "commit"]` tries git-commit, then git
+pub fn find_page(topic: &[String]) -> Option<TldrPage> {
+    let mut candidates = Vec::new();
+    if topic.len() > 1 {
+        candidates.push(topic.join("-").to_lowercase());
+    }
+    candidates.push(topic.first()?.to_lowercase());
+
+    for name in candidates {
+        if let Some(page) = index().get(&name).and_then(|path| std::fs::read_to_string(path).ok()).and_then(|md| parse_page(&md)) {
+            return Some(page);
+        }
+        if let Some(page) = BUILTIN_PAGES.iter().filter_map(|md| parse_page(md)).find(|page| page.name == name) {
+            return Some(page);
+        }
+    }
+    None
+}
@@ -1,5 +1,6 @@
 import React, { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { useTerminalStore } from '../store/terminalStore';
 import {
   Folder,
   FolderOpen,
@@ -30,10 +31,18 @@ interface DirectoryInfo {
   is_directory: boolean;
 }
 
+interface ChildDirectories {
+  entries: DirectoryInfo[];
+  total: number;
+  truncated: boolean;
+}
+
 interface FileExplorerItem extends DirectoryInfo {
   expanded?: boolean;
   level: number;
   children?: FileExplorerItem[];
+  // Entries of a large directory that were not listed
+  hiddenCount?: number;
   loading?: boolean;
 }
 
@@ -50,6 +59,7 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
   onFileSelect,
   activeSessionId
 }) => {
+  const createSession = useTerminalStore(state => state.createSession);
   const [searchQuery, setSearchQuery] = useState('');
   const [fileTree, setFileTree] = useState<FileExplorerItem[]>([]);
   const [loading, setLoading] = useState(false);
@@ -95,10 +105,11 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
     try {
       console.log(`📂 FileExplorer: loadDirectory called with path: ${path}`);
       setLoading(true);
-      const children = await invoke<DirectoryInfo[]>('get_child_directories', {
+      const listing = await invoke<ChildDirectories>('get_child_directories', {
         currentPath: path
       });
-      console.log(`📂 FileExplorer: Loaded ${children.length} items from: ${path}`);
+      const children = listing.entries;
+      console.log(`📂 FileExplorer: Loaded ${children.length} of ${listing.total} items from: ${path}`);
 
       // Create root item for current directory
       const currentDir: FileExplorerItem = {
@@ -111,7 +122,8 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
           ...child,
           level: 1,
           expanded: false
-        }))
+        })),
+        hiddenCount: listing.truncated ? listing.total - children.length : undefined
       };
 
       setFileTree([currentDir]);
@@ -140,12 +152,12 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
 
       if (!item.children) {
         try {
-          const children = await invoke<DirectoryInfo[]>('get_child_directories', {
+          const listing = await invoke<ChildDirectories>('get_child_directories', {
             currentPath: item.path
           });
 
           // Update tree with loaded children
-          setFileTree(prevTree => updateTreeWithChildren(prevTree, item.path, children, item.level + 1));
+          setFileTree(prevTree => updateTreeWithChildren(prevTree, item.path, listing, item.level + 1));
         } catch (error) {
           console.error('Failed to load children for', item.path, error);
         }
@@ -156,24 +168,25 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
   const updateTreeWithChildren = (
     tree: FileExplorerItem[],
     targetPath: string,
-    children: DirectoryInfo[],
+    listing: ChildDirectories,
     level: number
   ): FileExplorerItem[] => {
     return tree.map(item => {
       if (item.path === targetPath) {
         return {
           ...item,
-          children: children.map(child => ({
+          children: listing.entries.map(child => ({
             ...child,
             level,
             expanded: false
-          }))
+          })),
+          hiddenCount: listing.truncated ? listing.total - listing.entries.length : undefined
         };
       }
       if (item.children) {
         return {
           ...item,
-          children: updateTreeWithChildren(item.children, targetPath, children, level)
+          children: updateTreeWithChildren(item.children, targetPath, listing, level)
         };
       }
       return item;
@@ -375,12 +388,12 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
     setIsSearching(true);
     try {
       // Search in current directory and subdirectories
-      const children = await invoke<DirectoryInfo[]>('get_child_directories', {
+      const listing = await invoke<ChildDirectories>('get_child_directories', {
         currentPath: currentPath
       });
 
-      // Filter results based on search query
-      const filtered = children.filter(item =>
+      // Filter results based on search query; a truncated listing only covers its first page
+      const filtered = listing.entries.filter(item =>
         item.name.toLowerCase().includes(query.toLowerCase())
       );
 
@@ -579,6 +592,15 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
         {item.is_directory && isExpanded && item.children && (
           <div>
             {item.children.map(child => renderTreeItem(child))}
+            {item.hiddenCount !== undefined && item.hiddenCount > 0 && (
+              <div
+                className="py-1 px-2 text-xs text-terminal-muted italic"
+                style={{ paddingLeft: `${(item.level + 1) * 16 + 8}px` }}
+                title="Large directory: only the first entries are listed"
+              >
+                ... and {item.hiddenCount} more items not shown
+              </div>
+            )}
           </div>
         )}
       </div>
@@ -788,6 +810,16 @@ export const FileExplorer: React.FC<FileExplorerProps> = ({
                   <Terminal className="w-4 h-4" />
                   <span>Open in Terminal</span>
                 </button>
+                <button
+                  onClick={async () => {
+                    await createSession(contextMenu.item.name, { workingDirectory: contextMenu.item.path });
+                    setContextMenu(null);
+                  }}
+                  className="w-full flex items-center gap-3 px-3 py-2 text-sm text-terminal-text hover:bg-terminal-border rounded transition-colors"
+                >
+                  <Terminal className="w-4 h-4" />
+                  <span>Open New Terminal Here</span>
+                </button>
               </>
             ) : (
               <>
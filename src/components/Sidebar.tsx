@@ -1,16 +1,26 @@
 import React, { useState, useEffect } from 'react';
-import { useTerminalStore } from '../store/terminalStore';
-import { Terminal, Plus, X, Settings as SettingsIcon, Folder } from 'lucide-react';
+import { invoke } from '@tauri-apps/api/core';
+import { useTerminalStore, LaunchProfile } from '../store/terminalStore';
+import { Terminal, Plus, X, Settings as SettingsIcon, Folder, ExternalLink, ChevronDown } from 'lucide-react';
 import { Settings } from './Settings';
 import { FileExplorer } from './FileExplorer';
 
 export const Sidebar: React.FC = () => {
-  const { sessions, activeSession, setActiveSession, createSession, closeSession, updateSessionTitle } = useTerminalStore();
+  const { sessions, activeSession, setActiveSession, createSession, closeSession, updateSessionTitle, detachSession } = useTerminalStore();
   const [showSettings, setShowSettings] = useState(false);
   const [editingSessionId, setEditingSessionId] = useState<string | null>(null);
   const [editingTitle, setEditingTitle] = useState('');
   const [activeTab, setActiveTab] = useState<'terminals' | 'explorer'>('terminals');
   const [currentExplorerPath, setCurrentExplorerPath] = useState('/Users/pierre-ai-engineer/Code/pH7Console');
+  const [profiles, setProfiles] = useState<LaunchProfile[]>([]);
+  const [showProfiles, setShowProfiles] = useState(false);
+
+  // Launch profiles, offered next to the new terminal button
+  useEffect(() => {
+    invoke<LaunchProfile[]>('list_launch_profiles')
+      .then(setProfiles)
+      .catch(error => console.error('Failed to load launch profiles:', error));
+  }, [showProfiles]);
 
   // Debug: Log important state values
   useEffect(() => {
@@ -29,6 +39,11 @@ export const Sidebar: React.FC = () => {
     await createSession(`Terminal ${sessionCount}`);
   };
 
+  const handleCreateFromProfile = async (profile: LaunchProfile) => {
+    setShowProfiles(false);
+    await createSession(profile.title || profile.name, { profileId: profile.id });
+  };
+
   const handleSettingsClick = () => {
     setShowSettings(true);
   };
@@ -169,13 +184,41 @@ export const Sidebar: React.FC = () => {
           <div className="flex items-center justify-between mb-3">
             <h1 className="font-semibold text-terminal-text">pH7Console</h1>
             {activeTab === 'terminals' && (
-              <button
-                onClick={handleCreateSession}
-                className="p-1 hover:bg-terminal-border rounded transition-colors focus-ring"
-                title="New Terminal"
-              >
-                <Plus className="w-4 h-4 text-terminal-muted" />
-              </button>
+              <div className="relative flex items-center">
+                <button
+                  onClick={handleCreateSession}
+                  className="p-1 hover:bg-terminal-border rounded transition-colors focus-ring"
+                  title="New Terminal"
+                >
+                  <Plus className="w-4 h-4 text-terminal-muted" />
+                </button>
+                {profiles.length > 0 && (
+                  <button
+                    onClick={() => setShowProfiles(!showProfiles)}
+                    className="p-1 hover:bg-terminal-border rounded transition-colors focus-ring"
+                    title="New Terminal from Profile"
+                  >
+                    <ChevronDown className="w-4 h-4 text-terminal-muted" />
+                  </button>
+                )}
+                {showProfiles && (
+                  <div
+                    className="absolute right-0 top-full mt-1 bg-terminal-bg border border-terminal-border rounded-lg shadow-xl py-1 z-50 min-w-[180px]"
+                    onMouseLeave={() => setShowProfiles(false)}
+                  >
+                    {profiles.map(profile => (
+                      <button
+                        key={profile.id}
+                        onClick={() => handleCreateFromProfile(profile)}
+                        className="w-full text-left px-3 py-2 text-sm text-terminal-text hover:bg-terminal-border transition-colors"
+                        title={profile.working_directory || profile.shell || profile.name}
+                      >
+                        {profile.name}
+                      </button>
+                    ))}
+                  </div>
+                )}
+              </div>
             )}
           </div>
           
@@ -267,6 +310,18 @@ export const Sidebar: React.FC = () => {
                         </div>
                       </div>
 
+                      {sessions.length > 1 && (
+                        <button
+                          onClick={(e) => {
+                            e.stopPropagation();
+                            detachSession(session.id);
+                          }}
+                          className="opacity-0 group-hover:opacity-100 p-1 hover:bg-black/20 rounded transition-all"
+                          title="Open in a new window"
+                        >
+                          <ExternalLink className="w-3 h-3" />
+                        </button>
+                      )}
                       {sessions.length > 1 && (
                         <button
                           onClick={(e) => handleCloseSession(session.id, e)}
@@ -13,6 +13,7 @@ export const Terminal: React.FC = () => {
   const [completions, setCompletions] = useState<string[]>([]);
   const [selectedCompletion, setSelectedCompletion] = useState(0);
   const [originalNaturalLanguage, setOriginalNaturalLanguage] = useState<string | null>(null);
+  const [acceptedSuggestion, setAcceptedSuggestion] = useState<string | null>(null);
   const [showHistoryModal, setShowHistoryModal] = useState(false);
   const [hoveredCommand, setHoveredCommand] = useState<string | null>(null);
   const [commandExplanation, setCommandExplanation] = useState<string | null>(null);
@@ -263,6 +264,7 @@ export const Terminal: React.FC = () => {
       }
 
       setInput(selectedCommand);
+      setAcceptedSuggestion(selectedCommand);
       setShowSuggestions(false);
       setOriginalNaturalLanguage(null); // Clear the stored original command
       return;
@@ -547,7 +549,9 @@ export const Terminal: React.FC = () => {
       return;
     }
 
-    await executeCommand(input);
+    // Passing the accepted suggestion lets the backend learn from any edits made to it
+    await executeCommand(input, acceptedSuggestion ?? undefined);
+    setAcceptedSuggestion(null);
 
     setInput('');
     setShowSuggestions(false);
@@ -787,6 +791,7 @@ export const Terminal: React.FC = () => {
                     }`}
                   onClick={() => {
                     setInput(completion);
+                    setAcceptedSuggestion(completion);
                     setShowSuggestions(false);
                   }}
                 >
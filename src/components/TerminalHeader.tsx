@@ -41,6 +41,12 @@ interface DirectoryInfo {
   is_directory: boolean;
 }
 
+interface ChildDirectories {
+  entries: DirectoryInfo[];
+  total: number;
+  truncated: boolean;
+}
+
 interface TerminalHeaderProps {
   currentPath: string;
   onPathChange?: (newPath: string) => void;
@@ -54,6 +60,8 @@ const TerminalHeader: React.FC<TerminalHeaderProps> = ({ currentPath, onPathChan
   const [showPathDropdown, setShowPathDropdown] = useState(false);
   const [parentDirectories, setParentDirectories] = useState<DirectoryInfo[]>([]);
   const [childDirectories, setChildDirectories] = useState<DirectoryInfo[]>([]);
+  // Entries in the current directory, including those a large listing left out
+  const [childTotal, setChildTotal] = useState(0);
   const [dropdownLoading, setDropdownLoading] = useState(false);
   const dropdownRef = useRef<HTMLDivElement>(null);
 
@@ -106,15 +114,17 @@ const TerminalHeader: React.FC<TerminalHeaderProps> = ({ currentPath, onPathChan
     try {
       const [parents, children] = await Promise.all([
         invoke<DirectoryInfo[]>('get_parent_directories', { currentPath }),
-        invoke<DirectoryInfo[]>('get_child_directories', { currentPath })
+        invoke<ChildDirectories>('get_child_directories', { currentPath })
       ]);
       
       setParentDirectories(parents);
-      setChildDirectories(children);
+      setChildDirectories(children.entries);
+      setChildTotal(children.total);
     } catch (error) {
       console.error('Failed to fetch directory info:', error);
       setParentDirectories([]);
       setChildDirectories([]);
+      setChildTotal(0);
     } finally {
       setDropdownLoading(false);
     }
@@ -535,9 +545,9 @@ const TerminalHeader: React.FC<TerminalHeaderProps> = ({ currentPath, onPathChan
                           )}
                         </div>
                       ))}
-                      {childDirectories.length > 10 && (
+                      {childTotal > 10 && (
                         <div className="px-3 py-2 text-xs text-gray-500 text-center italic">
-                          ... and {childDirectories.length - 10} more items
+                          ... and {childTotal - 10} more items
                         </div>
                       )}
                     </>
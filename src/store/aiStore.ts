@@ -50,6 +50,31 @@ interface UserAnalytics {
   most_used_commands: [string, number][];
   learning_examples: number;
   patterns_learned: number;
+  model_quality: BackendQuality[];
+  activities: ActivityCluster[];
+  outcomes: Record<string, number>;
+}
+
+interface ActivityCluster {
+  label: string;
+  labeled_by: 'llm' | 'heuristic';
+  commands: number;
+  share: number;
+  total_duration_ms: number;
+  success_rate: number;
+  top_programs: [string, number][];
+  representative_commands: string[];
+  suggestions: string[];
+  last_active: string;
+}
+
+interface BackendQuality {
+  backend: string;
+  comparisons: number;
+  accepted: number;
+  errors: number;
+  acceptance_rate: number;
+  average_latency_ms: number;
 }
 
 export const useAIStore = create<AIState>((set, get) => ({
@@ -136,7 +161,10 @@ export const useAIStore = create<AIState>((set, get) => ({
         sessionId,
       });
     } catch (error) {
-      console.error('Failed to get completions:', error);
+      // A newer keystroke replaced this request
+      if (error !== 'superseded') {
+        console.error('Failed to get completions:', error);
+      }
       return [];
     }
   },
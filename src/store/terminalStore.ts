@@ -1,5 +1,7 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { getCurrentWindow } from '@tauri-apps/api/window';
 
 export interface CommandExecution {
   id: string;
@@ -8,18 +10,76 @@ export interface CommandExecution {
   exit_code?: number;
   duration_ms: number;
   timestamp: string;
+  outcome?: ExecutionOutcome;
 }
 
+export type ExecutionOutcome =
+  | { kind: 'exited'; value: number }
+  | { kind: 'signaled'; value: number }
+  | { kind: 'timed_out' }
+  | { kind: 'spawn_error'; value: 'not_found' | 'permission_denied' | 'other' };
+
 export interface TerminalSession {
   id: string;
   title: string;
   working_directory: string;
+  display_directory?: string;
   is_active: boolean;
   created_at: string;
+  // Launch profile the session was opened with, so a restored session gets it again
+  profile_id?: string;
+}
+
+// What create_terminal takes besides the title; a profile fills whatever is left unset
+export interface SessionLaunchOptions {
+  workingDirectory?: string;
+  shell?: string;
+  env?: Record<string, string | null>;
+  profileId?: string;
+}
+
+// Entry of list_launch_profiles
+export interface LaunchProfile {
+  id: string;
+  name: string;
+  title?: string;
+  working_directory?: string;
+  shell?: string;
+  env: Record<string, string | null>;
+}
+
+// Payload of get_session_status and the `session-status` event
+export interface SessionStatus {
+  session_id: string;
+  last_exit_code?: number;
+  last_duration_ms?: number;
+  running_process?: string;
+  working_directory: string;
+  git?: { branch: string; dirty: boolean; ahead: number; behind: number };
+  background_jobs: number;
 }
 
+// Payload of the `working-directory-recovered` event
+export interface DirectoryRecovery {
+  session_id: string;
+  missing: string;
+  recovered: string;
+}
+
+// Payload of list_windows and the `windows-changed` event
+export interface WindowLayout {
+  label: string;
+  session_ids: string[];
+}
+
+// A detached window is opened as index.html?session=<id>; it shows the sessions the backend assigns it
+const detachedSession = new URLSearchParams(window.location.search).get('session');
+
 interface TerminalState {
+  // Sessions shown in this window
   sessions: TerminalSession[];
+  // Sessions another window shows, kept to take them back when they return
+  elsewhere: TerminalSession[];
   activeSession: string | null;
   commandHistory: CommandExecution[];
   currentInput: string;
@@ -27,11 +87,13 @@ interface TerminalState {
   isInitialized: boolean;
 
   // Actions
-  createSession: (title?: string) => Promise<void>;
+  createSession: (title?: string, options?: SessionLaunchOptions) => Promise<void>;
   closeSession: (sessionId: string) => Promise<void>;
   updateSessionTitle: (sessionId: string, title: string) => Promise<void>;
   setActiveSession: (sessionId: string) => void;
-  executeCommand: (command: string) => Promise<void>;
+  detachSession: (sessionId: string) => Promise<void>;
+  applyWindowLayouts: (layouts: WindowLayout[]) => Promise<void>;
+  executeCommand: (command: string, suggestedCommand?: string) => Promise<void>;
   clearHistory: () => void;
   setCurrentInput: (input: string) => void;
   getHistory: () => CommandExecution[];
@@ -42,21 +104,23 @@ interface TerminalState {
 
 export const useTerminalStore = create<TerminalState>((set, get) => ({
   sessions: [],
+  elsewhere: [],
   activeSession: null,
   commandHistory: [],
   currentInput: '',
   isExecuting: false,
   isInitialized: false,
 
-  createSession: async (title?: string) => {
+  createSession: async (title?: string, options?: SessionLaunchOptions) => {
     try {
-      const sessionId = await invoke<string>('create_terminal', { title });
+      const sessionId = await invoke<string>('create_terminal', { title, ...options });
       const newSession: TerminalSession = {
         id: sessionId,
         title: title || `Terminal ${sessionId.slice(0, 8)}`,
-        working_directory: '~',
+        working_directory: options?.workingDirectory || '~',
         is_active: true,
         created_at: new Date().toISOString(),
+        profile_id: options?.profileId,
       };
 
       set(state => ({
@@ -118,7 +182,40 @@ export const useTerminalStore = create<TerminalState>((set, get) => ({
     set({ activeSession: sessionId });
   },
 
-  executeCommand: async (command: string) => {
+  detachSession: async (sessionId: string) => {
+    try {
+      // The window list arrives through `windows-changed` and moves the session out of this window
+      await invoke('detach_session', { sessionId });
+    } catch (error) {
+      console.error('Failed to detach terminal session:', error);
+    }
+  },
+
+  applyWindowLayouts: async (layouts: WindowLayout[]) => {
+    const label = getCurrentWindow().label;
+    const mine = layouts.find(layout => layout.label === label)?.session_ids ?? [];
+    let known = [...get().sessions, ...get().elsewhere];
+
+    // Sessions that come from another window and were never shown here
+    if (mine.some(id => !known.some(session => session.id === id))) {
+      const all = await invoke<TerminalSession[]>('get_all_sessions');
+      known = [...known, ...all.filter(session => !known.some(k => k.id === session.id))];
+    }
+
+    set(state => {
+      // Tabs keep their order; sessions coming back go last
+      const sessions = known.filter(session => mine.includes(session.id));
+      const elsewhere = known.filter(session => !mine.includes(session.id));
+      const stillHere = sessions.some(session => session.id === state.activeSession);
+      return {
+        sessions,
+        elsewhere,
+        activeSession: stillHere ? state.activeSession : sessions[0]?.id ?? null,
+      };
+    });
+  },
+
+  executeCommand: async (command: string, suggestedCommand?: string) => {
     const { activeSession } = get();
     if (!activeSession || !command.trim()) return;
 
@@ -128,6 +225,7 @@ export const useTerminalStore = create<TerminalState>((set, get) => ({
       const execution = await invoke<CommandExecution>('execute_command', {
         sessionId: activeSession,
         command: command.trim(),
+        suggestedCommand,
       });
 
       set(state => ({
@@ -163,6 +261,26 @@ export const useTerminalStore = create<TerminalState>((set, get) => ({
 
     console.log('📝 Initializing terminal sessions...');
 
+    try {
+      await listen<WindowLayout[]>('windows-changed', event => {
+        get().applyWindowLayouts(event.payload);
+      });
+    } catch (error) {
+      console.error('Failed to follow window changes:', error);
+    }
+
+    // A detached window shows sessions the main window created; it neither restores nor creates any
+    if (detachedSession) {
+      try {
+        await get().applyWindowLayouts(await invoke<WindowLayout[]>('list_windows'));
+        set({ activeSession: detachedSession });
+      } catch (error) {
+        console.error('Failed to load the detached session:', error);
+      }
+      set({ isInitialized: true });
+      return;
+    }
+
     try {
       // First try to load persisted sessions
       await get().loadPersistedSessions();
@@ -203,7 +321,9 @@ export const useTerminalStore = create<TerminalState>((set, get) => ({
         for (const session of persistedSessions) {
           try {
             const sessionId = await invoke<string>('create_terminal', {
-              title: session.title
+              title: session.title,
+              workingDirectory: session.working_directory !== '~' ? session.working_directory : undefined,
+              profileId: session.profile_id,
             });
 
             const newSession: TerminalSession = {
@@ -234,8 +354,10 @@ export const useTerminalStore = create<TerminalState>((set, get) => ({
   },
 
   persistSessions: async () => {
+    // The main window keeps the list for every window
+    if (detachedSession) return;
     try {
-      const { sessions } = get();
+      const sessions = [...get().sessions, ...get().elsewhere];
       // Only persist session metadata (not the backend session IDs)
       const sessionMetadata = sessions.map(session => ({
         id: session.id, // We'll generate new IDs when recreating
@@ -243,6 +365,7 @@ export const useTerminalStore = create<TerminalState>((set, get) => ({
         working_directory: session.working_directory,
         is_active: session.is_active,
         created_at: session.created_at,
+        profile_id: session.profile_id,
       }));
 
       localStorage.setItem('pH7Console_sessions', JSON.stringify(sessionMetadata));